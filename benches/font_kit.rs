@@ -0,0 +1,203 @@
+// font-kit/benches/font_kit.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Benchmarks of character lookup, outline extraction, rasterization, and font enumeration.
+//!
+//! Run them with `cargo bench --features bench-internals`. Each benchmark is measured with every
+//! font in `bench_fixtures::FONT_FIXTURES`; pass a filter such as
+//! `rasterize_deterministic/arial` to run some.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use font_kit::bench_fixtures::{self, FONT_FIXTURES, RASTER_SIZES, SAMPLE_TEXT};
+use font_kit::canvas::{Canvas, Format, RasterizationOptions};
+use font_kit::hinting::HintingOptions;
+use font_kit::loader::Loader;
+use font_kit::outline::OutlineBuilder;
+use pathfinder_geometry::transform2d::Transform2F;
+
+fn cmap_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cmap_lookup");
+    group.throughput(Throughput::Elements(SAMPLE_TEXT.chars().count() as u64));
+    for fixture in FONT_FIXTURES {
+        let font = fixture.load();
+        group.bench_function(fixture.name, |b| {
+            b.iter(|| {
+                for character in SAMPLE_TEXT.chars() {
+                    black_box(font.glyph_for_char(black_box(character)));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn outline_extraction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("outline");
+    for fixture in FONT_FIXTURES {
+        let font = fixture.load();
+        let glyph_ids = bench_fixtures::sample_glyphs(&font);
+        group.throughput(Throughput::Elements(glyph_ids.len() as u64));
+        group.bench_function(fixture.name, |b| {
+            b.iter(|| {
+                for &glyph_id in &glyph_ids {
+                    let mut builder = OutlineBuilder::new();
+                    font.outline(glyph_id, HintingOptions::None, &mut builder)
+                        .unwrap();
+                    black_box(builder.into_outline());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+// Rasterizes every sample glyph of `font` at each size into canvases allocated up front, so that
+// only rasterization is measured.
+fn bench_rasterization<F, R>(
+    c: &mut Criterion,
+    group_name: &str,
+    name: &str,
+    font: &F,
+    rasterize: R,
+) where
+    F: Loader,
+    R: Fn(&F, &mut Canvas, u32, f32, Transform2F),
+{
+    let mut group = c.benchmark_group(group_name);
+    let glyph_ids = bench_fixtures::sample_glyphs(font);
+    group.throughput(Throughput::Elements(glyph_ids.len() as u64));
+    for &size in RASTER_SIZES {
+        let mut glyphs: Vec<(u32, Transform2F, Canvas)> = glyph_ids
+            .iter()
+            .map(|&glyph_id| {
+                let bounds = font
+                    .raster_bounds(
+                        glyph_id,
+                        size,
+                        Transform2F::default(),
+                        HintingOptions::None,
+                        RasterizationOptions::GrayscaleAa,
+                    )
+                    .unwrap();
+                let transform = Transform2F::from_translation(-bounds.origin().to_f32());
+                (glyph_id, transform, Canvas::new(bounds.size(), Format::A8))
+            })
+            .collect();
+        group.bench_function(BenchmarkId::new(name, size), |b| {
+            b.iter(|| {
+                for (glyph_id, transform, canvas) in &mut glyphs {
+                    rasterize(font, canvas, *glyph_id, size, *transform);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn rasterization(c: &mut Criterion) {
+    for fixture in FONT_FIXTURES {
+        let font = fixture.load();
+        bench_rasterization(
+            c,
+            "rasterize_deterministic",
+            fixture.name,
+            &font,
+            |font, canvas, glyph_id, size, transform| {
+                font.rasterize_glyph_deterministic(
+                    canvas,
+                    glyph_id,
+                    size,
+                    transform,
+                    RasterizationOptions::GrayscaleAa,
+                )
+                .unwrap()
+            },
+        );
+
+        // The pure-Rust loader rasterizes with the fixed-point rasterizer measured above, so the
+        // default loader's rasterizer is only measured where it is a platform one or FreeType.
+        #[cfg(any(
+            target_os = "macos",
+            target_os = "ios",
+            target_family = "windows",
+            feature = "loader-freetype-default"
+        ))]
+        {
+            use font_kit::loaders::default::Font as NativeFont;
+
+            let font = NativeFont::from_bytes(fixture.data(), 0).unwrap();
+            bench_rasterization(
+                c,
+                "rasterize_native",
+                fixture.name,
+                &font,
+                |font, canvas, glyph_id, size, transform| {
+                    font.rasterize_glyph(
+                        canvas,
+                        glyph_id,
+                        size,
+                        transform,
+                        HintingOptions::None,
+                        RasterizationOptions::GrayscaleAa,
+                    )
+                    .unwrap()
+                },
+            );
+        }
+    }
+}
+
+#[cfg(feature = "source")]
+fn source_enumeration(c: &mut Criterion) {
+    use font_kit::family_name::FamilyName;
+    use font_kit::properties::Properties;
+    use font_kit::source::SystemSource;
+    use font_kit::sources::mem::MemSource;
+
+    let mut group = c.benchmark_group("source");
+    let font_data: Vec<_> = FONT_FIXTURES.iter().map(|fixture| fixture.data()).collect();
+    group.bench_function("mem_index", |b| {
+        b.iter(|| MemSource::from_bytes(font_data.iter().cloned()).unwrap())
+    });
+    let mem_source = MemSource::from_bytes(font_data.iter().cloned()).unwrap();
+    let family_names = [
+        FamilyName::Title("Inconsolata".to_owned()),
+        FamilyName::Monospace,
+    ];
+    group.bench_function("mem_select_best_match", |b| {
+        b.iter(|| {
+            mem_source
+                .select_best_match(&family_names, &Properties::new())
+                .unwrap()
+        })
+    });
+
+    // What's installed varies from machine to machine, so compare these only on the same one.
+    group.sample_size(10);
+    group.bench_function("system_all_fonts", |b| {
+        b.iter(|| SystemSource::new().all_fonts().unwrap())
+    });
+    group.bench_function("system_all_families", |b| {
+        b.iter(|| SystemSource::new().all_families().unwrap())
+    });
+    group.finish();
+}
+
+#[cfg(not(feature = "source"))]
+fn source_enumeration(_: &mut Criterion) {}
+
+criterion_group!(
+    benches,
+    cmap_lookup,
+    outline_extraction,
+    rasterization,
+    source_enumeration
+);
+criterion_main!(benches);
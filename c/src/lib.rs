@@ -8,34 +8,647 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+//! The C interface to `font-kit`. `build.rs` generates `build/include/font-kit/font-kit.h` from
+//! this crate with `cbindgen`.
+//!
+//! Functions that can fail return an `FKStatus` and write their results through pointers only on
+//! success. Panics are caught and reported as an internal error rather than unwinding into C. A
+//! font may be used from several threads at once.
+
+use font_kit::canvas::{CanvasMut, Format, RasterizationOptions};
+use font_kit::error::GlyphLoadingError;
+use font_kit::font::Font;
 use font_kit::handle::Handle;
+use font_kit::hinting::HintingOptions;
+use font_kit::loader::Loader;
+use font_kit::outline::OutlineSink;
+use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
 use std::mem;
+use std::os::raw::c_void;
+use std::panic::{self, AssertUnwindSafe};
 use std::slice;
 use std::sync::Arc;
 
 pub type FKDataRef = *const Vec<u8>;
 pub type FKHandleRef = *mut Handle;
+pub type FKFontRef = *mut Font;
+
+/// The result of a call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FKStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullPointer = 1,
+    /// The font data could not be parsed, or the font index was out of range.
+    ParseError = 2,
+    /// The font has no such glyph, or no glyph for the character.
+    NoSuchGlyph = 3,
+    /// The pixel buffer is too small for the given size, stride, and format.
+    BufferTooSmall = 4,
+    /// An unexpected error occurred inside the library.
+    InternalError = 5,
+}
+
+/// The pixel format of a buffer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FKFormat {
+    /// One byte of coverage per pixel.
+    A8 = 0,
+    /// Three bytes of coverage per pixel, for subpixel antialiasing.
+    Rgb24 = 1,
+    /// Four bytes of premultiplied RGBA per pixel, for color glyphs.
+    Rgba32 = 2,
+    /// One bit per pixel, eight pixels to a byte with the leftmost in the most significant bit.
+    A1 = 3,
+}
+
+/// How glyph edges are antialiased.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FKRasterization {
+    /// Each pixel is either entirely on or off.
+    Bilevel = 0,
+    /// Grayscale antialiasing.
+    GrayscaleAa = 1,
+    /// Subpixel RGB antialiasing, for LCD screens.
+    SubpixelAa = 2,
+    /// Bi-level or grayscale, as the font's `gasp` table recommends at the size rasterized.
+    FontDefault = 3,
+}
+
+/// Font-wide metrics, in font units. See `Metrics`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FKMetrics {
+    /// The number of font units per em.
+    pub units_per_em: u32,
+    /// The maximum amount the font rises above the baseline.
+    pub ascent: f32,
+    /// The maximum amount the font descends below the baseline; usually negative.
+    pub descent: f32,
+    /// The distance between baselines, on top of the ascent and descent.
+    pub line_gap: f32,
+    /// The distance of the top of the underline from the baseline.
+    pub underline_position: f32,
+    /// The underline thickness.
+    pub underline_thickness: f32,
+    /// The amount that uppercase letters rise above the baseline.
+    pub cap_height: f32,
+    /// The amount that non-ascending lowercase letters rise above the baseline.
+    pub x_height: f32,
+}
 
+/// A rectangle of pixels, with `y` increasing downward.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FKRect {
+    /// The left edge.
+    pub x: i32,
+    /// The top edge.
+    pub y: i32,
+    /// The width.
+    pub width: i32,
+    /// The height.
+    pub height: i32,
+}
+
+/// Functions that receive the path of a glyph from `FKFontGetOutline()`, in font units with `y`
+/// increasing upward. Each is passed the `user_data` given to `FKFontGetOutline()`. Null
+/// functions are skipped.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[allow(missing_debug_implementations)]
+pub struct FKOutlineCallbacks {
+    /// Moves the pen to `(x, y)`.
+    pub move_to: Option<unsafe extern "C" fn(user_data: *mut c_void, x: f32, y: f32)>,
+    /// Draws a line to `(x, y)`.
+    pub line_to: Option<unsafe extern "C" fn(user_data: *mut c_void, x: f32, y: f32)>,
+    /// Draws a quadratic Bézier curve with control point `(cx, cy)` to `(x, y)`.
+    pub quadratic_curve_to:
+        Option<unsafe extern "C" fn(user_data: *mut c_void, cx: f32, cy: f32, x: f32, y: f32)>,
+    /// Draws a cubic Bézier curve with control points `(c0x, c0y)` and `(c1x, c1y)` to `(x, y)`.
+    #[allow(clippy::type_complexity)]
+    pub cubic_curve_to: Option<
+        unsafe extern "C" fn(
+            user_data: *mut c_void,
+            c0x: f32,
+            c0y: f32,
+            c1x: f32,
+            c1y: f32,
+            x: f32,
+            y: f32,
+        ),
+    >,
+    /// Closes the current contour.
+    pub close: Option<unsafe extern "C" fn(user_data: *mut c_void)>,
+}
+
+/// Copies `len` bytes of font data.
+///
+/// # Safety
+///
+/// `bytes` must point to `len` readable bytes.
 #[no_mangle]
 pub unsafe extern "C" fn FKDataCreate(bytes: *const u8, len: usize) -> FKDataRef {
     Arc::into_raw(Arc::new(slice::from_raw_parts(bytes, len).to_vec()))
 }
 
+/// # Safety
+///
+/// `data` must be live data from `FKDataCreate()`.
 #[no_mangle]
 pub unsafe extern "C" fn FKDataDestroy(data: FKDataRef) {
     drop(Arc::from_raw(data))
 }
 
 /// Does not take ownership of `bytes`.
+///
+/// # Safety
+///
+/// `bytes` must be live data from `FKDataCreate()`.
 #[no_mangle]
-pub unsafe extern "C" fn FKHandleCreateWithMemory(bytes: FKDataRef, font_index: u32)
-                                                  -> FKHandleRef {
+pub unsafe extern "C" fn FKHandleCreateWithMemory(
+    bytes: FKDataRef,
+    font_index: u32,
+) -> FKHandleRef {
     let bytes = Arc::from_raw(bytes);
     mem::forget(bytes.clone());
     Box::into_raw(Box::new(Handle::from_memory(bytes, font_index)))
 }
 
+/// # Safety
+///
+/// `handle` must be a handle that hasn't been destroyed.
 #[no_mangle]
 pub unsafe extern "C" fn FKHandleDestroy(handle: FKHandleRef) {
     drop(Box::from_raw(handle))
 }
+
+/// Does not take ownership of `bytes`. For collections, `font_index` selects the font; pass 0
+/// otherwise.
+///
+/// # Safety
+///
+/// `bytes` must be live data from `FKDataCreate()`, and `out_font` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn FKFontCreateWithMemory(
+    bytes: FKDataRef,
+    font_index: u32,
+    out_font: *mut FKFontRef,
+) -> FKStatus {
+    if bytes.is_null() || out_font.is_null() {
+        return FKStatus::NullPointer;
+    }
+    let bytes = Arc::from_raw(bytes);
+    mem::forget(bytes.clone());
+    guard(|| match Font::from_bytes(bytes, font_index) {
+        Ok(font) => {
+            *out_font = Box::into_raw(Box::new(font));
+            FKStatus::Ok
+        }
+        Err(_) => FKStatus::ParseError,
+    })
+}
+
+/// Does not take ownership of `handle`.
+///
+/// # Safety
+///
+/// `handle` must be a live handle, and `out_font` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn FKFontCreateWithHandle(
+    handle: FKHandleRef,
+    out_font: *mut FKFontRef,
+) -> FKStatus {
+    let handle = match handle.as_ref() {
+        Some(handle) if !out_font.is_null() => handle,
+        _ => return FKStatus::NullPointer,
+    };
+    guard(|| match Font::from_handle(handle) {
+        Ok(font) => {
+            *out_font = Box::into_raw(Box::new(font));
+            FKStatus::Ok
+        }
+        Err(_) => FKStatus::ParseError,
+    })
+}
+
+/// Null is ignored.
+///
+/// # Safety
+///
+/// `font` must be null or a font that hasn't been destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn FKFontDestroy(font: FKFontRef) {
+    if !font.is_null() {
+        drop(Box::from_raw(font));
+    }
+}
+
+/// Returns 0 if `font` is null.
+///
+/// # Safety
+///
+/// `font` must be null or a live font.
+#[no_mangle]
+pub unsafe extern "C" fn FKFontGetGlyphCount(font: FKFontRef) -> u32 {
+    match font.as_ref() {
+        Some(font) => font.glyph_count(),
+        None => 0,
+    }
+}
+
+/// Stores the glyph that the font maps the Unicode scalar value `character` to in
+/// `*out_glyph_id`.
+///
+/// # Safety
+///
+/// `font` must be a live font, and `out_glyph_id` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn FKFontGetGlyphForChar(
+    font: FKFontRef,
+    character: u32,
+    out_glyph_id: *mut u32,
+) -> FKStatus {
+    let font = match font.as_ref() {
+        Some(font) if !out_glyph_id.is_null() => font,
+        _ => return FKStatus::NullPointer,
+    };
+    guard(
+        || match char::from_u32(character).and_then(|character| font.glyph_for_char(character)) {
+            Some(glyph_id) => {
+                *out_glyph_id = glyph_id;
+                FKStatus::Ok
+            }
+            None => FKStatus::NoSuchGlyph,
+        },
+    )
+}
+
+/// # Safety
+///
+/// `font` must be a live font, and `out_metrics` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn FKFontGetMetrics(
+    font: FKFontRef,
+    out_metrics: *mut FKMetrics,
+) -> FKStatus {
+    let font = match font.as_ref() {
+        Some(font) if !out_metrics.is_null() => font,
+        _ => return FKStatus::NullPointer,
+    };
+    guard(|| {
+        let metrics = font.metrics();
+        *out_metrics = FKMetrics {
+            units_per_em: metrics.units_per_em,
+            ascent: metrics.ascent,
+            descent: metrics.descent,
+            line_gap: metrics.line_gap,
+            underline_position: metrics.underline_position,
+            underline_thickness: metrics.underline_thickness,
+            cap_height: metrics.cap_height,
+            x_height: metrics.x_height,
+        };
+        FKStatus::Ok
+    })
+}
+
+/// Stores the horizontal advance of a glyph, in font units, in `*out_advance`.
+///
+/// # Safety
+///
+/// `font` must be a live font, and `out_advance` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn FKFontGetAdvance(
+    font: FKFontRef,
+    glyph_id: u32,
+    out_advance: *mut f32,
+) -> FKStatus {
+    let font = match font.as_ref() {
+        Some(font) if !out_advance.is_null() => font,
+        _ => return FKStatus::NullPointer,
+    };
+    guard(|| match font.advance(glyph_id) {
+        Ok(advance) => {
+            *out_advance = advance.x();
+            FKStatus::Ok
+        }
+        Err(error) => status_for_glyph_error(error),
+    })
+}
+
+/// Sends the unhinted path of a glyph to `callbacks`.
+///
+/// # Safety
+///
+/// `font` must be a live font, `callbacks` must point to valid callbacks, and the callbacks must
+/// be safe to call with `user_data`.
+#[no_mangle]
+pub unsafe extern "C" fn FKFontGetOutline(
+    font: FKFontRef,
+    glyph_id: u32,
+    callbacks: *const FKOutlineCallbacks,
+    user_data: *mut c_void,
+) -> FKStatus {
+    let (font, callbacks) = match (font.as_ref(), callbacks.as_ref()) {
+        (Some(font), Some(callbacks)) => (font, *callbacks),
+        _ => return FKStatus::NullPointer,
+    };
+    guard(|| {
+        let mut sink = CallbackSink {
+            callbacks,
+            user_data,
+        };
+        match font.outline(glyph_id, HintingOptions::None, &mut sink) {
+            Ok(()) => FKStatus::Ok,
+            Err(error) => status_for_glyph_error(error),
+        }
+    })
+}
+
+/// Stores the pixels that a glyph rasterized at `point_size` touches in `*out_rect`, relative to
+/// the glyph origin, with `y` increasing downward.
+///
+/// # Safety
+///
+/// `font` must be a live font, and `out_rect` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn FKFontGetRasterBounds(
+    font: FKFontRef,
+    glyph_id: u32,
+    point_size: f32,
+    rasterization: FKRasterization,
+    out_rect: *mut FKRect,
+) -> FKStatus {
+    let font = match font.as_ref() {
+        Some(font) if !out_rect.is_null() => font,
+        _ => return FKStatus::NullPointer,
+    };
+    guard(|| {
+        match font.raster_bounds(
+            glyph_id,
+            point_size,
+            Transform2F::default(),
+            HintingOptions::None,
+            rasterization.into(),
+        ) {
+            Ok(rect) => {
+                *out_rect = FKRect {
+                    x: rect.min_x(),
+                    y: rect.min_y(),
+                    width: rect.width(),
+                    height: rect.height(),
+                };
+                FKStatus::Ok
+            }
+            Err(error) => status_for_glyph_error(error),
+        }
+    })
+}
+
+/// Rasterizes a glyph into a buffer owned by the caller, with the glyph origin at pixel
+/// `(origin_x, origin_y)`.
+///
+/// Rows are `stride` bytes apart, and only the `width` by `height` pixels of the buffer are
+/// written. Use `FKFontGetRasterBounds()` to size the buffer and place the origin, which is at
+/// `(-rect.x, -rect.y)` for a buffer that exactly fits the glyph.
+///
+/// Glyphs are rasterized with `font-kit`'s fixed-point rasterizer, whose output is bit-identical
+/// on every platform.
+///
+/// # Safety
+///
+/// `font` must be a live font, and `pixels` must point to `stride * height` writable bytes.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn FKFontRasterizeGlyph(
+    font: FKFontRef,
+    glyph_id: u32,
+    point_size: f32,
+    origin_x: f32,
+    origin_y: f32,
+    rasterization: FKRasterization,
+    pixels: *mut u8,
+    width: i32,
+    height: i32,
+    stride: usize,
+    format: FKFormat,
+) -> FKStatus {
+    let font = match font.as_ref() {
+        Some(font) if !pixels.is_null() => font,
+        _ => return FKStatus::NullPointer,
+    };
+    if width < 0 || height < 0 {
+        return FKStatus::BufferTooSmall;
+    }
+    let pixels = slice::from_raw_parts_mut(pixels, stride * height as usize);
+    guard(|| {
+        let size = Vector2I::new(width, height);
+        let mut target = match CanvasMut::new(pixels, size, stride, format.into()) {
+            Some(target) => target,
+            None => return FKStatus::BufferTooSmall,
+        };
+        match font.rasterize_glyph_into(
+            &mut target,
+            glyph_id,
+            point_size,
+            Transform2F::from_translation(Vector2F::new(origin_x, origin_y)),
+            HintingOptions::None,
+            rasterization.into(),
+        ) {
+            Ok(()) => FKStatus::Ok,
+            Err(error) => status_for_glyph_error(error),
+        }
+    })
+}
+
+// Runs the body of an exported function, turning panics into an error.
+fn guard<F>(body: F) -> FKStatus
+where
+    F: FnOnce() -> FKStatus,
+{
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(FKStatus::InternalError)
+}
+
+fn status_for_glyph_error(error: GlyphLoadingError) -> FKStatus {
+    match error {
+        GlyphLoadingError::NoSuchGlyph => FKStatus::NoSuchGlyph,
+        _ => FKStatus::InternalError,
+    }
+}
+
+impl From<FKFormat> for Format {
+    fn from(format: FKFormat) -> Format {
+        match format {
+            FKFormat::A8 => Format::A8,
+            FKFormat::Rgb24 => Format::Rgb24,
+            FKFormat::Rgba32 => Format::Rgba32,
+            FKFormat::A1 => Format::A1,
+        }
+    }
+}
+
+impl From<FKRasterization> for RasterizationOptions {
+    fn from(rasterization: FKRasterization) -> RasterizationOptions {
+        match rasterization {
+            FKRasterization::Bilevel => RasterizationOptions::Bilevel,
+            FKRasterization::GrayscaleAa => RasterizationOptions::GrayscaleAa,
+            FKRasterization::SubpixelAa => RasterizationOptions::SubpixelAa,
+            FKRasterization::FontDefault => RasterizationOptions::FontDefault,
+        }
+    }
+}
+
+struct CallbackSink {
+    callbacks: FKOutlineCallbacks,
+    user_data: *mut c_void,
+}
+
+impl OutlineSink for CallbackSink {
+    fn move_to(&mut self, to: Vector2F) {
+        if let Some(move_to) = self.callbacks.move_to {
+            unsafe { move_to(self.user_data, to.x(), to.y()) }
+        }
+    }
+
+    fn line_to(&mut self, to: Vector2F) {
+        if let Some(line_to) = self.callbacks.line_to {
+            unsafe { line_to(self.user_data, to.x(), to.y()) }
+        }
+    }
+
+    fn quadratic_curve_to(&mut self, ctrl: Vector2F, to: Vector2F) {
+        if let Some(quadratic_curve_to) = self.callbacks.quadratic_curve_to {
+            unsafe { quadratic_curve_to(self.user_data, ctrl.x(), ctrl.y(), to.x(), to.y()) }
+        }
+    }
+
+    fn cubic_curve_to(&mut self, ctrl: LineSegment2F, to: Vector2F) {
+        if let Some(cubic_curve_to) = self.callbacks.cubic_curve_to {
+            let (ctrl0, ctrl1) = (ctrl.from(), ctrl.to());
+            unsafe {
+                cubic_curve_to(
+                    self.user_data,
+                    ctrl0.x(),
+                    ctrl0.y(),
+                    ctrl1.x(),
+                    ctrl1.y(),
+                    to.x(),
+                    to.y(),
+                )
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        if let Some(close) = self.callbacks.close {
+            unsafe { close(self.user_data) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        FKDataCreate, FKDataDestroy, FKFontCreateWithMemory, FKFontDestroy, FKFontGetGlyphCount,
+        FKFontGetGlyphForChar, FKFontGetMetrics, FKFontGetOutline, FKFontRasterizeGlyph, FKFormat,
+        FKMetrics, FKOutlineCallbacks, FKRasterization, FKStatus,
+    };
+    use std::os::raw::c_void;
+    use std::ptr;
+
+    static INCONSOLATA: &[u8] =
+        include_bytes!("../../resources/tests/inconsolata/Inconsolata-Regular.ttf");
+
+    unsafe extern "C" fn count_point(user_data: *mut c_void, _: f32, _: f32) {
+        *(user_data as *mut u32) += 1;
+    }
+
+    #[test]
+    fn test_capi() {
+        unsafe {
+            let mut font = ptr::null_mut();
+            let truncated = FKDataCreate(INCONSOLATA.as_ptr(), 4);
+            assert_eq!(
+                FKFontCreateWithMemory(truncated, 0, &mut font),
+                FKStatus::ParseError
+            );
+            FKDataDestroy(truncated);
+            let data = FKDataCreate(INCONSOLATA.as_ptr(), INCONSOLATA.len());
+            assert_eq!(FKFontCreateWithMemory(data, 0, &mut font), FKStatus::Ok);
+            FKDataDestroy(data);
+            assert!(FKFontGetGlyphCount(font) > 0);
+
+            let mut glyph_id = 0;
+            assert_eq!(
+                FKFontGetGlyphForChar(font, 'L' as u32, &mut glyph_id),
+                FKStatus::Ok
+            );
+            assert_eq!(
+                FKFontGetGlyphForChar(font, 0xd800, &mut glyph_id),
+                FKStatus::NoSuchGlyph
+            );
+            let mut metrics = FKMetrics::default();
+            assert_eq!(FKFontGetMetrics(font, &mut metrics), FKStatus::Ok);
+            assert_eq!(metrics.units_per_em, 1000);
+
+            let mut points = 0u32;
+            let callbacks = FKOutlineCallbacks {
+                move_to: Some(count_point),
+                line_to: Some(count_point),
+                quadratic_curve_to: None,
+                cubic_curve_to: None,
+                close: None,
+            };
+            let user_data = &mut points as *mut u32 as *mut c_void;
+            assert_eq!(
+                FKFontGetOutline(font, glyph_id, &callbacks, user_data),
+                FKStatus::Ok
+            );
+            assert!(points >= 6);
+
+            let mut pixels = vec![0u8; 32 * 32];
+            assert_eq!(
+                FKFontRasterizeGlyph(
+                    font,
+                    glyph_id,
+                    24.0,
+                    4.0,
+                    28.0,
+                    FKRasterization::GrayscaleAa,
+                    pixels.as_mut_ptr(),
+                    32,
+                    32,
+                    16,
+                    FKFormat::A8,
+                ),
+                FKStatus::BufferTooSmall
+            );
+            assert_eq!(
+                FKFontRasterizeGlyph(
+                    font,
+                    glyph_id,
+                    24.0,
+                    4.0,
+                    28.0,
+                    FKRasterization::GrayscaleAa,
+                    pixels.as_mut_ptr(),
+                    32,
+                    32,
+                    32,
+                    FKFormat::A8,
+                ),
+                FKStatus::Ok
+            );
+            assert!(pixels.iter().any(|&pixel| pixel != 0));
+
+            FKFontDestroy(font);
+            FKFontDestroy(ptr::null_mut());
+        }
+    }
+}
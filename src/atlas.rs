@@ -0,0 +1,257 @@
+// font-kit/src/atlas.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rasterizing many glyphs into a single image, as GPU text renderers do.
+//!
+//! `Loader::rasterize_glyphs_to_atlas()` measures every glyph first, packs them into rows
+//! ("shelves") sorted by height, and then rasterizes each one into its slot with
+//! `Loader::rasterize_glyph_into()`, so that no separate packing pass is needed. Whether a glyph
+//! is drawn straight into the atlas or into a temporary canvas that is then copied into its slot
+//! depends on the loader's implementation of that method.
+
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2I;
+use std::collections::HashMap;
+
+use crate::canvas::{Canvas, Format, RasterizationOptions};
+use crate::error::GlyphLoadingError;
+use crate::hinting::HintingOptions;
+use crate::loader::Loader;
+
+/// The number of empty pixels left between glyphs, so that sampling a glyph with bilinear
+/// filtering doesn't pick up its neighbors.
+pub const ATLAS_PADDING: i32 = 1;
+
+/// A glyph in a `GlyphAtlas`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasGlyph {
+    /// The glyph ID.
+    pub glyph_id: u32,
+    /// The pixels that the glyph occupies in the atlas image. This is empty for glyphs that draw
+    /// nothing, such as spaces.
+    pub rect: RectI,
+    /// The position of the top left corner of the glyph image relative to the glyph origin, in
+    /// pixels, as in `RasterizedGlyph::origin`.
+    pub origin: Vector2I,
+}
+
+/// Glyphs rasterized into a single image by `Loader::rasterize_glyphs_to_atlas()`.
+#[derive(Debug)]
+pub struct GlyphAtlas {
+    /// The atlas image.
+    pub canvas: Canvas,
+    /// The position of each requested glyph in the image, in the order in which they were
+    /// requested. Glyphs requested more than once are rasterized once and share a rectangle.
+    pub glyphs: Vec<AtlasGlyph>,
+}
+
+/// Rasterizes glyphs into an atlas. See `Loader::rasterize_glyphs_to_atlas()`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rasterize_glyphs_to_atlas<L>(
+    loader: &L,
+    glyph_ids: &[u32],
+    point_size: f32,
+    transform: Transform2F,
+    hinting_options: HintingOptions,
+    rasterization_options: RasterizationOptions,
+    format: Format,
+    width: i32,
+) -> Result<GlyphAtlas, GlyphLoadingError>
+where
+    L: Loader,
+{
+    // Measure each distinct glyph once.
+    let mut slots: HashMap<u32, usize> = HashMap::new();
+    let mut glyphs: Vec<AtlasGlyph> = vec![];
+    for &glyph_id in glyph_ids {
+        if slots.contains_key(&glyph_id) {
+            continue;
+        }
+        let bounds = loader.raster_bounds(
+            glyph_id,
+            point_size,
+            transform,
+            hinting_options,
+            rasterization_options,
+        )?;
+        slots.insert(glyph_id, glyphs.len());
+        glyphs.push(AtlasGlyph {
+            glyph_id,
+            rect: RectI::new(Vector2I::default(), bounds.size()),
+            origin: bounds.origin(),
+        });
+    }
+
+    // Pack the glyphs into shelves, tallest first, so that each shelf wastes little height.
+    let width = glyphs
+        .iter()
+        .map(|glyph| glyph.rect.width())
+        .fold(width, i32::max);
+    let mut order: Vec<usize> = (0..glyphs.len()).collect();
+    order.sort_by_key(|&index| -glyphs[index].rect.height());
+    let (mut cursor, mut shelf_height) = (Vector2I::default(), 0);
+    for index in order {
+        let size = glyphs[index].rect.size();
+        if size.x() <= 0 || size.y() <= 0 {
+            glyphs[index].rect = RectI::default();
+            continue;
+        }
+        if cursor.x() + size.x() > width {
+            cursor = Vector2I::new(0, cursor.y() + shelf_height + ATLAS_PADDING);
+            shelf_height = 0;
+        }
+        glyphs[index].rect = RectI::new(cursor, size);
+        cursor += Vector2I::new(size.x() + ATLAS_PADDING, 0);
+        shelf_height = shelf_height.max(size.y());
+    }
+
+    let mut canvas = Canvas::new(Vector2I::new(width, cursor.y() + shelf_height), format);
+    {
+        let mut view = canvas.as_view_mut();
+        for glyph in &glyphs {
+            if glyph.rect.width() <= 0 || glyph.rect.height() <= 0 {
+                continue;
+            }
+            let mut slot = view.sub_view_mut(glyph.rect).unwrap();
+            loader.rasterize_glyph_into(
+                &mut slot,
+                glyph.glyph_id,
+                point_size,
+                Transform2F::from_translation(-glyph.origin.to_f32()) * transform,
+                hinting_options,
+                rasterization_options,
+            )?;
+        }
+    }
+
+    let glyphs = glyph_ids
+        .iter()
+        .map(|glyph_id| glyphs[slots[glyph_id]])
+        .collect();
+    Ok(GlyphAtlas { canvas, glyphs })
+}
+
+#[cfg(test)]
+mod test {
+    use super::ATLAS_PADDING;
+    use crate::canvas::{Canvas, Format, RasterizationOptions};
+    use crate::font::Font;
+    use crate::hinting::HintingOptions;
+    use crate::loader::Loader;
+    use crate::test_fixtures::INCONSOLATA;
+    use pathfinder_geometry::rect::RectI;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::Vector2I;
+
+    #[test]
+    fn test_rasterize_glyphs_to_atlas() {
+        let font = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        let glyph_ids: Vec<u32> = "HoH A"
+            .chars()
+            .map(|character| font.glyph_for_char(character).unwrap())
+            .collect();
+        let atlas = font
+            .rasterize_glyphs_to_atlas(
+                &glyph_ids,
+                100.0,
+                Transform2F::default(),
+                HintingOptions::None,
+                RasterizationOptions::GrayscaleAa,
+                Format::A8,
+                128,
+            )
+            .unwrap();
+        assert_eq!(atlas.canvas.size.x(), 128);
+        assert_eq!(atlas.glyphs.len(), glyph_ids.len());
+        assert_eq!(atlas.glyphs[0], atlas.glyphs[2]);
+        assert_eq!(atlas.glyphs[3].rect, RectI::default());
+
+        let bounds = atlas.canvas.size;
+        for (index, glyph) in [atlas.glyphs[0], atlas.glyphs[1], atlas.glyphs[4]]
+            .iter()
+            .enumerate()
+        {
+            let expected = font
+                .raster_bounds(
+                    glyph.glyph_id,
+                    100.0,
+                    Transform2F::default(),
+                    HintingOptions::None,
+                    RasterizationOptions::GrayscaleAa,
+                )
+                .unwrap();
+            assert_eq!(
+                (glyph.rect.size(), glyph.origin),
+                (expected.size(), expected.origin())
+            );
+            assert!(glyph.rect.min_x() >= 0 && glyph.rect.min_y() >= 0);
+            assert!(glyph.rect.max_x() <= bounds.x() && glyph.rect.max_y() <= bounds.y());
+            for other in &[atlas.glyphs[0], atlas.glyphs[1], atlas.glyphs[4]][index + 1..] {
+                let padded = other
+                    .rect
+                    .contract(Vector2I::new(-ATLAS_PADDING, -ATLAS_PADDING));
+                assert!(glyph.rect.intersection(padded).is_none());
+            }
+        }
+    }
+
+    // Each slot holds the same pixels as rasterizing its glyph on its own, down to small sizes
+    // where glyphs are only a few pixels across.
+    fn check_atlas_pixels(text: &str, point_size: f32, format: Format) {
+        let font = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        let glyph_ids: Vec<u32> = text
+            .chars()
+            .map(|character| font.glyph_for_char(character).unwrap())
+            .collect();
+        let atlas = font
+            .rasterize_glyphs_to_atlas(
+                &glyph_ids,
+                point_size,
+                Transform2F::default(),
+                HintingOptions::None,
+                RasterizationOptions::GrayscaleAa,
+                format,
+                64,
+            )
+            .unwrap();
+        assert_eq!(atlas.canvas.format, format);
+        for glyph in &atlas.glyphs {
+            let mut expected = Canvas::new(glyph.rect.size(), format);
+            font.rasterize_glyph(
+                &mut expected,
+                glyph.glyph_id,
+                point_size,
+                Transform2F::from_translation(-glyph.origin.to_f32()),
+                HintingOptions::None,
+                RasterizationOptions::GrayscaleAa,
+            )
+            .unwrap();
+            assert!(expected.pixels.iter().any(|&pixel| pixel > 0));
+            let slot = atlas
+                .canvas
+                .as_view()
+                .sub_view(glyph.rect)
+                .unwrap()
+                .to_canvas();
+            assert_eq!(slot.pixels, expected.pixels);
+        }
+    }
+
+    #[test]
+    fn test_rasterize_glyphs_to_atlas_small_a8() {
+        check_atlas_pixels("Hello", 12.0, Format::A8);
+    }
+
+    #[test]
+    fn test_rasterize_glyphs_to_atlas_rgba32() {
+        check_atlas_pixels("Hello", 48.0, Format::Rgba32);
+    }
+}
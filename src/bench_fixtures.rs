@@ -0,0 +1,115 @@
+// font-kit/src/bench_fixtures.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Fonts and inputs shared by the benchmarks in `benches/`.
+//!
+//! The benchmarks are built as a separate crate, so these are public, but they aren't part of the
+//! stable API. The fonts are read from the repository's `resources` directory, which isn't
+//! published, so they are only available in a checkout of the repository.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::font::Font;
+use crate::loader::Loader;
+
+/// A font to benchmark with.
+#[derive(Clone, Copy, Debug)]
+pub struct FontFixture {
+    /// The name benchmarks are reported under.
+    pub name: &'static str,
+    /// The path of the font file within the `resources` directory.
+    pub path: &'static str,
+}
+
+/// Representative fonts: TrueType outlines, with and without hinting instructions, and CFF
+/// outlines.
+pub const FONT_FIXTURES: &[FontFixture] = &[
+    FontFixture {
+        name: "arial",
+        path: "Arial_regular.ttf",
+    },
+    FontFixture {
+        name: "dejavu-sans-mono",
+        path: "DejaVuSansMono.ttf",
+    },
+    FontFixture {
+        name: "eb-garamond-cff",
+        path: "tests/eb-garamond/EBGaramond12-Regular.otf",
+    },
+    FontFixture {
+        name: "inconsolata",
+        path: "tests/inconsolata/Inconsolata-Regular.ttf",
+    },
+];
+
+/// Text whose glyphs are looked up, outlined, and rasterized: every Latin letter and digit, with
+/// some punctuation.
+pub const SAMPLE_TEXT: &str = "The quick brown fox jumps over the lazy dog. PACK MY BOX WITH FIVE \
+                               DOZEN LIQUOR JUGS! 0123456789";
+
+/// The sizes glyphs are rasterized at, in pixels per em, from UI text to display sizes.
+pub const RASTER_SIZES: &[f32] = &[12.0, 24.0, 48.0, 96.0];
+
+impl FontFixture {
+    /// Returns the path of the font file.
+    pub fn full_path(&self) -> PathBuf {
+        resources_dir().join(self.path)
+    }
+
+    /// Reads the font file.
+    ///
+    /// Panics if it can't be read, such as outside a checkout of the repository.
+    pub fn data(&self) -> Arc<Vec<u8>> {
+        let path = self.full_path();
+        match std::fs::read(&path) {
+            Ok(data) => Arc::new(data),
+            Err(error) => panic!("can't read {}: {}", path.display(), error),
+        }
+    }
+
+    /// Loads the font with the pure-Rust loader.
+    pub fn load(&self) -> Font {
+        Font::from_bytes(self.data(), 0).expect("fixture font fails to load")
+    }
+}
+
+/// Returns the repository's `resources` directory.
+pub fn resources_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources")
+}
+
+/// Returns the glyphs of the distinct characters of `SAMPLE_TEXT` that `font` supports, sorted
+/// by ID.
+pub fn sample_glyphs<F>(font: &F) -> Vec<u32>
+where
+    F: Loader,
+{
+    let mut glyph_ids: Vec<u32> = SAMPLE_TEXT
+        .chars()
+        .filter_map(|character| font.glyph_for_char(character))
+        .collect();
+    glyph_ids.sort_unstable();
+    glyph_ids.dedup();
+    glyph_ids
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sample_glyphs, FONT_FIXTURES};
+
+    #[test]
+    fn test_fixtures_load() {
+        for fixture in FONT_FIXTURES {
+            let font = fixture.load();
+            assert!(sample_glyphs(&font).len() > 60, "{}", fixture.name);
+        }
+    }
+}
@@ -15,25 +15,57 @@ use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::vector::Vector2I;
 use std::cmp;
 use std::fmt;
+use std::ops::Range;
 
+use crate::loader::Loader;
+use crate::units::{PpemRounding, Px};
 use crate::utils;
 
 lazy_static! {
     static ref BITMAP_1BPP_TO_8BPP_LUT: [[u8; 8]; 256] = {
         let mut lut = [[0; 8]; 256];
-        for byte in 0..0x100 {
-            let mut value = [0; 8];
-            for bit in 0..8 {
+        for (byte, value) in lut.iter_mut().enumerate() {
+            for (bit, pixel) in value.iter_mut().enumerate() {
                 if (byte & (0x80 >> bit)) != 0 {
-                    value[bit] = 0xff;
+                    *pixel = 0xff;
                 }
             }
-            lut[byte] = value
+        }
+        lut
+    };
+    static ref SRGB_TO_LINEAR_LUT: [f32; 256] = {
+        let mut lut = [0.0; 256];
+        for (value, linear) in lut.iter_mut().enumerate() {
+            let value = value as f32 / 255.0;
+            *linear = if value <= 0.04045 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            };
+        }
+        lut
+    };
+    static ref LINEAR_TO_SRGB_LUT: [u8; LINEAR_TO_SRGB_LUT_SIZE] = {
+        let mut lut = [0; LINEAR_TO_SRGB_LUT_SIZE];
+        for (value, srgb) in lut.iter_mut().enumerate() {
+            let value = value as f32 / (LINEAR_TO_SRGB_LUT_SIZE - 1) as f32;
+            let value = if value <= 0.0031308 {
+                value * 12.92
+            } else {
+                1.055 * value.powf(1.0 / 2.4) - 0.055
+            };
+            *srgb = (value * 255.0).round() as u8;
         }
         lut
     };
 }
 
+// Linear values are quantized more finely than sRGB ones so that dark colors survive the round trip.
+const LINEAR_TO_SRGB_LUT_SIZE: usize = 4096;
+
+// The coverage at which a pixel is turned on when converting to `A1`.
+const A1_THRESHOLD: u8 = 128;
+
 /// An in-memory bitmap surface for glyph rasterization.
 pub struct Canvas {
     /// The raw pixel data.
@@ -54,11 +86,7 @@ impl Canvas {
     /// The canvas is initialized with transparent black (all values 0).
     #[inline]
     pub fn new(size: Vector2I, format: Format) -> Canvas {
-        Canvas::with_stride(
-            size,
-            size.x() as usize * format.bytes_per_pixel() as usize,
-            format,
-        )
+        Canvas::with_stride(size, format.row_len(size.x() as usize), format)
     }
 
     /// Creates a new blank canvas with the given pixel size, stride (number of bytes between
@@ -74,7 +102,6 @@ impl Canvas {
         }
     }
 
-    #[allow(dead_code)]
     pub(crate) fn blit_from_canvas(&mut self, src: &Canvas) {
         self.blit_from(
             Vector2I::default(),
@@ -85,7 +112,6 @@ impl Canvas {
         )
     }
 
-    #[allow(dead_code)]
     pub(crate) fn blit_from(
         &mut self,
         dst_point: Vector2I,
@@ -94,31 +120,8 @@ impl Canvas {
         src_stride: usize,
         src_format: Format,
     ) {
-        let dst_rect = RectI::new(dst_point, src_size);
-        let dst_rect = dst_rect.intersection(RectI::new(Vector2I::default(), self.size));
-        let dst_rect = match dst_rect {
-            Some(dst_rect) => dst_rect,
-            None => return,
-        };
-
-        match (self.format, src_format) {
-            (Format::A8, Format::A8)
-            | (Format::Rgb24, Format::Rgb24)
-            | (Format::Rgba32, Format::Rgba32) => {
-                self.blit_from_with::<BlitMemcpy>(dst_rect, src_bytes, src_stride, src_format)
-            }
-            (Format::A8, Format::Rgb24) => {
-                self.blit_from_with::<BlitRgb24ToA8>(dst_rect, src_bytes, src_stride, src_format)
-            }
-            (Format::Rgb24, Format::A8) => {
-                self.blit_from_with::<BlitA8ToRgb24>(dst_rect, src_bytes, src_stride, src_format)
-            }
-            (Format::Rgb24, Format::Rgba32) => self
-                .blit_from_with::<BlitRgba32ToRgb24>(dst_rect, src_bytes, src_stride, src_format),
-            (Format::Rgba32, Format::Rgb24) => self
-                .blit_from_with::<BlitRgb24ToRgba32>(dst_rect, src_bytes, src_stride, src_format),
-            (Format::Rgba32, Format::A8) | (Format::A8, Format::Rgba32) => unimplemented!(),
-        }
+        self.as_view_mut()
+            .blit_from(dst_point, src_bytes, src_size, src_stride, src_format)
     }
 
     #[allow(dead_code)]
@@ -166,10 +169,557 @@ impl Canvas {
         }
     }
 
+    /// Returns a copy of this canvas converted to another format.
+    ///
+    /// `A8` coverage becomes white with that alpha in `Rgba32`, and gray in `Rgb24`. Converting to
+    /// `A8` keeps the alpha of `Rgba32` and the green channel of `Rgb24`. `Rgb24` is opaque, and
+    /// converting `Rgba32` to it keeps the premultiplied colors, as if over black.
+    ///
+    /// `A1` is converted as if it were `A8` with every pixel fully on or off, and converting to
+    /// `A1` turns on the pixels that would be at least half covered in `A8`. Call `threshold()`
+    /// first to choose another cutoff.
+    pub fn to_format(&self, format: Format) -> Canvas {
+        let mut canvas = Canvas::new(self.size, format);
+        canvas.blit_from_canvas(self);
+        canvas
+    }
+
+    /// Returns the pixels as non-premultiplied RGBA, four bytes per pixel with no padding between
+    /// rows.
+    ///
+    /// This is the layout that the HTML canvas `ImageData` expects, so the result can be passed
+    /// straight to `new ImageData()` or `putImageData()`. Other formats are converted as by
+    /// `to_format(Format::Rgba32)`, so `A8` coverage becomes white with that alpha.
+    pub fn to_image_data(&self) -> Vec<u8> {
+        let mut pixels = self.to_format(Format::Rgba32).pixels;
+        for pixel in pixels.chunks_exact_mut(4) {
+            let alpha = pixel[3] as u32;
+            if alpha == 0 || alpha == 255 {
+                continue;
+            }
+            for channel in &mut pixel[..3] {
+                *channel = cmp::min((*channel as u32 * 255 + alpha / 2) / alpha, 255) as u8;
+            }
+        }
+        pixels
+    }
+
+    /// Encodes the canvas as a binary PGM image, a format that almost every image viewer opens
+    /// and that is simple enough to diff in snapshot tests.
+    ///
+    /// Pixels are written as `A8` coverage, converted as by `to_format(Format::A8)`, so glyphs
+    /// appear white on black.
+    pub fn to_pgm(&self) -> Vec<u8> {
+        let coverage = self.to_format(Format::A8);
+        let mut pgm = format!("P5\n{} {}\n255\n", self.size.x(), self.size.y()).into_bytes();
+        pgm.extend_from_slice(&coverage.pixels);
+        pgm
+    }
+
+    /// Encodes the canvas as a PNG image.
+    ///
+    /// `A8` and `A1` canvases become grayscale images of their coverage, so glyphs appear white
+    /// on black; `Rgb24` canvases become RGB images, and `Rgba32` canvases become RGBA images with
+    /// the alpha unpremultiplied as by `to_image_data()`. PNG images can't be empty, so an empty
+    /// vector is returned for canvases with no pixels.
+    #[cfg(feature = "png")]
+    pub fn to_png(&self) -> Vec<u8> {
+        let (color_type, pixels) = self.png_pixels();
+        let mut data = vec![];
+        if self.size.x() <= 0 || self.size.y() <= 0 {
+            return data;
+        }
+        let mut encoder = png::Encoder::new(&mut data, self.size.x() as u32, self.size.y() as u32);
+        encoder.set_color(color_type);
+        encoder.set_depth(png::BitDepth::Eight);
+        // Writing to memory can only fail for invalid sizes, which are ruled out above.
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&pixels).unwrap();
+        writer.finish().unwrap();
+        data
+    }
+
+    // Returns the pixels as `to_png()` encodes them, tightly packed.
+    #[cfg(feature = "png")]
+    pub(crate) fn png_pixels(&self) -> (png::ColorType, Vec<u8>) {
+        match self.format {
+            Format::A8 | Format::A1 => {
+                (png::ColorType::Grayscale, self.to_format(Format::A8).pixels)
+            }
+            Format::Rgb24 => (png::ColorType::Rgb, self.to_format(Format::Rgb24).pixels),
+            Format::Rgba32 => (png::ColorType::Rgba, self.to_image_data()),
+        }
+    }
+
+    /// Composites `src` onto this canvas, with its top left corner at `dst_origin`.
+    ///
+    /// Pixels that fall outside this canvas are skipped, and `src` is converted to this canvas's
+    /// format as `to_format()` would. The arithmetic is on premultiplied values as they are
+    /// stored, so it suits stacking the layers of a color glyph or copying glyphs into an atlas;
+    /// use `composite_onto()` to draw text in a color with gamma-correct blending.
+    pub fn blend(&mut self, src: &Canvas, dst_origin: Vector2I, mode: BlendMode) {
+        self.as_view_mut().blend(&src.as_view(), dst_origin, mode)
+    }
+
+    /// Turns each pixel of glyph coverage in this canvas fully on if it is at least `threshold`,
+    /// and fully off otherwise. Pixels with no coverage stay off.
+    ///
+    /// This is how `RasterizationOptions::BilevelThreshold` makes bi-level glyphs out of
+    /// antialiased ones. `A8` and `Rgb24` values become 0 or 255; `A1` canvases are bi-level
+    /// already, and `Rgba32` canvases hold color glyphs, so both are left alone.
+    pub fn threshold(&mut self, threshold: u8) {
+        self.as_view_mut().threshold(threshold)
+    }
+
+    /// Applies gamma correction and stem darkening to the glyph coverage in this canvas.
+    ///
+    /// `pixels_per_em` is the size the glyph was rasterized at, which decides how much stems are
+    /// darkened. `A8` and `Rgb24` canvases are treated as coverage; `A1` canvases have no partial
+    /// coverage to adjust and `Rgba32` canvases hold color glyphs, so both are left alone.
+    pub fn adjust_coverage(&mut self, options: CoverageOptions, pixels_per_em: f32) {
+        let channels = match self.format {
+            Format::A8 => 1,
+            Format::Rgb24 => 3,
+            Format::A1 | Format::Rgba32 => return,
+        };
+
+        let darkening = if options.stem_darkening {
+            stem_darkening_amount(pixels_per_em)
+        } else {
+            0.0
+        };
+        if darkening > 0.0 {
+            // Spread each row's coverage sideways by half the amount on each side, which widens
+            // vertical stems by the full amount.
+            let spread = darkening * 0.5;
+            let row_len = self.size.x() as usize * channels;
+            let mut row = vec![0; row_len];
+            for y in 0..self.size.y() as usize {
+                let start = y * self.stride;
+                row.copy_from_slice(&self.pixels[start..(start + row_len)]);
+                for (index, pixel) in self.pixels[start..(start + row_len)].iter_mut().enumerate() {
+                    let left = index.checked_sub(channels).map_or(0, |index| row[index]);
+                    let right = row.get(index + channels).copied().unwrap_or(0);
+                    let neighbor = cmp::max(left, right);
+                    if neighbor > *pixel {
+                        *pixel += ((neighbor - *pixel) as f32 * spread).round() as u8;
+                    }
+                }
+            }
+        }
+
+        if options.gamma > 0.0 && options.gamma != 1.0 {
+            let inverse_gamma = 1.0 / options.gamma;
+            let mut lut = [0; 256];
+            for (coverage, adjusted) in lut.iter_mut().enumerate() {
+                *adjusted = ((coverage as f32 / 255.0).powf(inverse_gamma) * 255.0).round() as u8;
+            }
+            for y in 0..self.size.y() as usize {
+                let start = y * self.stride;
+                for pixel in &mut self.pixels[start..(start + self.size.x() as usize * channels)] {
+                    *pixel = lut[*pixel as usize];
+                }
+            }
+        }
+    }
+
+    // Widens the glyph in this canvas by `amount` pixels to the right, for synthetic bold.
+    pub(crate) fn embolden(&mut self, amount: f32) {
+        if self.format == Format::A1 {
+            let mut canvas = self.to_format(Format::A8);
+            canvas.embolden(amount);
+            self.blit_from_canvas(&canvas);
+            return;
+        }
+
+        let bytes_per_pixel = self.format.bytes_per_pixel() as usize;
+        let whole_pixels = amount.floor() as usize;
+        let fraction = amount - amount.floor();
+        let row_len = self.size.x() as usize * bytes_per_pixel;
+        let mut row = vec![0; row_len];
+        for y in 0..self.size.y() as usize {
+            let start = y * self.stride;
+            row.copy_from_slice(&self.pixels[start..(start + row_len)]);
+            for (index, pixel) in self.pixels[start..(start + row_len)].iter_mut().enumerate() {
+                let sample = |offset: usize| {
+                    index
+                        .checked_sub(offset * bytes_per_pixel)
+                        .map_or(0, |index| row[index])
+                };
+                let covered = (0..=whole_pixels).map(sample).max().unwrap_or(0);
+                let partial = (sample(whole_pixels + 1) as f32 * fraction).round() as u8;
+                *pixel = cmp::max(covered, partial);
+            }
+        }
+    }
+
+    /// Returns a read-only view of the whole canvas.
+    #[inline]
+    pub fn as_view(&self) -> CanvasView<'_> {
+        CanvasView {
+            pixels: &self.pixels,
+            size: self.size,
+            stride: self.stride,
+            format: self.format,
+        }
+    }
+
+    /// Returns a writable view of the whole canvas. Use `CanvasMut::sub_view_mut()` on it to
+    /// narrow it to a region.
+    #[inline]
+    pub fn as_view_mut(&mut self) -> CanvasMut<'_> {
+        CanvasMut {
+            pixels: &mut self.pixels,
+            size: self.size,
+            stride: self.stride,
+            format: self.format,
+        }
+    }
+
+    /// Blends the glyph coverage in this canvas onto `target` in the given color.
+    ///
+    /// `offset` is the position of this canvas's origin in `target`; pixels that fall outside
+    /// `target` are skipped. `text_color` is non-premultiplied sRGB with alpha. `target` may be
+    /// `Rgba32` (premultiplied) or `Rgb24`, or it may be `A8` or `A1`, in which case the coverage,
+    /// scaled by the alpha of `text_color`, is accumulated into it as alpha and the color is
+    /// ignored. `A1` targets are blended as `A8` and then thresholded at half coverage.
+    ///
+    /// A8 canvases are treated as grayscale coverage and Rgb24 canvases as per-channel subpixel
+    /// coverage. Rgba32 canvases, such as color emoji, are composited as they are, and
+    /// `text_color` is ignored.
+    ///
+    /// Blending happens in linear light rather than directly on sRGB values, which would make
+    /// dark text on light backgrounds look too thin and light text on dark backgrounds too heavy.
+    /// The coverage is additionally raised to the power of `1 / gamma`: 1.0 leaves it unchanged,
+    /// and larger values make text heavier.
+    pub fn composite_onto(
+        &self,
+        target: &mut Canvas,
+        offset: Vector2I,
+        text_color: [u8; 4],
+        gamma: f32,
+    ) {
+        if self.format == Format::A1 {
+            let canvas = self.to_format(Format::A8);
+            return canvas.composite_onto(target, offset, text_color, gamma);
+        }
+
+        if target.format == Format::A1 {
+            let mut coverage = target.to_format(Format::A8);
+            self.composite_onto(&mut coverage, offset, text_color, gamma);
+            *target = coverage.to_format(Format::A1);
+            return;
+        }
+
+        let target_bytes_per_pixel = target.format.bytes_per_pixel() as usize;
+        let src_bytes_per_pixel = self.format.bytes_per_pixel() as usize;
+
+        let dst_rect = RectI::new(offset, self.size);
+        let dst_rect = match dst_rect.intersection(RectI::new(Vector2I::default(), target.size)) {
+            Some(dst_rect) => dst_rect,
+            None => return,
+        };
+
+        let text_alpha = text_color[3] as f32 / 255.0;
+        let text_color = [
+            SRGB_TO_LINEAR_LUT[text_color[0] as usize],
+            SRGB_TO_LINEAR_LUT[text_color[1] as usize],
+            SRGB_TO_LINEAR_LUT[text_color[2] as usize],
+        ];
+        let inverse_gamma = if gamma > 0.0 { 1.0 / gamma } else { 1.0 };
+        let adjust_coverage = |coverage: u8| (coverage as f32 / 255.0).powf(inverse_gamma);
+
+        for y in dst_rect.min_y()..dst_rect.max_y() {
+            for x in dst_rect.min_x()..dst_rect.max_x() {
+                let src_index = (y - offset.y()) as usize * self.stride
+                    + (x - offset.x()) as usize * src_bytes_per_pixel;
+                let src = &self.pixels[src_index..(src_index + src_bytes_per_pixel)];
+
+                // The linear premultiplied source color and the per-channel source alpha.
+                let (color, alpha) = match self.format {
+                    Format::A8 => {
+                        let alpha = adjust_coverage(src[0]) * text_alpha;
+                        (text_color.map(|channel| channel * alpha), [alpha; 3])
+                    }
+                    Format::Rgb24 => {
+                        let alpha = [
+                            adjust_coverage(src[0]) * text_alpha,
+                            adjust_coverage(src[1]) * text_alpha,
+                            adjust_coverage(src[2]) * text_alpha,
+                        ];
+                        let color = [
+                            text_color[0] * alpha[0],
+                            text_color[1] * alpha[1],
+                            text_color[2] * alpha[2],
+                        ];
+                        (color, alpha)
+                    }
+                    Format::Rgba32 => {
+                        let alpha = src[3] as f32 / 255.0;
+                        (linear_premultiplied(src, alpha), [alpha; 3])
+                    }
+                    Format::A1 => unreachable!(),
+                };
+
+                let dst_index = y as usize * target.stride + x as usize * target_bytes_per_pixel;
+                let dst = &mut target.pixels[dst_index..(dst_index + target_bytes_per_pixel)];
+                let average_alpha = (alpha[0] + alpha[1] + alpha[2]) / 3.0;
+                if target.format == Format::A8 {
+                    let dst_alpha = dst[0] as f32 / 255.0;
+                    let result_alpha = average_alpha + dst_alpha * (1.0 - average_alpha);
+                    dst[0] = (result_alpha * 255.0).round() as u8;
+                    continue;
+                }
+
+                let dst_alpha = if target.format == Format::Rgba32 {
+                    dst[3] as f32 / 255.0
+                } else {
+                    1.0
+                };
+                let dst_color = linear_premultiplied(dst, dst_alpha);
+                let result_alpha = average_alpha + dst_alpha * (1.0 - average_alpha);
+                for channel in 0..3 {
+                    let result = color[channel] + dst_color[channel] * (1.0 - alpha[channel]);
+                    dst[channel] = if target.format == Format::Rgba32 {
+                        // `Rgba32` is premultiplied in sRGB space.
+                        let unpremultiplied = if result_alpha > 0.0 {
+                            result / result_alpha
+                        } else {
+                            0.0
+                        };
+                        (linear_to_srgb(unpremultiplied) as f32 * result_alpha).round() as u8
+                    } else {
+                        linear_to_srgb(result)
+                    };
+                }
+                if target.format == Format::Rgba32 {
+                    dst[3] = (result_alpha * 255.0).round() as u8;
+                }
+            }
+        }
+    }
+}
+
+/// A read-only image borrowed from a `Canvas` or another buffer, possibly a region of it.
+#[derive(Clone, Copy)]
+pub struct CanvasView<'a> {
+    /// The pixel data, starting at the top left pixel of the view.
+    pub pixels: &'a [u8],
+    /// The size of the view, in pixels.
+    pub size: Vector2I,
+    /// The number of *bytes* between successive rows.
+    pub stride: usize,
+    /// The image format of the pixels.
+    pub format: Format,
+}
+
+/// A writable image borrowed from a `Canvas` or another buffer, such as an atlas page or a
+/// framebuffer, possibly a region of it.
+///
+/// Rows are `stride` bytes apart and only the first `size.x()` pixels of each row belong to the
+/// view, so writing to a view never touches pixels outside its region.
+pub struct CanvasMut<'a> {
+    /// The pixel data, starting at the top left pixel of the view.
+    pub pixels: &'a mut [u8],
+    /// The size of the view, in pixels.
+    pub size: Vector2I,
+    /// The number of *bytes* between successive rows.
+    pub stride: usize,
+    /// The image format of the pixels.
+    pub format: Format,
+}
+
+impl<'a> CanvasView<'a> {
+    /// Wraps an existing buffer. Returns `None` if the buffer is too small for the size and
+    /// stride, or the stride is too small for the width.
+    pub fn new(
+        pixels: &'a [u8],
+        size: Vector2I,
+        stride: usize,
+        format: Format,
+    ) -> Option<CanvasView<'a>> {
+        if pixels.len() < required_len(size, stride, format)? {
+            return None;
+        }
+        Some(CanvasView {
+            pixels,
+            size,
+            stride,
+            format,
+        })
+    }
+
+    /// Returns a view of a region of this view, or `None` if the region doesn't fit inside it.
+    pub fn sub_view(&self, rect: RectI) -> Option<CanvasView<'a>> {
+        let range = region_range(self.size, self.stride, self.format, rect)?;
+        Some(CanvasView {
+            pixels: &self.pixels[range],
+            size: rect.size(),
+            stride: self.stride,
+            format: self.format,
+        })
+    }
+
+    /// Copies the view into a new, tightly packed canvas.
+    pub fn to_canvas(&self) -> Canvas {
+        let mut canvas = Canvas::new(self.size, self.format);
+        canvas.blit_from(
+            Vector2I::default(),
+            self.pixels,
+            self.size,
+            self.stride,
+            self.format,
+        );
+        canvas
+    }
+}
+
+impl<'a> CanvasMut<'a> {
+    /// Wraps an existing buffer. Returns `None` if the buffer is too small for the size and
+    /// stride, or the stride is too small for the width.
+    pub fn new(
+        pixels: &'a mut [u8],
+        size: Vector2I,
+        stride: usize,
+        format: Format,
+    ) -> Option<CanvasMut<'a>> {
+        if pixels.len() < required_len(size, stride, format)? {
+            return None;
+        }
+        Some(CanvasMut {
+            pixels,
+            size,
+            stride,
+            format,
+        })
+    }
+
+    /// Returns a writable view of a region of this view, or `None` if the region doesn't fit
+    /// inside it.
+    pub fn sub_view_mut(&mut self, rect: RectI) -> Option<CanvasMut<'_>> {
+        let range = region_range(self.size, self.stride, self.format, rect)?;
+        Some(CanvasMut {
+            pixels: &mut self.pixels[range],
+            size: rect.size(),
+            stride: self.stride,
+            format: self.format,
+        })
+    }
+
+    /// Returns a read-only view of the same pixels.
+    #[inline]
+    pub fn as_view(&self) -> CanvasView<'_> {
+        CanvasView {
+            pixels: self.pixels,
+            size: self.size,
+            stride: self.stride,
+            format: self.format,
+        }
+    }
+
+    /// Sets every pixel of this view to transparent black.
+    #[allow(dead_code)]
+    pub(crate) fn clear(&mut self) {
+        let row_len = self.format.row_len(self.size.x() as usize);
+        for y in 0..self.size.y() as usize {
+            let row = &mut self.pixels[(y * self.stride)..];
+            if self.format == Format::A1 {
+                // The last byte of a row may hold pixels outside the view.
+                for x in 0..self.size.x() as usize {
+                    set_coverage_at(row, self.format, x, 0);
+                }
+            } else {
+                row[..row_len].fill(0);
+            }
+        }
+    }
+
+    // See `Canvas::threshold()`.
+    pub(crate) fn threshold(&mut self, threshold: u8) {
+        let channels = match self.format {
+            Format::A8 => 1,
+            Format::Rgb24 => 3,
+            Format::A1 | Format::Rgba32 => return,
+        };
+        let row_len = self.size.x() as usize * channels;
+        for y in 0..self.size.y() as usize {
+            let start = y * self.stride;
+            for pixel in &mut self.pixels[start..(start + row_len)] {
+                *pixel = if *pixel > 0 && *pixel >= threshold {
+                    255
+                } else {
+                    0
+                };
+            }
+        }
+    }
+
+    // Copies an image in another buffer into this view, with its top left corner at `dst_point`,
+    // converting it to this view's format as `Canvas::to_format()` would. Pixels that fall
+    // outside the view are skipped.
+    pub(crate) fn blit_from(
+        &mut self,
+        dst_point: Vector2I,
+        src_bytes: &[u8],
+        src_size: Vector2I,
+        src_stride: usize,
+        src_format: Format,
+    ) {
+        let dst_rect = RectI::new(dst_point, src_size);
+        let dst_rect = dst_rect.intersection(RectI::new(Vector2I::default(), self.size));
+        let dst_rect = match dst_rect {
+            Some(dst_rect) => dst_rect,
+            None => return,
+        };
+        let src_origin = dst_rect.origin() - dst_point;
+
+        match (self.format, src_format) {
+            (Format::A1, _) | (_, Format::A1) => {
+                // Pixels don't start on byte boundaries, so convert them one at a time.
+                for y in 0..dst_rect.height() {
+                    let dest_row_start = (y + dst_rect.origin_y()) as usize * self.stride;
+                    let src_row_start = (y + src_origin.y()) as usize * src_stride;
+                    let dest_row_pixels = &mut self.pixels[dest_row_start..];
+                    let src_row_pixels = &src_bytes[src_row_start..];
+                    for x in 0..dst_rect.width() as usize {
+                        let src_x = src_origin.x() as usize + x;
+                        let coverage = coverage_at(src_row_pixels, src_format, src_x);
+                        let dest_x = dst_rect.origin_x() as usize + x;
+                        set_coverage_at(dest_row_pixels, self.format, dest_x, coverage);
+                    }
+                }
+            }
+            (Format::A8, Format::A8)
+            | (Format::Rgb24, Format::Rgb24)
+            | (Format::Rgba32, Format::Rgba32) => self.blit_from_with::<BlitMemcpy>(
+                dst_rect, src_bytes, src_origin, src_stride, src_format,
+            ),
+            (Format::A8, Format::Rgb24) => self.blit_from_with::<BlitRgb24ToA8>(
+                dst_rect, src_bytes, src_origin, src_stride, src_format,
+            ),
+            (Format::Rgb24, Format::A8) => self.blit_from_with::<BlitA8ToRgb24>(
+                dst_rect, src_bytes, src_origin, src_stride, src_format,
+            ),
+            (Format::Rgb24, Format::Rgba32) => self.blit_from_with::<BlitRgba32ToRgb24>(
+                dst_rect, src_bytes, src_origin, src_stride, src_format,
+            ),
+            (Format::Rgba32, Format::Rgb24) => self.blit_from_with::<BlitRgb24ToRgba32>(
+                dst_rect, src_bytes, src_origin, src_stride, src_format,
+            ),
+            (Format::Rgba32, Format::A8) => self.blit_from_with::<BlitA8ToRgba32>(
+                dst_rect, src_bytes, src_origin, src_stride, src_format,
+            ),
+            (Format::A8, Format::Rgba32) => self.blit_from_with::<BlitRgba32ToA8>(
+                dst_rect, src_bytes, src_origin, src_stride, src_format,
+            ),
+        }
+    }
+
     fn blit_from_with<B: Blit>(
         &mut self,
         rect: RectI,
         src_bytes: &[u8],
+        src_origin: Vector2I,
         src_stride: usize,
         src_format: Format,
     ) {
@@ -180,7 +730,8 @@ impl Canvas {
             let (dest_row_start, src_row_start) = (
                 (y + rect.origin_y()) as usize * self.stride
                     + rect.origin_x() as usize * dest_bytes_per_pixel,
-                y as usize * src_stride,
+                (y + src_origin.y()) as usize * src_stride
+                    + src_origin.x() as usize * src_bytes_per_pixel,
             );
             let dest_row_end = dest_row_start + rect.width() as usize * dest_bytes_per_pixel;
             let src_row_end = src_row_start + rect.width() as usize * src_bytes_per_pixel;
@@ -189,6 +740,202 @@ impl Canvas {
             B::blit(dest_row_pixels, src_row_pixels)
         }
     }
+
+    /// Composites `src` onto this view, with its top left corner at `dst_origin`. See
+    /// `Canvas::blend()`.
+    pub fn blend(&mut self, src: &CanvasView, dst_origin: Vector2I, mode: BlendMode) {
+        let dst_rect = RectI::new(dst_origin, src.size);
+        let dst_rect = match dst_rect.intersection(RectI::new(Vector2I::default(), self.size)) {
+            Some(dst_rect) => dst_rect,
+            None => return,
+        };
+
+        for y in dst_rect.min_y()..dst_rect.max_y() {
+            let src_row = &src.pixels[((y - dst_origin.y()) as usize * src.stride)..];
+            let dst_row = &mut self.pixels[(y as usize * self.stride)..];
+            for x in dst_rect.min_x()..dst_rect.max_x() {
+                let src_pixel =
+                    premultiplied_rgba(src_row, src.format, (x - dst_origin.x()) as usize);
+                let dst_pixel = premultiplied_rgba(dst_row, self.format, x as usize);
+
+                let result = match mode {
+                    BlendMode::Source => src_pixel,
+                    BlendMode::Over => {
+                        let inverse_alpha = 255 - src_pixel[3];
+                        [0, 1, 2, 3].map(|channel| {
+                            src_pixel[channel]
+                                .saturating_add(mul_u8(dst_pixel[channel], inverse_alpha))
+                        })
+                    }
+                    BlendMode::SourceIn => src_pixel.map(|channel| mul_u8(channel, dst_pixel[3])),
+                };
+                let index = x as usize * self.format.bytes_per_pixel() as usize;
+                match self.format {
+                    Format::Rgba32 => dst_row[index..(index + 4)].copy_from_slice(&result),
+                    Format::Rgb24 => dst_row[index..(index + 3)].copy_from_slice(&result[0..3]),
+                    Format::A8 | Format::A1 => {
+                        set_coverage_at(dst_row, self.format, x as usize, result[3])
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Returns the number of bytes that an image with the given layout spans, or `None` if the stride
+// is too small for the width.
+fn required_len(size: Vector2I, stride: usize, format: Format) -> Option<usize> {
+    if size.x() < 0 || size.y() < 0 {
+        return None;
+    }
+    let row_len = format.row_len(size.x() as usize);
+    if stride < row_len {
+        return None;
+    }
+    if size.x() == 0 || size.y() == 0 {
+        return Some(0);
+    }
+    Some((size.y() as usize - 1) * stride + row_len)
+}
+
+// Returns the byte range of the pixels that a region of an image spans.
+fn region_range(
+    size: Vector2I,
+    stride: usize,
+    format: Format,
+    rect: RectI,
+) -> Option<Range<usize>> {
+    let inside = rect.min_x() >= 0
+        && rect.min_y() >= 0
+        && rect.width() >= 0
+        && rect.height() >= 0
+        && rect.max_x() <= size.x()
+        && rect.max_y() <= size.y();
+    if !inside {
+        return None;
+    }
+    // `A1` regions must start on a byte boundary.
+    let start_bit = rect.min_x() as usize * format.bits_per_pixel() as usize;
+    if !start_bit.is_multiple_of(8) {
+        return None;
+    }
+    let start = rect.min_y() as usize * stride + start_bit / 8;
+    Some(start..(start + required_len(rect.size(), stride, format)?))
+}
+
+// Reads the pixel at `x` in a row as premultiplied RGBA, treating `A8` and `A1` as white and
+// `Rgb24` as opaque.
+fn premultiplied_rgba(row: &[u8], format: Format, x: usize) -> [u8; 4] {
+    match format {
+        Format::Rgba32 => [row[x * 4], row[x * 4 + 1], row[x * 4 + 2], row[x * 4 + 3]],
+        Format::Rgb24 => [row[x * 3], row[x * 3 + 1], row[x * 3 + 2], 255],
+        Format::A8 | Format::A1 => [coverage_at(row, format, x); 4],
+    }
+}
+
+// Reads the pixel at `x` in a row as `A8` coverage, as `Canvas::to_format()` converts it.
+fn coverage_at(row: &[u8], format: Format, x: usize) -> u8 {
+    match format {
+        Format::Rgba32 => row[x * 4 + 3],
+        Format::Rgb24 => row[x * 3 + 1],
+        Format::A8 => row[x],
+        Format::A1 if row[x / 8] & (0x80 >> (x % 8)) != 0 => 255,
+        Format::A1 => 0,
+    }
+}
+
+// Writes `A8` coverage to the pixel at `x` in a row, as `Canvas::to_format()` converts it.
+fn set_coverage_at(row: &mut [u8], format: Format, x: usize, coverage: u8) {
+    match format {
+        Format::Rgba32 => row[(x * 4)..(x * 4 + 4)].fill(coverage),
+        Format::Rgb24 => row[(x * 3)..(x * 3 + 3)].fill(coverage),
+        Format::A8 => row[x] = coverage,
+        Format::A1 if coverage >= A1_THRESHOLD => row[x / 8] |= 0x80 >> (x % 8),
+        Format::A1 => row[x / 8] &= !(0x80 >> (x % 8)),
+    }
+}
+
+// Multiplies two values in the range 0–255 as if they were in the range 0–1, rounding.
+// Returns how many pixels stems are widened by at the given size.
+fn stem_darkening_amount(pixels_per_em: f32) -> f32 {
+    const FULL_DARKENING_PPEM: f32 = 9.0;
+    const NO_DARKENING_PPEM: f32 = 36.0;
+    const MAX_DARKENING: f32 = 0.5;
+    let fraction = (NO_DARKENING_PPEM - pixels_per_em) / (NO_DARKENING_PPEM - FULL_DARKENING_PPEM);
+    MAX_DARKENING * fraction.clamp(0.0, 1.0)
+}
+
+fn mul_u8(a: u8, b: u8) -> u8 {
+    ((a as u32 * b as u32 + 127) / 255) as u8
+}
+
+// Converts the first three channels of an sRGB pixel that is premultiplied by `alpha` to linear
+// light, still premultiplied.
+fn linear_premultiplied(pixel: &[u8], alpha: f32) -> [f32; 3] {
+    if alpha <= 0.0 {
+        return [0.0; 3];
+    }
+    let mut color = [0.0; 3];
+    for (linear, &srgb) in color.iter_mut().zip(pixel) {
+        let unpremultiplied = (srgb as f32 / alpha).round().min(255.0) as usize;
+        *linear = SRGB_TO_LINEAR_LUT[unpremultiplied] * alpha;
+    }
+    color
+}
+
+#[inline]
+fn linear_to_srgb(value: f32) -> u8 {
+    let index = (value.clamp(0.0, 1.0) * (LINEAR_TO_SRGB_LUT_SIZE - 1) as f32).round();
+    LINEAR_TO_SRGB_LUT[index as usize]
+}
+
+impl<'a> fmt::Debug for CanvasView<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CanvasView")
+            .field("pixels", &self.pixels.len())
+            .field("size", &self.size)
+            .field("stride", &self.stride)
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for CanvasMut<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CanvasMut")
+            .field("pixels", &self.pixels.len())
+            .field("size", &self.size)
+            .field("stride", &self.stride)
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+/// Converts the canvas to its coverage, as `Canvas::to_format(Format::A8)` does.
+#[cfg(feature = "image")]
+impl From<&Canvas> for image::GrayImage {
+    fn from(canvas: &Canvas) -> image::GrayImage {
+        let coverage = canvas.to_format(Format::A8);
+        image::GrayImage::from_raw(
+            canvas.size.x() as u32,
+            canvas.size.y() as u32,
+            coverage.pixels,
+        )
+        .unwrap()
+    }
+}
+
+/// Converts the canvas to non-premultiplied RGBA, as `Canvas::to_image_data()` does.
+#[cfg(feature = "image")]
+impl From<&Canvas> for image::RgbaImage {
+    fn from(canvas: &Canvas) -> image::RgbaImage {
+        image::RgbaImage::from_raw(
+            canvas.size.x() as u32,
+            canvas.size.y() as u32,
+            canvas.to_image_data(),
+        )
+        .unwrap()
+    }
 }
 
 impl fmt::Debug for Canvas {
@@ -211,6 +958,11 @@ pub enum Format {
     Rgb24,
     /// A8.
     A8,
+    /// A1, packed eight pixels to a byte with the leftmost pixel in the most significant bit.
+    ///
+    /// Each pixel is either entirely on or off, as on e-ink and monochrome displays. Views into
+    /// `A1` images must start on a byte boundary.
+    A1,
 }
 
 impl Format {
@@ -221,6 +973,7 @@ impl Format {
             Format::Rgba32 => 32,
             Format::Rgb24 => 24,
             Format::A8 => 8,
+            Format::A1 => 1,
         }
     }
 
@@ -230,7 +983,7 @@ impl Format {
         match self {
             Format::Rgba32 => 4,
             Format::Rgb24 => 3,
-            Format::A8 => 1,
+            Format::A8 | Format::A1 => 1,
         }
     }
 
@@ -241,21 +994,207 @@ impl Format {
     }
 
     /// Returns the number of bytes per pixel that this image format corresponds to.
+    ///
+    /// This is 0 for `A1`, which packs several pixels into each byte; use `row_len()` to size
+    /// rows.
     #[inline]
     pub fn bytes_per_pixel(self) -> u8 {
         self.bits_per_pixel() / 8
     }
+
+    /// Returns the number of bytes that a row of `width` pixels takes up, without padding.
+    #[inline]
+    pub fn row_len(self, width: usize) -> usize {
+        utils::div_round_up(width * self.bits_per_pixel() as usize, 8)
+    }
+
+    /// Picks the format to render a glyph in from a list of acceptable formats, most preferred
+    /// first.
+    ///
+    /// `Rgba32` suits only glyphs with color, such as color emoji; `Rgb24` suits glyphs rendered
+    /// with subpixel antialiasing; `A1` suits glyphs without color rendered bi-level; and `A8`
+    /// suits every glyph. The first acceptable format that
+    /// suits the glyph is returned. If none does, the first acceptable format is returned and the
+    /// glyph is converted to it, and if the list is empty, `A8` is returned.
+    pub fn negotiate(
+        acceptable_formats: &[Format],
+        has_color: bool,
+        rasterization_options: RasterizationOptions,
+    ) -> Format {
+        let rasterization_options = rasterization_options.plain();
+        let suits = |format: Format| match format {
+            Format::Rgba32 => has_color,
+            Format::Rgb24 => rasterization_options == RasterizationOptions::SubpixelAa,
+            Format::A1 => {
+                !has_color
+                    && matches!(
+                        rasterization_options,
+                        RasterizationOptions::Bilevel | RasterizationOptions::BilevelThreshold(_)
+                    )
+            }
+            Format::A8 => true,
+        };
+        acceptable_formats
+            .iter()
+            .copied()
+            .find(|&format| suits(format))
+            .or_else(|| acceptable_formats.first().copied())
+            .unwrap_or(Format::A8)
+    }
+}
+
+/// How `Canvas::blend()` combines a source image with the canvas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The source replaces the canvas.
+    Source,
+    /// The source is drawn over the canvas, which shows through where the source is transparent.
+    Over,
+    /// The source is kept only where the canvas is opaque, replacing the canvas. This masks a
+    /// color layer with a glyph's coverage.
+    SourceIn,
 }
 
 /// The antialiasing strategy that should be used when rasterizing glyphs.
+///
+/// Gamma correction and stem darkening are set separately, with `CoverageOptions`.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum RasterizationOptions {
     /// "Black-and-white" rendering. Each pixel is either entirely on or off.
     Bilevel,
+    /// "Black-and-white" rendering from grayscale antialiasing: each pixel is turned on if its
+    /// coverage, out of 255, is at least the given threshold.
+    ///
+    /// `Bilevel` leaves the choice of pixels to the rasterizer, which usually turns on those whose
+    /// centers are inside the outline. A threshold lets the weight of the text be tuned instead,
+    /// for e-ink panels and monochrome screens: lower values make it bolder and higher values
+    /// lighter, and 128 comes close to `Bilevel` without hinting.
+    BilevelThreshold(u8),
     /// Grayscale antialiasing. Only one channel is used.
     GrayscaleAa,
     /// Subpixel RGB antialiasing, for LCD screens.
     SubpixelAa,
+    /// Bi-level rendering or grayscale antialiasing, whichever the font's `gasp` table recommends
+    /// at the size being rasterized. Fonts without a `gasp` table are antialiased.
+    ///
+    /// Use `resolve()` to find out which one that is.
+    FontDefault,
+    /// The given antialiasing, rasterized with the fixed-point rasterizer in the `deterministic`
+    /// module instead of the loader's own, so that the output is bit-identical on every platform
+    /// and processor. The outline is never hinted. Use `deterministic()` to set this flag on other
+    /// options.
+    Deterministic(DeterministicAa),
+}
+
+/// The antialiasing of `RasterizationOptions::Deterministic`, which mirrors the other options.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeterministicAa {
+    /// As `RasterizationOptions::Bilevel`.
+    Bilevel,
+    /// As `RasterizationOptions::BilevelThreshold`.
+    BilevelThreshold(u8),
+    /// As `RasterizationOptions::GrayscaleAa`.
+    GrayscaleAa,
+    /// As `RasterizationOptions::SubpixelAa`, without the filtering that LCD rasterizers apply.
+    SubpixelAa,
+    /// As `RasterizationOptions::FontDefault`.
+    FontDefault,
+}
+
+impl RasterizationOptions {
+    /// Returns the options that rasterizing glyphs of `font` at `pixels_per_em` uses.
+    ///
+    /// `FontDefault` becomes `Bilevel` or `GrayscaleAa` according to the font's `gasp` table, and
+    /// stays deterministic if it was; the other options are returned unchanged.
+    pub fn resolve<F>(self, font: &F, pixels_per_em: f32) -> RasterizationOptions
+    where
+        F: Loader,
+    {
+        match self {
+            RasterizationOptions::FontDefault => {
+                let ppem = PpemRounding::Nearest.round(Px(pixels_per_em)).0;
+                let ppem = ppem.clamp(0.0, u16::MAX as f32) as u16;
+                font.gasp_behavior(ppem)
+                    .map_or(RasterizationOptions::GrayscaleAa, |behavior| {
+                        behavior.rasterization_options()
+                    })
+            }
+            RasterizationOptions::Deterministic(DeterministicAa::FontDefault) => {
+                RasterizationOptions::FontDefault
+                    .resolve(font, pixels_per_em)
+                    .deterministic()
+            }
+            options => options,
+        }
+    }
+
+    /// Returns these options with the `Deterministic` flag set.
+    pub fn deterministic(self) -> RasterizationOptions {
+        RasterizationOptions::Deterministic(match self {
+            RasterizationOptions::Bilevel => DeterministicAa::Bilevel,
+            RasterizationOptions::BilevelThreshold(threshold) => {
+                DeterministicAa::BilevelThreshold(threshold)
+            }
+            RasterizationOptions::GrayscaleAa => DeterministicAa::GrayscaleAa,
+            RasterizationOptions::SubpixelAa => DeterministicAa::SubpixelAa,
+            RasterizationOptions::FontDefault => DeterministicAa::FontDefault,
+            RasterizationOptions::Deterministic(antialiasing) => antialiasing,
+        })
+    }
+
+    /// Returns true if these options have the `Deterministic` flag set.
+    #[inline]
+    pub fn is_deterministic(self) -> bool {
+        matches!(self, RasterizationOptions::Deterministic(_))
+    }
+
+    /// Returns these options with the `Deterministic` flag cleared.
+    pub fn plain(self) -> RasterizationOptions {
+        match self {
+            RasterizationOptions::Deterministic(antialiasing) => match antialiasing {
+                DeterministicAa::Bilevel => RasterizationOptions::Bilevel,
+                DeterministicAa::BilevelThreshold(threshold) => {
+                    RasterizationOptions::BilevelThreshold(threshold)
+                }
+                DeterministicAa::GrayscaleAa => RasterizationOptions::GrayscaleAa,
+                DeterministicAa::SubpixelAa => RasterizationOptions::SubpixelAa,
+                DeterministicAa::FontDefault => RasterizationOptions::FontDefault,
+            },
+            options => options,
+        }
+    }
+}
+
+/// Adjustments to antialiased glyph coverage that make text match the weight of platform
+/// renderers, applied by `Canvas::adjust_coverage()` and `Loader::rasterize_glyph_adjusted()`.
+///
+/// Antialiased text composited without these looks lighter than native text, most of all at
+/// small sizes and in light text on dark backgrounds. The default leaves coverage unchanged.
+///
+/// These aren't part of `RasterizationOptions`, which only chooses the antialiasing strategy:
+/// every loader hands that to its platform rasterizer, none of which take a gamma, and it goes
+/// into `RasterKey`s and the C API as a small code. Coverage is adjusted after rasterization
+/// instead, so it works the same with every loader.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CoverageOptions {
+    /// The gamma to apply to coverage, which is raised to the power of `1 / gamma`. 1.0 leaves it
+    /// unchanged, and larger values make text heavier; 1.8 to 2.2 approximates most platforms.
+    pub gamma: f32,
+    /// Whether to embolden stems at small sizes, as macOS and FreeType's CFF rasterizer do.
+    ///
+    /// The stems of a glyph rasterized at 9 pixels per em or less are widened by half a pixel,
+    /// and the amount falls off until there is no darkening at 36 pixels per em.
+    pub stem_darkening: bool,
+}
+
+impl Default for CoverageOptions {
+    #[inline]
+    fn default() -> CoverageOptions {
+        CoverageOptions {
+            gamma: 1.0,
+            stem_darkening: false,
+        }
+    }
 }
 
 trait Blit {
@@ -320,3 +1259,289 @@ impl Blit for BlitRgb24ToRgba32 {
         }
     }
 }
+
+struct BlitA8ToRgba32;
+
+impl Blit for BlitA8ToRgba32 {
+    #[inline]
+    fn blit(dest: &mut [u8], src: &[u8]) {
+        for (dest, src) in dest.chunks_mut(4).zip(src.iter()) {
+            dest.copy_from_slice(&[*src; 4]);
+        }
+    }
+}
+
+struct BlitRgba32ToA8;
+
+impl Blit for BlitRgba32ToA8 {
+    #[inline]
+    fn blit(dest: &mut [u8], src: &[u8]) {
+        for (dest, src) in dest.iter_mut().zip(src.chunks(4)) {
+            *dest = src[3]
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        BlendMode, Canvas, CanvasMut, CanvasView, CoverageOptions, Format, RasterizationOptions,
+    };
+    use pathfinder_geometry::rect::RectI;
+    use pathfinder_geometry::vector::Vector2I;
+
+    #[test]
+    fn test_negotiate_format() {
+        let formats = [Format::Rgba32, Format::Rgb24, Format::A8];
+        let negotiate = |has_color, options| Format::negotiate(&formats, has_color, options);
+        assert_eq!(
+            negotiate(true, RasterizationOptions::GrayscaleAa),
+            Format::Rgba32
+        );
+        assert_eq!(
+            negotiate(false, RasterizationOptions::SubpixelAa),
+            Format::Rgb24
+        );
+        assert_eq!(
+            negotiate(false, RasterizationOptions::GrayscaleAa),
+            Format::A8
+        );
+        let rgba_only = [Format::Rgba32];
+        assert_eq!(
+            Format::negotiate(&rgba_only, false, RasterizationOptions::Bilevel),
+            Format::Rgba32
+        );
+        assert_eq!(
+            Format::negotiate(&[], true, RasterizationOptions::Bilevel),
+            Format::A8
+        );
+        let a1_first = [Format::A1, Format::A8];
+        assert_eq!(
+            Format::negotiate(&a1_first, false, RasterizationOptions::BilevelThreshold(96)),
+            Format::A1
+        );
+        assert_eq!(
+            Format::negotiate(&a1_first, false, RasterizationOptions::GrayscaleAa),
+            Format::A8
+        );
+    }
+
+    #[test]
+    fn test_a1() {
+        let mut coverage = Canvas::new(Vector2I::new(10, 2), Format::A8);
+        coverage.pixels[..10].copy_from_slice(&[255, 0, 128, 127, 64, 0, 0, 0, 200, 1]);
+        coverage.pixels[10..].copy_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 255]);
+
+        let packed = coverage.to_format(Format::A1);
+        assert_eq!(Format::A1.row_len(10), 2);
+        assert_eq!((packed.stride, packed.pixels.len()), (2, 4));
+        assert_eq!(packed.pixels, [0b1010_0000, 0b1000_0000, 0, 0b0100_0000]);
+        assert_eq!(
+            packed.to_format(Format::A8).pixels[..10],
+            [255, 0, 255, 0, 0, 0, 0, 0, 255, 0]
+        );
+
+        let mut thresholded = coverage.to_format(Format::A8);
+        thresholded.threshold(64);
+        assert_eq!(
+            thresholded.pixels[..10],
+            [255, 0, 255, 255, 255, 0, 0, 0, 255, 0]
+        );
+        thresholded.threshold(0);
+        assert_eq!(thresholded.pixels[5], 0);
+
+        // Blending into an `A1` canvas sets and clears single bits.
+        let mut target = Canvas::new(Vector2I::new(12, 1), Format::A1);
+        target.pixels.copy_from_slice(&[0xff, 0xf0]);
+        let mut gap = Canvas::new(Vector2I::new(3, 1), Format::A8);
+        gap.pixels.copy_from_slice(&[0, 255, 0]);
+        target.blend(&gap, Vector2I::new(6, 0), BlendMode::Source);
+        assert_eq!(target.pixels, [0b1111_1101, 0b0111_0000]);
+
+        assert!(packed
+            .as_view()
+            .sub_view(RectI::new(Vector2I::new(8, 0), Vector2I::new(2, 2)))
+            .is_some());
+        assert!(packed
+            .as_view()
+            .sub_view(RectI::new(Vector2I::new(3, 0), Vector2I::new(2, 2)))
+            .is_none());
+    }
+
+    #[test]
+    fn test_to_image_data() {
+        let mut canvas = Canvas::with_stride(Vector2I::new(2, 1), 12, Format::Rgba32);
+        canvas.pixels[..8].copy_from_slice(&[64, 32, 0, 128, 10, 20, 30, 255]);
+        assert_eq!(
+            canvas.to_image_data(),
+            vec![128, 64, 0, 128, 10, 20, 30, 255]
+        );
+
+        let mut mask = Canvas::new(Vector2I::new(1, 1), Format::A8);
+        mask.pixels[0] = 51;
+        assert_eq!(mask.to_image_data(), vec![255, 255, 255, 51]);
+    }
+
+    #[test]
+    fn test_to_pgm() {
+        let mut mask = Canvas::with_stride(Vector2I::new(2, 2), 4, Format::A8);
+        mask.pixels.copy_from_slice(&[0, 64, 9, 9, 128, 255, 9, 9]);
+        assert_eq!(mask.to_pgm(), b"P5\n2 2\n255\n\x00\x40\x80\xff");
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_to_png() {
+        let mut color = Canvas::new(Vector2I::new(2, 1), Format::Rgba32);
+        color
+            .pixels
+            .copy_from_slice(&[64, 32, 0, 128, 10, 20, 30, 255]);
+        let png = color.to_png();
+
+        let decoder = png::Decoder::new(&png[..]);
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (2, 1));
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        assert_eq!(pixels, color.to_image_data());
+
+        assert!(Canvas::new(Vector2I::new(0, 3), Format::A8)
+            .to_png()
+            .is_empty());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_image_conversions() {
+        let mut mask = Canvas::new(Vector2I::new(2, 1), Format::A1);
+        mask.pixels[0] = 0b0100_0000;
+        let gray = image::GrayImage::from(&mask);
+        assert_eq!(gray.get_pixel(1, 0).0, [255]);
+        assert_eq!(gray.get_pixel(0, 0).0, [0]);
+        let rgba = image::RgbaImage::from(&mask);
+        assert_eq!(rgba.get_pixel(1, 0).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_blend() {
+        let mut mask = Canvas::new(Vector2I::new(2, 1), Format::A8);
+        mask.pixels.copy_from_slice(&[255, 0]);
+        let mut color = Canvas::new(Vector2I::new(2, 1), Format::Rgba32);
+        color
+            .pixels
+            .copy_from_slice(&[0, 0, 255, 255, 0, 0, 255, 255]);
+
+        // Mask a blue layer with the coverage, then draw it over a half-transparent red pixel.
+        let mut layer = mask.to_format(Format::Rgba32);
+        layer.blend(&color, Vector2I::default(), BlendMode::SourceIn);
+        assert_eq!(layer.pixels, [0, 0, 255, 255, 0, 0, 0, 0]);
+
+        let mut target = Canvas::new(Vector2I::new(3, 1), Format::Rgba32);
+        target.pixels[8..12].copy_from_slice(&[128, 0, 0, 128]);
+        target.blend(&layer, Vector2I::new(1, 0), BlendMode::Over);
+        assert_eq!(target.pixels, [0, 0, 0, 0, 0, 0, 255, 255, 128, 0, 0, 128]);
+
+        assert_eq!(layer.to_format(Format::A8).pixels, mask.pixels);
+        let mut gray = Canvas::new(Vector2I::new(2, 1), Format::Rgb24);
+        gray.blend(&mask, Vector2I::default(), BlendMode::Source);
+        assert_eq!(gray.pixels, [255, 255, 255, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_views() {
+        let mut atlas = Canvas::new(Vector2I::new(4, 3), Format::A8);
+        let mut glyph = Canvas::new(Vector2I::new(2, 2), Format::A8);
+        glyph.pixels.copy_from_slice(&[1, 2, 3, 4]);
+
+        let region = RectI::new(Vector2I::new(1, 1), Vector2I::new(2, 2));
+        let mut atlas_view = atlas.as_view_mut();
+        let mut slot = atlas_view.sub_view_mut(region).unwrap();
+        slot.blend(&glyph.as_view(), Vector2I::default(), BlendMode::Source);
+        assert_eq!(atlas.pixels, [0, 0, 0, 0, 0, 1, 2, 0, 0, 3, 4, 0]);
+
+        let view = atlas.as_view().sub_view(region).unwrap();
+        assert_eq!(view.pixels.len(), 6);
+        assert_eq!(view.to_canvas().pixels, glyph.pixels);
+        assert!(atlas
+            .as_view()
+            .sub_view(RectI::new(Vector2I::new(3, 0), Vector2I::splat(2)))
+            .is_none());
+
+        let mut buffer = [0; 7];
+        assert!(CanvasMut::new(&mut buffer, Vector2I::new(2, 2), 6, Format::A8).is_none());
+        assert!(CanvasView::new(&buffer, Vector2I::new(2, 2), 5, Format::A8).is_some());
+    }
+
+    #[test]
+    fn test_composite_onto_blends_in_linear_light() {
+        let mut glyph = Canvas::new(Vector2I::new(2, 1), Format::A8);
+        glyph.pixels.copy_from_slice(&[255, 128]);
+
+        let mut target = Canvas::new(Vector2I::new(3, 1), Format::Rgba32);
+        target.pixels.iter_mut().for_each(|pixel| *pixel = 255);
+        glyph.composite_onto(&mut target, Vector2I::new(1, 0), [0, 0, 0, 255], 1.0);
+
+        // Untouched, fully covered, and half covered. Half coverage in linear light is lighter
+        // than the naïve sRGB midpoint.
+        assert_eq!(&target.pixels[0..4], &[255, 255, 255, 255]);
+        assert_eq!(&target.pixels[4..8], &[0, 0, 0, 255]);
+        assert_eq!(&target.pixels[8..12], &[187, 187, 187, 255]);
+    }
+
+    #[test]
+    fn test_composite_onto_coverage_targets() {
+        let mut glyph = Canvas::new(Vector2I::new(2, 1), Format::A8);
+        glyph.pixels.copy_from_slice(&[255, 128]);
+
+        // Coverage accumulates as alpha, scaled by the text alpha; the color is ignored.
+        let mut target = Canvas::new(Vector2I::new(3, 1), Format::A8);
+        target.pixels.copy_from_slice(&[10, 0, 128]);
+        glyph.composite_onto(&mut target, Vector2I::new(1, 0), [255, 0, 0, 255], 1.0);
+        assert_eq!(target.pixels, [10, 255, 192]);
+
+        let mut target = Canvas::new(Vector2I::new(2, 1), Format::A8);
+        glyph.composite_onto(&mut target, Vector2I::default(), [0, 0, 0, 128], 1.0);
+        assert_eq!(target.pixels, [128, 64]);
+
+        // `A1` targets are thresholded at half coverage.
+        let mut target = Canvas::new(Vector2I::new(3, 1), Format::A1);
+        glyph.composite_onto(&mut target, Vector2I::new(1, 0), [0, 0, 0, 255], 1.0);
+        assert_eq!(target.pixels, [0b0110_0000]);
+    }
+
+    #[test]
+    fn test_adjust_coverage() {
+        let mut canvas = Canvas::new(Vector2I::new(4, 1), Format::A8);
+        canvas.pixels.copy_from_slice(&[0, 255, 64, 0]);
+
+        let mut unchanged = canvas.to_format(Format::A8);
+        unchanged.adjust_coverage(CoverageOptions::default(), 12.0);
+        assert_eq!(unchanged.pixels, canvas.pixels);
+
+        let gamma = CoverageOptions {
+            gamma: 2.0,
+            ..CoverageOptions::default()
+        };
+        let mut corrected = canvas.to_format(Format::A8);
+        corrected.adjust_coverage(gamma, 12.0);
+        assert_eq!(corrected.pixels, [0, 255, 128, 0]);
+
+        let darkening = CoverageOptions {
+            stem_darkening: true,
+            ..CoverageOptions::default()
+        };
+        let mut large = canvas.to_format(Format::A8);
+        large.adjust_coverage(darkening, 48.0);
+        assert_eq!(large.pixels, canvas.pixels);
+        let mut small = canvas.to_format(Format::A8);
+        small.adjust_coverage(darkening, 8.0);
+        assert!(small.pixels[0] > 0 && small.pixels[2] > 64);
+        assert_eq!(small.pixels[1], 255);
+
+        let mut color = Canvas::new(Vector2I::new(1, 1), Format::Rgba32);
+        color.pixels.copy_from_slice(&[10, 20, 30, 40]);
+        color.adjust_coverage(gamma, 12.0);
+        assert_eq!(color.pixels, [10, 20, 30, 40]);
+    }
+}
@@ -0,0 +1,128 @@
+// font-kit/src/carets.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Caret positions inside ligatures, from the `LigCaretList` of the `GDEF` table.
+//!
+//! `ttf-parser` doesn't read this part of `GDEF`, so it is parsed here. Carets given as coordinates
+//! are used as they are; carets given as contour points are looked up in the glyph's outline.
+//! Device tables and variation deltas are ignored.
+
+use byteorder::{BigEndian, ByteOrder};
+use ttf_parser::{Face, Tag};
+
+use crate::glyph_contours;
+
+/// Returns the caret positions inside a ligature glyph, in font units from its origin along the
+/// direction of the text, in increasing order. The result is empty if the glyph isn't a ligature
+/// or the font doesn't say where its carets go.
+pub(crate) fn ligature_carets(face: &Face, glyph_id: u16) -> Vec<f32> {
+    let mut carets = read_ligature_carets(face, glyph_id).unwrap_or_default();
+    carets.sort_by(|a, b| a.total_cmp(b));
+    carets
+}
+
+fn read_ligature_carets(face: &Face, glyph_id: u16) -> Option<Vec<f32>> {
+    let gdef = face.raw_face().table(Tag::from_bytes(b"GDEF"))?;
+    let lig_caret_list = subtable(gdef, read_u16(gdef, 8)?)?;
+    let coverage = subtable(lig_caret_list, read_u16(lig_caret_list, 0)?)?;
+    let index = coverage_index(coverage, glyph_id)?;
+    if index >= read_u16(lig_caret_list, 2)? {
+        return None;
+    }
+    let lig_glyph = subtable(
+        lig_caret_list,
+        read_u16(lig_caret_list, 4 + 2 * index as usize)?,
+    )?;
+
+    let caret_count = read_u16(lig_glyph, 0)? as usize;
+    let mut carets = Vec::with_capacity(caret_count);
+    for caret in 0..caret_count {
+        let caret_value = subtable(lig_glyph, read_u16(lig_glyph, 2 + 2 * caret)?)?;
+        let position = match read_u16(caret_value, 0)? {
+            // A coordinate, without or with a device table.
+            1 | 3 => read_u16(caret_value, 2)? as i16 as f32,
+            // The x coordinate of a point in the glyph's outline.
+            2 => {
+                let point_index = read_u16(caret_value, 2)? as usize;
+                let contours = glyph_contours::glyph_contours(face, glyph_id).ok()?;
+                contours
+                    .iter()
+                    .flat_map(|contour| &contour.points)
+                    .nth(point_index)?
+                    .0
+                    .x()
+            }
+            _ => continue,
+        };
+        carets.push(position);
+    }
+    Some(carets)
+}
+
+// Returns the index of the glyph in a coverage table, if it's covered.
+fn coverage_index(coverage: &[u8], glyph_id: u16) -> Option<u16> {
+    let count = read_u16(coverage, 2)? as usize;
+    match read_u16(coverage, 0)? {
+        1 => (0..count)
+            .position(|index| read_u16(coverage, 4 + 2 * index) == Some(glyph_id))
+            .map(|index| index as u16),
+        2 => (0..count).find_map(|index| {
+            let record = 4 + 6 * index;
+            let (start, end) = (read_u16(coverage, record)?, read_u16(coverage, record + 2)?);
+            let start_index = read_u16(coverage, record + 4)?;
+            if (start..=end).contains(&glyph_id) {
+                Some(start_index + (glyph_id - start))
+            } else {
+                None
+            }
+        }),
+        _ => None,
+    }
+}
+
+// Returns the data at a nonzero offset from the start of `table`.
+fn subtable(table: &[u8], offset: u16) -> Option<&[u8]> {
+    match offset {
+        0 => None,
+        offset => table.get(offset as usize..),
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..(offset + 2)).map(BigEndian::read_u16)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use crate::test_fixtures::EB_GARAMOND;
+
+    #[test]
+    fn test_ligature_carets() {
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let units_per_em = font.metrics().units_per_em as f32;
+
+        let ffi = font.glyph_for_char('\u{fb03}').unwrap();
+        let carets = font.ligature_carets(ffi, units_per_em);
+        assert_eq!(carets.len(), 2);
+        assert!(0.0 < carets[0] && carets[0] < carets[1]);
+        assert!(carets[1] < font.advance(ffi).unwrap().x());
+        assert_eq!(
+            font.ligature_carets(ffi, units_per_em / 2.0),
+            [carets[0] / 2.0, carets[1] / 2.0]
+        );
+
+        let fi = font.glyph_for_char('\u{fb01}').unwrap();
+        assert_eq!(font.ligature_carets(fi, units_per_em).len(), 1);
+        let a = font.glyph_for_char('a').unwrap();
+        assert!(font.ligature_carets(a, units_per_em).is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+// font-kit/src/coverage.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Sets of Unicode codepoints, used to describe which characters a font supports.
+
+use std::cmp;
+use std::iter::{self, FromIterator};
+use std::ops::RangeInclusive;
+
+/// A set of Unicode codepoints, stored compactly as sorted, disjoint ranges.
+///
+/// Fonts usually support long runs of consecutive codepoints, so this takes far less memory than
+/// a set of individual characters, and lookups are a binary search.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnicodeRangeSet {
+    // Sorted, non-overlapping, and non-adjacent.
+    ranges: Vec<RangeInclusive<u32>>,
+}
+
+impl UnicodeRangeSet {
+    /// Creates an empty set.
+    #[inline]
+    pub fn new() -> UnicodeRangeSet {
+        UnicodeRangeSet::default()
+    }
+
+    /// Adds a single codepoint to the set.
+    #[inline]
+    pub fn insert(&mut self, codepoint: u32) {
+        self.insert_range(codepoint..=codepoint)
+    }
+
+    /// Adds a range of codepoints to the set, merging it with the ranges it touches.
+    pub fn insert_range(&mut self, range: RangeInclusive<u32>) {
+        let (mut start, mut end) = (*range.start(), *range.end());
+        if start > end {
+            return;
+        }
+
+        let first = self
+            .ranges
+            .partition_point(|range| range.end().saturating_add(1) < start);
+        let last = self
+            .ranges
+            .partition_point(|range| *range.start() <= end.saturating_add(1));
+        if first < last {
+            start = cmp::min(start, *self.ranges[first].start());
+            end = cmp::max(end, *self.ranges[last - 1].end());
+        }
+        self.ranges.splice(first..last, iter::once(start..=end));
+    }
+
+    /// Returns true if the set contains the given character.
+    #[inline]
+    pub fn contains(&self, character: char) -> bool {
+        self.contains_codepoint(character as u32)
+    }
+
+    /// Returns true if the set contains the given codepoint.
+    pub fn contains_codepoint(&self, codepoint: u32) -> bool {
+        let index = self
+            .ranges
+            .partition_point(|range| *range.end() < codepoint);
+        self.ranges
+            .get(index)
+            .is_some_and(|range| range.contains(&codepoint))
+    }
+
+    /// Returns the ranges in the set, sorted and disjoint.
+    #[inline]
+    pub fn ranges(&self) -> &[RangeInclusive<u32>] {
+        &self.ranges
+    }
+
+    /// Returns the number of codepoints in the set.
+    pub fn len(&self) -> usize {
+        self.ranges
+            .iter()
+            .map(|range| (range.end() - range.start()) as usize + 1)
+            .sum()
+    }
+
+    /// Returns true if the set contains no codepoints.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns an iterator over the codepoints in the set, in ascending order.
+    pub fn codepoints(&self) -> impl Iterator<Item = u32> + '_ {
+        self.ranges.iter().flat_map(|range| range.clone())
+    }
+
+    /// Returns the set of codepoints that are in either `self` or `other`.
+    pub fn union(&self, other: &UnicodeRangeSet) -> UnicodeRangeSet {
+        let mut union = self.clone();
+        for range in &other.ranges {
+            union.insert_range(range.clone());
+        }
+        union
+    }
+}
+
+impl FromIterator<u32> for UnicodeRangeSet {
+    fn from_iter<I>(codepoints: I) -> UnicodeRangeSet
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let mut codepoints: Vec<u32> = codepoints.into_iter().collect();
+        codepoints.sort_unstable();
+        codepoints.dedup();
+
+        let mut ranges: Vec<RangeInclusive<u32>> = vec![];
+        for codepoint in codepoints {
+            match ranges.last_mut() {
+                Some(range) if *range.end() + 1 == codepoint => {
+                    *range = *range.start()..=codepoint;
+                }
+                _ => ranges.push(codepoint..=codepoint),
+            }
+        }
+        UnicodeRangeSet { ranges }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UnicodeRangeSet;
+
+    #[test]
+    fn test_insert_range_merges_neighbors() {
+        let mut set: UnicodeRangeSet = [0x41, 0x42, 0x43, 0x50].iter().copied().collect();
+        assert_eq!(set.ranges(), &[0x41..=0x43, 0x50..=0x50]);
+
+        set.insert_range(0x44..=0x4f);
+        assert_eq!(set.ranges(), &[0x41..=0x50]);
+        assert_eq!(set.len(), 16);
+        assert!(set.contains('A'));
+        assert!(!set.contains('a'));
+    }
+}
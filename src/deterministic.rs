@@ -0,0 +1,558 @@
+// font-kit/src/deterministic.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A rasterizer whose output is the same on every platform.
+//!
+//! The platform rasterizers differ in their hinting, filtering, and rounding, and floating-point
+//! code can give different results on different processors when the compiler uses fused
+//! multiply-add or vector instructions. This rasterizer converts each point to fixed point as soon
+//! as it is transformed, and does everything after that, from flattening curves to accumulating
+//! coverage, with integers. A given outline, size, and transform therefore always produce the same
+//! pixels, which makes the output safe to hash for content-addressed caches and to compare exactly
+//! in golden tests.
+//!
+//! `Loader::rasterize_glyph_deterministic()` rasterizes glyphs with it, as do `rasterize_glyph()`
+//! and the glyph caches and atlases built on it when given `RasterizationOptions::Deterministic`.
+//! Its output is only as
+//! reproducible as the outline: the pure-Rust `Font` loader reads outlines straight from the font
+//! file, so it is the same everywhere, whereas the platform loaders get outlines from the system.
+
+use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+
+use crate::canvas::{Canvas, CanvasMut, Format, RasterizationOptions};
+use crate::error::GlyphLoadingError;
+use crate::hinting::HintingOptions;
+use crate::loader::Loader;
+use crate::outline::{Outline, OutlineSink};
+use crate::units;
+
+// Fixed-point coordinates have 8 fractional bits.
+const FRAC_BITS: u32 = 8;
+const ONE: i64 = 1 << FRAC_BITS;
+
+// Coordinates are clamped to this magnitude so that interpolating between them can't overflow.
+const MAX_COORDINATE: i64 = 1 << 28;
+
+// The accumulated coverage of a fully covered pixel: twice its area in fixed point.
+const FULL_COVERAGE: i64 = 2 * ONE * ONE;
+
+// Curves are flattened until they stray no more than 1/16 of a pixel from their chords.
+const FLATNESS: i64 = ONE / 16;
+const MAX_CURVE_SEGMENTS: i64 = 256;
+
+// The coverage at which `Bilevel` turns a pixel on.
+const BILEVEL_THRESHOLD: u8 = 128;
+
+/// Rasterizes an outline into `canvas`, replacing its contents.
+///
+/// `transform` maps outline coordinates to pixels, with the origin at the top left of the canvas
+/// and y pointing down. Antialiased coverage is the exact area of each pixel inside the outline,
+/// under the nonzero fill rule. `SubpixelAa` rasterizes each color channel as a third of a pixel,
+/// without the filtering that LCD rasterizers apply, and `FontDefault` antialiases, since there's
+/// no font to ask.
+pub fn rasterize_outline(
+    outline: &Outline,
+    transform: Transform2F,
+    canvas: &mut Canvas,
+    rasterization_options: RasterizationOptions,
+) {
+    let mut rasterizer = Rasterizer::new(canvas.size, transform, (1.0, 1.0), rasterization_options);
+    outline.copy_to(&mut rasterizer);
+    rasterizer.finish(canvas);
+}
+
+// Rasterizes a glyph into `target` as `Loader::rasterize_glyph_deterministic()` does, drawing
+// straight into its pixels.
+pub(crate) fn rasterize_glyph_into<L>(
+    loader: &L,
+    target: &mut CanvasMut,
+    glyph_id: u32,
+    point_size: f32,
+    transform: Transform2F,
+    rasterization_options: RasterizationOptions,
+) -> Result<(), GlyphLoadingError>
+where
+    L: Loader,
+{
+    let pixels_per_em = units::pixels_per_em(point_size, &transform);
+    let rasterization_options = rasterization_options.plain().resolve(loader, pixels_per_em);
+    // Raster coordinates point down.
+    let scale = point_size as f64 / loader.metrics().units_per_em as f64;
+    let mut rasterizer = Rasterizer::new(
+        target.size,
+        transform,
+        (scale, -scale),
+        rasterization_options,
+    );
+    loader.outline(glyph_id, HintingOptions::None, &mut rasterizer)?;
+    rasterizer.finish_into(target);
+    Ok(())
+}
+
+/// An outline sink that accumulates the coverage of the outline it receives.
+pub(crate) struct Rasterizer {
+    // The affine transform from outline coordinates to pixels, as `[a, b, c, d, x, y]`.
+    matrix: [f64; 6],
+    rasterization_options: RasterizationOptions,
+    size: Vector2I,
+    // The width of the accumulation buffer in samples, which is three per pixel for subpixel
+    // antialiasing.
+    width: i64,
+    height: i64,
+    // Each row has two samples past the right edge, which collect coverage that leaves the canvas.
+    accumulation: Vec<i64>,
+    start: (i64, i64),
+    current: (i64, i64),
+}
+
+impl Rasterizer {
+    /// Creates a rasterizer for a canvas of `size` pixels. Points are scaled by `scale`, such as
+    /// from font units to pixels with y pointing down, and then transformed.
+    pub(crate) fn new(
+        size: Vector2I,
+        transform: Transform2F,
+        scale: (f64, f64),
+        rasterization_options: RasterizationOptions,
+    ) -> Rasterizer {
+        let rasterization_options = rasterization_options.plain();
+        let samples_per_pixel = match rasterization_options {
+            RasterizationOptions::SubpixelAa => 3,
+            _ => 1,
+        };
+        // Scalar `f64` arithmetic is rounded the same way everywhere, unlike the SIMD code that
+        // composes `Transform2F`s.
+        let oversample = samples_per_pixel as f64;
+        let matrix = [
+            transform.matrix.m11() as f64 * scale.0 * oversample,
+            transform.matrix.m21() as f64 * scale.0,
+            transform.matrix.m12() as f64 * scale.1 * oversample,
+            transform.matrix.m22() as f64 * scale.1,
+            transform.vector.x() as f64 * oversample,
+            transform.vector.y() as f64,
+        ];
+        let width = size.x().max(0) as i64 * samples_per_pixel;
+        let height = size.y().max(0) as i64;
+        Rasterizer {
+            matrix,
+            rasterization_options,
+            size,
+            width,
+            height,
+            accumulation: vec![0; ((width + 2) * height) as usize],
+            start: (0, 0),
+            current: (0, 0),
+        }
+    }
+
+    /// Writes the accumulated coverage into `canvas`, converting it to the canvas's format.
+    pub(crate) fn finish(self, canvas: &mut Canvas) {
+        self.finish_into(&mut canvas.as_view_mut())
+    }
+
+    /// Writes the accumulated coverage into `target` a row at a time, converting it to the
+    /// target's format. Every pixel of the target is overwritten.
+    pub(crate) fn finish_into(mut self, target: &mut CanvasMut) {
+        self.line(self.current, self.start);
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        let (format, threshold) = match self.rasterization_options {
+            RasterizationOptions::SubpixelAa => (Format::Rgb24, None),
+            RasterizationOptions::Bilevel => (Format::A8, Some(BILEVEL_THRESHOLD)),
+            RasterizationOptions::BilevelThreshold(threshold) => (Format::A8, Some(threshold)),
+            RasterizationOptions::GrayscaleAa
+            | RasterizationOptions::FontDefault
+            | RasterizationOptions::Deterministic(_) => (Format::A8, None),
+        };
+
+        let row_len = self.width as usize;
+        let row_size = Vector2I::new(self.size.x(), 1);
+        let mut pixels = vec![0; row_len];
+        for (y, samples) in self.accumulation.chunks_exact(row_len + 2).enumerate() {
+            let mut winding = 0;
+            for (pixel, &delta) in pixels.iter_mut().zip(samples) {
+                winding += delta;
+                let covered = winding.abs().min(FULL_COVERAGE);
+                *pixel = ((covered * 255 + FULL_COVERAGE / 2) / FULL_COVERAGE) as u8;
+                if let Some(threshold) = threshold {
+                    *pixel = if *pixel > 0 && *pixel >= threshold {
+                        255
+                    } else {
+                        0
+                    };
+                }
+            }
+            target.blit_from(
+                Vector2I::new(0, y as i32),
+                &pixels,
+                row_size,
+                row_len,
+                format,
+            );
+        }
+    }
+
+    fn to_fixed(&self, point: Vector2F) -> (i64, i64) {
+        let (x, y) = (point.x() as f64, point.y() as f64);
+        let m = &self.matrix;
+        let fixed = |value: f64| {
+            (value * ONE as f64)
+                .round()
+                .clamp(-MAX_COORDINATE as f64, MAX_COORDINATE as f64) as i64
+        };
+        (
+            fixed(m[0] * x + m[2] * y + m[4]),
+            fixed(m[1] * x + m[3] * y + m[5]),
+        )
+    }
+
+    // Accumulates the coverage to the right of a line, one row at a time.
+    fn line(&mut self, from: (i64, i64), to: (i64, i64)) {
+        if from.1 == to.1 {
+            return;
+        }
+        let (sign, top, bottom) = if from.1 < to.1 {
+            (1, from, to)
+        } else {
+            (-1, to, from)
+        };
+        let (y_start, y_end) = (top.1.max(0), bottom.1.min(self.height * ONE));
+        if y_start >= y_end {
+            return;
+        }
+        let x_at = |y: i64| top.0 + ((bottom.0 - top.0) * (y - top.1)).div_euclid(bottom.1 - top.1);
+        for row in (y_start >> FRAC_BITS)..=((y_end - 1) >> FRAC_BITS) {
+            let y_0 = y_start.max(row * ONE);
+            let y_1 = y_end.min((row + 1) * ONE);
+            self.row_span(row, (x_at(y_0), y_0), (x_at(y_1), y_1), sign);
+        }
+    }
+
+    // Accumulates the coverage of part of a line within one row, one column at a time. The part
+    // runs downward from `from` to `to`.
+    fn row_span(&mut self, row: i64, from: (i64, i64), to: (i64, i64), sign: i64) {
+        let y_at = |x: i64| from.1 + ((to.1 - from.1) * (x - from.0)).div_euclid(to.0 - from.0);
+        let mut previous = from;
+        if from.0 < to.0 {
+            let first = (from.0.div_euclid(ONE) + 1).max(0);
+            let last = ((to.0 - 1).div_euclid(ONE)).min(self.width);
+            for column in first..=last {
+                let x = column * ONE;
+                let point = (x, y_at(x));
+                self.cell(row, previous, point, sign);
+                previous = point;
+            }
+        } else if from.0 > to.0 {
+            let first = ((from.0 - 1).div_euclid(ONE)).min(self.width);
+            let last = (to.0.div_euclid(ONE) + 1).max(0);
+            for column in (last..=first).rev() {
+                let x = column * ONE;
+                let point = (x, y_at(x));
+                self.cell(row, previous, point, sign);
+                previous = point;
+            }
+        }
+        self.cell(row, previous, to, sign);
+    }
+
+    // Accumulates the coverage of part of a line within one pixel. The pixel it crosses gets the
+    // area to the right of the line, and the rest goes to the next pixel, where it carries on
+    // across the row. Parts left of the canvas cover all of it, and parts to its right none.
+    fn cell(&mut self, row: i64, from: (i64, i64), to: (i64, i64), sign: i64) {
+        let right_edge = self.width * ONE;
+        let (x_0, x_1) = (from.0.clamp(0, right_edge), to.0.clamp(0, right_edge));
+        let column = x_0.min(x_1) >> FRAC_BITS;
+        let cover = (to.1 - from.1) * sign;
+        let left_area = x_0 + x_1 - 2 * column * ONE;
+        let index = (row * (self.width + 2) + column) as usize;
+        self.accumulation[index] += cover * (2 * ONE - left_area);
+        self.accumulation[index + 1] += cover * left_area;
+    }
+
+    fn quadratic(&mut self, ctrl: (i64, i64), to: (i64, i64)) {
+        let from = self.current;
+        let deviation = (from.0 - 2 * ctrl.0 + to.0)
+            .abs()
+            .max((from.1 - 2 * ctrl.1 + to.1).abs());
+        // A quadratic strays from the chords of `n` equal steps by at most a quarter of the
+        // deviation over `n` squared.
+        let n = curve_segments(deviation, 4);
+        let point = |i: i64, from: i64, ctrl: i64, to: i64| {
+            let j = n - i;
+            div_round(j * j * from + 2 * j * i * ctrl + i * i * to, n * n)
+        };
+        for i in 1..=n {
+            let next = (
+                point(i, from.0, ctrl.0, to.0),
+                point(i, from.1, ctrl.1, to.1),
+            );
+            self.line(self.current, next);
+            self.current = next;
+        }
+    }
+
+    fn cubic(&mut self, ctrl_0: (i64, i64), ctrl_1: (i64, i64), to: (i64, i64)) {
+        let from = self.current;
+        let deviation = [
+            from.0 - 2 * ctrl_0.0 + ctrl_1.0,
+            from.1 - 2 * ctrl_0.1 + ctrl_1.1,
+            ctrl_0.0 - 2 * ctrl_1.0 + to.0,
+            ctrl_0.1 - 2 * ctrl_1.1 + to.1,
+        ]
+        .iter()
+        .map(|value| value.abs())
+        .max()
+        .unwrap();
+        // A cubic strays by at most three quarters of the deviation over `n` squared.
+        let n = curve_segments(3 * deviation, 4);
+        let point = |i: i64, from: i64, ctrl_0: i64, ctrl_1: i64, to: i64| {
+            let j = n - i;
+            div_round(
+                j * j * j * from + 3 * j * j * i * ctrl_0 + 3 * j * i * i * ctrl_1 + i * i * i * to,
+                n * n * n,
+            )
+        };
+        for i in 1..=n {
+            let next = (
+                point(i, from.0, ctrl_0.0, ctrl_1.0, to.0),
+                point(i, from.1, ctrl_0.1, ctrl_1.1, to.1),
+            );
+            self.line(self.current, next);
+            self.current = next;
+        }
+    }
+}
+
+impl OutlineSink for Rasterizer {
+    fn move_to(&mut self, to: Vector2F) {
+        // Contours are closed even if the outline doesn't say so.
+        self.line(self.current, self.start);
+        self.start = self.to_fixed(to);
+        self.current = self.start;
+    }
+
+    fn line_to(&mut self, to: Vector2F) {
+        let to = self.to_fixed(to);
+        self.line(self.current, to);
+        self.current = to;
+    }
+
+    fn quadratic_curve_to(&mut self, ctrl: Vector2F, to: Vector2F) {
+        let (ctrl, to) = (self.to_fixed(ctrl), self.to_fixed(to));
+        self.quadratic(ctrl, to);
+    }
+
+    fn cubic_curve_to(&mut self, ctrl: LineSegment2F, to: Vector2F) {
+        let (ctrl_0, ctrl_1) = (self.to_fixed(ctrl.from()), self.to_fixed(ctrl.to()));
+        let to = self.to_fixed(to);
+        self.cubic(ctrl_0, ctrl_1, to);
+    }
+
+    fn close(&mut self) {
+        self.line(self.current, self.start);
+        self.current = self.start;
+    }
+}
+
+// Returns the number of equal steps that keep a curve within `FLATNESS` of its chords, given that
+// it strays by at most `deviation / divisor` over `n` squared.
+fn curve_segments(deviation: i64, divisor: i64) -> i64 {
+    let mut n = 1;
+    while n < MAX_CURVE_SEGMENTS && n * n * FLATNESS * divisor < deviation {
+        n += 1;
+    }
+    n
+}
+
+// Divides, rounding halves up.
+fn div_round(numerator: i64, denominator: i64) -> i64 {
+    (2 * numerator + denominator).div_euclid(2 * denominator)
+}
+
+#[cfg(test)]
+mod test {
+    use super::rasterize_outline;
+    use crate::canvas::{Canvas, Format, RasterizationOptions};
+    use crate::font::Font;
+    use crate::hinting::HintingOptions;
+    use crate::loader::Loader;
+    use crate::outline::{Contour, Outline, PointFlags};
+    use crate::raster_cache::{GlyphRasterCache, RasterKey};
+    use crate::test_fixtures::ARIAL;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, Vector2I};
+    use std::sync::Arc;
+
+    fn rectangle(left: f32, top: f32, right: f32, bottom: f32) -> Outline {
+        let mut contour = Contour::new();
+        for &(x, y) in &[(left, top), (right, top), (right, bottom), (left, bottom)] {
+            contour.push(Vector2F::new(x, y), PointFlags::empty());
+        }
+        Outline {
+            contours: vec![contour],
+        }
+    }
+
+    #[test]
+    fn test_rasterize_outline() {
+        // Coverage is the exact area of each pixel inside the rectangle.
+        let outline = rectangle(0.5, 0.25, 2.5, 1.25);
+        let mut canvas = Canvas::new(Vector2I::new(3, 2), Format::A8);
+        let transform = Transform2F::default();
+        rasterize_outline(
+            &outline,
+            transform,
+            &mut canvas,
+            RasterizationOptions::GrayscaleAa,
+        );
+        assert_eq!(canvas.pixels, [96, 191, 96, 32, 64, 32]);
+
+        // The contour's direction doesn't matter.
+        let mut reversed = outline.clone();
+        reversed.contours[0].positions.reverse();
+        let mut reversed_canvas = Canvas::new(Vector2I::new(3, 2), Format::A8);
+        rasterize_outline(
+            &reversed,
+            transform,
+            &mut reversed_canvas,
+            RasterizationOptions::GrayscaleAa,
+        );
+        assert_eq!(reversed_canvas.pixels, canvas.pixels);
+
+        rasterize_outline(
+            &outline,
+            transform,
+            &mut canvas,
+            RasterizationOptions::Bilevel,
+        );
+        assert_eq!(canvas.pixels, [0, 255, 0, 0, 0, 0]);
+        rasterize_outline(
+            &outline,
+            transform,
+            &mut canvas,
+            RasterizationOptions::BilevelThreshold(90),
+        );
+        assert_eq!(canvas.pixels, [255, 255, 255, 0, 0, 0]);
+
+        // Each channel covers a third of a pixel.
+        let outline = rectangle(0.0, 0.0, 0.5, 1.0);
+        let mut canvas = Canvas::new(Vector2I::new(1, 1), Format::Rgb24);
+        rasterize_outline(
+            &outline,
+            transform,
+            &mut canvas,
+            RasterizationOptions::SubpixelAa,
+        );
+        assert_eq!(canvas.pixels, [255, 128, 0]);
+    }
+
+    #[test]
+    fn test_rasterize_glyph_deterministic() {
+        let font = Font::from_bytes(Arc::new(ARIAL.to_vec()), 0).unwrap();
+        let glyph_id = font.glyph_for_char('a').unwrap();
+        let transform = Transform2F::from_rotation(0.3);
+        let bounds = font
+            .raster_bounds(
+                glyph_id,
+                32.0,
+                transform,
+                HintingOptions::None,
+                RasterizationOptions::GrayscaleAa,
+            )
+            .unwrap();
+        let transform = Transform2F::from_translation(-bounds.origin().to_f32()) * transform;
+        let rasterize = |rasterization_options, format| {
+            let mut canvas = Canvas::new(bounds.size(), format);
+            font.rasterize_glyph_deterministic(
+                &mut canvas,
+                glyph_id,
+                32.0,
+                transform,
+                rasterization_options,
+            )
+            .unwrap();
+            canvas
+        };
+
+        // The glyph fits its raster bounds, touching every edge.
+        let canvas = rasterize(RasterizationOptions::GrayscaleAa, Format::A8);
+        let width = bounds.width() as usize;
+        let rows: Vec<&[u8]> = canvas.pixels.chunks(width).collect();
+        assert!(rows[0].iter().any(|&pixel| pixel > 0));
+        assert!(rows[rows.len() - 1].iter().any(|&pixel| pixel > 0));
+        assert!(rows.iter().any(|row| row[0] > 0));
+        assert!(rows.iter().any(|row| row[width - 1] > 0));
+
+        // These pixels are part of the output's contract: they must not change between releases
+        // or platforms.
+        let checksum = canvas
+            .pixels
+            .iter()
+            .enumerate()
+            .fold(0u64, |sum, (index, &pixel)| {
+                sum.wrapping_mul(31)
+                    .wrapping_add(pixel as u64 * (index as u64 + 1))
+            });
+        assert_eq!(checksum, 16270299188595646167);
+
+        let bilevel = rasterize(RasterizationOptions::Bilevel, Format::A1);
+        let threshold = rasterize(RasterizationOptions::BilevelThreshold(128), Format::A1);
+        assert_eq!(bilevel.pixels, threshold.pixels);
+        assert_eq!(
+            bilevel.pixels,
+            canvas.to_format(Format::A1).pixels,
+            "bi-level rendering thresholds the antialiased coverage"
+        );
+
+        // The deterministic flag routes the other entry points here, even when hinting is asked
+        // for.
+        let deterministic = RasterizationOptions::GrayscaleAa.deterministic();
+        let mut flagged = Canvas::new(bounds.size(), Format::A8);
+        font.rasterize_glyph(
+            &mut flagged,
+            glyph_id,
+            32.0,
+            transform,
+            HintingOptions::Full(32.0),
+            deterministic,
+        )
+        .unwrap();
+        assert_eq!(flagged.pixels, canvas.pixels);
+
+        let mut cache = GlyphRasterCache::new(1 << 20);
+        let key = RasterKey::new(
+            0,
+            glyph_id,
+            32.0,
+            Transform2F::from_rotation(0.3),
+            HintingOptions::None,
+            deterministic,
+        );
+        let cached = cache.rasterize(&font, key).unwrap();
+        assert_eq!(cached.origin, bounds.origin());
+        assert_eq!(cached.canvas.pixels, canvas.pixels);
+
+        let atlas = font
+            .rasterize_glyphs_to_atlas(
+                &[glyph_id],
+                32.0,
+                Transform2F::from_rotation(0.3),
+                HintingOptions::None,
+                deterministic,
+                Format::A8,
+                0,
+            )
+            .unwrap();
+        assert_eq!(atlas.glyphs[0].rect.size(), bounds.size());
+        assert_eq!(atlas.canvas.pixels, canvas.pixels);
+    }
+}
@@ -0,0 +1,126 @@
+// font-kit/src/device_metrics.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Whole-pixel metrics at particular sizes, as Windows GDI uses them.
+//!
+//! Hinted TrueType fonts made for Windows often carry the results of hinting at common sizes: the
+//! `hdmx` table lists the advance of every glyph in whole pixels, and the `VDMX` table the highest
+//! and lowest pixel any glyph reaches. GDI lays text out with these values, so using them keeps
+//! line breaks, caret positions, and line heights the same as in native Windows applications.
+//!
+//! `Font::device_advance()` and `Font::device_vertical_metrics()` read the tables at a given
+//! number of pixels per em, falling back to rounding the scaled metrics at sizes the tables don't
+//! list. `Font::layout_run_at_ppem()` lays out a run of text with device advances.
+
+use byteorder::{BigEndian, ByteOrder};
+use ttf_parser::{Face, GlyphId, Tag};
+
+/// The vertical extent of a font's glyphs at a particular size, in whole pixels, for sizing lines
+/// and clipping rectangles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeviceVerticalMetrics {
+    /// The highest any glyph reaches above the baseline.
+    pub ascent: f32,
+    /// The lowest any glyph reaches, as a negative distance from the baseline like
+    /// `Metrics::descent`.
+    pub descent: f32,
+}
+
+/// Returns the advance of a glyph in whole pixels from the `hdmx` table, if the table has a
+/// record for `ppem`.
+pub(crate) fn hdmx_advance(face: &Face, glyph_id: GlyphId, ppem: u16) -> Option<u8> {
+    let hdmx = face.raw_face().table(Tag::from_bytes(b"hdmx"))?;
+    let record_count = read_i16(hdmx, 2)?.max(0) as usize;
+    let record_size = BigEndian::read_u32(hdmx.get(4..8)?) as usize;
+    if glyph_id.0 >= face.number_of_glyphs() {
+        return None;
+    }
+    (0..record_count)
+        .map(|index| 8 + index * record_size)
+        .find(|&record| hdmx.get(record).map(|&size| size as u16) == Some(ppem))
+        .and_then(|record| hdmx.get(record + 2 + glyph_id.0 as usize).copied())
+}
+
+/// Returns the highest and lowest pixel of any glyph, from the `VDMX` table, if the table has an
+/// entry for `ppem` for square pixels.
+pub(crate) fn vdmx_extents(face: &Face, ppem: u16) -> Option<(i16, i16)> {
+    let vdmx = face.raw_face().table(Tag::from_bytes(b"VDMX"))?;
+    let ratio_count = read_u16(vdmx, 4)? as usize;
+    // Find the first ratio range that contains 1:1, or that matches any ratio.
+    let ratio = (0..ratio_count).find(|&index| {
+        let range = match vdmx.get((6 + 4 * index)..(10 + 4 * index)) {
+            Some(range) => range,
+            None => return false,
+        };
+        let (x_ratio, y_start_ratio, y_end_ratio) = (range[1], range[2], range[3]);
+        (x_ratio == 0 && y_start_ratio == 0 && y_end_ratio == 0)
+            || (y_start_ratio <= x_ratio && x_ratio <= y_end_ratio)
+    })?;
+
+    let group = read_u16(vdmx, 6 + 4 * ratio_count + 2 * ratio)? as usize;
+    let record_count = read_u16(vdmx, group)? as usize;
+    let (start_size, end_size) = (*vdmx.get(group + 2)?, *vdmx.get(group + 3)?);
+    if ppem < start_size as u16 || ppem > end_size as u16 {
+        return None;
+    }
+    (0..record_count)
+        .map(|index| group + 4 + 6 * index)
+        .find(|&record| read_u16(vdmx, record) == Some(ppem))
+        .and_then(|record| Some((read_i16(vdmx, record + 2)?, read_i16(vdmx, record + 4)?)))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..(offset + 2)).map(BigEndian::read_u16)
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Option<i16> {
+    data.get(offset..(offset + 2)).map(BigEndian::read_i16)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use crate::test_fixtures::ARIAL;
+
+    #[test]
+    fn test_device_metrics() {
+        let font = Font::from_bytes_borrowed(ARIAL, 0).unwrap();
+        let units_per_em = font.metrics().units_per_em as f32;
+        let l = font.glyph_for_char('l').unwrap();
+        let linear_advance = |ppem: u16| font.advance(l).unwrap().x() * ppem as f32 / units_per_em;
+
+        // Hinting narrows the "l" at 16 pixels per em, and `hdmx` records that.
+        assert_eq!(linear_advance(16).round(), 4.0);
+        assert_eq!(font.device_advance(l, 16).unwrap(), 3.0);
+        // There's no record at this size, so the advance is rounded.
+        assert_eq!(
+            font.device_advance(l, 300).unwrap(),
+            linear_advance(300).round()
+        );
+        assert!(font.device_advance(u16::MAX as u32, 16).is_err());
+
+        // `VDMX` says hinting raises the ascent above the rounded-up scaled ascent at 12 pixels.
+        let metrics = font.device_vertical_metrics(12);
+        assert_eq!((metrics.ascent, metrics.descent), (12.0, -3.0));
+        assert!(font.metrics().ascent * 12.0 / units_per_em < 11.0);
+        let metrics = font.device_vertical_metrics(500);
+        assert_eq!(
+            metrics.ascent,
+            (font.metrics().ascent * 500.0 / units_per_em).ceil()
+        );
+
+        let glyphs = font.layout_run_at_ppem("Hill", 16);
+        assert_eq!(glyphs[3].advance.x(), 3.0);
+        assert!(glyphs
+            .iter()
+            .all(|glyph| glyph.position.x() == glyph.position.x().round()));
+    }
+}
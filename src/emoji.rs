@@ -0,0 +1,236 @@
+// font-kit/src/emoji.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Finding emoji sequences in text, and whether they should be shown as emoji or as text.
+//!
+//! This follows Unicode Technical Standard #51 closely enough for font fallback: a sequence is an
+//! emoji with its modifiers, variation selectors, keycap marks, and tags, possibly joined to more
+//! emoji with zero-width joiners. Flags are pairs of regional indicators.
+
+const VARIATION_SELECTOR_TEXT: char = '\u{fe0e}';
+const VARIATION_SELECTOR_EMOJI: char = '\u{fe0f}';
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+const COMBINING_KEYCAP: char = '\u{20e3}';
+
+/// How an emoji sequence should be shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Presentation {
+    /// Monochrome, like the surrounding text.
+    Text,
+    /// In color, as emoji usually are.
+    Emoji,
+}
+
+// Characters whose default presentation is emoji (`Emoji_Presentation=Yes` in Unicode 16), as
+// sorted, disjoint ranges.
+static EMOJI_PRESENTATION: &[(u32, u32)] = &[
+    (0x231a, 0x231b),
+    (0x23e9, 0x23ec),
+    (0x23f0, 0x23f0),
+    (0x23f3, 0x23f3),
+    (0x25fd, 0x25fe),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267f, 0x267f),
+    (0x2693, 0x2693),
+    (0x26a1, 0x26a1),
+    (0x26aa, 0x26ab),
+    (0x26bd, 0x26be),
+    (0x26c4, 0x26c5),
+    (0x26ce, 0x26ce),
+    (0x26d4, 0x26d4),
+    (0x26ea, 0x26ea),
+    (0x26f2, 0x26f3),
+    (0x26f5, 0x26f5),
+    (0x26fa, 0x26fa),
+    (0x26fd, 0x26fd),
+    (0x2705, 0x2705),
+    (0x270a, 0x270b),
+    (0x2728, 0x2728),
+    (0x274c, 0x274c),
+    (0x274e, 0x274e),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27b0, 0x27b0),
+    (0x27bf, 0x27bf),
+    (0x2b1b, 0x2b1c),
+    (0x2b50, 0x2b50),
+    (0x2b55, 0x2b55),
+    (0x1f004, 0x1f004),
+    (0x1f0cf, 0x1f0cf),
+    (0x1f18e, 0x1f18e),
+    (0x1f191, 0x1f19a),
+    (0x1f1e6, 0x1f1ff),
+    (0x1f201, 0x1f201),
+    (0x1f21a, 0x1f21a),
+    (0x1f22f, 0x1f22f),
+    (0x1f232, 0x1f236),
+    (0x1f238, 0x1f23a),
+    (0x1f250, 0x1f251),
+    (0x1f300, 0x1f320),
+    (0x1f32d, 0x1f335),
+    (0x1f337, 0x1f37c),
+    (0x1f37e, 0x1f393),
+    (0x1f3a0, 0x1f3ca),
+    (0x1f3cf, 0x1f3d3),
+    (0x1f3e0, 0x1f3f0),
+    (0x1f3f4, 0x1f3f4),
+    (0x1f3f8, 0x1f43e),
+    (0x1f440, 0x1f440),
+    (0x1f442, 0x1f4fc),
+    (0x1f4ff, 0x1f53d),
+    (0x1f54b, 0x1f54e),
+    (0x1f550, 0x1f567),
+    (0x1f57a, 0x1f57a),
+    (0x1f595, 0x1f596),
+    (0x1f5a4, 0x1f5a4),
+    (0x1f5fb, 0x1f64f),
+    (0x1f680, 0x1f6c5),
+    (0x1f6cc, 0x1f6cc),
+    (0x1f6d0, 0x1f6d2),
+    (0x1f6d5, 0x1f6d7),
+    (0x1f6dc, 0x1f6df),
+    (0x1f6eb, 0x1f6ec),
+    (0x1f6f4, 0x1f6fc),
+    (0x1f7e0, 0x1f7eb),
+    (0x1f7f0, 0x1f7f0),
+    (0x1f90c, 0x1f93a),
+    (0x1f93c, 0x1f945),
+    (0x1f947, 0x1f9ff),
+    (0x1fa70, 0x1fa7c),
+    (0x1fa80, 0x1fa89),
+    (0x1fa8f, 0x1fac6),
+    (0x1face, 0x1fadc),
+    (0x1fadf, 0x1fae9),
+    (0x1faf0, 0x1faf8),
+];
+
+/// Returns true if the character is shown as an emoji unless a variation selector says otherwise.
+pub(crate) fn has_emoji_presentation(character: char) -> bool {
+    let codepoint = character as u32;
+    let index = EMOJI_PRESENTATION.partition_point(|&(_, end)| end < codepoint);
+    EMOJI_PRESENTATION
+        .get(index)
+        .is_some_and(|&(start, _)| start <= codepoint)
+}
+
+/// Returns true for zero-width joiners, which join emoji into sequences but have no glyphs of
+/// their own in many emoji fonts.
+#[inline]
+pub(crate) fn is_joiner(character: char) -> bool {
+    character == ZERO_WIDTH_JOINER
+}
+
+#[inline]
+fn is_regional_indicator(character: char) -> bool {
+    matches!(character, '\u{1f1e6}'..='\u{1f1ff}')
+}
+
+#[inline]
+fn is_modifier(character: char) -> bool {
+    matches!(character, '\u{1f3fb}'..='\u{1f3ff}')
+}
+
+#[inline]
+fn is_tag(character: char) -> bool {
+    matches!(character, '\u{e0020}'..='\u{e007f}')
+}
+
+/// If `text` starts with an emoji sequence, returns its length in bytes and its presentation.
+///
+/// A character that is shown as text by default only starts a sequence if a variation selector,
+/// modifier, or keycap mark follows it, so plain digits and symbols such as `©` don't.
+pub(crate) fn leading_sequence(text: &str) -> Option<(usize, Presentation)> {
+    let mut chars = text.char_indices().peekable();
+    let (_, first) = chars.next()?;
+
+    if is_regional_indicator(first) {
+        let len = match chars.peek() {
+            Some(&(index, next)) if is_regional_indicator(next) => index + next.len_utf8(),
+            _ => first.len_utf8(),
+        };
+        return Some((len, Presentation::Emoji));
+    }
+
+    let mut presentation = if has_emoji_presentation(first) {
+        Some(Presentation::Emoji)
+    } else {
+        None
+    };
+    let mut len = first.len_utf8();
+    while let Some(&(_, character)) = chars.peek() {
+        match character {
+            VARIATION_SELECTOR_TEXT => presentation = Some(Presentation::Text),
+            VARIATION_SELECTOR_EMOJI | COMBINING_KEYCAP => presentation = Some(Presentation::Emoji),
+            _ if is_modifier(character) => presentation = Some(Presentation::Emoji),
+            _ if is_tag(character) => {}
+            ZERO_WIDTH_JOINER if presentation == Some(Presentation::Emoji) => {
+                // A joiner only continues the sequence if another emoji follows it.
+                chars.next();
+                match chars.peek() {
+                    Some(&(_, next)) if !next.is_whitespace() && !is_joiner(next) => {}
+                    _ => break,
+                }
+            }
+            _ => break,
+        }
+        if let Some((index, character)) = chars.next() {
+            len = index + character.len_utf8();
+        }
+    }
+    presentation.map(|presentation| (len, presentation))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{has_emoji_presentation, leading_sequence, Presentation};
+
+    #[test]
+    fn test_leading_sequence() {
+        assert!(has_emoji_presentation('😀'));
+        assert!(has_emoji_presentation('⚡'));
+        assert!(!has_emoji_presentation('©'));
+        assert!(!has_emoji_presentation('a'));
+
+        fn emoji(text: &str) -> Option<(&str, Presentation)> {
+            leading_sequence(text).map(|(len, presentation)| (&text[..len], presentation))
+        }
+        assert_eq!(emoji("😀 hi"), Some(("😀", Presentation::Emoji)));
+        assert_eq!(emoji("hi 😀"), None);
+        assert_eq!(emoji("© 2024"), None);
+        assert_eq!(
+            emoji("©\u{fe0f}!"),
+            Some(("©\u{fe0f}", Presentation::Emoji))
+        );
+        assert_eq!(
+            emoji("⚡\u{fe0e}!"),
+            Some(("⚡\u{fe0e}", Presentation::Text))
+        );
+        assert_eq!(
+            emoji("1\u{fe0f}\u{20e3}2"),
+            Some(("1\u{fe0f}\u{20e3}", Presentation::Emoji))
+        );
+        assert_eq!(emoji("👋🏽!"), Some(("👋🏽", Presentation::Emoji)));
+        assert_eq!(
+            emoji("👩\u{200d}👩\u{200d}👧 x"),
+            Some(("👩\u{200d}👩\u{200d}👧", Presentation::Emoji))
+        );
+        assert_eq!(emoji("🇯🇵🇫🇷"), Some(("🇯🇵", Presentation::Emoji)));
+        assert_eq!(
+            emoji("🏴\u{e0067}\u{e0062}\u{e0065}\u{e006e}\u{e0067}\u{e007f}."),
+            Some((
+                "🏴\u{e0067}\u{e0062}\u{e0065}\u{e006e}\u{e0067}\u{e007f}",
+                Presentation::Emoji
+            ))
+        );
+        assert_eq!(emoji("😀\u{200d}"), Some(("😀", Presentation::Emoji)));
+    }
+}
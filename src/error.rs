@@ -10,9 +10,12 @@
 
 //! Various types of errors that `font-kit` can return.
 
+use byteorder::{BigEndian, ByteOrder};
 use std::convert::From;
 use std::error::Error;
 use std::io;
+#[cfg(feature = "testing")]
+use std::path::PathBuf;
 
 macro_rules! impl_display {
     ($enum:ident, {$($variant:pat => $fmt_string:expr),+$(,)* }) => {
@@ -31,32 +34,124 @@ macro_rules! impl_display {
 }
 
 /// Reasons why a loader might fail to load a font.
+///
+/// Table tags are OpenType tags packed big-endian into a `u32`.
 #[derive(Debug)]
 pub enum FontLoadingError {
-    /// The data was of a format the loader didn't recognize.
-    UnknownFormat,
+    /// The data was of a format the loader didn't recognize. The first four bytes of the font,
+    /// which identify its format, are included; the formats that `FileType::detect()` lists are
+    /// recognized.
+    UnknownFormat {
+        /// The four bytes found, packed big-endian, with zeros past the end of short data.
+        magic: u32,
+    },
     /// Attempted to load an invalid index in a TrueType or OpenType font collection.
     ///
     /// For example, if a `.ttc` file has 2 fonts in it, and you ask for the 5th one, you'll get
     /// this error.
     NoSuchFontInCollection,
-    /// Attempted to load a malformed or corrupted font.
+    /// The data ended in the middle of a structure that started before the given offset.
+    Truncated {
+        /// The length of the data, where it ended.
+        offset: usize,
+    },
+    /// A table that the font needs is missing.
+    MissingTable {
+        /// The table.
+        table: u32,
+    },
+    /// A table is malformed; see `validation::validate()` for details.
+    MalformedTable {
+        /// The table.
+        table: u32,
+    },
+    /// Attempted to load a malformed or corrupted font, with no more specific information, as
+    /// when a platform API rejects it.
     Parse,
     /// Attempted to load a font from the filesystem, but there is no filesystem (e.g. in
     /// WebAssembly).
     NoFilesystem,
-    /// A disk or similar I/O error occurred while attempting to load the font.
+    /// A disk or similar I/O error occurred while attempting to load the font. The error is also
+    /// the `source()` of this one.
     Io(io::Error),
+    /// Attempted to load a font from a URI handle, but no resolver is registered for the URI's
+    /// scheme, which is included.
+    NoResolver(String),
+    /// Attempted to load a font from a URI handle whose URI, which is included, doesn't start
+    /// with a scheme such as `https:`.
+    InvalidUri(String),
+    /// Resolving a URI handle produced another URI handle too many times, as when resolvers refer
+    /// to each other in a cycle.
+    ResolutionCycle,
+    /// The font exceeded a limit set with `LoadOptions::max_glyphs()` or
+    /// `LoadOptions::max_table_size()`.
+    LimitExceeded,
 }
 
-impl Error for FontLoadingError {}
+impl FontLoadingError {
+    /// Returns an `UnknownFormat` error for data that starts with the given bytes.
+    pub(crate) fn unknown_format(data: &[u8]) -> FontLoadingError {
+        let mut magic = [0; 4];
+        let len = data.len().min(4);
+        magic[..len].copy_from_slice(&data[..len]);
+        FontLoadingError::UnknownFormat {
+            magic: u32::from_be_bytes(magic),
+        }
+    }
+
+    /// Converts an error from parsing the font at `font_index` in `data` with `ttf-parser`.
+    pub(crate) fn from_face_parsing_error(
+        error: ttf_parser::FaceParsingError,
+        data: &[u8],
+        font_index: u32,
+    ) -> FontLoadingError {
+        use ttf_parser::FaceParsingError;
+        let table = |name: &[u8; 4]| FontLoadingError::MissingTable {
+            table: u32::from_be_bytes(*name),
+        };
+        match error {
+            FaceParsingError::UnknownMagic => {
+                // The magic that failed may be that of a font in a collection.
+                let offset = if data.starts_with(b"ttcf") {
+                    let position = 12 + font_index as usize * 4;
+                    data.get(position..(position + 4))
+                        .map_or(0, BigEndian::read_u32) as usize
+                } else {
+                    0
+                };
+                FontLoadingError::unknown_format(data.get(offset..).unwrap_or_default())
+            }
+            FaceParsingError::FaceIndexOutOfBounds => FontLoadingError::NoSuchFontInCollection,
+            FaceParsingError::NoHeadTable => table(b"head"),
+            FaceParsingError::NoHheaTable => table(b"hhea"),
+            FaceParsingError::NoMaxpTable => table(b"maxp"),
+            FaceParsingError::MalformedFont => FontLoadingError::Parse,
+        }
+    }
+}
+
+impl Error for FontLoadingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            FontLoadingError::Io(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
 
 impl_display! { FontLoadingError, {
-        UnknownFormat => "unknown format",
+        UnknownFormat { magic } => format!("unknown format: {}", tag_name(*magic)),
         NoSuchFontInCollection => "no such font in the collection",
+        Truncated { offset } => format!("data ended unexpectedly at offset {}", offset),
+        MissingTable { table } => format!("missing table: {}", tag_name(*table)),
+        MalformedTable { table } => format!("malformed table: {}", tag_name(*table)),
         Parse => "parse error",
         NoFilesystem => "no filesystem present",
         Io(e) => format!("I/O error: {}", e),
+        NoResolver(scheme) => format!("no resolver registered for URI scheme: {}", scheme),
+        InvalidUri(uri) => format!("URI has no scheme: {}", uri),
+        ResolutionCycle => "too many nested URI handles; resolvers may form a cycle",
+        LimitExceeded => "font exceeds a resource limit",
     }
 }
 
@@ -66,6 +161,16 @@ impl From<io::Error> for FontLoadingError {
     }
 }
 
+// Formats a tag or magic number as its characters in quotes, or in hex if they aren't printable.
+fn tag_name(tag: u32) -> String {
+    let bytes = tag.to_be_bytes();
+    if bytes.iter().all(|byte| (0x20..0x7f).contains(byte)) {
+        format!("'{}'", String::from_utf8_lossy(&bytes))
+    } else {
+        format!("0x{:08x}", tag)
+    }
+}
+
 /// Reasons why a font might fail to load a glyph.
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum GlyphLoadingError {
@@ -73,6 +178,10 @@ pub enum GlyphLoadingError {
     NoSuchGlyph,
     /// A platform function returned an error.
     PlatformError,
+    /// The glyph's data in the font is malformed.
+    Parse,
+    /// The glyph's data couldn't be read, as for fonts loaded with `Font::from_reader()`.
+    Io,
 }
 
 impl Error for GlyphLoadingError {}
@@ -80,6 +189,8 @@ impl Error for GlyphLoadingError {}
 impl_display! { GlyphLoadingError, {
         NoSuchGlyph => "no such glyph",
         PlatformError => "platform error",
+        Parse => "malformed glyph data",
+        Io => "I/O error reading glyph data",
     }
 }
 
@@ -106,3 +217,139 @@ impl_display! { SelectionError, {
         CannotAccessSource => "failed to access source",
     }
 }
+
+/// Reasons why a CSS-style font property string might fail to parse.
+#[derive(Clone, PartialEq, Debug)]
+pub enum CssParseError {
+    /// The string contained a value that is not a valid style, weight, or stretch.
+    UnknownValue(String),
+    /// The string specified the same property more than once.
+    DuplicateValue(String),
+}
+
+impl Error for CssParseError {}
+
+impl_display! { CssParseError, {
+        UnknownValue(value) => format!("unknown font property value: {}", value),
+        DuplicateValue(value) => format!("duplicate font property value: {}", value),
+    }
+}
+
+/// Reasons why a font might fail to subset.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SubsetError {
+    /// A requested glyph ID was out of range.
+    NoSuchGlyph(u32),
+    /// The font's outlines are in a format that subsetting doesn't support, such as `CFF2` or
+    /// CID-keyed `CFF`.
+    UnsupportedOutlines,
+    /// A table needed to build the subset was missing or malformed.
+    Parse,
+}
+
+impl Error for SubsetError {}
+
+impl_display! { SubsetError, {
+        NoSuchGlyph(glyph_id) => format!("no such glyph: {}", glyph_id),
+        UnsupportedOutlines => "unsupported outline format",
+        Parse => "failed to parse font tables",
+    }
+}
+
+/// Reasons why an image might fail to match its golden image. See
+/// `testing::compare_to_golden()`.
+#[cfg(feature = "testing")]
+#[derive(Debug)]
+pub enum GoldenError {
+    /// There is no golden image at the given path. Set `FONT_KIT_UPDATE_GOLDEN` to create it.
+    Missing(PathBuf),
+    /// The golden image is not a PNG image that can be read.
+    Decode(String),
+    /// The golden image and the image differ in size.
+    SizeMismatch {
+        /// The size of the golden image.
+        expected: (u32, u32),
+        /// The size of the image.
+        actual: (u32, u32),
+    },
+    /// The golden image is grayscale and the image is in color, or the other way around.
+    FormatMismatch,
+    /// More pixels differ than the comparison allows. An image of the differences was written
+    /// to `diff_path`, and the image itself next to it.
+    Mismatch {
+        /// The number of pixels that differ by more than the tolerance.
+        differing_pixels: usize,
+        /// The largest difference in any channel.
+        max_difference: u8,
+        /// Where the image of the differences was written.
+        diff_path: PathBuf,
+    },
+    /// An I/O error occurred while reading or writing images.
+    Io(io::Error),
+}
+
+#[cfg(feature = "testing")]
+impl Error for GoldenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            GoldenError::Io(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "testing")]
+impl_display! { GoldenError, {
+        Missing(path) => format!("missing golden image: {}", path.display()),
+        Decode(message) => format!("failed to decode golden image: {}", message),
+        SizeMismatch { expected, actual } => format!(
+            "expected a {}x{} image, got {}x{}",
+            expected.0, expected.1, actual.0, actual.1
+        ),
+        FormatMismatch => "golden image has a different color type",
+        Mismatch { differing_pixels, max_difference, diff_path } => format!(
+            "{} pixels differ by up to {}; see {}",
+            differing_pixels, max_difference, diff_path.display()
+        ),
+        Io(e) => format!("I/O error: {}", e),
+    }
+}
+
+#[cfg(feature = "testing")]
+impl From<io::Error> for GoldenError {
+    fn from(error: io::Error) -> GoldenError {
+        GoldenError::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FontLoadingError;
+    use crate::font::Font;
+    use std::error::Error;
+    use std::io;
+
+    #[test]
+    fn test_font_loading_error_context() {
+        let error = Font::from_bytes_borrowed(b"<html></html>", 0).unwrap_err();
+        assert_eq!(error.to_string(), "unknown format: '<htm'");
+        assert_eq!(
+            FontLoadingError::unknown_format(&[0, 1]).to_string(),
+            "unknown format: 0x00010000"
+        );
+        assert_eq!(
+            FontLoadingError::MissingTable {
+                table: u32::from_be_bytes(*b"cmap")
+            }
+            .to_string(),
+            "missing table: 'cmap'"
+        );
+
+        let error = FontLoadingError::from(io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            io::Error::from(io::ErrorKind::NotFound).to_string()
+        );
+        assert!(FontLoadingError::Parse.source().is_none());
+    }
+}
@@ -0,0 +1,735 @@
+// font-kit/src/fallback.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Font fallback: choosing fonts for the characters that a font does not support.
+//!
+//! A `FallbackChain` is an ordered list of fonts to try. Given some text, it splits off the
+//! longest leading run that can be rendered with a single font, preferring the primary font, and
+//! reports which fonts cover that run. Calling it repeatedly on the rest of the text segments
+//! mixed-script text into runs that a shaper can handle one at a time.
+//!
+//! Emoji sequences, including those joined with zero-width joiners or carrying skin-tone
+//! modifiers, are never split across runs. Emoji are set in fonts with color glyphs where possible,
+//! unless U+FE0E asks for text presentation or `FallbackOptions::prefer_color_emoji` is false, in
+//! which case monochrome fonts are preferred.
+//!
+//! `Source::measure_with_fallback()` does the same starting from family names instead of loaded
+//! fonts, and measures the result.
+
+use crate::emoji::{self, Presentation};
+use crate::han::{HanSignals, HanVariant};
+use crate::loader::{FallbackFont, FallbackResult, Loader};
+
+#[cfg(feature = "source")]
+use crate::error::SelectionError;
+#[cfg(feature = "source")]
+use crate::family_name::FamilyName;
+#[cfg(feature = "source")]
+use crate::font::Font;
+#[cfg(feature = "source")]
+use crate::font_cache::{FontCache, FontKey};
+#[cfg(feature = "source")]
+use crate::properties::Properties;
+#[cfg(feature = "source")]
+use crate::source::{Source, SystemSource};
+use std::ops::Range;
+#[cfg(feature = "source")]
+use std::sync::Arc;
+
+// Families that cover large parts of Unicode on each platform, in order of preference.
+#[cfg(all(feature = "source", target_family = "windows"))]
+const SYSTEM_FALLBACK_FAMILIES: &[&str] = &[
+    "Segoe UI",
+    "Segoe UI Symbol",
+    "Segoe UI Emoji",
+    "Microsoft YaHei",
+    "Microsoft JhengHei",
+    "Yu Gothic",
+    "Malgun Gothic",
+    "Nirmala UI",
+    "Ebrima",
+    "Arial Unicode MS",
+];
+#[cfg(all(feature = "source", any(target_os = "macos", target_os = "ios")))]
+const SYSTEM_FALLBACK_FAMILIES: &[&str] = &[
+    "Helvetica",
+    "Lucida Grande",
+    "Apple Color Emoji",
+    "PingFang SC",
+    "PingFang TC",
+    "Hiragino Sans",
+    "Apple SD Gothic Neo",
+    "Kohinoor Devanagari",
+    "Apple Symbols",
+    "Arial Unicode MS",
+];
+#[cfg(all(
+    feature = "source",
+    not(any(target_family = "windows", target_os = "macos", target_os = "ios"))
+))]
+const SYSTEM_FALLBACK_FAMILIES: &[&str] = &[
+    "DejaVu Sans",
+    "Noto Sans",
+    "Noto Sans CJK SC",
+    "Noto Sans CJK TC",
+    "Noto Sans CJK JP",
+    "Noto Sans CJK KR",
+    "Noto Color Emoji",
+    "Noto Sans Symbols",
+    "Noto Sans Symbols2",
+    "Droid Sans Fallback",
+    "Roboto",
+    "Liberation Sans",
+];
+
+/// Options that affect which fonts `FallbackChain::fallbacks_for_with_options()` chooses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FallbackOptions {
+    /// Whether emoji should be set in fonts with color glyphs where possible. True by default.
+    ///
+    /// When this is false, and for emoji followed by U+FE0E VARIATION SELECTOR-15, fonts without
+    /// color glyphs are preferred instead.
+    pub prefer_color_emoji: bool,
+}
+
+impl Default for FallbackOptions {
+    #[inline]
+    fn default() -> FallbackOptions {
+        FallbackOptions {
+            prefer_color_emoji: true,
+        }
+    }
+}
+
+/// An ordered list of fonts to fall back on when a font does not support some characters.
+#[derive(Clone, Debug)]
+pub struct FallbackChain<F> {
+    fonts: Vec<F>,
+}
+
+impl<F> FallbackChain<F>
+where
+    F: Loader,
+{
+    /// Creates a chain from the given fonts, in order of preference.
+    #[inline]
+    pub fn new(fonts: Vec<F>) -> FallbackChain<F> {
+        FallbackChain { fonts }
+    }
+
+    /// Creates a chain from installed fonts that together cover most of Unicode.
+    ///
+    /// Fonts that are not installed are skipped. The `locale` argument is a language tag such as
+    /// `"en-US"` or `"zh-Hans-CN"`; CJK fonts are ordered for it as `prefer_locale()` does.
+    #[cfg(feature = "source")]
+    pub fn system(locale: &str) -> FallbackChain<F> {
+        let source = SystemSource::new();
+        let fonts = SYSTEM_FALLBACK_FAMILIES
+            .iter()
+            .filter_map(|&family_name| {
+                source
+                    .select_best_match(
+                        &[FamilyName::Title(family_name.to_owned())],
+                        &Properties::new(),
+                    )
+                    .ok()
+            })
+            .filter_map(|handle| F::from_handle(&handle).ok())
+            .collect();
+        let mut chain = FallbackChain::new(fonts);
+        chain.prefer_locale(locale);
+        chain
+    }
+
+    /// Returns the fonts in the chain, in order of preference.
+    #[inline]
+    pub fn fonts(&self) -> &[F] {
+        &self.fonts
+    }
+
+    /// Appends a font to the end of the chain.
+    #[inline]
+    pub fn push(&mut self, font: F) {
+        self.fonts.push(font)
+    }
+
+    /// Reorders the CJK fonts in the chain so that those made for the Han variant that readers of
+    /// `locale` expect come first, such as Japanese faces for `"ja"` and Traditional Chinese ones
+    /// for `"zh-TW"` or `"zh-Hant"`.
+    ///
+    /// Unified ideographs are shared between Chinese, Japanese, and Korean but drawn differently
+    /// in each, so the first CJK font that covers them should match the locale. A font's variant
+    /// is judged from the CJK code pages in its `OS/2` table, the languages of its localized
+    /// names, and regional suffixes such as "SC" or "JP" on its family name. Fonts that aren't CJK
+    /// keep their places, as does everything when the locale's language isn't written with Han
+    /// characters.
+    pub fn prefer_locale(&mut self, locale: &str) {
+        let variant = match HanVariant::from_locale(locale) {
+            Some(variant) => variant,
+            None => return,
+        };
+        let (slots, mut cjk_fonts): (Vec<usize>, Vec<(HanSignals, F)>) = self
+            .fonts
+            .iter()
+            .enumerate()
+            .map(|(index, font)| (index, HanSignals::from_font(font)))
+            .filter(|(_, signals)| signals.is_cjk())
+            .map(|(index, signals)| (index, (signals, self.fonts[index].clone())))
+            .unzip();
+        // A stable sort, so fonts that suit the variant equally well keep their order.
+        cjk_fonts.sort_by_key(|(signals, _)| signals.rank(variant));
+        for (slot, (_, font)) in slots.into_iter().zip(cjk_fonts) {
+            self.fonts[slot] = font;
+        }
+    }
+
+    /// Finds the fonts to use for the leading run of `text`.
+    ///
+    /// The run is as long as possible while still being covered by a single font. `primary` is
+    /// preferred wherever it covers the text; otherwise the first font in the chain that covers
+    /// the first character is used. The returned fonts are those that cover the whole run, best
+    /// first. If nothing covers the first character, the run spans all the characters that
+    /// nothing covers, and no fonts are returned.
+    ///
+    /// Whitespace, control characters, joiners, and variation selectors never end a run. An emoji
+    /// sequence always starts a new run, set in a color font if one covers it. This is the same as
+    /// `fallbacks_for_with_options()` with the default options.
+    #[inline]
+    pub fn fallbacks_for(&self, primary: Option<&F>, text: &str) -> FallbackResult<F> {
+        self.fallbacks_for_with_options(primary, text, &FallbackOptions::default())
+    }
+
+    /// Finds the fonts to use for the leading run of `text`, as `fallbacks_for()` does, with the
+    /// given options.
+    pub fn fallbacks_for_with_options(
+        &self,
+        primary: Option<&F>,
+        text: &str,
+        options: &FallbackOptions,
+    ) -> FallbackResult<F> {
+        let (first_index, first) = match text
+            .char_indices()
+            .find(|&(_, character)| !is_neutral(character))
+        {
+            Some(first) => first,
+            None => {
+                return FallbackResult {
+                    fonts: primary
+                        .into_iter()
+                        .chain(&self.fonts)
+                        .take(1)
+                        .map(fallback_font)
+                        .collect(),
+                    valid_len: text.len(),
+                }
+            }
+        };
+
+        if let Some((len, presentation)) = emoji::leading_sequence(&text[first_index..]) {
+            return self.emoji_fallbacks(
+                primary,
+                text,
+                first_index..(first_index + len),
+                presentation,
+                options,
+            );
+        }
+
+        let primary_covers = |character| primary.is_some_and(|font| covers(font, character));
+        if primary_covers(first) {
+            let valid_len = end_before_emoji(text, run_length(text, primary_covers));
+            return FallbackResult {
+                fonts: primary.into_iter().map(fallback_font).collect(),
+                valid_len,
+            };
+        }
+
+        let chosen = match self.fonts.iter().find(|font| covers(*font, first)) {
+            Some(chosen) => chosen,
+            None => {
+                let valid_len = end_before_emoji(
+                    text,
+                    run_length(text, |character| {
+                        !primary_covers(character)
+                            && !self.fonts.iter().any(|font| covers(font, character))
+                    }),
+                );
+                return FallbackResult {
+                    fonts: vec![],
+                    valid_len,
+                };
+            }
+        };
+
+        // Switch back to the primary font as soon as it covers the text again.
+        let valid_len = end_before_emoji(
+            text,
+            run_length(text, |character| {
+                !primary_covers(character) && covers(chosen, character)
+            }),
+        );
+        let run = &text[..valid_len];
+        let fonts = self
+            .fonts
+            .iter()
+            .filter(|font| {
+                run.chars()
+                    .all(|character| is_neutral(character) || covers(*font, character))
+            })
+            .map(fallback_font)
+            .collect();
+        FallbackResult { fonts, valid_len }
+    }
+
+    // Finds the fonts for a run of emoji sequences, the first of which spans `first_sequence`.
+    // The run continues while the sequences have the same presentation and the chosen font covers
+    // them.
+    fn emoji_fallbacks(
+        &self,
+        primary: Option<&F>,
+        text: &str,
+        first_sequence: Range<usize>,
+        presentation: Presentation,
+        options: &FallbackOptions,
+    ) -> FallbackResult<F> {
+        let wants_color = presentation == Presentation::Emoji && options.prefer_color_emoji;
+        let sequence = &text[first_sequence.clone()];
+        let mut candidates: Vec<&F> = primary
+            .into_iter()
+            .chain(&self.fonts)
+            .filter(|font| covers_sequence(*font, sequence))
+            .collect();
+        // A stable sort, so fonts keep their order of preference within each group.
+        candidates.sort_by_key(|font| has_color(*font, sequence) != wants_color);
+
+        let chosen = candidates.first().copied();
+        let mut valid_len = first_sequence.end;
+        loop {
+            let rest = &text[valid_len..];
+            let neutral_len = run_length(rest, |_| false);
+            let next = emoji::leading_sequence(&rest[neutral_len..])
+                .filter(|&(_, next_presentation)| next_presentation == presentation)
+                .map(|(len, _)| &rest[neutral_len..(neutral_len + len)])
+                .filter(|next| match chosen {
+                    Some(chosen) => {
+                        covers_sequence(chosen, next)
+                            && has_color(chosen, next) == has_color(chosen, sequence)
+                    }
+                    None => !candidates_cover(primary, &self.fonts, next),
+                });
+            match next {
+                Some(next) => valid_len += neutral_len + next.len(),
+                None => {
+                    valid_len += neutral_len;
+                    break;
+                }
+            }
+        }
+
+        let run = &text[..valid_len];
+        let fonts = candidates
+            .into_iter()
+            .filter(|font| covers_sequence(*font, run))
+            .map(fallback_font)
+            .collect();
+        FallbackResult { fonts, valid_len }
+    }
+}
+
+/// The result of `Source::measure_with_fallback()`.
+#[cfg(feature = "source")]
+#[derive(Clone, Debug)]
+pub struct FallbackMeasurement {
+    /// The total advance of the text, in points.
+    pub advance: f32,
+    /// The runs of the text, in logical order, with the font chosen for each.
+    pub runs: Vec<FallbackRun>,
+}
+
+/// A run of text set in a single font by `Source::measure_with_fallback()`.
+#[cfg(feature = "source")]
+#[derive(Clone, Debug)]
+pub struct FallbackRun {
+    /// The byte range of the run in the text.
+    pub range: Range<usize>,
+    /// The key of the font in the cache that was passed in.
+    pub font_key: FontKey,
+    /// The font that the run is set in.
+    pub font: Arc<Font>,
+    /// False if no font covers the run, in which case it is set in the primary font and will
+    /// render as missing glyphs.
+    pub covered: bool,
+    /// The advance of the run, in points.
+    pub advance: f32,
+}
+
+// The fonts that `measure_with_fallback()` has loaded so far, and the families it has yet to try.
+#[cfg(feature = "source")]
+struct FallbackCandidates<'a, S>
+where
+    S: Source + ?Sized,
+{
+    source: &'a S,
+    cache: &'a mut FontCache,
+    properties: &'a Properties,
+    family_names: Vec<FamilyName>,
+    next_family: usize,
+    fonts: Vec<(FontKey, Arc<Font>)>,
+}
+
+#[cfg(feature = "source")]
+impl<'a, S> FallbackCandidates<'a, S>
+where
+    S: Source + ?Sized,
+{
+    // Loads the best match in the next family that can be found. Families whose fonts fail to
+    // load, or that resolve to a font that was already loaded, are skipped.
+    fn load_next(&mut self) -> bool {
+        while let Some(family_name) = self.family_names.get(self.next_family) {
+            self.next_family += 1;
+            let loaded = self
+                .source
+                .select_best_match(std::slice::from_ref(family_name), self.properties)
+                .ok()
+                .and_then(|handle| self.cache.load(&handle).ok());
+            if let Some((key, font)) = loaded {
+                if self.fonts.iter().all(|&(loaded_key, _)| loaded_key != key) {
+                    self.fonts.push((key, font));
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // Returns the index of the first font that covers `character`, loading more fonts as needed.
+    fn font_for(&mut self, character: char) -> Option<usize> {
+        if let Some(index) = self
+            .fonts
+            .iter()
+            .position(|(_, font)| font.supports_char(character))
+        {
+            return Some(index);
+        }
+        while self.load_next() {
+            if self.fonts.last().unwrap().1.supports_char(character) {
+                return Some(self.fonts.len() - 1);
+            }
+        }
+        None
+    }
+}
+
+/// The implementation of `Source::measure_with_fallback()`.
+#[cfg(feature = "source")]
+pub(crate) fn measure_with_fallback<S>(
+    source: &S,
+    cache: &mut FontCache,
+    text: &str,
+    family_names: &[FamilyName],
+    properties: &Properties,
+    point_size: f32,
+) -> Result<FallbackMeasurement, SelectionError>
+where
+    S: Source + ?Sized,
+{
+    let mut candidates = FallbackCandidates {
+        source,
+        cache,
+        properties,
+        family_names: family_names
+            .iter()
+            .cloned()
+            .chain(
+                SYSTEM_FALLBACK_FAMILIES
+                    .iter()
+                    .map(|&family_name| FamilyName::Title(family_name.to_owned())),
+            )
+            .collect(),
+        next_family: 0,
+        fonts: vec![],
+    };
+
+    // The primary font is the first of the requested families that can be loaded.
+    while candidates.next_family < family_names.len() && candidates.fonts.is_empty() {
+        candidates.load_next();
+    }
+    if candidates.fonts.is_empty() {
+        return Err(SelectionError::NotFound);
+    }
+
+    // Assign each character to a font, as (font index, covered). Neutral characters stay in the
+    // current run.
+    let mut assignments: Vec<(Range<usize>, usize, bool)> = vec![];
+    for (offset, character) in text.char_indices() {
+        let end = offset + character.len_utf8();
+        let assignment = match assignments.last() {
+            Some(&(_, index, covered)) if is_neutral(character) => (index, covered),
+            _ if is_neutral(character) => (0, true),
+            _ => match candidates.font_for(character) {
+                Some(index) => (index, true),
+                None => (0, false),
+            },
+        };
+        match assignments.last_mut() {
+            Some(&mut (ref mut range, index, covered)) if (index, covered) == assignment => {
+                range.end = end
+            }
+            _ => assignments.push((offset..end, assignment.0, assignment.1)),
+        }
+    }
+
+    let runs: Vec<FallbackRun> = assignments
+        .into_iter()
+        .map(|(range, index, covered)| {
+            let (font_key, font) = candidates.fonts[index].clone();
+            let advance = font
+                .layout_run(&text[range.clone()], point_size)
+                .last()
+                .map_or(0.0, |glyph| glyph.position.x() + glyph.advance.x());
+            FallbackRun {
+                range,
+                font_key,
+                font,
+                covered,
+                advance,
+            }
+        })
+        .collect();
+    Ok(FallbackMeasurement {
+        advance: runs.iter().map(|run| run.advance).sum(),
+        runs,
+    })
+}
+
+#[inline]
+fn is_neutral(character: char) -> bool {
+    character.is_whitespace()
+        || character.is_control()
+        || is_variation_selector(character)
+        || emoji::is_joiner(character)
+}
+
+#[inline]
+fn is_variation_selector(character: char) -> bool {
+    matches!(character, '\u{fe00}'..='\u{fe0f}' | '\u{e0100}'..='\u{e01ef}')
+}
+
+#[inline]
+fn covers<F>(font: &F, character: char) -> bool
+where
+    F: Loader,
+{
+    font.supports_char(character)
+}
+
+// Returns true if the font covers every character in `text` that isn't neutral.
+fn covers_sequence<F>(font: &F, text: &str) -> bool
+where
+    F: Loader,
+{
+    text.chars()
+        .all(|character| is_neutral(character) || covers(font, character))
+}
+
+// Returns true if any of the fonts covers `text`.
+fn candidates_cover<F>(primary: Option<&F>, fonts: &[F], text: &str) -> bool
+where
+    F: Loader,
+{
+    primary
+        .into_iter()
+        .chain(fonts)
+        .any(|font| covers_sequence(font, text))
+}
+
+// Returns true if the font draws the first character of `text` with a color glyph.
+fn has_color<F>(font: &F, text: &str) -> bool
+where
+    F: Loader,
+{
+    text.chars()
+        .next()
+        .and_then(|character| font.glyph_for_char(character))
+        .is_some_and(|glyph_id| font.glyph_has_color(glyph_id))
+}
+
+// Shortens a run of `text` of length `valid_len` so that it ends before the first emoji sequence
+// after its first character, since emoji sequences start runs of their own.
+fn end_before_emoji(text: &str, valid_len: usize) -> usize {
+    text[..valid_len]
+        .char_indices()
+        .skip(1)
+        .find(|&(index, character)| {
+            !is_neutral(character) && emoji::leading_sequence(&text[index..]).is_some()
+        })
+        .map_or(valid_len, |(index, _)| index)
+}
+
+// Returns the length in bytes of the leading run of `text` whose characters satisfy `predicate`
+// or are neutral.
+fn run_length<P>(text: &str, predicate: P) -> usize
+where
+    P: Fn(char) -> bool,
+{
+    text.char_indices()
+        .find(|&(_, character)| !is_neutral(character) && !predicate(character))
+        .map_or(text.len(), |(index, _)| index)
+}
+
+fn fallback_font<F>(font: &F) -> FallbackFont<F>
+where
+    F: Loader,
+{
+    FallbackFont {
+        font: font.clone(),
+        scale: 1.0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FallbackChain, FallbackOptions};
+    use crate::font::Font;
+    use crate::loader::Loader;
+
+    static INCONSOLATA_PATH: &str = "resources/tests/inconsolata/Inconsolata-Regular.ttf";
+    static DEJAVU_SANS_MONO_PATH: &str = "resources/DejaVuSansMono.ttf";
+
+    #[test]
+    fn test_fallbacks_for_mixed_script_text() {
+        let primary = Font::from_path(INCONSOLATA_PATH, 0).unwrap();
+        let chain = FallbackChain::new(vec![Font::from_path(DEJAVU_SANS_MONO_PATH, 0).unwrap()]);
+        let text = "ab Ωψ cd";
+
+        let result = chain.fallbacks_for(Some(&primary), text);
+        assert_eq!(result.valid_len, 3);
+        assert_eq!(
+            result.fonts[0].font.postscript_name(),
+            primary.postscript_name()
+        );
+
+        let result = chain.fallbacks_for(Some(&primary), &text[3..]);
+        assert_eq!(&text[3..][..result.valid_len], "Ωψ ");
+        assert_eq!(
+            result.fonts[0].font.postscript_name().as_deref(),
+            Some("DejaVuSansMono")
+        );
+    }
+
+    #[test]
+    fn test_fallbacks_for_emoji() {
+        let primary = Font::from_path(INCONSOLATA_PATH, 0).unwrap();
+        let chain = FallbackChain::new(vec![Font::from_path(DEJAVU_SANS_MONO_PATH, 0).unwrap()]);
+
+        // Emoji start runs of their own, even in the middle of covered text.
+        let text = "ab\u{26a1}\u{2615} cd";
+        let result = chain.fallbacks_for(Some(&primary), text);
+        assert_eq!(&text[..result.valid_len], "ab");
+        let result = chain.fallbacks_for(Some(&primary), &text[2..]);
+        assert_eq!(&text[2..][..result.valid_len], "\u{26a1}\u{2615} ");
+        assert_eq!(
+            result.fonts[0].font.postscript_name().as_deref(),
+            Some("DejaVuSansMono")
+        );
+
+        // Sequences joined with zero-width joiners aren't split, even when nothing covers them.
+        let text = "\u{1f469}\u{200d}\u{1f469}\u{200d}\u{1f467}!";
+        let result = chain.fallbacks_for(Some(&primary), text);
+        assert!(result.fonts.is_empty());
+        assert_eq!(
+            &text[..result.valid_len],
+            "\u{1f469}\u{200d}\u{1f469}\u{200d}\u{1f467}"
+        );
+
+        let options = FallbackOptions {
+            prefer_color_emoji: false,
+        };
+        let text = "\u{2614}\u{fe0e}";
+        let result = chain.fallbacks_for_with_options(Some(&primary), text, &options);
+        assert_eq!(result.valid_len, text.len());
+        assert_eq!(
+            result.fonts[0].font.postscript_name().as_deref(),
+            Some("DejaVuSansMono")
+        );
+    }
+
+    #[test]
+    fn test_prefer_locale_keeps_other_fonts() {
+        let mut chain = FallbackChain::new(vec![
+            Font::from_path(INCONSOLATA_PATH, 0).unwrap(),
+            Font::from_path(DEJAVU_SANS_MONO_PATH, 0).unwrap(),
+        ]);
+        for locale in ["ja-JP", "zh-Hant", "ko", "en-US"] {
+            chain.prefer_locale(locale);
+            let names: Vec<_> = chain
+                .fonts()
+                .iter()
+                .map(|font| font.postscript_name().unwrap())
+                .collect();
+            assert_eq!(names, ["Inconsolata-Regular", "DejaVuSansMono"]);
+        }
+    }
+
+    #[cfg(feature = "source")]
+    #[test]
+    fn test_measure_with_fallback() {
+        use crate::family_name::FamilyName;
+        use crate::font_cache::FontCache;
+        use crate::handle::Handle;
+        use crate::properties::Properties;
+        use crate::source::Source;
+        use crate::sources::mem::MemSource;
+
+        let source = MemSource::from_fonts(
+            [INCONSOLATA_PATH, DEJAVU_SANS_MONO_PATH]
+                .iter()
+                .map(|path| Handle::from_path(path.into(), 0)),
+        )
+        .unwrap();
+        let mut cache = FontCache::new();
+        let family_names = [
+            FamilyName::Title("Inconsolata".to_owned()),
+            FamilyName::Title("DejaVu Sans Mono".to_owned()),
+        ];
+        let text = "ab Ωψ cd";
+
+        let measurement = source
+            .measure_with_fallback(&mut cache, text, &family_names, &Properties::new(), 16.0)
+            .unwrap();
+        let ranges: Vec<_> = measurement
+            .runs
+            .iter()
+            .map(|run| run.range.clone())
+            .collect();
+        assert_eq!(ranges, vec![0..3, 3..8, 8..10]);
+        assert!(measurement.runs.iter().all(|run| run.covered));
+        assert_eq!(measurement.runs[0].font_key, measurement.runs[2].font_key);
+        assert_eq!(
+            measurement.runs[1].font.postscript_name().as_deref(),
+            Some("DejaVuSansMono")
+        );
+        let total: f32 = measurement.runs.iter().map(|run| run.advance).sum();
+        assert_eq!(measurement.advance, total);
+        assert!(measurement.advance > 0.0);
+
+        // The fonts are cached, so measuring again loads nothing new.
+        let fonts_loaded = cache.stats().cached_fonts;
+        source
+            .measure_with_fallback(&mut cache, text, &family_names, &Properties::new(), 16.0)
+            .unwrap();
+        assert_eq!(cache.stats().cached_fonts, fonts_loaded);
+
+        let missing = [FamilyName::Title("Missing".to_owned())];
+        assert!(source
+            .measure_with_fallback(&mut cache, text, &missing, &Properties::new(), 16.0)
+            .is_err());
+    }
+}
@@ -15,6 +15,7 @@ use crate::family_handle::FamilyHandle;
 use crate::font::Font;
 use crate::handle::Handle;
 use crate::loader::Loader;
+use crate::properties::Properties;
 
 /// Defines a set of faces that vary in weight, width or slope.
 #[derive(Debug)]
@@ -29,7 +30,8 @@ impl<F> Family<F>
 where
     F: Loader,
 {
-    pub(crate) fn from_font_handles<'a, I>(font_handles: I) -> Result<Family<F>, FontLoadingError>
+    /// Loads each of the fonts referenced by the given handles.
+    pub fn from_font_handles<'a, I>(font_handles: I) -> Result<Family<F>, FontLoadingError>
     where
         I: Iterator<Item = &'a Handle>,
     {
@@ -40,8 +42,12 @@ where
         Ok(Family { fonts })
     }
 
+    /// Loads all the fonts in a family.
+    ///
+    /// To list the styles in a family without loading its fonts, use
+    /// `FamilyHandle::properties()` instead.
     #[inline]
-    pub(crate) fn from_handle(family_handle: &FamilyHandle) -> Result<Family<F>, FontLoadingError> {
+    pub fn from_handle(family_handle: &FamilyHandle) -> Result<Family<F>, FontLoadingError> {
         Family::from_font_handles(family_handle.fonts.iter())
     }
 
@@ -51,6 +57,18 @@ where
         &self.fonts
     }
 
+    /// Returns the number of fonts in this family.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.fonts.len()
+    }
+
+    /// Returns the style, weight, and stretch of each font in this family, in the same order as
+    /// `fonts()`.
+    pub fn properties(&self) -> Vec<Properties> {
+        self.fonts.iter().map(|font| font.properties()).collect()
+    }
+
     /// Returns true if and only if this family is empty.
     #[inline]
     pub fn is_empty(&self) -> bool {
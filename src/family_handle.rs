@@ -10,7 +10,9 @@
 
 //! Encapsulates the information needed to locate and open the fonts in a family.
 
+use crate::error::FontLoadingError;
 use crate::handle::Handle;
+use crate::properties::Properties;
 
 /// Encapsulates the information needed to locate and open the fonts in a family.
 #[derive(Debug)]
@@ -48,6 +50,25 @@ impl FamilyHandle {
         self.fonts.push(font)
     }
 
+    /// Returns the number of fonts in this set.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.fonts.len()
+    }
+
+    /// Returns the style, weight, and stretch of each font in this set, in the same order as
+    /// `fonts()`.
+    ///
+    /// The fonts aren't loaded: only their `head`, `OS/2`, and `name` tables are read, as
+    /// `Handle::info()` does. This makes it cheap to list the styles of a family, as font chooser
+    /// dialogs do.
+    pub fn properties(&self) -> Result<Vec<Properties>, FontLoadingError> {
+        self.fonts
+            .iter()
+            .map(|font| Ok(font.info()?.properties))
+            .collect()
+    }
+
     /// Returns true if and only if this set has no fonts in it.
     #[inline]
     pub fn is_empty(&self) -> bool {
@@ -60,3 +81,32 @@ impl FamilyHandle {
         &self.fonts
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::FamilyHandle;
+    use crate::handle::Handle;
+    use crate::properties::Style;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_family_properties() {
+        let path = PathBuf::from("resources/tests/eb-garamond/EBGaramond12.otc");
+        let family_handle = FamilyHandle::from_font_handles(
+            (0..2).map(|font_index| Handle::from_path(path.clone(), font_index)),
+        );
+        assert_eq!(family_handle.count(), 2);
+        let styles: Vec<_> = family_handle
+            .properties()
+            .unwrap()
+            .iter()
+            .map(|properties| properties.style)
+            .collect();
+        assert_eq!(styles, [Style::Normal, Style::Italic]);
+
+        let mut family_handle = FamilyHandle::new();
+        family_handle.push(Handle::from_memory(Arc::new(vec![0; 4]), 0));
+        assert!(family_handle.properties().is_err());
+    }
+}
@@ -17,6 +17,7 @@
 ///
 /// TODO(pcwalton): `system-ui`, `emoji`, `math`, `fangsong`
 #[derive(Clone, Debug, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FamilyName {
     /// A specific font family, specified by name: e.g. "Arial", "times".
     Title(String),
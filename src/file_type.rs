@@ -10,6 +10,12 @@
 
 //! The type of a font file: either a single font or a TrueType/OpenType collection.
 
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt};
+use std::io::{Cursor, Error as IOError, ErrorKind};
+
+use crate::error::FontLoadingError;
+use crate::utils;
+
 /// The type of a font file: either a single font or a TrueType/OpenType collection.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FileType {
@@ -18,3 +24,139 @@ pub enum FileType {
     /// The font file represents a collection of fonts (`.ttc`, `.otc`, etc.)
     Collection(u32),
 }
+
+/// The number of leading bytes of a file that `FileType::detect()` needs, except for WOFF2
+/// files, which need the whole file.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) const HEADER_LEN: usize = 12;
+
+const WOFF_SIGNATURE: &[u8; 4] = b"wOFF";
+pub(crate) const WOFF2_SIGNATURE: &[u8; 4] = b"wOF2";
+const COLLECTION_SIGNATURE: &[u8; 4] = b"ttcf";
+const WOFF2_HEADER_LEN: u64 = 48;
+
+impl FileType {
+    /// Determines whether the given data is a font, and if so, whether it is a single font or a
+    /// collection, by looking at its signature.
+    ///
+    /// TrueType, OpenType, and their collections, WOFF, WOFF2, and Type 1 fonts (both PFB and PFA)
+    /// are recognized. This doesn't validate the rest of the data, so the font may still fail to
+    /// load; not every loader supports every one of these formats.
+    pub fn detect(data: &[u8]) -> Result<FileType, FontLoadingError> {
+        if data.starts_with(COLLECTION_SIGNATURE) {
+            let font_count = data
+                .get(8..12)
+                .ok_or(FontLoadingError::Truncated { offset: data.len() })?;
+            let font_count = BigEndian::read_u32(font_count);
+            return Ok(FileType::Collection(font_count));
+        }
+        if data.starts_with(WOFF2_SIGNATURE) {
+            // The data is in memory, so the only possible error is running out of it.
+            return detect_woff2(data)
+                .map_err(|_| FontLoadingError::Truncated { offset: data.len() });
+        }
+        if utils::SFNT_VERSIONS
+            .iter()
+            .any(|version| data.starts_with(version))
+            || data.starts_with(WOFF_SIGNATURE)
+            || data.starts_with(&[0x80, 0x01])
+            || data.starts_with(b"%!PS-AdobeFont")
+            || data.starts_with(b"%!FontType1")
+        {
+            return Ok(FileType::Single);
+        }
+        Err(FontLoadingError::unknown_format(data))
+    }
+}
+
+// WOFF2 files can contain collections, in which case the number of fonts is stored after the
+// table directory, whose entries have variable lengths.
+fn detect_woff2(data: &[u8]) -> Result<FileType, IOError> {
+    let mut cursor = Cursor::new(data);
+    cursor.set_position(4);
+    let flavor = cursor.read_u32::<BigEndian>()?;
+    if flavor.to_be_bytes() != *COLLECTION_SIGNATURE {
+        return Ok(FileType::Single);
+    }
+
+    cursor.set_position(12);
+    let table_count = cursor.read_u16::<BigEndian>()?;
+    cursor.set_position(WOFF2_HEADER_LEN);
+    for _ in 0..table_count {
+        let flags = cursor.read_u8()?;
+        let is_glyf_or_loca = match flags & 0x3f {
+            0x3f => matches!(
+                &cursor.read_u32::<BigEndian>()?.to_be_bytes(),
+                b"glyf" | b"loca"
+            ),
+            known_tag => known_tag == 10 || known_tag == 11,
+        };
+        read_uint_base_128(&mut cursor)?;
+        let transform_version = flags >> 6;
+        let transformed = if is_glyf_or_loca {
+            transform_version != 3
+        } else {
+            transform_version != 0
+        };
+        if transformed {
+            read_uint_base_128(&mut cursor)?;
+        }
+    }
+
+    // The collection header: a version, followed by the number of fonts.
+    cursor.read_u32::<BigEndian>()?;
+    let font_count = match cursor.read_u8()? {
+        253 => cursor.read_u16::<BigEndian>()?,
+        254 => cursor.read_u8()? as u16 + 506,
+        255 => cursor.read_u8()? as u16 + 253,
+        count => count as u16,
+    };
+    Ok(FileType::Collection(font_count as u32))
+}
+
+fn read_uint_base_128(cursor: &mut Cursor<&[u8]>) -> Result<u32, IOError> {
+    let mut value = 0u32;
+    for _ in 0..5 {
+        let byte = cursor.read_u8()?;
+        if value & 0xfe00_0000 != 0 {
+            return Err(ErrorKind::InvalidData.into());
+        }
+        value = (value << 7) | (byte & 0x7f) as u32;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(ErrorKind::InvalidData.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::FileType;
+    use crate::error::FontLoadingError;
+    use crate::test_fixtures::{EB_GARAMOND, EB_GARAMOND_OTC};
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(FileType::detect(EB_GARAMOND).unwrap(), FileType::Single);
+        assert_eq!(
+            FileType::detect(EB_GARAMOND_OTC).unwrap(),
+            FileType::Collection(2)
+        );
+        assert_eq!(
+            FileType::detect(b"wOFF\0\x01\0\0").unwrap(),
+            FileType::Single
+        );
+        assert_eq!(
+            FileType::detect(&[0x80, 0x01, 0, 0]).unwrap(),
+            FileType::Single
+        );
+        assert!(matches!(
+            FileType::detect(b"<html></html>"),
+            Err(FontLoadingError::UnknownFormat { magic: 0x3c68746d })
+        ));
+        assert!(matches!(
+            FileType::detect(b"ttcf"),
+            Err(FontLoadingError::Truncated { offset: 4 })
+        ));
+    }
+}
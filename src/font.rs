@@ -10,166 +10,1535 @@
 
 //! A font face loaded into memory.
 //!
-//! The Font type in this crate represents the default loader.
+//! The Font type in this crate is the pure-Rust loader, which is available on every platform and
+//! is also exported as `loaders::ttf_parser::Font`. The platform's default loader is
+//! `loaders::default::Font`.
 
 //pub use crate::loaders::default::Font;
 
-use crate::error::GlyphLoadingError;
+use crate::carets;
+use crate::coverage::UnicodeRangeSet;
+use crate::device_metrics::{self, DeviceVerticalMetrics};
+use crate::error::{GlyphLoadingError, SubsetError};
+use crate::fallback::FallbackChain;
+use crate::glyph_contours::{self, GlyphContour};
+#[cfg(feature = "source")]
+use crate::han::HanVariant;
 use crate::handle::Handle;
 use crate::hinting::HintingOptions;
+use crate::incremental::{self, LazyOutlines, ReadAt};
+#[cfg(feature = "last-resort")]
+use crate::last_resort;
+use crate::layout::{self, GlyphClass, PositionedGlyph, TextDirection, WrappedText};
+use crate::layout_features::LayoutFeatures;
+use crate::load_options::{self, LoadOptions};
+use crate::math::{self, MathConstants, MathGlyphConstruction};
+#[cfg(any(unix, target_family = "windows"))]
+use crate::mmap::Mmap;
+use crate::os2::{CodePageRanges, EmbeddingPermissions, Panose, UnicodeRanges};
 use crate::outline::OutlineSink;
+use crate::pdf::{self, PdfFontDescriptor};
+#[cfg(feature = "rayon")]
+use crate::raster_cache::{GlyphRequest, RasterizedGlyph};
+use crate::script::{self, UnicodeScript};
+#[cfg(feature = "shaping")]
+use crate::shaping::{self, ShapeOptions};
+use crate::subset::{self, Subset};
+use crate::svg::{self, SvgPathSink};
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+use crate::task::Task;
+use crate::units::{PpemRounding, Px};
+use crate::validation::{self, ValidationIssue};
 use crate::{
-    canvas::{Canvas, Format, RasterizationOptions},
+    canvas::{Canvas, CanvasMut, RasterizationOptions},
+    deterministic,
     error::FontLoadingError,
     file_type::FileType,
+    glyph_cache::GlyphCache,
     loader::{FallbackResult, Loader},
-    metrics::Metrics,
+    metrics::{DecorationRects, GlyphOrientation, Metrics, MetricsOverride},
     properties::{Properties, Stretch, Style, Weight},
+    substitution,
+    synthesis::SynthesisOptions,
+    utils,
 };
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{file_type, shared_data};
+#[cfg(feature = "source")]
+use lazy_static::lazy_static;
+use pathfinder_geometry::line_segment::LineSegment2F;
 use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::transform2d::Transform2F;
-use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use pathfinder_geometry::vector::Vector2F;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "source")]
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
 use std::fs::File;
-use std::{path::Path, sync::Arc};
-use ttf_parser::{Face, GlyphId};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{Read, Seek, SeekFrom};
+use std::marker::PhantomData;
+use std::ops::Deref;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::sync::Arc;
+#[cfg(feature = "source")]
+use std::sync::Mutex;
+use ttf_parser::{name_id, Face, GlyphId, PlatformId, RasterImageFormat};
 
-static ARIAL: &'static [u8] = include_bytes!("../resources/DejaVuSansMono.ttf");
+// The font that `MissingFontPolicy::Bundled` returns.
+#[cfg(feature = "source")]
+pub(crate) static DEJAVU_SANS_MONO: &[u8] = include_bytes!("../resources/DejaVuSansMono.ttf");
 
-#[derive(Debug, Clone)]
+/// A Core Text font, the native font type of this loader on macOS and iOS.
+///
+/// See `Loader::native_font()`.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub type NativeFont = crate::loaders::core_text::NativeFont;
+
+/// A DirectWrite font and font face, the native font type of this loader on Windows.
+///
+/// See `Loader::native_font()`.
+#[cfg(target_family = "windows")]
+pub type NativeFont = crate::loaders::directwrite::NativeFont;
+
+/// The native font type of this loader on platforms whose text APIs it doesn't convert to: the
+/// font's data and its index, from which FreeType faces or other libraries' fonts can be created.
+///
+/// See `Loader::native_font()`.
+#[cfg(not(any(target_os = "macos", target_os = "ios", target_family = "windows")))]
+#[derive(Clone, Debug)]
+pub struct NativeFont {
+    // Shared with the font that it came from, so that memory-mapped fonts aren't copied.
+    font_data: FontData,
+    /// The index of the font in a collection, or 0 for a single font.
+    pub font_index: u32,
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "ios", target_family = "windows")))]
+impl NativeFont {
+    /// Creates a native font from font data, which may be a collection, and the index of a font
+    /// in it.
+    #[inline]
+    pub fn new(font_data: Arc<Vec<u8>>, font_index: u32) -> NativeFont {
+        NativeFont {
+            font_data: FontData::Memory(font_data),
+            font_index,
+        }
+    }
+
+    /// Returns the font data, which may be a collection.
+    #[inline]
+    pub fn font_data(&self) -> &[u8] {
+        self.font_data.as_slice()
+    }
+}
+
+#[cfg(feature = "source")]
+lazy_static! {
+    // Loading the system fallback fonts is expensive, so they are loaded once per Han variant,
+    // which is the only part of the locale that `FallbackChain::system()` looks at. Keying by it
+    // rather than by the locale string means that spellings such as "ja-JP" and "ja_JP.UTF-8"
+    // share a chain and the map holds at most one chain per variant.
+    static ref SYSTEM_FALLBACK_CHAINS: Mutex<HashMap<Option<HanVariant>, Arc<FallbackChain<Font>>>> =
+        Mutex::new(HashMap::new());
+}
+
+#[cfg(feature = "source")]
+fn system_fallback_chain(locale: &str) -> Arc<FallbackChain<Font>> {
+    let key = HanVariant::from_locale(locale);
+    if let Some(chain) = SYSTEM_FALLBACK_CHAINS.lock().unwrap().get(&key) {
+        return Arc::clone(chain);
+    }
+    // The chain is built without the lock held, so that threads wanting other chains aren't
+    // blocked while fonts load. If two threads build the same chain, the first one stored wins.
+    let chain = Arc::new(FallbackChain::system(locale));
+    Arc::clone(
+        SYSTEM_FALLBACK_CHAINS
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert(chain),
+    )
+}
+
+/// A font loaded with the pure-Rust `ttf-parser` backend.
+///
+/// Fonts are `Send` and `Sync`. Cloning one is cheap: the clone shares the font data, the parsed
+/// tables, and the caches of glyph lookups with the original, so a multi-threaded pipeline can
+/// give each thread its own clone instead of a lock. See `clone_shared()`.
+#[derive(Debug)]
 pub struct Font {
-    font_data: Arc<Vec<u8>>,
+    shared: Arc<SharedFace>,
+    font_index: u32,
+    metrics_override: MetricsOverride,
+}
+
+// The parts of a font that its clones share, and that fonts loaded from the same file share
+// through `shared_data`.
+#[derive(Debug)]
+pub(crate) struct SharedFace {
+    font_data: FontData,
     face: Face<'static>,
+    cache: GlyphCache,
+    // The outlines of fonts from `from_reader()`, which aren't in `font_data`.
+    lazy_outlines: Option<Arc<LazyOutlines>>,
+}
+
+impl SharedFace {
+    fn parse(font_data: FontData, font_index: u32) -> Result<SharedFace, FontLoadingError> {
+        // SAFETY: `font_data` is stored alongside the face, so it outlives it.
+        let data = unsafe { font_data.as_static_slice() };
+        let face = Face::parse(data, font_index)
+            .map_err(|error| FontLoadingError::from_face_parsing_error(error, data, font_index))?;
+        Ok(SharedFace {
+            font_data,
+            face,
+            cache: GlyphCache::new(),
+            lazy_outlines: None,
+        })
+    }
+}
+
+impl Clone for SharedFace {
+    // Copies are only made to change the variation settings, which invalidates the cache.
+    fn clone(&self) -> SharedFace {
+        SharedFace {
+            font_data: self.font_data.clone(),
+            face: self.face.clone(),
+            cache: GlyphCache::new(),
+            lazy_outlines: self.lazy_outlines.clone(),
+        }
+    }
+}
+
+// The storage that a font's face borrows from.
+#[derive(Debug, Clone)]
+pub(crate) enum FontData {
+    Memory(Arc<Vec<u8>>),
+    #[cfg(any(unix, target_family = "windows"))]
+    Mapped(Arc<Mmap>),
+    // Data borrowed by a `FontRef`, which ensures that the font doesn't outlive it.
+    Borrowed(&'static [u8]),
+}
+
+impl FontData {
+    // SAFETY: The returned slice lives as long as the heap buffer or mapping, which is never
+    // mutated or moved, and is freed only when the last clone of this `FontData` is dropped. The
+    // caller must keep this `FontData` alive for as long as it uses the slice.
+    unsafe fn as_static_slice(&self) -> &'static [u8] {
+        &*(self.as_slice() as *const [u8])
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        match *self {
+            FontData::Memory(ref bytes) => bytes,
+            #[cfg(any(unix, target_family = "windows"))]
+            FontData::Mapped(ref mmap) => mmap,
+            FontData::Borrowed(data) => data,
+        }
+    }
+}
+
+impl Clone for Font {
+    fn clone(&self) -> Font {
+        match self.shared.font_data {
+            // Clones of a borrowed font can outlive the borrow, so they get their own copy of the
+            // data.
+            FontData::Borrowed(data) => {
+                let mut font = Font::from_font_data(
+                    FontData::Memory(Arc::new(data.to_vec())),
+                    self.font_index,
+                )
+                .expect("font data that parsed once should parse again");
+                font.metrics_override = self.metrics_override;
+                font
+            }
+            _ => self.clone_borrowing(),
+        }
+    }
+}
+
+/// A font that borrows its data rather than owning it, created by `Font::from_bytes_borrowed()`.
+///
+/// This dereferences to a `Font`, so all of the usual methods are available. Cloning the `Font`
+/// that it dereferences to, for example with `to_font()`, copies the data so that the clone can
+/// outlive the borrow; cloning the `FontRef` itself does not.
+#[derive(Debug)]
+pub struct FontRef<'a> {
+    font: Font,
+    phantom: PhantomData<&'a [u8]>,
+}
+
+impl<'a> FontRef<'a> {
+    /// Returns a font that owns a copy of the data, and so can outlive this one.
+    #[inline]
+    pub fn to_font(&self) -> Font {
+        self.font.clone()
+    }
+}
+
+impl<'a> Clone for FontRef<'a> {
+    #[inline]
+    fn clone(&self) -> FontRef<'a> {
+        FontRef {
+            font: self.font.clone_borrowing(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a> Deref for FontRef<'a> {
+    type Target = Font;
+
+    #[inline]
+    fn deref(&self) -> &Font {
+        &self.font
+    }
 }
 
 impl Font {
+    /// Loads the font pointed to by a handle.
     pub fn from_handle(handle: &Handle) -> Result<Self, FontLoadingError> {
         <Self as Loader>::from_handle(handle)
     }
 
-    pub fn analyze_file(file: &mut File) -> Result<FileType, FontLoadingError> {
-        Ok(FileType::Collection(1))
+    /// Loads a font by memory-mapping the file at the given path rather than reading it into
+    /// memory.
+    ///
+    /// Only the parts of the file that are used occupy memory, which greatly reduces memory use
+    /// when many large fonts are loaded. `copy_font_data()` copies the contents of mapped fonts;
+    /// `with_font_data()` borrows them.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the font or any of its clones is alive.
+    /// Installed system fonts, which are replaced rather than modified in place, are safe to map.
+    #[cfg(any(unix, target_family = "windows"))]
+    pub unsafe fn from_path_mmap<P>(path: P, font_index: u32) -> Result<Font, FontLoadingError>
+    where
+        P: AsRef<Path>,
+    {
+        <Font as Loader>::from_path_mmap(path, font_index)
     }
-}
 
-impl Loader for Font {
-    type NativeFont = u8;
+    /// Loads a font through a `ReadAt` implementation, keeping its outlines out of memory until
+    /// they are needed.
+    ///
+    /// This suits very large fonts, such as CJK fonts, of which only a few glyphs are rendered.
+    /// Every table but `glyf` or `CFF ` is read up front; outlines are then read as glyphs are
+    /// outlined or measured. The loaded font is standalone, so its font index is 0 whichever font
+    /// of a collection it came from. See the `incremental` module.
+    pub fn from_reader<R>(reader: R, font_index: u32) -> Result<Font, FontLoadingError>
+    where
+        R: ReadAt + 'static,
+    {
+        let (font_data, lazy_outlines) = incremental::load(Box::new(reader), font_index)?;
+        let mut font = Font::from_font_data(FontData::Memory(Arc::new(font_data)), 0)?;
+        Arc::get_mut(&mut font.shared).unwrap().lazy_outlines = lazy_outlines.map(Arc::new);
+        Ok(font)
+    }
 
-    fn from_bytes(_font_data: Arc<Vec<u8>>, font_index: u32) -> Result<Self, FontLoadingError> {
-        let face = Face::parse(ARIAL, font_index).map_err(|_| FontLoadingError::UnknownFormat)?;
-        Ok(Font {
-            font_data: Arc::new(ARIAL.to_owned()),
-            face,
-        })
+    /// Returns default load options, which can be customized and then used to load a font. See
+    /// the `load_options` module.
+    #[inline]
+    pub fn builder() -> LoadOptions {
+        LoadOptions::new()
+    }
+
+    /// Loads a font from raw font data with the given options.
+    pub fn from_bytes_with_options(
+        font_data: Arc<Vec<u8>>,
+        options: &LoadOptions,
+    ) -> Result<Font, FontLoadingError> {
+        load_options::check_limits(&font_data, options.font_index, options)?;
+        <Font as Loader>::from_bytes(font_data, options.font_index)?.with_load_options(options)
     }
 
+    /// Loads a font from a file with the given options.
+    ///
+    /// Files are always read into memory; use `from_path_with_options()` to map them.
     #[cfg(not(target_arch = "wasm32"))]
-    fn from_file(_file: &mut File, _font_index: u32) -> Result<Self, FontLoadingError> {
-        /*let mut reader = BufReader::new(file);
-        let mut font_data =
-            Vec::with_capacity(file.metadata().map(|md| md.len() as usize).unwrap_or(0));
-        reader.read_to_end(&mut font_data);*/
-
-        let face = Face::parse(ARIAL, 0).map_err(|_| FontLoadingError::UnknownFormat)?;
-        Ok(Font {
-            font_data: Arc::new(ARIAL.to_owned()),
-            face,
+    pub fn from_file_with_options(
+        file: &mut File,
+        options: &LoadOptions,
+    ) -> Result<Font, FontLoadingError> {
+        load_options::check_file_limits(file, options.font_index, options)?;
+        <Font as Loader>::from_file(file, options.font_index)?.with_load_options(options)
+    }
+
+    /// Loads a font from the path to a file with the given options.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_path_with_options<P>(
+        path: P,
+        options: &LoadOptions,
+    ) -> Result<Font, FontLoadingError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        load_options::check_file_limits(&mut file, options.font_index, options)?;
+        #[cfg(any(unix, target_family = "windows"))]
+        {
+            if options.mmap {
+                // SAFETY: Whoever set `LoadOptions::mmap()` promised not to modify the file.
+                let mmap = unsafe { Mmap::map(&file)? };
+                return Font::from_font_data(FontData::Mapped(Arc::new(mmap)), options.font_index)?
+                    .with_load_options(options);
+            }
+        }
+        <Font as Loader>::from_path(path, options.font_index)?.with_load_options(options)
+    }
+
+    /// Loads the font pointed to by a handle with the given options.
+    ///
+    /// The font index comes from the handle, not from the options.
+    pub fn from_handle_with_options(
+        handle: &Handle,
+        options: &LoadOptions,
+    ) -> Result<Font, FontLoadingError> {
+        let font = match *handle {
+            #[cfg(not(target_arch = "wasm32"))]
+            Handle::Path {
+                ref path,
+                font_index,
+            } => {
+                let options = LoadOptions {
+                    font_index,
+                    ..options.clone()
+                };
+                return Font::from_path_with_options(path, &options);
+            }
+            Handle::Memory {
+                ref bytes,
+                font_index,
+            } => {
+                load_options::check_limits(bytes, font_index, options)?;
+                Font::from_bytes(Arc::clone(bytes), font_index)?
+            }
+            Handle::Uri { .. } | Handle::Named { .. } => {
+                return Font::from_handle_with_options(&handle.resolve()?, options)
+            }
+            #[cfg(target_arch = "wasm32")]
+            Handle::Path { .. } => return Err(FontLoadingError::NoFilesystem),
+        };
+        font.with_load_options(options)
+    }
+
+    // Applies the options that take effect after the font is parsed. The limits are checked
+    // before, by each caller.
+    fn with_load_options(mut self, options: &LoadOptions) -> Result<Font, FontLoadingError> {
+        let data = self.shared.font_data.as_slice();
+        if options.strict {
+            load_options::check_required_tables(&self.shared.face)?;
+            load_options::check_structure(data, self.font_index)?;
+        }
+        for &(axis, value) in &options.variations {
+            self.set_variation(axis, value);
+        }
+        self.metrics_override = options.metrics_override;
+        Ok(self)
+    }
+
+    /// Parses a font from data that the caller owns, without copying it.
+    ///
+    /// This is for data that lives in a static or an arena allocation anyway. Use `from_bytes()`
+    /// for data that should be shared with the font.
+    pub fn from_bytes_borrowed(
+        font_data: &[u8],
+        font_index: u32,
+    ) -> Result<FontRef<'_>, FontLoadingError> {
+        // SAFETY: The font can only be reached through the returned `FontRef`, which can't
+        // outlive `font_data`, and clones of it copy the data.
+        let font_data: &'static [u8] = unsafe { &*(font_data as *const [u8]) };
+        Ok(FontRef {
+            font: Font::from_font_data(FontData::Borrowed(font_data), font_index)?,
+            phantom: PhantomData,
         })
     }
 
-    unsafe fn from_native_font(native_font: Self::NativeFont) -> Self {
-        let face = Face::parse(ARIAL, 0)
-            .map_err(|_| FontLoadingError::UnknownFormat)
-            .unwrap();
+    // Returns storage for this font's data that can outlive the font: the font's own storage, or a
+    // copy where the font borrows its data or reads its outlines on demand.
+    #[cfg(any(
+        feature = "loader-freetype",
+        not(any(target_os = "macos", target_os = "ios", target_family = "windows"))
+    ))]
+    pub(crate) fn owned_font_data(&self) -> Option<FontData> {
+        if let Some(ref lazy_outlines) = self.shared.lazy_outlines {
+            return lazy_outlines
+                .read_all()
+                .map(|font_data| FontData::Memory(Arc::new(font_data)));
+        }
+        match self.shared.font_data {
+            FontData::Borrowed(data) => Some(FontData::Memory(Arc::new(data.to_vec()))),
+            ref font_data => Some(font_data.clone()),
+        }
+    }
+
+    fn from_font_data(font_data: FontData, font_index: u32) -> Result<Font, FontLoadingError> {
+        Ok(Font::from_shared_face(
+            Arc::new(SharedFace::parse(font_data, font_index)?),
+            font_index,
+        ))
+    }
+
+    fn from_shared_face(shared: Arc<SharedFace>, font_index: u32) -> Font {
         Font {
-            font_data: Arc::new(ARIAL.to_owned()),
-            face,
+            shared,
+            font_index,
+            metrics_override: MetricsOverride::default(),
+        }
+    }
+
+    // Loads a font from a file, sharing the face with other fonts loaded from the same file.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_shared_file(
+        file: &mut File,
+        path: Option<&Path>,
+        font_index: u32,
+    ) -> Result<Font, FontLoadingError> {
+        let shared = shared_data::read_shared_face(file, path, font_index, |font_data| {
+            SharedFace::parse(FontData::Memory(font_data), font_index)
+        })?;
+        Ok(Font::from_shared_face(shared, font_index))
+    }
+
+    // Returns true if this font and `other` share their parsed tables.
+    #[cfg(test)]
+    pub(crate) fn shares_face_with(&self, other: &Font) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+
+    // Outlines a glyph, reading its outline first if the font is from `from_reader()`.
+    fn outline_glyph(
+        &self,
+        glyph_id: GlyphId,
+        builder: &mut dyn ttf_parser::OutlineBuilder,
+    ) -> Result<Option<ttf_parser::Rect>, GlyphLoadingError> {
+        match self.shared.lazy_outlines {
+            Some(ref lazy_outlines) => lazy_outlines.outline(&self.shared.face, glyph_id, builder),
+            None => Ok(self.shared.face.outline_glyph(glyph_id, builder)),
+        }
+    }
+
+    // Returns the bounds of a glyph's outline, reading it first if the font is from
+    // `from_reader()`.
+    fn glyph_bounding_box(&self, glyph_id: GlyphId) -> Option<ttf_parser::Rect> {
+        match self.shared.lazy_outlines {
+            Some(ref lazy_outlines) => lazy_outlines
+                .bounding_box(&self.shared.face, glyph_id)
+                .ok()
+                .flatten(),
+            None => self.shared.face.glyph_bounding_box(glyph_id),
         }
     }
 
-    fn analyze_bytes(_font_data: Arc<Vec<u8>>) -> Result<FileType, FontLoadingError> {
-        Ok(FileType::Collection(1))
+    // Clones the font, sharing its data even if the data is borrowed.
+    fn clone_borrowing(&self) -> Font {
+        Font {
+            shared: Arc::clone(&self.shared),
+            font_index: self.font_index,
+            metrics_override: self.metrics_override,
+        }
+    }
+
+    /// Returns a font that shares this font's data, parsed tables, and glyph caches, for handing
+    /// to another thread.
+    ///
+    /// This is what `clone()` does; it exists to make the sharing explicit in multi-threaded
+    /// shaping pipelines. Sharing costs one atomic reference count increment, and lookups that one
+    /// thread caches are then available to all of them. Changing the variation settings of a
+    /// clone, for example with `set_variation()`, gives it its own tables and caches, leaving the
+    /// others unchanged. The one exception is fonts from `from_bytes_borrowed()`, whose clones
+    /// must be able to outlive the borrow and so get their own copy of the data.
+    #[inline]
+    pub fn clone_shared(&self) -> Font {
+        self.clone()
+    }
+
+    /// Loads the font at the given path on a background thread.
+    ///
+    /// The returned task can be awaited from any async runtime. If no background thread can be
+    /// started, the task completes immediately with `FontLoadingError::Io`.
+    #[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+    pub fn from_path_async<P>(path: P, font_index: u32) -> Task<Result<Font, FontLoadingError>>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_owned();
+        Task::spawn(move || <Font as Loader>::from_path(path, font_index))
+            .unwrap_or_else(|error| Task::ready(Err(FontLoadingError::Io(error))))
+    }
+
+    /// Loads the font pointed to by a handle on a background thread.
+    ///
+    /// The returned task can be awaited from any async runtime. If no background thread can be
+    /// started, the task completes immediately with `FontLoadingError::Io`.
+    #[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+    pub fn from_handle_async(handle: Handle) -> Task<Result<Font, FontLoadingError>> {
+        Task::spawn(move || <Font as Loader>::from_handle(&handle))
+            .unwrap_or_else(|error| Task::ready(Err(FontLoadingError::Io(error))))
     }
 
+    /// Determines whether a file represents a supported font, and, if so, what type of font it is.
     #[cfg(not(target_arch = "wasm32"))]
-    fn analyze_file(_file: &mut File) -> Result<FileType, FontLoadingError> {
-        Ok(FileType::Collection(1))
+    pub fn analyze_file(file: &mut File) -> Result<FileType, FontLoadingError> {
+        <Font as Loader>::analyze_file(file)
+    }
+
+    /// Returns the contours of a glyph exactly as they are stored in the font, in design units.
+    ///
+    /// Unlike `outline()`, this keeps every point from the font along with whether it is on or
+    /// off the curve, so TrueType outlines can be reproduced without loss.
+    pub fn glyph_contours(&self, glyph_id: u32) -> Result<Vec<GlyphContour>, GlyphLoadingError> {
+        glyph_contours::glyph_contours(&self.shared.face, glyph_id as u16)
+    }
+
+    /// Returns the size, in pixels per em, of the bitmap strike (`sbix`, `CBDT`, or `EBDT`) that
+    /// would be used to draw a glyph at the given size, or `None` if the glyph has no bitmaps.
+    ///
+    /// Strikes only exist at whole pixel sizes, so `rounding` determines which one a fractional
+    /// size selects. `PpemRounding::Exact` behaves like `PpemRounding::Up`, which picks a strike
+    /// that can be scaled down rather than up.
+    pub fn bitmap_strike_size(
+        &self,
+        glyph_id: u32,
+        px_per_em: Px,
+        rounding: PpemRounding,
+    ) -> Option<u16> {
+        let rounding = match rounding {
+            PpemRounding::Exact => PpemRounding::Up,
+            rounding => rounding,
+        };
+        let px_per_em = rounding.round(px_per_em).0.clamp(1.0, u16::MAX as f32) as u16;
+        self.shared
+            .face
+            .glyph_raster_image(GlyphId(glyph_id as u16), px_per_em)
+            .map(|image| image.pixels_per_em)
+    }
+
+    /// Returns the glyph substitution features in the font's `GSUB` table, such as ligatures,
+    /// small caps, and stylistic sets.
+    ///
+    /// The result is empty if the font has no `GSUB` table.
+    pub fn gsub_features(&self) -> LayoutFeatures {
+        LayoutFeatures::from_table(self.shared.face.tables().gsub)
+    }
+
+    /// Returns the scripts, such as Latin, Cyrillic, or Hebrew, that the font supports.
+    ///
+    /// This combines the font's `cmap` coverage with the scripts named in its `GSUB` and `GPOS`
+    /// tables; see the `script` module for the rules. Scripts are returned in the order of
+    /// `UnicodeScript`.
+    pub fn scripts(&self) -> Vec<UnicodeScript> {
+        let tables = self.shared.face.tables();
+        let layout_scripts: Vec<u32> = [tables.gsub, tables.gpos]
+            .iter()
+            .flatten()
+            .flat_map(|table| table.scripts.into_iter().map(|script| script.tag.0))
+            .collect();
+        script::supported_scripts(&self.coverage(), &layout_scripts)
+    }
+
+    /// Returns the glyph positioning features in the font's `GPOS` table, such as kerning and mark
+    /// positioning.
+    ///
+    /// The result is empty if the font has no `GPOS` table.
+    pub fn gpos_features(&self) -> LayoutFeatures {
+        LayoutFeatures::from_table(self.shared.face.tables().gpos)
+    }
+
+    /// Returns whether a glyph is a base glyph, a ligature, a combining mark, or a component,
+    /// from the glyph classes in the font's `GDEF` table.
+    ///
+    /// Layout code uses this to give marks no advance of their own and to keep them in the
+    /// cluster of the preceding base. Glyphs the font doesn't classify, including every glyph of
+    /// fonts without `GDEF` glyph classes, are `GlyphClass::Unclassified`.
+    pub fn glyph_class(&self, glyph_id: u32) -> GlyphClass {
+        layout::glyph_class(&self.shared.face, GlyphId(glyph_id as u16))
+    }
+
+    /// Returns where to draw the combining mark `mark_glyph` so that it sits on `base_glyph`, as
+    /// an offset in font units from the origin of the base glyph to the origin of the mark.
+    ///
+    /// The anchors come from the `GPOS` mark-to-base, mark-to-ligature, and mark-to-mark
+    /// attachment lookups, so `base_glyph` may itself be a mark when stacking diacritics. Marks on
+    /// ligatures attach to the last component. Returns `None` if no lookup attaches the mark to
+    /// the base, in which case renderers usually leave the mark where its own metrics put it.
+    pub fn mark_anchor(&self, base_glyph: u32, mark_glyph: u32) -> Option<Vector2F> {
+        layout::mark_anchor(
+            &self.shared.face,
+            GlyphId(base_glyph as u16),
+            GlyphId(mark_glyph as u16),
+        )
+    }
+
+    /// Returns the constants in the font's `MATH` table that position scripts, fractions,
+    /// radicals, and limits in formulas, or `None` if the font isn't a math font. See the `math`
+    /// module.
+    pub fn math_constants(&self) -> Option<MathConstants> {
+        math::constants(&self.shared.face)
+    }
+
+    /// Returns the italics correction of a glyph from the font's `MATH` table: the extra space to
+    /// leave after the glyph when a superscript or an upright glyph follows it, in font units.
+    pub fn math_italics_correction(&self, glyph_id: u32) -> Option<f32> {
+        math::italics_correction(&self.shared.face, GlyphId(glyph_id as u16))
+    }
+
+    /// Returns where accents are centered horizontally over a glyph, as a distance from its
+    /// origin in font units, from the font's `MATH` table. Accents over glyphs without one are
+    /// centered on the glyph's advance.
+    pub fn math_top_accent_attachment(&self, glyph_id: u32) -> Option<f32> {
+        math::top_accent_attachment(&self.shared.face, GlyphId(glyph_id as u16))
+    }
+
+    /// Returns true if the font's `MATH` table marks the glyph as an extended shape, such as a
+    /// large integral, whose scripts are placed as if it were a tall base.
+    pub fn is_math_extended_shape(&self, glyph_id: u32) -> bool {
+        math::is_extended_shape(&self.shared.face, GlyphId(glyph_id as u16))
+    }
+
+    /// Returns the larger variants of a glyph, and the parts to assemble it from, for stretching
+    /// it vertically, as for parentheses and radicals, or horizontally, as for arrows and braces
+    /// over formulas. Returns `None` if the font's `MATH` table has no construction for the glyph
+    /// in that direction.
+    pub fn math_glyph_construction(
+        &self,
+        glyph_id: u32,
+        orientation: GlyphOrientation,
+    ) -> Option<MathGlyphConstruction> {
+        math::glyph_construction(&self.shared.face, GlyphId(glyph_id as u16), orientation)
+    }
+
+    /// Applies the `GSUB` substitutions of the given features, such as `liga` or `smcp`, to a
+    /// run of glyphs and returns the resulting glyphs.
+    ///
+    /// Only single and ligature substitutions are applied, using the font's default script and
+    /// language. This is not a replacement for a shaper, but it suffices for programming ligatures
+    /// and small caps. Features are OpenType tags packed big-endian into a `u32`.
+    pub fn substitute(&self, glyph_ids: &[u32], features: &[u32]) -> Vec<u32> {
+        substitution::substitute(&self.shared.face, glyph_ids, features)
+    }
+
+    /// Returns the advances of many glyphs at once, in font units, like calling `advance()` for
+    /// each. Glyphs that don't exist have zero advances.
+    ///
+    /// This skips the per-call error handling of `advance()`, which matters when measuring long
+    /// runs of text.
+    pub fn advances(&self, glyph_ids: &[u32]) -> Vec<Vector2F> {
+        glyph_ids
+            .iter()
+            .map(|&glyph_id| {
+                let advance = self.cached_advance(glyph_id);
+                Vector2F::new(advance.unwrap_or(0) as f32, 0.0)
+            })
+            .collect()
+    }
+
+    fn cached_advance(&self, glyph_id: u32) -> Option<u16> {
+        let face = &self.shared.face;
+        let glyph_count = face.number_of_glyphs() as u32;
+        self.shared.cache.advance(glyph_id, glyph_count, || {
+            face.glyph_hor_advance(GlyphId(glyph_id as u16))
+        })
+    }
+
+    /// Rasterizes many glyphs at once on rayon's thread pool, such as to fill a glyph atlas at
+    /// startup, each into a new canvas that covers its raster bounds.
+    ///
+    /// Each glyph is measured with `raster_bounds()` and rasterized with `rasterize_glyph()` on its
+    /// own thread, with the request's hinting and rasterization options, so the images are the
+    /// same as those calls make. This loader always rasterizes with the fixed-point rasterizer of
+    /// the `deterministic` module, which never hints, so the `Deterministic` flag of the requests'
+    /// rasterization options makes no difference. The images are returned in the order of
+    /// `requests`. If any glyph fails to load, the first such error is
+    /// returned.
+    #[cfg(feature = "rayon")]
+    pub fn rasterize_glyphs_parallel(
+        &self,
+        requests: &[GlyphRequest],
+    ) -> Result<Vec<RasterizedGlyph>, GlyphLoadingError> {
+        requests
+            .par_iter()
+            .map(|request| {
+                let bounds = self.raster_bounds(
+                    request.glyph_id,
+                    request.point_size,
+                    request.transform,
+                    request.hinting_options,
+                    request.rasterization_options,
+                )?;
+                let mut canvas = Canvas::new(bounds.size(), request.format);
+                self.rasterize_glyph(
+                    &mut canvas,
+                    request.glyph_id,
+                    request.point_size,
+                    Transform2F::from_translation(-bounds.origin().to_f32()) * request.transform,
+                    request.hinting_options,
+                    request.rasterization_options,
+                )?;
+                Ok(RasterizedGlyph {
+                    canvas,
+                    origin: bounds.origin(),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the glyph for each character of `text`, like calling `glyph_for_char()` for each,
+    /// with `None` for characters that the font doesn't support.
+    pub fn glyphs_for_chars(&self, text: &str) -> Vec<Option<u32>> {
+        text.chars()
+            .map(|character| self.glyph_for_char(character))
+            .collect()
+    }
+
+    /// Looks up the glyph and advance of each character of `text` ahead of time, so that layout
+    /// finds them in the font's cache instead of in the `cmap` and `hmtx` tables.
+    ///
+    /// Clones of the font share the cache, so warming it once is enough for all of them.
+    pub fn prewarm_cache(&self, text: &str) {
+        for character in text.chars() {
+            if let Some(glyph_id) = self.glyph_for_char(character) {
+                self.cached_advance(glyph_id);
+            }
+        }
+    }
+
+    /// Looks up the glyphs and advances of the printable ASCII characters ahead of time, like
+    /// `prewarm_cache()`.
+    pub fn prewarm_ascii_cache(&self) {
+        for character in ' '..='~' {
+            if let Some(glyph_id) = self.glyph_for_char(character) {
+                self.cached_advance(glyph_id);
+            }
+        }
+    }
+
+    /// Sets the value of a variation axis, such as `wght` or `wdth`, of a variable font.
+    ///
+    /// The axis is an OpenType tag packed big-endian into a `u32`. Values are clamped to the
+    /// axis's range. This only updates the font's normalized coordinates, without parsing
+    /// anything, so it is cheap enough to call every frame while animating an axis. If the font
+    /// shares its tables with clones, it first gets its own copy of them, leaving the clones
+    /// unchanged.
+    ///
+    /// Returns false, leaving the font unchanged, if the font has no such axis.
+    pub fn set_variation(&mut self, axis: u32, value: f32) -> bool {
+        let tag = ttf_parser::Tag(axis);
+        if !self
+            .shared
+            .face
+            .variation_axes()
+            .into_iter()
+            .any(|variation_axis| variation_axis.tag == tag)
+        {
+            return false;
+        }
+        let shared = Arc::make_mut(&mut self.shared);
+        shared.cache = GlyphCache::new();
+        shared.face.set_variation(tag, value).is_some()
+    }
+
+    /// Returns a copy of this font with a variation axis set to the given value. See
+    /// `set_variation()`.
+    ///
+    /// The copy shares the font data and the parsed tables with this font, unless this font
+    /// borrows its data, in which case the copy owns a copy of the data like any clone would.
+    /// Axes that the font doesn't have are ignored.
+    pub fn with_variation(&self, axis: u32, value: f32) -> Font {
+        let mut font = self.clone();
+        font.set_variation(axis, value);
+        font
+    }
+
+    /// Returns the overrides applied to the metrics that `metrics()` returns.
+    #[inline]
+    pub fn metrics_override(&self) -> MetricsOverride {
+        self.metrics_override
+    }
+
+    /// Sets overrides for the font's ascent, descent, and line gap, which `metrics()` and
+    /// anything built on it then report instead of the font's own values.
+    ///
+    /// This works like the CSS `ascent-override`, `descent-override`, and `line-gap-override`
+    /// descriptors, and is useful for giving fallback fonts the same line height as the primary
+    /// font.
+    #[inline]
+    pub fn set_metrics_override(&mut self, metrics_override: MetricsOverride) {
+        self.metrics_override = metrics_override
+    }
+
+    /// Returns this font with the given metrics overrides. See `set_metrics_override()`.
+    #[inline]
+    pub fn with_metrics_override(mut self, metrics_override: MetricsOverride) -> Font {
+        self.set_metrics_override(metrics_override);
+        self
+    }
+
+    /// Returns the rectangles to fill to underline and strike out a run of text set in this font,
+    /// given the run's width and the font size. See `Metrics::decoration_rects()`.
+    #[inline]
+    pub fn decoration_rects(&self, point_size: f32, width: f32) -> DecorationRects {
+        self.metrics().decoration_rects(point_size, width)
+    }
+
+    /// Returns the angle of this font's slant, in degrees counterclockwise from vertical, from the
+    /// `italicAngle` field of the `post` table. Fonts that slant to the right, as most italics
+    /// do, have negative angles, and upright fonts have an angle of 0.
+    ///
+    /// Fonts without a `post` table get the angle of the caret slope in the `hhea` table instead.
+    pub fn italic_angle(&self) -> f32 {
+        if let Some(angle) = self.shared.face.italic_angle() {
+            return angle;
+        }
+        let hhea = self
+            .shared
+            .face
+            .raw_face()
+            .table(ttf_parser::Tag::from_bytes(b"hhea"))
+            .unwrap_or_default();
+        let field = |offset: usize| {
+            hhea.get(offset..offset + 2)
+                .map(|bytes| i16::from_be_bytes([bytes[0], bytes[1]]) as f32)
+        };
+        match (field(18), field(20)) {
+            (Some(rise), Some(run)) if rise != 0.0 => -run.atan2(rise).to_degrees(),
+            _ => 0.0,
+        }
+    }
+
+    /// Returns the slant of the caret and of the cursor in text set in this font with the given
+    /// synthesis, as the horizontal shift to the right per unit of height.
+    ///
+    /// This combines the font's own italic angle with the synthesized oblique, so that carets
+    /// lean with the glyphs whether the font is a real italic or a slanted upright font.
+    pub fn caret_slant(&self, synthesis: &SynthesisOptions) -> f32 {
+        (-self.italic_angle()).to_radians().tan() + synthesis.oblique
+    }
+
+    /// Returns where the caret goes between the components of a ligature glyph such as "ffi",
+    /// from the font's `GDEF` ligature caret list.
+    ///
+    /// Positions are distances from the origin of the glyph along the direction of the text, in
+    /// the same units as `point_size`, in increasing order; a ligature of three characters has two.
+    /// The result is empty if the glyph isn't a ligature or the font doesn't list its carets, in
+    /// which case editors usually divide the advance evenly between the characters.
+    pub fn ligature_carets(&self, glyph_id: u32, point_size: f32) -> Vec<f32> {
+        let scale = point_size / self.shared.face.units_per_em() as f32;
+        carets::ligature_carets(&self.shared.face, glyph_id as u16)
+            .into_iter()
+            .map(|caret| caret * scale)
+            .collect()
+    }
+
+    /// Returns the synthesis needed to approximate the `requested` properties with this font,
+    /// like `SynthesisOptions::for_properties()`.
+    ///
+    /// Unlike that function, this also looks at the italic angle, so that fonts that are slanted
+    /// but don't say so in their `OS/2` table, as some obliques don't, aren't slanted again.
+    pub fn synthesis_for(&self, requested: &Properties) -> SynthesisOptions {
+        let mut synthesis = SynthesisOptions::for_properties(requested, &self.properties());
+        if self.italic_angle() != 0.0 {
+            synthesis.oblique = 0.0;
+        }
+        synthesis
+    }
+
+    /// Lays out a single line of text left to right at the given size, mapping characters to
+    /// glyphs with the `cmap` table and applying the font's kerning.
+    ///
+    /// Positions and advances are in the same units as `point_size`, with the pen starting at the
+    /// origin and the y axis pointing up. No substitutions or bidirectional reordering are done;
+    /// see the `layout` module for details.
+    pub fn layout_run(&self, text: &str, point_size: f32) -> Vec<PositionedGlyph> {
+        let scale = point_size / self.shared.face.units_per_em() as f32;
+        let mut glyphs = layout::layout_run(&self.shared.face, text);
+        layout::scale_glyphs(&mut glyphs, scale);
+        glyphs
+    }
+
+    /// Lays out a single line of mixed left-to-right and right-to-left text at the given size,
+    /// returning its glyphs in visual order from left to right.
+    ///
+    /// The text is split into runs of a single direction with `layout::bidi_runs()` and the runs
+    /// are reordered with `layout::reorder_runs()`. Each run is then laid out like `layout_run()`,
+    /// with brackets mirrored in right-to-left runs. This suits Hebrew labels; Arabic needs a
+    /// shaper to join its letters.
+    pub fn layout_bidi(
+        &self,
+        text: &str,
+        point_size: f32,
+        base_direction: Option<TextDirection>,
+    ) -> Vec<PositionedGlyph> {
+        let scale = point_size / self.shared.face.units_per_em() as f32;
+        let mut glyphs = layout::layout_bidi(&self.shared.face, text, base_direction);
+        layout::scale_glyphs(&mut glyphs, scale);
+        glyphs
+    }
+
+    /// Lays out a single line of text like `layout_run()`, at `ppem` pixels per em, with every
+    /// advance a whole number of pixels as Windows GDI lays text out.
+    ///
+    /// Advances come from `device_advance()`, and kerning and offsets are rounded to whole pixels,
+    /// so every glyph starts on a pixel boundary. Positions and advances are in pixels.
+    pub fn layout_run_at_ppem(&self, text: &str, ppem: u16) -> Vec<PositionedGlyph> {
+        let face = &self.shared.face;
+        let scale = ppem as f32 / face.units_per_em() as f32;
+        let mut glyphs = layout::layout_run(face, text);
+        for glyph in &mut glyphs {
+            let glyph_id = GlyphId(glyph.glyph_id as u16);
+            let kerning = glyph.advance.x() - face.glyph_hor_advance(glyph_id).unwrap_or(0) as f32;
+            let advance = self.device_advance(glyph.glyph_id, ppem).unwrap_or(0.0);
+            glyph.advance = Vector2F::new(advance + (kerning * scale).round(), 0.0);
+            glyph.offset = (glyph.offset * scale).round();
+        }
+        layout::update_positions(&mut glyphs);
+        glyphs
+    }
+
+    /// Returns the advance of a glyph at `ppem` pixels per em in whole pixels, as Windows GDI
+    /// uses it.
+    ///
+    /// This is the advance from the font's `hdmx` table, which records the advances of hinted
+    /// glyphs, if it has one for this size. Otherwise it is the advance scaled to the size and
+    /// rounded to the nearest pixel. See the `device_metrics` module.
+    pub fn device_advance(&self, glyph_id: u32, ppem: u16) -> Result<f32, GlyphLoadingError> {
+        let face = &self.shared.face;
+        if let Some(advance) = device_metrics::hdmx_advance(face, GlyphId(glyph_id as u16), ppem) {
+            return Ok(advance as f32);
+        }
+        let scale = ppem as f32 / face.units_per_em() as f32;
+        Ok((self.advance(glyph_id)?.x() * scale).round())
+    }
+
+    /// Returns how far the font's glyphs reach above and below the baseline at `ppem` pixels per
+    /// em, in whole pixels.
+    ///
+    /// The values come from the font's `VDMX` table if it has an entry for this size, which tells
+    /// how far hinting moves the glyphs, and otherwise from the ascent and descent scaled to the
+    /// size and rounded outwards. See the `device_metrics` module.
+    pub fn device_vertical_metrics(&self, ppem: u16) -> DeviceVerticalMetrics {
+        if let Some((y_max, y_min)) = device_metrics::vdmx_extents(&self.shared.face, ppem) {
+            return DeviceVerticalMetrics {
+                ascent: y_max as f32,
+                descent: y_min as f32,
+            };
+        }
+        let metrics = self.metrics();
+        let scale = ppem as f32 / metrics.units_per_em as f32;
+        DeviceVerticalMetrics {
+            ascent: (metrics.ascent * scale).ceil(),
+            descent: (metrics.descent * scale).floor(),
+        }
+    }
+
+    /// Returns the logical bounds of a line of text laid out with `layout_run()`.
+    ///
+    /// The rectangle starts at the origin and spans the total advance horizontally, and the font's
+    /// descent to its ascent vertically (y axis up), honoring any metrics override. It doesn't
+    /// depend on the glyphs' ink, so labels of different text have the same height.
+    pub fn measure_text(&self, text: &str, point_size: f32) -> RectF {
+        let scale = point_size / self.shared.face.units_per_em() as f32;
+        let glyphs = layout::layout_run(&self.shared.face, text);
+        layout::run_bounds(&glyphs, &self.metrics()) * scale
+    }
+
+    /// Returns an SVG document that draws a glyph at the given size. See the `svg` module.
+    pub fn glyph_to_svg(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+    ) -> Result<String, GlyphLoadingError> {
+        let metrics = self.metrics();
+        let scale = point_size / metrics.units_per_em as f32;
+        let mut sink = SvgPathSink::new();
+        self.outline(glyph_id, HintingOptions::None, &mut sink)?;
+        let size = Vector2F::new(
+            self.advance(glyph_id)?.x(),
+            metrics.ascent - metrics.descent,
+        ) * scale;
+        let content = if sink.path_data().is_empty() {
+            String::new()
+        } else {
+            svg::path_element(
+                sink.path_data(),
+                Vector2F::new(0.0, metrics.ascent * scale),
+                scale,
+            )
+        };
+        Ok(svg::document(size, &content))
+    }
+
+    /// Returns an SVG document that draws a line of text laid out with `layout_run()`, with one
+    /// `<path>` element per visible glyph. See the `svg` module.
+    pub fn text_to_svg(&self, text: &str, point_size: f32) -> String {
+        let scale = point_size / self.shared.face.units_per_em() as f32;
+        let bounds = self.measure_text(text, point_size);
+        let mut content = String::new();
+        for glyph in self.layout_run(text, point_size) {
+            let mut sink = SvgPathSink::new();
+            if self
+                .outline(glyph.glyph_id, HintingOptions::None, &mut sink)
+                .is_err()
+                || sink.path_data().is_empty()
+            {
+                continue;
+            }
+            let origin = glyph.position + glyph.offset;
+            content.push_str(&svg::path_element(
+                sink.path_data(),
+                Vector2F::new(origin.x(), bounds.max_y() - origin.y()),
+                scale,
+            ));
+        }
+        svg::document(bounds.size(), &content)
+    }
+
+    /// Returns the entries of a PDF font descriptor for this font. See the `pdf` module.
+    pub fn pdf_font_descriptor(&self) -> PdfFontDescriptor {
+        pdf::font_descriptor(self, &self.shared.face)
+    }
+
+    /// Returns the widths of the glyphs from `first` to `last`, inclusive, in PDF glyph space, for
+    /// the `W` or `Widths` array of a PDF font. See the `pdf` module.
+    pub fn pdf_widths(&self, first: u32, last: u32) -> Vec<f32> {
+        pdf::widths(self, first, last)
+    }
+
+    /// Returns a ToUnicode CMap mapping the given glyph IDs to the characters that this font's
+    /// character map maps to them. Glyphs that no character maps to are left out.
+    ///
+    /// For a subset, call this on the subset font, whose glyph IDs are the ones in the PDF.
+    pub fn pdf_to_unicode_cmap(&self, glyph_ids: &[u32]) -> String {
+        pdf::to_unicode_cmap(pdf::glyph_text(&self.shared.face, glyph_ids))
+    }
+
+    /// Returns a standalone font containing only the given glyphs, `.notdef`, and the glyphs that
+    /// composite glyphs among them are built from, for embedding in PDFs and web fonts. See the
+    /// `subset` module.
+    pub fn subset(&self, glyph_ids: &[u32]) -> Result<Subset, SubsetError> {
+        subset::subset(&self.shared.face, glyph_ids)
+    }
+
+    /// Returns a standalone font containing the glyphs for the characters in `text`, as
+    /// `subset()` does. Characters that the font has no glyph for are left out.
+    pub fn subset_text(&self, text: &str) -> Result<Subset, SubsetError> {
+        let glyph_ids: Vec<u32> = text
+            .chars()
+            .filter_map(|character| self.glyph_for_char(character))
+            .collect();
+        self.subset(&glyph_ids)
+    }
+
+    /// Returns how this font may be embedded in documents, from the `fsType` field of its `OS/2`
+    /// table. Check this before embedding the font in a PDF or ebook. See the `os2` module.
+    pub fn embedding_permissions(&self) -> EmbeddingPermissions {
+        EmbeddingPermissions::from_os2(self.os2_table())
+    }
+
+    /// Returns the Unicode ranges that this font declares support for in its `OS/2` table. See
+    /// the `os2` module.
+    pub fn unicode_ranges(&self) -> UnicodeRanges {
+        UnicodeRanges::from_os2(self.os2_table())
+    }
+
+    /// Returns the code pages that this font declares support for in its `OS/2` table. See the
+    /// `os2` module.
+    pub fn codepage_ranges(&self) -> CodePageRanges {
+        CodePageRanges::from_os2(self.os2_table())
+    }
+
+    /// Returns the PANOSE classification from this font's `OS/2` table, or `None` if the font has
+    /// no `OS/2` table. See the `os2` module.
+    pub fn panose(&self) -> Option<Panose> {
+        self.os2_table().and_then(Panose::from_os2)
     }
 
+    fn os2_table(&self) -> Option<&[u8]> {
+        self.shared
+            .face
+            .raw_face()
+            .table(ttf_parser::Tag::from_bytes(b"OS/2"))
+    }
+
+    /// Returns a hash that identifies this font by its contents, for deduplicating fonts found
+    /// through several sources and for cache keys that survive the font file moving.
+    ///
+    /// The hash covers the PostScript name, `checkSumAdjustment` from the `head` table, and the
+    /// tag, checksum, and length of every table. The table data isn't read, so this is cheap even
+    /// for large fonts, and the same font has the same fingerprint whether it is loaded from a
+    /// file, from memory, or from a collection. It is stable across runs and platforms. Variation
+    /// settings don't affect it.
+    pub fn fingerprint(&self) -> u64 {
+        let raw_face = self.shared.face.raw_face();
+        let head = raw_face.table(ttf_parser::Tag::from_bytes(b"head"));
+        let mut hash = utils::FNV_OFFSET_BASIS;
+        hash = utils::fnv1a(
+            hash,
+            head.and_then(|head| head.get(8..12)).unwrap_or_default(),
+        );
+        hash = utils::fnv1a(hash, self.postscript_name().unwrap_or_default().as_bytes());
+        for record in raw_face.table_records {
+            hash = utils::fnv1a(hash, &record.tag.to_bytes());
+            hash = utils::fnv1a(hash, &record.check_sum.to_be_bytes());
+            hash = utils::fnv1a(hash, &record.length.to_be_bytes());
+        }
+        hash
+    }
+
+    /// Returns true if this font and `other` are the same font, as far as their fingerprints
+    /// tell. See `fingerprint()`.
     #[inline]
+    pub fn same_font_as(&self, other: &Font) -> bool {
+        self.fingerprint() == other.fingerprint()
+    }
+
+    /// Checks this font for structural problems, such as bad checksums, glyph data out of
+    /// bounds, or character mappings to missing glyphs. See the `validation` module.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        // The data parsed as a font when it was loaded, so the table directory is readable.
+        validation::validate(self.shared.font_data.as_slice(), self.font_index).unwrap_or_default()
+    }
+
+    /// Lays out text like `layout_run()`, breaking it into lines no wider than `max_width`.
+    ///
+    /// Lines break after spaces and hyphens, around ideographs, and at newlines; see the `layout`
+    /// module for the exact rules. `max_width` and all lengths in the result are in the same units
+    /// as `point_size`. This suits tooltips and simple text boxes; it doesn't justify or
+    /// hyphenate.
+    pub fn wrap_text(&self, text: &str, max_width: f32, point_size: f32) -> WrappedText {
+        let scale = point_size / self.shared.face.units_per_em() as f32;
+        let mut wrapped =
+            layout::wrap_text(&self.shared.face, text, max_width / scale, &self.metrics());
+        for line in &mut wrapped.lines {
+            layout::scale_glyphs(&mut line.glyphs, scale);
+            line.width *= scale;
+            line.baseline *= scale;
+        }
+        wrapped.height *= scale;
+        wrapped
+    }
+
+    /// Shapes a run of text, returning its glyphs positioned in font units.
+    ///
+    /// The text is shaped with rustybuzz, which handles complex scripts; see the `shaping` module
+    /// for details.
+    #[cfg(feature = "shaping")]
+    pub fn shape(&self, text: &str, options: &ShapeOptions) -> Vec<PositionedGlyph> {
+        shaping::shape(&self.shared.face, text, options)
+    }
+
+    /// Returns the set of characters that the font maps to glyphs.
+    ///
+    /// This is built from the Unicode subtables of the `cmap` table, so it agrees with
+    /// `glyph_for_char()` and `supports_char()`. It is computed on each call, so callers that
+    /// query it repeatedly should keep the result.
+    pub fn coverage(&self) -> UnicodeRangeSet {
+        coverage_from_cmap(self.shared.face.tables().cmap)
+    }
+
+    /// Returns the first string in the `name` table with the given name ID, preferring Unicode
+    /// records.
+    fn name(&self, name_id: u16) -> Option<String> {
+        name_from_table(self.shared.face.names(), name_id)
+    }
+}
+
+// Forwards the outlines that ttf-parser produces to an `OutlineSink`.
+struct OutlineSinkAdapter<'a, S>(&'a mut S);
+
+impl<'a, S> ttf_parser::OutlineBuilder for OutlineSinkAdapter<'a, S>
+where
+    S: OutlineSink,
+{
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to(Vector2F::new(x, y));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to(Vector2F::new(x, y));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.0
+            .quadratic_curve_to(Vector2F::new(x1, y1), Vector2F::new(x, y));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.0.cubic_curve_to(
+            LineSegment2F::new(Vector2F::new(x1, y1), Vector2F::new(x2, y2)),
+            Vector2F::new(x, y),
+        );
+    }
+
+    fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+/// Returns the name with the given ID, preferring Unicode names to Macintosh Roman ones.
+pub(crate) fn name_from_table(names: ttf_parser::name::Names, name_id: u16) -> Option<String> {
+    names
+        .into_iter()
+        .filter(|name| name.name_id == name_id && name.is_unicode())
+        .find_map(|name| name.to_string())
+        .or_else(|| {
+            names
+                .into_iter()
+                .filter(|name| {
+                    name.name_id == name_id
+                        && name.platform_id == PlatformId::Macintosh
+                        && name.encoding_id == 0
+                })
+                .map(|name| name.name.iter().map(|&byte| byte as char).collect())
+                .next()
+        })
+}
+
+/// Returns the set of characters that the Unicode subtables of a `cmap` table map to glyphs.
+pub(crate) fn coverage_from_cmap(cmap: Option<ttf_parser::cmap::Table>) -> UnicodeRangeSet {
+    let mut codepoints = vec![];
+    if let Some(cmap) = cmap {
+        for subtable in cmap
+            .subtables
+            .into_iter()
+            .filter(|subtable| subtable.is_unicode())
+        {
+            subtable.codepoints(|codepoint| {
+                if char::from_u32(codepoint).is_some() && subtable.glyph_index(codepoint).is_some()
+                {
+                    codepoints.push(codepoint);
+                }
+            });
+        }
+    }
+    codepoints.into_iter().collect()
+}
+
+/// Returns the style, weight, and stretch described by an `OS/2` table, or the defaults if there
+/// is none.
+///
+/// For variable fonts, the stretch is the default value of the `wdth` axis in `fvar` instead of
+/// `usWidthClass`, which describes the default instance too but only coarsely.
+pub(crate) fn properties_from_os2(
+    os2: Option<ttf_parser::os2::Table>,
+    fvar: Option<ttf_parser::fvar::Table>,
+) -> Properties {
+    use ttf_parser::Style as S;
+    use ttf_parser::Weight as W;
+    let (style, weight) = os2.map_or((S::Normal, W::Normal), |os2| (os2.style(), os2.weight()));
+    let wdth = fvar.and_then(|fvar| {
+        fvar.axes
+            .into_iter()
+            .find(|axis| axis.tag == ttf_parser::Tag::from_bytes(b"wdth"))
+    });
+    let stretch = match (wdth, os2) {
+        (Some(axis), _) => Stretch(axis.def_value / 100.0),
+        (None, Some(os2)) => Stretch(Stretch::MAPPING[os2.width().to_number() as usize - 1]),
+        (None, None) => Stretch::NORMAL,
+    };
+    Properties {
+        style: match style {
+            S::Italic => Style::Italic,
+            S::Oblique => Style::Oblique,
+            S::Normal => Style::Normal,
+        },
+        weight: match weight {
+            W::Thin => Weight::THIN,
+            W::ExtraLight => Weight::EXTRA_LIGHT,
+            W::Light => Weight::LIGHT,
+            W::Normal => Weight::NORMAL,
+            W::Medium => Weight::MEDIUM,
+            W::SemiBold => Weight::SEMIBOLD,
+            W::Bold => Weight::BOLD,
+            W::ExtraBold => Weight::BOLD,
+            W::Black => Weight::BLACK,
+            W::Other(val) => Weight(val as f32),
+        },
+        stretch,
+    }
+}
+
+impl Loader for Font {
+    type NativeFont = NativeFont;
+
+    fn from_bytes(font_data: Arc<Vec<u8>>, font_index: u32) -> Result<Self, FontLoadingError> {
+        Font::from_font_data(FontData::Memory(font_data), font_index)
+    }
+
+    /// Loads a font from a file.
+    ///
+    /// Faces loaded from the same file share a single copy of its contents, so loading every face
+    /// of a large collection costs the size of the file only once, and loading a face that is
+    /// already loaded reuses its parsed tables. See the `shared_data` module.
     #[cfg(not(target_arch = "wasm32"))]
-    fn analyze_path<P>(_path: P) -> Result<FileType, FontLoadingError>
+    fn from_file(file: &mut File, font_index: u32) -> Result<Self, FontLoadingError> {
+        Font::from_shared_file(file, None, font_index)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_path<P>(path: P, font_index: u32) -> Result<Self, FontLoadingError>
     where
         P: AsRef<Path>,
     {
-        Ok(FileType::Collection(1))
+        let path = path.as_ref();
+        Font::from_shared_file(&mut File::open(path)?, Some(path), font_index)
     }
 
-    fn native_font(&self) -> Self::NativeFont {
-        0u8
+    /// Loads a font by memory-mapping the file at the given path rather than reading it.
+    #[cfg(any(unix, target_family = "windows"))]
+    unsafe fn from_path_mmap<P>(path: P, font_index: u32) -> Result<Self, FontLoadingError>
+    where
+        P: AsRef<Path>,
+    {
+        let mmap = Mmap::map(&File::open(path)?)?;
+        Font::from_font_data(FontData::Mapped(Arc::new(mmap)), font_index)
+    }
+
+    /// Creates a font from the data of a native font.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the native font's data can't be read, as for fonts that Core Text synthesizes,
+    /// or isn't a font this loader can parse.
+    unsafe fn from_native_font(native_font: NativeFont) -> Self {
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        let (font_data, font_index) = {
+            let font = crate::loaders::core_text::Font::from_native_font(native_font);
+            (font.copy_font_data(), 0)
+        };
+        #[cfg(target_family = "windows")]
+        let (font_data, font_index) = {
+            let font_index = native_font.dwrite_font_face.get_index();
+            let font = crate::loaders::directwrite::Font::from_native_font(native_font);
+            (font.copy_font_data(), font_index)
+        };
+        #[cfg(not(any(target_os = "macos", target_os = "ios", target_family = "windows")))]
+        let (font_data, font_index) = (Some(native_font.font_data), native_font.font_index);
+        #[cfg(any(target_os = "macos", target_os = "ios", target_family = "windows"))]
+        let font_data = font_data.map(FontData::Memory);
+
+        let font_data = font_data.expect("the native font's data couldn't be read");
+        Font::from_font_data(font_data, font_index).expect("the native font couldn't be parsed")
+    }
+
+    fn analyze_bytes(font_data: Arc<Vec<u8>>) -> Result<FileType, FontLoadingError> {
+        FileType::detect(&font_data)
+    }
+
+    /// Determines whether a file represents a supported font, and, if so, what type of font it is.
+    ///
+    /// Only the first few bytes of the file are read, except for WOFF2 files.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn analyze_file(file: &mut File) -> Result<FileType, FontLoadingError> {
+        file.seek(SeekFrom::Start(0))?;
+        let mut header = Vec::with_capacity(file_type::HEADER_LEN);
+        file.take(file_type::HEADER_LEN as u64)
+            .read_to_end(&mut header)?;
+        if header.starts_with(file_type::WOFF2_SIGNATURE) {
+            file.seek(SeekFrom::Start(0))?;
+            return FileType::detect(&utils::slurp_file(file)?);
+        }
+        FileType::detect(&header)
+    }
+
+    /// Returns a native font created from this font's data.
+    ///
+    /// On macOS, iOS, and Windows, this creates a system font object each time it is called.
+    /// Passing the result to `from_native_font()` gives back the same font, without its variation
+    /// settings or metrics override.
+    fn native_font(&self) -> NativeFont {
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        {
+            crate::loaders::core_text::Font::from_bytes(
+                self.copy_font_data().unwrap(),
+                self.font_index,
+            )
+            .expect("Core Text couldn't load the font")
+            .native_font()
+        }
+        #[cfg(target_family = "windows")]
+        {
+            crate::loaders::directwrite::Font::from_bytes(
+                self.copy_font_data().unwrap(),
+                self.font_index,
+            )
+            .expect("DirectWrite couldn't load the font")
+            .native_font()
+        }
+        #[cfg(not(any(target_os = "macos", target_os = "ios", target_family = "windows")))]
+        {
+            NativeFont {
+                font_data: self.owned_font_data().unwrap(),
+                font_index: self.font_index,
+            }
+        }
     }
 
     fn postscript_name(&self) -> Option<String> {
-        None
+        self.name(name_id::POST_SCRIPT_NAME)
     }
 
     fn full_name(&self) -> String {
-        "Arial".to_owned()
+        self.name(name_id::FULL_NAME).unwrap_or_default()
     }
 
     fn family_name(&self) -> String {
-        "Arail".to_owned()
+        self.name(name_id::TYPOGRAPHIC_FAMILY)
+            .or_else(|| self.name(name_id::FAMILY))
+            .unwrap_or_default()
     }
 
     fn is_monospace(&self) -> bool {
-        self.face.is_monospaced()
+        self.shared.face.is_monospaced()
     }
 
     fn glyph_count(&self) -> u32 {
-        self.face.number_of_glyphs() as u32
+        self.shared.face.number_of_glyphs() as u32
     }
 
     fn properties(&self) -> Properties {
-        use ttf_parser::Weight as W;
-        Properties {
-            style: if self.face.is_italic() {
-                Style::Italic
-            } else if self.face.is_oblique() {
-                Style::Oblique
-            } else {
-                Style::Normal
-            },
-            weight: match self.face.weight() {
-                W::Thin => Weight::THIN,
-                W::ExtraLight => Weight::EXTRA_LIGHT,
-                W::Light => Weight::LIGHT,
-                W::Normal => Weight::NORMAL,
-                W::Medium => Weight::MEDIUM,
-                W::SemiBold => Weight::SEMIBOLD,
-                W::Bold => Weight::BOLD,
-                W::ExtraBold => Weight::BOLD,
-                W::Black => Weight::BLACK,
-                W::Other(val) => Weight(val as f32),
-            },
-            stretch: Stretch::NORMAL,
-        }
+        let tables = self.shared.face.tables();
+        properties_from_os2(tables.os2, tables.fvar)
     }
 
     fn glyph_for_char(&self, character: char) -> Option<u32> {
-        self.face.glyph_index(character).map(|id| id.0 as u32)
+        let face = &self.shared.face;
+        self.shared.cache.glyph_for_char(character, || {
+            face.glyph_index(character).map(|id| id.0 as u32)
+        })
     }
 
+    fn glyph_for_variation(&self, character: char, variation_selector: char) -> Option<u32> {
+        self.shared
+            .face
+            .glyph_variation_index(character, variation_selector)
+            .map(|id| id.0 as u32)
+    }
+
+    /// Sends the vector path for a glyph to a sink.
+    ///
+    /// Hinting is not supported, so `hinting_mode` is ignored. Glyphs without an outline, such as
+    /// spaces and bitmap-only glyphs, send nothing.
     fn outline<S: OutlineSink>(
         &self,
         glyph_id: u32,
-        hinting_mode: HintingOptions,
+        _hinting_mode: HintingOptions,
         sink: &mut S,
     ) -> Result<(), GlyphLoadingError> {
+        if glyph_id >= self.glyph_count() {
+            return Err(GlyphLoadingError::NoSuchGlyph);
+        }
+        self.outline_glyph(GlyphId(glyph_id as u16), &mut OutlineSinkAdapter(sink))?;
         Ok(())
     }
 
     fn typographic_bounds(&self, glyph_id: u32) -> Result<RectF, GlyphLoadingError> {
         let rect = self
-            .face
             .glyph_bounding_box(ttf_parser::GlyphId(glyph_id as u16))
             .ok_or(GlyphLoadingError::NoSuchGlyph)?;
 
@@ -180,24 +1549,140 @@ impl Loader for Font {
         Ok(rect)
     }
 
+    /// Returns the left side bearing from the `hmtx` table, which unlike the glyph's bounds also
+    /// exists for glyphs without an outline.
+    fn left_side_bearing(&self, glyph_id: u32) -> Result<f32, GlyphLoadingError> {
+        let glyph_id = GlyphId(glyph_id as u16);
+        match self.shared.face.glyph_hor_side_bearing(glyph_id) {
+            Some(side_bearing) => Ok(side_bearing as f32),
+            None => self
+                .glyph_bounding_box(glyph_id)
+                .map(|rect| rect.x_min as f32)
+                .ok_or(GlyphLoadingError::NoSuchGlyph),
+        }
+    }
+
+    fn right_side_bearing(&self, glyph_id: u32) -> Result<f32, GlyphLoadingError> {
+        let width = self
+            .glyph_bounding_box(GlyphId(glyph_id as u16))
+            .map_or(0.0, |rect| rect.width() as f32);
+        Ok(self.advance(glyph_id)?.x() - self.left_side_bearing(glyph_id)? - width)
+    }
+
     fn advance(&self, glyph_id: u32) -> Result<Vector2F, GlyphLoadingError> {
-        let h = self
-            .face
-            .glyph_hor_advance(GlyphId(glyph_id as u16))
+        let advance = self
+            .cached_advance(glyph_id)
             .ok_or(GlyphLoadingError::NoSuchGlyph)?;
-        let v = self
-            .face
-            .glyph_ver_advance(GlyphId(glyph_id as u16))
-            .ok_or(GlyphLoadingError::NoSuchGlyph)?;
-        Ok(Vector2F::new(h as f32, v as f32))
+        Ok(Vector2F::new(advance as f32, 0.0))
+    }
+
+    fn advance_with_orientation(
+        &self,
+        glyph_id: u32,
+        orientation: GlyphOrientation,
+    ) -> Result<Vector2F, GlyphLoadingError> {
+        if orientation == GlyphOrientation::Horizontal {
+            return self.advance(glyph_id);
+        }
+
+        let glyph_id = GlyphId(glyph_id as u16);
+        if glyph_id.0 >= self.shared.face.number_of_glyphs() {
+            return Err(GlyphLoadingError::NoSuchGlyph);
+        }
+        let advance = match self.shared.face.glyph_ver_advance(glyph_id) {
+            Some(advance) => advance as f32,
+            // Without `vmtx`, every glyph is one line of horizontal text tall.
+            None => {
+                let height =
+                    self.shared.face.ascender() as i32 - self.shared.face.descender() as i32;
+                if height > 0 {
+                    height as f32
+                } else {
+                    self.shared.face.units_per_em() as f32
+                }
+            }
+        };
+        Ok(Vector2F::new(0.0, advance))
     }
 
     fn origin(&self, glyph_id: u32) -> Result<Vector2F, GlyphLoadingError> {
-        Ok(Vector2F::default())
+        let glyph_id = GlyphId(glyph_id as u16);
+        if glyph_id.0 >= self.shared.face.number_of_glyphs() {
+            return Err(GlyphLoadingError::NoSuchGlyph);
+        }
+        // Bitmap glyphs (`sbix`, `CBDT`) are offset from the origin by their strike's metrics,
+        // which are in pixels of the strike's size. Use the largest strike, which is the most
+        // precise.
+        match self.shared.face.glyph_raster_image(glyph_id, u16::MAX) {
+            Some(image) if image.pixels_per_em > 0 => {
+                let scale = self.shared.face.units_per_em() as f32 / image.pixels_per_em as f32;
+                Ok(Vector2F::new(image.x as f32, image.y as f32) * scale)
+            }
+            _ => Ok(Vector2F::default()),
+        }
+    }
+
+    fn origin_with_orientation(
+        &self,
+        glyph_id: u32,
+        orientation: GlyphOrientation,
+    ) -> Result<Vector2F, GlyphLoadingError> {
+        let origin = self.origin(glyph_id)?;
+        if orientation == GlyphOrientation::Horizontal {
+            return Ok(origin);
+        }
+
+        let glyph_id = GlyphId(glyph_id as u16);
+        let advance = self.shared.face.glyph_hor_advance(glyph_id).unwrap_or(0) as f32;
+        // The vertical origin comes from `VORG` if present, otherwise from the top side bearing in
+        // `vmtx`, and otherwise sits at the ascender, as recommended by the OpenType spec.
+        let vertical_origin_y = match self.shared.face.glyph_y_origin(glyph_id) {
+            Some(y_origin) => y_origin as f32,
+            None => match (
+                self.shared.face.glyph_ver_side_bearing(glyph_id),
+                self.glyph_bounding_box(glyph_id),
+            ) {
+                (Some(top_side_bearing), Some(bounds)) => {
+                    top_side_bearing as f32 + bounds.y_max as f32
+                }
+                _ => self
+                    .shared
+                    .face
+                    .vertical_ascender()
+                    .unwrap_or_else(|| self.shared.face.ascender()) as f32,
+            },
+        };
+        Ok(origin - Vector2F::new(advance * 0.5, vertical_origin_y))
     }
 
     fn metrics(&self) -> Metrics {
-        Metrics::default()
+        let global_bounding_box = self.shared.face.global_bounding_box();
+        let underline_metrics = self.shared.face.underline_metrics();
+        let strikeout_metrics = self.shared.face.strikeout_metrics();
+        let metrics = Metrics {
+            units_per_em: self.shared.face.units_per_em() as u32,
+            ascent: self.shared.face.ascender() as f32,
+            descent: self.shared.face.descender() as f32,
+            line_gap: self.shared.face.line_gap() as f32,
+            underline_position: underline_metrics.map_or(0.0, |metrics| metrics.position as f32),
+            underline_thickness: underline_metrics.map_or(0.0, |metrics| metrics.thickness as f32),
+            strikeout_position: strikeout_metrics.map_or(0.0, |metrics| metrics.position as f32),
+            strikeout_thickness: strikeout_metrics.map_or(0.0, |metrics| metrics.thickness as f32),
+            cap_height: self.shared.face.capital_height().unwrap_or(0) as f32,
+            x_height: self.shared.face.x_height().unwrap_or(0) as f32,
+            bounding_box: RectF::from_points(
+                Vector2F::new(
+                    global_bounding_box.x_min as f32,
+                    global_bounding_box.y_min as f32,
+                ),
+                Vector2F::new(
+                    global_bounding_box.x_max as f32,
+                    global_bounding_box.y_max as f32,
+                ),
+            ),
+        }
+        .with_estimated_strikeout();
+        self.metrics_override.apply(&metrics)
     }
 
     fn rasterize_glyph(
@@ -206,47 +1691,71 @@ impl Loader for Font {
         glyph_id: u32,
         point_size: f32,
         transform: Transform2F,
-        hinting_options: HintingOptions,
+        _hinting_options: HintingOptions,
         rasterization_options: RasterizationOptions,
     ) -> Result<(), GlyphLoadingError> {
-        /*let raster = self
-            .face
-            .glyph_raster_image(GlyphId(self.glyph_for_char('a').unwrap() as u16), 12)
-            .unwrap();
-        canvas.pixels = raster.data.to_owned();*/
-        canvas.pixels = [
-            88u8, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 140,
-            88, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 140, 0,
-            0, 0, 0, 0, 0, 0, 188, 255, 232, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 188, 255,
-            232, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 188, 255, 232, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 188, 255, 232, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 188, 255,
-            232, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 188, 255, 232, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 188, 255, 232, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 188, 255,
-            232, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 188, 255, 232, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 188, 255, 232, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 188, 255,
-            232, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 188, 255, 232, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 188, 255, 232, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 188, 255,
-            232, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 188, 255, 232, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 188, 255, 232, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 188, 255,
-            232, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 188, 255, 232, 0, 0, 0, 0, 0, 0, 0,
-        ]
-        .to_vec();
-        canvas.pixels.extend(
-            std::iter::repeat(0u8).take((canvas.size.x() * canvas.size.y()) as usize - 20 * 17),
-        ); //canvas.size = Vector2I::new(20, 17);
-        canvas.format = Format::A8;
-        Ok(())
+        // This loader has no rasterizer of its own, and never hints.
+        self.rasterize_glyph_deterministic(
+            canvas,
+            glyph_id,
+            point_size,
+            transform,
+            rasterization_options,
+        )
+    }
+
+    fn rasterize_glyph_into(
+        &self,
+        target: &mut CanvasMut,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        _hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        deterministic::rasterize_glyph_into(
+            self,
+            target,
+            glyph_id,
+            point_size,
+            transform,
+            rasterization_options,
+        )
+    }
+
+    /// Returns true for glyphs with `COLR` layers, embedded color bitmaps, or `SVG` documents.
+    fn glyph_has_color(&self, glyph_id: u32) -> bool {
+        let glyph = GlyphId(glyph_id as u16);
+        self.shared.face.is_color_glyph(glyph)
+            || self.shared.face.glyph_svg_image(glyph).is_some()
+            || self
+                .shared
+                .face
+                .glyph_raster_image(glyph, u16::MAX)
+                .is_some_and(|image| {
+                    matches!(
+                        image.format,
+                        RasterImageFormat::PNG | RasterImageFormat::BitmapPremulBgra32
+                    )
+                })
     }
 
     fn get_fallbacks(&self, text: &str, locale: &str) -> FallbackResult<Self> {
-        FallbackResult {
-            fonts: Vec::new(),
-            valid_len: text.len(),
-        }
+        #[cfg(feature = "source")]
+        let fallbacks = system_fallback_chain(locale).fallbacks_for(Some(self), text);
+        #[cfg(not(feature = "source"))]
+        let fallbacks = {
+            let _ = locale;
+            FallbackChain::new(vec![]).fallbacks_for(Some(self), text)
+        };
+        #[cfg(feature = "last-resort")]
+        let fallbacks = last_resort::append(fallbacks);
+        fallbacks
     }
 
     fn load_font_table(&self, table_tag: u32) -> Option<Box<[u8]>> {
-        self.face
+        self.shared
+            .face
             .raw_face()
             .table(ttf_parser::Tag(table_tag))
             .map(|t| t.into())
@@ -254,13 +1763,507 @@ impl Loader for Font {
 
     fn supports_hinting_options(
         &self,
-        hinting_options: HintingOptions,
-        for_rasterization: bool,
+        _hinting_options: HintingOptions,
+        _for_rasterization: bool,
     ) -> bool {
         false
     }
 
     fn copy_font_data(&self) -> Option<Arc<Vec<u8>>> {
-        Some(Arc::clone(&self.font_data))
+        if let Some(ref lazy_outlines) = self.shared.lazy_outlines {
+            return lazy_outlines.read_all().map(Arc::new);
+        }
+        match self.shared.font_data {
+            FontData::Memory(ref bytes) => Some(Arc::clone(bytes)),
+            #[cfg(any(unix, target_family = "windows"))]
+            FontData::Mapped(ref mmap) => Some(Arc::new(mmap.to_vec())),
+            FontData::Borrowed(data) => Some(Arc::new(data.to_vec())),
+        }
+    }
+
+    fn with_font_data<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        if let Some(ref lazy_outlines) = self.shared.lazy_outlines {
+            return lazy_outlines.read_all().map(|font_data| f(&font_data));
+        }
+        Some(f(self.shared.font_data.as_slice()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Font;
+    use crate::canvas::{Canvas, Format, RasterizationOptions};
+    use crate::hinting::HintingOptions;
+    use crate::loader::Loader;
+    use crate::metrics::{GlyphOrientation, MetricsOverride};
+    use crate::test_fixtures::{EB_GARAMOND, EB_GARAMOND_OTC, INCONSOLATA};
+    use pathfinder_geometry::rect::RectI;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, Vector2I};
+    use std::sync::Arc;
+    use ttf_parser::GlyphId;
+
+    #[test]
+    fn test_borrowed_font_clones_own_their_data() {
+        let font_data = EB_GARAMOND.to_vec();
+        let font_ref = Font::from_bytes_borrowed(&font_data, 0).unwrap();
+        assert_eq!(font_ref.family_name(), "EB Garamond");
+
+        let font = font_ref.to_font();
+        drop(font_ref);
+        drop(font_data);
+        assert_eq!(font.family_name(), "EB Garamond");
+        assert_eq!(font.copy_font_data().unwrap().len(), EB_GARAMOND.len());
+    }
+
+    #[test]
+    fn test_with_variation_shares_data() {
+        let font = Font::from_bytes(Arc::new(EB_GARAMOND.to_vec()), 0).unwrap();
+        let wght = u32::from_be_bytes(*b"wght");
+        let varied = font.with_variation(wght, 700.0);
+        assert!(Arc::ptr_eq(
+            &font.copy_font_data().unwrap(),
+            &varied.copy_font_data().unwrap()
+        ));
+        // EB Garamond is not a variable font.
+        assert!(!varied.clone().set_variation(wght, 700.0));
+    }
+
+    #[test]
+    fn test_prewarm_cache() {
+        let font = Font::from_bytes(Arc::new(EB_GARAMOND.to_vec()), 0).unwrap();
+        let clone = font.clone();
+        font.prewarm_ascii_cache();
+        font.prewarm_cache("漢字 – ‘quoted’");
+
+        // Every lookup is answered from the cache that the clone shares.
+        let cache = &clone.shared.cache;
+        let glyph_count = font.glyph_count();
+        for character in (' '..='~').chain("–‘’".chars()) {
+            let glyph_id =
+                cache.glyph_for_char(character, || panic!("{:?} isn't cached", character));
+            assert_eq!(
+                glyph_id,
+                font.shared
+                    .face
+                    .glyph_index(character)
+                    .map(|id| id.0 as u32)
+            );
+            let advance = cache.advance(glyph_id.unwrap(), glyph_count, || panic!());
+            assert_eq!(
+                advance,
+                font.shared
+                    .face
+                    .glyph_hor_advance(GlyphId(glyph_id.unwrap() as u16))
+            );
+        }
+        // EB Garamond has no CJK glyphs, which is cached too.
+        assert_eq!(cache.glyph_for_char('漢', || panic!()), None);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_rasterize_glyphs_parallel() {
+        use crate::canvas::{Canvas, Format, RasterizationOptions};
+        use crate::hinting::HintingOptions;
+        use crate::raster_cache::GlyphRequest;
+        use pathfinder_geometry::transform2d::Transform2F;
+
+        let font = Font::from_bytes(Arc::new(EB_GARAMOND.to_vec()), 0).unwrap();
+        let mut requests: Vec<GlyphRequest> = "The quick brown fox"
+            .chars()
+            .map(|character| GlyphRequest::new(font.glyph_for_char(character).unwrap(), 24.0))
+            .collect();
+        requests[1].rasterization_options = RasterizationOptions::Bilevel.deterministic();
+        requests[1].format = Format::A1;
+        requests[2].transform = Transform2F::from_rotation(0.5);
+        requests[4].hinting_options = HintingOptions::Full(24.0);
+
+        let glyphs = font.rasterize_glyphs_parallel(&requests).unwrap();
+        assert_eq!(glyphs.len(), requests.len());
+        for (glyph, request) in glyphs.iter().zip(&requests) {
+            let mut canvas = Canvas::new(glyph.canvas.size, request.format);
+            font.rasterize_glyph(
+                &mut canvas,
+                request.glyph_id,
+                request.point_size,
+                Transform2F::from_translation(-glyph.origin.to_f32()) * request.transform,
+                request.hinting_options,
+                request.rasterization_options,
+            )
+            .unwrap();
+            assert_eq!(glyph.canvas.format, request.format);
+            assert_eq!(glyph.canvas.pixels, canvas.pixels);
+        }
+        assert!(glyphs[0].canvas.pixels.iter().any(|&pixel| pixel > 0));
+        // The space has nothing to draw.
+        assert!(glyphs[3].canvas.pixels.is_empty());
+
+        requests.push(GlyphRequest::new(u16::MAX as u32, 24.0));
+        assert!(font.rasterize_glyphs_parallel(&requests).is_err());
+    }
+
+    #[test]
+    fn test_native_font_round_trip() {
+        let font = Font::from_bytes(Arc::new(EB_GARAMOND_OTC.to_vec()), 1).unwrap();
+        let native_font = font.native_font();
+        let round_tripped = unsafe { Font::from_native_font(native_font) };
+        assert_eq!(round_tripped.postscript_name(), font.postscript_name());
+        assert_eq!(round_tripped.glyph_count(), font.glyph_count());
+        assert!(round_tripped.same_font_as(&font));
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let font = Font::from_bytes(Arc::new(EB_GARAMOND.to_vec()), 0).unwrap();
+        let path = "resources/tests/eb-garamond/EBGaramond12-Regular.otf";
+        let same = Font::from_path(path, 0).unwrap();
+        assert_eq!(font.fingerprint(), same.fingerprint());
+        assert!(font.same_font_as(&same));
+
+        let regular = Font::from_bytes_borrowed(EB_GARAMOND_OTC, 0).unwrap();
+        let italic = Font::from_bytes_borrowed(EB_GARAMOND_OTC, 1).unwrap();
+        assert!(!regular.same_font_as(&italic));
+    }
+
+    #[test]
+    fn test_metrics_override() {
+        let font = Font::from_bytes(Arc::new(EB_GARAMOND.to_vec()), 0).unwrap();
+        let metrics = font.metrics();
+        assert_eq!(metrics.units_per_em, 1000);
+        assert!(metrics.ascent > 0.0 && metrics.descent < 0.0);
+
+        let font = font.with_metrics_override(MetricsOverride {
+            ascent: Some(0.8),
+            descent: Some(0.2),
+            line_gap: None,
+        });
+        let overridden = font.clone().metrics();
+        assert_eq!(overridden.ascent, 800.0);
+        assert_eq!(overridden.descent, -200.0);
+        assert_eq!(overridden.line_gap, metrics.line_gap);
+    }
+
+    #[test]
+    fn test_vertical_metrics() {
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let glyph_id = font.glyph_for_char('A').unwrap();
+        assert_eq!(font.origin(glyph_id).unwrap(), Vector2F::default());
+
+        // EB Garamond has no vertical metrics, so the vertical origin is at the ascender.
+        let advance = font
+            .shared
+            .face
+            .glyph_hor_advance(GlyphId(glyph_id as u16))
+            .unwrap() as f32;
+        let ascender = font.shared.face.ascender() as f32;
+        assert_eq!(
+            font.origin_with_orientation(glyph_id, GlyphOrientation::Vertical)
+                .unwrap(),
+            Vector2F::new(-advance * 0.5, -ascender)
+        );
+
+        assert_eq!(font.advance(glyph_id).unwrap(), Vector2F::new(advance, 0.0));
+        let descender = font.shared.face.descender() as f32;
+        assert_eq!(
+            font.advance_with_orientation(glyph_id, GlyphOrientation::Vertical)
+                .unwrap(),
+            Vector2F::new(0.0, ascender - descender)
+        );
+    }
+
+    #[test]
+    fn test_stretch_from_width_class() {
+        use crate::properties::Stretch;
+
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        assert_eq!(font.properties().stretch, Stretch::NORMAL);
+
+        // Mark the font as condensed by rewriting `usWidthClass` in its `OS/2` table.
+        let mut font_data = EB_GARAMOND.to_vec();
+        let table_count = u16::from_be_bytes([font_data[4], font_data[5]]) as usize;
+        let os2_offset = (0..table_count)
+            .map(|index| &font_data[12 + index * 16..][..16])
+            .find(|record| &record[..4] == b"OS/2")
+            .map(|record| u32::from_be_bytes([record[8], record[9], record[10], record[11]]))
+            .unwrap() as usize;
+        font_data[os2_offset + 6..][..2].copy_from_slice(&3u16.to_be_bytes());
+        let font = Font::from_bytes(Arc::new(font_data), 0).unwrap();
+        assert_eq!(font.properties().stretch, Stretch::CONDENSED);
+    }
+
+    #[test]
+    fn test_italic_angle() {
+        use crate::properties::{Properties, Style};
+        use crate::synthesis::SynthesisOptions;
+
+        let regular = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        assert_eq!(regular.italic_angle(), 0.0);
+        assert_eq!(regular.caret_slant(&SynthesisOptions::default()), 0.0);
+
+        let path = "resources/tests/eb-garamond/EBGaramond12-Italic.otf";
+        let italic = Font::from_path(path, 0).unwrap();
+        assert!(italic.italic_angle() < 0.0);
+        assert!(italic.caret_slant(&SynthesisOptions::default()) > 0.0);
+
+        let requested = *Properties::new().style(Style::Italic);
+        let synthesis = regular.synthesis_for(&requested);
+        assert_eq!(synthesis.oblique, SynthesisOptions::OBLIQUE_SLANT);
+        assert_eq!(regular.caret_slant(&synthesis), synthesis.oblique);
+        assert!(!italic.synthesis_for(&requested).is_synthesized());
+    }
+
+    #[test]
+    fn test_decoration_rects() {
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let metrics = font.metrics();
+        let os2 = font.shared.face.strikeout_metrics().unwrap();
+        assert_eq!(metrics.strikeout_position, os2.position as f32);
+        assert_eq!(metrics.strikeout_thickness, os2.thickness as f32);
+
+        let rects = font.decoration_rects(metrics.units_per_em as f32, 100.0);
+        assert_eq!(rects.underline.max_y(), metrics.underline_position);
+        assert_eq!(rects.underline.height(), metrics.underline_thickness);
+        assert_eq!(rects.strikeout.max_y(), metrics.strikeout_position);
+        assert_eq!(rects.strikeout.width(), 100.0);
+        assert!(rects.strikeout.min_y() > 0.0 && rects.underline.max_y() < 0.0);
+    }
+
+    #[test]
+    fn test_side_bearings_and_ink_bounds() {
+        use pathfinder_geometry::transform2d::Transform2F;
+
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let units_per_em = font.metrics().units_per_em as f32;
+        let glyph_id = font.glyph_for_char('H').unwrap();
+        let bounds = font.typographic_bounds(glyph_id).unwrap();
+        let advance = font.advance(glyph_id).unwrap().x();
+        assert_eq!(font.left_side_bearing(glyph_id).unwrap(), bounds.min_x());
+        assert_eq!(
+            font.right_side_bearing(glyph_id).unwrap(),
+            advance - bounds.max_x()
+        );
+
+        let space = font.glyph_for_char(' ').unwrap();
+        assert_eq!(font.left_side_bearing(space).unwrap(), 0.0);
+        assert_eq!(
+            font.right_side_bearing(space).unwrap(),
+            font.advance(space).unwrap().x()
+        );
+
+        // At one point per font unit, the ink of a glyph with straight edges is its bounding box.
+        let ink = font
+            .ink_bounds(glyph_id, units_per_em, Transform2F::default())
+            .unwrap();
+        assert_eq!(ink, bounds);
+        let ink = font
+            .ink_bounds(glyph_id, units_per_em * 2.0, Transform2F::default())
+            .unwrap();
+        assert_eq!(ink, bounds * 2.0);
+        let glyph_id = font.glyph_for_char('o').unwrap();
+        let bounds = font.typographic_bounds(glyph_id).unwrap();
+        let ink = font
+            .ink_bounds(glyph_id, units_per_em, Transform2F::default())
+            .unwrap();
+        assert!(bounds.contains_rect(ink));
+        assert_eq!(
+            font.ink_bounds(space, 12.0, Transform2F::default())
+                .unwrap(),
+            Default::default()
+        );
+    }
+
+    #[test]
+    fn test_typed_metrics() {
+        use crate::units::Px;
+
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let metrics = font.metrics();
+        let scale = font.scale_context(Px(metrics.units_per_em as f32 * 0.5));
+        let glyph_id = font.glyph_for_char('H').unwrap();
+        assert_eq!(
+            font.advance_px(glyph_id, scale).unwrap(),
+            Px(font.advance(glyph_id).unwrap().x() * 0.5)
+        );
+        assert_eq!(
+            font.typographic_bounds_px(glyph_id, scale)
+                .unwrap()
+                .to_rect_f(),
+            font.typographic_bounds(glyph_id).unwrap() * 0.5
+        );
+        let scaled = font.metrics_px(scale);
+        assert_eq!(scaled.ascent, Px(metrics.ascent * 0.5));
+        assert_eq!(scaled.descent, Px(metrics.descent * 0.5));
+    }
+
+    #[test]
+    fn test_raster_bounds() {
+        use crate::canvas::RasterizationOptions;
+        use crate::hinting::HintingOptions;
+        use pathfinder_geometry::transform2d::Transform2F;
+        use pathfinder_geometry::vector::Vector2I;
+
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let units_per_em = font.metrics().units_per_em as f32;
+        let glyph_id = font.glyph_for_char('H').unwrap();
+        let raster_bounds = |transform| {
+            font.raster_bounds(
+                glyph_id,
+                units_per_em,
+                transform,
+                HintingOptions::None,
+                RasterizationOptions::GrayscaleAa,
+            )
+            .unwrap()
+        };
+
+        // At one pixel per font unit, the bounds are the glyph's bounding box flipped upside down.
+        let bounds = font.typographic_bounds(glyph_id).unwrap();
+        let upright = raster_bounds(Transform2F::default());
+        assert_eq!(upright.min_x(), bounds.min_x().floor() as i32);
+        assert_eq!(upright.min_y(), -bounds.max_y().ceil() as i32);
+        assert_eq!(upright.max_y(), -bounds.min_y().floor() as i32);
+
+        // Rotating the glyph by a quarter turn swaps its width and height.
+        let rotated = raster_bounds(Transform2F::from_rotation(std::f32::consts::FRAC_PI_2));
+        let size = rotated.size() - Vector2I::new(upright.height(), upright.width());
+        assert!(size.x().abs() <= 1 && size.y().abs() <= 1);
+    }
+
+    #[test]
+    fn test_batch_glyph_queries() {
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let glyph_ids = font.glyphs_for_chars("Aa\u{10ffff}A");
+        assert_eq!(glyph_ids[0], font.glyph_for_char('A'));
+        assert_eq!(glyph_ids[1], font.glyph_for_char('a'));
+        assert_eq!(glyph_ids[2], None);
+        assert_eq!(glyph_ids[3], glyph_ids[0]);
+
+        let a = glyph_ids[0].unwrap();
+        let advances = font.advances(&[a, font.glyph_count()]);
+        assert_eq!(advances[0], font.advance(a).unwrap());
+        assert_eq!(advances[1], Vector2F::zero());
+    }
+
+    #[test]
+    fn test_clone_shared() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Font>();
+
+        let font = Font::from_bytes(Arc::new(EB_GARAMOND.to_vec()), 0).unwrap();
+        let glyph_id = font.glyph_for_char('A');
+        let shared = font.clone_shared();
+        assert!(Arc::ptr_eq(&font.shared, &shared.shared));
+        let glyph_id_on_thread = std::thread::spawn(move || shared.glyph_for_char('A'))
+            .join()
+            .unwrap();
+        assert_eq!(glyph_id_on_thread, glyph_id);
+
+        // Fonts without the axis are left alone, so they keep sharing.
+        let mut varied = font.clone_shared();
+        assert!(!varied.set_variation(u32::from_be_bytes(*b"wght"), 700.0));
+        assert!(Arc::ptr_eq(&font.shared, &varied.shared));
+    }
+
+    #[cfg(feature = "source")]
+    #[test]
+    fn test_system_fallback_chains_shared_between_locale_spellings() {
+        use super::system_fallback_chain;
+
+        let chain = system_fallback_chain("ja-JP");
+        assert!(Arc::ptr_eq(&chain, &system_fallback_chain("ja_JP.UTF-8")));
+        assert!(Arc::ptr_eq(
+            &system_fallback_chain("en-US"),
+            &system_fallback_chain("de")
+        ));
+    }
+
+    #[test]
+    fn test_rasterize_glyph_negotiated() {
+        let font = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        let formats = [Format::Rgb24, Format::A8];
+        for &(character, point_size) in &[('.', 12.0), ('W', 12.0), ('W', 48.0)] {
+            let glyph_id = font.glyph_for_char(character).unwrap();
+            for &(rasterization_options, format) in &[
+                (RasterizationOptions::SubpixelAa, Format::Rgb24),
+                (RasterizationOptions::GrayscaleAa, Format::A8),
+            ] {
+                let glyph = font
+                    .rasterize_glyph_negotiated(
+                        glyph_id,
+                        point_size,
+                        Transform2F::default(),
+                        HintingOptions::None,
+                        rasterization_options,
+                        &formats,
+                    )
+                    .unwrap();
+                let canvas = &glyph.canvas;
+                assert_eq!(canvas.format, format);
+                assert_eq!(canvas.stride, format.row_len(canvas.size.x() as usize));
+                assert_eq!(
+                    canvas.pixels.len(),
+                    canvas.stride * canvas.size.y() as usize
+                );
+                assert!(canvas.pixels.iter().any(|&pixel| pixel > 0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rasterize_glyph_into() {
+        let font = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        let glyph_id = font.glyph_for_char('g').unwrap();
+        for &(rasterization_options, format) in &[
+            (RasterizationOptions::GrayscaleAa, Format::A8),
+            (RasterizationOptions::Bilevel, Format::A1),
+            (RasterizationOptions::SubpixelAa, Format::Rgba32),
+        ] {
+            let bounds = font
+                .raster_bounds(
+                    glyph_id,
+                    24.0,
+                    Transform2F::default(),
+                    HintingOptions::None,
+                    rasterization_options,
+                )
+                .unwrap();
+            let transform = Transform2F::from_translation(-bounds.origin().to_f32());
+            let mut expected = Canvas::new(bounds.size(), format);
+            font.rasterize_glyph(
+                &mut expected,
+                glyph_id,
+                24.0,
+                transform,
+                HintingOptions::None,
+                rasterization_options,
+            )
+            .unwrap();
+
+            // The glyph replaces its region of the page and leaves the rest alone.
+            let mut page = Canvas::new(bounds.size() + Vector2I::new(16, 2), format);
+            page.pixels.fill(0x55);
+            let slot = RectI::new(Vector2I::new(8, 1), bounds.size());
+            font.rasterize_glyph_into(
+                &mut page.as_view_mut().sub_view_mut(slot).unwrap(),
+                glyph_id,
+                24.0,
+                transform,
+                HintingOptions::None,
+                rasterization_options,
+            )
+            .unwrap();
+            let view = page.as_view();
+            assert_eq!(
+                view.sub_view(slot).unwrap().to_canvas().pixels,
+                expected.pixels
+            );
+            assert!(page.pixels[..page.stride]
+                .iter()
+                .all(|&pixel| pixel == 0x55));
+        }
     }
 }
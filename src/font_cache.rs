@@ -0,0 +1,245 @@
+// font-kit/src/font_cache.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A cache of loaded fonts with explicit control over when they are released.
+//!
+//! Fonts loaded from the same handle are loaded only once and shared. The cache hands out
+//! reference-counted fonts, so it can tell which fonts are still in use elsewhere in the
+//! application. Long-running applications can unload individual fonts, or all the fonts that
+//! nothing else refers to any more, to release their memory.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::error::FontLoadingError;
+use crate::font::Font;
+use crate::handle::Handle;
+use crate::loader::Loader;
+
+/// Identifies a font in a `FontCache`.
+///
+/// Keys are never reused by the same cache, even after the font they refer to is unloaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FontKey(u64);
+
+/// Counts of the fonts in a `FontCache`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FontCacheStats {
+    /// The number of fonts in the cache.
+    pub cached_fonts: usize,
+    /// The number of cached fonts that are also referenced outside the cache.
+    pub live_fonts: usize,
+}
+
+/// A cache of loaded fonts, keyed by the handle they were loaded from.
+#[derive(Debug)]
+pub struct FontCache<F = Font> {
+    fonts: HashMap<FontKey, CachedFont<F>>,
+    keys: HashMap<HandleKey, FontKey>,
+    next_key: u64,
+}
+
+#[derive(Debug)]
+struct CachedFont<F> {
+    font: Arc<F>,
+    // The handle the font was loaded from, if any. For fonts in memory, this keeps the data alive
+    // so that its address, which identifies it in `keys`, can't be reused.
+    handle: Option<(HandleKey, Handle)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum HandleKey {
+    Path(PathBuf, u32),
+    Uri(String, u32),
+    Memory(usize, u32),
+    Named(Box<HandleKey>, String),
+}
+
+impl HandleKey {
+    fn new(handle: &Handle) -> HandleKey {
+        match *handle {
+            Handle::Path {
+                ref path,
+                font_index,
+            } => HandleKey::Path(path.clone(), font_index),
+            Handle::Uri {
+                ref uri,
+                font_index,
+            } => HandleKey::Uri(uri.clone(), font_index),
+            Handle::Memory {
+                ref bytes,
+                font_index,
+            } => HandleKey::Memory(Arc::as_ptr(bytes) as usize, font_index),
+            Handle::Named {
+                ref collection,
+                ref postscript_name,
+            } => HandleKey::Named(
+                Box::new(HandleKey::new(collection)),
+                postscript_name.clone(),
+            ),
+        }
+    }
+}
+
+impl<F> Default for FontCache<F> {
+    fn default() -> FontCache<F> {
+        FontCache {
+            fonts: HashMap::new(),
+            keys: HashMap::new(),
+            next_key: 0,
+        }
+    }
+}
+
+impl<F> FontCache<F>
+where
+    F: Loader,
+{
+    /// Creates an empty cache.
+    #[inline]
+    pub fn new() -> FontCache<F> {
+        FontCache::default()
+    }
+
+    /// Returns the font loaded from `handle`, loading it if it isn't cached yet.
+    pub fn load(&mut self, handle: &Handle) -> Result<(FontKey, Arc<F>), FontLoadingError> {
+        let handle_key = HandleKey::new(handle);
+        if let Some(&key) = self.keys.get(&handle_key) {
+            return Ok((key, self.fonts[&key].font.clone()));
+        }
+
+        let font = Arc::new(F::from_handle(handle)?);
+        let key = self.insert_font(font.clone(), Some((handle_key.clone(), handle.clone())));
+        self.keys.insert(handle_key, key);
+        Ok((key, font))
+    }
+
+    /// Adds a font that was loaded some other way to the cache.
+    pub fn insert(&mut self, font: F) -> (FontKey, Arc<F>) {
+        let font = Arc::new(font);
+        (self.insert_font(font.clone(), None), font)
+    }
+
+    /// Returns the font with the given key, if it is still cached.
+    #[inline]
+    pub fn get(&self, key: FontKey) -> Option<Arc<F>> {
+        self.fonts
+            .get(&key)
+            .map(|cached_font| cached_font.font.clone())
+    }
+
+    /// Removes the font with the given key from the cache, returning true if it was cached.
+    ///
+    /// Clones of the font held elsewhere remain valid; the font's memory is released when the
+    /// last of them is dropped. Loading the same handle again loads the font anew.
+    pub fn unload(&mut self, key: FontKey) -> bool {
+        match self.fonts.remove(&key) {
+            Some(cached_font) => {
+                if let Some((handle_key, _)) = cached_font.handle {
+                    self.keys.remove(&handle_key);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes all the fonts that are not referenced outside the cache, returning how many were
+    /// removed.
+    pub fn unload_unused(&mut self) -> usize {
+        let unused: Vec<FontKey> = self
+            .fonts
+            .iter()
+            .filter(|(_, cached_font)| Arc::strong_count(&cached_font.font) == 1)
+            .map(|(&key, _)| key)
+            .collect();
+        for &key in &unused {
+            self.unload(key);
+        }
+        unused.len()
+    }
+
+    /// Returns true if the font with the given key is referenced outside the cache.
+    pub fn is_live(&self, key: FontKey) -> bool {
+        self.fonts
+            .get(&key)
+            .is_some_and(|cached_font| Arc::strong_count(&cached_font.font) > 1)
+    }
+
+    /// Returns the number of cached fonts.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
+    /// Returns true if no fonts are cached.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.fonts.is_empty()
+    }
+
+    /// Returns counts of the cached fonts and of those still in use.
+    pub fn stats(&self) -> FontCacheStats {
+        FontCacheStats {
+            cached_fonts: self.fonts.len(),
+            live_fonts: self
+                .fonts
+                .values()
+                .filter(|cached_font| Arc::strong_count(&cached_font.font) > 1)
+                .count(),
+        }
+    }
+
+    fn insert_font(&mut self, font: Arc<F>, handle: Option<(HandleKey, Handle)>) -> FontKey {
+        let key = FontKey(self.next_key);
+        self.next_key += 1;
+        self.fonts.insert(key, CachedFont { font, handle });
+        key
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FontCache, FontCacheStats};
+    use crate::font::Font;
+    use crate::handle::Handle;
+    use std::path::PathBuf;
+
+    static INCONSOLATA_PATH: &str = "resources/tests/inconsolata/Inconsolata-Regular.ttf";
+    static EB_GARAMOND_PATH: &str = "resources/tests/eb-garamond/EBGaramond12-Regular.otf";
+
+    #[test]
+    fn test_unload_unused() {
+        let mut cache: FontCache<Font> = FontCache::new();
+        let inconsolata = Handle::from_path(PathBuf::from(INCONSOLATA_PATH), 0);
+        let (key, font) = cache.load(&inconsolata).unwrap();
+        assert_eq!(cache.load(&inconsolata).unwrap().0, key);
+        let (eb_garamond_key, _) = cache
+            .load(&Handle::from_path(PathBuf::from(EB_GARAMOND_PATH), 0))
+            .unwrap();
+        assert_eq!(
+            cache.stats(),
+            FontCacheStats {
+                cached_fonts: 2,
+                live_fonts: 1,
+            }
+        );
+
+        assert_eq!(cache.unload_unused(), 1);
+        assert!(cache.get(eb_garamond_key).is_none());
+        assert!(cache.is_live(key));
+
+        drop(font);
+        assert!(cache.unload(key));
+        assert!(cache.is_empty());
+        assert_ne!(cache.load(&inconsolata).unwrap().0, key);
+    }
+}
@@ -0,0 +1,413 @@
+// font-kit/src/font_changes.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Notifications when fonts are installed or removed while the application is running.
+//!
+//! A watcher runs on a background thread and sends a `FontChangeEvent` down a channel whenever
+//! the set of fonts in the directories it watches changes, so that applications can refresh font
+//! pickers and invalidate caches. It stops when its `FontChangeReceiver` is dropped.
+//!
+//! Changes are detected from the font directories rather than from the platforms' own font change
+//! notifications (`WM_FONTCHANGE` on Windows, Core Text's font manager notifications on macOS, or
+//! fontconfig's configuration changes), so fonts that are activated without being copied into a
+//! font directory, such as fonts registered for a single process, aren't noticed.
+//!
+//! With the `watch` feature, which is on by default, `watch_directories_natively()` uses the
+//! operating system's file notifications, through the `notify` crate. Changes are reported as
+//! soon as they happen, including files overwritten in place, and each event lists the files that
+//! changed. `SystemSource::subscribe_changes()` and `FsSource::subscribe_changes()` use it.
+//!
+//! `watch_directories()` instead detects changes by periodically comparing the modification times
+//! of the watched directories and their subdirectories, which works the same way on every
+//! platform, and is what the sources fall back to without the `watch` feature or where
+//! notifications are unavailable. Each check walks every watched directory, so the interval
+//! shouldn't be short. Installing, removing, or renaming a font changes the modification time of
+//! its directory; overwriting a font file in place does not.
+
+#[cfg(feature = "watch")]
+use log::warn;
+#[cfg(feature = "watch")]
+use notify::event::{EventKind, ModifyKind, RenameMode};
+#[cfg(feature = "watch")]
+use notify::{RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::ops::Deref;
+#[cfg(feature = "watch")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::sources::cache::{self, DirectoryStamp};
+
+/// How long `watch_directories_natively()` waits for more notifications before sending an event,
+/// so that installing a family of fonts produces one event rather than dozens.
+#[cfg(feature = "watch")]
+const COALESCING_DELAY: Duration = Duration::from_millis(100);
+
+/// How often `watch_directories_natively()` checks whether its receiver has been dropped while no
+/// notifications arrive.
+#[cfg(feature = "watch")]
+const STOP_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often `SystemSource::subscribe_changes()` checks for changes when it can't use the
+/// operating system's file notifications.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The receiving end of a font watcher's channel, which can be used as a `Receiver`.
+///
+/// Dropping it stops the watcher: its thread exits the next time it checks, within one poll
+/// interval, whether or not any fonts have changed.
+#[derive(Debug)]
+pub struct FontChangeReceiver {
+    receiver: Receiver<FontChangeEvent>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl FontChangeReceiver {
+    fn new(receiver: Receiver<FontChangeEvent>) -> (FontChangeReceiver, Arc<AtomicBool>) {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let receiver = FontChangeReceiver {
+            receiver,
+            stopped: Arc::clone(&stopped),
+        };
+        (receiver, stopped)
+    }
+}
+
+impl Deref for FontChangeReceiver {
+    type Target = Receiver<FontChangeEvent>;
+
+    #[inline]
+    fn deref(&self) -> &Receiver<FontChangeEvent> {
+        &self.receiver
+    }
+}
+
+impl Drop for FontChangeReceiver {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A change to the set of fonts in the watched directories.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FontChangeEvent {
+    /// The directories whose contents changed, including directories that were created or
+    /// removed.
+    pub changed_directories: Vec<PathBuf>,
+    /// The files that changed, in the order the changes happened.
+    ///
+    /// Only `watch_directories_natively()` can tell which files changed. `watch_directories()`
+    /// leaves this empty.
+    pub changed_files: Vec<FontFileChange>,
+}
+
+/// A change to a single file in a watched directory.
+///
+/// The file may not be a font. Sources decide what is a font by reading the file, so every change
+/// is reported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FontFileChange {
+    /// A file was created, or renamed into place.
+    Added(PathBuf),
+    /// A file was deleted, or renamed away.
+    Removed(PathBuf),
+    /// The contents of a file changed.
+    Modified(PathBuf),
+}
+
+impl FontFileChange {
+    /// Returns the path of the file that changed.
+    #[inline]
+    pub fn path(&self) -> &PathBuf {
+        match *self {
+            FontFileChange::Added(ref path)
+            | FontFileChange::Removed(ref path)
+            | FontFileChange::Modified(ref path) => path,
+        }
+    }
+}
+
+/// Watches the given directories and everything below them for fonts being added or removed,
+/// checking every `poll_interval`.
+///
+/// The current state of the directories is recorded before this function returns, so any change
+/// made afterward is reported.
+pub fn watch_directories(directories: Vec<PathBuf>, poll_interval: Duration) -> FontChangeReceiver {
+    let (sender, receiver) = mpsc::channel();
+    let (receiver, stopped) = FontChangeReceiver::new(receiver);
+    let mut stamps = cache::directory_stamps(&directories);
+    thread::Builder::new()
+        .name("font-kit watcher".to_owned())
+        .spawn(move || loop {
+            thread::sleep(poll_interval);
+            if stopped.load(Ordering::Relaxed) {
+                break;
+            }
+            let new_stamps = cache::directory_stamps(&directories);
+            let changed_directories = changed_directories(&stamps, &new_stamps);
+            stamps = new_stamps;
+            if changed_directories.is_empty() {
+                continue;
+            }
+            if sender
+                .send(FontChangeEvent {
+                    changed_directories,
+                    changed_files: vec![],
+                })
+                .is_err()
+            {
+                break;
+            }
+        })
+        .expect("failed to spawn font watcher thread");
+    receiver
+}
+
+/// Watches the given directories and everything below them with the operating system's file
+/// notifications, sending an event as soon as files change.
+///
+/// If the directories can't be watched this way, for example because the system limit on watches
+/// has been reached, this falls back to `watch_directories()` with `DEFAULT_POLL_INTERVAL`.
+/// Directories that don't exist are skipped, as they are by `watch_directories()`; unlike there,
+/// fonts installed into them once they are created aren't noticed.
+#[cfg(feature = "watch")]
+pub fn watch_directories_natively(directories: Vec<PathBuf>) -> FontChangeReceiver {
+    let (notifications_sender, notifications) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(notifications_sender) {
+        Ok(watcher) => watcher,
+        Err(error) => {
+            warn!("Falling back to polling for font changes: {}", error);
+            return watch_directories(directories, DEFAULT_POLL_INTERVAL);
+        }
+    };
+    for directory in directories.iter().filter(|directory| directory.is_dir()) {
+        if let Err(error) = watcher.watch(directory, RecursiveMode::Recursive) {
+            warn!("Falling back to polling for font changes: {}", error);
+            return watch_directories(directories, DEFAULT_POLL_INTERVAL);
+        }
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let (receiver, stopped) = FontChangeReceiver::new(receiver);
+    thread::Builder::new()
+        .name("font-kit watcher".to_owned())
+        .spawn(move || {
+            // The watcher stops when it is dropped, so it lives as long as this thread.
+            let _watcher = watcher;
+            while !stopped.load(Ordering::Relaxed) {
+                let notification = match notifications.recv_timeout(STOP_CHECK_INTERVAL) {
+                    Ok(notification) => notification,
+                    Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                };
+                let mut changed_files = vec![];
+                add_changed_files(notification, &mut changed_files);
+                while let Ok(notification) = notifications.recv_timeout(COALESCING_DELAY) {
+                    add_changed_files(notification, &mut changed_files);
+                }
+                if changed_files.is_empty() {
+                    continue;
+                }
+
+                let mut changed_directories: Vec<PathBuf> = changed_files
+                    .iter()
+                    .filter_map(|change| change.path().parent().map(Path::to_owned))
+                    .collect();
+                changed_directories.sort();
+                changed_directories.dedup();
+                if sender
+                    .send(FontChangeEvent {
+                        changed_directories,
+                        changed_files,
+                    })
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        })
+        .expect("failed to spawn font watcher thread");
+    receiver
+}
+
+// Watches the directories natively with the `watch` feature, and by polling otherwise.
+pub(crate) fn subscribe(directories: Vec<PathBuf>) -> FontChangeReceiver {
+    #[cfg(feature = "watch")]
+    {
+        watch_directories_natively(directories)
+    }
+    #[cfg(not(feature = "watch"))]
+    {
+        watch_directories(directories, DEFAULT_POLL_INTERVAL)
+    }
+}
+
+// Translates a notification into file changes. Accesses and metadata changes are ignored, since
+// they don't change which fonts are installed.
+#[cfg(feature = "watch")]
+fn add_changed_files(
+    notification: notify::Result<notify::Event>,
+    changed_files: &mut Vec<FontFileChange>,
+) {
+    let event = match notification {
+        Ok(event) => event,
+        Err(error) => {
+            warn!("Error watching for font changes: {}", error);
+            return;
+        }
+    };
+    let mut paths = event.paths.into_iter();
+    match event.kind {
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            changed_files.extend(paths.map(FontFileChange::Added))
+        }
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            changed_files.extend(paths.map(FontFileChange::Removed))
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            changed_files.extend(paths.next().map(FontFileChange::Removed));
+            changed_files.extend(paths.map(FontFileChange::Added));
+        }
+        EventKind::Modify(ModifyKind::Metadata(_)) | EventKind::Access(_) => {}
+        EventKind::Modify(_) | EventKind::Any | EventKind::Other => {
+            changed_files.extend(paths.map(FontFileChange::Modified))
+        }
+    }
+}
+
+// Returns the directories that were added, removed, or modified between the two scans.
+fn changed_directories(
+    old_stamps: &[DirectoryStamp],
+    new_stamps: &[DirectoryStamp],
+) -> Vec<PathBuf> {
+    let mut old_times: HashMap<&PathBuf, Option<SystemTime>> = old_stamps
+        .iter()
+        .map(|&(ref path, modified)| (path, modified))
+        .collect();
+    let mut changed = vec![];
+    for &(ref path, modified) in new_stamps {
+        if old_times.remove(path) != Some(modified) {
+            changed.push(path.clone());
+        }
+    }
+    // Whatever is left was removed.
+    changed.extend(old_times.into_keys().cloned());
+    changed.sort();
+    changed.dedup();
+    changed
+}
+
+#[cfg(test)]
+mod test {
+    use super::{changed_directories, watch_directories};
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant, SystemTime};
+
+    #[test]
+    fn test_changed_directories() {
+        let time = Some(SystemTime::UNIX_EPOCH);
+        let later = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1));
+        let old_stamps = vec![
+            (PathBuf::from("/fonts"), time),
+            (PathBuf::from("/fonts/modified"), time),
+            (PathBuf::from("/fonts/removed"), time),
+            (PathBuf::from("/fonts/same"), time),
+        ];
+        let new_stamps = vec![
+            (PathBuf::from("/fonts"), time),
+            (PathBuf::from("/fonts/added"), time),
+            (PathBuf::from("/fonts/modified"), later),
+            (PathBuf::from("/fonts/same"), time),
+        ];
+        assert_eq!(
+            changed_directories(&old_stamps, &new_stamps),
+            [
+                PathBuf::from("/fonts/added"),
+                PathBuf::from("/fonts/modified"),
+                PathBuf::from("/fonts/removed"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_watch_directories() {
+        let directory = env::temp_dir().join(format!("font-kit-watch-test-{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        let changes = watch_directories(vec![directory.clone()], Duration::from_millis(10));
+        let subdirectory = directory.join("new-fonts");
+        fs::create_dir(&subdirectory).unwrap();
+        let event = changes.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(event.changed_directories.contains(&subdirectory));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_watcher_stops_when_dropped() {
+        let directory = env::temp_dir().join(format!("font-kit-stop-test-{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        let changes = watch_directories(vec![directory.clone()], Duration::from_millis(10));
+        let stopped = Arc::clone(&changes.stopped);
+        drop(changes);
+        // The watcher thread holds the other reference until it exits.
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Arc::strong_count(&stopped) > 1 {
+            assert!(Instant::now() < deadline);
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn test_watch_directories_natively() {
+        use super::{watch_directories_natively, FontFileChange};
+
+        let directory =
+            env::temp_dir().join(format!("font-kit-native-watch-test-{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        let changes = watch_directories_natively(vec![directory.clone()]);
+        let font_path = directory.join("Inconsolata-Regular.ttf");
+        fs::copy(
+            "resources/tests/inconsolata/Inconsolata-Regular.ttf",
+            &font_path,
+        )
+        .unwrap();
+        let event = changes.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(event
+            .changed_files
+            .contains(&FontFileChange::Added(font_path.clone())));
+        assert!(event.changed_directories.contains(&directory));
+
+        fs::remove_file(&font_path).unwrap();
+        let event = changes.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(event
+            .changed_files
+            .contains(&FontFileChange::Removed(font_path)));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
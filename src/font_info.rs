@@ -0,0 +1,155 @@
+// font-kit/src/font_info.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A summary of a font that is cheap to read, for listing fonts.
+//!
+//! `Handle::info()` reads only the table directory and the handful of small tables that the
+//! summary needs, without loading the font; from files, nothing else is read from disk. This keeps
+//! font pickers responsive across thousands of installed fonts; load the font itself once it is
+//! chosen.
+//!
+//! `Handle::coverage()` reads the set of supported characters the same way, from the `cmap` table
+//! alone. It is kept out of `FontInfo` because it is much larger than the rest of the summary.
+//!
+//! Sources use these summaries to index fonts, so scanning a directory never loads outlines.
+
+use ttf_parser::{name_id, os2, RawFace, Tag};
+
+use crate::coverage::UnicodeRangeSet;
+use crate::error::FontLoadingError;
+use crate::font;
+use crate::properties::Properties;
+
+/// A summary of a font: its names, its properties, and a few facts about its contents.
+///
+/// Returned by `Handle::info()`. The names and properties are the same as those that the loaded
+/// font reports.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FontInfo {
+    /// The family name, such as "Helvetica". The typographic family name is preferred.
+    pub family_name: String,
+    /// The name of the font within its family, such as "Bold Italic". The typographic subfamily
+    /// name is preferred.
+    pub subfamily_name: String,
+    /// The PostScript name, if the font has one.
+    pub postscript_name: Option<String>,
+    /// The style, weight, and stretch.
+    pub properties: Properties,
+    /// Whether the font has variation axes.
+    pub is_variable: bool,
+    /// The minimum and maximum of the font's `wdth` variation axis, as percentages of the normal
+    /// width, if it has one.
+    pub width_range: Option<(f32, f32)>,
+    /// Whether the font has color glyphs, as color emoji fonts do: `COLR` layers, color
+    /// bitmaps, or `SVG` documents.
+    pub has_color: bool,
+    /// The number of glyphs in the font.
+    pub glyph_count: u32,
+}
+
+// The tables that `FontInfo::from_data()` reads. It only checks whether the font has the others.
+pub(crate) const INFO_TABLES: [u32; 4] = [
+    u32::from_be_bytes(*b"name"),
+    u32::from_be_bytes(*b"maxp"),
+    u32::from_be_bytes(*b"OS/2"),
+    u32::from_be_bytes(*b"fvar"),
+];
+
+// The table that `coverage_from_data()` reads.
+pub(crate) const COVERAGE_TABLES: [u32; 1] = [u32::from_be_bytes(*b"cmap")];
+
+impl FontInfo {
+    /// Reads the summary of the font at `font_index` in raw font data.
+    pub(crate) fn from_data(data: &[u8], font_index: u32) -> Result<FontInfo, FontLoadingError> {
+        let face = RawFace::parse(data, font_index)
+            .map_err(|error| FontLoadingError::from_face_parsing_error(error, data, font_index))?;
+        let table = |tag: &[u8; 4]| face.table(Tag::from_bytes(tag));
+
+        let names = table(b"name")
+            .and_then(ttf_parser::name::Table::parse)
+            .map(|table| table.names)
+            .unwrap_or_default();
+        let name = |primary, fallback| {
+            font::name_from_table(names, primary).or_else(|| font::name_from_table(names, fallback))
+        };
+        let glyph_count = table(b"maxp")
+            .and_then(ttf_parser::maxp::Table::parse)
+            .ok_or(FontLoadingError::MissingTable {
+                table: u32::from_be_bytes(*b"maxp"),
+            })?
+            .number_of_glyphs
+            .get() as u32;
+
+        let fvar = table(b"fvar").and_then(ttf_parser::fvar::Table::parse);
+        Ok(FontInfo {
+            family_name: name(name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY).unwrap_or_default(),
+            subfamily_name: name(name_id::TYPOGRAPHIC_SUBFAMILY, name_id::SUBFAMILY)
+                .unwrap_or_default(),
+            postscript_name: font::name_from_table(names, name_id::POST_SCRIPT_NAME),
+            properties: font::properties_from_os2(table(b"OS/2").and_then(os2::Table::parse), fvar),
+            is_variable: table(b"fvar").is_some(),
+            width_range: fvar.and_then(|fvar| {
+                fvar.axes
+                    .into_iter()
+                    .find(|axis| axis.tag == ttf_parser::Tag::from_bytes(b"wdth"))
+                    .map(|axis| (axis.min_value, axis.max_value))
+            }),
+            has_color: [b"COLR", b"CBDT", b"sbix", b"SVG "]
+                .iter()
+                .any(|tag| table(tag).is_some()),
+            glyph_count,
+        })
+    }
+}
+
+/// Reads the set of characters that the font at `font_index` in raw font data supports.
+pub(crate) fn coverage_from_data(
+    data: &[u8],
+    font_index: u32,
+) -> Result<UnicodeRangeSet, FontLoadingError> {
+    let face = RawFace::parse(data, font_index)
+        .map_err(|error| FontLoadingError::from_face_parsing_error(error, data, font_index))?;
+    Ok(font::coverage_from_cmap(
+        face.table(Tag::from_bytes(b"cmap"))
+            .and_then(ttf_parser::cmap::Table::parse),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::handle::Handle;
+    use crate::loader::Loader;
+    use std::sync::Arc;
+
+    static EB_GARAMOND_OTC_PATH: &str = "resources/tests/eb-garamond/EBGaramond12.otc";
+
+    #[test]
+    fn test_info_matches_loaded_font() {
+        for font_index in 0..2 {
+            let handle = Handle::from_path(EB_GARAMOND_OTC_PATH.into(), font_index);
+            let info = handle.info().unwrap();
+            let font = handle.load().unwrap();
+            assert_eq!(info.family_name, font.family_name());
+            assert_eq!(info.postscript_name, font.postscript_name());
+            assert_eq!(info.properties, font.properties());
+            assert_eq!(info.glyph_count, font.glyph_count());
+            assert!(!info.is_variable);
+            assert_eq!(info.width_range, None);
+            assert!(!info.has_color);
+            assert_eq!(handle.coverage().unwrap(), font.coverage());
+        }
+
+        let data = Arc::new(std::fs::read(EB_GARAMOND_OTC_PATH).unwrap());
+        let italic = Handle::from_memory(data.clone(), 1).info().unwrap();
+        assert!(italic.subfamily_name.contains("Italic"));
+        assert!(Handle::from_memory(data, 7).info().is_err());
+    }
+}
@@ -0,0 +1,135 @@
+// font-kit/src/gasp.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! How a font wants to be rasterized at each size, from its `gasp` table.
+//!
+//! Hinted TrueType fonts often look best bi-level at text sizes, where their hints were tuned for
+//! whole pixels, and antialiased at very small and large sizes. The `gasp` table records which
+//! sizes are which. `Loader::gasp_behavior()` reads it, and `RasterizationOptions::FontDefault`
+//! rasterizes as it says.
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::canvas::RasterizationOptions;
+
+// `rangeGaspBehavior` flags.
+const GRIDFIT: u16 = 0x0001;
+const DOGRAY: u16 = 0x0002;
+const SYMMETRIC_GRIDFIT: u16 = 0x0004;
+const SYMMETRIC_SMOOTHING: u16 = 0x0008;
+
+/// The rasterization a font recommends at a particular size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GaspBehavior {
+    /// Whether outlines should be hinted.
+    pub grid_fit: bool,
+    /// Whether glyphs should be antialiased.
+    pub grayscale: bool,
+    /// Whether outlines should be hinted only along the vertical axis when rasterizing with
+    /// ClearType, as DirectWrite does by default.
+    pub symmetric_grid_fit: bool,
+    /// Whether glyphs should be antialiased along both axes when rasterizing with ClearType.
+    pub symmetric_smoothing: bool,
+}
+
+impl GaspBehavior {
+    /// Reads the behavior at `ppem` pixels per em from the contents of a `gasp` table.
+    ///
+    /// Returns `None` if the table is malformed or has no range covering `ppem`.
+    pub fn from_table(gasp: &[u8], ppem: u16) -> Option<GaspBehavior> {
+        let version = read_u16(gasp, 0)?;
+        let range_count = read_u16(gasp, 2)? as usize;
+        let flags = (0..range_count)
+            .map(|index| 4 + 4 * index)
+            .find(|&range| read_u16(gasp, range).is_none_or(|max_ppem| ppem <= max_ppem))
+            .and_then(|range| read_u16(gasp, range + 2))?;
+        // Version 0 tables predate the symmetric flags.
+        let flags = if version == 0 {
+            flags & (GRIDFIT | DOGRAY)
+        } else {
+            flags
+        };
+        Some(GaspBehavior {
+            grid_fit: flags & GRIDFIT != 0,
+            grayscale: flags & DOGRAY != 0,
+            symmetric_grid_fit: flags & SYMMETRIC_GRIDFIT != 0,
+            symmetric_smoothing: flags & SYMMETRIC_SMOOTHING != 0,
+        })
+    }
+
+    /// Returns the rasterization options that follow this behavior: grayscale antialiasing if the
+    /// font asks for either kind of smoothing, and bi-level rendering otherwise.
+    ///
+    /// Subpixel antialiasing depends on the display rather than the font, so it is never chosen.
+    pub fn rasterization_options(&self) -> RasterizationOptions {
+        if self.grayscale || self.symmetric_smoothing {
+            RasterizationOptions::GrayscaleAa
+        } else {
+            RasterizationOptions::Bilevel
+        }
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..(offset + 2)).map(BigEndian::read_u16)
+}
+
+#[cfg(test)]
+mod test {
+    use super::GaspBehavior;
+    use crate::canvas::RasterizationOptions;
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use crate::test_fixtures::{ARIAL, INCONSOLATA};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_gasp_behavior() {
+        // Arial antialiases up to 8 pixels per em, hints bi-level up to 17, and does both above.
+        let font = Font::from_bytes(Arc::new(ARIAL.to_vec()), 0).unwrap();
+        let behavior = font.gasp_behavior(8).unwrap();
+        assert!(behavior.grayscale && !behavior.grid_fit);
+        let behavior = font.gasp_behavior(12).unwrap();
+        assert!(!behavior.grayscale && behavior.grid_fit);
+        let behavior = font.gasp_behavior(18).unwrap();
+        assert!(behavior.grayscale && behavior.grid_fit);
+
+        let resolve =
+            |pixels_per_em| RasterizationOptions::FontDefault.resolve(&font, pixels_per_em);
+        assert_eq!(resolve(8.0), RasterizationOptions::GrayscaleAa);
+        assert_eq!(resolve(12.0), RasterizationOptions::Bilevel);
+        assert_eq!(resolve(17.4), RasterizationOptions::Bilevel);
+        assert_eq!(resolve(40.0), RasterizationOptions::GrayscaleAa);
+        assert_eq!(
+            RasterizationOptions::SubpixelAa.resolve(&font, 12.0),
+            RasterizationOptions::SubpixelAa
+        );
+
+        // Inconsolata sets every flag at every size.
+        let font = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        assert_eq!(
+            font.gasp_behavior(12),
+            Some(GaspBehavior {
+                grid_fit: true,
+                grayscale: true,
+                symmetric_grid_fit: true,
+                symmetric_smoothing: true,
+            })
+        );
+
+        // Version 0 tables can't set the symmetric flags.
+        let table = [0, 0, 0, 1, 0xff, 0xff, 0, 0x0c];
+        assert_eq!(
+            GaspBehavior::from_table(&table, 12),
+            Some(GaspBehavior::default())
+        );
+        assert_eq!(GaspBehavior::from_table(&table[..4], 12), None);
+    }
+}
@@ -0,0 +1,192 @@
+// font-kit/src/glyph_cache.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Lock-free caches of the glyph lookups that text layout repeats most often.
+//!
+//! A font and its clones share one cache, so that threads shaping text with the same font warm
+//! it up for each other. Entries are filled in with relaxed atomic stores: a lookup always
+//! computes the same value, so a thread that misses an entry that another thread is storing
+//! merely computes it again.
+//!
+//! Latin-1 characters and advances have an entry each. Other characters share a small table of
+//! recently used ones, indexed by their low bits, so that scripts with large alphabets such as
+//! CJK are cached in a fixed amount of memory: a character evicts the one in its slot.
+
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::OnceLock;
+
+// Characters below this are cached. This covers ASCII and Latin-1, which make up most text in
+// most documents, in one kilobyte.
+const CACHED_CHARACTERS: usize = 256;
+
+// An entry that hasn't been looked up yet.
+const UNKNOWN: u32 = u32::MAX;
+// A cached character that the font has no glyph for.
+const MISSING: u32 = u32::MAX - 1;
+
+// Other characters are cached in this many slots, in four kilobytes. Each entry packs the high
+// bits of the character, plus one so that zero means empty, above 17 bits for the glyph, which is
+// a 16-bit glyph ID or `RECENT_MISSING`.
+const RECENT_SLOT_BITS: u32 = 10;
+const RECENT_SLOTS: usize = 1 << RECENT_SLOT_BITS;
+const RECENT_GLYPH_BITS: u32 = 17;
+const RECENT_MISSING: u32 = 1 << 16;
+
+pub(crate) struct GlyphCache {
+    glyphs: [AtomicU32; CACHED_CHARACTERS],
+    recent_glyphs: [AtomicU32; RECENT_SLOTS],
+    // Indexed by glyph ID; allocated on first use, since most fonts are only queried for names
+    // or metrics.
+    advances: OnceLock<Box<[AtomicU32]>>,
+}
+
+impl GlyphCache {
+    pub(crate) fn new() -> GlyphCache {
+        GlyphCache {
+            glyphs: [const { AtomicU32::new(UNKNOWN) }; CACHED_CHARACTERS],
+            recent_glyphs: [const { AtomicU32::new(0) }; RECENT_SLOTS],
+            advances: OnceLock::new(),
+        }
+    }
+
+    /// Returns the glyph for `character`, calling `lookup` to find it if it isn't cached.
+    #[inline]
+    pub(crate) fn glyph_for_char<F>(&self, character: char, lookup: F) -> Option<u32>
+    where
+        F: FnOnce() -> Option<u32>,
+    {
+        let entry = match self.glyphs.get(character as usize) {
+            Some(entry) => entry,
+            None => return self.recent_glyph_for_char(character, lookup),
+        };
+        match entry.load(Ordering::Relaxed) {
+            UNKNOWN => {
+                let glyph_id = lookup();
+                entry.store(glyph_id.unwrap_or(MISSING), Ordering::Relaxed);
+                glyph_id
+            }
+            MISSING => None,
+            glyph_id => Some(glyph_id),
+        }
+    }
+
+    fn recent_glyph_for_char<F>(&self, character: char, lookup: F) -> Option<u32>
+    where
+        F: FnOnce() -> Option<u32>,
+    {
+        let character = character as u32;
+        let entry = &self.recent_glyphs[character as usize % RECENT_SLOTS];
+        let tag = (character >> RECENT_SLOT_BITS) + 1;
+        let value = entry.load(Ordering::Relaxed);
+        if value >> RECENT_GLYPH_BITS == tag {
+            return match value & ((1 << RECENT_GLYPH_BITS) - 1) {
+                RECENT_MISSING => None,
+                glyph_id => Some(glyph_id),
+            };
+        }
+        let glyph_id = lookup();
+        match glyph_id {
+            Some(glyph_id) if glyph_id < RECENT_MISSING => {
+                entry.store((tag << RECENT_GLYPH_BITS) | glyph_id, Ordering::Relaxed)
+            }
+            Some(_) => {}
+            None => entry.store(
+                (tag << RECENT_GLYPH_BITS) | RECENT_MISSING,
+                Ordering::Relaxed,
+            ),
+        }
+        glyph_id
+    }
+
+    /// Returns the advance of `glyph_id`, calling `lookup` to find it if it isn't cached.
+    /// `glyph_count` is the number of glyphs in the font.
+    #[inline]
+    pub(crate) fn advance<F>(&self, glyph_id: u32, glyph_count: u32, lookup: F) -> Option<u16>
+    where
+        F: FnOnce() -> Option<u16>,
+    {
+        let advances = self
+            .advances
+            .get_or_init(|| (0..glyph_count).map(|_| AtomicU32::new(UNKNOWN)).collect());
+        let entry = match advances.get(glyph_id as usize) {
+            Some(entry) => entry,
+            None => return lookup(),
+        };
+        match entry.load(Ordering::Relaxed) {
+            UNKNOWN => {
+                let advance = lookup();
+                entry.store(advance.map_or(MISSING, u32::from), Ordering::Relaxed);
+                advance
+            }
+            MISSING => None,
+            advance => Some(advance as u16),
+        }
+    }
+}
+
+impl Default for GlyphCache {
+    #[inline]
+    fn default() -> GlyphCache {
+        GlyphCache::new()
+    }
+}
+
+impl Debug for GlyphCache {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.debug_struct("GlyphCache").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::GlyphCache;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_glyph_cache() {
+        let cache = GlyphCache::new();
+        let lookups = Cell::new(0);
+        let lookups = &lookups;
+        let lookup = |glyph_id| {
+            move || {
+                lookups.set(lookups.get() + 1);
+                glyph_id
+            }
+        };
+        assert_eq!(cache.glyph_for_char('a', lookup(Some(3))), Some(3));
+        assert_eq!(cache.glyph_for_char('a', lookup(Some(4))), Some(3));
+        assert_eq!(cache.glyph_for_char('b', lookup(None)), None);
+        assert_eq!(cache.glyph_for_char('b', lookup(Some(4))), None);
+        assert_eq!(lookups.get(), 2);
+
+        // Other characters are cached until another one takes their slot.
+        assert_eq!(cache.glyph_for_char('Ω', lookup(Some(5))), Some(5));
+        assert_eq!(cache.glyph_for_char('Ω', lookup(Some(6))), Some(5));
+        assert_eq!(cache.glyph_for_char('漢', lookup(None)), None);
+        assert_eq!(cache.glyph_for_char('漢', lookup(Some(6))), None);
+        assert_eq!(lookups.get(), 4);
+        let same_slot = char::from_u32('Ω' as u32 + 1024).unwrap();
+        assert_eq!(cache.glyph_for_char(same_slot, lookup(Some(7))), Some(7));
+        assert_eq!(cache.glyph_for_char('Ω', lookup(Some(8))), Some(8));
+        assert_eq!(cache.glyph_for_char('\u{10ffff}', lookup(Some(9))), Some(9));
+        assert_eq!(
+            cache.glyph_for_char('\u{10ffff}', lookup(Some(10))),
+            Some(9)
+        );
+        // Glyph IDs that don't fit in 16 bits are returned but not cached.
+        assert_eq!(cache.glyph_for_char('→', lookup(Some(70000))), Some(70000));
+        assert_eq!(cache.glyph_for_char('→', lookup(Some(11))), Some(11));
+
+        assert_eq!(cache.advance(1, 2, || Some(500)), Some(500));
+        assert_eq!(cache.advance(1, 2, || Some(0)), Some(500));
+        assert_eq!(cache.advance(2, 2, || None), None);
+    }
+}
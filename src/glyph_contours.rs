@@ -0,0 +1,449 @@
+// font-kit/src/glyph_contours.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Glyph contours exactly as they are stored in the font.
+//!
+//! `Loader::outline()` produces Bézier path commands, which loses information that font editors
+//! and converters need: TrueType outlines may contain runs of off-curve points with implied
+//! on-curve points between them, and the path commands don't say which points were in the font
+//! and which were inferred. The contours returned here contain exactly the points from the font,
+//! in design units and in their original order, with each point's kind.
+
+use byteorder::{BigEndian, ByteOrder};
+use pathfinder_geometry::transform2d::{Matrix2x2F, Transform2F};
+use pathfinder_geometry::vector::Vector2F;
+use ttf_parser::{Face, GlyphId, Tag};
+
+use crate::error::GlyphLoadingError;
+
+// Composite glyphs nested deeper than this are treated as malformed, to avoid cycles.
+const MAX_COMPONENT_DEPTH: u32 = 8;
+
+// Simple glyph flags.
+const ON_CURVE_POINT: u8 = 0x01;
+const X_SHORT_VECTOR: u8 = 0x02;
+const Y_SHORT_VECTOR: u8 = 0x04;
+const REPEAT_FLAG: u8 = 0x08;
+const X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR: u8 = 0x10;
+const Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR: u8 = 0x20;
+
+// Composite glyph flags.
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+/// The kind of a point in a glyph contour.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PointKind {
+    /// A point on the outline.
+    OnCurve,
+    /// The control point of a quadratic Bézier curve, as found in TrueType outlines.
+    ///
+    /// Two consecutive quadratic control points have an implied on-curve point midway between
+    /// them, which is not included in the contour.
+    QuadraticControl,
+    /// One of the two control points of a cubic Bézier curve, as found in CFF outlines.
+    CubicControl,
+}
+
+/// A closed contour of a glyph, in design units.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GlyphContour {
+    /// The points of the contour in the order they are stored in the font.
+    ///
+    /// For TrueType glyphs that aren't composites, the coordinates are the integers stored in the
+    /// font. Components of composite glyphs are transformed as the composite specifies.
+    pub points: Vec<(Vector2F, PointKind)>,
+}
+
+/// Returns the contours of a glyph, preferring the `glyf` table and falling back to the other
+/// outline formats that `ttf-parser` supports.
+pub(crate) fn glyph_contours(
+    face: &Face,
+    glyph_id: u16,
+) -> Result<Vec<GlyphContour>, GlyphLoadingError> {
+    if glyph_id >= face.number_of_glyphs() {
+        return Err(GlyphLoadingError::NoSuchGlyph);
+    }
+
+    let raw_face = face.raw_face();
+    if let (Some(head), Some(loca), Some(glyf)) = (
+        raw_face.table(Tag::from_bytes(b"head")),
+        raw_face.table(Tag::from_bytes(b"loca")),
+        raw_face.table(Tag::from_bytes(b"glyf")),
+    ) {
+        let glyf = GlyfTable::new(head, loca, glyf).ok_or(GlyphLoadingError::Parse)?;
+        let mut contours = vec![];
+        glyf.append_contours(glyph_id, Transform2F::default(), 0, &mut contours)?;
+        return Ok(contours);
+    }
+
+    let mut builder = ContourBuilder::default();
+    face.outline_glyph(GlyphId(glyph_id), &mut builder);
+    builder.finish_contour();
+    Ok(builder.contours)
+}
+
+struct GlyfTable<'a> {
+    loca: &'a [u8],
+    glyf: &'a [u8],
+    long_offsets: bool,
+}
+
+impl<'a> GlyfTable<'a> {
+    fn new(head: &'a [u8], loca: &'a [u8], glyf: &'a [u8]) -> Option<GlyfTable<'a>> {
+        // `indexToLocFormat` is at offset 50 of the `head` table.
+        let index_to_loc_format = BigEndian::read_i16(head.get(50..52)?);
+        Some(GlyfTable {
+            loca,
+            glyf,
+            long_offsets: index_to_loc_format != 0,
+        })
+    }
+
+    fn glyph_data(&self, glyph_id: u16) -> Option<&'a [u8]> {
+        let index = glyph_id as usize;
+        let (start, end) = if self.long_offsets {
+            let offsets = self.loca.get(index * 4..index * 4 + 8)?;
+            (
+                BigEndian::read_u32(&offsets[0..4]) as usize,
+                BigEndian::read_u32(&offsets[4..8]) as usize,
+            )
+        } else {
+            let offsets = self.loca.get(index * 2..index * 2 + 4)?;
+            (
+                BigEndian::read_u16(&offsets[0..2]) as usize * 2,
+                BigEndian::read_u16(&offsets[2..4]) as usize * 2,
+            )
+        };
+        if start > end {
+            return None;
+        }
+        self.glyf.get(start..end)
+    }
+
+    fn append_contours(
+        &self,
+        glyph_id: u16,
+        transform: Transform2F,
+        depth: u32,
+        contours: &mut Vec<GlyphContour>,
+    ) -> Result<(), GlyphLoadingError> {
+        if depth > MAX_COMPONENT_DEPTH {
+            return Err(GlyphLoadingError::Parse);
+        }
+        let data = self.glyph_data(glyph_id).ok_or(GlyphLoadingError::Parse)?;
+        // Glyphs without outlines, such as spaces, have no data at all.
+        if data.is_empty() {
+            return Ok(());
+        }
+        if data.len() < 10 {
+            return Err(GlyphLoadingError::Parse);
+        }
+
+        let number_of_contours = BigEndian::read_i16(&data[0..2]);
+        let result = if number_of_contours >= 0 {
+            append_simple_contours(
+                &data[10..],
+                number_of_contours as usize,
+                transform,
+                contours,
+            )
+        } else {
+            self.append_composite_contours(&data[10..], transform, depth, contours)
+        };
+        result.ok_or(GlyphLoadingError::Parse)?
+    }
+
+    fn append_composite_contours(
+        &self,
+        mut data: &[u8],
+        transform: Transform2F,
+        depth: u32,
+        contours: &mut Vec<GlyphContour>,
+    ) -> Option<Result<(), GlyphLoadingError>> {
+        loop {
+            let flags = read_u16(&mut data)?;
+            let glyph_id = read_u16(&mut data)?;
+            // Offsets are signed, but point numbers are unsigned.
+            let signed = flags & ARGS_ARE_XY_VALUES != 0;
+            let (argument_1, argument_2) = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+                let (argument_1, argument_2) = (read_u16(&mut data)?, read_u16(&mut data)?);
+                if signed {
+                    (argument_1 as i16 as i32, argument_2 as i16 as i32)
+                } else {
+                    (argument_1 as i32, argument_2 as i32)
+                }
+            } else {
+                let (argument_1, argument_2) = (read_u8(&mut data)?, read_u8(&mut data)?);
+                if signed {
+                    (argument_1 as i8 as i32, argument_2 as i8 as i32)
+                } else {
+                    (argument_1 as i32, argument_2 as i32)
+                }
+            };
+
+            let matrix = if flags & WE_HAVE_A_SCALE != 0 {
+                Matrix2x2F::from_scale(read_f2dot14(&mut data)?)
+            } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                let x_scale = read_f2dot14(&mut data)?;
+                let y_scale = read_f2dot14(&mut data)?;
+                Matrix2x2F::from_scale(Vector2F::new(x_scale, y_scale))
+            } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                let x_scale = read_f2dot14(&mut data)?;
+                let scale_01 = read_f2dot14(&mut data)?;
+                let scale_10 = read_f2dot14(&mut data)?;
+                let y_scale = read_f2dot14(&mut data)?;
+                Matrix2x2F::row_major(x_scale, scale_10, scale_01, y_scale)
+            } else {
+                Matrix2x2F::default()
+            };
+
+            let first_contour = contours.len();
+            let component_transform = transform
+                * Transform2F {
+                    matrix,
+                    vector: Vector2F::zero(),
+                };
+            if let Err(error) =
+                self.append_contours(glyph_id, component_transform, depth + 1, contours)
+            {
+                return Some(Err(error));
+            }
+
+            let offset = if flags & ARGS_ARE_XY_VALUES != 0 {
+                transform.matrix * Vector2F::new(argument_1 as f32, argument_2 as f32)
+            } else {
+                // The component is positioned so that its point `argument_2` lands on point
+                // `argument_1` of the glyph so far.
+                let parent_point = nth_point(&contours[..first_contour], argument_1 as usize)?;
+                let child_point = nth_point(&contours[first_contour..], argument_2 as usize)?;
+                parent_point - child_point
+            };
+            for contour in &mut contours[first_contour..] {
+                for point in &mut contour.points {
+                    point.0 += offset;
+                }
+            }
+
+            if flags & MORE_COMPONENTS == 0 {
+                return Some(Ok(()));
+            }
+        }
+    }
+}
+
+fn append_simple_contours(
+    mut data: &[u8],
+    number_of_contours: usize,
+    transform: Transform2F,
+    contours: &mut Vec<GlyphContour>,
+) -> Option<Result<(), GlyphLoadingError>> {
+    let mut end_points = Vec::with_capacity(number_of_contours);
+    for _ in 0..number_of_contours {
+        end_points.push(read_u16(&mut data)? as usize);
+    }
+    let point_count = match end_points.last() {
+        Some(&last_end_point) => last_end_point + 1,
+        None => return Some(Ok(())),
+    };
+    let instruction_length = read_u16(&mut data)? as usize;
+    data = data.get(instruction_length..)?;
+
+    let mut flags = Vec::with_capacity(point_count);
+    while flags.len() < point_count {
+        let flag = read_u8(&mut data)?;
+        let repeat_count = if flag & REPEAT_FLAG != 0 {
+            read_u8(&mut data)? as usize + 1
+        } else {
+            1
+        };
+        flags.extend((0..repeat_count).map(|_| flag));
+    }
+    flags.truncate(point_count);
+
+    let xs = read_coordinates(
+        &mut data,
+        &flags,
+        X_SHORT_VECTOR,
+        X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR,
+    )?;
+    let ys = read_coordinates(
+        &mut data,
+        &flags,
+        Y_SHORT_VECTOR,
+        Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR,
+    )?;
+
+    let mut start = 0;
+    for end_point in end_points {
+        if end_point < start || end_point >= point_count {
+            return None;
+        }
+        let points = (start..=end_point)
+            .map(|index| {
+                let position = transform * Vector2F::new(xs[index] as f32, ys[index] as f32);
+                let kind = if flags[index] & ON_CURVE_POINT != 0 {
+                    PointKind::OnCurve
+                } else {
+                    PointKind::QuadraticControl
+                };
+                (position, kind)
+            })
+            .collect();
+        contours.push(GlyphContour { points });
+        start = end_point + 1;
+    }
+    Some(Ok(()))
+}
+
+fn read_coordinates(
+    data: &mut &[u8],
+    flags: &[u8],
+    short_flag: u8,
+    same_or_positive_flag: u8,
+) -> Option<Vec<i32>> {
+    let mut coordinate = 0;
+    let mut coordinates = Vec::with_capacity(flags.len());
+    for &flag in flags {
+        if flag & short_flag != 0 {
+            let delta = read_u8(data)? as i32;
+            if flag & same_or_positive_flag != 0 {
+                coordinate += delta;
+            } else {
+                coordinate -= delta;
+            }
+        } else if flag & same_or_positive_flag == 0 {
+            coordinate += read_u16(data)? as i16 as i32;
+        }
+        coordinates.push(coordinate);
+    }
+    Some(coordinates)
+}
+
+fn nth_point(contours: &[GlyphContour], index: usize) -> Option<Vector2F> {
+    contours
+        .iter()
+        .flat_map(|contour| contour.points.iter())
+        .nth(index)
+        .map(|point| point.0)
+}
+
+fn read_u8(data: &mut &[u8]) -> Option<u8> {
+    let (&value, rest) = data.split_first()?;
+    *data = rest;
+    Some(value)
+}
+
+fn read_u16(data: &mut &[u8]) -> Option<u16> {
+    let value = BigEndian::read_u16(data.get(0..2)?);
+    *data = &data[2..];
+    Some(value)
+}
+
+fn read_f2dot14(data: &mut &[u8]) -> Option<f32> {
+    Some(read_u16(data)? as i16 as f32 / 16384.0)
+}
+
+// Collects the contours of CFF and other non-TrueType outlines.
+#[derive(Default)]
+struct ContourBuilder {
+    contours: Vec<GlyphContour>,
+    current_contour: GlyphContour,
+}
+
+impl ContourBuilder {
+    fn push(&mut self, x: f32, y: f32, kind: PointKind) {
+        self.current_contour
+            .points
+            .push((Vector2F::new(x, y), kind));
+    }
+
+    fn finish_contour(&mut self) {
+        if !self.current_contour.points.is_empty() {
+            self.contours
+                .push(std::mem::take(&mut self.current_contour));
+        }
+    }
+}
+
+impl ttf_parser::OutlineBuilder for ContourBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.finish_contour();
+        self.push(x, y, PointKind::OnCurve);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.push(x, y, PointKind::OnCurve);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.push(x1, y1, PointKind::QuadraticControl);
+        self.push(x, y, PointKind::OnCurve);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.push(x1, y1, PointKind::CubicControl);
+        self.push(x2, y2, PointKind::CubicControl);
+        self.push(x, y, PointKind::OnCurve);
+    }
+
+    fn close(&mut self) {
+        // The contour is closed implicitly, so drop the point that returns to the start.
+        let points = &mut self.current_contour.points;
+        if points.len() > 1 && points.last() == points.first() {
+            points.pop();
+        }
+        self.finish_contour();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PointKind;
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use crate::test_fixtures::{EB_GARAMOND, EB_GARAMOND_TTF};
+    use pathfinder_geometry::vector::Vector2F;
+
+    #[test]
+    fn test_glyph_contours() {
+        let font = Font::from_bytes_borrowed(EB_GARAMOND_TTF, 0).unwrap();
+        let glyph_id = font.glyph_for_char('o').unwrap();
+        let contours = font.glyph_contours(glyph_id).unwrap();
+        assert_eq!(contours.len(), 2);
+        for contour in &contours {
+            assert!(contour
+                .points
+                .iter()
+                .all(|&(position, kind)| position == position.round()
+                    && kind != PointKind::CubicControl));
+            assert!(contour
+                .points
+                .iter()
+                .any(|&(_, kind)| kind == PointKind::QuadraticControl));
+        }
+        let space = font.glyph_for_char(' ').unwrap();
+        assert!(font.glyph_contours(space).unwrap().is_empty());
+
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let glyph_id = font.glyph_for_char('o').unwrap();
+        let contours = font.glyph_contours(glyph_id).unwrap();
+        assert_eq!(contours.len(), 2);
+        assert!(contours[0]
+            .points
+            .iter()
+            .any(|&(_, kind)| kind == PointKind::CubicControl));
+        assert_ne!(contours[0].points[0].0, Vector2F::zero());
+    }
+}
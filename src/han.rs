@@ -0,0 +1,265 @@
+// font-kit/src/han.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Telling apart the regional variants of CJK fonts.
+//!
+//! Unified ideographs share code points across Chinese, Japanese, and Korean, but each region
+//! draws many of them differently, so a fallback font for them should match the reader's locale.
+//! A font's variant is guessed from the CJK code pages in its `OS/2` table, the languages of the
+//! records in its `name` table, and, as a last resort, a regional suffix on its family name, as
+//! in "Noto Sans CJK JP".
+
+use crate::loader::Loader;
+use crate::os2::CodePageRanges;
+use ttf_parser::PlatformId;
+
+/// A regional variant of Han characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum HanVariant {
+    SimplifiedChinese,
+    TraditionalChinese,
+    Japanese,
+    Korean,
+}
+
+const HAN_VARIANTS: [HanVariant; 4] = [
+    HanVariant::SimplifiedChinese,
+    HanVariant::TraditionalChinese,
+    HanVariant::Japanese,
+    HanVariant::Korean,
+];
+
+impl HanVariant {
+    /// Returns the variant that readers of a BCP 47 language tag such as `"zh-Hant-TW"` or
+    /// `"ja_JP"` expect, or `None` if the language doesn't use Han characters.
+    pub(crate) fn from_locale(locale: &str) -> Option<HanVariant> {
+        let mut subtags = locale
+            .split(['-', '_', '.', '@'])
+            .map(|subtag| subtag.to_ascii_lowercase());
+        match subtags.next()?.as_str() {
+            "ja" | "jpn" => Some(HanVariant::Japanese),
+            "ko" | "kor" => Some(HanVariant::Korean),
+            "zh" | "zho" | "chi" | "cmn" | "yue" | "wuu" | "hak" | "nan" => {
+                // An explicit script wins over the region, so "zh-Hans-HK" is simplified.
+                let mut variant = HanVariant::SimplifiedChinese;
+                for subtag in subtags {
+                    match subtag.as_str() {
+                        "hans" => return Some(HanVariant::SimplifiedChinese),
+                        "hant" => return Some(HanVariant::TraditionalChinese),
+                        "tw" | "hk" | "mo" => variant = HanVariant::TraditionalChinese,
+                        _ => {}
+                    }
+                }
+                Some(variant)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the variant of a language ID in a `name` table record, or `None` if the language
+    /// doesn't use Han characters.
+    pub(crate) fn from_name_language(
+        platform_id: PlatformId,
+        language_id: u16,
+    ) -> Option<HanVariant> {
+        match platform_id {
+            // Windows language IDs: the low ten bits are the language and the rest the region.
+            PlatformId::Windows => match (language_id & 0x3ff, language_id >> 10) {
+                (0x04, 0x01 | 0x03 | 0x05 | 0x1f) => Some(HanVariant::TraditionalChinese),
+                (0x04, _) => Some(HanVariant::SimplifiedChinese),
+                (0x11, _) => Some(HanVariant::Japanese),
+                (0x12, _) => Some(HanVariant::Korean),
+                _ => None,
+            },
+            // Macintosh language codes.
+            PlatformId::Macintosh => match language_id {
+                11 => Some(HanVariant::Japanese),
+                19 => Some(HanVariant::TraditionalChinese),
+                23 => Some(HanVariant::Korean),
+                33 => Some(HanVariant::SimplifiedChinese),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // The `OS/2` code page bits of the variant: JIS, GBK, Wansung and Johab, and Big5.
+    fn code_page_bits(self) -> &'static [u32] {
+        match self {
+            HanVariant::Japanese => &[17],
+            HanVariant::SimplifiedChinese => &[18],
+            HanVariant::Korean => &[19, 21],
+            HanVariant::TraditionalChinese => &[20],
+        }
+    }
+
+    // The suffixes that mark a family as being for this variant.
+    fn family_suffixes(self) -> &'static [&'static str] {
+        match self {
+            HanVariant::SimplifiedChinese => &["sc", "cn"],
+            HanVariant::TraditionalChinese => &["tc", "hk", "tw"],
+            HanVariant::Japanese => &["jp"],
+            HanVariant::Korean => &["kr"],
+        }
+    }
+}
+
+/// What a font declares about the Han variants it was made for.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct HanSignals {
+    code_pages: [bool; 4],
+    names: [bool; 4],
+    family_suffix: Option<HanVariant>,
+}
+
+impl HanSignals {
+    /// Reads the signals from a font's `OS/2` and `name` tables and its family name.
+    pub(crate) fn from_font<F>(font: &F) -> HanSignals
+    where
+        F: Loader,
+    {
+        let os2 = font.load_font_table(u32::from_be_bytes(*b"OS/2"));
+        let code_page_ranges = CodePageRanges::from_os2(os2.as_deref());
+        let mut signals = HanSignals::default();
+        for (index, variant) in HAN_VARIANTS.iter().enumerate() {
+            signals.code_pages[index] = variant
+                .code_page_bits()
+                .iter()
+                .any(|&bit| code_page_ranges.contains(bit));
+        }
+
+        let name = font.load_font_table(u32::from_be_bytes(*b"name"));
+        if let Some(table) = name.as_deref().and_then(ttf_parser::name::Table::parse) {
+            for record in table.names {
+                if let Some(variant) =
+                    HanVariant::from_name_language(record.platform_id, record.language_id)
+                {
+                    signals.names[variant_index(variant)] = true;
+                }
+            }
+        }
+
+        // "SC" also stands for small caps, so only trust the suffix of fonts that are CJK by
+        // other measures.
+        let family_name = font.family_name();
+        if signals.is_cjk() || family_name.contains("CJK") {
+            signals.family_suffix = family_suffix(&family_name);
+        }
+        signals
+    }
+
+    /// Returns true if the font declares anything about Han variants at all.
+    pub(crate) fn is_cjk(&self) -> bool {
+        self.code_pages.iter().any(|&set| set)
+            || self.names.iter().any(|&set| set)
+            || self.family_suffix.is_some()
+    }
+
+    /// Returns a sort key for how well the font suits `variant`; lower is better.
+    ///
+    /// Localized names for the variant's language count the most, since a font usually carries
+    /// them only for the region it was made for. Then come a matching family suffix and a matching
+    /// code page. Among fonts that are otherwise equal, those that declare fewer other variants
+    /// are preferred, since pan-CJK fonts often claim every code page.
+    pub(crate) fn rank(&self, variant: HanVariant) -> (bool, bool, bool, usize) {
+        let index = variant_index(variant);
+        let others = (0..HAN_VARIANTS.len())
+            .filter(|&other| other != index && (self.code_pages[other] || self.names[other]))
+            .count();
+        (
+            !self.names[index],
+            self.family_suffix != Some(variant),
+            !self.code_pages[index],
+            others,
+        )
+    }
+}
+
+fn variant_index(variant: HanVariant) -> usize {
+    HAN_VARIANTS
+        .iter()
+        .position(|&candidate| candidate == variant)
+        .unwrap()
+}
+
+// Reads a regional suffix such as "SC" or "JP" off a family name like "Noto Serif CJK JP".
+fn family_suffix(family_name: &str) -> Option<HanVariant> {
+    let suffix = family_name.rsplit([' ', '-']).next()?.to_ascii_lowercase();
+    HAN_VARIANTS
+        .iter()
+        .copied()
+        .find(|variant| variant.family_suffixes().contains(&suffix.as_str()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{family_suffix, HanVariant};
+    use ttf_parser::PlatformId;
+
+    #[test]
+    fn test_han_variants() {
+        assert_eq!(
+            HanVariant::from_locale("zh-CN"),
+            Some(HanVariant::SimplifiedChinese)
+        );
+        assert_eq!(
+            HanVariant::from_locale("zh"),
+            Some(HanVariant::SimplifiedChinese)
+        );
+        assert_eq!(
+            HanVariant::from_locale("zh_TW.UTF-8"),
+            Some(HanVariant::TraditionalChinese)
+        );
+        assert_eq!(
+            HanVariant::from_locale("zh-Hant"),
+            Some(HanVariant::TraditionalChinese)
+        );
+        assert_eq!(
+            HanVariant::from_locale("zh-Hans-HK"),
+            Some(HanVariant::SimplifiedChinese)
+        );
+        assert_eq!(HanVariant::from_locale("ja-JP"), Some(HanVariant::Japanese));
+        assert_eq!(HanVariant::from_locale("ko"), Some(HanVariant::Korean));
+        assert_eq!(HanVariant::from_locale("en-US"), None);
+        assert_eq!(HanVariant::from_locale(""), None);
+
+        assert_eq!(
+            HanVariant::from_name_language(PlatformId::Windows, 0x0804),
+            Some(HanVariant::SimplifiedChinese)
+        );
+        assert_eq!(
+            HanVariant::from_name_language(PlatformId::Windows, 0x0c04),
+            Some(HanVariant::TraditionalChinese)
+        );
+        assert_eq!(
+            HanVariant::from_name_language(PlatformId::Windows, 0x0411),
+            Some(HanVariant::Japanese)
+        );
+        assert_eq!(
+            HanVariant::from_name_language(PlatformId::Macintosh, 23),
+            Some(HanVariant::Korean)
+        );
+        assert_eq!(
+            HanVariant::from_name_language(PlatformId::Windows, 0x0409),
+            None
+        );
+
+        assert_eq!(
+            family_suffix("Noto Sans CJK JP"),
+            Some(HanVariant::Japanese)
+        );
+        assert_eq!(
+            family_suffix("PingFang TC"),
+            Some(HanVariant::TraditionalChinese)
+        );
+        assert_eq!(family_suffix("Noto Serif CJK KR"), Some(HanVariant::Korean));
+        assert_eq!(family_suffix("Times New Roman"), None);
+    }
+}
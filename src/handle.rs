@@ -10,22 +10,40 @@
 
 //! Encapsulates the information needed to locate and open a font.
 //!
-//! This is either the path to the font or the raw in-memory font data.
+//! This is either the path to the font, the raw in-memory font data, or a URI that an
+//! application-provided resolver turns into one of those. Fonts in collections are selected by
+//! index, or by PostScript name with `Handle::Named`.
 //!
 //! To open the font referenced by a handle, use a loader.
 
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::File;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
+use crate::coverage::UnicodeRangeSet;
 use crate::error::FontLoadingError;
 use crate::font::Font;
+use crate::font_info::{self, FontInfo};
+#[cfg(any(unix, target_family = "windows"))]
+use crate::incremental;
+use crate::loader::Loader;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils;
 
 /// Encapsulates the information needed to locate and open a font.
 ///
-/// This is either the path to the font or the raw in-memory font data.
+/// This is either the path to the font, the raw in-memory font data, or a URI to be resolved by
+/// a `HandleResolver`.
 ///
 /// To open the font referenced by a handle, use a loader.
+///
+/// With the `serde` feature, handles can be serialized. The contents of in-memory fonts are
+/// serialized as bytes, so they should be kept out of serialized data that needs to stay small.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Handle {
     /// A font on disk referenced by a path.
     Path {
@@ -36,15 +54,38 @@ pub enum Handle {
         /// If the path refers to a single font, this value will be 0.
         font_index: u32,
     },
+    /// A font identified by a URI, such as `https://example.com/font.ttf` or
+    /// `appbundle://fonts/font.ttf`.
+    ///
+    /// Loading the font requires a `HandleResolver` to be registered for the URI's scheme.
+    Uri {
+        /// The URI of the font.
+        uri: String,
+        /// The index of the font, if the URI refers to a collection.
+        ///
+        /// If the URI refers to a single font, this value will be 0.
+        font_index: u32,
+    },
     /// A font in memory.
     Memory {
         /// The raw TrueType/OpenType/etc. data that makes up this font.
+        #[cfg_attr(feature = "serde", serde(with = "arc_bytes"))]
         bytes: Arc<Vec<u8>>,
         /// The index of the font, if the memory consists of a collection.
         ///
         /// If the memory consists of a single font, this value will be 0.
         font_index: u32,
     },
+    /// A font in a collection, selected by its PostScript name rather than by its index.
+    ///
+    /// The index is looked up each time the handle is loaded or resolved, so a stored handle keeps
+    /// selecting the same font after the collection is updated and its fonts are reordered.
+    Named {
+        /// The collection, which may be any other kind of handle. Its font index is ignored.
+        collection: Box<Handle>,
+        /// The PostScript name of the font.
+        postscript_name: String,
+    },
 }
 
 impl Handle {
@@ -66,9 +107,450 @@ impl Handle {
         Handle::Memory { bytes, font_index }
     }
 
+    /// Creates a new handle from a URI, to be resolved by the resolver registered for its scheme
+    /// when the font is loaded.
+    ///
+    /// `font_index` specifies the index of the font to choose if the URI refers to a font
+    /// collection. If it refers to a single font, pass 0.
+    #[inline]
+    pub fn from_uri(uri: String, font_index: u32) -> Handle {
+        Handle::Uri { uri, font_index }
+    }
+
+    /// Creates a new handle for the font with the given PostScript name in a collection.
+    ///
+    /// `collection` refers to the collection, and its font index is ignored. The font is looked up
+    /// when the handle is loaded, so the handle stays valid if the collection's fonts are
+    /// reordered.
+    #[inline]
+    pub fn from_postscript_name(collection: Handle, postscript_name: String) -> Handle {
+        Handle::Named {
+            collection: Box::new(collection),
+            postscript_name,
+        }
+    }
+
+    /// Resolves a URI or named handle into a handle that a loader can open. URIs are resolved with
+    /// the resolver registered for their scheme, and named fonts by reading their collection and
+    /// looking up their index. Other handles are returned unchanged.
+    ///
+    /// Resolvers may return another URI handle, which is resolved in turn. If that happens too
+    /// many times, as when resolvers refer to each other in a cycle, this fails with
+    /// `FontLoadingError::ResolutionCycle`. URIs without a scheme fail with
+    /// `FontLoadingError::InvalidUri`. If a collection has no font with the requested PostScript name, this fails with
+    /// `FontLoadingError::NoSuchFontInCollection`.
+    pub fn resolve(&self) -> Result<Handle, FontLoadingError> {
+        let mut handle = self.clone();
+        for _ in 0..MAX_RESOLUTION_DEPTH {
+            let (uri, font_index) = match handle {
+                Handle::Uri {
+                    ref uri,
+                    font_index,
+                } => (uri, font_index),
+                Handle::Named {
+                    ref collection,
+                    ref postscript_name,
+                } => {
+                    return collection
+                        .resolve()?
+                        .select_by_postscript_name(postscript_name)
+                }
+                _ => return Ok(handle),
+            };
+            let scheme =
+                uri_scheme(uri).ok_or_else(|| FontLoadingError::InvalidUri(uri.clone()))?;
+            let resolver = RESOLVERS
+                .read()
+                .unwrap()
+                .get(&scheme.to_ascii_lowercase())
+                .cloned()
+                .ok_or_else(|| FontLoadingError::NoResolver(scheme.to_owned()))?;
+            handle = resolver.resolve(uri, font_index)?;
+        }
+        Err(FontLoadingError::ResolutionCycle)
+    }
+
+    /// Reads a summary of the font: its names, properties, and a few facts about its contents.
+    ///
+    /// This is much cheaper than loading the font, because only a few small tables are read.
+    /// See the `font_info` module.
+    pub fn info(&self) -> Result<FontInfo, FontLoadingError> {
+        self.with_tables(&font_info::INFO_TABLES, FontInfo::from_data)
+    }
+
+    /// Reads the set of characters that the font supports, without loading the font.
+    ///
+    /// Only the `cmap` table is read. The result is the same as the loaded font's `coverage()`.
+    pub fn coverage(&self) -> Result<UnicodeRangeSet, FontLoadingError> {
+        self.with_tables(&font_info::COVERAGE_TABLES, font_info::coverage_from_data)
+    }
+
     /// A convenience method to load this handle with the default loader, producing a Font.
     #[inline]
     pub fn load(&self) -> Result<Font, FontLoadingError> {
         Font::from_handle(self)
     }
+
+    /// Loads this handle with the given loader, such as `loaders::core_text::Font` for native
+    /// rendering on macOS, or `font::Font` for the same results on every platform.
+    #[inline]
+    pub fn load_with<L>(&self) -> Result<L, FontLoadingError>
+    where
+        L: Loader,
+    {
+        L::from_handle(self)
+    }
+}
+
+impl Handle {
+    // Calls `f` with the font data that this handle refers to and the font index.
+    fn with_data<F, R>(&self, f: F) -> Result<R, FontLoadingError>
+    where
+        F: FnOnce(&[u8], u32) -> Result<R, FontLoadingError>,
+    {
+        match *self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Handle::Path {
+                ref path,
+                font_index,
+            } => f(&utils::slurp_file(&mut File::open(path)?)?, font_index),
+            #[cfg(target_arch = "wasm32")]
+            Handle::Path { .. } => Err(FontLoadingError::NoFilesystem),
+            Handle::Memory {
+                ref bytes,
+                font_index,
+            } => f(bytes, font_index),
+            Handle::Uri { .. } | Handle::Named { .. } => self.resolve()?.with_data(f),
+        }
+    }
+
+    // Calls `f` with the font data like `with_data()`, except that only the given tables are read
+    // from files.
+    #[cfg_attr(not(any(unix, target_family = "windows")), allow(unused_variables))]
+    fn with_tables<F, R>(&self, tags: &[u32], f: F) -> Result<R, FontLoadingError>
+    where
+        F: FnOnce(&[u8], u32) -> Result<R, FontLoadingError>,
+    {
+        match *self {
+            #[cfg(any(unix, target_family = "windows"))]
+            Handle::Path {
+                ref path,
+                font_index,
+            } => f(
+                &incremental::read_tables(&File::open(path)?, font_index, tags)?,
+                0,
+            ),
+            _ => self.with_data(f),
+        }
+    }
+
+    // Returns this resolved handle with the index of the font with the given PostScript name.
+    fn select_by_postscript_name(self, postscript_name: &str) -> Result<Handle, FontLoadingError> {
+        let index = self.with_data(|data, _| {
+            let font_count = ttf_parser::fonts_in_collection(data).unwrap_or(1);
+            (0..font_count)
+                .find(|&font_index| {
+                    FontInfo::from_data(data, font_index)
+                        .is_ok_and(|info| info.postscript_name.as_deref() == Some(postscript_name))
+                })
+                .ok_or(FontLoadingError::NoSuchFontInCollection)
+        })?;
+        Ok(match self {
+            Handle::Path { path, .. } => Handle::Path {
+                path,
+                font_index: index,
+            },
+            Handle::Memory { bytes, .. } => Handle::Memory {
+                bytes,
+                font_index: index,
+            },
+            handle @ (Handle::Uri { .. } | Handle::Named { .. }) => handle,
+        })
+    }
+}
+
+// Guards against resolvers that resolve URIs to each other in a cycle.
+const MAX_RESOLUTION_DEPTH: usize = 8;
+
+// Returns the scheme of `uri`: the part before the first colon, which per RFC 3986 is a letter
+// followed by letters, digits, `+`, `-`, or `.`.
+fn uri_scheme(uri: &str) -> Option<&str> {
+    let (scheme, _) = uri.split_once(':')?;
+    let mut chars = scheme.chars();
+    if !chars.next()?.is_ascii_alphabetic() {
+        return None;
+    }
+    if chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')) {
+        Some(scheme)
+    } else {
+        None
+    }
+}
+
+lazy_static! {
+    static ref RESOLVERS: RwLock<HashMap<String, Arc<dyn HandleResolver>>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Turns URI handles into handles that loaders can open.
+///
+/// Applications register resolvers for the URI schemes they use with `register_resolver()`, for
+/// example to fetch fonts over the network, or to look them up in an asset bundle. URI handles
+/// can then be used anywhere other handles can, and are resolved when the font is loaded.
+///
+/// Closures of type `Fn(&str, u32) -> Result<Handle, FontLoadingError>` implement this trait.
+pub trait HandleResolver: Send + Sync {
+    /// Returns a handle for the font at `uri`, typically a path or memory handle.
+    ///
+    /// `font_index` is the index of the font within the resource, and should usually be passed
+    /// through to the returned handle.
+    fn resolve(&self, uri: &str, font_index: u32) -> Result<Handle, FontLoadingError>;
+}
+
+impl<F> HandleResolver for F
+where
+    F: Fn(&str, u32) -> Result<Handle, FontLoadingError> + Send + Sync,
+{
+    #[inline]
+    fn resolve(&self, uri: &str, font_index: u32) -> Result<Handle, FontLoadingError> {
+        self(uri, font_index)
+    }
+}
+
+/// Registers a resolver for URI handles with the given scheme, such as `"https"`, replacing any
+/// previously registered for it.
+///
+/// Schemes are case-insensitive. Resolvers are global to the process.
+pub fn register_resolver(scheme: &str, resolver: Arc<dyn HandleResolver>) {
+    RESOLVERS
+        .write()
+        .unwrap()
+        .insert(scheme.to_ascii_lowercase(), resolver);
+}
+
+/// Removes the resolver for the given scheme, returning it if there was one.
+pub fn unregister_resolver(scheme: &str) -> Option<Arc<dyn HandleResolver>> {
+    RESOLVERS
+        .write()
+        .unwrap()
+        .remove(&scheme.to_ascii_lowercase())
+}
+
+// Serializes font data with `serialize_bytes()`, which binary formats store compactly, rather than
+// as a sequence of integers.
+#[cfg(feature = "serde")]
+mod arc_bytes {
+    use serde::de::{Deserializer, Error, SeqAccess, Visitor};
+    use serde::ser::Serializer;
+    use std::fmt;
+    use std::sync::Arc;
+
+    pub(super) fn serialize<S>(bytes: &Arc<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(bytes)
+    }
+
+    pub(super) fn deserialize<'de, D>(deserializer: D) -> Result<Arc<Vec<u8>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_byte_buf(BytesVisitor)
+            .map(Arc::new)
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("font data bytes")
+        }
+
+        fn visit_bytes<E>(self, bytes: &[u8]) -> Result<Vec<u8>, E>
+        where
+            E: Error,
+        {
+            Ok(bytes.to_vec())
+        }
+
+        fn visit_byte_buf<E>(self, bytes: Vec<u8>) -> Result<Vec<u8>, E>
+        where
+            E: Error,
+        {
+            Ok(bytes)
+        }
+
+        // Self-describing text formats such as JSON represent bytes as a sequence.
+        fn visit_seq<A>(self, mut seq: A) -> Result<Vec<u8>, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{register_resolver, unregister_resolver, Handle};
+    use crate::error::FontLoadingError;
+    use crate::loader::Loader;
+    use crate::properties::Style;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_uri_handles_use_registered_resolver() {
+        let handle = Handle::from_uri("test-fonts://inconsolata".to_owned(), 0);
+        assert!(matches!(
+            handle.load(),
+            Err(FontLoadingError::NoResolver(ref scheme)) if scheme == "test-fonts"
+        ));
+
+        register_resolver(
+            "test-fonts",
+            Arc::new(|uri: &str, font_index| {
+                assert_eq!(uri, "test-fonts://inconsolata");
+                Ok(Handle::from_path(
+                    PathBuf::from("resources/tests/inconsolata/Inconsolata-Regular.ttf"),
+                    font_index,
+                ))
+            }),
+        );
+        let font = handle.load().unwrap();
+        assert_eq!(font.family_name(), "Inconsolata");
+        assert!(unregister_resolver("TEST-FONTS").is_some());
+    }
+
+    #[test]
+    fn test_invalid_and_cyclic_uris() {
+        assert!(matches!(
+            Handle::from_uri("fonts/Inconsolata.ttf".to_owned(), 0).resolve(),
+            Err(FontLoadingError::InvalidUri(ref uri)) if uri == "fonts/Inconsolata.ttf"
+        ));
+        assert!(matches!(
+            Handle::from_uri(":inconsolata".to_owned(), 0).resolve(),
+            Err(FontLoadingError::InvalidUri(_))
+        ));
+
+        register_resolver(
+            "test-cycle",
+            Arc::new(|uri: &str, font_index| Ok(Handle::from_uri(uri.to_owned(), font_index))),
+        );
+        let handle = Handle::from_uri("test-cycle:inconsolata".to_owned(), 0);
+        assert!(matches!(
+            handle.resolve(),
+            Err(FontLoadingError::ResolutionCycle)
+        ));
+        assert!(unregister_resolver("test-cycle").is_some());
+    }
+
+    #[test]
+    fn test_named_handles() {
+        let path = PathBuf::from("resources/tests/eb-garamond/EBGaramond12.otc");
+        let handle = Handle::from_postscript_name(
+            Handle::from_path(path.clone(), 0),
+            "EBGaramond12-Italic".to_owned(),
+        );
+        assert!(matches!(
+            handle.resolve().unwrap(),
+            Handle::Path { font_index: 1, .. }
+        ));
+        assert_eq!(handle.info().unwrap().properties.style, Style::Italic);
+        let font = handle.load().unwrap();
+        assert_eq!(font.postscript_name().unwrap(), "EBGaramond12-Italic");
+
+        let bytes = Arc::new(std::fs::read(&path).unwrap());
+        let handle = Handle::from_postscript_name(
+            Handle::from_memory(bytes, 1),
+            "EBGaramond12-Regular".to_owned(),
+        );
+        assert_eq!(
+            handle.load().unwrap().postscript_name().unwrap(),
+            "EBGaramond12-Regular"
+        );
+
+        let handle = Handle::from_postscript_name(Handle::from_path(path, 0), "Missing".to_owned());
+        assert!(matches!(
+            handle.load(),
+            Err(FontLoadingError::NoSuchFontInCollection)
+        ));
+    }
+
+    #[test]
+    fn test_load_with() {
+        let handle = Handle::from_path(
+            PathBuf::from("resources/tests/inconsolata/Inconsolata-Regular.ttf"),
+            0,
+        );
+        let font = handle.load_with::<crate::loaders::default::Font>().unwrap();
+        assert_eq!(font.family_name(), "Inconsolata");
+        let font = handle
+            .load_with::<crate::loaders::ttf_parser::Font>()
+            .unwrap();
+        assert_eq!(font.postscript_name().unwrap(), "Inconsolata-Regular");
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_test {
+    use super::Handle;
+    use crate::family_name::FamilyName;
+    use crate::properties::{Properties, Stretch, Style, Weight};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    fn round_trip<T>(value: &T) -> T
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_handle_round_trip() {
+        let bytes = Arc::new(vec![0, 1, 0, 0, 255]);
+        match round_trip(&Handle::from_memory(bytes.clone(), 3)) {
+            Handle::Memory {
+                bytes: round_tripped,
+                font_index: 3,
+            } => assert_eq!(round_tripped, bytes),
+            handle => panic!("expected a memory handle, got {:?}", handle),
+        }
+
+        let path = PathBuf::from("resources/tests/eb-garamond/EBGaramond12.otc");
+        match round_trip(&Handle::from_path(path.clone(), 1)) {
+            Handle::Path {
+                path: round_tripped,
+                font_index: 1,
+            } => assert_eq!(round_tripped, path),
+            handle => panic!("expected a path handle, got {:?}", handle),
+        }
+    }
+
+    #[test]
+    fn test_properties_and_family_name_round_trip() {
+        let properties = Properties {
+            style: Style::Oblique,
+            weight: Weight::BOLD,
+            stretch: Stretch::CONDENSED,
+        };
+        assert_eq!(round_trip(&properties), properties);
+
+        for family_name in &[
+            FamilyName::Title("EB Garamond".to_owned()),
+            FamilyName::Serif,
+            FamilyName::Monospace,
+        ] {
+            assert_eq!(&round_trip(family_name), family_name);
+        }
+    }
 }
@@ -12,6 +12,10 @@
 //!
 //! This affects both outlines and rasterization.
 
+use pathfinder_geometry::transform2d::Transform2F;
+
+use crate::units::PpemRounding;
+
 /// Specifies how hinting (grid fitting) is to be performed (or not performed) for a glyph.
 ///
 /// This affects both outlines and rasterization.
@@ -53,4 +57,19 @@ impl HintingOptions {
             | HintingOptions::Full(size) => Some(size),
         }
     }
+
+    /// Returns these options with the grid-fitting size rounded as specified, for glyphs drawn
+    /// with `transform`.
+    ///
+    /// Hinting at whole pixel sizes gives the most consistent stem widths. The size is rounded in
+    /// device pixels, as `PpemRounding::round_point_size()` does.
+    pub fn rounded(self, rounding: PpemRounding, transform: Transform2F) -> HintingOptions {
+        let round = |size| rounding.round_point_size(size, transform);
+        match self {
+            HintingOptions::None => HintingOptions::None,
+            HintingOptions::Vertical(size) => HintingOptions::Vertical(round(size)),
+            HintingOptions::VerticalSubpixel(size) => HintingOptions::VerticalSubpixel(round(size)),
+            HintingOptions::Full(size) => HintingOptions::Full(round(size)),
+        }
+    }
 }
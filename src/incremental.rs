@@ -0,0 +1,518 @@
+// font-kit/src/incremental.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Loading fonts a piece at a time, for fonts too large to keep in memory.
+//!
+//! CJK fonts are often 20 to 40 megabytes, nearly all of it outlines, yet an application may only
+//! ever render a few hundred of their glyphs. `Font::from_reader()` reads a font through a
+//! `ReadAt` implementation, such as a file, an archive, or a network cache, and keeps every table
+//! but the outlines in memory. `glyf` outlines are then read one glyph at a time as they are
+//! needed, and `CFF` outlines, whose glyphs share subroutines, all at once the first time one is
+//! needed. For fonts in local files, `Font::from_path_mmap()` gets the same effect from the
+//! operating system.
+//!
+//! Only outlines and glyph bounds are read on demand. Functions that examine the outline tables
+//! directly, such as `Font::glyph_contours()`, `Font::subset()`, and `Font::validate()`, see a
+//! font without outlines. Variable fonts are read in full, since their outlines depend on other
+//! tables.
+
+use byteorder::{BigEndian, ByteOrder};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::{self, Debug, Formatter};
+use std::io;
+use std::num::NonZeroU16;
+use std::sync::{Arc, Mutex, OnceLock};
+use ttf_parser::head::IndexToLocationFormat;
+use ttf_parser::{Face, GlyphId, OutlineBuilder, Rect};
+
+use crate::error::{FontLoadingError, GlyphLoadingError};
+use crate::subset;
+use crate::utils;
+
+// The tables that are read on demand.
+const GLYF: u32 = u32::from_be_bytes(*b"glyf");
+const CFF: u32 = u32::from_be_bytes(*b"CFF ");
+
+// Outlines in these tables vary with tables that are kept in memory, so their fonts are read in
+// full.
+const VARIATION_TABLES: [u32; 2] = [u32::from_be_bytes(*b"gvar"), u32::from_be_bytes(*b"CFF2")];
+
+const TTC_TAG: u32 = u32::from_be_bytes(*b"ttcf");
+
+// Composite glyphs made of more glyphs than this are treated as malformed.
+const MAX_COMPOSITE_GLYPHS: usize = 256;
+
+/// Data that can be read at arbitrary offsets, such as a file.
+///
+/// Reads may come from several threads at once, so implementations shouldn't depend on a seek
+/// position.
+pub trait ReadAt: Send + Sync {
+    /// Returns the length of the data, in bytes.
+    fn size(&self) -> io::Result<u64>;
+
+    /// Fills `buf` with the data starting at `offset`, failing if the data ends first.
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()>;
+}
+
+impl ReadAt for [u8] {
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        let start = usize::try_from(offset).unwrap_or(usize::MAX);
+        let data = start
+            .checked_add(buf.len())
+            .and_then(|end| self.get(start..end))
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        buf.copy_from_slice(data);
+        Ok(())
+    }
+}
+
+impl ReadAt for Vec<u8> {
+    #[inline]
+    fn size(&self) -> io::Result<u64> {
+        self.as_slice().size()
+    }
+
+    #[inline]
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        self.as_slice().read_exact_at(buf, offset)
+    }
+}
+
+impl<R> ReadAt for Arc<R>
+where
+    R: ReadAt + ?Sized,
+{
+    #[inline]
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+
+    #[inline]
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        (**self).read_exact_at(buf, offset)
+    }
+}
+
+#[cfg(any(unix, target_family = "windows"))]
+impl ReadAt for std::fs::File {
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+
+    #[cfg(unix)]
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        std::os::unix::fs::FileExt::read_exact_at(self, buf, offset)
+    }
+
+    #[cfg(target_family = "windows")]
+    fn read_exact_at(&self, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+        use std::os::windows::fs::FileExt;
+
+        while !buf.is_empty() {
+            match self.seek_read(buf, offset) {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+                Ok(read) => {
+                    buf = &mut buf[read..];
+                    offset += read as u64;
+                }
+                Err(ref error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The outline tables of a font loaded with `Font::from_reader()`, read as they are needed.
+pub(crate) struct LazyOutlines {
+    reader: Box<dyn ReadAt>,
+    size: u64,
+    // The table records of the font, for `copy_font_data()`.
+    tables: Vec<TableRecord>,
+    glyf: Option<TableRecord>,
+    cff: Option<TableRecord>,
+    glyph_data: Mutex<HashMap<u16, Arc<[u8]>>>,
+    cff_data: OnceLock<Box<[u8]>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct TableRecord {
+    tag: u32,
+    offset: u64,
+    len: u32,
+}
+
+/// Reads every table of the font at `font_index` except its outlines, and returns them as a
+/// standalone font, along with a way to read the outlines later. Fonts whose outlines can't be
+/// read on demand are returned whole, with no `LazyOutlines`.
+pub(crate) fn load(
+    reader: Box<dyn ReadAt>,
+    font_index: u32,
+) -> Result<(Vec<u8>, Option<LazyOutlines>), FontLoadingError> {
+    let size = reader.size()?;
+    let tables = table_records(&*reader, size, font_index)?;
+
+    let find = |tag| tables.iter().find(|table| table.tag == tag).copied();
+    let (glyf, cff) = (find(GLYF), find(CFF));
+    let variable = tables
+        .iter()
+        .any(|table| VARIATION_TABLES.contains(&table.tag));
+    if variable || (glyf.is_none() && cff.is_none()) {
+        let font_data = build_font(&*reader, size, tables.iter())?;
+        return Ok((font_data, None));
+    }
+
+    let kept = tables
+        .iter()
+        .filter(|table| table.tag != GLYF && table.tag != CFF);
+    let font_data = build_font(&*reader, size, kept)?;
+    let lazy_outlines = LazyOutlines {
+        reader,
+        size,
+        tables,
+        glyf,
+        cff,
+        glyph_data: Mutex::new(HashMap::new()),
+        cff_data: OnceLock::new(),
+    };
+    Ok((font_data, Some(lazy_outlines)))
+}
+
+/// Reads the tables with the given tags from the font at `font_index`, and returns them as a
+/// standalone font, without reading the rest of the font.
+///
+/// The font's other tables are included but empty, so that whether the font has them can still be
+/// checked.
+#[cfg(any(unix, target_family = "windows", test))]
+pub(crate) fn read_tables(
+    reader: &dyn ReadAt,
+    font_index: u32,
+    tags: &[u32],
+) -> Result<Vec<u8>, FontLoadingError> {
+    let size = reader.size()?;
+    let tables = table_records(reader, size, font_index)?
+        .iter()
+        .map(|table| {
+            let len = if tags.contains(&table.tag) {
+                table.len
+            } else {
+                0
+            };
+            Ok((table.tag, read(reader, size, table.offset, len as u64)?))
+        })
+        .collect::<Result<Vec<_>, FontLoadingError>>()?;
+    Ok(utils::build_sfnt(&tables))
+}
+
+// Reads the table directory of the font at `font_index`.
+fn table_records(
+    reader: &dyn ReadAt,
+    size: u64,
+    font_index: u32,
+) -> Result<Vec<TableRecord>, FontLoadingError> {
+    let header = read(reader, size, 0, 12)?;
+    let font_offset = if BigEndian::read_u32(&header) == TTC_TAG {
+        let font_count = BigEndian::read_u32(&header[8..]);
+        if font_index >= font_count {
+            return Err(FontLoadingError::NoSuchFontInCollection);
+        }
+        let offset = read(reader, size, 12 + 4 * font_index as u64, 4)?;
+        BigEndian::read_u32(&offset) as u64
+    } else if font_index > 0 {
+        return Err(FontLoadingError::NoSuchFontInCollection);
+    } else {
+        0
+    };
+
+    let offset_table = read(reader, size, font_offset, 12)?;
+    let table_count = BigEndian::read_u16(&offset_table[4..]) as u64;
+    let records = read(reader, size, font_offset + 12, 16 * table_count)?;
+    Ok(records
+        .chunks_exact(16)
+        .map(|record| TableRecord {
+            tag: BigEndian::read_u32(record),
+            offset: BigEndian::read_u32(&record[8..]) as u64,
+            len: BigEndian::read_u32(&record[12..]),
+        })
+        .collect())
+}
+
+// Reads the given tables into a standalone font.
+fn build_font<'a, I>(reader: &dyn ReadAt, size: u64, tables: I) -> Result<Vec<u8>, FontLoadingError>
+where
+    I: Iterator<Item = &'a TableRecord>,
+{
+    let tables = tables
+        .map(|table| {
+            Ok((
+                table.tag,
+                read(reader, size, table.offset, table.len as u64)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, FontLoadingError>>()?;
+    Ok(utils::build_sfnt(&tables))
+}
+
+impl LazyOutlines {
+    /// Sends the outline of a glyph to `builder`, reading it if it hasn't been read yet, and
+    /// returns its bounds. Glyphs without an outline return `None`.
+    pub(crate) fn outline(
+        &self,
+        face: &Face,
+        glyph_id: GlyphId,
+        builder: &mut dyn OutlineBuilder,
+    ) -> Result<Option<Rect>, GlyphLoadingError> {
+        if self.glyf.is_some() {
+            self.glyf_outline(face, glyph_id, builder)
+        } else if let Some(table) = self.cff {
+            let cff = match self.cff_data.get() {
+                Some(cff) => cff,
+                None => {
+                    let cff = self.read(table.offset, table.len as u64)?;
+                    self.cff_data.get_or_init(|| cff.into_boxed_slice())
+                }
+            };
+            let table = ttf_parser::cff::Table::parse(cff).ok_or(GlyphLoadingError::Parse)?;
+            Ok(table.outline(glyph_id, builder).ok())
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the bounds of a glyph's outline, reading it if it hasn't been read yet.
+    pub(crate) fn bounding_box(
+        &self,
+        face: &Face,
+        glyph_id: GlyphId,
+    ) -> Result<Option<Rect>, GlyphLoadingError> {
+        self.outline(face, glyph_id, &mut DiscardOutline)
+    }
+
+    /// Reads the whole font, outlines included, as a standalone font.
+    pub(crate) fn read_all(&self) -> Option<Vec<u8>> {
+        build_font(&*self.reader, self.size, self.tables.iter()).ok()
+    }
+
+    // Builds a `glyf` table out of the glyph and any glyphs it is composed of, renumbered from 0
+    // in the order they are found, and outlines glyph 0 of that.
+    fn glyf_outline(
+        &self,
+        face: &Face,
+        glyph_id: GlyphId,
+        builder: &mut dyn OutlineBuilder,
+    ) -> Result<Option<Rect>, GlyphLoadingError> {
+        let mut glyph_ids = vec![glyph_id.0];
+        let mut glyph_data = vec![];
+        while let Some(&glyph_id) = glyph_ids.get(glyph_data.len()) {
+            let mut data = self.glyph_data(face, glyph_id)?.to_vec();
+            let components =
+                subset::composite_components(&data).map_err(|_| GlyphLoadingError::Parse)?;
+            for (offset, component) in components {
+                let index = match glyph_ids.iter().position(|&id| id == component) {
+                    Some(index) => index,
+                    None if glyph_ids.len() < MAX_COMPOSITE_GLYPHS => {
+                        glyph_ids.push(component);
+                        glyph_ids.len() - 1
+                    }
+                    None => return Err(GlyphLoadingError::Parse),
+                };
+                BigEndian::write_u16(&mut data[offset..], index as u16);
+            }
+            glyph_data.push(data);
+        }
+
+        let mut loca = Vec::with_capacity(4 * (glyph_data.len() + 1));
+        let mut glyf = vec![];
+        for data in &glyph_data {
+            loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+            glyf.extend_from_slice(data);
+            glyf.resize((glyf.len() + 3) & !3, 0);
+        }
+        loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+
+        let glyph_count = NonZeroU16::new(glyph_data.len() as u16).unwrap();
+        let loca = ttf_parser::loca::Table::parse(glyph_count, IndexToLocationFormat::Long, &loca)
+            .ok_or(GlyphLoadingError::Parse)?;
+        let glyf = ttf_parser::glyf::Table::parse(loca, &glyf).ok_or(GlyphLoadingError::Parse)?;
+        Ok(glyf.outline(GlyphId(0), builder))
+    }
+
+    // Returns the data of one glyph in the `glyf` table, reading it if it hasn't been read yet.
+    fn glyph_data(&self, face: &Face, glyph_id: u16) -> Result<Arc<[u8]>, GlyphLoadingError> {
+        if let Some(data) = self.glyph_data.lock().unwrap().get(&glyph_id) {
+            return Ok(Arc::clone(data));
+        }
+        let glyf = self.glyf.ok_or(GlyphLoadingError::Parse)?;
+        let loca = face
+            .raw_face()
+            .table(ttf_parser::Tag::from_bytes(b"loca"))
+            .and_then(|loca| {
+                let tables = face.tables();
+                ttf_parser::loca::Table::parse(
+                    tables.maxp.number_of_glyphs,
+                    tables.head.index_to_location_format,
+                    loca,
+                )
+            })
+            .ok_or(GlyphLoadingError::Parse)?;
+        let data: Arc<[u8]> = match loca.glyph_range(GlyphId(glyph_id)) {
+            Some(range) if range.end <= glyf.len as usize => self
+                .read(glyf.offset + range.start as u64, range.len() as u64)?
+                .into(),
+            Some(_) => return Err(GlyphLoadingError::Parse),
+            // Empty glyphs, such as spaces, have no data.
+            None => Arc::new([]),
+        };
+        self.glyph_data
+            .lock()
+            .unwrap()
+            .insert(glyph_id, Arc::clone(&data));
+        Ok(data)
+    }
+
+    fn read(&self, offset: u64, len: u64) -> Result<Vec<u8>, GlyphLoadingError> {
+        read(&*self.reader, self.size, offset, len).map_err(|_| GlyphLoadingError::Io)
+    }
+}
+
+impl Debug for LazyOutlines {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("LazyOutlines")
+            .field("glyf", &self.glyf)
+            .field("cff", &self.cff)
+            .finish_non_exhaustive()
+    }
+}
+
+// Reads `len` bytes at `offset`, checking them against the size of the data first so that a
+// malformed length can't make us allocate more than the font holds.
+fn read(
+    reader: &dyn ReadAt,
+    size: u64,
+    offset: u64,
+    len: u64,
+) -> Result<Vec<u8>, FontLoadingError> {
+    if offset.checked_add(len).is_none_or(|end| end > size) {
+        return Err(FontLoadingError::Truncated {
+            offset: size as usize,
+        });
+    }
+    let mut data = vec![0; len as usize];
+    reader.read_exact_at(&mut data, offset)?;
+    Ok(data)
+}
+
+struct DiscardOutline;
+
+impl OutlineBuilder for DiscardOutline {
+    fn move_to(&mut self, _: f32, _: f32) {}
+    fn line_to(&mut self, _: f32, _: f32) {}
+    fn quad_to(&mut self, _: f32, _: f32, _: f32, _: f32) {}
+    fn curve_to(&mut self, _: f32, _: f32, _: f32, _: f32, _: f32, _: f32) {}
+    fn close(&mut self) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_tables, ReadAt};
+    use crate::font::Font;
+    use crate::font_info::{self, FontInfo};
+    use crate::loader::Loader;
+    use crate::test_fixtures::{ARIAL, EB_GARAMOND_OTC};
+    use std::io;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    // Counts the bytes read from a font.
+    struct CountingReader {
+        data: &'static [u8],
+        bytes_read: Arc<AtomicU64>,
+    }
+
+    impl ReadAt for CountingReader {
+        fn size(&self) -> io::Result<u64> {
+            self.data.size()
+        }
+
+        fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+            self.bytes_read
+                .fetch_add(buf.len() as u64, Ordering::Relaxed);
+            self.data.read_exact_at(buf, offset)
+        }
+    }
+
+    fn check_lazy_font(data: &'static [u8], font_index: u32, text: &str) {
+        let bytes_read = Arc::new(AtomicU64::new(0));
+        let reader = CountingReader {
+            data,
+            bytes_read: Arc::clone(&bytes_read),
+        };
+        let font = Font::from_reader(reader, font_index).unwrap();
+        let loaded = bytes_read.load(Ordering::Relaxed);
+        let expected = Font::from_bytes(Arc::new(data.to_vec()), font_index).unwrap();
+        assert_eq!(font.postscript_name(), expected.postscript_name());
+
+        for character in text.chars() {
+            let glyph_id = font.glyph_for_char(character).unwrap();
+            assert_eq!(glyph_id, expected.glyph_for_char(character).unwrap());
+            assert_eq!(
+                font.glyph_path(glyph_id).unwrap(),
+                expected.glyph_path(glyph_id).unwrap()
+            );
+            // Spaces have no bounds, either way.
+            assert_eq!(
+                font.typographic_bounds(glyph_id),
+                expected.typographic_bounds(glyph_id)
+            );
+        }
+        assert!(bytes_read.load(Ordering::Relaxed) < data.len() as u64);
+        assert!(loaded < bytes_read.load(Ordering::Relaxed));
+
+        // The outlines are read back in, into a standalone font.
+        let copy = Font::from_bytes(font.copy_font_data().unwrap(), 0).unwrap();
+        let glyph_id = copy.glyph_for_char('H').unwrap();
+        assert_eq!(
+            copy.glyph_path(glyph_id).unwrap(),
+            expected.glyph_path(glyph_id).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_reader() {
+        // A TrueType font, whose glyphs are read one at a time. "é" is a composite glyph.
+        check_lazy_font(ARIAL, 0, "Hé, ");
+        // A CFF font in a collection.
+        check_lazy_font(EB_GARAMOND_OTC, 1, "Hé, ");
+
+        assert!(Font::from_reader(ARIAL.to_vec(), 1).is_err());
+        assert!(Font::from_reader(ARIAL[..1000].to_vec(), 0).is_err());
+    }
+
+    #[test]
+    fn test_read_tables() {
+        let bytes_read = Arc::new(AtomicU64::new(0));
+        let reader = CountingReader {
+            data: EB_GARAMOND_OTC,
+            bytes_read: Arc::clone(&bytes_read),
+        };
+        let font_data = read_tables(&reader, 1, &font_info::INFO_TABLES).unwrap();
+        assert!(bytes_read.load(Ordering::Relaxed) < EB_GARAMOND_OTC.len() as u64 / 10);
+        assert_eq!(
+            FontInfo::from_data(&font_data, 0).unwrap(),
+            FontInfo::from_data(EB_GARAMOND_OTC, 1).unwrap()
+        );
+    }
+}
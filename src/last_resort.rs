@@ -0,0 +1,401 @@
+// font-kit/src/last_resort.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A built-in font of last resort, which supports every character by drawing it as a box.
+//!
+//! When no installed font supports a character, text renderers draw a box (often called "tofu")
+//! so that the reader can see that something is missing. This font provides that box for every
+//! Unicode character except surrogates, and a blank glyph for whitespace. Like Unicode's Last
+//! Resort font, it maps all of Unicode with a few `cmap` format 13 groups, so it is about a
+//! kilobyte, and it is built when it is first used rather than shipped as a file. Its `.notdef`
+//! glyph is the same box.
+//!
+//! With the `last-resort` feature, `Loader::get_fallbacks()` returns this font as the last
+//! fallback, so that callers always have something to render.
+
+use lazy_static::lazy_static;
+use std::sync::Arc;
+
+use crate::error::FontLoadingError;
+use crate::loader::{FallbackFont, FallbackResult, Loader};
+use crate::utils;
+
+/// The family name of the last-resort font.
+pub const FAMILY_NAME: &str = "font-kit Last Resort";
+/// The PostScript name of the last-resort font.
+pub const POSTSCRIPT_NAME: &str = "FontKitLastResort";
+
+lazy_static! {
+    static ref FONT_DATA: Arc<Vec<u8>> = Arc::new(build_font());
+}
+
+const UNITS_PER_EM: u16 = 1000;
+const ASCENDER: i16 = 800;
+const DESCENDER: i16 = -200;
+const ADVANCE: u16 = 600;
+
+// The glyphs: `.notdef`, the box that every other character maps to, and a blank for whitespace.
+const BOX_GLYPH: u32 = 1;
+const SPACE_GLYPH: u32 = 2;
+const GLYPH_COUNT: u16 = 3;
+
+// The box, as an outer contour and a counter. TrueType outer contours run clockwise.
+const BOX_CONTOURS: [[(i16, i16); 4]; 2] = [
+    [(50, 0), (50, 700), (550, 700), (550, 0)],
+    [(100, 50), (500, 50), (500, 650), (100, 650)],
+];
+
+/// Returns the data of the last-resort font.
+///
+/// The data is built the first time this is called and shared afterward.
+#[inline]
+pub fn font_data() -> Arc<Vec<u8>> {
+    FONT_DATA.clone()
+}
+
+/// Loads the last-resort font with the given loader.
+pub fn font<F>() -> Result<F, FontLoadingError>
+where
+    F: Loader,
+{
+    F::from_bytes(font_data(), 0)
+}
+
+/// Appends the last-resort font to a fallback result, unless the loader can't load it.
+pub(crate) fn append<F>(mut fallbacks: FallbackResult<F>) -> FallbackResult<F>
+where
+    F: Loader,
+{
+    if let Ok(font) = font() {
+        fallbacks.fonts.push(FallbackFont { font, scale: 1.0 })
+    }
+    fallbacks
+}
+
+fn build_font() -> Vec<u8> {
+    let mut font_data = utils::build_sfnt(&[
+        (tag(b"OS/2"), build_os2()),
+        (tag(b"cmap"), build_cmap()),
+        (tag(b"glyf"), build_glyf()),
+        (tag(b"head"), build_head()),
+        (tag(b"hhea"), build_hhea()),
+        (tag(b"hmtx"), build_hmtx()),
+        (tag(b"loca"), build_loca()),
+        (tag(b"maxp"), build_maxp()),
+        (tag(b"name"), build_name()),
+        (tag(b"post"), build_post()),
+    ]);
+    utils::set_checksum_adjustment(&mut font_data);
+    font_data
+}
+
+// Returns the ranges of characters that map to each glyph, in order, as `cmap` format 13 groups.
+fn character_groups() -> Vec<(u32, u32, u32)> {
+    let mut groups: Vec<(u32, u32, u32)> = vec![];
+    for character in (0..=0x10ffff).filter_map(char::from_u32) {
+        let codepoint = character as u32;
+        let glyph = if character.is_whitespace() {
+            SPACE_GLYPH
+        } else {
+            BOX_GLYPH
+        };
+        match groups.last_mut() {
+            Some(&mut (_, ref mut end, last_glyph))
+                if last_glyph == glyph && *end + 1 == codepoint =>
+            {
+                *end = codepoint
+            }
+            _ => groups.push((codepoint, codepoint, glyph)),
+        }
+    }
+    groups
+}
+
+fn build_cmap() -> Vec<u8> {
+    let groups = character_groups();
+    let mut data = vec![];
+    push_u16(&mut data, 0);
+    push_u16(&mut data, 1);
+    // One subtable, for the Windows platform's full Unicode encoding.
+    push_u16(&mut data, 3);
+    push_u16(&mut data, 10);
+    push_u32(&mut data, 12);
+    push_u16(&mut data, 13);
+    push_u16(&mut data, 0);
+    push_u32(&mut data, 16 + groups.len() as u32 * 12);
+    push_u32(&mut data, 0);
+    push_u32(&mut data, groups.len() as u32);
+    for &(start, end, glyph) in &groups {
+        push_u32(&mut data, start);
+        push_u32(&mut data, end);
+        push_u32(&mut data, glyph);
+    }
+    data
+}
+
+fn build_box_glyph() -> Vec<u8> {
+    let mut data = vec![];
+    push_u16(&mut data, BOX_CONTOURS.len() as u16);
+    for bound in [50, 0, 550, 700] {
+        push_u16(&mut data, bound as u16);
+    }
+    push_u16(&mut data, 3);
+    push_u16(&mut data, 7);
+    // No instructions.
+    push_u16(&mut data, 0);
+    // Every point is on the curve, with 16-bit coordinates.
+    data.extend_from_slice(&[0x01; 8]);
+    let points = BOX_CONTOURS.iter().flatten();
+    for coordinate in [0, 1] {
+        let mut last = 0;
+        for point in points.clone() {
+            let value = if coordinate == 0 { point.0 } else { point.1 };
+            push_u16(&mut data, (value - last) as u16);
+            last = value;
+        }
+    }
+    data.resize((data.len() + 3) & !3, 0);
+    data
+}
+
+fn build_glyf() -> Vec<u8> {
+    let box_glyph = build_box_glyph();
+    let mut data = box_glyph.clone();
+    data.extend_from_slice(&box_glyph);
+    data
+}
+
+fn build_loca() -> Vec<u8> {
+    let box_glyph_len = build_box_glyph().len() as u32;
+    let mut data = vec![];
+    for offset in [0, box_glyph_len, box_glyph_len * 2, box_glyph_len * 2] {
+        push_u32(&mut data, offset);
+    }
+    data
+}
+
+fn build_head() -> Vec<u8> {
+    let mut data = vec![];
+    push_u32(&mut data, 0x0001_0000);
+    push_u32(&mut data, 0x0001_0000);
+    // The checksum adjustment, which `set_checksum_adjustment()` fills in.
+    push_u32(&mut data, 0);
+    push_u32(&mut data, 0x5f0f_3cf5);
+    // Baseline and left sidebearing at zero, and integer scaling.
+    push_u16(&mut data, 0x000b);
+    push_u16(&mut data, UNITS_PER_EM);
+    // Creation and modification dates.
+    data.extend_from_slice(&[0; 16]);
+    for bound in [50, 0, 550, 700] {
+        push_u16(&mut data, bound as u16);
+    }
+    // Mac style, smallest readable size, and font direction hint.
+    push_u16(&mut data, 0);
+    push_u16(&mut data, 8);
+    push_u16(&mut data, 2);
+    // Long `loca` offsets, and the current glyph data format.
+    push_u16(&mut data, 1);
+    push_u16(&mut data, 0);
+    data
+}
+
+fn build_hhea() -> Vec<u8> {
+    let mut data = vec![];
+    push_u32(&mut data, 0x0001_0000);
+    push_u16(&mut data, ASCENDER as u16);
+    push_u16(&mut data, DESCENDER as u16);
+    push_u16(&mut data, 0);
+    push_u16(&mut data, ADVANCE);
+    // Minimum left and right sidebearings, and maximum extent.
+    push_u16(&mut data, 0);
+    push_u16(&mut data, 50);
+    push_u16(&mut data, 550);
+    // An upright caret.
+    push_u16(&mut data, 1);
+    push_u16(&mut data, 0);
+    push_u16(&mut data, 0);
+    data.extend_from_slice(&[0; 10]);
+    push_u16(&mut data, GLYPH_COUNT);
+    data
+}
+
+fn build_hmtx() -> Vec<u8> {
+    let mut data = vec![];
+    for left_side_bearing in [50, 50, 0] {
+        push_u16(&mut data, ADVANCE);
+        push_u16(&mut data, left_side_bearing);
+    }
+    data
+}
+
+fn build_maxp() -> Vec<u8> {
+    let mut data = vec![];
+    push_u32(&mut data, 0x0001_0000);
+    push_u16(&mut data, GLYPH_COUNT);
+    // Points and contours in simple and composite glyphs.
+    push_u16(&mut data, 8);
+    push_u16(&mut data, 2);
+    push_u16(&mut data, 0);
+    push_u16(&mut data, 0);
+    // Two zones, and no hinting resources.
+    push_u16(&mut data, 2);
+    data.extend_from_slice(&[0; 16]);
+    data
+}
+
+fn build_name() -> Vec<u8> {
+    let full_name = format!("{} Regular", FAMILY_NAME);
+    let names: [(u16, &str); 6] = [
+        (1, FAMILY_NAME),
+        (2, "Regular"),
+        (3, POSTSCRIPT_NAME),
+        (4, &full_name),
+        (5, "Version 1.0"),
+        (6, POSTSCRIPT_NAME),
+    ];
+    let strings: Vec<Vec<u8>> = names
+        .iter()
+        .map(|&(_, name)| {
+            name.encode_utf16()
+                .flat_map(|code_unit| code_unit.to_be_bytes())
+                .collect()
+        })
+        .collect();
+
+    let mut data = vec![];
+    push_u16(&mut data, 0);
+    push_u16(&mut data, names.len() as u16);
+    push_u16(&mut data, 6 + names.len() as u16 * 12);
+    let mut offset = 0;
+    for (&(name_id, _), string) in names.iter().zip(&strings) {
+        // Windows, Unicode BMP, US English.
+        push_u16(&mut data, 3);
+        push_u16(&mut data, 1);
+        push_u16(&mut data, 0x0409);
+        push_u16(&mut data, name_id);
+        push_u16(&mut data, string.len() as u16);
+        push_u16(&mut data, offset);
+        offset += string.len() as u16;
+    }
+    for string in &strings {
+        data.extend_from_slice(string);
+    }
+    data
+}
+
+fn build_os2() -> Vec<u8> {
+    let mut data = vec![];
+    push_u16(&mut data, 4);
+    push_u16(&mut data, ADVANCE);
+    // Regular weight, normal width, and installable embedding.
+    push_u16(&mut data, 400);
+    push_u16(&mut data, 5);
+    push_u16(&mut data, 0);
+    // Subscript and superscript sizes and offsets.
+    for value in [650, 600, 0, 75, 650, 600, 0, 350] {
+        push_u16(&mut data, value);
+    }
+    // Strikeout size and position, and family class.
+    push_u16(&mut data, 50);
+    push_u16(&mut data, 300);
+    push_u16(&mut data, 0);
+    // PANOSE, Unicode ranges, and vendor ID.
+    data.extend_from_slice(&[0; 10]);
+    data.extend_from_slice(&[0; 16]);
+    data.extend_from_slice(b"NONE");
+    // Regular, and the first and last characters in the BMP.
+    push_u16(&mut data, 0x0040);
+    push_u16(&mut data, 0);
+    push_u16(&mut data, 0xffff);
+    push_u16(&mut data, ASCENDER as u16);
+    push_u16(&mut data, DESCENDER as u16);
+    push_u16(&mut data, 0);
+    push_u16(&mut data, ASCENDER as u16);
+    push_u16(&mut data, -DESCENDER as u16);
+    // Code page ranges.
+    data.extend_from_slice(&[0; 8]);
+    // x-height, cap height, default and break characters, and maximum context.
+    push_u16(&mut data, 500);
+    push_u16(&mut data, 700);
+    push_u16(&mut data, 0);
+    push_u16(&mut data, 0x20);
+    push_u16(&mut data, 0);
+    data
+}
+
+fn build_post() -> Vec<u8> {
+    let mut data = vec![];
+    // Version 3, which has no glyph names.
+    push_u32(&mut data, 0x0003_0000);
+    push_u32(&mut data, 0);
+    push_u16(&mut data, (-100i16) as u16);
+    push_u16(&mut data, 50);
+    // Fixed pitch, and no memory usage hints.
+    push_u32(&mut data, 1);
+    data.extend_from_slice(&[0; 16]);
+    data
+}
+
+#[inline]
+fn tag(name: &[u8; 4]) -> u32 {
+    u32::from_be_bytes(*name)
+}
+
+#[inline]
+fn push_u16(data: &mut Vec<u8>, value: u16) {
+    data.extend_from_slice(&value.to_be_bytes());
+}
+
+#[inline]
+fn push_u32(data: &mut Vec<u8>, value: u32) {
+    data.extend_from_slice(&value.to_be_bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use super::{font, font_data, FAMILY_NAME, POSTSCRIPT_NAME};
+    use crate::font::Font;
+    use crate::hinting::HintingOptions;
+    use crate::loader::Loader;
+    use crate::outline::OutlineBuilder;
+    use crate::test_fixtures::INCONSOLATA;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_last_resort_font() {
+        assert!(font_data().len() < 2048);
+        let font: Font = font().unwrap();
+        assert_eq!(font.family_name(), FAMILY_NAME);
+        assert_eq!(font.postscript_name().unwrap(), POSTSCRIPT_NAME);
+
+        for character in ['A', '中', '🙂', '\u{d7ff}', '\u{e000}', '\u{10ffff}'] {
+            assert_eq!(font.glyph_for_char(character), Some(1));
+        }
+        for character in [' ', '\t', '\u{3000}'] {
+            assert_eq!(font.glyph_for_char(character), Some(2));
+        }
+        assert_eq!(font.typographic_bounds(1).unwrap().size().x(), 500.0);
+        assert_eq!(font.advance(2).unwrap().x(), 600.0);
+
+        for (glyph_id, contour_count) in [(0, 2), (1, 2), (2, 0)] {
+            let mut builder = OutlineBuilder::new();
+            font.outline(glyph_id, HintingOptions::None, &mut builder)
+                .unwrap();
+            assert_eq!(builder.into_outline().contours.len(), contour_count);
+        }
+
+        let inconsolata = Font::from_bytes(Arc::new(INCONSOLATA.to_vec()), 0).unwrap();
+        for text in ["abc", "\u{10fffd}"] {
+            let fallbacks = inconsolata.get_fallbacks(text, "en-US");
+            let last_resort = &fallbacks.fonts.last().unwrap().font;
+            assert_eq!(last_resort.postscript_name().unwrap(), POSTSCRIPT_NAME);
+        }
+    }
+}
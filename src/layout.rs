@@ -0,0 +1,728 @@
+// font-kit/src/layout.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Simple single-line text layout.
+//!
+//! `Font::layout_run()` maps each character to a glyph through the `cmap` table and positions the
+//! glyphs using their advances and the font's kerning. No substitutions are applied and the text
+//! is laid out left to right, which is enough for labels, debug overlays, and similar text in
+//! scripts that don't need shaping. Enable the `shaping` feature for ligatures and complex scripts.
+//!
+//! `Font::wrap_text()` additionally breaks text into lines that fit a given width. Lines are
+//! broken at a simplified subset of the Unicode line breaking algorithm (UAX #14): after spaces
+//! and hyphens, around ideographs, and always at newlines and other mandatory breaks. Words too
+//! long to fit on a line by themselves are broken between characters.
+//!
+//! `bidi_runs()` splits mixed left-to-right and right-to-left text, such as English and Hebrew or
+//! Arabic, into runs of a single direction using the Unicode bidirectional algorithm (UAX #9), and
+//! `reorder_runs()` puts the runs of a line into visual order. Each run can then be
+//! handed to a shaper, or `Font::layout_bidi()` can lay out a whole line with the basic layouter.
+//!
+//! `Font::glyph_class()` tells base glyphs, ligatures, and marks apart, and `Font::mark_anchor()`
+//! reads where a combining mark attaches to a base glyph or to another mark from the font's `GPOS`
+//! mark attachment lookups, for renderers that place diacritics themselves.
+
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::vector::Vector2F;
+use ttf_parser::gpos::{Anchor, MarkArray, PairAdjustment, PositioningSubtable};
+use ttf_parser::opentype_layout::Coverage;
+use ttf_parser::{Face, GlyphId};
+
+use std::ops::Range;
+use unicode_bidi::{BidiInfo, Level};
+
+use crate::metrics::Metrics;
+use crate::substitution::{self, ClusterGlyph};
+
+/// A glyph positioned within a run of text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionedGlyph {
+    /// The glyph ID.
+    pub glyph_id: u32,
+    /// The byte offset in the text of the first character that this glyph represents.
+    ///
+    /// Glyphs of a ligature share the cluster of its first character.
+    pub cluster: u32,
+    /// The pen position before this glyph, relative to the start of the run on the baseline.
+    ///
+    /// The glyph's origin is at `position + offset`.
+    pub position: Vector2F,
+    /// How far to move the pen after drawing this glyph.
+    pub advance: Vector2F,
+    /// How far to displace this glyph from the pen position, without moving the pen.
+    pub offset: Vector2F,
+}
+
+/// Text broken into lines by `Font::wrap_text()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WrappedText {
+    /// The lines, from top to bottom. There is always at least one line, even for empty text.
+    pub lines: Vec<WrappedLine>,
+    /// The height of all the lines together, which is the number of lines times the line height
+    /// (ascent plus descent plus line gap).
+    pub height: f32,
+}
+
+/// One line of wrapped text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WrappedLine {
+    /// The byte range of the text that this line consumes, including trailing spaces and the line
+    /// break, if any. The ranges of consecutive lines are adjacent.
+    pub range: Range<usize>,
+    /// The glyphs of the line, excluding trailing spaces and the line break. Positions are
+    /// relative to the start of the line on its baseline, with the y axis pointing up.
+    pub glyphs: Vec<PositionedGlyph>,
+    /// The width of the line's glyphs, not counting trailing spaces.
+    pub width: f32,
+    /// The distance down from the top of the text to this line's baseline.
+    pub baseline: f32,
+}
+
+/// The direction in which a run of text is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextDirection {
+    /// Left to right, as in Latin or Cyrillic text.
+    LeftToRight,
+    /// Right to left, as in Hebrew or Arabic text.
+    RightToLeft,
+}
+
+/// The role of a glyph in layout, from the glyph class definitions in a font's `GDEF` table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GlyphClass {
+    /// The font doesn't classify the glyph, or has no `GDEF` glyph classes at all.
+    Unclassified,
+    /// A spacing glyph that marks can attach to, such as a letter.
+    Base,
+    /// A spacing glyph that stands for several characters, such as "ffi".
+    Ligature,
+    /// A combining mark, such as an accent, which has no advance of its own and belongs to the
+    /// cluster of the glyph before it.
+    Mark,
+    /// Part of a character that is drawn with several glyphs.
+    Component,
+}
+
+/// A run of text with a single resolved embedding level, produced by `bidi_runs()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BidiRun {
+    /// The byte range of the run in the text.
+    pub range: Range<usize>,
+    /// The resolved embedding level. Even levels are left to right and odd levels are right to
+    /// left; higher levels are nested inside lower ones, as numbers are inside Arabic text.
+    pub level: u8,
+}
+
+impl BidiRun {
+    /// Returns the direction of the run, which follows from its level.
+    #[inline]
+    pub fn direction(&self) -> TextDirection {
+        if self.level & 1 == 0 {
+            TextDirection::LeftToRight
+        } else {
+            TextDirection::RightToLeft
+        }
+    }
+}
+
+/// Splits text into runs that each have a single direction, in logical order.
+///
+/// Each paragraph of the text, as ended by a newline or paragraph separator, is resolved on its
+/// own with the Unicode bidirectional algorithm, including explicit embeddings, overrides, and
+/// isolates. Its base direction is `base_direction` if given, or else the direction of its first
+/// strongly directional character, defaulting to left to right.
+pub fn bidi_runs(text: &str, base_direction: Option<TextDirection>) -> Vec<BidiRun> {
+    let paragraph_level = base_direction.map(|direction| match direction {
+        TextDirection::LeftToRight => Level::ltr(),
+        TextDirection::RightToLeft => Level::rtl(),
+    });
+    let bidi_info = BidiInfo::new(text, paragraph_level);
+    let mut runs: Vec<BidiRun> = vec![];
+    for paragraph in &bidi_info.paragraphs {
+        // The levels are per byte, with whitespace at the end of the paragraph reset (rule L1).
+        let levels = bidi_info.reordered_levels(paragraph, paragraph.range.clone());
+        let paragraph_offset = paragraph.range.start;
+        for (offset, character) in text[paragraph.range.clone()].char_indices() {
+            let level = levels[offset].number();
+            let (start, end) = (
+                paragraph_offset + offset,
+                paragraph_offset + offset + character.len_utf8(),
+            );
+            // Runs never cross paragraphs.
+            match runs.last_mut() {
+                Some(run) if run.level == level && run.range.start >= paragraph_offset => {
+                    run.range.end = end
+                }
+                _ => runs.push(BidiRun {
+                    range: start..end,
+                    level,
+                }),
+            }
+        }
+    }
+    runs
+}
+
+/// Returns the runs of one line in visual order, from left to right, reversing sequences of
+/// right-to-left runs and of runs nested within them.
+///
+/// The runs must be in logical order, as returned by `bidi_runs()`. To reorder wrapped text, pass
+/// the runs of each line separately, splitting runs that cross a line break.
+pub fn reorder_runs(runs: &[BidiRun]) -> Vec<BidiRun> {
+    let levels: Vec<Level> = runs
+        .iter()
+        .map(|run| Level::new(run.level.min(Level::max_implicit_depth())).unwrap())
+        .collect();
+    BidiInfo::reorder_visual(&levels)
+        .into_iter()
+        .map(|index| runs[index].clone())
+        .collect()
+}
+
+/// Lays out a line of bidirectional text in font units, in visual order.
+pub(crate) fn layout_bidi(
+    face: &Face,
+    text: &str,
+    base_direction: Option<TextDirection>,
+) -> Vec<PositionedGlyph> {
+    let mut positioned_glyphs = vec![];
+    for run in reorder_runs(&bidi_runs(text, base_direction)) {
+        let right_to_left = run.direction() == TextDirection::RightToLeft;
+        let glyphs: Vec<ClusterGlyph> = text[run.range.clone()]
+            .char_indices()
+            .map(|(index, character)| {
+                let character = if right_to_left {
+                    unicode_bidi_mirroring::get_mirrored(character).unwrap_or(character)
+                } else {
+                    character
+                };
+                let glyph = face.glyph_index(character).unwrap_or(GlyphId(0));
+                (glyph, (run.range.start + index) as u32)
+            })
+            .collect();
+        let mut run_glyphs = glyphs_with_advances(face, &glyphs);
+        apply_kerning(face, &glyphs, &mut run_glyphs);
+        if right_to_left {
+            run_glyphs.reverse();
+        }
+        positioned_glyphs.extend(run_glyphs);
+    }
+    update_positions(&mut positioned_glyphs);
+    positioned_glyphs
+}
+
+/// Lays out a run of text in font units, without substitutions.
+pub(crate) fn layout_run(face: &Face, text: &str) -> Vec<PositionedGlyph> {
+    let glyphs = map_characters(face, text);
+    let mut positioned_glyphs = glyphs_with_advances(face, &glyphs);
+    apply_kerning(face, &glyphs, &mut positioned_glyphs);
+    update_positions(&mut positioned_glyphs);
+    positioned_glyphs
+}
+
+/// Returns the logical bounds of a laid out run in font units: from the start of the run to the
+/// end of its last advance horizontally, and from the descent to the ascent vertically.
+pub(crate) fn run_bounds(positioned_glyphs: &[PositionedGlyph], metrics: &Metrics) -> RectF {
+    let width = positioned_glyphs
+        .last()
+        .map_or(0.0, |glyph| glyph.position.x() + glyph.advance.x());
+    RectF::from_points(
+        Vector2F::new(0.0, metrics.descent),
+        Vector2F::new(width, metrics.ascent),
+    )
+}
+
+/// Breaks text into lines no wider than `max_width`. All lengths are in font units.
+pub(crate) fn wrap_text(face: &Face, text: &str, max_width: f32, metrics: &Metrics) -> WrappedText {
+    let characters: Vec<char> = text.chars().collect();
+    let glyphs = layout_run(face, text);
+    let count = characters.len();
+    let glyph_end = |index: usize| glyphs[index].position.x() + glyphs[index].advance.x();
+
+    let line_height = metrics.ascent - metrics.descent + metrics.line_gap;
+    let mut lines = vec![];
+    let mut push_line = |start: usize, end: usize| {
+        let mut visible_end = end;
+        while visible_end > start
+            && (is_breaking_space(characters[visible_end - 1])
+                || is_mandatory_break(&characters, visible_end - 1))
+        {
+            visible_end -= 1;
+        }
+        let line_start = glyphs.get(start).map_or(0.0, |glyph| glyph.position.x());
+        let line_glyphs = glyphs[start..visible_end]
+            .iter()
+            .map(|glyph| PositionedGlyph {
+                position: glyph.position - Vector2F::new(line_start, 0.0),
+                ..*glyph
+            })
+            .collect();
+        let width = if visible_end > start {
+            glyph_end(visible_end - 1) - line_start
+        } else {
+            0.0
+        };
+        let byte_offset =
+            |index: usize| glyphs.get(index).map_or(text.len(), |g| g.cluster as usize);
+        lines.push(WrappedLine {
+            range: byte_offset(start)..byte_offset(end),
+            glyphs: line_glyphs,
+            width,
+            baseline: metrics.ascent + line_height * lines.len() as f32,
+        });
+    };
+
+    let (mut start, mut index) = (0, 0);
+    let mut last_break = None;
+    while index < count {
+        if is_mandatory_break(&characters, index) {
+            push_line(start, index + 1);
+            start = index + 1;
+            last_break = None;
+        } else if index > start
+            && !is_breaking_space(characters[index])
+            && glyph_end(index) - glyphs[start].position.x() > max_width
+        {
+            // Break at the last opportunity, or before this character if there's none.
+            let end = last_break.map_or(index, |last_break| last_break + 1);
+            push_line(start, end);
+            start = end;
+            last_break = None;
+            index = end;
+            continue;
+        } else if can_break_after(&characters, index) {
+            last_break = Some(index);
+        }
+        index += 1;
+    }
+    push_line(start, count);
+
+    let height = line_height * lines.len() as f32;
+    WrappedText { lines, height }
+}
+
+// Spaces that lines can break after and that are trimmed from the ends of lines. No-break spaces
+// are excluded.
+fn is_breaking_space(character: char) -> bool {
+    character.is_whitespace()
+        && !matches!(character, '\u{a0}' | '\u{2007}' | '\u{202f}')
+        && !matches!(character, '\n' | '\r' | '\u{b}' | '\u{c}' | '\u{85}')
+        && !matches!(character, '\u{2028}' | '\u{2029}')
+}
+
+// Whether a line must end after the character at `index`. A CR LF pair breaks after the LF.
+fn is_mandatory_break(characters: &[char], index: usize) -> bool {
+    match characters[index] {
+        '\r' => characters.get(index + 1) != Some(&'\n'),
+        '\n' | '\u{b}' | '\u{c}' | '\u{85}' | '\u{2028}' | '\u{2029}' => true,
+        _ => false,
+    }
+}
+
+// Whether a line may end after the character at `index`, short of a mandatory break.
+fn can_break_after(characters: &[char], index: usize) -> bool {
+    let (character, next) = match characters.get(index + 1) {
+        Some(&next) => (characters[index], next),
+        None => return false,
+    };
+    if is_breaking_space(next) || is_closing_punctuation(next) || is_opening_punctuation(character)
+    {
+        return false;
+    }
+    if is_breaking_space(character) || character == '\u{200b}' {
+        return true;
+    }
+    // Break after a hyphen only within a word, so that negative numbers stay whole.
+    if matches!(character, '-' | '\u{2010}' | '\u{2013}') {
+        return index > 0 && characters[index - 1].is_alphabetic();
+    }
+    is_ideographic(character) || is_ideographic(next)
+}
+
+fn is_ideographic(character: char) -> bool {
+    matches!(character as u32,
+        0x2e80..=0x2fff |   // CJK radicals and Kangxi radicals
+        0x3040..=0x30ff |   // Hiragana and Katakana
+        0x3400..=0x4dbf |   // CJK Unified Ideographs Extension A
+        0x4e00..=0x9fff |   // CJK Unified Ideographs
+        0xac00..=0xd7af |   // Hangul syllables
+        0xf900..=0xfaff |   // CJK Compatibility Ideographs
+        0x20000..=0x3ffff) // Supplementary and tertiary ideographic planes
+}
+
+fn is_opening_punctuation(character: char) -> bool {
+    matches!(
+        character,
+        '(' | '['
+            | '{'
+            | '\u{2018}'
+            | '\u{201c}'
+            | '\u{3008}'
+            | '\u{300a}'
+            | '\u{300c}'
+            | '\u{300e}'
+            | '\u{3010}'
+            | '\u{ff08}'
+    )
+}
+
+fn is_closing_punctuation(character: char) -> bool {
+    matches!(
+        character,
+        ')' | ']'
+            | '}'
+            | ','
+            | '.'
+            | ':'
+            | ';'
+            | '!'
+            | '?'
+            | '\u{2019}'
+            | '\u{201d}'
+            | '\u{3001}'
+            | '\u{3002}'
+            | '\u{3009}'
+            | '\u{300b}'
+            | '\u{300d}'
+            | '\u{300f}'
+            | '\u{3011}'
+            | '\u{ff09}'
+            | '\u{ff0c}'
+            | '\u{ff01}'
+            | '\u{ff1f}'
+    )
+}
+
+/// Maps each character of the text to a glyph, using the `.notdef` glyph for characters the
+/// font doesn't cover.
+pub(crate) fn map_characters(face: &Face, text: &str) -> Vec<ClusterGlyph> {
+    text.char_indices()
+        .map(|(index, character)| {
+            let glyph = face.glyph_index(character).unwrap_or(GlyphId(0));
+            (glyph, index as u32)
+        })
+        .collect()
+}
+
+/// Creates unpositioned glyphs with their horizontal advances.
+pub(crate) fn glyphs_with_advances(face: &Face, glyphs: &[ClusterGlyph]) -> Vec<PositionedGlyph> {
+    glyphs
+        .iter()
+        .map(|&(glyph, cluster)| PositionedGlyph {
+            glyph_id: glyph.0 as u32,
+            cluster,
+            position: Vector2F::zero(),
+            advance: Vector2F::new(face.glyph_hor_advance(glyph).unwrap_or(0) as f32, 0.0),
+            offset: Vector2F::zero(),
+        })
+        .collect()
+}
+
+/// Scales the positions, advances, and offsets of glyphs, for example from font units to pixels.
+pub(crate) fn scale_glyphs(positioned_glyphs: &mut [PositionedGlyph], scale: f32) {
+    for glyph in positioned_glyphs {
+        glyph.position *= scale;
+        glyph.advance *= scale;
+        glyph.offset *= scale;
+    }
+}
+
+/// Sets the pen position of each glyph from the advances of the glyphs before it.
+pub(crate) fn update_positions(positioned_glyphs: &mut [PositionedGlyph]) {
+    let mut pen = Vector2F::zero();
+    for glyph in positioned_glyphs {
+        glyph.position = pen;
+        pen += glyph.advance;
+    }
+}
+
+/// Adjusts advances and offsets using the `GPOS` `kern` feature, or the legacy `kern` table if
+/// the font has no `GPOS` kerning.
+pub(crate) fn apply_kerning(
+    face: &Face,
+    glyphs: &[ClusterGlyph],
+    positioned_glyphs: &mut [PositionedGlyph],
+) {
+    let kern = u32::from_be_bytes(*b"kern");
+    let gpos_lookups = match face.tables().gpos {
+        Some(table) => substitution::lookup_indices(&table, &[kern])
+            .into_iter()
+            .filter_map(|index| table.lookups.get(index))
+            .flat_map(|lookup| {
+                (0..lookup.subtables.len())
+                    .filter_map(move |index| lookup.subtables.get::<PositioningSubtable>(index))
+            })
+            .filter_map(|subtable| match subtable {
+                PositioningSubtable::Pair(pair) => Some(pair),
+                _ => None,
+            })
+            .collect(),
+        None => vec![],
+    };
+
+    for index in 1..glyphs.len() {
+        let (left, right) = (glyphs[index - 1].0, glyphs[index].0);
+        if !gpos_lookups.is_empty() {
+            for pair in &gpos_lookups {
+                if let Some((first, second)) = pair_adjustment(pair, left, right) {
+                    positioned_glyphs[index - 1].advance += Vector2F::new(first.0 as f32, 0.0);
+                    positioned_glyphs[index - 1].offset += Vector2F::new(first.1 as f32, 0.0);
+                    positioned_glyphs[index].advance += Vector2F::new(second.0 as f32, 0.0);
+                    positioned_glyphs[index].offset += Vector2F::new(second.1 as f32, 0.0);
+                    break;
+                }
+            }
+        } else if let Some(kern_table) = face.tables().kern {
+            let kerning: i16 = kern_table
+                .subtables
+                .into_iter()
+                .filter(|subtable| subtable.horizontal && !subtable.variable)
+                .filter_map(|subtable| subtable.glyphs_kerning(left, right))
+                .sum();
+            positioned_glyphs[index - 1].advance += Vector2F::new(kerning as f32, 0.0);
+        }
+    }
+}
+
+// Returns the `(x_advance, x_placement)` adjustments of the two glyphs of a pair, if the subtable
+// covers the pair.
+fn pair_adjustment(
+    pair: &PairAdjustment,
+    left: GlyphId,
+    right: GlyphId,
+) -> Option<((i16, i16), (i16, i16))> {
+    let (first, second) = match *pair {
+        PairAdjustment::Format1 { coverage, sets } => sets.get(coverage.get(left)?)?.get(right)?,
+        PairAdjustment::Format2 {
+            coverage,
+            classes,
+            matrix,
+        } => {
+            coverage.get(left)?;
+            matrix.get((classes.0.get(left), classes.1.get(right)))?
+        }
+    };
+    Some((
+        (first.x_advance, first.x_placement),
+        (second.x_advance, second.x_placement),
+    ))
+}
+
+/// Returns the `GDEF` class of a glyph.
+pub(crate) fn glyph_class(face: &Face, glyph_id: GlyphId) -> GlyphClass {
+    match face
+        .tables()
+        .gdef
+        .and_then(|gdef| gdef.glyph_class(glyph_id))
+    {
+        Some(ttf_parser::gdef::GlyphClass::Base) => GlyphClass::Base,
+        Some(ttf_parser::gdef::GlyphClass::Ligature) => GlyphClass::Ligature,
+        Some(ttf_parser::gdef::GlyphClass::Mark) => GlyphClass::Mark,
+        Some(ttf_parser::gdef::GlyphClass::Component) => GlyphClass::Component,
+        None => GlyphClass::Unclassified,
+    }
+}
+
+/// Returns the offset that puts `mark` on its attachment point on `base`, from the first
+/// mark-to-base, mark-to-ligature, or mark-to-mark subtable in the `GPOS` lookup list that covers
+/// both glyphs.
+pub(crate) fn mark_anchor(face: &Face, base: GlyphId, mark: GlyphId) -> Option<Vector2F> {
+    let table = face.tables().gpos?;
+    table
+        .lookups
+        .into_iter()
+        .flat_map(|lookup| {
+            (0..lookup.subtables.len())
+                .filter_map(move |index| lookup.subtables.get::<PositioningSubtable>(index))
+        })
+        .find_map(|subtable| match subtable {
+            PositioningSubtable::MarkToBase(adjustment) => {
+                let base_index = adjustment.base_coverage.get(base)?;
+                attach(
+                    &adjustment.mark_coverage,
+                    &adjustment.marks,
+                    mark,
+                    |class| adjustment.anchors.get(base_index, class),
+                )
+            }
+            PositioningSubtable::MarkToLigature(adjustment) => {
+                // Without knowing which component the mark belongs to, attach it to the last one,
+                // as shapers do.
+                let ligature_index = adjustment.ligature_coverage.get(base)?;
+                let components = adjustment.ligature_array.get(ligature_index)?;
+                let last = components.rows.checked_sub(1)?;
+                attach(
+                    &adjustment.mark_coverage,
+                    &adjustment.marks,
+                    mark,
+                    |class| components.get(last, class),
+                )
+            }
+            PositioningSubtable::MarkToMark(adjustment) => {
+                let base_index = adjustment.mark2_coverage.get(base)?;
+                attach(
+                    &adjustment.mark1_coverage,
+                    &adjustment.marks,
+                    mark,
+                    |class| adjustment.mark2_matrix.get(base_index, class),
+                )
+            }
+            _ => None,
+        })
+}
+
+// Returns the offset from the base's anchor for the mark's class to the mark's own anchor.
+fn attach<'a, A>(
+    mark_coverage: &Coverage,
+    marks: &MarkArray<'a>,
+    mark: GlyphId,
+    base_anchor: A,
+) -> Option<Vector2F>
+where
+    A: FnOnce(u16) -> Option<Anchor<'a>>,
+{
+    let (class, mark_anchor) = marks.get(mark_coverage.get(mark)?)?;
+    let base_anchor = base_anchor(class)?;
+    Some(Vector2F::new(
+        (base_anchor.x - mark_anchor.x) as f32,
+        (base_anchor.y - mark_anchor.y) as f32,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bidi_runs, reorder_runs, BidiRun, GlyphClass, TextDirection};
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use crate::test_fixtures::{EB_GARAMOND, INCONSOLATA};
+
+    #[test]
+    fn test_layout_run() {
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let glyphs = font.layout_run("AVé", 20.0);
+        assert_eq!(glyphs.len(), 3);
+        assert_eq!(glyphs[2].cluster, 2);
+        assert_eq!(glyphs[0].position.x(), 0.0);
+        assert_eq!(glyphs[1].position.x(), glyphs[0].advance.x());
+
+        // "AV" is kerned, so the "A" advances less than its unkerned advance.
+        let unkerned = font.advance(glyphs[0].glyph_id).unwrap().x() * 20.0 / 1000.0;
+        assert!(glyphs[0].advance.x() < unkerned);
+
+        let bounds = font.measure_text("AVé", 20.0);
+        assert_eq!(bounds.origin_x(), 0.0);
+        assert_eq!(
+            bounds.width(),
+            glyphs[2].position.x() + glyphs[2].advance.x()
+        );
+        assert!(bounds.min_y() < 0.0 && bounds.max_y() > 0.0);
+        assert_eq!(font.measure_text("", 20.0).width(), 0.0);
+    }
+
+    #[test]
+    fn test_glyph_class() {
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let class = |character| font.glyph_class(font.glyph_for_char(character).unwrap());
+        assert_eq!(class('a'), GlyphClass::Base);
+        assert_eq!(class('\u{301}'), GlyphClass::Mark);
+        assert_eq!(font.glyph_class(u16::MAX as u32), GlyphClass::Unclassified);
+    }
+
+    #[test]
+    fn test_mark_anchor() {
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let glyph = |character| font.glyph_for_char(character).unwrap();
+        let lower = font.mark_anchor(glyph('a'), glyph('\u{301}')).unwrap();
+        let upper = font.mark_anchor(glyph('A'), glyph('\u{301}')).unwrap();
+        assert!(lower.x() > 0.0);
+        // The accent sits higher over the capital.
+        assert!(upper.y() > lower.y());
+        assert_eq!(font.mark_anchor(glyph('a'), glyph('b')), None);
+
+        // Marks stack on other marks through mark-to-mark lookups.
+        let font = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        let glyph = |character| font.glyph_for_char(character).unwrap();
+        let stacked = font
+            .mark_anchor(glyph('\u{301}'), glyph('\u{301}'))
+            .unwrap();
+        assert!(stacked.y() > 0.0);
+    }
+
+    #[test]
+    fn test_wrap_text() {
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let text = "hello world foo\nbar";
+        let max_width = font.measure_text("hello world", 20.0).width() + 1.0;
+        let wrapped = font.wrap_text(text, max_width, 20.0);
+        let ranges: Vec<_> = wrapped
+            .lines
+            .iter()
+            .map(|line| line.range.clone())
+            .collect();
+        assert_eq!(ranges, vec![0..12, 12..16, 16..19]);
+        assert_eq!(wrapped.lines[0].glyphs.len(), 11);
+        assert!(wrapped.lines[0].width <= max_width);
+        assert_eq!(wrapped.lines[1].glyphs[0].position.x(), 0.0);
+        assert!(wrapped.lines[1].baseline > wrapped.lines[0].baseline);
+        let line_height = wrapped.lines[1].baseline - wrapped.lines[0].baseline;
+        assert!((wrapped.height - line_height * 3.0).abs() < 0.001);
+
+        // A word that doesn't fit is broken between characters.
+        let narrow = font.wrap_text("abcdef", font.measure_text("ab", 20.0).width(), 20.0);
+        assert!(narrow.lines.len() >= 3);
+        assert!(narrow.lines.iter().all(|line| !line.glyphs.is_empty()));
+        assert_eq!(font.wrap_text("", 100.0, 20.0).lines.len(), 1);
+    }
+
+    #[test]
+    fn test_bidi_runs() {
+        // "abc", a Hebrew word, and a number that the Hebrew context makes right to left.
+        let text = "abc \u{5d0}\u{5d1}\u{5d2} 123";
+        let runs = bidi_runs(text, None);
+        let levels: Vec<_> = runs
+            .iter()
+            .map(|run| (run.range.clone(), run.level))
+            .collect();
+        assert_eq!(levels, vec![(0..4, 0), (4..11, 1), (11..14, 2)]);
+        assert_eq!(runs[1].direction(), TextDirection::RightToLeft);
+
+        let visual: Vec<_> = reorder_runs(&runs)
+            .into_iter()
+            .map(|run| run.range)
+            .collect();
+        assert_eq!(visual, vec![0..4, 11..14, 4..11]);
+
+        let rtl_runs = bidi_runs("abc", Some(TextDirection::RightToLeft));
+        assert_eq!(
+            rtl_runs,
+            vec![BidiRun {
+                range: 0..3,
+                level: 2
+            }]
+        );
+
+        // Isolates (rules X5a–X6a) nest left-to-right text in a right-to-left paragraph.
+        let runs = bidi_runs("\u{5d0} \u{2066}abc\u{2069} \u{5d1}", None);
+        let levels: Vec<_> = runs
+            .iter()
+            .map(|run| (run.range.clone(), run.level))
+            .collect();
+        assert_eq!(levels, vec![(0..6, 1), (6..9, 2), (9..15, 1)]);
+
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let glyphs = font.layout_bidi(text, 20.0, None);
+        let clusters: Vec<u32> = glyphs.iter().map(|glyph| glyph.cluster).collect();
+        assert_eq!(clusters, vec![0, 1, 2, 3, 11, 12, 13, 10, 8, 6, 4]);
+        assert_eq!(
+            glyphs[4].position.x(),
+            glyphs[3].position.x() + glyphs[3].advance.x()
+        );
+    }
+}
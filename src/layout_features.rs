@@ -0,0 +1,149 @@
+// font-kit/src/layout_features.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The OpenType layout features that a font supports.
+//!
+//! Font pickers use this to offer options such as small caps (`smcp`) or stylistic sets
+//! (`ss01`–`ss20`) only for fonts that implement them. Tags are OpenType tags packed big-endian
+//! into a `u32`, as in `Loader::load_font_table()`; `u32::from_be_bytes(*b"smcp")` produces one.
+
+use ttf_parser::opentype_layout::{LanguageSystem, LayoutTable};
+
+/// The scripts, languages, and features in a font's `GSUB` or `GPOS` table.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LayoutFeatures {
+    /// Every feature tag in the table, in ascending order and without duplicates.
+    pub features: Vec<u32>,
+    /// The scripts that the table has features for, in the order they appear in the table.
+    pub scripts: Vec<ScriptFeatures>,
+}
+
+/// The languages and features that a font supports for one script.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScriptFeatures {
+    /// The script tag, such as `latn` or `arab`.
+    pub script: u32,
+    /// The language systems for this script. The default language system, if any, comes first.
+    pub languages: Vec<LanguageFeatures>,
+}
+
+/// The features that a font supports for one language of a script.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LanguageFeatures {
+    /// The language tag, such as `TRK `, or `None` for the script's default language system.
+    pub language: Option<u32>,
+    /// The feature tags for this language, in ascending order and without duplicates.
+    pub features: Vec<u32>,
+}
+
+impl LayoutFeatures {
+    pub(crate) fn from_table(table: Option<LayoutTable>) -> LayoutFeatures {
+        let table = match table {
+            Some(table) => table,
+            None => return LayoutFeatures::default(),
+        };
+
+        let mut features: Vec<u32> = table.features.into_iter().map(|f| f.tag.0).collect();
+        features.sort_unstable();
+        features.dedup();
+
+        let scripts = table
+            .scripts
+            .into_iter()
+            .map(|script| {
+                let default_language = script
+                    .default_language
+                    .map(|language| language_features(&table, None, language));
+                let other_languages = script
+                    .languages
+                    .into_iter()
+                    .map(|language| language_features(&table, Some(language.tag.0), language));
+                let languages = default_language
+                    .into_iter()
+                    .chain(other_languages)
+                    .collect();
+                ScriptFeatures {
+                    script: script.tag.0,
+                    languages,
+                }
+            })
+            .collect();
+
+        LayoutFeatures { features, scripts }
+    }
+
+    /// Returns true if the table contains the given feature for any script or language.
+    #[inline]
+    pub fn contains(&self, feature: u32) -> bool {
+        self.features.binary_search(&feature).is_ok()
+    }
+
+    /// Returns the features available for the given script and language.
+    ///
+    /// A `language` of `None`, or one the script has no language system for, selects the script's
+    /// default language system. Returns an empty list if the table doesn't cover the script.
+    pub fn features_for(&self, script: u32, language: Option<u32>) -> &[u32] {
+        let script = match self.scripts.iter().find(|entry| entry.script == script) {
+            Some(script) => script,
+            None => return &[],
+        };
+        script
+            .languages
+            .iter()
+            .find(|entry| language.is_some() && entry.language == language)
+            .or_else(|| {
+                script
+                    .languages
+                    .iter()
+                    .find(|entry| entry.language.is_none())
+            })
+            .map_or(&[], |entry| &entry.features)
+    }
+}
+
+fn language_features(
+    table: &LayoutTable,
+    language: Option<u32>,
+    language_system: LanguageSystem,
+) -> LanguageFeatures {
+    let mut features: Vec<u32> = language_system
+        .required_feature
+        .into_iter()
+        .chain(language_system.feature_indices)
+        .filter_map(|index| table.features.get(index))
+        .map(|feature| feature.tag.0)
+        .collect();
+    features.sort_unstable();
+    features.dedup();
+    LanguageFeatures { language, features }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::font::Font;
+    use crate::test_fixtures::EB_GARAMOND;
+
+    #[test]
+    fn test_layout_features() {
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let gsub = font.gsub_features();
+        assert!(gsub.contains(u32::from_be_bytes(*b"liga")));
+        assert!(gsub.contains(u32::from_be_bytes(*b"smcp")));
+        assert!(!gsub.contains(u32::from_be_bytes(*b"kern")));
+
+        let latin = gsub.features_for(u32::from_be_bytes(*b"latn"), None);
+        assert!(latin.contains(&u32::from_be_bytes(*b"liga")));
+        assert!(gsub
+            .features_for(u32::from_be_bytes(*b"zzzz"), None)
+            .is_empty());
+
+        assert!(font.gpos_features().contains(u32::from_be_bytes(*b"kern")));
+    }
+}
@@ -20,6 +20,7 @@
 //!     use font_kit::canvas::{Canvas, Format, RasterizationOptions};
 //!     use font_kit::family_name::FamilyName;
 //!     use font_kit::hinting::HintingOptions;
+//!     use font_kit::loader::Loader;
 //!     use font_kit::properties::Properties;
 //!     use font_kit::source::SystemSource;
 //!     use pathfinder_geometry::transform2d::Transform2F;
@@ -57,7 +58,11 @@
 //! * DirectWrite (Windows): The newer system framework for text rendering on Windows. Does
 //!   vertical hinting but not full hinting.
 //!
-//! * FreeType (cross-platform): A full-featured font rendering framework.
+//! * FreeType (cross-platform): A full-featured font rendering framework, with full hinting and
+//!   LCD filtering. It links the system FreeType library.
+//!
+//! * ttf-parser (cross-platform): A loader written in pure Rust, which `font::Font` uses. This is
+//!   the default on platforms other than macOS and Windows.
 //!
 //! Available sources:
 //!
@@ -75,11 +80,17 @@
 //!
 //! * Multi (cross-platform): A source that allows multiple sources to be queried at once.
 //!
-//! On Windows and macOS, the FreeType loader and the Fontconfig source are not built by default.
-//! To build them, use the `loader-freetype` and `source-fontconfig` Cargo features respectively.
-//! If you want them to be the default, instead use the `loader-freetype-default` and
+//! * Alias (cross-platform): A source that wraps another one and resolves family names through
+//!   user-defined aliases and metric-compatible substitutes, such as Liberation Sans for Arial.
+//!
+//! * Web (cross-platform): A source holding the fonts that a web page has been given access to
+//!   through the Local Font Access API. This is the default on WebAssembly.
+//!
+//! The FreeType loader is not built by default, nor is the Fontconfig source on Windows and
+//! macOS. To build them, use the `loader-freetype` and `source-fontconfig` Cargo features
+//! respectively. If you want them to be the default, instead use the `loader-freetype-default` and
 //! `source-fontconfig-default` Cargo features respectively. Beware that
-//! `source-fontconfig-default` is rarely what you want on those two platforms!
+//! `source-fontconfig-default` is rarely what you want on Windows and macOS!
 //!
 //! ## Features
 //!
@@ -124,25 +135,77 @@
 #[macro_use]
 extern crate bitflags;
 
+pub mod atlas;
+#[cfg(feature = "bench-internals")]
+#[doc(hidden)]
+pub mod bench_fixtures;
 pub mod canvas;
+pub mod coverage;
+pub mod deterministic;
+pub mod device_metrics;
 pub mod error;
+pub mod fallback;
 pub mod family;
 pub mod family_handle;
 pub mod family_name;
 pub mod file_type;
 pub mod font;
+pub mod font_cache;
+#[cfg(all(feature = "source", not(target_arch = "wasm32")))]
+pub mod font_changes;
+pub mod font_info;
+pub mod gasp;
+pub mod glyph_contours;
 pub mod handle;
 pub mod hinting;
+pub mod incremental;
+#[cfg(feature = "last-resort")]
+pub mod last_resort;
+pub mod layout;
+pub mod layout_features;
+pub mod load_options;
 pub mod loader;
 pub mod loaders;
+pub mod matching;
+pub mod math;
 pub mod metrics;
+pub mod os2;
 pub mod outline;
+#[cfg(any(feature = "lyon", feature = "tiny-skia"))]
+pub mod path_sinks;
+pub mod pdf;
 pub mod properties;
+pub mod raster_cache;
+#[cfg(feature = "ft-compare")]
+pub mod raster_compare;
+pub mod rendering_config;
+pub mod script;
+#[cfg(feature = "shaping")]
+pub mod shaping;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod shared_data;
+pub mod subset;
+pub mod svg;
+pub mod synthesis;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod units;
+pub mod validation;
 
 #[cfg(feature = "source")]
 pub mod source;
 #[cfg(feature = "source")]
 pub mod sources;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub mod task;
 
-mod matching;
+mod carets;
+mod emoji;
+mod glyph_cache;
+mod han;
+#[cfg(any(unix, target_family = "windows"))]
+mod mmap;
+mod substitution;
+#[cfg(test)]
+mod test_fixtures;
 mod utils;
@@ -0,0 +1,407 @@
+// font-kit/src/load_options.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Options for loading a font.
+//!
+//! `LoadOptions` gathers everything that can be decided when a font is loaded into one value, so
+//! that new options can be added without adding parameters to every loading function. Start from
+//! `Font::builder()` or `LoadOptions::new()`, set the options that matter, and either pass the
+//! options to one of the `Font::*_with_options()` functions or call one of the `load_*()` methods:
+//!
+//! ```
+//! # use font_kit::font::Font;
+//! # use std::sync::Arc;
+//! # let data = Arc::new(include_bytes!("../resources/tests/eb-garamond/EBGaramond12.otc").to_vec());
+//! let italic = Font::builder().font_index(1).strict(true).load_bytes(data)?;
+//! # Ok::<(), font_kit::error::FontLoadingError>(())
+//! ```
+
+#[cfg(not(target_arch = "wasm32"))]
+use byteorder::{BigEndian, ByteOrder};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs::File, path::Path};
+
+use crate::error::FontLoadingError;
+use crate::font::Font;
+use crate::handle::Handle;
+use crate::metrics::MetricsOverride;
+use crate::validation::{self, Directory};
+
+/// Options for loading a font. See the module documentation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoadOptions {
+    pub(crate) font_index: u32,
+    pub(crate) variations: Vec<(u32, f32)>,
+    pub(crate) mmap: bool,
+    pub(crate) strict: bool,
+    pub(crate) metrics_override: MetricsOverride,
+    pub(crate) max_glyphs: Option<u32>,
+    pub(crate) max_table_size: Option<usize>,
+}
+
+impl LoadOptions {
+    /// Returns the default options: the first font of a collection, read into memory, with no
+    /// variations, overrides, or extra checks.
+    #[inline]
+    pub fn new() -> LoadOptions {
+        LoadOptions::default()
+    }
+
+    /// Selects the font to load from a collection (`.ttc`/`.otc`/etc.). The default is 0.
+    ///
+    /// Handles carry their own index, so this is ignored when loading from a handle.
+    #[inline]
+    pub fn font_index(mut self, font_index: u32) -> LoadOptions {
+        self.font_index = font_index;
+        self
+    }
+
+    /// Sets a variation axis, such as `wght`, once the font is loaded. See
+    /// `Font::set_variation()`.
+    ///
+    /// The axis is an OpenType tag packed big-endian into a `u32`. Axes that the font doesn't have
+    /// are ignored.
+    #[inline]
+    pub fn variation(mut self, axis: u32, value: f32) -> LoadOptions {
+        self.variations.push((axis, value));
+        self
+    }
+
+    /// Whether to memory-map files rather than read them into memory. The default is false.
+    ///
+    /// This applies to loading from paths and from `Handle::Path` handles; see
+    /// `Font::from_path_mmap()` for the trade-offs. It has no effect on platforms without
+    /// memory mapping.
+    ///
+    /// # Safety
+    ///
+    /// If `mmap` is true, files loaded with these options must not be modified or truncated while
+    /// the fonts loaded from them, or any of their clones, are alive.
+    #[inline]
+    pub unsafe fn mmap(mut self, mmap: bool) -> LoadOptions {
+        self.mmap = mmap;
+        self
+    }
+
+    /// Whether to reject fonts that lack the tables needed to map characters to glyphs and draw
+    /// them, or whose tables are structurally broken. The default is false.
+    ///
+    /// Without this, a font only needs to have the tables required to parse it at all, and
+    /// missing or broken tables show up later as missing glyphs or names. With it, the font is
+    /// validated as `Font::validate()` does, and any structural issue rejects it; checksum
+    /// mismatches, which are common in fonts that work, are allowed.
+    #[inline]
+    pub fn strict(mut self, strict: bool) -> LoadOptions {
+        self.strict = strict;
+        self
+    }
+
+    /// Rejects fonts with more than this many glyphs, with `FontLoadingError::LimitExceeded`.
+    /// The default is no limit.
+    ///
+    /// Together with `max_table_size()`, this bounds the work that glyph-wide operations and
+    /// caches can do for fonts from untrusted sources. Both limits are checked against the table
+    /// directory before the font is parsed, and for files before the file is read.
+    #[inline]
+    pub fn max_glyphs(mut self, max_glyphs: u32) -> LoadOptions {
+        self.max_glyphs = Some(max_glyphs);
+        self
+    }
+
+    /// Rejects fonts with any table larger than this many bytes, with
+    /// `FontLoadingError::LimitExceeded`. The default is no limit.
+    ///
+    /// Files are also rejected, before they are read, if they are longer than a font with tables
+    /// of at most this size could be: its table directory and tables, for each font of a
+    /// collection.
+    #[inline]
+    pub fn max_table_size(mut self, max_table_size: usize) -> LoadOptions {
+        self.max_table_size = Some(max_table_size);
+        self
+    }
+
+    /// Sets overrides for the font's ascent, descent, and line gap. See
+    /// `Font::set_metrics_override()`.
+    #[inline]
+    pub fn metrics_override(mut self, metrics_override: MetricsOverride) -> LoadOptions {
+        self.metrics_override = metrics_override;
+        self
+    }
+
+    /// Loads a font from raw font data with these options.
+    #[inline]
+    pub fn load_bytes(&self, font_data: Arc<Vec<u8>>) -> Result<Font, FontLoadingError> {
+        Font::from_bytes_with_options(font_data, self)
+    }
+
+    /// Loads a font from a file with these options.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[inline]
+    pub fn load_file(&self, file: &mut File) -> Result<Font, FontLoadingError> {
+        Font::from_file_with_options(file, self)
+    }
+
+    /// Loads a font from a path with these options.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[inline]
+    pub fn load_path<P>(&self, path: P) -> Result<Font, FontLoadingError>
+    where
+        P: AsRef<Path>,
+    {
+        Font::from_path_with_options(path, self)
+    }
+
+    /// Loads the font pointed to by a handle with these options.
+    #[inline]
+    pub fn load_handle(&self, handle: &Handle) -> Result<Font, FontLoadingError> {
+        Font::from_handle_with_options(handle, self)
+    }
+
+    fn has_limits(&self) -> bool {
+        self.max_glyphs.is_some() || self.max_table_size.is_some()
+    }
+}
+
+/// Checks a font against the glyph and table size limits in the options, from its table
+/// directory, before anything else reads its tables.
+pub(crate) fn check_limits(
+    data: &[u8],
+    font_index: u32,
+    options: &LoadOptions,
+) -> Result<(), FontLoadingError> {
+    if !options.has_limits() {
+        return Ok(());
+    }
+    let directory = Directory::parse(data, font_index)?;
+    check_directory_limits(&directory, || directory.glyph_count(data), options)
+}
+
+/// Checks a font file against the glyph and table size limits in the options, reading only its
+/// table directory and glyph count, before the file is read or mapped.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn check_file_limits(
+    file: &mut File,
+    font_index: u32,
+    options: &LoadOptions,
+) -> Result<(), FontLoadingError> {
+    if !options.has_limits() {
+        return Ok(());
+    }
+    let file_len = file.metadata()?.len();
+    let header = read_at(file, 0, 12)?;
+    let (font_offset, font_count) = if header.starts_with(b"ttcf") {
+        let font_count = BigEndian::read_u32(&header[8..]);
+        if font_index >= font_count {
+            return Err(FontLoadingError::NoSuchFontInCollection);
+        }
+        let offset = read_at(file, 12 + 4 * font_index as u64, 4)?;
+        (BigEndian::read_u32(&offset) as u64, font_count as u64)
+    } else {
+        (0, 1)
+    };
+
+    // The font's header and table records are read into a standalone directory; the records'
+    // offsets stay relative to the start of the file.
+    let mut directory_data = read_at(file, font_offset, 12)?;
+    let table_count = BigEndian::read_u16(&directory_data[4..]) as u64;
+    directory_data.extend_from_slice(&read_at(file, font_offset + 12, 16 * table_count)?);
+    let directory = Directory::parse(&directory_data, 0)?;
+
+    let glyph_count = || {
+        let maxp = directory
+            .tables
+            .iter()
+            .find(|table| table.tag == u32::from_be_bytes(*b"maxp"))?;
+        let num_glyphs = read_at(file, (maxp.offset as u64).checked_add(4)?, 2).ok()?;
+        Some(BigEndian::read_u16(&num_glyphs))
+    };
+    check_directory_limits(&directory, glyph_count, options)?;
+
+    if let Some(max_table_size) = options.max_table_size {
+        let padded_table_size = (max_table_size as u64).saturating_add(3) & !3;
+        let font_size = (12 + 16 * table_count).saturating_add(table_count * padded_table_size);
+        let header_size = if font_count > 1 {
+            12 + 4 * font_count
+        } else {
+            0
+        };
+        if file_len
+            > font_count
+                .saturating_mul(font_size)
+                .saturating_add(header_size)
+        {
+            return Err(FontLoadingError::LimitExceeded);
+        }
+    }
+    Ok(())
+}
+
+// Reads `len` bytes of a file at `offset`.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_at(file: &mut File, offset: u64, len: u64) -> Result<Vec<u8>, FontLoadingError> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::with_capacity(len as usize);
+    file.take(len).read_to_end(&mut data)?;
+    if (data.len() as u64) < len {
+        return Err(FontLoadingError::Truncated {
+            offset: offset as usize + data.len(),
+        });
+    }
+    Ok(data)
+}
+
+fn check_directory_limits<F>(
+    directory: &Directory,
+    glyph_count: F,
+    options: &LoadOptions,
+) -> Result<(), FontLoadingError>
+where
+    F: FnOnce() -> Option<u16>,
+{
+    if let Some(max_table_size) = options.max_table_size {
+        if directory
+            .tables
+            .iter()
+            .any(|table| table.length > max_table_size)
+        {
+            return Err(FontLoadingError::LimitExceeded);
+        }
+    }
+    if let Some(max_glyphs) = options.max_glyphs {
+        let glyph_count = glyph_count().ok_or(FontLoadingError::MalformedTable {
+            table: u32::from_be_bytes(*b"maxp"),
+        })?;
+        if glyph_count as u32 > max_glyphs {
+            return Err(FontLoadingError::LimitExceeded);
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a font's tables aren't structurally broken, for strict loading.
+pub(crate) fn check_structure(data: &[u8], font_index: u32) -> Result<(), FontLoadingError> {
+    match validation::validate(data, font_index)?
+        .iter()
+        .find(|issue| issue.is_structural())
+    {
+        Some(issue) => Err(FontLoadingError::MalformedTable {
+            table: issue.table(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Checks that a font has the tables that strict loading requires: a character map, names, and
+/// outlines or bitmaps.
+pub(crate) fn check_required_tables(face: &ttf_parser::Face) -> Result<(), FontLoadingError> {
+    let tables = face.tables();
+    let has_glyphs = tables.glyf.is_some()
+        || tables.cff.is_some()
+        || tables.cff2.is_some()
+        || tables.cbdt.is_some()
+        || tables.sbix.is_some()
+        || tables.svg.is_some();
+    let missing = if tables.cmap.is_none() {
+        b"cmap"
+    } else if tables.name.is_none() {
+        b"name"
+    } else if !has_glyphs {
+        b"glyf"
+    } else {
+        return Ok(());
+    };
+    Err(FontLoadingError::MissingTable {
+        table: u32::from_be_bytes(*missing),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::error::FontLoadingError;
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use crate::metrics::MetricsOverride;
+    use crate::test_fixtures::EB_GARAMOND_OTC;
+
+    #[test]
+    fn test_load_options() {
+        let data = Arc::new(EB_GARAMOND_OTC.to_vec());
+        let regular = Font::builder().load_bytes(data.clone()).unwrap();
+        let italic = Font::builder()
+            .font_index(1)
+            .strict(true)
+            .metrics_override(MetricsOverride {
+                line_gap: Some(0.5),
+                ..MetricsOverride::default()
+            })
+            .load_bytes(data.clone())
+            .unwrap();
+        assert_ne!(regular.postscript_name(), italic.postscript_name());
+        assert_eq!(italic.metrics_override().line_gap, Some(0.5));
+        assert!(Font::builder().font_index(7).load_bytes(data).is_err());
+    }
+
+    #[test]
+    fn test_load_limits() {
+        let data = Arc::new(EB_GARAMOND_OTC.to_vec());
+        let font = Font::builder().load_bytes(data.clone()).unwrap();
+        let glyph_count = font.glyph_count();
+        assert!(Font::builder()
+            .max_glyphs(glyph_count)
+            .max_table_size(data.len())
+            .load_bytes(data.clone())
+            .is_ok());
+        assert!(matches!(
+            Font::builder()
+                .max_glyphs(glyph_count - 1)
+                .load_bytes(data.clone()),
+            Err(FontLoadingError::LimitExceeded)
+        ));
+        assert!(matches!(
+            Font::builder().max_table_size(1024).load_bytes(data),
+            Err(FontLoadingError::LimitExceeded)
+        ));
+    }
+
+    #[test]
+    fn test_file_limits() {
+        use std::{env, fs, process};
+
+        let path = "resources/tests/eb-garamond/EBGaramond12.otc";
+        let font = Font::builder().font_index(1).load_path(path).unwrap();
+        let options = Font::builder()
+            .font_index(1)
+            .max_glyphs(font.glyph_count())
+            .max_table_size(EB_GARAMOND_OTC.len());
+        assert!(options.load_path(path).is_ok());
+        // SAFETY: The test fixtures aren't modified while the tests run.
+        assert!(unsafe { options.clone().mmap(true) }
+            .load_path(path)
+            .is_ok());
+        assert!(matches!(
+            options.clone().max_table_size(1024).load_path(path),
+            Err(FontLoadingError::LimitExceeded)
+        ));
+
+        // A file far longer than its tables is rejected without being read.
+        let padded = env::temp_dir().join(format!("font-kit-limits-test-{}.otc", process::id()));
+        let mut data = EB_GARAMOND_OTC.to_vec();
+        data.resize(data.len() * 64, 0);
+        fs::write(&padded, data).unwrap();
+        let result = options.load_path(&padded);
+        fs::remove_file(&padded).unwrap();
+        assert!(matches!(result, Err(FontLoadingError::LimitExceeded)));
+    }
+}
@@ -14,17 +14,24 @@
 use log::warn;
 use pathfinder_geometry::rect::{RectF, RectI};
 use pathfinder_geometry::transform2d::Transform2F;
-use pathfinder_geometry::vector::Vector2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use std::any::Any;
 use std::sync::Arc;
 
-use crate::canvas::{Canvas, RasterizationOptions};
+use crate::atlas::{self, GlyphAtlas};
+use crate::canvas::{BlendMode, Canvas, CanvasMut, CoverageOptions, Format, RasterizationOptions};
+use crate::deterministic;
 use crate::error::{FontLoadingError, GlyphLoadingError};
 use crate::file_type::FileType;
+use crate::gasp::GaspBehavior;
 use crate::handle::Handle;
 use crate::hinting::HintingOptions;
-use crate::metrics::Metrics;
-use crate::outline::OutlineSink;
+use crate::metrics::{GlyphOrientation, Metrics};
+use crate::outline::{ExactBoundsSink, GlyphPath, OutlineBuilder, OutlineSink, TransformSink};
 use crate::properties::Properties;
+use crate::raster_cache::RasterizedGlyph;
+use crate::synthesis::SynthesisOptions;
+use crate::units::{self, FontUnits, Px, PxRect, ScaleContext, ScaledMetrics};
 
 #[cfg(not(target_arch = "wasm32"))]
 use std::fs::File;
@@ -62,7 +69,27 @@ pub trait Loader: Clone + Sized {
         Loader::from_file(&mut File::open(path)?, font_index)
     }
 
+    /// Loads the font at the given path by memory-mapping the file, if this loader supports it.
+    ///
+    /// The default implementation reads the file with `from_path()`.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the font or any of its clones is alive.
+    #[inline]
+    #[cfg(not(target_arch = "wasm32"))]
+    unsafe fn from_path_mmap<P>(path: P, font_index: u32) -> Result<Self, FontLoadingError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_path(path, font_index)
+    }
+
     /// Creates a font from a native API handle.
+    ///
+    /// # Safety
+    ///
+    /// `native_font` must be a valid handle for the native API that this loader wraps.
     unsafe fn from_native_font(native_font: Self::NativeFont) -> Self;
 
     /// Loads the font pointed to by a handle.
@@ -79,6 +106,7 @@ pub trait Loader: Clone + Sized {
             } => Self::from_path(path, font_index),
             #[cfg(target_arch = "wasm32")]
             Handle::Path { .. } => Err(FontLoadingError::NoFilesystem),
+            Handle::Uri { .. } | Handle::Named { .. } => Self::from_handle(&handle.resolve()?),
         }
     }
 
@@ -130,6 +158,26 @@ pub trait Loader: Clone + Sized {
     /// use cases like "what does character X look like on its own".
     fn glyph_for_char(&self, character: char) -> Option<u32>;
 
+    /// Returns the glyph ID for a Unicode variation sequence: a character followed by a variation
+    /// selector (U+FE00 to U+FE0F or U+E0100 to U+E01EF).
+    ///
+    /// Standardized variation sequences select, for example, text (U+FE0E) or emoji (U+FE0F)
+    /// presentation, and ideographic variation sequences select CJK glyph variants. Returns `None`
+    /// if the font does not define the sequence, in which case callers usually fall back to
+    /// `glyph_for_char()`.
+    #[inline]
+    fn glyph_for_variation(&self, _character: char, _variation_selector: char) -> Option<u32> {
+        warn!("unimplemented");
+        None
+    }
+
+    /// Returns true if the font maps the given character to a glyph other than `.notdef`.
+    #[inline]
+    fn supports_char(&self, character: char) -> bool {
+        self.glyph_for_char(character)
+            .is_some_and(|glyph_id| glyph_id != 0)
+    }
+
     /// Returns the glyph ID for the specified glyph name.
     #[inline]
     fn glyph_by_name(&self, _name: &str) -> Option<u32> {
@@ -152,20 +200,168 @@ pub trait Loader: Clone + Sized {
     where
         S: OutlineSink;
 
+    /// Returns the vector path for a glyph as an owned list of commands, in font units.
+    ///
+    /// This is `outline()` without hinting, for callers that would rather not implement
+    /// `OutlineSink`.
+    fn glyph_path(&self, glyph_id: u32) -> Result<GlyphPath, GlyphLoadingError> {
+        let mut path = GlyphPath::new();
+        self.outline(glyph_id, HintingOptions::None, &mut path)?;
+        Ok(path)
+    }
+
+    /// Sends the vector path for a glyph to a sink like `outline()`, emboldened and slanted to
+    /// synthesize a missing style. See the `synthesis` module.
+    fn outline_synthesized<S>(
+        &self,
+        glyph_id: u32,
+        hinting_mode: HintingOptions,
+        synthesis: &SynthesisOptions,
+        sink: &mut S,
+    ) -> Result<(), GlyphLoadingError>
+    where
+        S: OutlineSink,
+    {
+        if !synthesis.is_synthesized() {
+            return self.outline(glyph_id, hinting_mode, sink);
+        }
+        let mut builder = OutlineBuilder::new();
+        self.outline(glyph_id, hinting_mode, &mut builder)?;
+        let mut outline = builder.into_outline();
+        synthesis.apply(&mut outline, self.metrics().units_per_em);
+        outline.copy_to(sink);
+        Ok(())
+    }
+
     /// Returns the boundaries of a glyph in font units. The origin of the coordinate
     /// space is at the bottom left.
     fn typographic_bounds(&self, glyph_id: u32) -> Result<RectF, GlyphLoadingError>;
 
-    /// Returns the distance from the origin of the glyph with the given ID to the next, in font
-    /// units.
+    /// Returns the horizontal distance from the origin of a glyph to the left edge of its ink, in
+    /// font units. It is negative for glyphs that extend to the left of their origin.
+    fn left_side_bearing(&self, glyph_id: u32) -> Result<f32, GlyphLoadingError> {
+        Ok(self.typographic_bounds(glyph_id)?.min_x())
+    }
+
+    /// Returns the horizontal distance from the right edge of a glyph's ink to its advance, in
+    /// font units. It is negative for glyphs that extend past their advance, such as some italic
+    /// glyphs.
+    fn right_side_bearing(&self, glyph_id: u32) -> Result<f32, GlyphLoadingError> {
+        Ok(self.advance(glyph_id)?.x() - self.typographic_bounds(glyph_id)?.max_x())
+    }
+
+    /// Returns the exact bounds of a glyph's outline at the given size, after applying
+    /// `transform`.
+    ///
+    /// The bounds are in the same units as `point_size`, with the glyph origin at the origin and
+    /// the y axis pointing up. Unlike `typographic_bounds()`, these are computed from the outline
+    /// itself, so they are tight even when the glyph is rotated or skewed, and curves count only as
+    /// far as they reach. This makes them suitable for hit-testing carets and selections. Glyphs
+    /// without an outline, such as spaces, have empty bounds at the origin.
+    fn ink_bounds(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+    ) -> Result<RectF, GlyphLoadingError> {
+        let scale = point_size / self.metrics().units_per_em as f32;
+        let mut bounds = ExactBoundsSink::new();
+        self.outline(
+            glyph_id,
+            HintingOptions::None,
+            &mut TransformSink::new(&mut bounds, transform * Transform2F::from_scale(scale)),
+        )?;
+        Ok(bounds.bounds.unwrap_or_default())
+    }
+
+    /// Returns the distance from the origin of the glyph with the given ID to the next in
+    /// horizontal layout, in font units.
     fn advance(&self, glyph_id: u32) -> Result<Vector2F, GlyphLoadingError>;
 
+    /// Returns the distance from the origin of the glyph with the given ID to the next when laid
+    /// out in the given orientation, in font units.
+    ///
+    /// For `GlyphOrientation::Horizontal` this is the same as `advance()`. For
+    /// `GlyphOrientation::Vertical` the advance is returned as a positive `y` value, the distance
+    /// the pen moves down. Fonts without vertical metrics get a synthesized vertical advance of
+    /// the ascent minus the descent, or one em if the font has no ascent or descent either.
+    fn advance_with_orientation(
+        &self,
+        glyph_id: u32,
+        orientation: GlyphOrientation,
+    ) -> Result<Vector2F, GlyphLoadingError> {
+        let advance = self.advance(glyph_id)?;
+        match orientation {
+            GlyphOrientation::Horizontal => Ok(advance),
+            GlyphOrientation::Vertical => {
+                let metrics = self.metrics();
+                let mut height = metrics.ascent - metrics.descent;
+                if height <= 0.0 {
+                    height = metrics.units_per_em as f32;
+                }
+                Ok(Vector2F::new(0.0, height))
+            }
+        }
+    }
+
     /// Returns the amount that the given glyph should be displaced from the origin.
     fn origin(&self, glyph_id: u32) -> Result<Vector2F, GlyphLoadingError>;
 
+    /// Returns the amount that the given glyph should be displaced from the pen position when it
+    /// is laid out in the given orientation, in font units.
+    ///
+    /// For `GlyphOrientation::Horizontal` this is the same as `origin()`. For
+    /// `GlyphOrientation::Vertical` it also moves the glyph so that the pen position is at the
+    /// top center of the glyph. The default implementation estimates the vertical origin from the
+    /// glyph's horizontal advance and the font's ascent; loaders override it to use the font's
+    /// vertical metrics.
+    fn origin_with_orientation(
+        &self,
+        glyph_id: u32,
+        orientation: GlyphOrientation,
+    ) -> Result<Vector2F, GlyphLoadingError> {
+        let origin = self.origin(glyph_id)?;
+        match orientation {
+            GlyphOrientation::Horizontal => Ok(origin),
+            GlyphOrientation::Vertical => {
+                let advance = self.advance(glyph_id)?;
+                Ok(origin - Vector2F::new(advance.x() * 0.5, self.metrics().ascent))
+            }
+        }
+    }
+
     /// Retrieves various metrics that apply to the entire font.
     fn metrics(&self) -> Metrics;
 
+    /// Returns a scale context that converts this font's font units to pixels when it is rendered
+    /// at `px_per_em` pixels per em.
+    #[inline]
+    fn scale_context(&self, px_per_em: Px) -> ScaleContext {
+        ScaleContext::new(self.metrics().units_per_em, px_per_em)
+    }
+
+    /// Returns the horizontal advance of a glyph in pixels, scaled by a context from
+    /// `scale_context()`.
+    fn advance_px(&self, glyph_id: u32, scale: ScaleContext) -> Result<Px, GlyphLoadingError> {
+        Ok(scale.to_px(FontUnits(self.advance(glyph_id)?.x())))
+    }
+
+    /// Returns the typographic bounds of a glyph in pixels, scaled by a context from
+    /// `scale_context()`. The y axis points up.
+    fn typographic_bounds_px(
+        &self,
+        glyph_id: u32,
+        scale: ScaleContext,
+    ) -> Result<PxRect, GlyphLoadingError> {
+        Ok(scale.bounds_to_px(self.typographic_bounds(glyph_id)?))
+    }
+
+    /// Returns the font-wide metrics in pixels, scaled by a context from `scale_context()`.
+    #[inline]
+    fn metrics_px(&self, scale: ScaleContext) -> ScaledMetrics {
+        scale.metrics_to_px(&self.metrics())
+    }
+
     /// Returns a handle to this font, if possible.
     ///
     /// This is useful if you want to open the font with a different loader.
@@ -178,9 +374,23 @@ pub trait Loader: Clone + Sized {
     /// Attempts to return the raw font data (contents of the font file).
     ///
     /// If this font is a member of a collection, this function returns the data for the entire
-    /// collection.
+    /// collection. Fonts loaded from the same file may share this buffer; when accounting for
+    /// memory use, compare buffers with `Arc::ptr_eq()` to avoid counting them twice.
     fn copy_font_data(&self) -> Option<Arc<Vec<u8>>>;
 
+    /// Calls `f` with the raw font data, as `copy_font_data()` returns it, and returns its result.
+    ///
+    /// Unlike `copy_font_data()`, this borrows the data where the loader can, so it doesn't copy
+    /// the contents of memory-mapped fonts. Returns `None` if the font data is unavailable.
+    #[inline]
+    fn with_font_data<F, R>(&self, f: F) -> Option<R>
+    where
+        Self: Sized,
+        F: FnOnce(&[u8]) -> R,
+    {
+        self.copy_font_data().map(|font_data| f(&font_data))
+    }
+
     /// Returns true if and only if the font loader can perform hinting in the requested way.
     ///
     /// Some APIs support only rasterizing glyphs with hinting, not retriving hinted outlines. If
@@ -196,6 +406,11 @@ pub trait Loader: Clone + Sized {
     /// Returns the pixel boundaries that the glyph will take up when rendered using this loader's
     /// rasterizer at the given `point_size` and `transform`. The origin of the coordinate space is
     /// at the top left.
+    ///
+    /// This doesn't rasterize anything, so it is cheap enough to size canvases and atlas slots
+    /// before calling `rasterize_glyph()`. The default implementation measures the outline
+    /// itself, so the bounds stay tight when `transform` rotates or skews the glyph. Glyphs
+    /// without an outline, such as bitmap glyphs, are measured by their typographic bounds.
     fn raster_bounds(
         &self,
         glyph_id: u32,
@@ -204,9 +419,29 @@ pub trait Loader: Clone + Sized {
         _: HintingOptions,
         _: RasterizationOptions,
     ) -> Result<RectI, GlyphLoadingError> {
-        let typographic_bounds = self.typographic_bounds(glyph_id)?;
-        let typographic_raster_bounds =
-            typographic_bounds * (point_size / self.metrics().units_per_em as f32);
+        // Raster coordinates point down.
+        let scale = point_size / self.metrics().units_per_em as f32;
+        let mut ink_bounds = ExactBoundsSink::new();
+        self.outline(
+            glyph_id,
+            HintingOptions::None,
+            &mut TransformSink::new(
+                &mut ink_bounds,
+                transform * Transform2F::from_scale(Vector2F::new(scale, -scale)),
+            ),
+        )?;
+        if let Some(ink_bounds) = ink_bounds.bounds {
+            return Ok(ink_bounds.round_out().to_i32());
+        }
+
+        // The glyph exists, since it has an outline, so a glyph without bounds draws nothing.
+        let typographic_bounds = match self.typographic_bounds(glyph_id) {
+            Ok(typographic_bounds) => typographic_bounds,
+            Err(_) => return Ok(RectI::default()),
+        };
+        let typographic_raster_bounds = self
+            .scale_context(Px(point_size))
+            .rect_to_px(typographic_bounds);
 
         // Translate the origin to "origin is top left" coordinate system.
         let new_origin = Vector2F::new(
@@ -236,13 +471,227 @@ pub trait Loader: Clone + Sized {
         rasterization_options: RasterizationOptions,
     ) -> Result<(), GlyphLoadingError>;
 
+    /// Rasterizes a glyph into a view of an existing buffer, such as a region of an atlas page or
+    /// a framebuffer, with the given transform.
+    ///
+    /// The view's top left pixel is the origin of the transformed coordinate space, as with
+    /// `rasterize_glyph()`, and pixels outside the view are never written. Every pixel of the
+    /// view is overwritten, and those the glyph doesn't cover are cleared, so the view needn't be
+    /// cleared first, but glyphs can't be stacked in one view this way. The default
+    /// implementation rasterizes into a temporary canvas of the view's size and copies it over;
+    /// the pure-Rust and FreeType loaders override it to draw straight into the view.
+    fn rasterize_glyph_into(
+        &self,
+        target: &mut CanvasMut,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        let mut canvas = Canvas::new(target.size, target.format);
+        self.rasterize_glyph(
+            &mut canvas,
+            glyph_id,
+            point_size,
+            transform,
+            hinting_options,
+            rasterization_options,
+        )?;
+        target.blend(&canvas.as_view(), Vector2I::default(), BlendMode::Source);
+        Ok(())
+    }
+
+    /// Rasterizes a glyph like `rasterize_glyph()`, then applies gamma correction and stem
+    /// darkening to its coverage as `Canvas::adjust_coverage()` does.
+    ///
+    /// Bilevel rendering has no partial coverage to adjust, so `coverage_options` is ignored for
+    /// it. The size used for stem darkening includes the scale of `transform`.
+    #[allow(clippy::too_many_arguments)]
+    fn rasterize_glyph_adjusted(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+        coverage_options: CoverageOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        let pixels_per_em = units::pixels_per_em(point_size, &transform);
+        let rasterization_options = rasterization_options.resolve(self, pixels_per_em);
+        self.rasterize_glyph(
+            canvas,
+            glyph_id,
+            point_size,
+            transform,
+            hinting_options,
+            rasterization_options,
+        )?;
+        if let RasterizationOptions::GrayscaleAa | RasterizationOptions::SubpixelAa =
+            rasterization_options
+        {
+            canvas.adjust_coverage(coverage_options, pixels_per_em);
+        }
+        Ok(())
+    }
+
+    /// Rasterizes a glyph like `rasterize_glyph()`, but with the fixed-point rasterizer in the
+    /// `deterministic` module instead of the loader's own, so that the output is bit-identical on
+    /// every platform and processor for the same outline.
+    ///
+    /// Hinting differs between platforms, so the outline is never hinted. `FontDefault` is
+    /// resolved from the font's `gasp` table, and `SubpixelAa` is rendered without filtering.
+    /// `rasterize_glyph()` calls this for `RasterizationOptions::Deterministic`.
+    fn rasterize_glyph_deterministic(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        deterministic::rasterize_glyph_into(
+            self,
+            &mut canvas.as_view_mut(),
+            glyph_id,
+            point_size,
+            transform,
+            rasterization_options,
+        )
+    }
+
+    /// Rasterizes a glyph like `rasterize_glyph()`, emboldened and slanted to synthesize a missing
+    /// style. See the `synthesis` module.
+    ///
+    /// The slant is added to `transform`. The default implementation emboldens the rendered glyph
+    /// by widening its coverage to the right, as FreeType does for bitmap glyphs, so the canvas
+    /// should leave room for that; loaders that rasterize outlines themselves can override this to
+    /// embolden the outline instead.
+    #[allow(clippy::too_many_arguments)]
+    fn rasterize_glyph_synthesized(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+        synthesis: &SynthesisOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        // Raster coordinates point down, so the slant is the other way around.
+        let slant = Transform2F::row_major(1.0, -synthesis.oblique, 0.0, 1.0, 0.0, 0.0);
+        self.rasterize_glyph(
+            canvas,
+            glyph_id,
+            point_size,
+            transform * slant,
+            hinting_options,
+            rasterization_options,
+        )?;
+        if synthesis.embolden > 0.0 {
+            let pixels_per_em = units::pixels_per_em(point_size, &transform);
+            canvas.embolden(synthesis.embolden * pixels_per_em);
+        }
+        Ok(())
+    }
+
+    /// Returns true if the glyph has color, as color emoji do, which is lost when it is rendered
+    /// to a single-channel format.
+    ///
+    /// The default implementation returns false.
+    #[inline]
+    fn glyph_has_color(&self, _glyph_id: u32) -> bool {
+        false
+    }
+
+    /// Rasterizes a glyph into a new canvas in the best of the acceptable formats, which are
+    /// listed most preferred first. See `Format::negotiate()` for how the format is chosen.
+    ///
+    /// The canvas covers the glyph's raster bounds, and its `format` reports the format that was
+    /// used. This spares callers from guessing the format of each glyph up front.
+    fn rasterize_glyph_negotiated(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+        acceptable_formats: &[Format],
+    ) -> Result<RasterizedGlyph, GlyphLoadingError> {
+        let format = Format::negotiate(
+            acceptable_formats,
+            self.glyph_has_color(glyph_id),
+            rasterization_options,
+        );
+        let bounds = self.raster_bounds(
+            glyph_id,
+            point_size,
+            transform,
+            hinting_options,
+            rasterization_options,
+        )?;
+        let mut canvas = Canvas::new(bounds.size(), format);
+        self.rasterize_glyph(
+            &mut canvas,
+            glyph_id,
+            point_size,
+            Transform2F::from_translation(-bounds.origin().to_f32()) * transform,
+            hinting_options,
+            rasterization_options,
+        )?;
+        Ok(RasterizedGlyph {
+            canvas,
+            origin: bounds.origin(),
+        })
+    }
+
+    /// Rasterizes many glyphs into a single image, such as a texture atlas for a GPU text
+    /// renderer, in one call.
+    ///
+    /// The atlas is `width` pixels wide, or as wide as the widest glyph if that is wider, and as
+    /// tall as the glyphs need. Every glyph is measured with `raster_bounds()` and rasterized
+    /// straight into its slot with `rasterize_glyph_into()`. See the `atlas` module.
+    #[allow(clippy::too_many_arguments)]
+    fn rasterize_glyphs_to_atlas(
+        &self,
+        glyph_ids: &[u32],
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+        format: Format,
+        width: i32,
+    ) -> Result<GlyphAtlas, GlyphLoadingError> {
+        atlas::rasterize_glyphs_to_atlas(
+            self,
+            glyph_ids,
+            point_size,
+            transform,
+            hinting_options,
+            rasterization_options,
+            format,
+            width,
+        )
+    }
+
     /// Get font fallback results for the given text and locale.
     ///
     /// The `locale` argument is a language tag such as `"en-US"` or `"zh-Hans-CN"`.
+    ///
+    /// With the `last-resort` feature, the last font returned is always the font in the
+    /// `last_resort` module, which draws every character as a box.
     fn get_fallbacks(&self, text: &str, locale: &str) -> FallbackResult<Self>;
 
     /// Returns the OpenType font table with the given tag, if the table exists.
     fn load_font_table(&self, table_tag: u32) -> Option<Box<[u8]>>;
+
+    /// Returns how the font's `gasp` table recommends rasterizing it at `ppem` pixels per em, or
+    /// `None` if the font has no `gasp` table or the table doesn't cover that size.
+    fn gasp_behavior(&self, ppem: u16) -> Option<GaspBehavior> {
+        let gasp = self.load_font_table(u32::from_be_bytes(*b"gasp"))?;
+        GaspBehavior::from_table(&gasp, ppem)
+    }
 }
 
 /// The result of a fallback query.
@@ -263,3 +712,323 @@ pub struct FallbackFont<Font> {
     pub scale: f32,
     // TODO: add font simulation data
 }
+
+/// An object-safe counterpart of `Loader`, so that fonts from loaders chosen at runtime, or from
+/// plugins, can be stored together as `Box<dyn AnyFont>`.
+///
+/// Every loader implements this trait. The methods are those of `Loader` that don't construct
+/// fonts or use associated types; outlines go to a `&mut dyn OutlineSink` instead of a generic
+/// sink. `as_any()` recovers the concrete font.
+///
+/// Don't import this trait alongside `Loader` in code that calls methods on concrete fonts, since
+/// the calls would then be ambiguous.
+pub trait AnyFont: Any {
+    /// Returns the PostScript name of the font. See `Loader::postscript_name()`.
+    fn postscript_name(&self) -> Option<String>;
+
+    /// Returns the full name of the font. See `Loader::full_name()`.
+    fn full_name(&self) -> String;
+
+    /// Returns the name of the font family. See `Loader::family_name()`.
+    fn family_name(&self) -> String;
+
+    /// Returns true if and only if the font is monospace. See `Loader::is_monospace()`.
+    fn is_monospace(&self) -> bool;
+
+    /// Returns the CSS properties of the font. See `Loader::properties()`.
+    fn properties(&self) -> Properties;
+
+    /// Returns the number of glyphs in the font. See `Loader::glyph_count()`.
+    fn glyph_count(&self) -> u32;
+
+    /// Returns the usual glyph ID for a Unicode character. See `Loader::glyph_for_char()`.
+    fn glyph_for_char(&self, character: char) -> Option<u32>;
+
+    /// Returns the glyph ID for the specified glyph name. See `Loader::glyph_by_name()`.
+    fn glyph_by_name(&self, name: &str) -> Option<u32>;
+
+    /// Sends the vector path for a glyph to a sink. See `Loader::outline()`.
+    fn outline(
+        &self,
+        glyph_id: u32,
+        hinting_mode: HintingOptions,
+        sink: &mut dyn OutlineSink,
+    ) -> Result<(), GlyphLoadingError>;
+
+    /// Returns the boundaries of a glyph in font units. See `Loader::typographic_bounds()`.
+    fn typographic_bounds(&self, glyph_id: u32) -> Result<RectF, GlyphLoadingError>;
+
+    /// Returns the distance from the origin of the glyph with the given ID to the next, in font
+    /// units. See `Loader::advance()`.
+    fn advance(&self, glyph_id: u32) -> Result<Vector2F, GlyphLoadingError>;
+
+    /// Returns the amount that the given glyph should be displaced from the origin. See
+    /// `Loader::origin()`.
+    fn origin(&self, glyph_id: u32) -> Result<Vector2F, GlyphLoadingError>;
+
+    /// Retrieves various metrics that apply to the entire font. See `Loader::metrics()`.
+    fn metrics(&self) -> Metrics;
+
+    /// Returns a handle to this font, if possible. See `Loader::handle()`.
+    fn handle(&self) -> Option<Handle>;
+
+    /// Attempts to return the raw font data. See `Loader::copy_font_data()`.
+    fn copy_font_data(&self) -> Option<Arc<Vec<u8>>>;
+
+    /// Returns true if and only if the font loader can perform hinting in the requested way. See
+    /// `Loader::supports_hinting_options()`.
+    fn supports_hinting_options(
+        &self,
+        hinting_options: HintingOptions,
+        for_rasterization: bool,
+    ) -> bool;
+
+    /// Returns the pixel boundaries that the glyph will take up when rendered. See
+    /// `Loader::raster_bounds()`.
+    fn raster_bounds(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<RectI, GlyphLoadingError>;
+
+    /// Rasterizes a glyph to a canvas. See `Loader::rasterize_glyph()`.
+    fn rasterize_glyph(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(), GlyphLoadingError>;
+
+    /// Rasterizes a glyph into a view of an existing buffer. See
+    /// `Loader::rasterize_glyph_into()`.
+    fn rasterize_glyph_into(
+        &self,
+        target: &mut CanvasMut,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(), GlyphLoadingError>;
+
+    /// Returns the OpenType font table with the given tag, if the table exists. See
+    /// `Loader::load_font_table()`.
+    fn load_font_table(&self, table_tag: u32) -> Option<Box<[u8]>>;
+
+    /// Returns a copy of this font.
+    fn clone_box(&self) -> Box<dyn AnyFont>;
+
+    /// Returns this font as `Any`, so that it can be downcast to its loader's font type.
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<L> AnyFont for L
+where
+    L: Loader + 'static,
+{
+    #[inline]
+    fn postscript_name(&self) -> Option<String> {
+        Loader::postscript_name(self)
+    }
+
+    #[inline]
+    fn full_name(&self) -> String {
+        Loader::full_name(self)
+    }
+
+    #[inline]
+    fn family_name(&self) -> String {
+        Loader::family_name(self)
+    }
+
+    #[inline]
+    fn is_monospace(&self) -> bool {
+        Loader::is_monospace(self)
+    }
+
+    #[inline]
+    fn properties(&self) -> Properties {
+        Loader::properties(self)
+    }
+
+    #[inline]
+    fn glyph_count(&self) -> u32 {
+        Loader::glyph_count(self)
+    }
+
+    #[inline]
+    fn glyph_for_char(&self, character: char) -> Option<u32> {
+        Loader::glyph_for_char(self, character)
+    }
+
+    #[inline]
+    fn glyph_by_name(&self, name: &str) -> Option<u32> {
+        Loader::glyph_by_name(self, name)
+    }
+
+    #[inline]
+    fn outline(
+        &self,
+        glyph_id: u32,
+        hinting_mode: HintingOptions,
+        mut sink: &mut dyn OutlineSink,
+    ) -> Result<(), GlyphLoadingError> {
+        Loader::outline(self, glyph_id, hinting_mode, &mut sink)
+    }
+
+    #[inline]
+    fn typographic_bounds(&self, glyph_id: u32) -> Result<RectF, GlyphLoadingError> {
+        Loader::typographic_bounds(self, glyph_id)
+    }
+
+    #[inline]
+    fn advance(&self, glyph_id: u32) -> Result<Vector2F, GlyphLoadingError> {
+        Loader::advance(self, glyph_id)
+    }
+
+    #[inline]
+    fn origin(&self, glyph_id: u32) -> Result<Vector2F, GlyphLoadingError> {
+        Loader::origin(self, glyph_id)
+    }
+
+    #[inline]
+    fn metrics(&self) -> Metrics {
+        Loader::metrics(self)
+    }
+
+    #[inline]
+    fn handle(&self) -> Option<Handle> {
+        Loader::handle(self)
+    }
+
+    #[inline]
+    fn copy_font_data(&self) -> Option<Arc<Vec<u8>>> {
+        Loader::copy_font_data(self)
+    }
+
+    #[inline]
+    fn supports_hinting_options(
+        &self,
+        hinting_options: HintingOptions,
+        for_rasterization: bool,
+    ) -> bool {
+        Loader::supports_hinting_options(self, hinting_options, for_rasterization)
+    }
+
+    #[inline]
+    fn raster_bounds(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<RectI, GlyphLoadingError> {
+        Loader::raster_bounds(
+            self,
+            glyph_id,
+            point_size,
+            transform,
+            hinting_options,
+            rasterization_options,
+        )
+    }
+
+    #[inline]
+    fn rasterize_glyph(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        Loader::rasterize_glyph(
+            self,
+            canvas,
+            glyph_id,
+            point_size,
+            transform,
+            hinting_options,
+            rasterization_options,
+        )
+    }
+
+    #[inline]
+    fn rasterize_glyph_into(
+        &self,
+        target: &mut CanvasMut,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        Loader::rasterize_glyph_into(
+            self,
+            target,
+            glyph_id,
+            point_size,
+            transform,
+            hinting_options,
+            rasterization_options,
+        )
+    }
+
+    #[inline]
+    fn load_font_table(&self, table_tag: u32) -> Option<Box<[u8]>> {
+        Loader::load_font_table(self, table_tag)
+    }
+
+    #[inline]
+    fn clone_box(&self) -> Box<dyn AnyFont> {
+        Box::new(self.clone())
+    }
+
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Clone for Box<dyn AnyFont> {
+    #[inline]
+    fn clone(&self) -> Box<dyn AnyFont> {
+        (**self).clone_box()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AnyFont, Loader};
+    use crate::font::Font;
+    use crate::hinting::HintingOptions;
+    use crate::outline::GlyphPath;
+    use crate::test_fixtures::INCONSOLATA;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_any_font() {
+        let fonts: Vec<Box<dyn AnyFont>> = vec![Box::new(
+            Font::from_bytes(Arc::new(INCONSOLATA.to_vec()), 0).unwrap(),
+        )];
+        let font = fonts[0].clone();
+        assert_eq!(font.family_name(), "Inconsolata");
+
+        let glyph_id = font.glyph_for_char('o').unwrap();
+        let mut path = GlyphPath::new();
+        font.outline(glyph_id, HintingOptions::None, &mut path)
+            .unwrap();
+        assert!(!path.commands.is_empty());
+
+        let concrete = font.as_any().downcast_ref::<Font>().unwrap();
+        assert!(concrete.same_font_as(fonts[0].as_any().downcast_ref::<Font>().unwrap()));
+    }
+}
@@ -42,10 +42,13 @@ use crate::error::{FontLoadingError, GlyphLoadingError};
 use crate::file_type::FileType;
 use crate::handle::Handle;
 use crate::hinting::HintingOptions;
+#[cfg(feature = "last-resort")]
+use crate::last_resort;
 use crate::loader::{FallbackResult, Loader};
 use crate::metrics::Metrics;
 use crate::outline::OutlineSink;
 use crate::properties::{Properties, Stretch, Style, Weight};
+use crate::units;
 use crate::utils;
 
 const TTC_TAG: [u8; 4] = [b't', b't', b'c', b'f'];
@@ -142,6 +145,18 @@ impl Font {
             },
         }
 
+        // Fonts from collections, data fork fonts, and fonts created from memory are rebuilt from
+        // their tables, so that the data is that of this font alone, as `from_bytes()` leaves it.
+        let is_single_font = match font_data {
+            FontData::Memory(ref data) => font_is_single_otf(data),
+            FontData::Unavailable => false,
+        };
+        if !is_single_font {
+            if let Some(data) = font_data_from_tables(&core_text_font) {
+                font_data = FontData::Memory(Arc::new(data));
+            }
+        }
+
         Font {
             core_text_font,
             font_data,
@@ -405,6 +420,11 @@ impl Font {
         );
         let bounding_box = bounding_box * units_per_point as f32;
 
+        // Core Text doesn't report the strikeout, so read it from the `OS/2` table.
+        let strikeout = self
+            .load_font_table(u32::from_be_bytes(*b"OS/2"))
+            .and_then(|os2| ttf_parser::os2::Table::parse(&os2).map(|os2| os2.strikeout_metrics()));
+
         Metrics {
             units_per_em,
             ascent: (self.core_text_font.ascent() * units_per_point) as f32,
@@ -415,8 +435,11 @@ impl Font {
                 as f32,
             cap_height: (self.core_text_font.cap_height() * units_per_point) as f32,
             x_height: (self.core_text_font.x_height() * units_per_point) as f32,
+            strikeout_position: strikeout.map_or(0.0, |strikeout| strikeout.position as f32),
+            strikeout_thickness: strikeout.map_or(0.0, |strikeout| strikeout.thickness as f32),
             bounding_box,
         }
+        .with_estimated_strikeout()
     }
 
     /// Returns a handle to this font, if possible.
@@ -480,10 +503,37 @@ impl Font {
         hinting_options: HintingOptions,
         rasterization_options: RasterizationOptions,
     ) -> Result<(), GlyphLoadingError> {
+        if rasterization_options.is_deterministic() {
+            return self.rasterize_glyph_deterministic(
+                canvas,
+                glyph_id,
+                point_size,
+                transform,
+                rasterization_options,
+            );
+        }
         if canvas.size.x() == 0 || canvas.size.y() == 0 {
             return Ok(());
         }
 
+        let pixels_per_em = units::pixels_per_em(point_size, &transform);
+        let rasterization_options = rasterization_options.resolve(self, pixels_per_em);
+        if let RasterizationOptions::BilevelThreshold(threshold) = rasterization_options {
+            // Render with antialiasing, then threshold the coverage.
+            let mut temp_canvas = Canvas::new(canvas.size, Format::A8);
+            self.rasterize_glyph(
+                &mut temp_canvas,
+                glyph_id,
+                point_size,
+                transform,
+                hinting_options,
+                RasterizationOptions::GrayscaleAa,
+            )?;
+            temp_canvas.threshold(threshold);
+            canvas.blit_from_canvas(&temp_canvas);
+            return Ok(());
+        }
+
         let (cg_color_space, cg_image_format) =
             match format_to_cg_color_space_and_image_format(canvas.format) {
                 None => {
@@ -521,7 +571,7 @@ impl Font {
             Format::Rgba32 | Format::Rgb24 => {
                 core_graphics_context.set_rgb_fill_color(0.0, 0.0, 0.0, 0.0);
             }
-            Format::A8 => core_graphics_context.set_gray_fill_color(0.0, 0.0),
+            Format::A8 | Format::A1 => core_graphics_context.set_gray_fill_color(0.0, 0.0),
         }
 
         let core_graphics_size = CGSize::new(canvas.size.x() as f64, canvas.size.y() as f64);
@@ -533,7 +583,11 @@ impl Font {
                 core_graphics_context.set_should_smooth_fonts(false);
                 core_graphics_context.set_should_antialias(false);
             }
-            RasterizationOptions::GrayscaleAa | RasterizationOptions::SubpixelAa => {
+            RasterizationOptions::GrayscaleAa
+            | RasterizationOptions::BilevelThreshold(_)
+            | RasterizationOptions::SubpixelAa
+            | RasterizationOptions::FontDefault
+            | RasterizationOptions::Deterministic(_) => {
                 // FIXME(pcwalton): These shouldn't be handled the same!
                 core_graphics_context.set_allows_font_smoothing(true);
                 core_graphics_context.set_should_smooth_fonts(true);
@@ -545,7 +599,7 @@ impl Font {
             Format::Rgba32 | Format::Rgb24 => {
                 core_graphics_context.set_rgb_fill_color(1.0, 1.0, 1.0, 1.0);
             }
-            Format::A8 => core_graphics_context.set_gray_fill_color(1.0, 1.0),
+            Format::A8 | Format::A1 => core_graphics_context.set_gray_fill_color(1.0, 1.0),
         }
 
         // CoreGraphics origin is in the bottom left. This makes behavior consistent.
@@ -590,10 +644,13 @@ impl Font {
     /// would use CTFontCopyDefaultCascadeListForLanguages.
     fn get_fallbacks(&self, text: &str, _locale: &str) -> FallbackResult<Font> {
         warn!("unsupported");
-        FallbackResult {
+        let fallbacks = FallbackResult {
             fonts: Vec::new(),
             valid_len: text.len(),
-        }
+        };
+        #[cfg(feature = "last-resort")]
+        let fallbacks = last_resort::append(fallbacks);
+        fallbacks
     }
 
     #[inline]
@@ -817,13 +874,15 @@ fn font_is_collection(header: &[u8]) -> bool {
 
 fn read_number_of_fonts_from_otc_header(header: &[u8]) -> Result<u32, FontLoadingError> {
     if !font_is_collection(header) {
-        return Err(FontLoadingError::UnknownFormat);
+        return Err(FontLoadingError::unknown_format(header));
     }
     Ok((&header[8..]).read_u32::<BigEndian>()?)
 }
 
 fn get_slice_from_start(slice: &[u8], start: usize) -> Result<&[u8], FontLoadingError> {
-    slice.get(start..).ok_or(FontLoadingError::Parse)
+    slice.get(start..).ok_or(FontLoadingError::Truncated {
+        offset: slice.len(),
+    })
 }
 
 // Unpacks an OTC font "in-place".
@@ -853,7 +912,7 @@ fn unpack_otc_font(data: &mut [u8], font_index: u32) -> Result<(), FontLoadingEr
 // NB: This assumes little-endian, but that's true for all extant Apple hardware.
 fn format_to_cg_color_space_and_image_format(format: Format) -> Option<(CGColorSpace, u32)> {
     match format {
-        Format::Rgb24 => {
+        Format::Rgb24 | Format::A1 => {
             // Unsupported by Core Graphics.
             None
         }
@@ -865,6 +924,23 @@ fn format_to_cg_color_space_and_image_format(format: Format) -> Option<(CGColorS
     }
 }
 
+// Assembles a font file from the tables of a Core Text font.
+fn font_data_from_tables(core_text_font: &CTFont) -> Option<Vec<u8>> {
+    let tables: Vec<(u32, Vec<u8>)> = core_text_font
+        .get_available_font_tables()?
+        .iter()
+        .filter_map(|tag| {
+            let tag = *tag;
+            let data = core_text_font.get_font_table(tag)?;
+            Some((tag, data.bytes().to_vec()))
+        })
+        .collect();
+    if tables.is_empty() {
+        return None;
+    }
+    Some(utils::build_sfnt(&tables))
+}
+
 fn font_is_single_otf(header: &[u8]) -> bool {
     header.len() >= 4
         && ((&header[..4]).read_u32::<BigEndian>().unwrap() == 0x00010000
@@ -927,10 +1003,12 @@ mod test {
     use super::Font;
     use crate::properties::{Stretch, Weight};
 
+    #[cfg(feature = "source")]
+    use crate::loader::Loader;
     #[cfg(feature = "source")]
     use crate::source::SystemSource;
 
-    static TEST_FONT_POSTSCRIPT_NAME: &'static str = "ArialMT";
+    static TEST_FONT_POSTSCRIPT_NAME: &str = "ArialMT";
 
     #[cfg(feature = "source")]
     #[test]
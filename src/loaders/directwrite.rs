@@ -50,10 +50,13 @@ use crate::error::{FontLoadingError, GlyphLoadingError};
 use crate::file_type::FileType;
 use crate::handle::Handle;
 use crate::hinting::HintingOptions;
+#[cfg(feature = "last-resort")]
+use crate::last_resort;
 use crate::loader::{FallbackFont, FallbackResult, Loader};
 use crate::metrics::Metrics;
 use crate::outline::{OutlineBuilder, OutlineSink};
 use crate::properties::{Properties, Stretch, Style, Weight};
+use crate::units;
 
 const ERROR_BOUND: f32 = 0.0001;
 
@@ -367,7 +370,7 @@ impl Font {
         // Unfortunately, the bounding box info is Windows 8 only, so we need a fallback. First,
         // try to grab it from the font. If that fails, we try the `head` table. If there's no
         // `head` table, we give up.
-        match dwrite_font.metrics() {
+        let metrics = match dwrite_font.metrics() {
             DWriteFontMetrics::Metrics1(metrics) => Metrics {
                 units_per_em: metrics.designUnitsPerEm as u32,
                 ascent: metrics.ascent as f32,
@@ -377,6 +380,8 @@ impl Font {
                 x_height: metrics.xHeight as f32,
                 underline_position: metrics.underlinePosition as f32,
                 underline_thickness: metrics.underlineThickness as f32,
+                strikeout_position: metrics.strikethroughPosition as f32,
+                strikeout_thickness: metrics.strikethroughThickness as f32,
                 bounding_box: RectI::new(
                     Vector2I::new(metrics.glyphBoxLeft as i32, metrics.glyphBoxBottom as i32),
                     Vector2I::new(
@@ -414,10 +419,13 @@ impl Font {
                     x_height: metrics.xHeight as f32,
                     underline_position: metrics.underlinePosition as f32,
                     underline_thickness: metrics.underlineThickness as f32,
+                    strikeout_position: metrics.strikethroughPosition as f32,
+                    strikeout_thickness: metrics.strikethroughThickness as f32,
                     bounding_box,
                 }
             }
-        }
+        };
+        metrics.with_estimated_strikeout()
     }
 
     /// Returns a handle to this font, if possible.
@@ -455,6 +463,8 @@ impl Font {
         hinting_options: HintingOptions,
         rasterization_options: RasterizationOptions,
     ) -> Result<RectI, GlyphLoadingError> {
+        let pixels_per_em = units::pixels_per_em(point_size, &transform);
+        let rasterization_options = rasterization_options.resolve(self, pixels_per_em);
         let dwrite_analysis = self.build_glyph_analysis(
             glyph_id,
             point_size,
@@ -465,9 +475,11 @@ impl Font {
 
         let texture_type = match rasterization_options {
             RasterizationOptions::Bilevel => DWRITE_TEXTURE_ALIASED_1x1,
-            RasterizationOptions::GrayscaleAa | RasterizationOptions::SubpixelAa => {
-                DWRITE_TEXTURE_CLEARTYPE_3x1
-            }
+            RasterizationOptions::GrayscaleAa
+            | RasterizationOptions::BilevelThreshold(_)
+            | RasterizationOptions::SubpixelAa
+            | RasterizationOptions::FontDefault
+            | RasterizationOptions::Deterministic(_) => DWRITE_TEXTURE_CLEARTYPE_3x1,
         };
 
         let texture_bounds = dwrite_analysis.get_alpha_texture_bounds(texture_type)?;
@@ -501,6 +513,18 @@ impl Font {
         // TODO(pcwalton): This is woefully incomplete. See WebRender's code for a more complete
         // implementation.
 
+        if rasterization_options.is_deterministic() {
+            return self.rasterize_glyph_deterministic(
+                canvas,
+                glyph_id,
+                point_size,
+                transform,
+                rasterization_options,
+            );
+        }
+
+        let pixels_per_em = units::pixels_per_em(point_size, &transform);
+        let rasterization_options = rasterization_options.resolve(self, pixels_per_em);
         let dwrite_analysis = self.build_glyph_analysis(
             glyph_id,
             point_size,
@@ -511,9 +535,11 @@ impl Font {
 
         let texture_type = match rasterization_options {
             RasterizationOptions::Bilevel => DWRITE_TEXTURE_ALIASED_1x1,
-            RasterizationOptions::GrayscaleAa | RasterizationOptions::SubpixelAa => {
-                DWRITE_TEXTURE_CLEARTYPE_3x1
-            }
+            RasterizationOptions::GrayscaleAa
+            | RasterizationOptions::BilevelThreshold(_)
+            | RasterizationOptions::SubpixelAa
+            | RasterizationOptions::FontDefault
+            | RasterizationOptions::Deterministic(_) => DWRITE_TEXTURE_CLEARTYPE_3x1,
         };
 
         // TODO(pcwalton): Avoid a copy in some cases by writing directly to the canvas.
@@ -539,6 +565,16 @@ impl Font {
 
         let mut texture_bytes =
             dwrite_analysis.create_alpha_texture(texture_type, texture_bounds)?;
+        if let RasterizationOptions::BilevelThreshold(threshold) = rasterization_options {
+            let mut texture = Canvas {
+                pixels: texture_bytes,
+                size: texture_size,
+                stride: texture_stride,
+                format: texture_format,
+            };
+            texture.threshold(threshold);
+            texture_bytes = texture.pixels;
+        }
         canvas.blit_from(
             Vector2I::new(texture_bounds.left, texture_bounds.top),
             &mut texture_bytes,
@@ -599,9 +635,11 @@ impl Font {
 
             let rendering_mode = match rasterization_options {
                 RasterizationOptions::Bilevel => DWRITE_RENDERING_MODE_ALIASED,
-                RasterizationOptions::GrayscaleAa | RasterizationOptions::SubpixelAa => {
-                    DWRITE_RENDERING_MODE_NATURAL
-                }
+                RasterizationOptions::GrayscaleAa
+                | RasterizationOptions::BilevelThreshold(_)
+                | RasterizationOptions::SubpixelAa
+                | RasterizationOptions::FontDefault
+                | RasterizationOptions::Deterministic(_) => DWRITE_RENDERING_MODE_NATURAL,
             };
 
             Ok(DWriteGlyphRunAnalysis::create(
@@ -677,7 +715,10 @@ impl Font {
         } else {
             vec![]
         };
-        FallbackResult { fonts, valid_len }
+        let fallbacks = FallbackResult { fonts, valid_len };
+        #[cfg(feature = "last-resort")]
+        let fallbacks = last_resort::append(fallbacks);
+        fallbacks
     }
 
     /// Returns the raw contents of the OpenType table with the given tag.
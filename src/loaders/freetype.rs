@@ -1 +1,1073 @@
+// font-kit/src/loaders/freetype.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
 
+//! A cross-platform loader that uses the FreeType library to load and rasterize fonts.
+//!
+//! FreeType does the hinting and rasterization, including LCD filtering for subpixel
+//! antialiasing, so glyphs match what FreeType-based Linux desktops draw pixel for pixel. Names,
+//! properties, metrics, and tables come from the pure-Rust loader, which parses the same data.
+//!
+//! This loader is built with the `loader-freetype` feature and links the system FreeType library.
+
+#![allow(non_camel_case_types, non_snake_case)]
+
+use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::rect::{RectF, RectI};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use std::fmt::{self, Debug, Formatter};
+use std::os::raw::{c_char, c_int, c_long, c_short, c_uchar, c_uint, c_ulong, c_ushort, c_void};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use crate::canvas::{BlendMode, Canvas, CanvasMut, CanvasView, Format, RasterizationOptions};
+use crate::deterministic;
+use crate::error::{FontLoadingError, GlyphLoadingError};
+use crate::file_type::FileType;
+use crate::font::FontData;
+use crate::hinting::HintingOptions;
+use crate::loader::{FallbackFont, FallbackResult, Loader};
+use crate::metrics::{GlyphOrientation, Metrics};
+use crate::outline::OutlineSink;
+use crate::properties::Properties;
+use crate::units;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::File;
+
+/// A FreeType face, the native font type of this loader.
+pub type FT_Face = *mut FT_FaceRec;
+
+type FT_Library = *mut c_void;
+type FT_GlyphSlot = *mut FT_GlyphSlotRec;
+type FT_Error = c_int;
+type FT_Pos = c_long;
+type FT_Fixed = c_long;
+
+const FT_LOAD_NO_HINTING: i32 = 1 << 1;
+const FT_LOAD_NO_BITMAP: i32 = 1 << 3;
+const FT_LOAD_TARGET_NORMAL: i32 = (FT_RENDER_MODE_NORMAL as i32) << 16;
+const FT_LOAD_TARGET_LIGHT: i32 = (FT_RENDER_MODE_LIGHT as i32) << 16;
+const FT_LOAD_TARGET_MONO: i32 = (FT_RENDER_MODE_MONO as i32) << 16;
+const FT_LOAD_TARGET_LCD: i32 = (FT_RENDER_MODE_LCD as i32) << 16;
+
+const FT_RENDER_MODE_NORMAL: c_uint = 0;
+const FT_RENDER_MODE_LIGHT: c_uint = 1;
+const FT_RENDER_MODE_MONO: c_uint = 2;
+const FT_RENDER_MODE_LCD: c_uint = 3;
+
+const FT_PIXEL_MODE_MONO: c_uchar = 1;
+const FT_PIXEL_MODE_GRAY: c_uchar = 2;
+const FT_PIXEL_MODE_LCD: c_uchar = 5;
+
+const FT_GLYPH_FORMAT_OUTLINE: c_uint = u32::from_be_bytes(*b"outl");
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct FT_Vector {
+    x: FT_Pos,
+    y: FT_Pos,
+}
+
+#[repr(C)]
+struct FT_Matrix {
+    xx: FT_Fixed,
+    xy: FT_Fixed,
+    yx: FT_Fixed,
+    yy: FT_Fixed,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+struct FT_Generic {
+    data: *mut c_void,
+    finalizer: *mut c_void,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+struct FT_BBox {
+    x_min: FT_Pos,
+    y_min: FT_Pos,
+    x_max: FT_Pos,
+    y_max: FT_Pos,
+}
+
+/// The public part of a FreeType face record.
+///
+/// Only pointers to it are used; FreeType's private fields follow these.
+#[repr(C)]
+#[derive(Debug)]
+pub struct FT_FaceRec {
+    num_faces: c_long,
+    face_index: c_long,
+    face_flags: c_long,
+    style_flags: c_long,
+    num_glyphs: c_long,
+    family_name: *mut c_char,
+    style_name: *mut c_char,
+    num_fixed_sizes: c_int,
+    available_sizes: *mut c_void,
+    num_charmaps: c_int,
+    charmaps: *mut c_void,
+    generic: FT_Generic,
+    bbox: FT_BBox,
+    units_per_EM: c_ushort,
+    ascender: c_short,
+    descender: c_short,
+    height: c_short,
+    max_advance_width: c_short,
+    max_advance_height: c_short,
+    underline_position: c_short,
+    underline_thickness: c_short,
+    glyph: FT_GlyphSlot,
+}
+
+#[repr(C)]
+struct FT_Glyph_Metrics {
+    width: FT_Pos,
+    height: FT_Pos,
+    hori_bearing_x: FT_Pos,
+    hori_bearing_y: FT_Pos,
+    hori_advance: FT_Pos,
+    vert_bearing_x: FT_Pos,
+    vert_bearing_y: FT_Pos,
+    vert_advance: FT_Pos,
+}
+
+#[repr(C)]
+struct FT_Bitmap {
+    rows: c_uint,
+    width: c_uint,
+    pitch: c_int,
+    buffer: *mut c_uchar,
+    num_grays: c_ushort,
+    pixel_mode: c_uchar,
+    palette_mode: c_uchar,
+    palette: *mut c_void,
+}
+
+#[repr(C)]
+struct FT_Outline {
+    n_contours: c_short,
+    n_points: c_short,
+    points: *mut FT_Vector,
+    tags: *mut c_char,
+    contours: *mut c_short,
+    flags: c_int,
+}
+
+// The public part of a glyph slot; FreeType's private fields follow these.
+#[repr(C)]
+struct FT_GlyphSlotRec {
+    library: FT_Library,
+    face: FT_Face,
+    next: FT_GlyphSlot,
+    glyph_index: c_uint,
+    generic: FT_Generic,
+    metrics: FT_Glyph_Metrics,
+    linear_hori_advance: FT_Fixed,
+    linear_vert_advance: FT_Fixed,
+    advance: FT_Vector,
+    format: c_uint,
+    bitmap: FT_Bitmap,
+    bitmap_left: c_int,
+    bitmap_top: c_int,
+    outline: FT_Outline,
+}
+
+#[repr(C)]
+struct FT_MemoryRec {
+    user: *mut c_void,
+    alloc: extern "C" fn(*mut FT_MemoryRec, c_long) -> *mut c_void,
+    free: extern "C" fn(*mut FT_MemoryRec, *mut c_void),
+    realloc: extern "C" fn(*mut FT_MemoryRec, c_long, c_long, *mut c_void) -> *mut c_void,
+}
+
+#[repr(C)]
+struct FT_Outline_Funcs {
+    move_to: extern "C" fn(*const FT_Vector, *mut c_void) -> c_int,
+    line_to: extern "C" fn(*const FT_Vector, *mut c_void) -> c_int,
+    conic_to: extern "C" fn(*const FT_Vector, *const FT_Vector, *mut c_void) -> c_int,
+    cubic_to:
+        extern "C" fn(*const FT_Vector, *const FT_Vector, *const FT_Vector, *mut c_void) -> c_int,
+    shift: c_int,
+    delta: FT_Pos,
+}
+
+#[link(name = "freetype")]
+extern "C" {
+    fn FT_New_Library(memory: *mut FT_MemoryRec, library: *mut FT_Library) -> FT_Error;
+    fn FT_Add_Default_Modules(library: FT_Library);
+    fn FT_Set_Default_Properties(library: FT_Library);
+    fn FT_Reference_Library(library: FT_Library) -> FT_Error;
+    fn FT_Done_Library(library: FT_Library) -> FT_Error;
+    fn FT_Library_SetLcdFilter(library: FT_Library, filter: c_uint) -> FT_Error;
+    fn FT_New_Memory_Face(
+        library: FT_Library,
+        file_base: *const u8,
+        file_size: c_long,
+        face_index: c_long,
+        face: *mut FT_Face,
+    ) -> FT_Error;
+    fn FT_Reference_Face(face: FT_Face) -> FT_Error;
+    fn FT_Done_Face(face: FT_Face) -> FT_Error;
+    fn FT_Load_Sfnt_Table(
+        face: FT_Face,
+        tag: c_ulong,
+        offset: c_long,
+        buffer: *mut u8,
+        length: *mut c_ulong,
+    ) -> FT_Error;
+    fn FT_Set_Char_Size(
+        face: FT_Face,
+        char_width: c_long,
+        char_height: c_long,
+        horz_resolution: c_uint,
+        vert_resolution: c_uint,
+    ) -> FT_Error;
+    fn FT_Set_Transform(face: FT_Face, matrix: *mut FT_Matrix, delta: *mut FT_Vector);
+    fn FT_Load_Glyph(face: FT_Face, glyph_index: c_uint, load_flags: i32) -> FT_Error;
+    fn FT_Render_Glyph(slot: FT_GlyphSlot, render_mode: c_uint) -> FT_Error;
+    fn FT_Outline_Decompose(
+        outline: *mut FT_Outline,
+        func_interface: *const FT_Outline_Funcs,
+        user: *mut c_void,
+    ) -> FT_Error;
+}
+
+extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(block: *mut c_void);
+    fn realloc(block: *mut c_void, size: usize) -> *mut c_void;
+}
+
+// The allocator of every library this loader creates. `FT_Init_FreeType()` allocates one per
+// library and `FT_Done_FreeType()` frees it even while other references to the library remain, so
+// libraries are created and released with `FT_New_Library()` and `FT_Done_Library()` instead,
+// which count references properly, and share this one.
+struct Memory(FT_MemoryRec);
+
+// SAFETY: FreeType only reads the allocator, which has no state.
+unsafe impl Sync for Memory {}
+
+static MEMORY: Memory = Memory(FT_MemoryRec {
+    user: ptr::null_mut(),
+    alloc: ft_alloc,
+    free: ft_free,
+    realloc: ft_realloc,
+});
+
+extern "C" fn ft_alloc(_: *mut FT_MemoryRec, size: c_long) -> *mut c_void {
+    unsafe { malloc(size as usize) }
+}
+
+extern "C" fn ft_free(_: *mut FT_MemoryRec, block: *mut c_void) {
+    unsafe { free(block) }
+}
+
+extern "C" fn ft_realloc(
+    _: *mut FT_MemoryRec,
+    _: c_long,
+    new_size: c_long,
+    block: *mut c_void,
+) -> *mut c_void {
+    unsafe { realloc(block, new_size as usize) }
+}
+
+/// The filter that FreeType applies to subpixel-antialiased glyphs to reduce color fringes.
+///
+/// See `Font::set_lcd_filter()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LcdFilter {
+    /// No filtering; colors fringe visibly.
+    None,
+    /// FreeType's default filter, a compromise between sharpness and color fringes.
+    Default,
+    /// A sharper filter with more fringing, preferred by some with well-calibrated displays.
+    Light,
+    /// The filter of FreeType 2.3 and older, kept for compatibility.
+    Legacy,
+}
+
+/// A loaded font, rasterized with FreeType.
+///
+/// Cloning a font is cheap; clones share the FreeType face, which is locked while FreeType uses
+/// it, so a font can be used from several threads.
+#[derive(Clone)]
+pub struct Font {
+    font: crate::font::Font,
+    face: Arc<Mutex<FreeTypeFace>>,
+}
+
+// A FreeType face and a reference to the library that owns it.
+struct FreeTypeFace {
+    library: FT_Library,
+    face: FT_Face,
+}
+
+// SAFETY: The face and library are only used while the mutex around them is held, and FreeType
+// faces may move between threads.
+unsafe impl Send for FreeTypeFace {}
+
+impl Drop for FreeTypeFace {
+    fn drop(&mut self) {
+        unsafe {
+            FT_Done_Face(self.face);
+            FT_Done_Library(self.library);
+        }
+    }
+}
+
+impl Font {
+    // Creates a FreeType face for a font that the pure-Rust loader has parsed.
+    fn from_font(font: crate::font::Font, font_index: u32) -> Result<Font, FontLoadingError> {
+        // Shares the storage of the pure-Rust font, so mapped fonts stay mapped rather than copied.
+        let font_data = Box::new(font.owned_font_data().ok_or(FontLoadingError::Parse)?);
+        let (data_ptr, data_len) = {
+            let data = font_data.as_slice();
+            (data.as_ptr(), data.len())
+        };
+        unsafe {
+            let mut library = ptr::null_mut();
+            let memory = &MEMORY.0 as *const FT_MemoryRec as *mut FT_MemoryRec;
+            if FT_New_Library(memory, &mut library) != 0 {
+                return Err(FontLoadingError::Parse);
+            }
+            FT_Add_Default_Modules(library);
+            FT_Set_Default_Properties(library);
+            // Builds of FreeType without the patented ClearType filters reject this, and filter
+            // subpixel rendering their own way.
+            FT_Library_SetLcdFilter(library, 1);
+
+            let mut face = ptr::null_mut();
+            if FT_New_Memory_Face(
+                library,
+                data_ptr,
+                data_len as c_long,
+                font_index as c_long,
+                &mut face,
+            ) != 0
+            {
+                FT_Done_Library(library);
+                return Err(FontLoadingError::Parse);
+            }
+            // FreeType doesn't copy the data, so the face owns it until its last reference is
+            // gone, which may be after this font is, if the face was passed to
+            // `from_native_font()`.
+            (*face).generic = FT_Generic {
+                data: Box::into_raw(font_data) as *mut c_void,
+                finalizer: free_font_data as extern "C" fn(*mut c_void) as *mut c_void,
+            };
+            Ok(Font {
+                font,
+                face: Arc::new(Mutex::new(FreeTypeFace { library, face })),
+            })
+        }
+    }
+
+    /// Sets the filter that subpixel-antialiased glyphs are rendered with, which defaults to
+    /// `LcdFilter::Default`. The font's clones share the setting.
+    ///
+    /// Returns false if this build of FreeType doesn't support the filter. FreeType 2.10 and
+    /// later still render subpixel-antialiased glyphs without the ClearType filters, using their
+    /// own subpixel positioning instead.
+    pub fn set_lcd_filter(&self, filter: LcdFilter) -> bool {
+        let filter = match filter {
+            LcdFilter::None => 0,
+            LcdFilter::Default => 1,
+            LcdFilter::Light => 2,
+            LcdFilter::Legacy => 16,
+        };
+        let face = self.face.lock().unwrap();
+        unsafe { FT_Library_SetLcdFilter(face.library, filter) == 0 }
+    }
+
+    /// Returns the pure-Rust font that this font reads names, metrics, and tables from.
+    #[inline]
+    pub fn as_ttf_parser_font(&self) -> &crate::font::Font {
+        &self.font
+    }
+
+    // Loads a glyph at `point_size` pixels per em with the given transform in raster coordinates,
+    // renders it if `render_mode` is given, and passes the glyph slot to `f`.
+    fn with_glyph<F, R>(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Option<Transform2F>,
+        load_flags: i32,
+        render_mode: Option<c_uint>,
+        f: F,
+    ) -> Result<R, GlyphLoadingError>
+    where
+        F: FnOnce(&FT_GlyphSlotRec) -> Result<R, GlyphLoadingError>,
+    {
+        if glyph_id >= self.glyph_count() {
+            return Err(GlyphLoadingError::NoSuchGlyph);
+        }
+        let face = self.face.lock().unwrap();
+        unsafe {
+            let size = f32::max(point_size * 64.0, 1.0) as c_long;
+            if FT_Set_Char_Size(face.face, 0, size, 72, 72) != 0 {
+                return Err(GlyphLoadingError::PlatformError);
+            }
+
+            // Raster coordinates point down and FreeType's point up, so the transform is
+            // flipped on both sides.
+            match transform {
+                Some(transform) => {
+                    let (matrix, vector) = (transform.matrix, transform.vector);
+                    let mut matrix = FT_Matrix {
+                        xx: (matrix.m11() * 65536.0) as FT_Fixed,
+                        xy: (-matrix.m12() * 65536.0) as FT_Fixed,
+                        yx: (-matrix.m21() * 65536.0) as FT_Fixed,
+                        yy: (matrix.m22() * 65536.0) as FT_Fixed,
+                    };
+                    let mut delta = FT_Vector {
+                        x: (vector.x() * 64.0) as FT_Pos,
+                        y: (-vector.y() * 64.0) as FT_Pos,
+                    };
+                    FT_Set_Transform(face.face, &mut matrix, &mut delta);
+                }
+                None => FT_Set_Transform(face.face, ptr::null_mut(), ptr::null_mut()),
+            }
+
+            if FT_Load_Glyph(face.face, glyph_id, load_flags) != 0 {
+                return Err(GlyphLoadingError::Parse);
+            }
+            let slot = (*face.face).glyph;
+            if let Some(render_mode) = render_mode {
+                if FT_Render_Glyph(slot, render_mode) != 0 {
+                    return Err(GlyphLoadingError::PlatformError);
+                }
+            }
+            f(&*slot)
+        }
+    }
+
+    // Renders a glyph as `rasterize_glyph()` would and passes a view of FreeType's image and the
+    // position of its top left corner to `f`, without copying the image.
+    fn with_rendered_glyph<F, R>(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+        f: F,
+    ) -> Result<R, GlyphLoadingError>
+    where
+        F: FnOnce(CanvasView, Vector2I) -> R,
+    {
+        let pixels_per_em = units::pixels_per_em(point_size, &transform);
+        let rasterization_options = rasterization_options.resolve(self, pixels_per_em);
+        let render_mode = match rasterization_options {
+            RasterizationOptions::Bilevel => FT_RENDER_MODE_MONO,
+            RasterizationOptions::GrayscaleAa
+            | RasterizationOptions::BilevelThreshold(_)
+            | RasterizationOptions::FontDefault
+            | RasterizationOptions::Deterministic(_) => FT_RENDER_MODE_NORMAL,
+            RasterizationOptions::SubpixelAa => FT_RENDER_MODE_LCD,
+        };
+        self.with_glyph(
+            glyph_id,
+            point_size,
+            Some(transform),
+            load_flags(hinting_options, rasterization_options),
+            Some(render_mode),
+            |slot| unsafe {
+                let bitmap = &slot.bitmap;
+                let origin = Vector2I::new(slot.bitmap_left, -slot.bitmap_top);
+                // Rendered glyphs always flow down.
+                if bitmap.pitch < 0 {
+                    return Err(GlyphLoadingError::PlatformError);
+                }
+                let (rows, stride) = (bitmap.rows as usize, bitmap.pitch as usize);
+                let size = Vector2I::new(bitmap.width as i32, rows as i32);
+                if size.x() == 0 || size.y() == 0 || bitmap.buffer.is_null() {
+                    return Ok(f(
+                        CanvasView::new(&[], Vector2I::default(), 0, Format::A8).unwrap(),
+                        origin,
+                    ));
+                }
+                // The bitmap belongs to the glyph slot, which is ours until the face is unlocked.
+                let pixels = std::slice::from_raw_parts_mut(bitmap.buffer, rows * stride);
+                let (size, format) = match bitmap.pixel_mode {
+                    FT_PIXEL_MODE_GRAY => (size, Format::A8),
+                    FT_PIXEL_MODE_LCD => (Vector2I::new(size.x() / 3, size.y()), Format::Rgb24),
+                    FT_PIXEL_MODE_MONO => (size, Format::A1),
+                    _ => return Err(GlyphLoadingError::PlatformError),
+                };
+                if let RasterizationOptions::BilevelThreshold(threshold) = rasterization_options {
+                    let mut image = CanvasMut::new(pixels, size, stride, format)
+                        .ok_or(GlyphLoadingError::PlatformError)?;
+                    image.threshold(threshold);
+                }
+                let image = CanvasView::new(pixels, size, stride, format)
+                    .ok_or(GlyphLoadingError::PlatformError)?;
+                Ok(f(image, origin))
+            },
+        )
+    }
+}
+
+// Releases the data of a face created by `Font::from_font()` when FreeType destroys the face.
+extern "C" fn free_font_data(face: *mut c_void) {
+    unsafe {
+        let face = face as FT_Face;
+        drop(Box::from_raw((*face).generic.data as *mut FontData));
+    }
+}
+
+// Returns the FreeType load flags for the requested hinting. FreeType's hinting targets are named
+// after the rendering they suit.
+fn load_flags(hinting_options: HintingOptions, rasterization_options: RasterizationOptions) -> i32 {
+    let target = match (hinting_options, rasterization_options) {
+        (HintingOptions::None, _) => FT_LOAD_NO_HINTING,
+        (HintingOptions::Full(_), RasterizationOptions::Bilevel) => FT_LOAD_TARGET_MONO,
+        (HintingOptions::Full(_), _) => FT_LOAD_TARGET_NORMAL,
+        (HintingOptions::Vertical(_), _) => FT_LOAD_TARGET_LIGHT,
+        (HintingOptions::VerticalSubpixel(_), _) => FT_LOAD_TARGET_LCD,
+    };
+    target | FT_LOAD_NO_BITMAP
+}
+
+impl Debug for Font {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.debug_tuple("Font").field(&self.font).finish()
+    }
+}
+
+impl Loader for Font {
+    type NativeFont = FT_Face;
+
+    fn from_bytes(font_data: Arc<Vec<u8>>, font_index: u32) -> Result<Self, FontLoadingError> {
+        Font::from_font(
+            crate::font::Font::from_bytes(font_data, font_index)?,
+            font_index,
+        )
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_file(file: &mut File, font_index: u32) -> Result<Self, FontLoadingError> {
+        Font::from_font(crate::font::Font::from_file(file, font_index)?, font_index)
+    }
+
+    /// Creates a font from a FreeType face, which the font takes a new reference to, as it does
+    /// to the face's library.
+    ///
+    /// The face must not be used elsewhere while the font is in use. Faces created from memory
+    /// read from it until they are destroyed, so that memory must outlive the font too, unless
+    /// the face came from `native_font()`, whose faces own their data. `FT_Done_FreeType()` frees
+    /// a library's allocator even if references to the library remain, so a library created
+    /// with `FT_Init_FreeType()` must outlive the font.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the face isn't a TrueType or OpenType font, whose data the pure-Rust loader
+    /// can parse.
+    unsafe fn from_native_font(native_font: FT_Face) -> Self {
+        // A tag of 0 loads the whole font file.
+        let mut length = 0;
+        let mut font_data = vec![];
+        if FT_Load_Sfnt_Table(native_font, 0, 0, ptr::null_mut(), &mut length) == 0 {
+            font_data = vec![0; length as usize];
+            FT_Load_Sfnt_Table(native_font, 0, 0, font_data.as_mut_ptr(), &mut length);
+        }
+        // The high bits of the index select a named instance of a variable font.
+        let font_index = ((*native_font).face_index & 0xffff) as u32;
+        let font = crate::font::Font::from_bytes(Arc::new(font_data), font_index)
+            .expect("the FreeType face is not a TrueType or OpenType font");
+
+        let library = (*(*native_font).glyph).library;
+        FT_Reference_Face(native_font);
+        FT_Reference_Library(library);
+        Font {
+            face: Arc::new(Mutex::new(FreeTypeFace {
+                library,
+                face: native_font,
+            })),
+            font,
+        }
+    }
+
+    fn analyze_bytes(font_data: Arc<Vec<u8>>) -> Result<FileType, FontLoadingError> {
+        crate::font::Font::analyze_bytes(font_data)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn analyze_file(file: &mut File) -> Result<FileType, FontLoadingError> {
+        crate::font::Font::analyze_file(file)
+    }
+
+    /// Returns the FreeType face. It stays valid as long as this font or any of its clones is
+    /// alive, and must not be used while they are in use on other threads.
+    fn native_font(&self) -> FT_Face {
+        self.face.lock().unwrap().face
+    }
+
+    fn postscript_name(&self) -> Option<String> {
+        self.font.postscript_name()
+    }
+
+    fn full_name(&self) -> String {
+        self.font.full_name()
+    }
+
+    fn family_name(&self) -> String {
+        self.font.family_name()
+    }
+
+    fn is_monospace(&self) -> bool {
+        self.font.is_monospace()
+    }
+
+    fn properties(&self) -> Properties {
+        self.font.properties()
+    }
+
+    fn glyph_count(&self) -> u32 {
+        self.font.glyph_count()
+    }
+
+    fn glyph_for_char(&self, character: char) -> Option<u32> {
+        self.font.glyph_for_char(character)
+    }
+
+    fn glyph_for_variation(&self, character: char, variation_selector: char) -> Option<u32> {
+        self.font.glyph_for_variation(character, variation_selector)
+    }
+
+    fn glyph_by_name(&self, name: &str) -> Option<u32> {
+        self.font.glyph_by_name(name)
+    }
+
+    /// Sends the vector path for a glyph to a sink, hinted by FreeType if `hinting_mode` asks for
+    /// it.
+    ///
+    /// Hinted outlines are grid-fitted at the hinting size but are still in font units.
+    fn outline<S: OutlineSink>(
+        &self,
+        glyph_id: u32,
+        hinting_mode: HintingOptions,
+        sink: &mut S,
+    ) -> Result<(), GlyphLoadingError> {
+        let size = match hinting_mode.grid_fitting_size() {
+            Some(size) => size,
+            None => return self.font.outline(glyph_id, hinting_mode, sink),
+        };
+        let scale = self.metrics().units_per_em as f32 / (f32::max(size, 1.0 / 64.0) * 64.0);
+        self.with_glyph(
+            glyph_id,
+            size,
+            None,
+            load_flags(hinting_mode, RasterizationOptions::GrayscaleAa),
+            None,
+            |slot| {
+                // Bitmap glyphs have no outline.
+                if slot.format != FT_GLYPH_FORMAT_OUTLINE {
+                    return Ok(());
+                }
+                let funcs = FT_Outline_Funcs {
+                    move_to: move_to::<S>,
+                    line_to: line_to::<S>,
+                    conic_to: conic_to::<S>,
+                    cubic_to: cubic_to::<S>,
+                    shift: 0,
+                    delta: 0,
+                };
+                let mut decomposer = Decomposer {
+                    sink,
+                    scale,
+                    contour_open: false,
+                };
+                let outline = &slot.outline as *const FT_Outline as *mut FT_Outline;
+                let result = unsafe {
+                    FT_Outline_Decompose(
+                        outline,
+                        &funcs,
+                        &mut decomposer as *mut Decomposer<S> as *mut c_void,
+                    )
+                };
+                if decomposer.contour_open {
+                    decomposer.sink.close();
+                }
+                if result != 0 {
+                    return Err(GlyphLoadingError::Parse);
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn typographic_bounds(&self, glyph_id: u32) -> Result<RectF, GlyphLoadingError> {
+        self.font.typographic_bounds(glyph_id)
+    }
+
+    fn left_side_bearing(&self, glyph_id: u32) -> Result<f32, GlyphLoadingError> {
+        self.font.left_side_bearing(glyph_id)
+    }
+
+    fn right_side_bearing(&self, glyph_id: u32) -> Result<f32, GlyphLoadingError> {
+        self.font.right_side_bearing(glyph_id)
+    }
+
+    fn advance(&self, glyph_id: u32) -> Result<Vector2F, GlyphLoadingError> {
+        self.font.advance(glyph_id)
+    }
+
+    fn advance_with_orientation(
+        &self,
+        glyph_id: u32,
+        orientation: GlyphOrientation,
+    ) -> Result<Vector2F, GlyphLoadingError> {
+        self.font.advance_with_orientation(glyph_id, orientation)
+    }
+
+    fn origin(&self, glyph_id: u32) -> Result<Vector2F, GlyphLoadingError> {
+        self.font.origin(glyph_id)
+    }
+
+    fn origin_with_orientation(
+        &self,
+        glyph_id: u32,
+        orientation: GlyphOrientation,
+    ) -> Result<Vector2F, GlyphLoadingError> {
+        self.font.origin_with_orientation(glyph_id, orientation)
+    }
+
+    fn metrics(&self) -> Metrics {
+        self.font.metrics()
+    }
+
+    fn copy_font_data(&self) -> Option<Arc<Vec<u8>>> {
+        self.font.copy_font_data()
+    }
+
+    #[inline]
+    fn with_font_data<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&[u8]) -> R,
+    {
+        self.font.with_font_data(f)
+    }
+
+    fn supports_hinting_options(
+        &self,
+        _hinting_options: HintingOptions,
+        _for_rasterization: bool,
+    ) -> bool {
+        true
+    }
+
+    /// Returns the pixel boundaries of the glyph as FreeType renders it, which include the
+    /// effects of hinting and of the LCD filter.
+    ///
+    /// Unlike the default implementation, this renders the glyph.
+    fn raster_bounds(
+        &self,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<RectI, GlyphLoadingError> {
+        self.with_rendered_glyph(
+            glyph_id,
+            point_size,
+            transform,
+            hinting_options,
+            rasterization_options,
+            |image, origin| RectI::new(origin, image.size),
+        )
+    }
+
+    fn rasterize_glyph(
+        &self,
+        canvas: &mut Canvas,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        if rasterization_options.is_deterministic() {
+            return self.rasterize_glyph_deterministic(
+                canvas,
+                glyph_id,
+                point_size,
+                transform,
+                rasterization_options,
+            );
+        }
+        self.with_rendered_glyph(
+            glyph_id,
+            point_size,
+            transform,
+            hinting_options,
+            rasterization_options,
+            |image, origin| {
+                if image.size.x() > 0 && image.size.y() > 0 {
+                    let mut converted = Canvas::new(image.size, canvas.format);
+                    converted.blit_from(
+                        Vector2I::default(),
+                        image.pixels,
+                        image.size,
+                        image.stride,
+                        image.format,
+                    );
+                    canvas.blend(&converted, origin, BlendMode::Over);
+                }
+            },
+        )
+    }
+
+    /// Unlike `rasterize_glyph()`, this copies FreeType's image straight into `target`, clearing
+    /// the pixels that it doesn't cover.
+    fn rasterize_glyph_into(
+        &self,
+        target: &mut CanvasMut,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+    ) -> Result<(), GlyphLoadingError> {
+        if rasterization_options.is_deterministic() {
+            return deterministic::rasterize_glyph_into(
+                self,
+                target,
+                glyph_id,
+                point_size,
+                transform,
+                rasterization_options,
+            );
+        }
+        self.with_rendered_glyph(
+            glyph_id,
+            point_size,
+            transform,
+            hinting_options,
+            rasterization_options,
+            |image, origin| {
+                target.clear();
+                target.blit_from(origin, image.pixels, image.size, image.stride, image.format);
+            },
+        )
+    }
+
+    fn glyph_has_color(&self, glyph_id: u32) -> bool {
+        self.font.glyph_has_color(glyph_id)
+    }
+
+    fn get_fallbacks(&self, text: &str, locale: &str) -> FallbackResult<Self> {
+        let fallbacks = self.font.get_fallbacks(text, locale);
+        FallbackResult {
+            fonts: fallbacks
+                .fonts
+                .into_iter()
+                .filter_map(|fallback| {
+                    let font = Font::from_handle(&fallback.font.handle()?).ok()?;
+                    Some(FallbackFont {
+                        font,
+                        scale: fallback.scale,
+                    })
+                })
+                .collect(),
+            valid_len: fallbacks.valid_len,
+        }
+    }
+
+    fn load_font_table(&self, table_tag: u32) -> Option<Box<[u8]>> {
+        self.font.load_font_table(table_tag)
+    }
+}
+
+// Passes FreeType's outline callbacks on to a sink, converting from 26.6 pixels to font units.
+struct Decomposer<'a, S> {
+    sink: &'a mut S,
+    scale: f32,
+    contour_open: bool,
+}
+
+impl<S> Decomposer<'_, S> {
+    fn point(&self, point: *const FT_Vector) -> Vector2F {
+        let point = unsafe { *point };
+        Vector2F::new(point.x as f32, point.y as f32) * self.scale
+    }
+}
+
+extern "C" fn move_to<S: OutlineSink>(to: *const FT_Vector, user: *mut c_void) -> c_int {
+    let decomposer = unsafe { &mut *(user as *mut Decomposer<S>) };
+    // FreeType starts each contour with a move but never closes one.
+    if decomposer.contour_open {
+        decomposer.sink.close();
+    }
+    decomposer.contour_open = true;
+    let to = decomposer.point(to);
+    decomposer.sink.move_to(to);
+    0
+}
+
+extern "C" fn line_to<S: OutlineSink>(to: *const FT_Vector, user: *mut c_void) -> c_int {
+    let decomposer = unsafe { &mut *(user as *mut Decomposer<S>) };
+    let to = decomposer.point(to);
+    decomposer.sink.line_to(to);
+    0
+}
+
+extern "C" fn conic_to<S: OutlineSink>(
+    ctrl: *const FT_Vector,
+    to: *const FT_Vector,
+    user: *mut c_void,
+) -> c_int {
+    let decomposer = unsafe { &mut *(user as *mut Decomposer<S>) };
+    let (ctrl, to) = (decomposer.point(ctrl), decomposer.point(to));
+    decomposer.sink.quadratic_curve_to(ctrl, to);
+    0
+}
+
+extern "C" fn cubic_to<S: OutlineSink>(
+    ctrl0: *const FT_Vector,
+    ctrl1: *const FT_Vector,
+    to: *const FT_Vector,
+    user: *mut c_void,
+) -> c_int {
+    let decomposer = unsafe { &mut *(user as *mut Decomposer<S>) };
+    let ctrl = LineSegment2F::new(decomposer.point(ctrl0), decomposer.point(ctrl1));
+    let to = decomposer.point(to);
+    decomposer.sink.cubic_curve_to(ctrl, to);
+    0
+}
+
+#[cfg(test)]
+mod test {
+    use super::Font;
+    use crate::canvas::{Canvas, Format, RasterizationOptions};
+    use crate::hinting::HintingOptions;
+    use crate::loader::Loader;
+    use crate::outline::OutlineBuilder;
+    use crate::test_fixtures::{EB_GARAMOND_OTC, INCONSOLATA};
+    use pathfinder_geometry::rect::RectI;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::Vector2I;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_rasterize_with_freetype() {
+        let font = Font::from_bytes(Arc::new(INCONSOLATA.to_vec()), 0).unwrap();
+        assert_eq!(font.family_name(), "Inconsolata");
+        let glyph_id = font.glyph_for_char('H').unwrap();
+
+        for &(rasterization_options, format) in &[
+            (RasterizationOptions::Bilevel, Format::A8),
+            (RasterizationOptions::Bilevel, Format::A1),
+            (RasterizationOptions::BilevelThreshold(160), Format::A8),
+            (RasterizationOptions::GrayscaleAa, Format::A8),
+            (RasterizationOptions::SubpixelAa, Format::Rgb24),
+        ] {
+            let hinting_options = HintingOptions::Full(16.0);
+            let bounds = font
+                .raster_bounds(
+                    glyph_id,
+                    16.0,
+                    Transform2F::default(),
+                    hinting_options,
+                    rasterization_options,
+                )
+                .unwrap();
+            let mut canvas = Canvas::new(bounds.size(), format);
+            font.clone()
+                .rasterize_glyph(
+                    &mut canvas,
+                    glyph_id,
+                    16.0,
+                    Transform2F::from_translation(-bounds.origin().to_f32()),
+                    hinting_options,
+                    rasterization_options,
+                )
+                .unwrap();
+            if rasterization_options == RasterizationOptions::SubpixelAa {
+                // The edges of stems are colored.
+                assert!(canvas
+                    .pixels
+                    .chunks_exact(3)
+                    .any(|pixel| pixel[0] != pixel[2]));
+            } else {
+                // Fully hinted stems cover whole pixels.
+                assert!(canvas.to_format(Format::A8).pixels.contains(&255));
+            }
+            if let RasterizationOptions::BilevelThreshold(_) = rasterization_options {
+                assert!(canvas
+                    .pixels
+                    .iter()
+                    .all(|&pixel| pixel == 0 || pixel == 255));
+            }
+
+            // Drawing into a region of a larger image replaces that region and nothing else.
+            let mut page = Canvas::new(bounds.size() + Vector2I::new(16, 2), format);
+            page.pixels.fill(0x55);
+            let slot = RectI::new(Vector2I::new(8, 1), bounds.size());
+            font.rasterize_glyph_into(
+                &mut page.as_view_mut().sub_view_mut(slot).unwrap(),
+                glyph_id,
+                16.0,
+                Transform2F::from_translation(-bounds.origin().to_f32()),
+                hinting_options,
+                rasterization_options,
+            )
+            .unwrap();
+            let view = page.as_view();
+            assert_eq!(
+                view.sub_view(slot).unwrap().to_canvas().pixels,
+                canvas.pixels
+            );
+            assert!(page.pixels[..page.stride]
+                .iter()
+                .all(|&pixel| pixel == 0x55));
+        }
+    }
+
+    #[test]
+    fn test_native_font_round_trip() {
+        let font = Font::from_bytes(Arc::new(EB_GARAMOND_OTC.to_vec()), 1).unwrap();
+        let round_tripped = unsafe { Font::from_native_font(font.native_font()) };
+        drop(font);
+        assert_eq!(
+            round_tripped.postscript_name().as_deref(),
+            Some("EBGaramond12-Italic")
+        );
+        assert!(round_tripped
+            .raster_bounds(
+                round_tripped.glyph_for_char('a').unwrap(),
+                16.0,
+                Transform2F::default(),
+                HintingOptions::None,
+                RasterizationOptions::GrayscaleAa,
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_hinted_outline() {
+        let font = Font::from_bytes(Arc::new(INCONSOLATA.to_vec()), 0).unwrap();
+        let glyph_id = font.glyph_for_char('o').unwrap();
+        let mut unhinted = OutlineBuilder::new();
+        font.outline(glyph_id, HintingOptions::None, &mut unhinted)
+            .unwrap();
+        let mut hinted = OutlineBuilder::new();
+        font.outline(glyph_id, HintingOptions::Full(10.0), &mut hinted)
+            .unwrap();
+        let (unhinted, hinted) = (unhinted.into_outline(), hinted.into_outline());
+        assert_eq!(unhinted.contours.len(), hinted.contours.len());
+        assert_ne!(unhinted, hinted);
+    }
+}
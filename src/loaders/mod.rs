@@ -9,6 +9,11 @@
 // except according to those terms.
 
 //! The different system services that can load and rasterize fonts.
+//!
+//! Every loader implements the `Loader` trait, and `default` refers to the one that best matches
+//! the platform's own text rendering. Other loaders can be used side by side with it: load a
+//! handle with a specific one with `Handle::load_with()`, or store fonts from several as
+//! `Box<dyn AnyFont>`.
 
 #[cfg(all(
     any(target_os = "macos", target_os = "ios"),
@@ -19,11 +24,14 @@ pub use crate::loaders::core_text as default;
 #[cfg(all(target_family = "windows", not(feature = "loader-freetype-default")))]
 pub use crate::loaders::directwrite as default;
 
-#[cfg(any(
+#[cfg(feature = "loader-freetype-default")]
+pub use crate::loaders::freetype as default;
+
+#[cfg(all(
     not(any(target_os = "macos", target_os = "ios", target_family = "windows")),
-    feature = "loader-freetype-default"
+    not(feature = "loader-freetype-default")
 ))]
-pub use crate::loaders::freetype as default;
+pub use crate::loaders::ttf_parser as default;
 
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 pub mod core_text;
@@ -31,8 +39,8 @@ pub mod core_text;
 #[cfg(target_family = "windows")]
 pub mod directwrite;
 
-#[cfg(any(
-    not(any(target_os = "macos", target_os = "ios", target_family = "windows")),
-    feature = "loader-freetype"
-))]
+// FreeType is a system library, so it is only linked on request.
+#[cfg(feature = "loader-freetype")]
 pub mod freetype;
+
+pub mod ttf_parser;
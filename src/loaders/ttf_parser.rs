@@ -0,0 +1,16 @@
+// font-kit/src/loaders/ttf_parser.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A cross-platform loader, written in pure Rust on top of the `ttf-parser` crate.
+//!
+//! This is the loader behind `font::Font`, which offers many more methods than the `Loader`
+//! trait. It is available on every platform, and is the default except on macOS and Windows.
+
+pub use crate::font::{Font, FontRef};
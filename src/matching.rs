@@ -11,13 +11,240 @@
 //! Determines the closest font matching a description per the CSS Fonts Level 3 specification.
 
 use float_ord::FloatOrd;
+#[cfg(feature = "source")]
+use pathfinder_geometry::transform2d::Transform2F;
+#[cfg(feature = "source")]
+use pathfinder_geometry::vector::Vector2F;
 
 use crate::error::SelectionError;
+#[cfg(feature = "source")]
+use crate::family_name::FamilyName;
+#[cfg(feature = "source")]
+use crate::font::Font;
+use crate::handle::Handle;
 use crate::properties::{Properties, Stretch, Style, Weight};
+#[cfg(feature = "source")]
+use crate::source::Source;
 
-/// This follows CSS Fonts Level 3 § 5.2 [1].
+/// Selects the font in `source` that best matches the given families and properties.
 ///
-/// https://drafts.csswg.org/css-fonts-3/#font-style-matching
+/// The families are tried in order, and the first one that the source contains is used. Generic
+/// families, such as `FamilyName::SansSerif`, resolve to the platform's default families. Within
+/// the family, the font whose properties are closest to `properties` is chosen per
+/// `find_best_match()`.
+///
+/// This is the default implementation of `Source::select_best_match()`.
+#[cfg(feature = "source")]
+pub fn select_best_match<S>(
+    source: &S,
+    family_names: &[FamilyName],
+    properties: &Properties,
+) -> Result<Handle, SelectionError>
+where
+    S: Source + ?Sized,
+{
+    for family_name in family_names {
+        if let Ok(family_handle) = source.select_family_by_generic_name(family_name) {
+            let candidates = source.select_descriptions_in_family(&family_handle)?;
+            if let Ok(index) = find_best_match(&candidates, properties) {
+                return Ok(family_handle.fonts[index].clone());
+            }
+        }
+    }
+    Err(SelectionError::NotFound)
+}
+
+/// Adjusts the ranking of candidate fonts during matching, to encode preferences that the CSS
+/// algorithm knows nothing about, such as preferring variable fonts, avoiding bitmap-only fonts,
+/// or favoring a particular foundry.
+///
+/// Every font in the matched family is scored, and only the fonts with the highest score are
+/// considered by the CSS algorithm. So scores take precedence over properties: a font with a
+/// higher score is chosen even if another font is a closer match for the requested weight, style,
+/// and stretch. Give fonts equal scores to leave the choice between them to the CSS algorithm.
+///
+/// Closures of type `Fn(&Handle, &Properties) -> f32` implement this trait.
+pub trait MatchScorer {
+    /// Returns the score of the font with the given handle and properties. Higher scores are
+    /// preferred; fonts that a policy doesn't care about should score 0.
+    fn score(&self, handle: &Handle, properties: &Properties) -> f32;
+}
+
+impl<F> MatchScorer for F
+where
+    F: Fn(&Handle, &Properties) -> f32,
+{
+    #[inline]
+    fn score(&self, handle: &Handle, properties: &Properties) -> f32 {
+        self(handle, properties)
+    }
+}
+
+/// Selects a font like `select_best_match()`, but only considers the fonts in the matched family
+/// that `scorer` ranks highest.
+///
+/// This is the default implementation of `Source::select_best_match_with_scorer()`.
+#[cfg(feature = "source")]
+pub fn select_best_match_with_scorer<S, M>(
+    source: &S,
+    family_names: &[FamilyName],
+    properties: &Properties,
+    scorer: &M,
+) -> Result<Handle, SelectionError>
+where
+    S: Source + ?Sized,
+    M: MatchScorer + ?Sized,
+{
+    for family_name in family_names {
+        if let Ok(family_handle) = source.select_family_by_generic_name(family_name) {
+            let candidates = source.select_descriptions_in_family(&family_handle)?;
+            let scores: Vec<f32> = family_handle
+                .fonts
+                .iter()
+                .zip(&candidates)
+                .map(|(handle, candidate)| scorer.score(handle, candidate))
+                .collect();
+            if let Ok(index) = find_best_match_with_scores(&candidates, &scores, properties) {
+                return Ok(family_handle.fonts[index].clone());
+            }
+        }
+    }
+    Err(SelectionError::NotFound)
+}
+
+/// Limits on synthesizing a stretch that no font in the matched family has.
+///
+/// A stretch is synthesized by scaling outlines and advances horizontally. Extreme scales distort
+/// stems and counters badly, so the scale is clamped to these limits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StretchSynthesis {
+    /// The smallest horizontal scale to apply, when condensing.
+    pub min_scale: f32,
+    /// The largest horizontal scale to apply, when expanding.
+    pub max_scale: f32,
+}
+
+impl Default for StretchSynthesis {
+    #[inline]
+    fn default() -> StretchSynthesis {
+        StretchSynthesis {
+            min_scale: 0.75,
+            max_scale: 1.25,
+        }
+    }
+}
+
+/// The result of matching with synthesis: a font, and how to distort it to approximate the
+/// requested properties.
+#[cfg(feature = "source")]
+#[derive(Clone, Debug)]
+pub struct SynthesizedMatch {
+    /// The font that was selected.
+    pub handle: Handle,
+    /// The horizontal scale to apply to the font's outlines and advances. 1.0 means that nothing
+    /// is synthesized.
+    pub horizontal_scale: f32,
+    /// The value to set the font's `wdth` variation axis to, if it has one, as a percentage of
+    /// the normal width. Fonts with the axis are varied rather than scaled. See
+    /// `apply_variation()`.
+    pub width: Option<f32>,
+}
+
+#[cfg(feature = "source")]
+impl SynthesizedMatch {
+    /// Returns true if the font must be distorted to approximate the requested properties.
+    #[inline]
+    pub fn is_synthesized(&self) -> bool {
+        self.horizontal_scale != 1.0
+    }
+
+    /// Returns the transform to apply to outlines, for example with `TransformSink`, and to
+    /// rasterization.
+    #[inline]
+    pub fn transform(&self) -> Transform2F {
+        Transform2F::from_scale(Vector2F::new(self.horizontal_scale, 1.0))
+    }
+
+    /// Scales a glyph advance to account for the synthesis.
+    #[inline]
+    pub fn scale_advance(&self, advance: Vector2F) -> Vector2F {
+        Vector2F::new(advance.x() * self.horizontal_scale, advance.y())
+    }
+
+    /// Sets the `wdth` axis of `font`, loaded from `handle`, to `width` if the match has one.
+    #[inline]
+    pub fn apply_variation(&self, font: &mut Font) {
+        if let Some(width) = self.width {
+            font.set_variation(u32::from_be_bytes(*b"wdth"), width);
+        }
+    }
+}
+
+// Returns the `wdth` value and horizontal scale that approximate `requested` in a font of stretch
+// `available`. A font with a `wdth` axis is set as close to the requested width as its range
+// allows and isn't scaled.
+#[cfg(feature = "source")]
+fn synthesize_stretch(
+    requested: Stretch,
+    available: Stretch,
+    width_range: Option<(f32, f32)>,
+    limits: &StretchSynthesis,
+) -> (Option<f32>, f32) {
+    match width_range {
+        Some((min, max)) => (Some((requested.0 * 100.0).clamp(min, max)), 1.0),
+        None if available.0 > 0.0 => (
+            None,
+            (requested.0 / available.0).clamp(limits.min_scale, limits.max_scale),
+        ),
+        None => (None, 1.0),
+    }
+}
+
+/// Selects a font like `select_best_match()`, synthesizing the requested stretch if the selected
+/// font has a different one.
+///
+/// If the font has a `wdth` variation axis, the returned match sets it to the requested width,
+/// clamped to the axis's range. Otherwise, the font is scaled horizontally, as browsers do for
+/// `font-stretch` when a family has no font of the requested width.
+#[cfg(feature = "source")]
+pub fn select_best_match_with_stretch_synthesis<S>(
+    source: &S,
+    family_names: &[FamilyName],
+    properties: &Properties,
+    limits: &StretchSynthesis,
+) -> Result<SynthesizedMatch, SelectionError>
+where
+    S: Source + ?Sized,
+{
+    for family_name in family_names {
+        if let Ok(family_handle) = source.select_family_by_generic_name(family_name) {
+            let candidates = source.select_descriptions_in_family(&family_handle)?;
+            if let Ok(index) = find_best_match(&candidates, properties) {
+                let handle = family_handle.fonts[index].clone();
+                let width_range = handle.info().ok().and_then(|info| info.width_range);
+                let (width, horizontal_scale) = synthesize_stretch(
+                    properties.stretch,
+                    candidates[index].stretch,
+                    width_range,
+                    limits,
+                );
+                return Ok(SynthesizedMatch {
+                    handle,
+                    horizontal_scale,
+                    width,
+                });
+            }
+        }
+    }
+    Err(SelectionError::NotFound)
+}
+
+/// Returns the index of the candidate whose properties best match `query`.
+///
+/// Candidates are narrowed down by stretch first, then by style, and finally by weight. This
+/// follows CSS Fonts Level 3 § 5.2 [1].
+///
+/// [1]: https://drafts.csswg.org/css-fonts-3/#font-style-matching
 pub fn find_best_match(
     candidates: &[Properties],
     query: &Properties,
@@ -154,3 +381,147 @@ pub fn find_best_match(
         .next()
         .ok_or(SelectionError::NotFound)
 }
+
+/// Returns the index of the candidate whose properties best match `query` among the candidates
+/// with the highest score, as ranked by a `MatchScorer`.
+///
+/// `scores` must have one entry per candidate. Candidates with a NaN score are never chosen.
+pub fn find_best_match_with_scores(
+    candidates: &[Properties],
+    scores: &[f32],
+    query: &Properties,
+) -> Result<usize, SelectionError> {
+    debug_assert_eq!(candidates.len(), scores.len());
+    let best_score = scores
+        .iter()
+        .filter(|score| !score.is_nan())
+        .max_by_key(|&&score| FloatOrd(score))
+        .ok_or(SelectionError::NotFound)?;
+    let indices: Vec<usize> = (0..candidates.len())
+        .filter(|&index| scores[index] == *best_score)
+        .collect();
+    let best_candidates: Vec<Properties> = indices.iter().map(|&index| candidates[index]).collect();
+    find_best_match(&best_candidates, query).map(|index| indices[index])
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_best_match, find_best_match_with_scores};
+    #[cfg(feature = "source")]
+    use super::{select_best_match_with_stretch_synthesis, synthesize_stretch, StretchSynthesis};
+    #[cfg(feature = "source")]
+    use crate::family_name::FamilyName;
+    use crate::properties::{Properties, Stretch, Style, Weight};
+    #[cfg(feature = "source")]
+    use crate::sources::mem::MemSource;
+    #[cfg(feature = "source")]
+    use crate::test_fixtures::EB_GARAMOND;
+    #[cfg(feature = "source")]
+    use std::sync::Arc;
+
+    fn properties(style: Style, weight: f32, stretch: f32) -> Properties {
+        Properties {
+            style,
+            weight: Weight(weight),
+            stretch: Stretch(stretch),
+        }
+    }
+
+    #[test]
+    fn test_find_best_match_prefers_stretch_then_style_then_weight() {
+        let candidates = [
+            properties(Style::Normal, 400.0, 0.75),
+            properties(Style::Italic, 700.0, 1.0),
+            properties(Style::Normal, 300.0, 1.0),
+            properties(Style::Normal, 900.0, 1.0),
+        ];
+        let query = properties(Style::Normal, 400.0, 1.0);
+        assert_eq!(find_best_match(&candidates, &query), Ok(2));
+
+        let query = properties(Style::Oblique, 400.0, 1.0);
+        assert_eq!(find_best_match(&candidates, &query), Ok(1));
+
+        let query = properties(Style::Normal, 600.0, 1.0);
+        assert_eq!(find_best_match(&candidates, &query), Ok(3));
+    }
+
+    #[test]
+    fn test_scores_take_precedence_over_properties() {
+        let candidates = [
+            properties(Style::Normal, 400.0, 1.0),
+            properties(Style::Normal, 700.0, 1.0),
+            properties(Style::Italic, 400.0, 1.0),
+        ];
+        let query = properties(Style::Normal, 400.0, 1.0);
+        assert_eq!(
+            find_best_match_with_scores(&candidates, &[0.0, 1.0, 1.0], &query),
+            Ok(1)
+        );
+        assert_eq!(
+            find_best_match_with_scores(&candidates, &[f32::NAN, 0.0, 0.0], &query),
+            Ok(1)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "source")]
+    fn test_stretch_synthesis() {
+        let source = MemSource::from_bytes(vec![Arc::new(EB_GARAMOND.to_vec())]).unwrap();
+        let family_names = [FamilyName::Title("EB Garamond".to_owned())];
+        let mut query = Properties::new();
+        query.stretch(Stretch::CONDENSED);
+
+        let result = select_best_match_with_stretch_synthesis(
+            &source,
+            &family_names,
+            &query,
+            &StretchSynthesis::default(),
+        )
+        .unwrap();
+        assert_eq!(result.horizontal_scale, 0.75);
+
+        query.stretch(Stretch::ULTRA_CONDENSED);
+        let result = select_best_match_with_stretch_synthesis(
+            &source,
+            &family_names,
+            &query,
+            &StretchSynthesis::default(),
+        )
+        .unwrap();
+        assert_eq!(result.horizontal_scale, 0.75);
+        assert_eq!(result.width, None);
+    }
+
+    #[test]
+    #[cfg(feature = "source")]
+    fn test_synthesize_stretch() {
+        let limits = StretchSynthesis::default();
+        assert_eq!(
+            synthesize_stretch(Stretch::CONDENSED, Stretch::NORMAL, None, &limits),
+            (None, 0.75)
+        );
+        assert_eq!(
+            synthesize_stretch(
+                Stretch::CONDENSED,
+                Stretch::NORMAL,
+                Some((50.0, 100.0)),
+                &limits
+            ),
+            (Some(75.0), 1.0)
+        );
+        assert_eq!(
+            synthesize_stretch(
+                Stretch::EXPANDED,
+                Stretch::NORMAL,
+                Some((75.0, 100.0)),
+                &limits
+            ),
+            (Some(100.0), 1.0)
+        );
+    }
+
+    #[test]
+    fn test_find_best_match_empty() {
+        assert!(find_best_match(&[], &Properties::new()).is_err());
+    }
+}
@@ -0,0 +1,463 @@
+// font-kit/src/math.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The OpenType `MATH` table, which math fonts such as Latin Modern Math and STIX Two Math carry
+//! for laying out formulas.
+//!
+//! `Font::math_constants()` returns the font-wide constants that position scripts, fractions,
+//! radicals, and limits. Per-glyph data comes from `Font::math_italics_correction()`,
+//! `Font::math_top_accent_attachment()`, and `Font::is_math_extended_shape()`, and
+//! `Font::math_glyph_construction()` returns the larger variants of a glyph such as a parenthesis
+//! or an integral sign, and the parts to assemble it from when no variant is large enough.
+//!
+//! All distances are in font units. Device tables, which adjust values at particular pixel sizes,
+//! are ignored, as is math kerning.
+
+use ttf_parser::math::{Constants, GlyphConstruction};
+use ttf_parser::{Face, GlyphId};
+
+use crate::metrics::GlyphOrientation;
+
+/// The font-wide constants of a `MATH` table.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MathConstants {
+    /// The size of first-level subscripts and superscripts, as a percentage of the normal size,
+    /// such as 70.
+    pub script_percent_scale_down: i16,
+    /// The size of second-level subscripts and superscripts, as a percentage of the normal size.
+    pub script_script_percent_scale_down: i16,
+    /// The least height of a subformula before the delimiters around it are enlarged.
+    pub delimited_sub_formula_min_height: f32,
+    /// The least height of large operators, such as sums and integrals, in display style.
+    pub display_operator_min_height: f32,
+    /// The white space between lines of display formulas.
+    pub math_leading: f32,
+    /// The height of the math axis, on which fraction bars and operators are centered.
+    pub axis_height: f32,
+    /// The height up to which accents don't need to be raised.
+    pub accent_base_height: f32,
+    /// The height above which flattened accent forms should be used.
+    pub flattened_accent_base_height: f32,
+    /// How far subscripts are shifted down.
+    pub subscript_shift_down: f32,
+    /// The highest the top of a subscript may reach.
+    pub subscript_top_max: f32,
+    /// The least distance between the bottom of the base and the baseline of a subscript.
+    pub subscript_baseline_drop_min: f32,
+    /// How far superscripts are shifted up.
+    pub superscript_shift_up: f32,
+    /// How far superscripts are shifted up in cramped styles.
+    pub superscript_shift_up_cramped: f32,
+    /// The lowest the bottom of a superscript may reach.
+    pub superscript_bottom_min: f32,
+    /// The greatest distance between the top of the base and the baseline of a superscript.
+    pub superscript_baseline_drop_max: f32,
+    /// The least gap between a superscript and a subscript on the same base.
+    pub sub_superscript_gap_min: f32,
+    /// The highest the bottom of a superscript may be raised to when making room for a subscript.
+    pub superscript_bottom_max_with_subscript: f32,
+    /// The extra space after a subscript or superscript.
+    pub space_after_script: f32,
+    /// The least gap between the top of an operator and its upper limit.
+    pub upper_limit_gap_min: f32,
+    /// The least distance between the top of an operator and the baseline of its upper limit.
+    pub upper_limit_baseline_rise_min: f32,
+    /// The least gap between the bottom of an operator and its lower limit.
+    pub lower_limit_gap_min: f32,
+    /// The least distance between the bottom of an operator and the baseline of its lower limit.
+    pub lower_limit_baseline_drop_min: f32,
+    /// How far the top element of a stack is shifted up.
+    pub stack_top_shift_up: f32,
+    /// How far the top element of a stack is shifted up in display style.
+    pub stack_top_display_style_shift_up: f32,
+    /// How far the bottom element of a stack is shifted down.
+    pub stack_bottom_shift_down: f32,
+    /// How far the bottom element of a stack is shifted down in display style.
+    pub stack_bottom_display_style_shift_down: f32,
+    /// The least gap between the elements of a stack.
+    pub stack_gap_min: f32,
+    /// The least gap between the elements of a stack in display style.
+    pub stack_display_style_gap_min: f32,
+    /// How far the baseline of an element above a stretched glyph is shifted up.
+    pub stretch_stack_top_shift_up: f32,
+    /// How far the baseline of an element below a stretched glyph is shifted down.
+    pub stretch_stack_bottom_shift_down: f32,
+    /// The least gap between a stretched glyph and the element above it.
+    pub stretch_stack_gap_above_min: f32,
+    /// The least gap between a stretched glyph and the element below it.
+    pub stretch_stack_gap_below_min: f32,
+    /// How far the numerator of a fraction is shifted up.
+    pub fraction_numerator_shift_up: f32,
+    /// How far the numerator of a fraction is shifted up in display style.
+    pub fraction_numerator_display_style_shift_up: f32,
+    /// How far the denominator of a fraction is shifted down.
+    pub fraction_denominator_shift_down: f32,
+    /// How far the denominator of a fraction is shifted down in display style.
+    pub fraction_denominator_display_style_shift_down: f32,
+    /// The least gap between the numerator and the fraction bar.
+    pub fraction_numerator_gap_min: f32,
+    /// The least gap between the numerator and the fraction bar in display style.
+    pub fraction_num_display_style_gap_min: f32,
+    /// The thickness of the fraction bar.
+    pub fraction_rule_thickness: f32,
+    /// The least gap between the denominator and the fraction bar.
+    pub fraction_denominator_gap_min: f32,
+    /// The least gap between the denominator and the fraction bar in display style.
+    pub fraction_denom_display_style_gap_min: f32,
+    /// The horizontal gap between the numerator and denominator of a skewed fraction.
+    pub skewed_fraction_horizontal_gap: f32,
+    /// The vertical gap between the numerator and denominator of a skewed fraction.
+    pub skewed_fraction_vertical_gap: f32,
+    /// The gap between an overbar and the top of the element under it.
+    pub overbar_vertical_gap: f32,
+    /// The thickness of an overbar.
+    pub overbar_rule_thickness: f32,
+    /// The extra white space above an overbar.
+    pub overbar_extra_ascender: f32,
+    /// The gap between an underbar and the bottom of the element above it.
+    pub underbar_vertical_gap: f32,
+    /// The thickness of an underbar.
+    pub underbar_rule_thickness: f32,
+    /// The extra white space below an underbar.
+    pub underbar_extra_descender: f32,
+    /// The gap between the radicand and the bar of a radical.
+    pub radical_vertical_gap: f32,
+    /// The gap between the radicand and the bar of a radical in display style.
+    pub radical_display_style_vertical_gap: f32,
+    /// The thickness of the bar of a radical.
+    pub radical_rule_thickness: f32,
+    /// The extra white space above the bar of a radical.
+    pub radical_extra_ascender: f32,
+    /// The space before the degree of a radical.
+    pub radical_kern_before_degree: f32,
+    /// The space after the degree of a radical, which is usually negative.
+    pub radical_kern_after_degree: f32,
+    /// How far the bottom of the degree of a radical is raised, as a percentage of the height of
+    /// the radical sign.
+    pub radical_degree_bottom_raise_percent: i16,
+}
+
+impl MathConstants {
+    fn from_table(constants: Constants) -> MathConstants {
+        MathConstants {
+            script_percent_scale_down: constants.script_percent_scale_down(),
+            script_script_percent_scale_down: constants.script_script_percent_scale_down(),
+            delimited_sub_formula_min_height: constants.delimited_sub_formula_min_height() as f32,
+            display_operator_min_height: constants.display_operator_min_height() as f32,
+            math_leading: constants.math_leading().value as f32,
+            axis_height: constants.axis_height().value as f32,
+            accent_base_height: constants.accent_base_height().value as f32,
+            flattened_accent_base_height: constants.flattened_accent_base_height().value as f32,
+            subscript_shift_down: constants.subscript_shift_down().value as f32,
+            subscript_top_max: constants.subscript_top_max().value as f32,
+            subscript_baseline_drop_min: constants.subscript_baseline_drop_min().value as f32,
+            superscript_shift_up: constants.superscript_shift_up().value as f32,
+            superscript_shift_up_cramped: constants.superscript_shift_up_cramped().value as f32,
+            superscript_bottom_min: constants.superscript_bottom_min().value as f32,
+            superscript_baseline_drop_max: constants.superscript_baseline_drop_max().value as f32,
+            sub_superscript_gap_min: constants.sub_superscript_gap_min().value as f32,
+            superscript_bottom_max_with_subscript: constants
+                .superscript_bottom_max_with_subscript()
+                .value as f32,
+            space_after_script: constants.space_after_script().value as f32,
+            upper_limit_gap_min: constants.upper_limit_gap_min().value as f32,
+            upper_limit_baseline_rise_min: constants.upper_limit_baseline_rise_min().value as f32,
+            lower_limit_gap_min: constants.lower_limit_gap_min().value as f32,
+            lower_limit_baseline_drop_min: constants.lower_limit_baseline_drop_min().value as f32,
+            stack_top_shift_up: constants.stack_top_shift_up().value as f32,
+            stack_top_display_style_shift_up: constants.stack_top_display_style_shift_up().value
+                as f32,
+            stack_bottom_shift_down: constants.stack_bottom_shift_down().value as f32,
+            stack_bottom_display_style_shift_down: constants
+                .stack_bottom_display_style_shift_down()
+                .value as f32,
+            stack_gap_min: constants.stack_gap_min().value as f32,
+            stack_display_style_gap_min: constants.stack_display_style_gap_min().value as f32,
+            stretch_stack_top_shift_up: constants.stretch_stack_top_shift_up().value as f32,
+            stretch_stack_bottom_shift_down: constants.stretch_stack_bottom_shift_down().value
+                as f32,
+            stretch_stack_gap_above_min: constants.stretch_stack_gap_above_min().value as f32,
+            stretch_stack_gap_below_min: constants.stretch_stack_gap_below_min().value as f32,
+            fraction_numerator_shift_up: constants.fraction_numerator_shift_up().value as f32,
+            fraction_numerator_display_style_shift_up: constants
+                .fraction_numerator_display_style_shift_up()
+                .value as f32,
+            fraction_denominator_shift_down: constants.fraction_denominator_shift_down().value
+                as f32,
+            fraction_denominator_display_style_shift_down: constants
+                .fraction_denominator_display_style_shift_down()
+                .value as f32,
+            fraction_numerator_gap_min: constants.fraction_numerator_gap_min().value as f32,
+            fraction_num_display_style_gap_min: constants.fraction_num_display_style_gap_min().value
+                as f32,
+            fraction_rule_thickness: constants.fraction_rule_thickness().value as f32,
+            fraction_denominator_gap_min: constants.fraction_denominator_gap_min().value as f32,
+            fraction_denom_display_style_gap_min: constants
+                .fraction_denom_display_style_gap_min()
+                .value as f32,
+            skewed_fraction_horizontal_gap: constants.skewed_fraction_horizontal_gap().value as f32,
+            skewed_fraction_vertical_gap: constants.skewed_fraction_vertical_gap().value as f32,
+            overbar_vertical_gap: constants.overbar_vertical_gap().value as f32,
+            overbar_rule_thickness: constants.overbar_rule_thickness().value as f32,
+            overbar_extra_ascender: constants.overbar_extra_ascender().value as f32,
+            underbar_vertical_gap: constants.underbar_vertical_gap().value as f32,
+            underbar_rule_thickness: constants.underbar_rule_thickness().value as f32,
+            underbar_extra_descender: constants.underbar_extra_descender().value as f32,
+            radical_vertical_gap: constants.radical_vertical_gap().value as f32,
+            radical_display_style_vertical_gap: constants.radical_display_style_vertical_gap().value
+                as f32,
+            radical_rule_thickness: constants.radical_rule_thickness().value as f32,
+            radical_extra_ascender: constants.radical_extra_ascender().value as f32,
+            radical_kern_before_degree: constants.radical_kern_before_degree().value as f32,
+            radical_kern_after_degree: constants.radical_kern_after_degree().value as f32,
+            radical_degree_bottom_raise_percent: constants.radical_degree_bottom_raise_percent(),
+        }
+    }
+}
+
+/// The ways to draw a glyph larger, such as a parenthesis that must enclose a tall formula.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MathGlyphConstruction {
+    /// Ready-made variants of the glyph, from smallest to largest. The first is usually the
+    /// glyph itself.
+    pub variants: Vec<MathGlyphVariant>,
+    /// The parts to assemble the glyph from when even the largest variant is too small.
+    pub assembly: Option<MathGlyphAssembly>,
+    /// The least overlap between connected parts of an assembly, from the font's `MATH` table.
+    pub min_connector_overlap: f32,
+}
+
+/// A ready-made size variant of a glyph.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MathGlyphVariant {
+    /// The glyph ID of the variant.
+    pub glyph_id: u32,
+    /// The size of the variant in the direction it grows: its height for vertical constructions
+    /// and its width for horizontal ones.
+    pub advance: f32,
+}
+
+/// How to build a glyph of any size from parts, some of which may be repeated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MathGlyphAssembly {
+    /// The italics correction of the assembled glyph.
+    pub italics_correction: f32,
+    /// The parts, from bottom to top for vertical constructions and from left to right for
+    /// horizontal ones.
+    pub parts: Vec<MathGlyphPart>,
+}
+
+/// One part of a `MathGlyphAssembly`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MathGlyphPart {
+    /// The glyph ID of the part.
+    pub glyph_id: u32,
+    /// The length of the connector at the start of the part, which may overlap the previous part.
+    pub start_connector_length: f32,
+    /// The length of the connector at the end of the part, which may overlap the next part.
+    pub end_connector_length: f32,
+    /// The full size of the part in the direction of the assembly.
+    pub full_advance: f32,
+    /// True if the part may be repeated, or left out, to reach the wanted size.
+    pub is_extender: bool,
+}
+
+impl MathGlyphConstruction {
+    fn from_table(
+        construction: GlyphConstruction,
+        min_connector_overlap: u16,
+    ) -> MathGlyphConstruction {
+        MathGlyphConstruction {
+            variants: construction
+                .variants
+                .into_iter()
+                .map(|variant| MathGlyphVariant {
+                    glyph_id: variant.variant_glyph.0 as u32,
+                    advance: variant.advance_measurement as f32,
+                })
+                .collect(),
+            assembly: construction.assembly.map(|assembly| MathGlyphAssembly {
+                italics_correction: assembly.italics_correction.value as f32,
+                parts: assembly
+                    .parts
+                    .into_iter()
+                    .map(|part| MathGlyphPart {
+                        glyph_id: part.glyph_id.0 as u32,
+                        start_connector_length: part.start_connector_length as f32,
+                        end_connector_length: part.end_connector_length as f32,
+                        full_advance: part.full_advance as f32,
+                        is_extender: part.part_flags.extender(),
+                    })
+                    .collect(),
+            }),
+            min_connector_overlap: min_connector_overlap as f32,
+        }
+    }
+}
+
+pub(crate) fn constants(face: &Face) -> Option<MathConstants> {
+    face.tables().math?.constants.map(MathConstants::from_table)
+}
+
+pub(crate) fn italics_correction(face: &Face, glyph_id: GlyphId) -> Option<f32> {
+    let glyph_info = face.tables().math?.glyph_info?;
+    let value = glyph_info.italic_corrections?.get(glyph_id)?;
+    Some(value.value as f32)
+}
+
+pub(crate) fn top_accent_attachment(face: &Face, glyph_id: GlyphId) -> Option<f32> {
+    let glyph_info = face.tables().math?.glyph_info?;
+    let value = glyph_info.top_accent_attachments?.get(glyph_id)?;
+    Some(value.value as f32)
+}
+
+pub(crate) fn is_extended_shape(face: &Face, glyph_id: GlyphId) -> bool {
+    face.tables()
+        .math
+        .and_then(|math| math.glyph_info?.extended_shapes)
+        .is_some_and(|extended_shapes| extended_shapes.contains(glyph_id))
+}
+
+pub(crate) fn glyph_construction(
+    face: &Face,
+    glyph_id: GlyphId,
+    orientation: GlyphOrientation,
+) -> Option<MathGlyphConstruction> {
+    let variants = face.tables().math?.variants?;
+    let constructions = match orientation {
+        GlyphOrientation::Horizontal => variants.horizontal_constructions,
+        GlyphOrientation::Vertical => variants.vertical_constructions,
+    };
+    let construction = constructions.get(glyph_id)?;
+    Some(MathGlyphConstruction::from_table(
+        construction,
+        variants.min_connector_overlap,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use crate::metrics::GlyphOrientation;
+    use crate::test_fixtures::INCONSOLATA;
+    use crate::utils;
+    use std::sync::Arc;
+    use ttf_parser::RawFace;
+
+    // Glyphs to describe in the test `MATH` table.
+    const ITALIC: u16 = 10;
+    const ACCENTED: u16 = 11;
+    const PAREN: u16 = 12;
+    const LARGE_PAREN: u16 = 13;
+    const EXTENDER: u16 = 14;
+
+    fn push(data: &mut Vec<u8>, values: &[u16]) {
+        for value in values {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+
+    // Builds a small `MATH` table with every kind of data `math` reads.
+    fn math_table() -> Vec<u8> {
+        let mut data = vec![];
+        // Header: version, then offsets to the constants, glyph info, and variants.
+        push(&mut data, &[1, 0, 10, 224, 266]);
+
+        // Constants: percentages and minimum heights, 51 values, the first two of which are the
+        // math leading and the axis height, and the degree raise.
+        push(&mut data, &[70, 50, 1300, 1500]);
+        for index in 0..51 {
+            push(&mut data, &[if index == 1 { 250 } else { 0 }, 0]);
+        }
+        push(&mut data, &[60]);
+        assert_eq!(data.len(), 224);
+
+        // Glyph info: italics corrections, top accent attachments, extended shapes, no kerning.
+        push(&mut data, &[8, 22, 36, 0]);
+        push(&mut data, &[8, 1, 40, 0, 1, 1, ITALIC]);
+        push(&mut data, &[8, 1, 300, 0, 1, 1, ACCENTED]);
+        push(&mut data, &[1, 1, PAREN]);
+        assert_eq!(data.len(), 266);
+
+        // Variants: a vertical construction for the parenthesis with two variants and an
+        // assembly of two parts, the second of which is an extender.
+        push(&mut data, &[20, 12, 0, 1, 0, 18]);
+        push(&mut data, &[1, 1, PAREN]);
+        push(&mut data, &[12, 2, PAREN, 1000, LARGE_PAREN, 1500]);
+        push(&mut data, &[0, 0, 2]);
+        push(&mut data, &[LARGE_PAREN, 0, 100, 500, 0]);
+        push(&mut data, &[EXTENDER, 100, 100, 400, 1]);
+        data
+    }
+
+    fn math_font() -> Font {
+        let face = RawFace::parse(INCONSOLATA, 0).unwrap();
+        let mut tables: Vec<(u32, Vec<u8>)> = face
+            .table_records
+            .into_iter()
+            .map(|record| {
+                let range = record.offset as usize..(record.offset + record.length) as usize;
+                (record.tag.0, INCONSOLATA[range].to_vec())
+            })
+            .collect();
+        tables.push((u32::from_be_bytes(*b"MATH"), math_table()));
+        tables.sort_by_key(|&(tag, _)| tag);
+        Font::from_bytes(Arc::new(utils::build_sfnt(&tables)), 0).unwrap()
+    }
+
+    #[test]
+    fn test_math_table() {
+        let font = math_font();
+        let constants = font.math_constants().unwrap();
+        assert_eq!(constants.script_percent_scale_down, 70);
+        assert_eq!(constants.script_script_percent_scale_down, 50);
+        assert_eq!(constants.display_operator_min_height, 1500.0);
+        assert_eq!(constants.axis_height, 250.0);
+        assert_eq!(constants.math_leading, 0.0);
+        assert_eq!(constants.radical_degree_bottom_raise_percent, 60);
+
+        assert_eq!(font.math_italics_correction(ITALIC as u32), Some(40.0));
+        assert_eq!(font.math_italics_correction(ACCENTED as u32), None);
+        assert_eq!(
+            font.math_top_accent_attachment(ACCENTED as u32),
+            Some(300.0)
+        );
+        assert!(font.is_math_extended_shape(PAREN as u32));
+        assert!(!font.is_math_extended_shape(ITALIC as u32));
+
+        let construction = font
+            .math_glyph_construction(PAREN as u32, GlyphOrientation::Vertical)
+            .unwrap();
+        assert_eq!(construction.min_connector_overlap, 20.0);
+        let variants: Vec<_> = construction
+            .variants
+            .iter()
+            .map(|variant| (variant.glyph_id, variant.advance))
+            .collect();
+        assert_eq!(
+            variants,
+            [(PAREN as u32, 1000.0), (LARGE_PAREN as u32, 1500.0)]
+        );
+        let parts = construction.assembly.unwrap().parts;
+        assert_eq!(parts.len(), 2);
+        assert!(!parts[0].is_extender && parts[1].is_extender);
+        assert_eq!(parts[1].full_advance, 400.0);
+        assert!(font
+            .math_glyph_construction(PAREN as u32, GlyphOrientation::Horizontal)
+            .is_none());
+
+        let font = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        assert!(font.math_constants().is_none());
+        assert!(font
+            .math_glyph_construction(PAREN as u32, GlyphOrientation::Vertical)
+            .is_none());
+    }
+}
@@ -13,6 +13,7 @@
 //! For OpenType fonts, these mostly come from the `OS/2` table.
 
 use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::vector::Vector2F;
 
 /// Various metrics that apply to the entire font.
 ///
@@ -39,11 +40,28 @@ pub struct Metrics {
 
     /// The suggested distance of the top of the underline from the baseline (negative values
     /// indicate below baseline), in font units.
+    ///
+    /// For OpenType fonts, this comes from the `post` table.
     pub underline_position: f32,
 
     /// A suggested value for the underline thickness, in font units.
+    ///
+    /// For OpenType fonts, this comes from the `post` table.
     pub underline_thickness: f32,
 
+    /// The suggested distance of the top of the strikeout stroke from the baseline, in font
+    /// units.
+    ///
+    /// For OpenType fonts, this comes from the `OS/2` table. Fonts that don't specify it get a
+    /// stroke centered at half the x-height.
+    pub strikeout_position: f32,
+
+    /// A suggested value for the strikeout thickness, in font units.
+    ///
+    /// For OpenType fonts, this comes from the `OS/2` table. Fonts that don't specify it get the
+    /// underline thickness.
+    pub strikeout_thickness: f32,
+
     /// The approximate amount that uppercase letters rise above the baseline, in font units.
     pub cap_height: f32,
 
@@ -56,3 +74,101 @@ pub struct Metrics {
     /// This corresponds to the `xMin`/`xMax`/`yMin`/`yMax` values in the OpenType `head` table.
     pub bounding_box: RectF,
 }
+
+impl Metrics {
+    /// Returns these metrics with the strikeout estimated from the other metrics if the font
+    /// doesn't specify it, as many fonts without an `OS/2` table don't.
+    pub(crate) fn with_estimated_strikeout(mut self) -> Metrics {
+        if self.strikeout_thickness > 0.0 {
+            return self;
+        }
+        let x_height = if self.x_height > 0.0 {
+            self.x_height
+        } else {
+            self.units_per_em as f32 * 0.5
+        };
+        self.strikeout_thickness = self.underline_thickness;
+        self.strikeout_position = (x_height + self.strikeout_thickness) * 0.5;
+        self
+    }
+
+    /// Returns the rectangles to fill to underline and strike out a run of text, given its
+    /// width and the font size, both in the same units.
+    ///
+    /// The rectangles start at the origin of the run, on the baseline, with the y axis pointing
+    /// up as in `Font::layout_run()`.
+    pub fn decoration_rects(&self, point_size: f32, width: f32) -> DecorationRects {
+        let scale = point_size / self.units_per_em as f32;
+        let rect = |top: f32, thickness: f32| {
+            RectF::new(
+                Vector2F::new(0.0, (top - thickness) * scale),
+                Vector2F::new(width, thickness * scale),
+            )
+        };
+        DecorationRects {
+            underline: rect(self.underline_position, self.underline_thickness),
+            strikeout: rect(self.strikeout_position, self.strikeout_thickness),
+        }
+    }
+}
+
+/// Rectangles for drawing text decorations, from `Metrics::decoration_rects()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DecorationRects {
+    /// The underline.
+    pub underline: RectF,
+    /// The strikeout, also known as the strikethrough or line-through.
+    pub strikeout: RectF,
+}
+
+/// Replacements for a font's vertical metrics, like the CSS `ascent-override`,
+/// `descent-override`, and `line-gap-override` descriptors.
+///
+/// Each value is a fraction of the em size, so `Some(0.9)` corresponds to `ascent-override: 90%`.
+/// Metrics that are `None` keep the font's own value.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MetricsOverride {
+    /// The ascent above the baseline.
+    pub ascent: Option<f32>,
+    /// The descent below the baseline. Like the CSS descriptor, and unlike `Metrics::descent`,
+    /// this is positive for a descent below the baseline.
+    pub descent: Option<f32>,
+    /// The line gap.
+    pub line_gap: Option<f32>,
+}
+
+impl MetricsOverride {
+    /// Returns true if no metrics are overridden.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ascent.is_none() && self.descent.is_none() && self.line_gap.is_none()
+    }
+
+    /// Returns `metrics` with the overridden values replaced.
+    pub fn apply(&self, metrics: &Metrics) -> Metrics {
+        let units_per_em = metrics.units_per_em as f32;
+        Metrics {
+            ascent: self
+                .ascent
+                .map_or(metrics.ascent, |ascent| ascent * units_per_em),
+            descent: self
+                .descent
+                .map_or(metrics.descent, |descent| -descent * units_per_em),
+            line_gap: self
+                .line_gap
+                .map_or(metrics.line_gap, |line_gap| line_gap * units_per_em),
+            ..*metrics
+        }
+    }
+}
+
+/// The direction in which glyphs are laid out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GlyphOrientation {
+    /// Glyphs are placed left to right or right to left, with their origins on the baseline.
+    #[default]
+    Horizontal,
+    /// Glyphs are stacked top to bottom, as in vertical CJK text. Each glyph's origin is at the
+    /// top of the glyph, horizontally centered.
+    Vertical,
+}
@@ -0,0 +1,178 @@
+// font-kit/src/mmap.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Read-only memory maps of font files.
+//!
+//! Mapping a font instead of reading it means that only the pages that are actually used, such as
+//! the tables and glyphs needed for the text at hand, occupy memory, and that the operating system
+//! can drop them again under memory pressure. This matters for CJK and emoji fonts, which are
+//! often tens of megabytes each.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{Error as IOError, ErrorKind};
+use std::ops::Deref;
+use std::slice;
+
+/// A read-only mapping of an entire file.
+pub(crate) struct Mmap {
+    ptr: *const u8,
+    len: usize,
+}
+
+// The mapping is read-only and isn't tied to the thread that created it.
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    /// Maps the whole of `file` into memory.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the mapping is alive. The contents of the
+    /// mapping change if the file is modified, which breaks the guarantee that the borrowed slice
+    /// doesn't change, and accessing pages past the end of the file after it is truncated crashes
+    /// the process on some platforms. Installed fonts, which are replaced rather than modified in
+    /// place, are safe to map.
+    pub(crate) unsafe fn map(file: &File) -> Result<Mmap, IOError> {
+        let len = file.metadata()?.len();
+        if len == 0 {
+            return Err(IOError::new(
+                ErrorKind::UnexpectedEof,
+                "cannot map an empty file",
+            ));
+        }
+        if len > usize::MAX as u64 {
+            return Err(IOError::new(
+                ErrorKind::InvalidInput,
+                "file is too large to map",
+            ));
+        }
+        Mmap::map_len(file, len as usize)
+    }
+
+    #[cfg(unix)]
+    unsafe fn map_len(file: &File, len: usize) -> Result<Mmap, IOError> {
+        use std::os::unix::io::AsRawFd;
+        use std::ptr;
+
+        let ptr = libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(IOError::last_os_error());
+        }
+        Ok(Mmap {
+            ptr: ptr as *const u8,
+            len,
+        })
+    }
+
+    #[cfg(target_family = "windows")]
+    unsafe fn map_len(file: &File, len: usize) -> Result<Mmap, IOError> {
+        use std::os::windows::io::AsRawHandle;
+        use std::ptr;
+        use winapi::um::handleapi::CloseHandle;
+        use winapi::um::memoryapi::{CreateFileMappingW, MapViewOfFile, FILE_MAP_READ};
+        use winapi::um::winnt::PAGE_READONLY;
+
+        let mapping = CreateFileMappingW(
+            file.as_raw_handle() as _,
+            ptr::null_mut(),
+            PAGE_READONLY,
+            0,
+            0,
+            ptr::null(),
+        );
+        if mapping.is_null() {
+            return Err(IOError::last_os_error());
+        }
+        // The view keeps the mapping alive, so the mapping handle can be closed right away.
+        let ptr = MapViewOfFile(mapping, FILE_MAP_READ, 0, 0, len);
+        let error = IOError::last_os_error();
+        CloseHandle(mapping);
+        if ptr.is_null() {
+            return Err(error);
+        }
+        Ok(Mmap {
+            ptr: ptr as *const u8,
+            len,
+        })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    #[cfg(unix)]
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.len);
+        }
+    }
+
+    #[cfg(target_family = "windows")]
+    fn drop(&mut self) {
+        unsafe {
+            winapi::um::memoryapi::UnmapViewOfFile(self.ptr as *const _);
+        }
+    }
+}
+
+impl fmt::Debug for Mmap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mmap")
+            .field("ptr", &self.ptr)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::font::Font;
+    use crate::loader::Loader;
+
+    static EB_GARAMOND_COLLECTION_PATH: &str = "resources/tests/eb-garamond/EBGaramond12.otc";
+
+    #[test]
+    fn test_load_mapped_font() {
+        // SAFETY: The test fixtures aren't modified while the tests run.
+        let mapped = unsafe { Font::from_path_mmap(EB_GARAMOND_COLLECTION_PATH, 1) }.unwrap();
+        let read = Font::from_path(EB_GARAMOND_COLLECTION_PATH, 1).unwrap();
+        assert_eq!(mapped.postscript_name(), read.postscript_name());
+        assert_eq!(mapped.copy_font_data(), read.copy_font_data());
+        assert_eq!(
+            mapped.with_font_data(|font_data| font_data.len()),
+            read.with_font_data(|font_data| font_data.len())
+        );
+
+        // The native font shares the mapping rather than copying it.
+        #[cfg(not(any(target_os = "macos", target_os = "ios", target_family = "windows")))]
+        {
+            let native_font = mapped.native_font();
+            assert_eq!(native_font.font_data(), &**read.copy_font_data().unwrap());
+            let round_tripped = unsafe { Font::from_native_font(native_font) };
+            assert!(round_tripped.same_font_as(&read));
+        }
+    }
+}
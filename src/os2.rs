@@ -0,0 +1,610 @@
+// font-kit/src/os2.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Information from a font's `OS/2` table that isn't covered by its properties and metrics.
+//!
+//! `Font::embedding_permissions()` decodes the `fsType` field, which font vendors use to license
+//! embedding the font in documents. PDF and ebook generators must check it before embedding.
+//!
+//! `Font::unicode_ranges()` and `Font::codepage_ranges()` return the scripts and code pages that
+//! the font declares support for, which font pickers can group fonts by without reading each
+//! font's character map. `Font::panose()` returns the font's PANOSE classification, which
+//! describes its appearance.
+
+use byteorder::{BigEndian, ByteOrder};
+
+/// How a font may be embedded in documents, from the `OS/2` table's `fsType` field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EmbeddingPermissions {
+    /// What a document with the font embedded may do with it.
+    pub level: EmbeddingLevel,
+    /// Whether the font must be embedded whole rather than subset.
+    pub no_subsetting: bool,
+    /// Whether only the font's bitmaps may be embedded. Fonts without bitmaps can't be embedded at
+    /// all then.
+    pub bitmap_only: bool,
+}
+
+/// The embedding licensing level of a font, from least to most restrictive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EmbeddingLevel {
+    /// The font may be embedded and permanently installed by the document's readers.
+    Installable,
+    /// The font may be embedded, and documents may be edited with it.
+    Editable,
+    /// The font may be embedded, but only to view and print the document.
+    PreviewAndPrint,
+    /// The font must not be embedded, modified, or exchanged without the vendor's permission.
+    Restricted,
+}
+
+// `fsType` bits.
+const RESTRICTED: u16 = 0x0002;
+const PREVIEW_AND_PRINT: u16 = 0x0004;
+const EDITABLE: u16 = 0x0008;
+const NO_SUBSETTING: u16 = 0x0100;
+const BITMAP_ONLY: u16 = 0x0200;
+
+impl EmbeddingPermissions {
+    /// Decodes an `fsType` value.
+    ///
+    /// Old fonts may set more than one of the level bits, in which case the least restrictive
+    /// level applies, as the OpenType specification recommends.
+    pub fn from_fs_type(fs_type: u16) -> EmbeddingPermissions {
+        let level = if fs_type & 0x000f == 0 {
+            EmbeddingLevel::Installable
+        } else if fs_type & EDITABLE != 0 {
+            EmbeddingLevel::Editable
+        } else if fs_type & PREVIEW_AND_PRINT != 0 {
+            EmbeddingLevel::PreviewAndPrint
+        } else if fs_type & RESTRICTED != 0 {
+            EmbeddingLevel::Restricted
+        } else {
+            // Bit 0 is reserved; treat a value with only it set as installable.
+            EmbeddingLevel::Installable
+        };
+        EmbeddingPermissions {
+            level,
+            no_subsetting: fs_type & NO_SUBSETTING != 0,
+            bitmap_only: fs_type & BITMAP_ONLY != 0,
+        }
+    }
+
+    /// Returns the permissions of a font with the given `OS/2` table. Fonts without one declare no
+    /// restrictions.
+    pub(crate) fn from_os2(os2: Option<&[u8]>) -> EmbeddingPermissions {
+        let fs_type = os2
+            .and_then(|os2| os2.get(8..10))
+            .map_or(0, BigEndian::read_u16);
+        EmbeddingPermissions::from_fs_type(fs_type)
+    }
+
+    /// Returns true if the font's outlines may be embedded in a document at all.
+    #[inline]
+    pub fn allows_embedding(&self) -> bool {
+        self.level != EmbeddingLevel::Restricted && !self.bitmap_only
+    }
+
+    /// Returns true if the font may be embedded as a subset, as `Font::subset()` creates.
+    #[inline]
+    pub fn allows_subsetting(&self) -> bool {
+        self.allows_embedding() && !self.no_subsetting
+    }
+}
+
+/// The Unicode ranges that a font declares that it supports, from the `ulUnicodeRange` fields of
+/// its `OS/2` table.
+///
+/// Each of the 123 bits that the OpenType specification defines stands for one or more Unicode
+/// blocks; bit 0 is Basic Latin, bit 9 Cyrillic, bit 59 CJK Unified Ideographs, and so on. Fonts
+/// set these by hand, so they can be incomplete or wrong, but they are much cheaper to read than
+/// the character map.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnicodeRanges(pub u128);
+
+impl UnicodeRanges {
+    /// Returns true if the given bit is set.
+    #[inline]
+    pub fn contains(&self, bit: u32) -> bool {
+        bit < 128 && self.0 & (1 << bit) != 0
+    }
+
+    /// Returns the set bits in increasing order.
+    pub fn bits(&self) -> impl Iterator<Item = u32> {
+        let ranges = *self;
+        (0..128).filter(move |&bit| ranges.contains(bit))
+    }
+
+    /// Returns the names of the ranges with set bits, such as "Cyrillic", in bit order. Reserved
+    /// bits are left out.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        self.bits().filter_map(UnicodeRanges::name)
+    }
+
+    /// Returns the name of the Unicode range that a bit stands for, or `None` for reserved bits.
+    ///
+    /// Bits standing for several blocks are named after the first.
+    #[inline]
+    pub fn name(bit: u32) -> Option<&'static str> {
+        UNICODE_RANGE_NAMES.get(bit as usize).copied()
+    }
+
+    // Reads the four `ulUnicodeRange` fields, the first of which holds bits 0–31.
+    pub(crate) fn from_os2(os2: Option<&[u8]>) -> UnicodeRanges {
+        let fields = match os2.and_then(|os2| os2.get(42..58)) {
+            Some(fields) => fields,
+            None => return UnicodeRanges::default(),
+        };
+        UnicodeRanges(
+            fields
+                .chunks(4)
+                .enumerate()
+                .fold(0, |bits, (index, field)| {
+                    bits | (BigEndian::read_u32(field) as u128) << (index * 32)
+                }),
+        )
+    }
+}
+
+/// The code pages that a font declares that it is functional for, from the `ulCodePageRange`
+/// fields of its `OS/2` table.
+///
+/// Bit 0 is Windows code page 1252 (Latin 1), bit 17 code page 932 (Japanese), and so on. Fonts
+/// with an `OS/2` table older than version 1 have none.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CodePageRanges(pub u64);
+
+impl CodePageRanges {
+    /// Returns true if the given bit is set.
+    #[inline]
+    pub fn contains(&self, bit: u32) -> bool {
+        bit < 64 && self.0 & (1 << bit) != 0
+    }
+
+    /// Returns the set bits in increasing order.
+    pub fn bits(&self) -> impl Iterator<Item = u32> {
+        let ranges = *self;
+        (0..64).filter(move |&bit| ranges.contains(bit))
+    }
+
+    /// Returns the Windows code page numbers of the set bits, such as 1252, in bit order. Bits
+    /// without a code page number, such as the Macintosh character set, are left out.
+    pub fn code_pages(&self) -> impl Iterator<Item = u16> {
+        self.bits().filter_map(CodePageRanges::code_page)
+    }
+
+    /// Returns the Windows code page number that a bit stands for, if it stands for one.
+    pub fn code_page(bit: u32) -> Option<u16> {
+        CODE_PAGES
+            .iter()
+            .find(|&&(code_page_bit, _, _)| code_page_bit == bit)
+            .and_then(|&(_, code_page, _)| code_page)
+    }
+
+    /// Returns the name of the character set that a bit stands for, such as "Latin 1", or `None`
+    /// for reserved bits.
+    pub fn name(bit: u32) -> Option<&'static str> {
+        CODE_PAGES
+            .iter()
+            .find(|&&(code_page_bit, _, _)| code_page_bit == bit)
+            .map(|&(_, _, name)| name)
+    }
+
+    // Reads the two `ulCodePageRange` fields, which version 1 of the table added.
+    pub(crate) fn from_os2(os2: Option<&[u8]>) -> CodePageRanges {
+        match os2 {
+            Some(os2) if os2.len() >= 86 && BigEndian::read_u16(os2) >= 1 => CodePageRanges(
+                (BigEndian::read_u32(&os2[82..86]) as u64) << 32
+                    | BigEndian::read_u32(&os2[78..82]) as u64,
+            ),
+            _ => CodePageRanges::default(),
+        }
+    }
+}
+
+/// The PANOSE classification of a font, from its `OS/2` table: ten digits that describe its
+/// appearance, for finding visually similar fonts.
+///
+/// The meaning of every digit after the first depends on the family kind; the names of the fields
+/// are those for Latin text fonts. In every family kind, 0 means "any" and 1 means "no fit".
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Panose {
+    /// The kind of font: Latin text, hand-written, decorative, or symbols.
+    pub family_kind: PanoseFamilyKind,
+    /// The shape of the serifs, from 2 (cove) to 10 (rounded) for serif fonts and 11 (normal sans)
+    /// to 15 (rounded sans) for sans-serif fonts.
+    pub serif_style: u8,
+    /// The weight, from 2 (very light) to 11 (extra black).
+    pub weight: u8,
+    /// The proportions, such as 3 (modern), 6 (condensed), or 9 (monospaced).
+    pub proportion: u8,
+    /// The ratio between the thickest and thinnest strokes, from 2 (none) to 9 (very high).
+    pub contrast: u8,
+    /// How the stroke width changes around curves.
+    pub stroke_variation: u8,
+    /// The style of the arms and terminals of diagonal strokes.
+    pub arm_style: u8,
+    /// The shape of round letters, and whether they are upright or oblique.
+    pub letterform: u8,
+    /// Where the stems of letters such as "A" and "M" meet.
+    pub midline: u8,
+    /// The x-height relative to the cap height.
+    pub x_height: u8,
+}
+
+/// The first PANOSE digit, which determines what the others mean.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PanoseFamilyKind {
+    /// Any family kind (0): the font isn't classified.
+    Any,
+    /// No fit (1).
+    NoFit,
+    /// Latin text (2).
+    LatinText,
+    /// Latin hand-written (3).
+    LatinHandWritten,
+    /// Latin decorative (4).
+    LatinDecorative,
+    /// Latin symbol (5).
+    LatinSymbol,
+    /// A family kind that this version of the crate doesn't know.
+    Other(u8),
+}
+
+impl Panose {
+    /// Decodes the ten PANOSE digits, in the order they appear in the `OS/2` table.
+    pub fn from_bytes(bytes: [u8; 10]) -> Panose {
+        Panose {
+            family_kind: match bytes[0] {
+                0 => PanoseFamilyKind::Any,
+                1 => PanoseFamilyKind::NoFit,
+                2 => PanoseFamilyKind::LatinText,
+                3 => PanoseFamilyKind::LatinHandWritten,
+                4 => PanoseFamilyKind::LatinDecorative,
+                5 => PanoseFamilyKind::LatinSymbol,
+                kind => PanoseFamilyKind::Other(kind),
+            },
+            serif_style: bytes[1],
+            weight: bytes[2],
+            proportion: bytes[3],
+            contrast: bytes[4],
+            stroke_variation: bytes[5],
+            arm_style: bytes[6],
+            letterform: bytes[7],
+            midline: bytes[8],
+            x_height: bytes[9],
+        }
+    }
+
+    /// Returns the ten PANOSE digits, in the order they appear in the `OS/2` table.
+    pub fn to_bytes(&self) -> [u8; 10] {
+        let family_kind = match self.family_kind {
+            PanoseFamilyKind::Any => 0,
+            PanoseFamilyKind::NoFit => 1,
+            PanoseFamilyKind::LatinText => 2,
+            PanoseFamilyKind::LatinHandWritten => 3,
+            PanoseFamilyKind::LatinDecorative => 4,
+            PanoseFamilyKind::LatinSymbol => 5,
+            PanoseFamilyKind::Other(kind) => kind,
+        };
+        [
+            family_kind,
+            self.serif_style,
+            self.weight,
+            self.proportion,
+            self.contrast,
+            self.stroke_variation,
+            self.arm_style,
+            self.letterform,
+            self.midline,
+            self.x_height,
+        ]
+    }
+
+    /// Returns true if this is a Latin text font classified as having serifs.
+    #[inline]
+    pub fn is_serif(&self) -> bool {
+        self.family_kind == PanoseFamilyKind::LatinText && (2..=10).contains(&self.serif_style)
+    }
+
+    /// Returns true if this is a Latin text font classified as sans-serif.
+    #[inline]
+    pub fn is_sans_serif(&self) -> bool {
+        self.family_kind == PanoseFamilyKind::LatinText && (11..=15).contains(&self.serif_style)
+    }
+
+    /// Returns true if this is a Latin text font classified as monospaced.
+    #[inline]
+    pub fn is_monospaced(&self) -> bool {
+        self.family_kind == PanoseFamilyKind::LatinText && self.proportion == 9
+    }
+
+    /// Returns how different two classifications are, for ranking candidate substitutes: the sum
+    /// of the squared differences of the digits that both fonts specify. Digits that either font
+    /// leaves as "any" or "no fit" are skipped.
+    ///
+    /// Returns `None` if the family kinds differ, since the other digits aren't comparable then.
+    pub fn distance(&self, other: &Panose) -> Option<u32> {
+        if self.family_kind != other.family_kind {
+            return None;
+        }
+        let (digits, other_digits) = (self.to_bytes(), other.to_bytes());
+        Some(
+            digits[1..]
+                .iter()
+                .zip(&other_digits[1..])
+                .filter(|&(&digit, &other_digit)| digit > 1 && other_digit > 1)
+                .map(|(&digit, &other_digit)| {
+                    let difference = digit.abs_diff(other_digit) as u32;
+                    difference * difference
+                })
+                .sum(),
+        )
+    }
+
+    pub(crate) fn from_os2(os2: &[u8]) -> Option<Panose> {
+        let mut bytes = [0; 10];
+        bytes.copy_from_slice(os2.get(32..42)?);
+        Some(Panose::from_bytes(bytes))
+    }
+}
+
+static UNICODE_RANGE_NAMES: [&str; 123] = [
+    "Basic Latin",
+    "Latin-1 Supplement",
+    "Latin Extended-A",
+    "Latin Extended-B",
+    "IPA Extensions",
+    "Spacing Modifier Letters",
+    "Combining Diacritical Marks",
+    "Greek and Coptic",
+    "Coptic",
+    "Cyrillic",
+    "Armenian",
+    "Hebrew",
+    "Vai",
+    "Arabic",
+    "NKo",
+    "Devanagari",
+    "Bengali",
+    "Gurmukhi",
+    "Gujarati",
+    "Oriya",
+    "Tamil",
+    "Telugu",
+    "Kannada",
+    "Malayalam",
+    "Thai",
+    "Lao",
+    "Georgian",
+    "Balinese",
+    "Hangul Jamo",
+    "Latin Extended Additional",
+    "Greek Extended",
+    "General Punctuation",
+    "Superscripts And Subscripts",
+    "Currency Symbols",
+    "Combining Diacritical Marks For Symbols",
+    "Letterlike Symbols",
+    "Number Forms",
+    "Arrows",
+    "Mathematical Operators",
+    "Miscellaneous Technical",
+    "Control Pictures",
+    "Optical Character Recognition",
+    "Enclosed Alphanumerics",
+    "Box Drawing",
+    "Block Elements",
+    "Geometric Shapes",
+    "Miscellaneous Symbols",
+    "Dingbats",
+    "CJK Symbols And Punctuation",
+    "Hiragana",
+    "Katakana",
+    "Bopomofo",
+    "Hangul Compatibility Jamo",
+    "Phags-pa",
+    "Enclosed CJK Letters And Months",
+    "CJK Compatibility",
+    "Hangul Syllables",
+    "Non-Plane 0",
+    "Phoenician",
+    "CJK Unified Ideographs",
+    "Private Use Area (plane 0)",
+    "CJK Strokes",
+    "Alphabetic Presentation Forms",
+    "Arabic Presentation Forms-A",
+    "Combining Half Marks",
+    "Vertical Forms",
+    "Small Form Variants",
+    "Arabic Presentation Forms-B",
+    "Halfwidth And Fullwidth Forms",
+    "Specials",
+    "Tibetan",
+    "Syriac",
+    "Thaana",
+    "Sinhala",
+    "Myanmar",
+    "Ethiopic",
+    "Cherokee",
+    "Unified Canadian Aboriginal Syllabics",
+    "Ogham",
+    "Runic",
+    "Khmer",
+    "Mongolian",
+    "Braille Patterns",
+    "Yi Syllables",
+    "Tagalog",
+    "Old Italic",
+    "Gothic",
+    "Deseret",
+    "Byzantine Musical Symbols",
+    "Mathematical Alphanumeric Symbols",
+    "Private Use (plane 15)",
+    "Variation Selectors",
+    "Tags",
+    "Limbu",
+    "Tai Le",
+    "New Tai Lue",
+    "Buginese",
+    "Glagolitic",
+    "Tifinagh",
+    "Yijing Hexagram Symbols",
+    "Syloti Nagri",
+    "Linear B Syllabary",
+    "Ancient Greek Numbers",
+    "Ugaritic",
+    "Old Persian",
+    "Shavian",
+    "Osmanya",
+    "Cypriot Syllabary",
+    "Kharoshthi",
+    "Tai Xuan Jing Symbols",
+    "Cuneiform",
+    "Counting Rod Numerals",
+    "Sundanese",
+    "Lepcha",
+    "Ol Chiki",
+    "Saurashtra",
+    "Kayah Li",
+    "Rejang",
+    "Cham",
+    "Ancient Symbols",
+    "Phaistos Disc",
+    "Carian",
+    "Domino Tiles",
+];
+
+// The Windows code page and name of each defined code page bit; the others are reserved.
+static CODE_PAGES: [(u32, Option<u16>, &str); 34] = [
+    (0, Some(1252), "Latin 1"),
+    (1, Some(1250), "Latin 2: Eastern Europe"),
+    (2, Some(1251), "Cyrillic"),
+    (3, Some(1253), "Greek"),
+    (4, Some(1254), "Turkish"),
+    (5, Some(1255), "Hebrew"),
+    (6, Some(1256), "Arabic"),
+    (7, Some(1257), "Windows Baltic"),
+    (8, Some(1258), "Vietnamese"),
+    (16, Some(874), "Thai"),
+    (17, Some(932), "JIS/Japan"),
+    (18, Some(936), "Chinese: Simplified"),
+    (19, Some(949), "Korean Wansung"),
+    (20, Some(950), "Chinese: Traditional"),
+    (21, Some(1361), "Korean Johab"),
+    (29, None, "Macintosh Character Set (US Roman)"),
+    (30, None, "OEM Character Set"),
+    (31, None, "Symbol Character Set"),
+    (48, Some(869), "IBM Greek"),
+    (49, Some(866), "MS-DOS Russian"),
+    (50, Some(865), "MS-DOS Nordic"),
+    (51, Some(864), "Arabic"),
+    (52, Some(863), "MS-DOS Canadian French"),
+    (53, Some(862), "Hebrew"),
+    (54, Some(861), "MS-DOS Icelandic"),
+    (55, Some(860), "MS-DOS Portuguese"),
+    (56, Some(857), "IBM Turkish"),
+    (57, Some(855), "IBM Cyrillic"),
+    (58, Some(852), "Latin 2"),
+    (59, Some(775), "MS-DOS Baltic"),
+    (60, Some(737), "Greek; former 437 G"),
+    (61, Some(708), "Arabic; ASMO 708"),
+    (62, Some(850), "WE/Latin 1"),
+    (63, Some(437), "US"),
+];
+
+#[cfg(test)]
+mod test {
+    use super::{
+        CodePageRanges, EmbeddingLevel, EmbeddingPermissions, Panose, PanoseFamilyKind,
+        UnicodeRanges,
+    };
+    use crate::font::Font;
+    use crate::test_fixtures::{DEJAVU_SANS_MONO, EB_GARAMOND, INCONSOLATA};
+
+    #[test]
+    fn test_embedding_permissions() {
+        let font = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        let permissions = font.embedding_permissions();
+        assert_eq!(permissions.level, EmbeddingLevel::Installable);
+        assert!(permissions.allows_subsetting());
+
+        let restricted = EmbeddingPermissions::from_fs_type(0x0002);
+        assert_eq!(restricted.level, EmbeddingLevel::Restricted);
+        assert!(!restricted.allows_embedding());
+
+        let print = EmbeddingPermissions::from_fs_type(0x0104);
+        assert_eq!(print.level, EmbeddingLevel::PreviewAndPrint);
+        assert!(print.allows_embedding() && !print.allows_subsetting());
+
+        // Legacy fonts may set several levels; the least restrictive one wins.
+        assert_eq!(
+            EmbeddingPermissions::from_fs_type(0x000e).level,
+            EmbeddingLevel::Editable
+        );
+        assert!(!EmbeddingPermissions::from_fs_type(0x0200).allows_embedding());
+    }
+    #[test]
+    fn test_unicode_and_code_page_ranges() {
+        let font = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        let unicode_ranges = font.unicode_ranges();
+        assert_eq!(unicode_ranges.bits().collect::<Vec<_>>(), [0, 1, 2, 29, 32]);
+        assert_eq!(
+            unicode_ranges.names().last(),
+            Some("Superscripts And Subscripts")
+        );
+        assert_eq!(UnicodeRanges::name(122), Some("Domino Tiles"));
+        assert_eq!(UnicodeRanges::name(123), None);
+
+        let code_pages = font.codepage_ranges();
+        assert_eq!(code_pages.bits().collect::<Vec<_>>(), [0, 1, 4, 7, 8, 29]);
+        assert_eq!(
+            code_pages.code_pages().collect::<Vec<_>>(),
+            [1252, 1250, 1254, 1257, 1258]
+        );
+        assert_eq!(
+            CodePageRanges::name(29),
+            Some("Macintosh Character Set (US Roman)")
+        );
+        assert_eq!(CodePageRanges::name(9), None);
+    }
+    #[test]
+    fn test_panose() {
+        let garamond = Font::from_bytes_borrowed(EB_GARAMOND, 0)
+            .unwrap()
+            .panose()
+            .unwrap();
+        assert_eq!(garamond.family_kind, PanoseFamilyKind::LatinText);
+        assert_eq!(garamond.to_bytes(), [2, 2, 5, 2, 6, 2, 6, 2, 4, 3]);
+        assert!(garamond.is_serif() && !garamond.is_monospaced());
+
+        let dejavu = Font::from_bytes_borrowed(DEJAVU_SANS_MONO, 0).unwrap();
+        let dejavu = dejavu.panose().unwrap();
+        assert!(dejavu.is_sans_serif() && dejavu.is_monospaced());
+        assert_eq!(garamond.distance(&garamond), Some(0));
+        assert!(garamond.distance(&dejavu).unwrap() > 0);
+
+        // Inconsolata isn't classified, so its digits can't be compared with others.
+        let inconsolata = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        let inconsolata = inconsolata.panose().unwrap();
+        assert_eq!(inconsolata.family_kind, PanoseFamilyKind::Any);
+        assert_eq!(inconsolata.distance(&garamond), None);
+        assert_eq!(
+            Panose::from_bytes([9; 10]).family_kind,
+            PanoseFamilyKind::Other(9)
+        );
+    }
+}
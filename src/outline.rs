@@ -11,6 +11,8 @@
 //! Bézier paths.
 
 use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::Vector2F;
 use std::mem;
 
@@ -93,6 +95,33 @@ impl Outline {
             contour.copy_to(sink);
         }
     }
+
+    /// Widens the strokes of this outline by `amount`, as for synthetic bold, by moving every
+    /// edge outward by half of it. Negative amounts make the strokes thinner.
+    ///
+    /// Each point moves along the bisector of its two edges, far enough that both edges move by
+    /// the same distance, as in FreeType's `FT_Outline_Embolden()`. Control points move the same
+    /// way, which is exact for straight edges and close for curves.
+    pub fn embolden(&mut self, amount: f32) {
+        if amount == 0.0 {
+            return;
+        }
+        // Outer contours run clockwise in TrueType fonts and counterclockwise in CFF fonts.
+        let area: f32 = self.contours.iter().map(Contour::signed_area).sum();
+        let distance = if area < 0.0 { -amount } else { amount } * 0.5;
+        for contour in &mut self.contours {
+            contour.offset(distance);
+        }
+    }
+
+    /// Applies an affine transform to every point of this outline.
+    pub fn transform(&mut self, transform: &Transform2F) {
+        for contour in &mut self.contours {
+            for position in &mut contour.positions {
+                *position = *transform * *position;
+            }
+        }
+    }
 }
 
 impl Default for Contour {
@@ -149,6 +178,44 @@ impl Contour {
 
         sink.close();
     }
+
+    // Twice the area enclosed by the contour's points, positive if they run counterclockwise
+    // with y pointing up.
+    fn signed_area(&self) -> f32 {
+        let count = self.positions.len();
+        (0..count)
+            .map(|index| self.positions[index].det(self.positions[(index + 1) % count]))
+            .sum()
+    }
+
+    // Moves every point by `distance` to the right of the direction of travel, which is outward
+    // for counterclockwise contours.
+    fn offset(&mut self, distance: f32) {
+        let original = self.positions.clone();
+        let count = original.len();
+        for (index, &position) in original.iter().enumerate() {
+            // Skip over coincident points, such as a closing point that repeats the first one.
+            let previous = (1..count)
+                .map(|offset| original[(index + count - offset) % count])
+                .find(|&point| point != position);
+            let next = (1..count)
+                .map(|offset| original[(index + offset) % count])
+                .find(|&point| point != position);
+            let (previous, next) = match (previous, next) {
+                (Some(previous), Some(next)) => (previous, next),
+                _ => continue,
+            };
+            let incoming = (position - previous).normalize();
+            let outgoing = (next - position).normalize();
+            let normal = Vector2F::new(incoming.y() + outgoing.y(), -incoming.x() - outgoing.x());
+            // At sharp cusps the bisector is nearly parallel to the edges, and moving the point
+            // far enough would create spikes; leave those points alone.
+            let cos_plus_one = 1.0 + incoming.dot(outgoing);
+            if cos_plus_one > 1.0 / 16.0 {
+                self.positions[index] = position + normal * (distance / cos_plus_one);
+            }
+        }
+    }
 }
 
 impl Default for OutlineBuilder {
@@ -178,7 +245,7 @@ impl OutlineBuilder {
     pub fn take_outline(&mut self) -> Outline {
         assert!(self.current_contour.positions.is_empty());
         self.current_contour = Contour::new();
-        mem::replace(&mut self.outline, Outline::new())
+        mem::take(&mut self.outline)
     }
 }
 
@@ -212,6 +279,344 @@ impl OutlineSink for OutlineBuilder {
     fn close(&mut self) {
         self.outline
             .contours
-            .push(mem::replace(&mut self.current_contour, Contour::new()));
+            .push(mem::take(&mut self.current_contour));
+    }
+}
+
+/// A command in a `GlyphPath`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PathCommand {
+    /// Starts a new subpath at a point.
+    MoveTo(Vector2F),
+    /// Draws a line to a point.
+    LineTo(Vector2F),
+    /// Draws a quadratic Bézier curve through a control point to a point.
+    QuadTo(Vector2F, Vector2F),
+    /// Draws a cubic Bézier curve through two control points to a point.
+    CubicTo(Vector2F, Vector2F, Vector2F),
+    /// Closes the subpath, returning to its first point.
+    Close,
+}
+
+/// The outline of a glyph as an owned list of path commands, for use with path libraries that
+/// don't implement `OutlineSink`.
+///
+/// Returned by `Loader::glyph_path()`. The commands are in font units, with y pointing up.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct GlyphPath {
+    /// The path commands, in order.
+    pub commands: Vec<PathCommand>,
+    /// The smallest rectangle containing every point of the path, including control points.
+    ///
+    /// This contains the glyph, but can be slightly larger than its exact bounds where curves
+    /// don't reach their control points. It is empty at the origin if the path is empty.
+    pub bounds: RectF,
+}
+
+impl GlyphPath {
+    /// Creates a new empty path.
+    #[inline]
+    pub fn new() -> GlyphPath {
+        GlyphPath::default()
+    }
+
+    /// Sends this path to an `OutlineSink`.
+    pub fn copy_to<S>(&self, sink: &mut S)
+    where
+        S: OutlineSink,
+    {
+        for command in &self.commands {
+            match *command {
+                PathCommand::MoveTo(to) => sink.move_to(to),
+                PathCommand::LineTo(to) => sink.line_to(to),
+                PathCommand::QuadTo(ctrl, to) => sink.quadratic_curve_to(ctrl, to),
+                PathCommand::CubicTo(ctrl0, ctrl1, to) => {
+                    sink.cubic_curve_to(LineSegment2F::new(ctrl0, ctrl1), to)
+                }
+                PathCommand::Close => sink.close(),
+            }
+        }
+    }
+
+    #[inline]
+    fn include(&mut self, point: Vector2F) {
+        self.bounds = self.bounds.union_point(point);
+    }
+}
+
+impl OutlineSink for GlyphPath {
+    #[inline]
+    fn move_to(&mut self, to: Vector2F) {
+        if self.commands.is_empty() {
+            self.bounds = RectF::new(to, Vector2F::zero());
+        } else {
+            self.include(to);
+        }
+        self.commands.push(PathCommand::MoveTo(to));
+    }
+
+    #[inline]
+    fn line_to(&mut self, to: Vector2F) {
+        self.include(to);
+        self.commands.push(PathCommand::LineTo(to));
+    }
+
+    #[inline]
+    fn quadratic_curve_to(&mut self, ctrl: Vector2F, to: Vector2F) {
+        self.include(ctrl);
+        self.include(to);
+        self.commands.push(PathCommand::QuadTo(ctrl, to));
+    }
+
+    #[inline]
+    fn cubic_curve_to(&mut self, ctrl: LineSegment2F, to: Vector2F) {
+        self.include(ctrl.from());
+        self.include(ctrl.to());
+        self.include(to);
+        self.commands
+            .push(PathCommand::CubicTo(ctrl.from(), ctrl.to(), to));
+    }
+
+    #[inline]
+    fn close(&mut self) {
+        self.commands.push(PathCommand::Close);
+    }
+}
+
+/// An `OutlineSink` that computes the exact bounds of a path: unlike `GlyphPath::bounds`, curves
+/// only count as far as they reach, not as far as their control points.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ExactBoundsSink {
+    /// The bounds, or `None` if the path is empty.
+    pub(crate) bounds: Option<RectF>,
+    current: Vector2F,
+}
+
+impl ExactBoundsSink {
+    #[inline]
+    pub(crate) fn new() -> ExactBoundsSink {
+        ExactBoundsSink::default()
+    }
+
+    #[inline]
+    fn include(&mut self, point: Vector2F) {
+        self.bounds = Some(match self.bounds {
+            None => RectF::new(point, Vector2F::zero()),
+            Some(bounds) => bounds.union_point(point),
+        });
+    }
+}
+
+impl OutlineSink for ExactBoundsSink {
+    #[inline]
+    fn move_to(&mut self, to: Vector2F) {
+        self.include(to);
+        self.current = to;
+    }
+
+    #[inline]
+    fn line_to(&mut self, to: Vector2F) {
+        self.include(to);
+        self.current = to;
+    }
+
+    fn quadratic_curve_to(&mut self, ctrl: Vector2F, to: Vector2F) {
+        let from = self.current;
+        // Each coordinate has an extremum where its derivative, which is linear in t, is zero.
+        let denominator = from - ctrl * 2.0 + to;
+        for t in [
+            (from.x() - ctrl.x()) / denominator.x(),
+            (from.y() - ctrl.y()) / denominator.y(),
+        ] {
+            if t > 0.0 && t < 1.0 {
+                let u = 1.0 - t;
+                self.include(from * (u * u) + ctrl * (2.0 * u * t) + to * (t * t));
+            }
+        }
+        self.include(to);
+        self.current = to;
+    }
+
+    fn cubic_curve_to(&mut self, ctrl: LineSegment2F, to: Vector2F) {
+        let (from, ctrl0, ctrl1) = (self.current, ctrl.from(), ctrl.to());
+        // The derivative of each coordinate is a quadratic a·t² + b·t + c.
+        let a = (ctrl0 - ctrl1) * 3.0 + to - from;
+        let b = (from - ctrl0 * 2.0 + ctrl1) * 2.0;
+        let c = ctrl0 - from;
+        let mut roots = vec![];
+        for (a, b, c) in [(a.x(), b.x(), c.x()), (a.y(), b.y(), c.y())] {
+            if a.abs() < f32::EPSILON {
+                if b.abs() >= f32::EPSILON {
+                    roots.push(-c / b);
+                }
+                continue;
+            }
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant >= 0.0 {
+                let sqrt = discriminant.sqrt();
+                roots.push((-b + sqrt) / (2.0 * a));
+                roots.push((-b - sqrt) / (2.0 * a));
+            }
+        }
+        for t in roots {
+            if t > 0.0 && t < 1.0 {
+                let u = 1.0 - t;
+                self.include(
+                    from * (u * u * u)
+                        + ctrl0 * (3.0 * u * u * t)
+                        + ctrl1 * (3.0 * u * t * t)
+                        + to * (t * t * t),
+                );
+            }
+        }
+        self.include(to);
+        self.current = to;
+    }
+
+    #[inline]
+    fn close(&mut self) {}
+}
+
+/// An `OutlineSink` that applies an affine transform to every point before passing it on.
+///
+/// This is useful for synthesizing styles, such as condensing a font by scaling it horizontally.
+#[derive(Debug)]
+pub struct TransformSink<'a, S> {
+    sink: &'a mut S,
+    transform: Transform2F,
+}
+
+impl<'a, S> TransformSink<'a, S>
+where
+    S: OutlineSink,
+{
+    /// Creates a sink that transforms points by `transform` and forwards them to `sink`.
+    #[inline]
+    pub fn new(sink: &'a mut S, transform: Transform2F) -> TransformSink<'a, S> {
+        TransformSink { sink, transform }
+    }
+}
+
+// Lets loaders send outlines to a `&mut dyn OutlineSink`.
+impl<S> OutlineSink for &mut S
+where
+    S: OutlineSink + ?Sized,
+{
+    #[inline]
+    fn move_to(&mut self, to: Vector2F) {
+        (**self).move_to(to)
+    }
+
+    #[inline]
+    fn line_to(&mut self, to: Vector2F) {
+        (**self).line_to(to)
+    }
+
+    #[inline]
+    fn quadratic_curve_to(&mut self, ctrl: Vector2F, to: Vector2F) {
+        (**self).quadratic_curve_to(ctrl, to)
+    }
+
+    #[inline]
+    fn cubic_curve_to(&mut self, ctrl: LineSegment2F, to: Vector2F) {
+        (**self).cubic_curve_to(ctrl, to)
+    }
+
+    #[inline]
+    fn close(&mut self) {
+        (**self).close()
+    }
+}
+
+impl<'a, S> OutlineSink for TransformSink<'a, S>
+where
+    S: OutlineSink,
+{
+    #[inline]
+    fn move_to(&mut self, to: Vector2F) {
+        self.sink.move_to(self.transform * to)
+    }
+
+    #[inline]
+    fn line_to(&mut self, to: Vector2F) {
+        self.sink.line_to(self.transform * to)
+    }
+
+    #[inline]
+    fn quadratic_curve_to(&mut self, ctrl: Vector2F, to: Vector2F) {
+        self.sink
+            .quadratic_curve_to(self.transform * ctrl, self.transform * to)
+    }
+
+    #[inline]
+    fn cubic_curve_to(&mut self, ctrl: LineSegment2F, to: Vector2F) {
+        self.sink
+            .cubic_curve_to(self.transform * ctrl, self.transform * to)
+    }
+
+    #[inline]
+    fn close(&mut self) {
+        self.sink.close()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ExactBoundsSink, OutlineBuilder, OutlineSink, PathCommand};
+    use crate::error::GlyphLoadingError;
+    use crate::font::Font;
+    use crate::hinting::HintingOptions;
+    use crate::loader::Loader;
+    use crate::test_fixtures::INCONSOLATA;
+
+    #[test]
+    fn test_glyph_path() {
+        let font = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        let glyph_id = font.glyph_for_char('O').unwrap();
+        let path = font.glyph_path(glyph_id).unwrap();
+        assert!(matches!(path.commands[0], PathCommand::MoveTo(_)));
+        assert_eq!(path.commands.last(), Some(&PathCommand::Close));
+        assert!(path
+            .commands
+            .iter()
+            .any(|command| matches!(command, PathCommand::QuadTo(..))));
+
+        let bounds = font.typographic_bounds(glyph_id).unwrap();
+        assert!(path.bounds.contains_rect(bounds));
+
+        // Replaying the path reproduces the outline.
+        let mut from_path = OutlineBuilder::new();
+        path.copy_to(&mut from_path);
+        let mut from_font = OutlineBuilder::new();
+        font.outline(glyph_id, HintingOptions::None, &mut from_font)
+            .unwrap();
+        assert_eq!(from_path.into_outline(), from_font.into_outline());
+
+        let space = font.glyph_for_char(' ').unwrap();
+        assert!(font.glyph_path(space).unwrap().commands.is_empty());
+        assert!(matches!(
+            font.glyph_path(font.glyph_count()),
+            Err(GlyphLoadingError::NoSuchGlyph)
+        ));
+    }
+
+    #[test]
+    fn test_exact_bounds() {
+        use pathfinder_geometry::line_segment::LineSegment2F;
+        use pathfinder_geometry::vector::Vector2F;
+
+        let mut sink = ExactBoundsSink::new();
+        assert_eq!(sink.bounds, None);
+        sink.move_to(Vector2F::new(0.0, 0.0));
+        sink.quadratic_curve_to(Vector2F::new(50.0, 100.0), Vector2F::new(100.0, 0.0));
+        assert_eq!(sink.bounds.unwrap().max_y(), 50.0);
+        sink.cubic_curve_to(
+            LineSegment2F::new(Vector2F::new(100.0, -100.0), Vector2F::new(0.0, -100.0)),
+            Vector2F::new(0.0, 0.0),
+        );
+        sink.close();
+        let bounds = sink.bounds.unwrap();
+        assert_eq!(bounds.min_y(), -75.0);
+        assert_eq!((bounds.min_x(), bounds.max_x()), (0.0, 100.0));
     }
 }
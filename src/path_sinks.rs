@@ -0,0 +1,229 @@
+// font-kit/src/path_sinks.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `OutlineSink`s that build the path types of other 2D graphics crates.
+//!
+//! `LyonPathSink` builds a `lyon_path::Path`, for tessellation with lyon, and is available with the
+//! `lyon` feature. `TinySkiaPathSink` builds a `tiny_skia::Path`, for drawing with tiny-skia, and
+//! is available with the `tiny-skia` feature. For SVG path data, see `svg::SvgPathSink`.
+//!
+//! Points are passed through unchanged, in font units with y pointing up unless the sink is
+//! wrapped in a `TransformSink`.
+
+use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::vector::Vector2F;
+#[cfg(feature = "lyon")]
+use std::fmt::{self, Debug, Formatter};
+
+use crate::outline::OutlineSink;
+
+/// An `OutlineSink` that builds a `lyon_path::Path`.
+#[cfg(feature = "lyon")]
+pub struct LyonPathSink {
+    builder: lyon_path::path::Builder,
+    // Whether a subpath has been begun and not yet ended, since lyon requires every subpath to be
+    // ended before the next one begins.
+    in_subpath: bool,
+}
+
+#[cfg(feature = "lyon")]
+impl Default for LyonPathSink {
+    fn default() -> LyonPathSink {
+        LyonPathSink {
+            builder: lyon_path::Path::builder(),
+            in_subpath: false,
+        }
+    }
+}
+
+#[cfg(feature = "lyon")]
+impl LyonPathSink {
+    /// Creates a sink with an empty path.
+    #[inline]
+    pub fn new() -> LyonPathSink {
+        LyonPathSink::default()
+    }
+
+    /// Consumes this sink and returns the path, ending the last subpath without closing it if it
+    /// is still open.
+    pub fn into_path(mut self) -> lyon_path::Path {
+        if self.in_subpath {
+            self.builder.end(false);
+        }
+        self.builder.build()
+    }
+}
+
+#[cfg(feature = "lyon")]
+impl Debug for LyonPathSink {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("LyonPathSink")
+            .field("in_subpath", &self.in_subpath)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "lyon")]
+fn lyon_point(point: Vector2F) -> lyon_path::math::Point {
+    lyon_path::math::point(point.x(), point.y())
+}
+
+#[cfg(feature = "lyon")]
+impl OutlineSink for LyonPathSink {
+    fn move_to(&mut self, to: Vector2F) {
+        if self.in_subpath {
+            self.builder.end(false);
+        }
+        self.builder.begin(lyon_point(to));
+        self.in_subpath = true;
+    }
+
+    fn line_to(&mut self, to: Vector2F) {
+        self.builder.line_to(lyon_point(to));
+    }
+
+    fn quadratic_curve_to(&mut self, ctrl: Vector2F, to: Vector2F) {
+        self.builder
+            .quadratic_bezier_to(lyon_point(ctrl), lyon_point(to));
+    }
+
+    fn cubic_curve_to(&mut self, ctrl: LineSegment2F, to: Vector2F) {
+        self.builder.cubic_bezier_to(
+            lyon_point(ctrl.from()),
+            lyon_point(ctrl.to()),
+            lyon_point(to),
+        );
+    }
+
+    fn close(&mut self) {
+        if self.in_subpath {
+            self.builder.end(true);
+            self.in_subpath = false;
+        }
+    }
+}
+
+/// An `OutlineSink` that builds a `tiny_skia::Path`.
+#[cfg(feature = "tiny-skia")]
+#[derive(Clone, Debug, Default)]
+pub struct TinySkiaPathSink {
+    builder: tiny_skia::PathBuilder,
+}
+
+#[cfg(feature = "tiny-skia")]
+impl TinySkiaPathSink {
+    /// Creates a sink with an empty path.
+    #[inline]
+    pub fn new() -> TinySkiaPathSink {
+        TinySkiaPathSink::default()
+    }
+
+    /// Consumes this sink and returns the path, or `None` if the outline was empty, such as for a
+    /// space, since tiny-skia has no empty paths.
+    #[inline]
+    pub fn into_path(self) -> Option<tiny_skia::Path> {
+        self.builder.finish()
+    }
+}
+
+#[cfg(feature = "tiny-skia")]
+impl OutlineSink for TinySkiaPathSink {
+    fn move_to(&mut self, to: Vector2F) {
+        self.builder.move_to(to.x(), to.y());
+    }
+
+    fn line_to(&mut self, to: Vector2F) {
+        self.builder.line_to(to.x(), to.y());
+    }
+
+    fn quadratic_curve_to(&mut self, ctrl: Vector2F, to: Vector2F) {
+        self.builder.quad_to(ctrl.x(), ctrl.y(), to.x(), to.y());
+    }
+
+    fn cubic_curve_to(&mut self, ctrl: LineSegment2F, to: Vector2F) {
+        self.builder.cubic_to(
+            ctrl.from_x(),
+            ctrl.from_y(),
+            ctrl.to_x(),
+            ctrl.to_y(),
+            to.x(),
+            to.y(),
+        );
+    }
+
+    fn close(&mut self) {
+        self.builder.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::font::Font;
+    use crate::hinting::HintingOptions;
+    use crate::loader::Loader;
+    use crate::outline::OutlineBuilder;
+    use crate::test_fixtures::EB_GARAMOND;
+
+    // Returns the glyph for 'o' and the number of contours in its outline, which is two.
+    fn outline_o(font: &Font) -> (u32, usize) {
+        let glyph_id = font.glyph_for_char('o').unwrap();
+        let mut builder = OutlineBuilder::new();
+        font.outline(glyph_id, HintingOptions::None, &mut builder)
+            .unwrap();
+        (glyph_id, builder.into_outline().contours.len())
+    }
+
+    #[cfg(feature = "lyon")]
+    #[test]
+    fn test_lyon_path_sink() {
+        use super::LyonPathSink;
+        use lyon_path::Event;
+
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let (glyph_id, contour_count) = outline_o(&font);
+        let mut sink = LyonPathSink::new();
+        font.outline(glyph_id, HintingOptions::None, &mut sink)
+            .unwrap();
+        let path = sink.into_path();
+        let ends: Vec<bool> = path
+            .iter()
+            .filter_map(|event| match event {
+                Event::End { close, .. } => Some(close),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ends, vec![true; contour_count]);
+    }
+
+    #[cfg(feature = "tiny-skia")]
+    #[test]
+    fn test_tiny_skia_path_sink() {
+        use super::TinySkiaPathSink;
+
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let (glyph_id, _) = outline_o(&font);
+        let mut sink = TinySkiaPathSink::new();
+        font.outline(glyph_id, HintingOptions::None, &mut sink)
+            .unwrap();
+        let bounds = sink.into_path().unwrap().bounds();
+        let expected = font.typographic_bounds(glyph_id).unwrap();
+        assert!((bounds.left() - expected.min_x()).abs() < 1.0);
+        assert!((bounds.top() - expected.min_y()).abs() < 1.0);
+        assert!((bounds.right() - expected.max_x()).abs() < 1.0);
+        assert!((bounds.bottom() - expected.max_y()).abs() < 1.0);
+
+        let space = font.glyph_for_char(' ').unwrap();
+        let mut sink = TinySkiaPathSink::new();
+        font.outline(space, HintingOptions::None, &mut sink)
+            .unwrap();
+        assert!(sink.into_path().is_none());
+    }
+}
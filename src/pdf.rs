@@ -0,0 +1,263 @@
+// font-kit/src/pdf.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The values that PDF writers need to embed a font.
+//!
+//! A PDF embeds a font as a font program (see `Font::subset()`), a font descriptor, glyph widths,
+//! and a ToUnicode CMap that lets readers extract the text again. `Font::pdf_font_descriptor()`,
+//! `Font::pdf_widths()`, and `Font::pdf_to_unicode_cmap()` provide the last three. All lengths
+//! are in PDF glyph space, where the em is 1000 units.
+//!
+//! Fonts are assumed to be embedded as CIDFonts with the `Identity-H` encoding, so the character
+//! codes in the PDF content are glyph IDs, written as two bytes.
+
+use pathfinder_geometry::rect::RectF;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use ttf_parser::{Face, Tag};
+
+use crate::font::Font;
+use crate::loader::Loader;
+use crate::properties::Style;
+
+// Mappings per `beginbfchar` block; the PDF specification allows at most 100.
+const MAX_BFCHAR_ENTRIES: usize = 100;
+
+bitflags! {
+    /// The `Flags` entry of a PDF font descriptor.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PdfFontFlags: u32 {
+        /// All glyphs have the same width.
+        const FIXED_PITCH = 1 << 0;
+        /// Glyphs have serifs.
+        const SERIF = 1 << 1;
+        /// The font has glyphs outside the standard Latin character set.
+        const SYMBOLIC = 1 << 2;
+        /// Glyphs resemble cursive handwriting.
+        const SCRIPT = 1 << 3;
+        /// The font uses only the standard Latin character set.
+        const NONSYMBOLIC = 1 << 5;
+        /// Glyphs have dominant vertical strokes that are slanted.
+        const ITALIC = 1 << 6;
+    }
+}
+
+/// The entries of a PDF font descriptor, from `Font::pdf_font_descriptor()`.
+///
+/// Lengths are in PDF glyph space, where the em is 1000 units.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PdfFontDescriptor {
+    /// The PostScript name of the font, for `FontName`. Prefix this with a subset tag such as
+    /// `ABCDEF+` when embedding a subset.
+    pub font_name: String,
+    /// The `Flags` entry.
+    pub flags: PdfFontFlags,
+    /// The `FontBBox` entry: the union of all glyph bounding boxes.
+    pub font_bbox: RectF,
+    /// The `ItalicAngle` entry, in degrees counterclockwise from vertical. Negative for fonts
+    /// that slant to the right.
+    pub italic_angle: f32,
+    /// The `Ascent` entry.
+    pub ascent: f32,
+    /// The `Descent` entry, negative below the baseline.
+    pub descent: f32,
+    /// The `CapHeight` entry.
+    pub cap_height: f32,
+    /// The `XHeight` entry.
+    pub x_height: f32,
+    /// The `StemV` entry: the thickness of vertical stems.
+    ///
+    /// Fonts don't record this outside of the hinting data, so it is estimated from the weight,
+    /// as other PDF writers do.
+    pub stem_v: f32,
+}
+
+/// Returns the font descriptor of a font.
+pub(crate) fn font_descriptor(font: &Font, face: &Face) -> PdfFontDescriptor {
+    let metrics = font.metrics();
+    let scale = 1000.0 / metrics.units_per_em as f32;
+    let properties = font.properties();
+
+    let mut flags = PdfFontFlags::empty();
+    if font.is_monospace() {
+        flags |= PdfFontFlags::FIXED_PITCH;
+    }
+    // The high byte of the `sFamilyClass` field in `OS/2` is the IBM font class.
+    let family_class = face
+        .raw_face()
+        .table(Tag::from_bytes(b"OS/2"))
+        .and_then(|os2| os2.get(30))
+        .copied()
+        .unwrap_or(0);
+    match family_class {
+        1..=5 | 7 => flags |= PdfFontFlags::SERIF,
+        10 => flags |= PdfFontFlags::SCRIPT,
+        _ => {}
+    }
+    let has_unicode_cmap = face.tables().cmap.is_some_and(|cmap| {
+        cmap.subtables
+            .into_iter()
+            .any(|subtable| subtable.is_unicode())
+    });
+    flags |= if has_unicode_cmap && font.glyph_for_char('A').is_some() {
+        PdfFontFlags::NONSYMBOLIC
+    } else {
+        PdfFontFlags::SYMBOLIC
+    };
+    if properties.style != Style::Normal {
+        flags |= PdfFontFlags::ITALIC;
+    }
+
+    // An estimate that gives about 70 for regular weights and about 140 for bold ones.
+    let weight = (properties.weight.0 - 50.0) / 900.0;
+    let stem_v = 10.0 + 220.0 * weight * weight;
+
+    PdfFontDescriptor {
+        font_name: font.postscript_name().unwrap_or_default(),
+        flags,
+        font_bbox: metrics.bounding_box * scale,
+        italic_angle: font.italic_angle(),
+        ascent: metrics.ascent * scale,
+        descent: metrics.descent * scale,
+        cap_height: metrics.cap_height * scale,
+        x_height: metrics.x_height * scale,
+        stem_v: stem_v.round(),
+    }
+}
+
+/// Returns the widths of the glyphs from `first` to `last`, inclusive, in PDF glyph space.
+/// Glyphs that don't exist have zero width.
+pub(crate) fn widths(font: &Font, first: u32, last: u32) -> Vec<f32> {
+    let scale = 1000.0 / font.metrics().units_per_em as f32;
+    (first..=last)
+        .map(|glyph_id| {
+            font.advance(glyph_id)
+                .map_or(0.0, |advance| advance.x() * scale)
+        })
+        .collect()
+}
+
+/// Returns the text of each of the given glyphs, from the font's character map, as a map from
+/// glyph ID to text. Glyphs that several characters map to get the lowest of them.
+pub(crate) fn glyph_text(face: &Face, glyph_ids: &[u32]) -> BTreeMap<u32, String> {
+    let mut characters = BTreeMap::new();
+    if let Some(cmap) = face.tables().cmap {
+        for subtable in cmap.subtables {
+            if !subtable.is_unicode() {
+                continue;
+            }
+            subtable.codepoints(|codepoint| {
+                let glyph_id = match subtable.glyph_index(codepoint) {
+                    Some(glyph_id) if glyph_id.0 != 0 => glyph_id.0 as u32,
+                    _ => return,
+                };
+                if let Some(character) = char::from_u32(codepoint) {
+                    if glyph_ids.contains(&glyph_id) {
+                        let lowest = characters.entry(glyph_id).or_insert(character);
+                        *lowest = character.min(*lowest);
+                    }
+                }
+            });
+        }
+    }
+    characters
+        .into_iter()
+        .map(|(glyph_id, character)| (glyph_id, character.to_string()))
+        .collect()
+}
+
+/// Returns a ToUnicode CMap mapping two-byte character codes, such as glyph IDs in an
+/// `Identity-H` font, to the text that they represent.
+///
+/// Codes above 0xffff can't be written and are skipped.
+pub fn to_unicode_cmap<I>(mappings: I) -> String
+where
+    I: IntoIterator<Item = (u32, String)>,
+{
+    let mappings: Vec<(u32, String)> = mappings
+        .into_iter()
+        .filter(|(code, _)| *code <= 0xffff)
+        .collect();
+
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n\
+         12 dict begin\n\
+         begincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n\
+         /CMapType 2 def\n\
+         1 begincodespacerange\n\
+         <0000> <FFFF>\n\
+         endcodespacerange\n",
+    );
+    for chunk in mappings.chunks(MAX_BFCHAR_ENTRIES) {
+        writeln!(cmap, "{} beginbfchar", chunk.len()).unwrap();
+        for (code, text) in chunk {
+            write!(cmap, "<{:04X}> <", code).unwrap();
+            for unit in text.encode_utf16() {
+                write!(cmap, "{:04X}", unit).unwrap();
+            }
+            cmap.push_str(">\n");
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str(
+        "endcmap\n\
+         CMapName currentdict /CMap defineresource pop\n\
+         end\n\
+         end\n",
+    );
+    cmap
+}
+
+#[cfg(test)]
+mod test {
+    use super::{to_unicode_cmap, PdfFontFlags};
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use crate::test_fixtures::{EB_GARAMOND, INCONSOLATA};
+
+    #[test]
+    fn test_pdf_font_descriptor() {
+        let font = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        let descriptor = font.pdf_font_descriptor();
+        assert_eq!(descriptor.font_name, "Inconsolata-Regular");
+        assert!(descriptor
+            .flags
+            .contains(PdfFontFlags::FIXED_PITCH | PdfFontFlags::NONSYMBOLIC));
+        assert!(!descriptor.flags.contains(PdfFontFlags::ITALIC));
+        assert!(descriptor.ascent > 0.0 && descriptor.descent < 0.0);
+        assert!(descriptor.stem_v > 0.0);
+
+        let garamond = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        assert!(!garamond
+            .pdf_font_descriptor()
+            .flags
+            .contains(PdfFontFlags::FIXED_PITCH));
+    }
+
+    #[test]
+    fn test_pdf_widths_and_to_unicode() {
+        let font = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        let a = font.glyph_for_char('A').unwrap();
+        assert_eq!(font.pdf_widths(a, a + 2), [500.0; 3]);
+        assert_eq!(
+            font.pdf_widths(font.glyph_count(), font.glyph_count()),
+            [0.0]
+        );
+
+        let cmap = font.pdf_to_unicode_cmap(&[a]);
+        assert!(cmap.contains(&format!("1 beginbfchar\n<{:04X}> <0041>\nendbfchar", a)));
+
+        let cmap = to_unicode_cmap((0..150).map(|code| (code, "\u{1f600}".to_owned())));
+        assert!(cmap.contains("100 beginbfchar\n<0000> <D83DDE00>\n"));
+        assert!(cmap.contains("50 beginbfchar\n"));
+    }
+}
@@ -14,6 +14,9 @@
 //! https://drafts.csswg.org/css-fonts-3/
 
 use std::fmt::{self, Debug, Display, Formatter};
+use std::str::FromStr;
+
+use crate::error::CssParseError;
 
 /// Properties that specify which font in a family to use: e.g. style, weight, and stretchiness.
 ///
@@ -22,6 +25,7 @@ use std::fmt::{self, Debug, Display, Formatter};
 ///     # use font_kit::properties::{Properties, Style};
 ///     println!("{:?}", Properties::new().style(Style::Italic));
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Properties {
     /// The font style, as defined in CSS.
     pub style: Style,
@@ -59,10 +63,74 @@ impl Properties {
         self.stretch = stretch;
         self
     }
+
+    /// Parses a property set from a CSS-style string such as `"italic 600 condensed"`.
+    ///
+    /// The string consists of up to one style, one weight, and one stretch value, separated by
+    /// whitespace, in any order; omitted values take their defaults. Styles are `normal`,
+    /// `italic`, or `oblique`. Weights are numbers from 1 to 1000 or keywords (see
+    /// `Weight::from_css_keyword()`). Stretches are keywords such as `condensed` or percentages
+    /// such as `75%`. The keyword `normal` may appear several times and leaves a value at its
+    /// default.
+    pub fn from_css(css: &str) -> Result<Properties, CssParseError> {
+        let mut properties = Properties::new();
+        let (mut style, mut weight, mut stretch) = (None, None, None);
+        for token in css.split_whitespace() {
+            let token = token.to_ascii_lowercase();
+            if token == "normal" {
+                continue;
+            }
+
+            if let Some(value) = Style::from_css_keyword(&token) {
+                set_once(&mut style, value, &token)?;
+            } else if let Some(value) = Weight::from_css(&token) {
+                set_once(&mut weight, value, &token)?;
+            } else if let Some(value) = Stretch::from_css(&token) {
+                set_once(&mut stretch, value, &token)?;
+            } else {
+                return Err(CssParseError::UnknownValue(token));
+            }
+        }
+
+        properties.style = style.unwrap_or_default();
+        properties.weight = weight.unwrap_or_default();
+        properties.stretch = stretch.unwrap_or_default();
+        Ok(properties)
+    }
+
+    /// Formats this property set as a CSS-style string such as `"italic 600 condensed"`.
+    ///
+    /// All three values are always written, so the result can be parsed back with `from_css()`.
+    pub fn to_css(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.style.to_css(),
+            self.weight.to_css(),
+            self.stretch.to_css()
+        )
+    }
+}
+
+impl FromStr for Properties {
+    type Err = CssParseError;
+
+    #[inline]
+    fn from_str(css: &str) -> Result<Properties, CssParseError> {
+        Properties::from_css(css)
+    }
+}
+
+fn set_once<T>(slot: &mut Option<T>, value: T, token: &str) -> Result<(), CssParseError> {
+    if slot.is_some() {
+        return Err(CssParseError::DuplicateValue(token.to_owned()));
+    }
+    *slot = Some(value);
+    Ok(())
 }
 
 /// Allows italic or oblique faces to be selected.
 #[derive(Clone, Copy, PartialEq, Debug, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Style {
     /// A face that is neither italic not obliqued.
     #[default]
@@ -79,9 +147,31 @@ impl Display for Style {
     }
 }
 
+impl Style {
+    /// Parses a CSS `font-style` keyword: `normal`, `italic`, or `oblique`.
+    pub fn from_css_keyword(keyword: &str) -> Option<Style> {
+        match &*keyword.to_ascii_lowercase() {
+            "normal" => Some(Style::Normal),
+            "italic" => Some(Style::Italic),
+            "oblique" => Some(Style::Oblique),
+            _ => None,
+        }
+    }
+
+    /// Returns the CSS `font-style` keyword for this style.
+    pub fn to_css(self) -> &'static str {
+        match self {
+            Style::Normal => "normal",
+            Style::Italic => "italic",
+            Style::Oblique => "oblique",
+        }
+    }
+}
+
 /// The degree of blackness or stroke thickness of a font. This value ranges from 100.0 to 900.0,
 /// with 400.0 as normal.
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Weight(pub f32);
 
 impl Default for Weight {
@@ -110,12 +200,61 @@ impl Weight {
     pub const EXTRA_BOLD: Weight = Weight(800.0);
     /// Black weight (900), the thickest value.
     pub const BLACK: Weight = Weight(900.0);
+
+    // Weight keywords, in the style of the common weight names from the OpenType specification.
+    const KEYWORDS: [(&'static str, Weight); 15] = [
+        ("thin", Weight::THIN),
+        ("hairline", Weight::THIN),
+        ("extra-light", Weight::EXTRA_LIGHT),
+        ("ultra-light", Weight::EXTRA_LIGHT),
+        ("light", Weight::LIGHT),
+        ("normal", Weight::NORMAL),
+        ("regular", Weight::NORMAL),
+        ("medium", Weight::MEDIUM),
+        ("semi-bold", Weight::SEMIBOLD),
+        ("demi-bold", Weight::SEMIBOLD),
+        ("bold", Weight::BOLD),
+        ("extra-bold", Weight::EXTRA_BOLD),
+        ("ultra-bold", Weight::EXTRA_BOLD),
+        ("black", Weight::BLACK),
+        ("heavy", Weight::BLACK),
+    ];
+
+    /// Maps a weight keyword to its numeric value.
+    ///
+    /// Besides the CSS keywords `normal` and `bold`, this accepts the common weight names `thin`
+    /// (`hairline`), `extra-light` (`ultra-light`), `light`, `regular`, `medium`, `semi-bold`
+    /// (`demi-bold`), `extra-bold` (`ultra-bold`), and `black` (`heavy`).
+    pub fn from_css_keyword(keyword: &str) -> Option<Weight> {
+        let keyword = keyword.to_ascii_lowercase();
+        Weight::KEYWORDS
+            .iter()
+            .find(|&&(name, _)| name == keyword)
+            .map(|&(_, weight)| weight)
+    }
+
+    /// Parses a CSS `font-weight` value: either a keyword or a number from 1 to 1000 inclusive.
+    pub fn from_css(value: &str) -> Option<Weight> {
+        if let Some(weight) = Weight::from_css_keyword(value) {
+            return Some(weight);
+        }
+        match value.parse::<f32>() {
+            Ok(weight) if (1.0..=1000.0).contains(&weight) => Some(Weight(weight)),
+            _ => None,
+        }
+    }
+
+    /// Returns the CSS `font-weight` value for this weight, which is always numeric.
+    pub fn to_css(self) -> String {
+        self.0.to_string()
+    }
 }
 
 /// The width of a font as an approximate fraction of the normal width.
 ///
 /// Widths range from 0.5 to 2.0 inclusive, with 1.0 as the normal width.
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stretch(pub f32);
 
 impl Default for Stretch {
@@ -145,6 +284,44 @@ impl Stretch {
     /// Ultra-expanded width (200%), the widest possible.
     pub const ULTRA_EXPANDED: Stretch = Stretch(2.0);
 
+    // CSS `font-stretch` keywords, in the order of `usWidthClass` values.
+    const KEYWORDS: [&'static str; 9] = [
+        "ultra-condensed",
+        "extra-condensed",
+        "condensed",
+        "semi-condensed",
+        "normal",
+        "semi-expanded",
+        "expanded",
+        "extra-expanded",
+        "ultra-expanded",
+    ];
+
+    /// Parses a CSS `font-stretch` value: either a keyword such as `condensed` or a non-negative
+    /// percentage such as `75%`.
+    pub fn from_css(value: &str) -> Option<Stretch> {
+        let value = value.to_ascii_lowercase();
+        if let Some(index) = Stretch::KEYWORDS
+            .iter()
+            .position(|&keyword| keyword == value)
+        {
+            return Some(Stretch(Stretch::MAPPING[index]));
+        }
+        match value.strip_suffix('%')?.parse::<f32>() {
+            Ok(percentage) if percentage >= 0.0 => Some(Stretch(percentage / 100.0)),
+            _ => None,
+        }
+    }
+
+    /// Returns the CSS `font-stretch` value for this stretch: a keyword if one matches exactly,
+    /// and a percentage otherwise.
+    pub fn to_css(self) -> String {
+        match Stretch::MAPPING.iter().position(|&value| value == self.0) {
+            Some(index) => Stretch::KEYWORDS[index].to_owned(),
+            None => format!("{}%", self.0 * 100.0),
+        }
+    }
+
     // Mapping from `usWidthClass` values to CSS `font-stretch` values.
     pub(crate) const MAPPING: [f32; 9] = [
         Stretch::ULTRA_CONDENSED.0,
@@ -158,3 +335,40 @@ impl Stretch {
         Stretch::ULTRA_EXPANDED.0,
     ];
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Properties, Stretch, Style, Weight};
+
+    #[test]
+    fn test_properties_from_css() {
+        let properties = Properties::from_css("italic 600 condensed").unwrap();
+        assert_eq!(properties.style, Style::Italic);
+        assert_eq!(properties.weight, Weight::SEMIBOLD);
+        assert_eq!(properties.stretch, Stretch::CONDENSED);
+
+        let properties: Properties = "Bold 87.5%".parse().unwrap();
+        assert_eq!(properties.style, Style::Normal);
+        assert_eq!(properties.weight, Weight::BOLD);
+        assert_eq!(properties.stretch, Stretch::SEMI_CONDENSED);
+
+        assert_eq!(Properties::from_css("normal").unwrap(), Properties::new());
+        assert!(Properties::from_css("italic oblique").is_err());
+        assert!(Properties::from_css("wobbly").is_err());
+    }
+
+    #[test]
+    fn test_properties_css_round_trip() {
+        let mut properties = Properties::new();
+        properties
+            .style(Style::Oblique)
+            .weight(Weight(350.0))
+            .stretch(Stretch(0.8));
+        assert_eq!(properties.to_css(), "oblique 350 80%");
+        assert_eq!(
+            Properties::from_css(&properties.to_css()).unwrap(),
+            properties
+        );
+        assert_eq!(Properties::new().to_css(), "normal 400 normal");
+    }
+}
@@ -0,0 +1,853 @@
+// font-kit/src/raster_cache.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A cache of rasterized glyphs with a bounded memory footprint.
+//!
+//! Rasterizing a glyph is far more expensive than copying its pixels, and text tends to use the
+//! same few glyphs over and over. `GlyphRasterCache` keeps recently used glyph images around,
+//! evicting the least recently used ones once the configured number of bytes is exceeded. It
+//! records hit, miss, and eviction counts so that applications can tune its capacity.
+//!
+//! The cache can be saved to disk and loaded again on the next run, so that applications with a
+//! fixed set of glyphs, such as kiosks and embedded devices, don't pay for rasterizing them on
+//! every start. For the saved glyphs to be valid in a later run, font IDs must identify the font
+//! contents rather than, say, the order in which fonts were loaded; `RasterKey::font_id_for()`
+//! computes such an ID.
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2I;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::{Error as IOError, ErrorKind, Read, Write};
+
+use crate::canvas::{Canvas, Format, RasterizationOptions};
+use crate::error::GlyphLoadingError;
+use crate::font::Font;
+use crate::hinting::HintingOptions;
+use crate::loader::Loader;
+use crate::units::{self, PpemRounding};
+
+/// Identifies a rasterized glyph image: which glyph of which font, and how it was rendered.
+///
+/// Floating-point parameters are compared bitwise, except that `0.0` and `-0.0` are the same, so
+/// the same values must be passed to get a cache hit. Fractional sizes are kept exactly; round
+/// them with `rounded()`, or have a `GlyphRasterCache` do it with `set_ppem_rounding()`, to share
+/// images between nearby sizes.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RasterKey {
+    font_id: u64,
+    glyph_id: u32,
+    point_size: u32,
+    transform: [u32; 6],
+    hinting_options: (u8, u32),
+    rasterization_options: (u8, u8),
+}
+
+impl RasterKey {
+    /// Creates a key for the given glyph rendered with the given parameters.
+    ///
+    /// `font_id` is chosen by the caller to tell fonts apart when one cache is shared between
+    /// several of them.
+    pub fn new(
+        font_id: u64,
+        glyph_id: u32,
+        point_size: f32,
+        transform: Transform2F,
+        hinting_options: HintingOptions,
+        rasterization_options: RasterizationOptions,
+    ) -> RasterKey {
+        let hinting_options = match hinting_options {
+            HintingOptions::None => (0, 0),
+            HintingOptions::Vertical(size) => (1, canonical_bits(size)),
+            HintingOptions::VerticalSubpixel(size) => (2, canonical_bits(size)),
+            HintingOptions::Full(size) => (3, canonical_bits(size)),
+        };
+        let (kind, threshold) = match rasterization_options.plain() {
+            RasterizationOptions::Bilevel => (0, 0),
+            RasterizationOptions::GrayscaleAa => (1, 0),
+            RasterizationOptions::SubpixelAa => (2, 0),
+            RasterizationOptions::FontDefault => (3, 0),
+            RasterizationOptions::BilevelThreshold(threshold) => (4, threshold),
+            RasterizationOptions::Deterministic(_) => unreachable!(),
+        };
+        let rasterization_options = if rasterization_options.is_deterministic() {
+            (kind | DETERMINISTIC_FLAG, threshold)
+        } else {
+            (kind, threshold)
+        };
+        RasterKey {
+            font_id,
+            glyph_id,
+            point_size: canonical_bits(point_size),
+            transform: [
+                canonical_bits(transform.m11()),
+                canonical_bits(transform.m12()),
+                canonical_bits(transform.m21()),
+                canonical_bits(transform.m22()),
+                canonical_bits(transform.vector.x()),
+                canonical_bits(transform.vector.y()),
+            ],
+            hinting_options,
+            rasterization_options,
+        }
+    }
+
+    /// Returns a font ID derived from the contents of the font, which is the same across runs and
+    /// processes as long as the font file doesn't change.
+    ///
+    /// This is the font's `fingerprint()`, which doesn't read the font data, so it is cheap even
+    /// for large fonts. For FreeType fonts, pass `as_ttf_parser_font()`.
+    #[inline]
+    pub fn font_id_for(font: &Font) -> u64 {
+        font.fingerprint()
+    }
+
+    /// Returns the caller-assigned font ID.
+    #[inline]
+    pub fn font_id(&self) -> u64 {
+        self.font_id
+    }
+
+    /// Returns the glyph ID.
+    #[inline]
+    pub fn glyph_id(&self) -> u32 {
+        self.glyph_id
+    }
+
+    /// Returns the point size.
+    #[inline]
+    pub fn point_size(&self) -> f32 {
+        f32::from_bits(self.point_size)
+    }
+
+    /// Returns the transform.
+    pub fn transform(&self) -> Transform2F {
+        let [m11, m12, m21, m22, x, y] = self.transform.map(f32::from_bits);
+        Transform2F::row_major(m11, m12, m21, m22, x, y)
+    }
+
+    /// Returns the hinting options.
+    pub fn hinting_options(&self) -> HintingOptions {
+        let (kind, size) = self.hinting_options;
+        let size = f32::from_bits(size);
+        match kind {
+            1 => HintingOptions::Vertical(size),
+            2 => HintingOptions::VerticalSubpixel(size),
+            3 => HintingOptions::Full(size),
+            _ => HintingOptions::None,
+        }
+    }
+
+    /// Returns the rasterization options.
+    pub fn rasterization_options(&self) -> RasterizationOptions {
+        let (kind, threshold) = self.rasterization_options;
+        let options = match kind & !DETERMINISTIC_FLAG {
+            0 => RasterizationOptions::Bilevel,
+            2 => RasterizationOptions::SubpixelAa,
+            3 => RasterizationOptions::FontDefault,
+            4 => RasterizationOptions::BilevelThreshold(threshold),
+            _ => RasterizationOptions::GrayscaleAa,
+        };
+        if kind & DETERMINISTIC_FLAG != 0 {
+            options.deterministic()
+        } else {
+            options
+        }
+    }
+
+    /// Returns this key with the size the glyph is rendered at, and the size it is hinted at,
+    /// rounded to whole pixels per em as specified, taking the transform's scale into account.
+    pub fn rounded(&self, rounding: PpemRounding) -> RasterKey {
+        let transform = self.transform();
+        RasterKey::new(
+            self.font_id,
+            self.glyph_id,
+            rounding.round_point_size(self.point_size(), transform),
+            transform,
+            self.hinting_options().rounded(rounding, transform),
+            self.rasterization_options(),
+        )
+    }
+}
+
+impl fmt::Debug for RasterKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RasterKey")
+            .field("font_id", &self.font_id)
+            .field("glyph_id", &self.glyph_id)
+            .field("point_size", &self.point_size())
+            .field("transform", &self.transform())
+            .field("hinting_options", &self.hinting_options())
+            .field("rasterization_options", &self.rasterization_options())
+            .finish()
+    }
+}
+
+/// A rasterized glyph image.
+#[derive(Debug)]
+pub struct RasterizedGlyph {
+    /// The glyph image.
+    pub canvas: Canvas,
+    /// The position of the top left corner of the image relative to the glyph origin, in pixels.
+    pub origin: Vector2I,
+}
+
+impl RasterizedGlyph {
+    /// Returns the number of bytes that the image occupies.
+    #[inline]
+    pub fn byte_size(&self) -> usize {
+        self.canvas.pixels.len()
+    }
+}
+
+/// A glyph to rasterize in a batch, such as with `Font::rasterize_glyphs_parallel()`, and how to
+/// rasterize it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphRequest {
+    /// The glyph to rasterize.
+    pub glyph_id: u32,
+    /// The size of the font, in points.
+    pub point_size: f32,
+    /// The transform to apply to the glyph, before it is positioned in its image.
+    pub transform: Transform2F,
+    /// The hinting to apply to the outline. The deterministic rasterizer ignores it.
+    pub hinting_options: HintingOptions,
+    /// How to rasterize the glyph, including whether to use the deterministic rasterizer or the
+    /// font's own.
+    pub rasterization_options: RasterizationOptions,
+    /// The format of the glyph image.
+    pub format: Format,
+}
+
+impl GlyphRequest {
+    /// Creates a request for a glyph at `point_size`, untransformed, unhinted, and antialiased to
+    /// `A8` by the fixed-point rasterizer of the `deterministic` module. Set
+    /// `rasterization_options` to options without the `Deterministic` flag to use the font's own
+    /// rasterizer, as `Loader::rasterize_glyph()` does.
+    pub fn new(glyph_id: u32, point_size: f32) -> GlyphRequest {
+        GlyphRequest {
+            glyph_id,
+            point_size,
+            transform: Transform2F::default(),
+            hinting_options: HintingOptions::None,
+            rasterization_options: RasterizationOptions::GrayscaleAa.deterministic(),
+            format: Format::A8,
+        }
+    }
+}
+
+/// Counters describing how well a `GlyphRasterCache` is doing.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RasterCacheStats {
+    /// The number of lookups that found a cached glyph.
+    pub hits: u64,
+    /// The number of lookups that did not find a cached glyph.
+    pub misses: u64,
+    /// The number of glyphs evicted to stay within the capacity.
+    pub evictions: u64,
+    /// The number of glyphs currently cached.
+    pub entry_count: usize,
+    /// The number of bytes of glyph images currently cached.
+    pub bytes_used: usize,
+    /// The maximum number of bytes of glyph images to cache.
+    pub capacity_bytes: usize,
+}
+
+impl RasterCacheStats {
+    /// Returns the fraction of lookups that were hits, or 0.0 if there were no lookups.
+    pub fn hit_rate(&self) -> f32 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f32 / lookups as f32
+        }
+    }
+}
+
+/// A least-recently-used cache of rasterized glyphs, bounded by the number of bytes of glyph
+/// images it holds.
+#[derive(Debug)]
+pub struct GlyphRasterCache {
+    entries: HashMap<RasterKey, CacheEntry>,
+    // Maps the time of last use to each key, oldest first.
+    lru: BTreeMap<u64, RasterKey>,
+    clock: u64,
+    bytes_used: usize,
+    capacity_bytes: usize,
+    ppem_rounding: PpemRounding,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    glyph: RasterizedGlyph,
+    last_used: u64,
+}
+
+impl GlyphRasterCache {
+    /// Creates an empty cache that holds up to `capacity_bytes` bytes of glyph images.
+    pub fn new(capacity_bytes: usize) -> GlyphRasterCache {
+        GlyphRasterCache {
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+            bytes_used: 0,
+            capacity_bytes,
+            ppem_rounding: PpemRounding::Exact,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+        }
+    }
+
+    /// Returns the cached image for `key`, if any, and marks it as recently used.
+    pub fn get(&mut self, key: &RasterKey) -> Option<&RasterizedGlyph> {
+        let key = key.rounded(self.ppem_rounding);
+        if !self.entries.contains_key(&key) {
+            self.misses += 1;
+            return None;
+        }
+        self.hits += 1;
+        self.touch(&key);
+        self.entries.get(&key).map(|entry| &entry.glyph)
+    }
+
+    /// Adds an image to the cache, evicting the least recently used images as necessary.
+    ///
+    /// An image larger than the whole capacity is still cached, but it evicts everything else.
+    pub fn insert(&mut self, key: RasterKey, glyph: RasterizedGlyph) {
+        let key = key.rounded(self.ppem_rounding);
+        self.remove(&key);
+
+        self.clock += 1;
+        self.bytes_used += glyph.byte_size();
+        self.lru.insert(self.clock, key);
+        self.entries.insert(
+            key,
+            CacheEntry {
+                glyph,
+                last_used: self.clock,
+            },
+        );
+        self.evict(Some(&key));
+    }
+
+    /// Returns the cached image for `key`, rasterizing it with `font` on a miss.
+    ///
+    /// The glyph is rasterized at the size of the rounded key; see `set_ppem_rounding()`.
+    pub fn rasterize<F>(
+        &mut self,
+        font: &F,
+        key: RasterKey,
+    ) -> Result<&RasterizedGlyph, GlyphLoadingError>
+    where
+        F: Loader,
+    {
+        let key = key.rounded(self.ppem_rounding);
+        if self.entries.contains_key(&key) {
+            self.hits += 1;
+            self.touch(&key);
+            return Ok(&self.entries[&key].glyph);
+        }
+        self.misses += 1;
+
+        let (point_size, transform) = (key.point_size(), key.transform());
+        let pixels_per_em = units::pixels_per_em(point_size, &transform);
+        let (hinting_options, rasterization_options) = (
+            key.hinting_options(),
+            key.rasterization_options().resolve(font, pixels_per_em),
+        );
+        let bounds = font.raster_bounds(
+            key.glyph_id(),
+            point_size,
+            transform,
+            hinting_options,
+            rasterization_options,
+        )?;
+        let format = match rasterization_options.plain() {
+            RasterizationOptions::SubpixelAa => Format::Rgb24,
+            RasterizationOptions::Bilevel
+            | RasterizationOptions::BilevelThreshold(_)
+            | RasterizationOptions::GrayscaleAa
+            | RasterizationOptions::FontDefault
+            | RasterizationOptions::Deterministic(_) => Format::A8,
+        };
+        let mut canvas = Canvas::new(bounds.size(), format);
+        font.rasterize_glyph(
+            &mut canvas,
+            key.glyph_id(),
+            point_size,
+            Transform2F::from_translation(-bounds.origin().to_f32()) * transform,
+            hinting_options,
+            rasterization_options,
+        )?;
+
+        self.insert(
+            key,
+            RasterizedGlyph {
+                canvas,
+                origin: bounds.origin(),
+            },
+        );
+        Ok(&self.entries[&key].glyph)
+    }
+
+    /// Removes the image for `key` from the cache, returning it if it was present.
+    pub fn remove(&mut self, key: &RasterKey) -> Option<RasterizedGlyph> {
+        let entry = self.entries.remove(&key.rounded(self.ppem_rounding))?;
+        self.lru.remove(&entry.last_used);
+        self.bytes_used -= entry.glyph.byte_size();
+        Some(entry.glyph)
+    }
+
+    /// Removes all images from the cache. The statistics are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.lru.clear();
+        self.bytes_used = 0;
+    }
+
+    /// Returns the maximum number of bytes of glyph images to cache.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity_bytes
+    }
+
+    /// Changes the capacity, evicting the least recently used images if it shrinks.
+    pub fn set_capacity(&mut self, capacity_bytes: usize) {
+        self.capacity_bytes = capacity_bytes;
+        self.evict(None);
+    }
+
+    /// Returns how the sizes of keys are rounded. The default is `PpemRounding::Exact`.
+    #[inline]
+    pub fn ppem_rounding(&self) -> PpemRounding {
+        self.ppem_rounding
+    }
+
+    /// Sets how the sizes of keys are rounded before they are looked up, inserted, or
+    /// rasterized, so that sizes that round to the same number of pixels per em share an image.
+    ///
+    /// Images already in the cache are removed, since they were stored under differently rounded
+    /// keys. The statistics are kept.
+    pub fn set_ppem_rounding(&mut self, ppem_rounding: PpemRounding) {
+        if ppem_rounding != self.ppem_rounding {
+            self.ppem_rounding = ppem_rounding;
+            self.clear();
+        }
+    }
+
+    /// Returns the number of cached images.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no images are cached.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the keys of all cached images, in no particular order.
+    pub fn keys(&self) -> impl Iterator<Item = &RasterKey> {
+        self.entries.keys()
+    }
+
+    /// Returns the current statistics.
+    pub fn stats(&self) -> RasterCacheStats {
+        RasterCacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+            entry_count: self.entries.len(),
+            bytes_used: self.bytes_used,
+            capacity_bytes: self.capacity_bytes,
+        }
+    }
+
+    /// Resets the hit, miss, and eviction counters to zero.
+    pub fn reset_stats(&mut self) {
+        self.hits = 0;
+        self.misses = 0;
+        self.evictions = 0;
+    }
+
+    /// Writes all cached images to `writer`, so that they can be restored with `load()`.
+    ///
+    /// The statistics are not saved.
+    pub fn save<W>(&self, writer: &mut W) -> Result<(), IOError>
+    where
+        W: Write,
+    {
+        writer.write_all(CACHE_MAGIC)?;
+        writer.write_u32::<LittleEndian>(CACHE_VERSION)?;
+        writer.write_u32::<LittleEndian>(self.entries.len() as u32)?;
+
+        // Save the least recently used images first, so that loading them in order restores the
+        // order of use.
+        for key in self.lru.values() {
+            let glyph = &self.entries[key].glyph;
+            writer.write_u64::<LittleEndian>(key.font_id)?;
+            writer.write_u32::<LittleEndian>(key.glyph_id)?;
+            writer.write_u32::<LittleEndian>(key.point_size)?;
+            for &component in &key.transform {
+                writer.write_u32::<LittleEndian>(component)?;
+            }
+            writer.write_u8(key.hinting_options.0)?;
+            writer.write_u32::<LittleEndian>(key.hinting_options.1)?;
+            writer.write_u8(key.rasterization_options.0)?;
+            writer.write_u8(key.rasterization_options.1)?;
+
+            writer.write_i32::<LittleEndian>(glyph.origin.x())?;
+            writer.write_i32::<LittleEndian>(glyph.origin.y())?;
+            let canvas = &glyph.canvas;
+            writer.write_i32::<LittleEndian>(canvas.size.x())?;
+            writer.write_i32::<LittleEndian>(canvas.size.y())?;
+            writer.write_u32::<LittleEndian>(canvas.stride as u32)?;
+            writer.write_u8(match canvas.format {
+                Format::Rgba32 => 0,
+                Format::Rgb24 => 1,
+                Format::A8 => 2,
+                Format::A1 => 3,
+            })?;
+            writer.write_u32::<LittleEndian>(canvas.pixels.len() as u32)?;
+            writer.write_all(&canvas.pixels)?;
+        }
+        Ok(())
+    }
+
+    /// Reads images saved with `save()` into this cache, returning how many were read.
+    ///
+    /// Images are added as though by `insert()`, so they may evict each other, or images already
+    /// in the cache, if the capacity is too small. Data written by a different version of
+    /// `font-kit` is rejected with an `InvalidData` error, in which case callers should simply
+    /// start with an empty cache.
+    pub fn load<R>(&mut self, reader: &mut R) -> Result<usize, IOError>
+    where
+        R: Read,
+    {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != CACHE_MAGIC {
+            return Err(invalid_data("not a glyph cache"));
+        }
+        if reader.read_u32::<LittleEndian>()? != CACHE_VERSION {
+            return Err(invalid_data("unsupported glyph cache version"));
+        }
+
+        let entry_count = reader.read_u32::<LittleEndian>()? as usize;
+        for _ in 0..entry_count {
+            let font_id = reader.read_u64::<LittleEndian>()?;
+            let glyph_id = reader.read_u32::<LittleEndian>()?;
+            let point_size = reader.read_u32::<LittleEndian>()?;
+            let mut transform = [0; 6];
+            for component in &mut transform {
+                *component = reader.read_u32::<LittleEndian>()?;
+            }
+            let hinting_options = (reader.read_u8()?, reader.read_u32::<LittleEndian>()?);
+            let rasterization_options = (reader.read_u8()?, reader.read_u8()?);
+            let key = RasterKey {
+                font_id,
+                glyph_id,
+                point_size,
+                transform,
+                hinting_options,
+                rasterization_options,
+            };
+
+            let origin = Vector2I::new(
+                reader.read_i32::<LittleEndian>()?,
+                reader.read_i32::<LittleEndian>()?,
+            );
+            let size = Vector2I::new(
+                reader.read_i32::<LittleEndian>()?,
+                reader.read_i32::<LittleEndian>()?,
+            );
+            let stride = reader.read_u32::<LittleEndian>()? as usize;
+            let format = match reader.read_u8()? {
+                0 => Format::Rgba32,
+                1 => Format::Rgb24,
+                2 => Format::A8,
+                3 => Format::A1,
+                _ => return Err(invalid_data("invalid pixel format")),
+            };
+            let pixel_count = reader.read_u32::<LittleEndian>()? as usize;
+            if size.x() < 0
+                || size.y() < 0
+                || stride < format.row_len(size.x() as usize)
+                || stride.checked_mul(size.y() as usize) != Some(pixel_count)
+            {
+                return Err(invalid_data("invalid glyph image size"));
+            }
+            // The sizes come from the file, so the buffer only grows as far as the data goes.
+            let mut pixels = vec![];
+            reader
+                .by_ref()
+                .take(pixel_count as u64)
+                .read_to_end(&mut pixels)?;
+            if pixels.len() != pixel_count {
+                return Err(IOError::new(
+                    ErrorKind::UnexpectedEof,
+                    "truncated glyph image",
+                ));
+            }
+
+            self.insert(
+                key,
+                RasterizedGlyph {
+                    canvas: Canvas {
+                        pixels,
+                        size,
+                        stride,
+                        format,
+                    },
+                    origin,
+                },
+            );
+        }
+        Ok(entry_count)
+    }
+
+    fn touch(&mut self, key: &RasterKey) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.lru.remove(&entry.last_used);
+            self.clock += 1;
+            entry.last_used = self.clock;
+            self.lru.insert(self.clock, *key);
+        }
+    }
+
+    // Evicts the least recently used images, other than `keep`, until the cache fits.
+    fn evict(&mut self, keep: Option<&RasterKey>) {
+        while self.bytes_used > self.capacity_bytes {
+            let victim = match self.lru.values().find(|&key| Some(key) != keep) {
+                Some(&victim) => victim,
+                None => break,
+            };
+            self.remove(&victim);
+            self.evictions += 1;
+        }
+    }
+}
+
+const CACHE_MAGIC: &[u8; 4] = b"FKRC";
+
+// Bump this whenever the format of saved caches or the output of the rasterizer changes, so that
+// stale images are not reused.
+const CACHE_VERSION: u32 = 3;
+
+// Set in the kind of rasterization options in keys for `RasterizationOptions::Deterministic`.
+const DETERMINISTIC_FLAG: u8 = 0x80;
+
+// Returns the bits of `value`, treating `-0.0` as `0.0` so that both produce the same key.
+fn canonical_bits(value: f32) -> u32 {
+    if value == 0.0 {
+        0
+    } else {
+        value.to_bits()
+    }
+}
+
+fn invalid_data(message: &str) -> IOError {
+    IOError::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GlyphRasterCache, RasterKey, RasterizedGlyph};
+    use crate::canvas::{Canvas, Format, RasterizationOptions};
+    use crate::font::Font;
+    use crate::hinting::HintingOptions;
+    use crate::loader::Loader;
+    use crate::units::PpemRounding;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::Vector2I;
+    use std::io::ErrorKind;
+    use std::sync::Arc;
+
+    fn key(glyph_id: u32) -> RasterKey {
+        RasterKey::new(
+            0,
+            glyph_id,
+            16.0,
+            Transform2F::default(),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )
+    }
+
+    fn glyph() -> RasterizedGlyph {
+        RasterizedGlyph {
+            canvas: Canvas::new(Vector2I::new(4, 4), Format::A8),
+            origin: Vector2I::default(),
+        }
+    }
+
+    #[test]
+    fn test_key_round_trip() {
+        let transform = Transform2F::row_major(1.0, 0.25, -0.5, 2.0, 3.0, 4.0);
+        let key = RasterKey::new(
+            7,
+            42,
+            12.5,
+            transform,
+            HintingOptions::Vertical(12.5),
+            RasterizationOptions::SubpixelAa,
+        );
+        assert_eq!(key.transform(), transform);
+        assert_eq!(key.hinting_options(), HintingOptions::Vertical(12.5));
+        assert_eq!(
+            key.rasterization_options(),
+            RasterizationOptions::SubpixelAa
+        );
+
+        let deterministic = RasterizationOptions::BilevelThreshold(90).deterministic();
+        let deterministic_key =
+            RasterKey::new(7, 42, 12.5, transform, HintingOptions::None, deterministic);
+        assert_eq!(deterministic_key.rasterization_options(), deterministic);
+        assert!(
+            deterministic_key
+                != RasterKey::new(
+                    7,
+                    42,
+                    12.5,
+                    transform,
+                    HintingOptions::None,
+                    deterministic.plain()
+                )
+        );
+    }
+
+    #[test]
+    fn test_stats_and_eviction() {
+        let mut cache = GlyphRasterCache::new(32);
+        cache.insert(key(1), glyph());
+        cache.insert(key(2), glyph());
+        assert!(cache.get(&key(1)).is_some());
+        assert!(cache.get(&key(3)).is_none());
+
+        // Glyph 2 is the least recently used, so it goes.
+        cache.insert(key(3), glyph());
+        let mut keys: Vec<u32> = cache.keys().map(|key| key.glyph_id()).collect();
+        keys.sort();
+        assert_eq!(keys, [1, 3]);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 1, 1));
+        assert_eq!((stats.entry_count, stats.bytes_used), (2, 32));
+        assert_eq!(stats.hit_rate(), 0.5);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut cache = GlyphRasterCache::new(1024);
+        let mut glyph = glyph();
+        glyph.canvas.pixels[5] = 0xff;
+        glyph.origin = Vector2I::new(-1, 3);
+        cache.insert(key(1), glyph);
+        cache.insert(key(2), self::glyph());
+
+        let mut data = vec![];
+        cache.save(&mut data).unwrap();
+        let mut loaded = GlyphRasterCache::new(1024);
+        assert_eq!(loaded.load(&mut &data[..]).unwrap(), 2);
+
+        let glyph = loaded.get(&key(1)).unwrap();
+        assert_eq!(glyph.origin, Vector2I::new(-1, 3));
+        assert_eq!(glyph.canvas.pixels[5], 0xff);
+        assert!(loaded.load(&mut &data[1..]).is_err());
+    }
+
+    #[test]
+    fn test_load_truncated() {
+        let mut cache = GlyphRasterCache::new(1024);
+        cache.insert(key(1), glyph());
+        let mut data = vec![];
+        cache.save(&mut data).unwrap();
+
+        // Claim a 16384x16384 image where there are only 16 bytes of pixels.
+        let image_size = data.len() - 16 - 17;
+        for (offset, value) in [0, 4, 8].iter().zip(&[0x4000u32, 0x4000, 0x4000]) {
+            data[image_size + offset..][..4].copy_from_slice(&value.to_le_bytes());
+        }
+        data[image_size + 13..][..4].copy_from_slice(&0x1000_0000u32.to_le_bytes());
+        let mut loaded = GlyphRasterCache::new(1024);
+        let error = loaded.load(&mut &data[..]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(loaded.stats().entry_count, 0);
+    }
+
+    #[test]
+    fn test_fractional_sizes() {
+        let path = "resources/tests/eb-garamond/EBGaramond12-Regular.otf";
+        let font = Font::from_path(path, 0).unwrap();
+        let glyph_id = font.glyph_for_char('a').unwrap();
+        // At 1.25×, 11pt is 13.75 pixels per em, and 11.2pt is 14.
+        let key = |point_size| {
+            RasterKey::new(
+                0,
+                glyph_id,
+                point_size,
+                Transform2F::from_scale(1.25),
+                HintingOptions::Vertical(point_size),
+                RasterizationOptions::GrayscaleAa,
+            )
+        };
+
+        let mut exact = GlyphRasterCache::new(1 << 20);
+        exact.rasterize(&font, key(11.0)).unwrap();
+        exact.rasterize(&font, key(11.2)).unwrap();
+        assert_eq!(exact.len(), 2);
+        assert!(exact.get(&key(11.1)).is_none());
+
+        let mut rounded = GlyphRasterCache::new(1 << 20);
+        rounded.set_ppem_rounding(PpemRounding::Nearest);
+        let pixels = rounded
+            .rasterize(&font, key(11.0))
+            .unwrap()
+            .canvas
+            .pixels
+            .clone();
+        rounded.rasterize(&font, key(11.2)).unwrap();
+        assert!(rounded.get(&key(11.1)).is_some());
+        assert_eq!(rounded.len(), 1);
+        assert_eq!(rounded.stats().hits, 2);
+        assert_eq!(pixels, exact.get(&key(11.2)).unwrap().canvas.pixels);
+        assert_eq!(
+            key(11.0).rounded(PpemRounding::Nearest).hinting_options(),
+            key(11.2).hinting_options()
+        );
+
+        // Rounding down keeps a stored key where it is when it is looked up again.
+        rounded.set_ppem_rounding(PpemRounding::Down);
+        assert!(rounded.is_empty());
+        rounded.rasterize(&font, key(11.1)).unwrap();
+        let stored = *rounded.keys().next().unwrap();
+        assert!(rounded.remove(&stored).is_some());
+    }
+
+    #[test]
+    fn test_font_id_for() {
+        let path = "resources/tests/eb-garamond/EBGaramond12-Regular.otf";
+        let font = Font::from_path(path, 0).unwrap();
+        let same = Font::from_bytes(Arc::new(std::fs::read(path).unwrap()), 0).unwrap();
+        assert_eq!(RasterKey::font_id_for(&font), RasterKey::font_id_for(&same));
+        let italic = Font::from_path("resources/tests/eb-garamond/EBGaramond12.otc", 1).unwrap();
+        assert_ne!(
+            RasterKey::font_id_for(&font),
+            RasterKey::font_id_for(&italic)
+        );
+    }
+}
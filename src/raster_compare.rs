@@ -0,0 +1,220 @@
+// font-kit/src/raster_compare.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Measures how much two loaders' renderings of the same glyph differ.
+//!
+//! This is meant for migrating between rendering stacks, for example from FreeType to the
+//! pure-Rust loader: rasterize the glyphs an application uses with both, and fail a test if any
+//! of them differs by more than some tolerance. It is available with the `ft-compare` feature.
+
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2I;
+
+use crate::canvas::{Canvas, Format, RasterizationOptions};
+use crate::error::GlyphLoadingError;
+use crate::hinting::HintingOptions;
+use crate::loader::Loader;
+
+/// How much two glyph images differ.
+///
+/// Differences are per channel, so an RGB image has three samples per pixel.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RasterDifference {
+    /// The largest difference between corresponding samples, from 0 to 255.
+    pub max_difference: u8,
+    /// The mean difference between corresponding samples, from 0.0 to 255.0.
+    pub mean_difference: f32,
+    /// The number of pixels in which any sample differs.
+    pub differing_pixels: usize,
+    /// The total number of pixels compared.
+    pub pixel_count: usize,
+}
+
+impl RasterDifference {
+    /// Compares two images of the same size and format. `A1` images are compared as `A8` images
+    /// whose samples are all 0 or 255.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sizes or formats of the canvases differ.
+    pub fn between(a: &Canvas, b: &Canvas) -> RasterDifference {
+        assert_eq!(a.size, b.size, "canvases must have the same size");
+        assert_eq!(a.format, b.format, "canvases must have the same format");
+        if a.format == Format::A1 {
+            return RasterDifference::between(&a.to_format(Format::A8), &b.to_format(Format::A8));
+        }
+
+        let bytes_per_row = a.size.x() as usize * a.format.bytes_per_pixel() as usize;
+        let bytes_per_pixel = a.format.bytes_per_pixel() as usize;
+        let mut difference = RasterDifference {
+            pixel_count: a.size.x() as usize * a.size.y() as usize,
+            ..RasterDifference::default()
+        };
+        let mut total_difference = 0u64;
+        for y in 0..a.size.y() as usize {
+            let row_a = &a.pixels[y * a.stride..][..bytes_per_row];
+            let row_b = &b.pixels[y * b.stride..][..bytes_per_row];
+            for (pixel_a, pixel_b) in row_a
+                .chunks(bytes_per_pixel)
+                .zip(row_b.chunks(bytes_per_pixel))
+            {
+                let mut pixel_differs = false;
+                for (&sample_a, &sample_b) in pixel_a.iter().zip(pixel_b) {
+                    let sample_difference = sample_a.abs_diff(sample_b);
+                    difference.max_difference = difference.max_difference.max(sample_difference);
+                    total_difference += sample_difference as u64;
+                    pixel_differs |= sample_difference != 0;
+                }
+                difference.differing_pixels += pixel_differs as usize;
+            }
+        }
+
+        let sample_count = difference.pixel_count * bytes_per_pixel;
+        if sample_count > 0 {
+            difference.mean_difference = total_difference as f32 / sample_count as f32;
+        }
+        difference
+    }
+
+    /// Returns true if the images are identical.
+    #[inline]
+    pub fn is_identical(&self) -> bool {
+        self.max_difference == 0
+    }
+
+    /// Returns true if no sample differs by more than `tolerance`.
+    #[inline]
+    pub fn is_within(&self, tolerance: u8) -> bool {
+        self.max_difference <= tolerance
+    }
+}
+
+/// Rasterizes a glyph with two loaders and measures how much the images differ.
+///
+/// `reference` and `candidate` should be the same font loaded with different loaders, such as
+/// the FreeType loader and `font::Font`. Both images are rendered into canvases covering the union
+/// of the glyph's raster bounds according to each loader, so that a difference in bounds shows up
+/// as differing pixels rather than as an error.
+pub fn compare_glyph<A, B>(
+    reference: &A,
+    candidate: &B,
+    glyph_id: u32,
+    point_size: f32,
+    transform: Transform2F,
+    hinting_options: HintingOptions,
+    rasterization_options: RasterizationOptions,
+) -> Result<RasterDifference, GlyphLoadingError>
+where
+    A: Loader,
+    B: Loader,
+{
+    let reference_bounds = reference.raster_bounds(
+        glyph_id,
+        point_size,
+        transform,
+        hinting_options,
+        rasterization_options,
+    )?;
+    let candidate_bounds = candidate.raster_bounds(
+        glyph_id,
+        point_size,
+        transform,
+        hinting_options,
+        rasterization_options,
+    )?;
+    let bounds = RectI::from_points(
+        Vector2I::new(
+            reference_bounds.min_x().min(candidate_bounds.min_x()),
+            reference_bounds.min_y().min(candidate_bounds.min_y()),
+        ),
+        Vector2I::new(
+            reference_bounds.max_x().max(candidate_bounds.max_x()),
+            reference_bounds.max_y().max(candidate_bounds.max_y()),
+        ),
+    );
+
+    let reference_canvas = rasterize(
+        reference,
+        bounds,
+        glyph_id,
+        point_size,
+        transform,
+        hinting_options,
+        rasterization_options,
+    )?;
+    let candidate_canvas = rasterize(
+        candidate,
+        bounds,
+        glyph_id,
+        point_size,
+        transform,
+        hinting_options,
+        rasterization_options,
+    )?;
+    Ok(RasterDifference::between(
+        &reference_canvas,
+        &candidate_canvas,
+    ))
+}
+
+fn rasterize<F>(
+    font: &F,
+    bounds: RectI,
+    glyph_id: u32,
+    point_size: f32,
+    transform: Transform2F,
+    hinting_options: HintingOptions,
+    rasterization_options: RasterizationOptions,
+) -> Result<Canvas, GlyphLoadingError>
+where
+    F: Loader,
+{
+    let format = match rasterization_options.plain() {
+        RasterizationOptions::SubpixelAa => Format::Rgb24,
+        RasterizationOptions::Bilevel
+        | RasterizationOptions::BilevelThreshold(_)
+        | RasterizationOptions::GrayscaleAa
+        | RasterizationOptions::FontDefault
+        | RasterizationOptions::Deterministic(_) => Format::A8,
+    };
+    let mut canvas = Canvas::new(bounds.size(), format);
+    font.rasterize_glyph(
+        &mut canvas,
+        glyph_id,
+        point_size,
+        Transform2F::from_translation(-bounds.origin().to_f32()) * transform,
+        hinting_options,
+        rasterization_options,
+    )?;
+    Ok(canvas)
+}
+
+#[cfg(test)]
+mod test {
+    use super::RasterDifference;
+    use crate::canvas::{Canvas, Format};
+    use pathfinder_geometry::vector::Vector2I;
+
+    #[test]
+    fn test_difference_between_canvases() {
+        let a = Canvas::new(Vector2I::new(2, 2), Format::A8);
+        let mut b = Canvas::new(Vector2I::new(2, 2), Format::A8);
+        assert!(RasterDifference::between(&a, &b).is_identical());
+
+        b.pixels[1] = 40;
+        b.pixels[b.stride] = 8;
+        let difference = RasterDifference::between(&a, &b);
+        assert_eq!(difference.max_difference, 40);
+        assert_eq!(difference.differing_pixels, 2);
+        assert_eq!(difference.mean_difference, 12.0);
+        assert!(difference.is_within(40) && !difference.is_within(39));
+    }
+}
@@ -0,0 +1,518 @@
+// font-kit/src/rendering_config.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The user's text rendering preferences, as configured in the operating system.
+//!
+//! Applications that render text with the same antialiasing, hinting, and gamma as the rest of
+//! the desktop look native; `RenderingConfig::system()` reads those settings so that they can be
+//! used as defaults.
+//!
+//! On Linux and other Unix systems, the settings come from the `Xft.*` X resources and from
+//! fontconfig, the same places that GTK and Qt read them from. The X resources are read from the
+//! user's resource files; `XftSettings::query_x_server()` gets them from the running X server
+//! instead, at the cost of running `xrdb`. Fontconfig's configuration is loaded the first time it
+//! is needed and kept for the life of the process.
+//!
+//! Fontconfig also decides which font GTK and Qt applications use for a family name;
+//! `FontconfigSource::match_font()` makes the same choice.
+
+use crate::canvas::{CoverageOptions, RasterizationOptions};
+use crate::hinting::HintingOptions;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Text rendering preferences: antialiasing, hinting, subpixel layout, gamma, and resolution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderingConfig {
+    /// The antialiasing strategy.
+    pub rasterization_options: RasterizationOptions,
+    /// How strongly glyphs should be grid fitted.
+    pub hinting_style: HintingStyle,
+    /// The physical layout of the display's subpixels, for subpixel antialiasing.
+    pub subpixel_order: SubpixelOrder,
+    /// The gamma to apply to antialiased coverage. 1.0 means no correction.
+    ///
+    /// `rasterization_options` doesn't carry it; pass `coverage_options()` to
+    /// `Loader::rasterize_glyph_adjusted()` to apply it.
+    pub gamma: f32,
+    /// The resolution of the display, in dots per inch.
+    pub dpi: f32,
+    /// Whether the user interface uses light or dark colors.
+    ///
+    /// Antialiased text looks lighter on dark backgrounds, so renderers may want to adjust gamma
+    /// or stem darkening based on this.
+    pub theme: Theme,
+}
+
+/// How strongly glyphs should be grid fitted, independent of size.
+///
+/// This corresponds to fontconfig's `hintstyle` and to the equivalent settings on other
+/// platforms.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HintingStyle {
+    /// No hinting.
+    None,
+    /// Vertical-only hinting.
+    Slight,
+    /// Moderate hinting. Rendered as full hinting by `font-kit`.
+    Medium,
+    /// Hinting in both directions.
+    Full,
+}
+
+/// The physical layout of a display's subpixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SubpixelOrder {
+    /// The layout is unknown.
+    Unknown,
+    /// Horizontal red, green, blue.
+    Rgb,
+    /// Horizontal blue, green, red.
+    Bgr,
+    /// Vertical red, green, blue.
+    VRgb,
+    /// Vertical blue, green, red.
+    VBgr,
+    /// The display has no subpixels that can be addressed individually.
+    None,
+}
+
+/// Whether the user interface uses light or dark colors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Theme {
+    /// Dark text on light backgrounds.
+    Light,
+    /// Light text on dark backgrounds.
+    Dark,
+}
+
+impl Default for RenderingConfig {
+    fn default() -> RenderingConfig {
+        RenderingConfig {
+            rasterization_options: RasterizationOptions::GrayscaleAa,
+            hinting_style: HintingStyle::None,
+            subpixel_order: SubpixelOrder::Unknown,
+            gamma: 1.0,
+            dpi: 96.0,
+            theme: Theme::Light,
+        }
+    }
+}
+
+impl RenderingConfig {
+    /// Reads the user's text rendering preferences from the operating system.
+    ///
+    /// Settings that cannot be determined keep their default values.
+    pub fn system() -> RenderingConfig {
+        RenderingConfig::system_for_family("")
+    }
+
+    /// Reads the user's text rendering preferences for fonts of the given family.
+    ///
+    /// On Linux, fontconfig's `<match target="font">` rules can change hinting and antialiasing
+    /// for particular families, and are applied here as GTK and Qt apply them. On other platforms
+    /// the preferences don't depend on the font, so this is the same as `system()`.
+    pub fn system_for_family(family_name: &str) -> RenderingConfig {
+        let mut config = RenderingConfig::default();
+        platform::read_system_config(&mut config, family_name);
+        config
+    }
+
+    /// Returns the hinting options to use for glyphs rendered at the given point size.
+    pub fn hinting_options(&self, point_size: f32) -> HintingOptions {
+        match self.hinting_style {
+            HintingStyle::None => HintingOptions::None,
+            HintingStyle::Slight
+                if self.rasterization_options == RasterizationOptions::SubpixelAa =>
+            {
+                HintingOptions::VerticalSubpixel(point_size)
+            }
+            HintingStyle::Slight => HintingOptions::Vertical(point_size),
+            HintingStyle::Medium | HintingStyle::Full => HintingOptions::Full(point_size),
+        }
+    }
+
+    /// Returns the coverage adjustments that match these preferences: the configured gamma, with
+    /// stem darkening for light text on dark backgrounds, which otherwise looks too thin.
+    pub fn coverage_options(&self) -> CoverageOptions {
+        CoverageOptions {
+            gamma: self.gamma,
+            stem_darkening: self.theme == Theme::Dark,
+        }
+    }
+
+    // Turns antialiasing on or off, using subpixel antialiasing if the subpixel order is known.
+    pub(crate) fn set_antialias(&mut self, antialias: bool) {
+        self.rasterization_options = if !antialias {
+            RasterizationOptions::Bilevel
+        } else {
+            match self.subpixel_order {
+                SubpixelOrder::Rgb
+                | SubpixelOrder::Bgr
+                | SubpixelOrder::VRgb
+                | SubpixelOrder::VBgr => RasterizationOptions::SubpixelAa,
+                SubpixelOrder::Unknown | SubpixelOrder::None => RasterizationOptions::GrayscaleAa,
+            }
+        };
+    }
+}
+
+/// Rendering settings from the `Xft.*` X resources.
+///
+/// Each field is `None` if the corresponding resource is not set.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct XftSettings {
+    /// `Xft.dpi`: the resolution of the display, in dots per inch.
+    pub dpi: Option<f32>,
+    /// `Xft.antialias`: whether glyphs are antialiased.
+    pub antialias: Option<bool>,
+    /// `Xft.hinting`: whether glyphs are hinted at all.
+    pub hinting: Option<bool>,
+    /// `Xft.hintstyle`: how strongly glyphs are hinted.
+    pub hinting_style: Option<HintingStyle>,
+    /// `Xft.rgba`: the physical layout of the display's subpixels.
+    pub subpixel_order: Option<SubpixelOrder>,
+}
+
+impl XftSettings {
+    /// Reads the `Xft.*` resources of the current user from `~/.Xresources`, or from
+    /// `~/.Xdefaults` if that doesn't exist.
+    ///
+    /// The X server isn't contacted, so resources loaded into it by other means are missed; use
+    /// `query_x_server()` for those.
+    pub fn system() -> XftSettings {
+        if let Some(home) = env::var_os("HOME") {
+            for file_name in &[".Xresources", ".Xdefaults"] {
+                if let Ok(resources) = fs::read_to_string(PathBuf::from(&home).join(file_name)) {
+                    return XftSettings::parse(&resources);
+                }
+            }
+        }
+
+        XftSettings::default()
+    }
+
+    /// Queries the `Xft.*` resources of the running X server by running `xrdb -query` in a
+    /// subprocess.
+    ///
+    /// Returns `None` if `DISPLAY` isn't set or `xrdb` can't be run. Nothing in this crate calls
+    /// this, since spawning a process is slow and not always allowed; apply the result to a
+    /// `RenderingConfig` to use it.
+    pub fn query_x_server() -> Option<XftSettings> {
+        env::var_os("DISPLAY")?;
+        let output = Command::new("xrdb").arg("-query").output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(XftSettings::parse(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Parses the `Xft.*` entries out of X resources in `xrdb` format.
+    ///
+    /// Unknown resources and values are ignored.
+    pub fn parse(resources: &str) -> XftSettings {
+        let mut settings = XftSettings::default();
+        for line in resources.lines() {
+            let line = line.trim();
+            if line.starts_with('!') || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = match line.split_once(':') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => continue,
+            };
+            let name = match name
+                .strip_prefix("Xft.")
+                .or_else(|| name.strip_prefix("Xft*"))
+            {
+                Some(name) => name,
+                None => continue,
+            };
+
+            match name {
+                "dpi" => settings.dpi = value.parse().ok().filter(|dpi: &f32| *dpi > 0.0),
+                "antialias" => settings.antialias = parse_bool(value),
+                "hinting" => settings.hinting = parse_bool(value),
+                "hintstyle" => {
+                    settings.hinting_style = match value {
+                        "hintnone" => Some(HintingStyle::None),
+                        "hintslight" => Some(HintingStyle::Slight),
+                        "hintmedium" => Some(HintingStyle::Medium),
+                        "hintfull" => Some(HintingStyle::Full),
+                        _ => None,
+                    }
+                }
+                "rgba" => {
+                    settings.subpixel_order = match value {
+                        "rgb" => Some(SubpixelOrder::Rgb),
+                        "bgr" => Some(SubpixelOrder::Bgr),
+                        "vrgb" => Some(SubpixelOrder::VRgb),
+                        "vbgr" => Some(SubpixelOrder::VBgr),
+                        "none" => Some(SubpixelOrder::None),
+                        _ => None,
+                    }
+                }
+                _ => {}
+            }
+        }
+        settings
+    }
+
+    /// Overrides the settings in `config` with those that are set here.
+    pub fn apply(&self, config: &mut RenderingConfig) {
+        if let Some(dpi) = self.dpi {
+            config.dpi = dpi;
+        }
+        if let Some(subpixel_order) = self.subpixel_order {
+            config.subpixel_order = subpixel_order;
+        }
+        let antialias = self
+            .antialias
+            .unwrap_or(config.rasterization_options != RasterizationOptions::Bilevel);
+        config.set_antialias(antialias);
+
+        match (self.hinting, self.hinting_style) {
+            (Some(false), _) => config.hinting_style = HintingStyle::None,
+            (_, Some(hinting_style)) => config.hinting_style = hinting_style,
+            // Fontconfig hints fully if hinting is on and no style is given.
+            (Some(true), None) if config.hinting_style == HintingStyle::None => {
+                config.hinting_style = HintingStyle::Full
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match &*value.to_ascii_lowercase() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(target_family = "windows")]
+mod platform {
+    use std::ptr;
+    use winapi::shared::minwindef::{BOOL, DWORD, UINT};
+    use winapi::um::winnt::LPCWSTR;
+    use winapi::um::winreg::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+    use winapi::um::winuser::{self, SystemParametersInfoW};
+
+    use super::{HintingStyle, RenderingConfig, SubpixelOrder, Theme};
+    use crate::canvas::RasterizationOptions;
+
+    pub(super) fn read_system_config(config: &mut RenderingConfig, _: &str) {
+        // DirectWrite hints vertically only.
+        config.hinting_style = HintingStyle::Slight;
+
+        let mut smoothing: BOOL = 0;
+        if get_parameter(winuser::SPI_GETFONTSMOOTHING, &mut smoothing) && smoothing == 0 {
+            config.rasterization_options = RasterizationOptions::Bilevel;
+        } else {
+            let mut smoothing_type: UINT = 0;
+            if get_parameter(winuser::SPI_GETFONTSMOOTHINGTYPE, &mut smoothing_type)
+                && smoothing_type == winuser::FE_FONTSMOOTHINGCLEARTYPE
+            {
+                config.rasterization_options = RasterizationOptions::SubpixelAa;
+            }
+        }
+
+        let mut orientation: UINT = 0;
+        if get_parameter(winuser::SPI_GETFONTSMOOTHINGORIENTATION, &mut orientation) {
+            config.subpixel_order = match orientation {
+                winuser::FE_FONTSMOOTHINGORIENTATIONBGR => SubpixelOrder::Bgr,
+                _ => SubpixelOrder::Rgb,
+            };
+        }
+
+        // The ClearType contrast is the gamma times 1000, from 1000 to 2200.
+        let mut contrast: UINT = 0;
+        if get_parameter(winuser::SPI_GETFONTSMOOTHINGCONTRAST, &mut contrast) && contrast != 0 {
+            config.gamma = contrast as f32 / 1000.0;
+        }
+
+        if read_registry_dword(
+            "Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize",
+            "AppsUseLightTheme",
+        ) == Some(0)
+        {
+            config.theme = Theme::Dark;
+        }
+    }
+
+    fn get_parameter<T>(action: UINT, value: &mut T) -> bool {
+        unsafe { SystemParametersInfoW(action, 0, value as *mut T as *mut _, 0) != 0 }
+    }
+
+    fn read_registry_dword(subkey: &str, value_name: &str) -> Option<DWORD> {
+        let subkey = to_wide(subkey);
+        let value_name = to_wide(value_name);
+        let mut value: DWORD = 0;
+        let mut size = std::mem::size_of::<DWORD>() as DWORD;
+        let result = unsafe {
+            RegGetValueW(
+                HKEY_CURRENT_USER,
+                subkey.as_ptr() as LPCWSTR,
+                value_name.as_ptr() as LPCWSTR,
+                RRF_RT_REG_DWORD,
+                ptr::null_mut(),
+                &mut value as *mut DWORD as *mut _,
+                &mut size,
+            )
+        };
+        if result == 0 {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn to_wide(string: &str) -> Vec<u16> {
+        string.encode_utf16().chain(Some(0)).collect()
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod platform {
+    use core_foundation::base::{CFType, TCFType};
+    use core_foundation::number::CFNumber;
+    use core_foundation::string::{CFString, CFStringRef};
+    use std::os::raw::c_void;
+
+    use super::{RenderingConfig, Theme};
+    use crate::canvas::RasterizationOptions;
+
+    extern "C" {
+        static kCFPreferencesCurrentApplication: CFStringRef;
+
+        fn CFPreferencesCopyAppValue(
+            key: CFStringRef,
+            application_id: CFStringRef,
+        ) -> *const c_void;
+    }
+
+    pub(super) fn read_system_config(config: &mut RenderingConfig, _: &str) {
+        config.dpi = 72.0;
+
+        // `AppleFontSmoothing` is absent by default, which means smoothing is on. macOS no longer
+        // performs subpixel antialiasing, so smoothing always means grayscale.
+        let font_smoothing = copy_preference("AppleFontSmoothing")
+            .and_then(|value| value.downcast::<CFNumber>())
+            .and_then(|value| value.to_i32());
+        if font_smoothing == Some(0) {
+            config.rasterization_options = RasterizationOptions::Bilevel;
+        }
+
+        let interface_style = copy_preference("AppleInterfaceStyle")
+            .and_then(|value| value.downcast::<CFString>())
+            .map(|value| value.to_string());
+        if interface_style.as_deref() == Some("Dark") {
+            config.theme = Theme::Dark;
+        }
+    }
+
+    fn copy_preference(key: &str) -> Option<CFType> {
+        let key = CFString::new(key);
+        unsafe {
+            let value = CFPreferencesCopyAppValue(
+                key.as_concrete_TypeRef(),
+                kCFPreferencesCurrentApplication,
+            );
+            if value.is_null() {
+                None
+            } else {
+                Some(CFType::wrap_under_create_rule(value))
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use super::RenderingConfig;
+
+    // Pages can't read the system's rendering settings; browsers expose only a few, such as
+    // `prefers-color-scheme` and `devicePixelRatio`, which the page must pass in itself.
+    pub(super) fn read_system_config(_: &mut RenderingConfig, _: &str) {}
+}
+
+#[cfg(not(any(
+    target_family = "windows",
+    target_os = "macos",
+    target_os = "ios",
+    target_arch = "wasm32"
+)))]
+mod platform {
+    #[cfg(feature = "source")]
+    use lazy_static::lazy_static;
+    use std::env;
+    #[cfg(feature = "source")]
+    use std::sync::Mutex;
+
+    use super::{RenderingConfig, Theme, XftSettings};
+    #[cfg(feature = "source")]
+    use crate::sources::fontconfig::FontconfigSource;
+
+    #[cfg(feature = "source")]
+    lazy_static! {
+        // Loading fontconfig's configuration scans the installed fonts, so it is only done once.
+        static ref FONTCONFIG: Mutex<FontconfigSource> = Mutex::new(FontconfigSource::new());
+    }
+
+    pub(super) fn read_system_config(config: &mut RenderingConfig, family_name: &str) {
+        // GTK themes select their dark variant with a `:dark` suffix, e.g. `Adwaita:dark`.
+        if let Ok(gtk_theme) = env::var("GTK_THEME") {
+            if gtk_theme.to_ascii_lowercase().ends_with(":dark") {
+                config.theme = Theme::Dark;
+            }
+        }
+
+        let xft_settings = XftSettings::system();
+
+        // Fontconfig takes the X resources as defaults and may override them.
+        #[cfg(feature = "source")]
+        FONTCONFIG
+            .lock()
+            .unwrap()
+            .read_rendering_config(family_name, &xft_settings, config);
+        #[cfg(not(feature = "source"))]
+        {
+            let _ = family_name;
+            xft_settings.apply(config);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HintingStyle, RenderingConfig, SubpixelOrder, XftSettings};
+    use crate::canvas::RasterizationOptions;
+
+    #[test]
+    fn test_xft_settings() {
+        let resources = "! Comment\nXft.dpi:\t144\nXft.antialias: 1\nXft.hintstyle: hintslight\n\
+                         Xft.rgba: bgr\nXcursor.size: 24\n";
+        let settings = XftSettings::parse(resources);
+        assert_eq!(settings.dpi, Some(144.0));
+        assert_eq!(settings.hinting, None);
+
+        let mut config = RenderingConfig::default();
+        settings.apply(&mut config);
+        assert_eq!(
+            config.rasterization_options,
+            RasterizationOptions::SubpixelAa
+        );
+        assert_eq!(config.hinting_style, HintingStyle::Slight);
+        assert_eq!(config.subpixel_order, SubpixelOrder::Bgr);
+    }
+}
@@ -0,0 +1,268 @@
+// font-kit/src/script.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Writing systems that a font supports.
+//!
+//! `Font::scripts()` decides which scripts a font supports by checking how many of each script's
+//! basic letters the `cmap` table covers, and by looking for the script in the `GSUB` and `GPOS`
+//! tables. A script counts as supported if the font covers at least half of its basic letters, or
+//! if the layout tables name it and the font covers at least one of its letters.
+
+use std::fmt::{self, Debug, Display, Formatter};
+
+use crate::coverage::UnicodeRangeSet;
+
+/// A Unicode script, as reported by `Font::scripts()`.
+///
+/// Scripts are ordered roughly as they are in Unicode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum UnicodeScript {
+    /// The Latin script.
+    Latin,
+    /// The Greek script.
+    Greek,
+    /// The Cyrillic script.
+    Cyrillic,
+    /// The Armenian script.
+    Armenian,
+    /// The Hebrew script.
+    Hebrew,
+    /// The Arabic script.
+    Arabic,
+    /// The Syriac script.
+    Syriac,
+    /// The Thaana script, used for Dhivehi.
+    Thaana,
+    /// The Devanagari script.
+    Devanagari,
+    /// The Bengali script.
+    Bengali,
+    /// The Gurmukhi script.
+    Gurmukhi,
+    /// The Gujarati script.
+    Gujarati,
+    /// The Oriya script.
+    Oriya,
+    /// The Tamil script.
+    Tamil,
+    /// The Telugu script.
+    Telugu,
+    /// The Kannada script.
+    Kannada,
+    /// The Malayalam script.
+    Malayalam,
+    /// The Sinhala script.
+    Sinhala,
+    /// The Thai script.
+    Thai,
+    /// The Lao script.
+    Lao,
+    /// The Tibetan script.
+    Tibetan,
+    /// The Myanmar script.
+    Myanmar,
+    /// The Georgian script.
+    Georgian,
+    /// The Hangul script, used for Korean.
+    Hangul,
+    /// The Ethiopic script.
+    Ethiopic,
+    /// The Cherokee script.
+    Cherokee,
+    /// The Khmer script.
+    Khmer,
+    /// The Mongolian script.
+    Mongolian,
+    /// Hiragana, used for Japanese.
+    Hiragana,
+    /// Katakana, used for Japanese.
+    Katakana,
+    /// Bopomofo, used for Chinese phonetics.
+    Bopomofo,
+    /// Han ideographs, used for Chinese, Japanese, and Korean.
+    Han,
+}
+
+impl Display for UnicodeScript {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+// Every script, in order.
+static ALL_SCRIPTS: [UnicodeScript; 32] = [
+    UnicodeScript::Latin,
+    UnicodeScript::Greek,
+    UnicodeScript::Cyrillic,
+    UnicodeScript::Armenian,
+    UnicodeScript::Hebrew,
+    UnicodeScript::Arabic,
+    UnicodeScript::Syriac,
+    UnicodeScript::Thaana,
+    UnicodeScript::Devanagari,
+    UnicodeScript::Bengali,
+    UnicodeScript::Gurmukhi,
+    UnicodeScript::Gujarati,
+    UnicodeScript::Oriya,
+    UnicodeScript::Tamil,
+    UnicodeScript::Telugu,
+    UnicodeScript::Kannada,
+    UnicodeScript::Malayalam,
+    UnicodeScript::Sinhala,
+    UnicodeScript::Thai,
+    UnicodeScript::Lao,
+    UnicodeScript::Tibetan,
+    UnicodeScript::Myanmar,
+    UnicodeScript::Georgian,
+    UnicodeScript::Hangul,
+    UnicodeScript::Ethiopic,
+    UnicodeScript::Cherokee,
+    UnicodeScript::Khmer,
+    UnicodeScript::Mongolian,
+    UnicodeScript::Hiragana,
+    UnicodeScript::Katakana,
+    UnicodeScript::Bopomofo,
+    UnicodeScript::Han,
+];
+
+impl UnicodeScript {
+    /// Returns the OpenType script tags of this script, packed big-endian into `u32`s.
+    ///
+    /// Indic scripts and Myanmar have two tags, for the old and new shaping models; Hiragana and
+    /// Katakana share the `kana` tag.
+    pub fn opentype_tags(self) -> Vec<u32> {
+        self.tags()
+            .iter()
+            .map(|&&tag| u32::from_be_bytes(tag))
+            .collect()
+    }
+
+    fn tags(self) -> &'static [&'static [u8; 4]] {
+        match self {
+            UnicodeScript::Latin => &[b"latn"],
+            UnicodeScript::Greek => &[b"grek"],
+            UnicodeScript::Cyrillic => &[b"cyrl"],
+            UnicodeScript::Armenian => &[b"armn"],
+            UnicodeScript::Hebrew => &[b"hebr"],
+            UnicodeScript::Arabic => &[b"arab"],
+            UnicodeScript::Syriac => &[b"syrc"],
+            UnicodeScript::Thaana => &[b"thaa"],
+            UnicodeScript::Devanagari => &[b"deva", b"dev2"],
+            UnicodeScript::Bengali => &[b"beng", b"bng2"],
+            UnicodeScript::Gurmukhi => &[b"guru", b"gur2"],
+            UnicodeScript::Gujarati => &[b"gujr", b"gjr2"],
+            UnicodeScript::Oriya => &[b"orya", b"ory2"],
+            UnicodeScript::Tamil => &[b"taml", b"tml2"],
+            UnicodeScript::Telugu => &[b"telu", b"tel2"],
+            UnicodeScript::Kannada => &[b"knda", b"knd2"],
+            UnicodeScript::Malayalam => &[b"mlym", b"mlm2"],
+            UnicodeScript::Sinhala => &[b"sinh"],
+            UnicodeScript::Thai => &[b"thai"],
+            UnicodeScript::Lao => &[b"lao "],
+            UnicodeScript::Tibetan => &[b"tibt"],
+            UnicodeScript::Myanmar => &[b"mymr", b"mym2"],
+            UnicodeScript::Georgian => &[b"geor"],
+            UnicodeScript::Hangul => &[b"hang"],
+            UnicodeScript::Ethiopic => &[b"ethi"],
+            UnicodeScript::Cherokee => &[b"cher"],
+            UnicodeScript::Khmer => &[b"khmr"],
+            UnicodeScript::Mongolian => &[b"mong"],
+            UnicodeScript::Hiragana => &[b"kana"],
+            UnicodeScript::Katakana => &[b"kana"],
+            UnicodeScript::Bopomofo => &[b"bopo"],
+            UnicodeScript::Han => &[b"hani"],
+        }
+    }
+
+    // The inclusive ranges of the script's basic letters.
+    fn letters(self) -> &'static [(u32, u32)] {
+        match self {
+            UnicodeScript::Latin => &[(0x41, 0x5a), (0x61, 0x7a)],
+            UnicodeScript::Greek => &[(0x391, 0x3a9), (0x3b1, 0x3c9)],
+            UnicodeScript::Cyrillic => &[(0x410, 0x44f)],
+            UnicodeScript::Armenian => &[(0x531, 0x556), (0x561, 0x586)],
+            UnicodeScript::Hebrew => &[(0x5d0, 0x5ea)],
+            UnicodeScript::Arabic => &[(0x621, 0x64a)],
+            UnicodeScript::Syriac => &[(0x710, 0x72c)],
+            UnicodeScript::Thaana => &[(0x780, 0x7a5)],
+            UnicodeScript::Devanagari => &[(0x905, 0x939)],
+            UnicodeScript::Bengali => &[(0x985, 0x9b9)],
+            UnicodeScript::Gurmukhi => &[(0xa05, 0xa39)],
+            UnicodeScript::Gujarati => &[(0xa85, 0xab9)],
+            UnicodeScript::Oriya => &[(0xb05, 0xb39)],
+            UnicodeScript::Tamil => &[(0xb85, 0xbb9)],
+            UnicodeScript::Telugu => &[(0xc05, 0xc39)],
+            UnicodeScript::Kannada => &[(0xc85, 0xcb9)],
+            UnicodeScript::Malayalam => &[(0xd05, 0xd39)],
+            UnicodeScript::Sinhala => &[(0xd85, 0xdc6)],
+            UnicodeScript::Thai => &[(0xe01, 0xe2e)],
+            UnicodeScript::Lao => &[(0xe81, 0xeae)],
+            UnicodeScript::Tibetan => &[(0xf40, 0xf6c)],
+            UnicodeScript::Myanmar => &[(0x1000, 0x102a)],
+            UnicodeScript::Georgian => &[(0x10d0, 0x10fa)],
+            UnicodeScript::Hangul => &[(0xac00, 0xad00)],
+            UnicodeScript::Ethiopic => &[(0x1200, 0x1248)],
+            UnicodeScript::Cherokee => &[(0x13a0, 0x13f4)],
+            UnicodeScript::Khmer => &[(0x1780, 0x17a2)],
+            UnicodeScript::Mongolian => &[(0x1820, 0x1877)],
+            UnicodeScript::Hiragana => &[(0x3041, 0x3096)],
+            UnicodeScript::Katakana => &[(0x30a1, 0x30fa)],
+            UnicodeScript::Bopomofo => &[(0x3105, 0x312f)],
+            UnicodeScript::Han => &[(0x4e00, 0x4fff)],
+        }
+    }
+}
+
+/// Returns the scripts that a font supports, given its `cmap` coverage and the script tags of its
+/// `GSUB` and `GPOS` tables.
+pub(crate) fn supported_scripts(
+    coverage: &UnicodeRangeSet,
+    layout_scripts: &[u32],
+) -> Vec<UnicodeScript> {
+    ALL_SCRIPTS
+        .iter()
+        .copied()
+        .filter(|script| {
+            let letters = script.letters();
+            let total: u32 = letters.iter().map(|&(start, end)| end - start + 1).sum();
+            let covered = letters
+                .iter()
+                .flat_map(|&(start, end)| start..=end)
+                .filter(|&codepoint| coverage.contains_codepoint(codepoint))
+                .count() as u32;
+            let in_layout_tables = script
+                .opentype_tags()
+                .iter()
+                .any(|tag| layout_scripts.contains(tag));
+            covered * 2 >= total || (in_layout_tables && covered > 0)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::UnicodeScript;
+    use crate::font::Font;
+    use crate::test_fixtures::EB_GARAMOND;
+
+    #[test]
+    fn test_scripts() {
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let scripts = font.scripts();
+        assert!(scripts.contains(&UnicodeScript::Latin));
+        assert!(!scripts.contains(&UnicodeScript::Arabic));
+        assert!(!scripts.contains(&UnicodeScript::Han));
+        assert_eq!(UnicodeScript::Latin.to_string(), "Latin");
+        assert_eq!(
+            UnicodeScript::Devanagari.opentype_tags(),
+            vec![u32::from_be_bytes(*b"deva"), u32::from_be_bytes(*b"dev2")]
+        );
+    }
+}
@@ -0,0 +1,159 @@
+// font-kit/src/shaping.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Turning text into positioned glyphs.
+//!
+//! `Font::shape()` runs text through [rustybuzz], a port of HarfBuzz, so that complex scripts
+//! such as Arabic and the Indic scripts are joined, reordered, and have their marks positioned
+//! as they should be. This is available with the `shaping` feature.
+//!
+//! The script, language, and direction of the text are guessed from its characters, except that
+//! `ShapeOptions::right_to_left` forces right-to-left layout. Text in several scripts or directions should
+//! be split into runs, for example with `layout::bidi_runs()`, and shaped a run at
+//! a time.
+//!
+//! [rustybuzz]: https://github.com/RazrFalcon/rustybuzz
+
+use pathfinder_geometry::vector::Vector2F;
+use rustybuzz::{Direction, Feature, UnicodeBuffer};
+use ttf_parser::{Face, Tag};
+
+pub use crate::layout::PositionedGlyph;
+
+// The features that `ShapeOptions::default()` enables, which rustybuzz applies unless they are
+// turned off.
+const DEFAULT_FEATURES: [&[u8; 4]; 5] = [b"ccmp", b"liga", b"clig", b"calt", b"kern"];
+
+/// Options that control how text is shaped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShapeOptions {
+    /// The `GSUB` and `GPOS` features to apply, as OpenType tags packed big-endian into a `u32`.
+    ///
+    /// The default enables the features that text should always be shaped with: `ccmp`, `liga`,
+    /// `clig`, `calt`, and `kern`; leaving one of those out turns it off. Features that a script
+    /// requires, such as the Arabic joining forms and mark positioning, are always applied.
+    pub features: Vec<u32>,
+    /// Whether to lay the text out right to left. Otherwise its direction is that of its script,
+    /// so Arabic and Hebrew text runs right to left either way.
+    ///
+    /// The glyphs are returned in visual order, so for right-to-left text the glyph of the last
+    /// character comes first.
+    pub right_to_left: bool,
+}
+
+impl Default for ShapeOptions {
+    fn default() -> ShapeOptions {
+        ShapeOptions {
+            features: DEFAULT_FEATURES
+                .iter()
+                .map(|&&tag| u32::from_be_bytes(tag))
+                .collect(),
+            right_to_left: false,
+        }
+    }
+}
+
+/// Shapes a run of text in a single font.
+pub(crate) fn shape(face: &Face, text: &str, options: &ShapeOptions) -> Vec<PositionedGlyph> {
+    let face = rustybuzz::Face::from_face(face.clone());
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    if options.right_to_left {
+        buffer.set_direction(Direction::RightToLeft);
+    }
+
+    let disabled = DEFAULT_FEATURES
+        .iter()
+        .map(|&&tag| u32::from_be_bytes(tag))
+        .filter(|tag| !options.features.contains(tag))
+        .map(|tag| Feature::new(Tag(tag), 0, ..));
+    let enabled = options
+        .features
+        .iter()
+        .map(|&tag| Feature::new(Tag(tag), 1, ..));
+    let features: Vec<Feature> = enabled.chain(disabled).collect();
+
+    let glyph_buffer = rustybuzz::shape(&face, &features, buffer);
+    let mut pen = Vector2F::zero();
+    glyph_buffer
+        .glyph_infos()
+        .iter()
+        .zip(glyph_buffer.glyph_positions())
+        .map(|(info, position)| {
+            let glyph = PositionedGlyph {
+                glyph_id: info.glyph_id,
+                cluster: info.cluster,
+                position: pen,
+                advance: Vector2F::new(position.x_advance as f32, position.y_advance as f32),
+                offset: Vector2F::new(position.x_offset as f32, position.y_offset as f32),
+            };
+            pen += glyph.advance;
+            glyph
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::ShapeOptions;
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use crate::test_fixtures::{DEJAVU_SANS_MONO, EB_GARAMOND};
+
+    #[test]
+    fn test_shape() {
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let options = ShapeOptions {
+            features: vec![u32::from_be_bytes(*b"dlig")],
+            right_to_left: false,
+        };
+        let glyphs = font.shape("Thé", &options);
+        assert_eq!(glyphs.len(), 2);
+        assert_eq!(glyphs[0].cluster, 0);
+        assert_eq!(glyphs[1].cluster, 2);
+
+        let unkerned = font.shape(
+            "AV",
+            &ShapeOptions {
+                features: vec![],
+                right_to_left: false,
+            },
+        );
+        let kerned = font.shape("AV", &ShapeOptions::default());
+        assert_eq!(
+            unkerned[0].advance.x(),
+            font.advance(unkerned[0].glyph_id).unwrap().x()
+        );
+        assert!(kerned[0].advance.x() < unkerned[0].advance.x());
+
+        let reversed = font.shape(
+            "AV",
+            &ShapeOptions {
+                right_to_left: true,
+                ..ShapeOptions::default()
+            },
+        );
+        assert_eq!(reversed[0].glyph_id, kerned[1].glyph_id);
+        assert_eq!(reversed[1].position.x(), reversed[0].advance.x());
+    }
+
+    #[test]
+    fn test_shape_arabic() {
+        let font = Font::from_bytes_borrowed(DEJAVU_SANS_MONO, 0).unwrap();
+        // Beh, alone and then joined to its neighbors. Arabic runs right to left without being
+        // asked to.
+        let isolated = font.shape("\u{628}", &ShapeOptions::default());
+        let joined = font.shape("\u{628}\u{628}\u{628}", &ShapeOptions::default());
+        assert_eq!(joined.len(), 3);
+        assert_eq!(joined[0].cluster, 4);
+        assert_ne!(joined[1].glyph_id, isolated[0].glyph_id);
+    }
+}
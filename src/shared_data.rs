@@ -0,0 +1,236 @@
+// font-kit/src/shared_data.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Sharing of font file contents and parsed faces between fonts loaded from the same file.
+//!
+//! A font collection such as a CJK `.ttc` can be tens of megabytes and contain a dozen faces.
+//! Loading each face separately would otherwise read the whole file into memory once per face,
+//! and loading the same face twice would parse its tables twice. Instead, the contents of each
+//! file and the faces parsed from it are kept in a registry of weak references, keyed by a
+//! fingerprint of the file, and reused for as long as any font loaded from it is alive. `stats()`
+//! reports how much is being shared.
+
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::path::Path;
+#[cfg(not(unix))]
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::SystemTime;
+
+use crate::error::FontLoadingError;
+use crate::font::SharedFace;
+use crate::utils;
+
+lazy_static! {
+    static ref REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+        files: HashMap::new(),
+        bytes_reused: 0,
+    });
+}
+
+/// Counts of the file contents and faces shared between loaded fonts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SharedDataStats {
+    /// The number of files whose contents are held by at least one loaded font.
+    pub files: usize,
+    /// The total size in bytes of those files' contents.
+    pub bytes: u64,
+    /// The number of parsed faces held by at least one loaded font.
+    pub faces: usize,
+    /// The number of bytes that loading fonts has not had to read again because the file's
+    /// contents were already loaded, since the process started.
+    pub bytes_reused: u64,
+}
+
+/// Returns counts of the file contents and faces currently shared between loaded fonts.
+pub fn stats() -> SharedDataStats {
+    let registry = REGISTRY.lock().unwrap();
+    let mut stats = SharedDataStats {
+        bytes_reused: registry.bytes_reused,
+        ..SharedDataStats::default()
+    };
+    for file in registry.files.values() {
+        if let Some(font_data) = file.font_data.upgrade() {
+            stats.files += 1;
+            stats.bytes += font_data.len() as u64;
+        }
+        stats.faces += file
+            .faces
+            .values()
+            .filter(|face| face.strong_count() > 0)
+            .count();
+    }
+    stats
+}
+
+struct Registry {
+    files: HashMap<FileFingerprint, SharedFile>,
+    bytes_reused: u64,
+}
+
+// What is shared of one file. Faces are keyed by their index in the file.
+#[derive(Default)]
+struct SharedFile {
+    font_data: Weak<Vec<u8>>,
+    faces: HashMap<u32, Weak<SharedFace>>,
+}
+
+impl SharedFile {
+    fn is_dead(&self) -> bool {
+        self.font_data.strong_count() == 0
+            && self.faces.values().all(|face| face.strong_count() == 0)
+    }
+}
+
+// Identifies the contents of a file. The size and modification time guard against reusing stale
+// contents after the file has been replaced.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+struct FileFingerprint {
+    id: FileId,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+enum FileId {
+    #[cfg(unix)]
+    Inode { device: u64, inode: u64 },
+    #[cfg(not(unix))]
+    Path(PathBuf),
+}
+
+impl FileFingerprint {
+    // Returns `None` if the file cannot be identified, in which case its contents aren't shared.
+    fn new(file: &File, path: Option<&Path>) -> Option<FileFingerprint> {
+        let metadata = file.metadata().ok()?;
+        #[cfg(unix)]
+        let id = {
+            use std::os::unix::fs::MetadataExt;
+            let _ = path;
+            FileId::Inode {
+                device: metadata.dev(),
+                inode: metadata.ino(),
+            }
+        };
+        #[cfg(not(unix))]
+        let id = FileId::Path(path?.canonicalize().ok()?);
+        Some(FileFingerprint {
+            id,
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// Returns the face at `font_index` in `file`, reusing the face or file contents of a font that
+/// is already loaded from the same file if there is one.
+///
+/// `parse` parses a face from the file's contents when there's no face to reuse. `path` is the
+/// path that `file` was opened from, if known. Some platforms need it to identify the file.
+pub(crate) fn read_shared_face<F>(
+    file: &mut File,
+    path: Option<&Path>,
+    font_index: u32,
+    parse: F,
+) -> Result<Arc<SharedFace>, FontLoadingError>
+where
+    F: FnOnce(Arc<Vec<u8>>) -> Result<SharedFace, FontLoadingError>,
+{
+    let fingerprint = match FileFingerprint::new(file, path) {
+        Some(fingerprint) => fingerprint,
+        None => {
+            file.seek(SeekFrom::Start(0))?;
+            return parse(Arc::new(utils::slurp_file(file)?)).map(Arc::new);
+        }
+    };
+
+    let shared_font_data = {
+        let mut registry = REGISTRY.lock().unwrap();
+        let shared = registry.files.get(&fingerprint).map(|file| {
+            let face = file.faces.get(&font_index).and_then(Weak::upgrade);
+            (face, file.font_data.upgrade())
+        });
+        match shared {
+            Some((Some(face), _)) => {
+                registry.bytes_reused += fingerprint.len;
+                return Ok(face);
+            }
+            Some((None, Some(font_data))) => {
+                registry.bytes_reused += fingerprint.len;
+                Some(font_data)
+            }
+            _ => None,
+        }
+    };
+
+    // The file is read and parsed without the lock held, so that loads of other files aren't
+    // blocked. If two threads load the same face at once, the first one stored wins.
+    let font_data = match shared_font_data {
+        Some(font_data) => font_data,
+        None => {
+            file.seek(SeekFrom::Start(0))?;
+            Arc::new(utils::slurp_file(file)?)
+        }
+    };
+    let face = Arc::new(parse(Arc::clone(&font_data))?);
+
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.files.retain(|_, file| !file.is_dead());
+    let file = registry.files.entry(fingerprint).or_default();
+    if let Some(stored_face) = file.faces.get(&font_index).and_then(Weak::upgrade) {
+        return Ok(stored_face);
+    }
+    if file.font_data.strong_count() == 0 {
+        file.font_data = Arc::downgrade(&font_data);
+    }
+    file.faces.insert(font_index, Arc::downgrade(&face));
+    Ok(face)
+}
+
+#[cfg(test)]
+mod test {
+    use super::stats;
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use std::sync::Arc;
+
+    static EB_GARAMOND_COLLECTION_PATH: &str = "resources/tests/eb-garamond/EBGaramond12.otc";
+
+    #[test]
+    fn test_collection_faces_share_font_data() {
+        let regular = Font::from_path(EB_GARAMOND_COLLECTION_PATH, 0).unwrap();
+        let italic = Font::from_path(EB_GARAMOND_COLLECTION_PATH, 1).unwrap();
+        assert_ne!(regular.postscript_name(), italic.postscript_name());
+        assert!(Arc::ptr_eq(
+            &regular.copy_font_data().unwrap(),
+            &italic.copy_font_data().unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_faces_are_shared() {
+        let path = "resources/tests/inconsolata/Inconsolata-Regular.ttf";
+        let len = std::fs::metadata(path).unwrap().len();
+        let first = Font::from_path(path, 0).unwrap();
+        let bytes_reused = stats().bytes_reused;
+        let second = Font::from_path(path, 0).unwrap();
+        assert!(first.shares_face_with(&second));
+
+        // Other tests load fonts concurrently, so the counts are only bounded from below.
+        let stats = stats();
+        assert!(stats.bytes_reused >= bytes_reused + len);
+        assert!(stats.files >= 1);
+        assert!(stats.faces >= 1);
+        assert!(stats.bytes >= len);
+    }
+}
@@ -11,15 +11,22 @@
 //! A database of installed fonts that can be queried.
 
 use crate::error::SelectionError;
-use crate::family::Family;
+use crate::fallback::{self, FallbackMeasurement};
 use crate::family_handle::FamilyHandle;
 use crate::family_name::FamilyName;
-use crate::font::Font;
+use crate::font_cache::FontCache;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::font_changes::{self, FontChangeReceiver};
 use crate::handle::Handle;
-use crate::loader::Loader;
-use crate::matching;
+use crate::matching::{self, MatchScorer};
 use crate::properties::Properties;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::sources::fs as fs_source;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+use crate::task::Task;
+use lazy_static::lazy_static;
 use std::any::Any;
+use std::sync::Arc;
 
 #[cfg(all(
     any(target_os = "macos", target_os = "ios"),
@@ -41,31 +48,133 @@ pub use crate::sources::directwrite::DirectWriteSource as SystemSource;
 pub use crate::sources::fontconfig::FontconfigSource as SystemSource;
 #[cfg(all(target_os = "android", not(feature = "source-fontconfig-default")))]
 pub use crate::sources::fs::FsSource as SystemSource;
+#[cfg(target_arch = "wasm32")]
+pub use crate::sources::web::WebSource as SystemSource;
 
-// FIXME(pcwalton): These could expand to multiple fonts, and they could be language-specific.
-#[cfg(any(target_family = "windows", target_os = "macos", target_os = "ios"))]
-const DEFAULT_FONT_FAMILY_SERIF: &'static str = "Times New Roman";
-#[cfg(any(target_family = "windows", target_os = "macos", target_os = "ios"))]
-const DEFAULT_FONT_FAMILY_SANS_SERIF: &'static str = "Arial";
-#[cfg(any(target_family = "windows", target_os = "macos", target_os = "ios"))]
-const DEFAULT_FONT_FAMILY_MONOSPACE: &'static str = "Courier New";
-#[cfg(any(target_family = "windows", target_os = "macos", target_os = "ios"))]
-const DEFAULT_FONT_FAMILY_CURSIVE: &'static str = "Comic Sans MS";
+// The families that each generic family resolves to, in order of preference. The first one that
+// a source contains is used.
+//
+// FIXME(pcwalton): These could be language-specific.
+#[cfg(target_family = "windows")]
+const GENERIC_FAMILIES_SERIF: &[&str] = &["Times New Roman", "Cambria"];
+#[cfg(target_family = "windows")]
+const GENERIC_FAMILIES_SANS_SERIF: &[&str] = &["Arial", "Segoe UI"];
+#[cfg(target_family = "windows")]
+const GENERIC_FAMILIES_MONOSPACE: &[&str] = &["Courier New", "Consolas"];
+#[cfg(target_family = "windows")]
+const GENERIC_FAMILIES_CURSIVE: &[&str] = &["Comic Sans MS"];
 #[cfg(target_family = "windows")]
-const DEFAULT_FONT_FAMILY_FANTASY: &'static str = "Impact";
+const GENERIC_FAMILIES_FANTASY: &[&str] = &["Impact"];
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const GENERIC_FAMILIES_SERIF: &[&str] = &["Times New Roman", "Times"];
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const GENERIC_FAMILIES_SANS_SERIF: &[&str] = &["Arial", "Helvetica"];
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const GENERIC_FAMILIES_MONOSPACE: &[&str] = &["Courier New", "Menlo", "Courier"];
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const GENERIC_FAMILIES_CURSIVE: &[&str] = &["Comic Sans MS", "Apple Chancery"];
 #[cfg(any(target_os = "macos", target_os = "ios"))]
-const DEFAULT_FONT_FAMILY_FANTASY: &'static str = "Papyrus";
+const GENERIC_FAMILIES_FANTASY: &[&str] = &["Papyrus"];
 
+// Fontconfig resolves the generic names itself. The concrete families are for other sources, such
+// as the filesystem source used on Android.
 #[cfg(not(any(target_family = "windows", target_os = "macos", target_os = "ios")))]
-const DEFAULT_FONT_FAMILY_SERIF: &str = "serif";
+const GENERIC_FAMILIES_SERIF: &[&str] = &[
+    "serif",
+    "DejaVu Serif",
+    "Liberation Serif",
+    "Noto Serif",
+    "Times New Roman",
+];
 #[cfg(not(any(target_family = "windows", target_os = "macos", target_os = "ios")))]
-const DEFAULT_FONT_FAMILY_SANS_SERIF: &str = "sans-serif";
+const GENERIC_FAMILIES_SANS_SERIF: &[&str] = &[
+    "sans-serif",
+    "Roboto",
+    "DejaVu Sans",
+    "Liberation Sans",
+    "Noto Sans",
+    "Arial",
+];
 #[cfg(not(any(target_family = "windows", target_os = "macos", target_os = "ios")))]
-const DEFAULT_FONT_FAMILY_MONOSPACE: &str = "monospace";
+const GENERIC_FAMILIES_MONOSPACE: &[&str] = &[
+    "monospace",
+    "Droid Sans Mono",
+    "DejaVu Sans Mono",
+    "Liberation Mono",
+    "Noto Sans Mono",
+    "Courier New",
+];
 #[cfg(not(any(target_family = "windows", target_os = "macos", target_os = "ios")))]
-const DEFAULT_FONT_FAMILY_CURSIVE: &str = "cursive";
+const GENERIC_FAMILIES_CURSIVE: &[&str] = &["cursive", "Dancing Script", "Comic Sans MS"];
 #[cfg(not(any(target_family = "windows", target_os = "macos", target_os = "ios")))]
-const DEFAULT_FONT_FAMILY_FANTASY: &str = "fantasy";
+const GENERIC_FAMILIES_FANTASY: &[&str] = &["fantasy", "Impact"];
+
+/// Returns the names of the families that a `FamilyName` may refer to, in order of preference.
+///
+/// A title resolves to itself, and generic families resolve to the platform defaults.
+pub(crate) fn family_names_for(family_name: &FamilyName) -> Vec<&str> {
+    match *family_name {
+        FamilyName::Title(ref title) => vec![title],
+        FamilyName::Serif => GENERIC_FAMILIES_SERIF.to_vec(),
+        FamilyName::SansSerif => GENERIC_FAMILIES_SANS_SERIF.to_vec(),
+        FamilyName::Monospace => GENERIC_FAMILIES_MONOSPACE.to_vec(),
+        FamilyName::Cursive => GENERIC_FAMILIES_CURSIVE.to_vec(),
+        FamilyName::Fantasy => GENERIC_FAMILIES_FANTASY.to_vec(),
+    }
+}
+
+lazy_static! {
+    static ref BUNDLED_FONT_DATA: Arc<Vec<u8>> = Arc::new(crate::font::DEJAVU_SANS_MONO.to_vec());
+}
+
+/// What to do when none of the requested families can be found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MissingFontPolicy {
+    /// Return `SelectionError::NotFound`, so that the caller can decide what to do.
+    #[default]
+    Error,
+    /// Return the installed font that best matches the properties in a default family, or failing
+    /// that, any installed font at all. This fails only if the source is empty.
+    AnyInstalled,
+    /// Return the font bundled with `font-kit`, DejaVu Sans Mono, which is always available. It is
+    /// monospaced, whatever family was requested, and covers Latin, Greek, and Cyrillic.
+    Bundled,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SystemSource {
+    /// Returns a channel that receives an event whenever fonts are installed or removed while the
+    /// application is running.
+    ///
+    /// The platform's font directories are watched with the operating system's file notifications
+    /// on a background thread, which exits once the receiver is dropped. Without the default
+    /// `watch` feature, they are checked every `font_changes::DEFAULT_POLL_INTERVAL` instead. Use
+    /// `font_changes::watch_directories_natively()` to watch other directories.
+    ///
+    /// The platforms' own font change notifications, such as `WM_FONTCHANGE` on Windows, Core
+    /// Text's font manager notifications on macOS, and fontconfig's configuration changes, aren't
+    /// used, so fonts activated without being installed into a font directory aren't reported.
+    ///
+    /// The system source doesn't update itself: create a new one when a change is reported.
+    pub fn subscribe_changes() -> FontChangeReceiver {
+        font_changes::subscribe(fs_source::default_font_directories())
+    }
+}
+
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+impl SystemSource {
+    /// Returns all fonts installed on the system, enumerating them on a background thread.
+    ///
+    /// The system source is created on the background thread too, since some platforms' sources
+    /// cannot be moved between threads. The returned task can be awaited from any async runtime.
+    /// If no background thread can be started, the task completes immediately with
+    /// `SelectionError::CannotAccessSource`.
+    pub fn all_fonts_async() -> Task<Result<Vec<Handle>, SelectionError>> {
+        Task::spawn(|| SystemSource::new().all_fonts())
+            .unwrap_or_else(|_| Task::ready(Err(SelectionError::CannotAccessSource)))
+    }
+}
 
 /// A database of installed fonts that can be queried.
 ///
@@ -88,12 +197,10 @@ pub trait Source: Any {
         // TODO(pcwalton): Optimize this by searching for families with similar names first.
         for family_name in self.all_families()? {
             if let Ok(family_handle) = self.select_family_by_name(&family_name) {
-                if let Ok(family) = Family::<Font>::from_handle(&family_handle) {
-                    for (handle, font) in family_handle.fonts().iter().zip(family.fonts().iter()) {
-                        if let Some(font_postscript_name) = font.postscript_name() {
-                            if font_postscript_name == postscript_name {
-                                return Ok((*handle).clone());
-                            }
+                for handle in family_handle.fonts() {
+                    if let Ok(info) = handle.info() {
+                        if info.postscript_name.as_deref() == Some(postscript_name) {
+                            return Ok(handle.clone());
                         }
                     }
                 }
@@ -109,14 +216,14 @@ pub trait Source: Any {
         &self,
         family_name: &FamilyName,
     ) -> Result<FamilyHandle, SelectionError> {
-        match *family_name {
-            FamilyName::Title(ref title) => self.select_family_by_name(title),
-            FamilyName::Serif => self.select_family_by_name(DEFAULT_FONT_FAMILY_SERIF),
-            FamilyName::SansSerif => self.select_family_by_name(DEFAULT_FONT_FAMILY_SANS_SERIF),
-            FamilyName::Monospace => self.select_family_by_name(DEFAULT_FONT_FAMILY_MONOSPACE),
-            FamilyName::Cursive => self.select_family_by_name(DEFAULT_FONT_FAMILY_CURSIVE),
-            FamilyName::Fantasy => self.select_family_by_name(DEFAULT_FONT_FAMILY_FANTASY),
+        for family_name in family_names_for(family_name) {
+            match self.select_family_by_name(family_name) {
+                Ok(family) => return Ok(family),
+                Err(SelectionError::NotFound) => {}
+                Err(err) => return Err(err),
+            }
         }
+        Err(SelectionError::NotFound)
     }
 
     /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
@@ -127,15 +234,75 @@ pub trait Source: Any {
         family_names: &[FamilyName],
         properties: &Properties,
     ) -> Result<Handle, SelectionError> {
-        for family_name in family_names {
-            if let Ok(family_handle) = self.select_family_by_generic_name(family_name) {
-                let candidates = self.select_descriptions_in_family(&family_handle)?;
-                if let Ok(index) = matching::find_best_match(&candidates, properties) {
-                    return Ok(family_handle.fonts[index].clone());
-                }
-            }
+        matching::select_best_match(self, family_names, properties)
+    }
+
+    /// Performs font matching like `select_best_match()`, but lets the caller choose what happens
+    /// if none of the families can be found.
+    fn select_best_match_with_policy(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+        policy: MissingFontPolicy,
+    ) -> Result<Handle, SelectionError> {
+        let error = match self.select_best_match(family_names, properties) {
+            Ok(handle) => return Ok(handle),
+            Err(error) => error,
+        };
+        match policy {
+            MissingFontPolicy::Error => Err(error),
+            MissingFontPolicy::AnyInstalled => self
+                .select_best_match(
+                    &[
+                        FamilyName::SansSerif,
+                        FamilyName::Serif,
+                        FamilyName::Monospace,
+                    ],
+                    properties,
+                )
+                .or_else(|_| {
+                    self.all_fonts()?
+                        .into_iter()
+                        .next()
+                        .ok_or(SelectionError::NotFound)
+                }),
+            MissingFontPolicy::Bundled => Ok(Handle::from_memory(BUNDLED_FONT_DATA.clone(), 0)),
         }
-        Err(SelectionError::NotFound)
+    }
+
+    /// Performs font matching like `select_best_match()`, but only considers the fonts in the
+    /// matched family that `scorer` ranks highest.
+    ///
+    /// See `MatchScorer` for how scores and properties interact.
+    #[inline]
+    fn select_best_match_with_scorer(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+        scorer: &dyn MatchScorer,
+    ) -> Result<Handle, SelectionError> {
+        matching::select_best_match_with_scorer(self, family_names, properties, scorer)
+    }
+
+    /// Measures `text` set in the best match for `family_names` and `properties`, falling back to
+    /// other fonts for the characters that font doesn't support.
+    ///
+    /// Fallback fonts are taken from the rest of `family_names`, as in a CSS `font-family` list,
+    /// and then from families that cover large parts of Unicode on this platform. They are
+    /// selected and loaded only when a character needs them, through `cache`, so that repeated
+    /// measurements reuse the loaded fonts. Returns the total advance in points and the font used
+    /// for each run of the text; runs that no font covers are set in the primary font.
+    ///
+    /// Returns `SelectionError::NotFound` if none of `family_names` can be loaded.
+    fn measure_with_fallback(
+        &self,
+        cache: &mut FontCache,
+        text: &str,
+        family_names: &[FamilyName],
+        properties: &Properties,
+        point_size: f32,
+    ) -> Result<FallbackMeasurement, SelectionError> {
+        fallback::measure_with_fallback(self, cache, text, family_names, properties, point_size)
     }
 
     #[doc(hidden)]
@@ -145,8 +312,8 @@ pub trait Source: Any {
     ) -> Result<Vec<Properties>, SelectionError> {
         let mut fields = vec![];
         for font_handle in family.fonts() {
-            match Font::from_handle(font_handle) {
-                Ok(font) => fields.push(font.properties()),
+            match font_handle.info() {
+                Ok(info) => fields.push(info.properties),
                 Err(e) => log::warn!("Error loading font from handle: {:?}", e),
             }
         }
@@ -161,3 +328,84 @@ pub trait Source: Any {
     /// trait object.
     fn as_mut_any(&mut self) -> &mut dyn Any;
 }
+
+#[cfg(test)]
+mod test {
+    use super::{family_names_for, MissingFontPolicy, Source};
+    use crate::error::SelectionError;
+    use crate::family_handle::FamilyHandle;
+    use crate::family_name::FamilyName;
+    use crate::handle::Handle;
+    use crate::properties::Properties;
+    use std::any::Any;
+
+    // A source that contains no fonts and fails to look up one family.
+    #[derive(Debug)]
+    struct FailingSource {
+        inaccessible_family: &'static str,
+    }
+
+    impl Source for FailingSource {
+        fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
+            Ok(vec![])
+        }
+
+        fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+            Ok(vec![])
+        }
+
+        fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+            if family_name == self.inaccessible_family {
+                Err(SelectionError::CannotAccessSource)
+            } else {
+                Err(SelectionError::NotFound)
+            }
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_mut_any(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    #[test]
+    fn test_select_family_by_generic_name_propagates_errors() {
+        let families = family_names_for(&FamilyName::Serif);
+        let source = FailingSource {
+            inaccessible_family: families[families.len() - 1],
+        };
+        assert_eq!(
+            source
+                .select_family_by_generic_name(&FamilyName::Serif)
+                .err(),
+            Some(SelectionError::CannotAccessSource)
+        );
+        assert_eq!(
+            source
+                .select_family_by_generic_name(&FamilyName::Monospace)
+                .err(),
+            Some(SelectionError::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_bundled_font() {
+        let source = FailingSource {
+            inaccessible_family: "",
+        };
+        let handle = source
+            .select_best_match_with_policy(
+                &[FamilyName::Title("Missing".to_owned())],
+                &Properties::new(),
+                MissingFontPolicy::Bundled,
+            )
+            .unwrap();
+        assert_eq!(
+            handle.info().unwrap().postscript_name.as_deref(),
+            Some("DejaVuSansMono")
+        );
+    }
+}
@@ -0,0 +1,385 @@
+// font-kit/src/sources/alias.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A source that resolves family names that another source doesn't contain to families that it
+//! does contain.
+//!
+//! Documents often name fonts that are only installed on some platforms, such as Arial or Times
+//! New Roman. Many of these have freely licensed clones with identical metrics, so that text set
+//! in the clone breaks into the same lines as the original. Fontconfig substitutes these clones
+//! automatically; `AliasSource` does the same on top of any other source.
+//!
+//! Applications can also register their own aliases, so that a family name resolves to the same
+//! font on every platform regardless of which fonts happen to be installed.
+
+use crate::error::SelectionError;
+use crate::family_handle::FamilyHandle;
+use crate::family_name::FamilyName;
+use crate::handle::Handle;
+use crate::properties::Properties;
+use crate::source::Source;
+use std::any::Any;
+
+/// Groups of families whose glyphs all have the same advances, so that any family in a group can
+/// stand in for any other without changing the layout of text. Within a group, families are in
+/// order of preference.
+///
+/// This follows Fontconfig's `30-metric-aliases.conf`.
+pub const METRIC_COMPATIBLE_FAMILIES: &[&[&str]] = &[
+    &[
+        "Arial",
+        "Helvetica",
+        "Liberation Sans",
+        "Arimo",
+        "Nimbus Sans",
+        "Nimbus Sans L",
+        "TeX Gyre Heros",
+    ],
+    &[
+        "Arial Narrow",
+        "Helvetica Narrow",
+        "Liberation Sans Narrow",
+        "Nimbus Sans Narrow",
+    ],
+    &[
+        "Times New Roman",
+        "Times",
+        "Liberation Serif",
+        "Tinos",
+        "Nimbus Roman",
+        "Nimbus Roman No9 L",
+        "TeX Gyre Termes",
+    ],
+    &[
+        "Courier New",
+        "Courier",
+        "Liberation Mono",
+        "Cousine",
+        "Nimbus Mono PS",
+        "Nimbus Mono L",
+        "TeX Gyre Cursor",
+    ],
+    &["Calibri", "Carlito"],
+    &["Cambria", "Caladea"],
+    &["Georgia", "Gelasio"],
+    &["Palatino", "Book Antiqua", "P052", "TeX Gyre Pagella"],
+    &["Bookman", "URW Bookman", "TeX Gyre Bonum"],
+    &["Century Schoolbook", "C059", "TeX Gyre Schola"],
+    &["Avant Garde", "URW Gothic", "TeX Gyre Adventor"],
+    &["Zapf Chancery", "Z003", "TeX Gyre Chorus"],
+    &["Bitstream Vera Sans", "DejaVu Sans"],
+    &["Bitstream Vera Serif", "DejaVu Serif"],
+    &["Bitstream Vera Sans Mono", "DejaVu Sans Mono"],
+];
+
+/// Returns the families that are metric-compatible with `family_name`, in order of preference,
+/// not including `family_name` itself. Names are compared case-insensitively.
+pub fn metric_compatible_families(family_name: &str) -> Vec<&'static str> {
+    METRIC_COMPATIBLE_FAMILIES
+        .iter()
+        .filter(|group| {
+            group
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(family_name))
+        })
+        .flat_map(|group| group.iter().copied())
+        .filter(|name| !name.eq_ignore_ascii_case(family_name))
+        .collect()
+}
+
+/// A source that wraps another source and resolves family names through aliases and
+/// substitutes.
+///
+/// Aliases and substitutes are consulted by `select_family_by_name()`, and so by
+/// `select_best_match()` and the other matching methods too. A family name is resolved by trying,
+/// in order:
+///
+/// 1. the families it was aliased to with `add_alias()`,
+/// 2. the family itself,
+/// 3. metric-compatible families, if `allow_metric_substitutes()` is set.
+///
+/// Aliases are not resolved recursively.
+#[allow(missing_debug_implementations)]
+pub struct AliasSource<S> {
+    source: S,
+    aliases: Vec<(String, Vec<String>)>,
+    allow_metric_substitutes: bool,
+}
+
+impl<S> AliasSource<S>
+where
+    S: Source,
+{
+    /// Wraps `source`. No substitutes are allowed until they are enabled.
+    pub fn new(source: S) -> AliasSource<S> {
+        AliasSource {
+            source,
+            aliases: vec![],
+            allow_metric_substitutes: false,
+        }
+    }
+
+    /// Allows missing families to be replaced by metric-compatible ones from
+    /// `METRIC_COMPATIBLE_FAMILIES`, such as Liberation Sans for Arial. The default is false.
+    #[inline]
+    pub fn allow_metric_substitutes(mut self, allow: bool) -> AliasSource<S> {
+        self.allow_metric_substitutes = allow;
+        self
+    }
+
+    /// Makes `family_name` resolve to the first of `targets` that the wrapped source contains,
+    /// even if it contains `family_name` itself. If it contains none of them, `family_name` is
+    /// looked up as usual. Family names are compared case-insensitively.
+    ///
+    /// Registering aliases for a family that already has some replaces them. For example,
+    /// `source.add_alias("Helvetica", ["Arial", "Liberation Sans"])` selects Arial or Liberation
+    /// Sans for Helvetica on every platform that has either, including macOS, which has Helvetica.
+    pub fn add_alias<I>(&mut self, family_name: &str, targets: I)
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let targets = targets.into_iter().map(Into::into).collect();
+        match self
+            .aliases
+            .iter_mut()
+            .find(|(name, _)| name.eq_ignore_ascii_case(family_name))
+        {
+            Some((_, existing)) => *existing = targets,
+            None => self.aliases.push((family_name.to_owned(), targets)),
+        }
+    }
+
+    /// Removes the aliases registered for `family_name`, returning them.
+    pub fn remove_alias(&mut self, family_name: &str) -> Option<Vec<String>> {
+        let index = self
+            .aliases
+            .iter()
+            .position(|(name, _)| name.eq_ignore_ascii_case(family_name))?;
+        Some(self.aliases.remove(index).1)
+    }
+
+    /// Returns the families that `family_name` was aliased to, in order of preference.
+    pub fn aliases_for(&self, family_name: &str) -> &[String] {
+        self.aliases
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(family_name))
+            .map_or(&[], |(_, targets)| targets)
+    }
+
+    /// Returns the wrapped source.
+    #[inline]
+    pub fn inner(&self) -> &S {
+        &self.source
+    }
+
+    /// Returns the wrapped source for modification.
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// Unwraps this source, returning the wrapped one.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    /// Returns the handles of all fonts in the wrapped source.
+    #[inline]
+    pub fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
+        self.source.all_fonts()
+    }
+
+    /// Returns the names of all families in the wrapped source.
+    #[inline]
+    pub fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        self.source.all_families()
+    }
+
+    /// Looks up a font family by name and returns the handles of all the fonts in that family.
+    ///
+    /// Aliases are tried first, then the family itself, and then, if they are allowed,
+    /// metric-compatible families.
+    pub fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        for target in self.aliases_for(family_name) {
+            match self.source.select_family_by_name(target) {
+                Err(SelectionError::NotFound) => {}
+                result => return result,
+            }
+        }
+        match self.source.select_family_by_name(family_name) {
+            Err(SelectionError::NotFound) => {}
+            result => return result,
+        }
+        if self.allow_metric_substitutes {
+            for substitute in metric_compatible_families(family_name) {
+                match self.source.select_family_by_name(substitute) {
+                    Err(SelectionError::NotFound) => {}
+                    result => return result,
+                }
+            }
+        }
+        Err(SelectionError::NotFound)
+    }
+
+    /// Selects a font by PostScript name, which should be a unique identifier.
+    ///
+    /// PostScript names are never substituted.
+    #[inline]
+    pub fn select_by_postscript_name(
+        &self,
+        postscript_name: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.source.select_by_postscript_name(postscript_name)
+    }
+
+    /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
+    /// handle.
+    #[inline]
+    pub fn select_best_match(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        <Self as Source>::select_best_match(self, family_names, properties)
+    }
+}
+
+impl<S> Source for AliasSource<S>
+where
+    S: Source,
+{
+    #[inline]
+    fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
+        self.all_fonts()
+    }
+
+    #[inline]
+    fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        self.all_families()
+    }
+
+    #[inline]
+    fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        self.select_family_by_name(family_name)
+    }
+
+    #[inline]
+    fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_postscript_name(postscript_name)
+    }
+
+    #[inline]
+    fn select_descriptions_in_family(
+        &self,
+        family: &FamilyHandle,
+    ) -> Result<Vec<Properties>, SelectionError> {
+        self.source.select_descriptions_in_family(family)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{metric_compatible_families, AliasSource};
+    use crate::error::SelectionError;
+    use crate::family_name::FamilyName;
+    use crate::handle::Handle;
+    use crate::loader::Loader;
+    use crate::properties::Properties;
+    use crate::sources::mem::MemSource;
+
+    static INCONSOLATA_PATH: &str = "resources/tests/inconsolata/Inconsolata-Regular.ttf";
+    static DEJAVU_SANS_MONO_PATH: &str = "resources/DejaVuSansMono.ttf";
+
+    #[test]
+    fn test_metric_substitutes() {
+        let substitutes = metric_compatible_families("arial");
+        assert_eq!(substitutes[..3], ["Helvetica", "Liberation Sans", "Arimo"]);
+        assert!(metric_compatible_families("Inconsolata").is_empty());
+
+        let mem_source = MemSource::from_fonts(
+            vec![Handle::from_path(DEJAVU_SANS_MONO_PATH.into(), 0)].into_iter(),
+        )
+        .unwrap();
+        let source = AliasSource::new(mem_source);
+        assert!(matches!(
+            source.select_family_by_name("Bitstream Vera Sans Mono"),
+            Err(SelectionError::NotFound)
+        ));
+
+        let source = source.allow_metric_substitutes(true);
+        assert_eq!(
+            source
+                .select_family_by_name("Bitstream Vera Sans Mono")
+                .unwrap()
+                .fonts()
+                .len(),
+            1
+        );
+        let font = source
+            .select_best_match(
+                &[FamilyName::Title("Bitstream Vera Sans Mono".to_owned())],
+                &Properties::new(),
+            )
+            .unwrap()
+            .load()
+            .unwrap();
+        assert_eq!(font.family_name(), "DejaVu Sans Mono");
+        assert!(matches!(
+            source.select_family_by_name("Arial"),
+            Err(SelectionError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mem_source = MemSource::from_fonts(
+            [INCONSOLATA_PATH, DEJAVU_SANS_MONO_PATH]
+                .iter()
+                .map(|path| Handle::from_path(path.into(), 0)),
+        )
+        .unwrap();
+        let mut source = AliasSource::new(mem_source);
+        source.add_alias("Code", ["Fira Code", "Inconsolata", "DejaVu Sans Mono"]);
+        source.add_alias("dejavu sans mono", vec!["Inconsolata".to_owned()]);
+        assert_eq!(source.aliases_for("CODE").len(), 3);
+
+        let family_name = |name: &str| {
+            source
+                .select_best_match(&[FamilyName::Title(name.to_owned())], &Properties::new())
+                .unwrap()
+                .load()
+                .unwrap()
+                .family_name()
+        };
+        assert_eq!(family_name("Code"), "Inconsolata");
+        assert_eq!(family_name("DejaVu Sans Mono"), "Inconsolata");
+
+        source.add_alias("Code", ["DejaVu Sans Mono"]);
+        assert_eq!(
+            source.remove_alias("DejaVu Sans Mono"),
+            Some(vec!["Inconsolata".to_owned()])
+        );
+        assert!(source.aliases_for("DejaVu Sans Mono").is_empty());
+        let font = source.select_family_by_name("Code").unwrap().fonts()[0]
+            .load()
+            .unwrap();
+        assert_eq!(font.family_name(), "DejaVu Sans Mono");
+    }
+}
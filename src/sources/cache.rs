@@ -0,0 +1,557 @@
+// font-kit/src/sources/cache.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A source that indexes fonts on disk and keeps the index in a cache file.
+//!
+//! Enumerating fonts requires opening and parsing every font file, which can take seconds on
+//! systems with thousands of fonts installed. This source saves the resulting index (the family
+//! name, PostScript name, and properties of every font) to a file, and reuses it as long as none
+//! of the indexed directories have changed since.
+//!
+//! Changes are detected by comparing the modification times of every directory that was scanned.
+//! Adding, removing, or renaming a font changes the modification time of its directory, but
+//! overwriting a font file in place does not; call `rebuild()` in that case.
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use log::warn;
+use std::any::Any;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Error as IOError, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+use crate::error::SelectionError;
+use crate::family_handle::FamilyHandle;
+use crate::family_name::FamilyName;
+use crate::handle::Handle;
+use crate::matching::MatchScorer;
+use crate::properties::{Properties, Stretch, Style, Weight};
+use crate::source::Source;
+use crate::sources::fs::{self as fs_source, FsSource};
+use crate::sources::mem::{FamilyEntry, MemSource};
+
+const CACHE_MAGIC: &[u8; 4] = b"FKFC";
+
+// Bump this whenever the format of the cache file or the way fonts are indexed changes, so that
+// stale caches are rebuilt instead of misread.
+const CACHE_VERSION: u32 = 2;
+
+/// A source that indexes the fonts in a set of directories, and caches the index on disk so
+/// that later runs can skip parsing the fonts.
+#[allow(missing_debug_implementations)]
+pub struct CachedSource {
+    mem_source: MemSource,
+    directories: Vec<PathBuf>,
+    cache_path: PathBuf,
+    loaded_from_cache: bool,
+}
+
+impl CachedSource {
+    /// Indexes the default set of font directories on this platform, using the cache file at
+    /// `cache_path`.
+    ///
+    /// The directories are the same ones that `FsSource::new()` scans.
+    pub fn new<P>(cache_path: P) -> CachedSource
+    where
+        P: AsRef<Path>,
+    {
+        CachedSource::from_dirs(fs_source::default_font_directories(), cache_path)
+    }
+
+    /// Indexes the fonts in the given directories, using the cache file at `cache_path`.
+    ///
+    /// If the cache file exists, was written for the same directories, and none of them have
+    /// changed since, the index is read from it. Otherwise the directories are scanned and the
+    /// cache file is rewritten. Failing to read or write the cache is not an error; it only makes
+    /// this slower.
+    pub fn from_dirs<I, P>(directories: I, cache_path: P) -> CachedSource
+    where
+        I: IntoIterator,
+        I::Item: AsRef<Path>,
+        P: AsRef<Path>,
+    {
+        let directories: Vec<PathBuf> = directories
+            .into_iter()
+            .map(|directory| directory.as_ref().to_owned())
+            .collect();
+        let cache_path = cache_path.as_ref().to_owned();
+
+        match read_cache(&cache_path, &directories) {
+            Ok(Some(entries)) => CachedSource {
+                mem_source: MemSource::from_entries(entries),
+                directories,
+                cache_path,
+                loaded_from_cache: true,
+            },
+            result => {
+                if let Err(error) = result {
+                    warn!("Ignoring font cache {:?}: {}", cache_path, error);
+                }
+                let mut source = CachedSource {
+                    mem_source: MemSource::empty(),
+                    directories,
+                    cache_path,
+                    loaded_from_cache: false,
+                };
+                source.rebuild();
+                source
+            }
+        }
+    }
+
+    /// Returns the location of the cache file in the per-user cache directory on this platform,
+    /// if there is one.
+    pub fn default_cache_path() -> Option<PathBuf> {
+        #[cfg(target_family = "windows")]
+        let cache_directory = std::env::var_os("LOCALAPPDATA").map(PathBuf::from);
+        #[cfg(target_os = "android")]
+        let cache_directory: Option<PathBuf> = None;
+        #[cfg(not(any(target_family = "windows", target_os = "android")))]
+        let cache_directory = dirs_next::cache_dir();
+
+        cache_directory.map(|mut path| {
+            path.push("font-kit");
+            path.push("fonts.cache");
+            path
+        })
+    }
+
+    /// Rescans the directories, ignoring the cache, and rewrites the cache file.
+    pub fn rebuild(&mut self) {
+        // Record the modification times before scanning, so that changes made during the scan
+        // invalidate the cache next time.
+        let stamps = directory_stamps(&self.directories);
+        let mut fonts = vec![];
+        for directory in &self.directories {
+            fonts.extend(FsSource::discover_fonts(directory));
+        }
+        self.mem_source = MemSource::from_loadable_fonts(fonts.into_iter());
+        self.loaded_from_cache = false;
+
+        if let Err(error) = write_cache(
+            &self.cache_path,
+            &self.directories,
+            &stamps,
+            &self.mem_source.entries(),
+        ) {
+            warn!(
+                "Failed to write font cache {:?}: {}",
+                self.cache_path, error
+            );
+        }
+    }
+
+    /// Returns true if the index was read from the cache file rather than built by scanning the
+    /// directories.
+    #[inline]
+    pub fn loaded_from_cache(&self) -> bool {
+        self.loaded_from_cache
+    }
+
+    /// Returns the path of the cache file.
+    #[inline]
+    pub fn cache_path(&self) -> &Path {
+        &self.cache_path
+    }
+
+    /// Returns paths of all fonts installed on the system.
+    pub fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
+        self.mem_source.all_fonts()
+    }
+
+    /// Returns the names of all families installed on the system.
+    pub fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        self.mem_source.all_families()
+    }
+
+    /// Looks up a font family by name and returns the handles of all the fonts in that family.
+    pub fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        self.mem_source.select_family_by_name(family_name)
+    }
+
+    /// Selects a font by PostScript name, which should be a unique identifier.
+    pub fn select_by_postscript_name(
+        &self,
+        postscript_name: &str,
+    ) -> Result<Handle, SelectionError> {
+        self.mem_source.select_by_postscript_name(postscript_name)
+    }
+
+    /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
+    /// handle.
+    #[inline]
+    pub fn select_best_match(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.mem_source.select_best_match(family_names, properties)
+    }
+}
+
+impl Source for CachedSource {
+    #[inline]
+    fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
+        self.all_fonts()
+    }
+
+    #[inline]
+    fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        self.all_families()
+    }
+
+    fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        self.select_family_by_name(family_name)
+    }
+
+    fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_postscript_name(postscript_name)
+    }
+
+    #[inline]
+    fn select_best_match(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.select_best_match(family_names, properties)
+    }
+
+    #[inline]
+    fn select_best_match_with_scorer(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+        scorer: &dyn MatchScorer,
+    ) -> Result<Handle, SelectionError> {
+        self.mem_source
+            .select_best_match_with_scorer(family_names, properties, scorer)
+    }
+
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    #[inline]
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// The modification time of a directory, or `None` if it doesn't exist.
+pub(crate) type DirectoryStamp = (PathBuf, Option<SystemTime>);
+
+pub(crate) fn directory_stamps(roots: &[PathBuf]) -> Vec<DirectoryStamp> {
+    let mut stamps = vec![];
+    for root in roots {
+        if !root.is_dir() {
+            stamps.push((root.clone(), None));
+            continue;
+        }
+        for directory_entry in WalkDir::new(root).follow_links(true).into_iter() {
+            let directory_entry = match directory_entry {
+                Ok(directory_entry) if directory_entry.file_type().is_dir() => directory_entry,
+                _ => continue,
+            };
+            let modified = directory_entry
+                .metadata()
+                .ok()
+                .and_then(|metadata| metadata.modified().ok());
+            stamps.push((directory_entry.into_path(), modified));
+        }
+    }
+    stamps
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .ok()
+        .filter(|metadata| metadata.is_dir())
+        .and_then(|metadata| metadata.modified().ok())
+}
+
+// Returns `Ok(None)` if there is no cache file, or if it is out of date.
+fn read_cache(
+    cache_path: &Path,
+    directories: &[PathBuf],
+) -> Result<Option<Vec<FamilyEntry>>, IOError> {
+    let file = match File::open(cache_path) {
+        Ok(file) => file,
+        Err(ref error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    let mut reader = BufReader::new(file);
+
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != CACHE_MAGIC || reader.read_u32::<LittleEndian>()? != CACHE_VERSION {
+        return Ok(None);
+    }
+
+    let root_count = reader.read_u32::<LittleEndian>()? as usize;
+    if root_count != directories.len() {
+        return Ok(None);
+    }
+    for directory in directories {
+        if read_path(&mut reader)? != *directory {
+            return Ok(None);
+        }
+    }
+
+    let stamp_count = reader.read_u32::<LittleEndian>()?;
+    for _ in 0..stamp_count {
+        let path = read_path(&mut reader)?;
+        let modified = read_time(&mut reader)?;
+        if modification_time(&path) != modified {
+            return Ok(None);
+        }
+    }
+
+    let entry_count = reader.read_u32::<LittleEndian>()?;
+    let mut entries = vec![];
+    for _ in 0..entry_count {
+        let path = read_path(&mut reader)?;
+        let font_index = reader.read_u32::<LittleEndian>()?;
+        let family_name = read_string(&mut reader)?;
+        let postscript_name = read_string(&mut reader)?;
+        let style = match reader.read_u8()? {
+            0 => Style::Normal,
+            1 => Style::Italic,
+            2 => Style::Oblique,
+            _ => return Err(invalid_data("invalid style")),
+        };
+        let weight = Weight(reader.read_f32::<LittleEndian>()?);
+        let stretch = Stretch(reader.read_f32::<LittleEndian>()?);
+        entries.push(FamilyEntry {
+            family_name,
+            postscript_name,
+            properties: Properties {
+                style,
+                weight,
+                stretch,
+            },
+            font: Handle::from_path(path, font_index),
+        });
+    }
+    Ok(Some(entries))
+}
+
+fn write_cache(
+    cache_path: &Path,
+    directories: &[PathBuf],
+    stamps: &[DirectoryStamp],
+    entries: &[FamilyEntry],
+) -> Result<(), IOError> {
+    if let Some(parent) = cache_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Write to a temporary file and rename it into place, so that a concurrent reader never sees
+    // a partially written cache.
+    let mut temporary_path = cache_path.as_os_str().to_owned();
+    temporary_path.push(format!(".{}.tmp", std::process::id()));
+    let temporary_path = PathBuf::from(temporary_path);
+
+    let result = (|| {
+        let mut writer = BufWriter::new(File::create(&temporary_path)?);
+        writer.write_all(CACHE_MAGIC)?;
+        writer.write_u32::<LittleEndian>(CACHE_VERSION)?;
+
+        writer.write_u32::<LittleEndian>(directories.len() as u32)?;
+        for directory in directories {
+            write_path(&mut writer, directory)?;
+        }
+
+        writer.write_u32::<LittleEndian>(stamps.len() as u32)?;
+        for (path, modified) in stamps {
+            write_path(&mut writer, path)?;
+            write_time(&mut writer, *modified)?;
+        }
+
+        let paths: Vec<_> = entries
+            .iter()
+            .filter_map(|entry| match entry.font {
+                Handle::Path {
+                    ref path,
+                    font_index,
+                } => Some((entry, path, font_index)),
+                Handle::Uri { .. } | Handle::Memory { .. } | Handle::Named { .. } => None,
+            })
+            .collect();
+        writer.write_u32::<LittleEndian>(paths.len() as u32)?;
+        for (entry, path, font_index) in paths {
+            write_path(&mut writer, path)?;
+            writer.write_u32::<LittleEndian>(font_index)?;
+            write_string(&mut writer, &entry.family_name)?;
+            write_string(&mut writer, &entry.postscript_name)?;
+            writer.write_u8(match entry.properties.style {
+                Style::Normal => 0,
+                Style::Italic => 1,
+                Style::Oblique => 2,
+            })?;
+            writer.write_f32::<LittleEndian>(entry.properties.weight.0)?;
+            writer.write_f32::<LittleEndian>(entry.properties.stretch.0)?;
+        }
+        writer.into_inner().map_err(|error| error.into_error())?;
+        fs::rename(&temporary_path, cache_path)
+    })();
+
+    if result.is_err() {
+        drop(fs::remove_file(&temporary_path));
+    }
+    result
+}
+
+fn read_bytes<R>(reader: &mut R) -> Result<Vec<u8>, IOError>
+where
+    R: Read,
+{
+    let len = reader.read_u32::<LittleEndian>()? as u64;
+    let mut bytes = vec![];
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(IOError::from(ErrorKind::UnexpectedEof));
+    }
+    Ok(bytes)
+}
+
+fn write_bytes<W>(writer: &mut W, bytes: &[u8]) -> Result<(), IOError>
+where
+    W: Write,
+{
+    writer.write_u32::<LittleEndian>(bytes.len() as u32)?;
+    writer.write_all(bytes)
+}
+
+fn read_string<R>(reader: &mut R) -> Result<String, IOError>
+where
+    R: Read,
+{
+    String::from_utf8(read_bytes(reader)?).map_err(|_| invalid_data("invalid UTF-8"))
+}
+
+fn write_string<W>(writer: &mut W, string: &str) -> Result<(), IOError>
+where
+    W: Write,
+{
+    write_bytes(writer, string.as_bytes())
+}
+
+#[cfg(unix)]
+fn read_path<R>(reader: &mut R) -> Result<PathBuf, IOError>
+where
+    R: Read,
+{
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+    Ok(PathBuf::from(OsString::from_vec(read_bytes(reader)?)))
+}
+
+#[cfg(unix)]
+fn write_path<W>(writer: &mut W, path: &Path) -> Result<(), IOError>
+where
+    W: Write,
+{
+    use std::os::unix::ffi::OsStrExt;
+    write_bytes(writer, path.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+fn read_path<R>(reader: &mut R) -> Result<PathBuf, IOError>
+where
+    R: Read,
+{
+    read_string(reader).map(PathBuf::from)
+}
+
+#[cfg(not(unix))]
+fn write_path<W>(writer: &mut W, path: &Path) -> Result<(), IOError>
+where
+    W: Write,
+{
+    let path = path
+        .to_str()
+        .ok_or_else(|| invalid_data("path is not valid Unicode"))?;
+    write_string(writer, path)
+}
+
+fn read_time<R>(reader: &mut R) -> Result<Option<SystemTime>, IOError>
+where
+    R: Read,
+{
+    if reader.read_u8()? == 0 {
+        return Ok(None);
+    }
+    let seconds = reader.read_u64::<LittleEndian>()?;
+    let nanoseconds = reader.read_u32::<LittleEndian>()?;
+    Ok(Some(UNIX_EPOCH + Duration::new(seconds, nanoseconds)))
+}
+
+fn write_time<W>(writer: &mut W, time: Option<SystemTime>) -> Result<(), IOError>
+where
+    W: Write,
+{
+    // Times before the epoch can't be represented; treat them as missing, which just means the
+    // cache will never be considered up to date.
+    match time.and_then(|time| time.duration_since(UNIX_EPOCH).ok()) {
+        None => writer.write_u8(0),
+        Some(duration) => {
+            writer.write_u8(1)?;
+            writer.write_u64::<LittleEndian>(duration.as_secs())?;
+            writer.write_u32::<LittleEndian>(duration.subsec_nanos())
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> IOError {
+    IOError::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::CachedSource;
+    use std::fs;
+
+    static INCONSOLATA_PATH: &str = "resources/tests/inconsolata/Inconsolata-Regular.ttf";
+    static EB_GARAMOND_PATH: &str = "resources/tests/eb-garamond/EBGaramond12-Regular.otf";
+
+    #[test]
+    fn test_cache_is_reused_until_directory_changes() {
+        let root = std::env::temp_dir().join(format!("font-kit-cache-test-{}", std::process::id()));
+        let font_directory = root.join("fonts");
+        let cache_path = root.join("fonts.cache");
+        drop(fs::remove_dir_all(&root));
+        fs::create_dir_all(&font_directory).unwrap();
+        fs::copy(INCONSOLATA_PATH, font_directory.join("Inconsolata.ttf")).unwrap();
+
+        let source = CachedSource::from_dirs(&[&font_directory], &cache_path);
+        assert!(!source.loaded_from_cache());
+        assert_eq!(source.all_families().unwrap(), ["Inconsolata"]);
+
+        let source = CachedSource::from_dirs(&[&font_directory], &cache_path);
+        assert!(source.loaded_from_cache());
+        assert_eq!(source.all_families().unwrap(), ["Inconsolata"]);
+        assert!(source
+            .select_by_postscript_name("Inconsolata-Regular")
+            .is_ok());
+
+        fs::copy(EB_GARAMOND_PATH, font_directory.join("EBGaramond.otf")).unwrap();
+        let source = CachedSource::from_dirs(&[&font_directory], &cache_path);
+        assert!(!source.loaded_from_cache());
+        assert_eq!(
+            source.all_families().unwrap(),
+            ["EB Garamond", "Inconsolata"]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
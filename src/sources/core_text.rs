@@ -13,7 +13,9 @@
 use core_foundation::array::CFArray;
 use core_foundation::base::{CFType, TCFType};
 use core_foundation::dictionary::CFDictionary;
-use core_foundation::string::CFString;
+use core_foundation::string::{CFString, CFStringRef};
+use core_graphics::base::CGFloat;
+use core_text::font::{kCTFontSystemFontType, CTFont, CTFontRef, CTFontUIFontType};
 use core_text::font_collection::{self, CTFontCollection};
 use core_text::font_descriptor::{self, CTFontDescriptor};
 use core_text::font_manager;
@@ -22,6 +24,7 @@ use std::collections::HashMap;
 use std::f32;
 use std::fs::File;
 use std::path::PathBuf;
+use std::ptr;
 use std::sync::Arc;
 
 use crate::error::SelectionError;
@@ -30,11 +33,20 @@ use crate::family_name::FamilyName;
 use crate::file_type::FileType;
 use crate::font::Font;
 use crate::handle::Handle;
+use crate::loader::Loader;
 use crate::loaders::core_text::{self as core_text_loader, FONT_WEIGHT_MAPPING};
 use crate::properties::{Properties, Stretch, Weight};
 use crate::source::Source;
 use crate::utils;
 
+extern "C" {
+    fn CTFontCreateUIFontForLanguage(
+        ui_type: CTFontUIFontType,
+        size: CGFloat,
+        language: CFStringRef,
+    ) -> CTFontRef;
+}
+
 /// A source that contains the installed fonts on macOS.
 #[allow(missing_debug_implementations)]
 #[allow(missing_copy_implementations)]
@@ -95,10 +107,28 @@ impl CoreTextSource {
         let collection = font_collection::new_from_descriptors(&descriptors);
         match collection.get_descriptors() {
             None => Err(SelectionError::NotFound),
-            Some(descriptors) => create_handle_from_descriptor(&*descriptors.get(0).unwrap()),
+            Some(descriptors) => {
+                create_handle_from_descriptor(&*descriptors.get(0).unwrap(), &mut HashMap::new())
+            }
         }
     }
 
+    /// Returns the font that the system uses for user interface elements (the "system font").
+    ///
+    /// On recent versions of macOS this is San Francisco, which is not visible through
+    /// `all_families()` or `select_family_by_name()`, so this is the only way to look it up.
+    pub fn select_ui_font(&self) -> Result<Handle, SelectionError> {
+        let core_text_font = unsafe {
+            let core_text_font_ref =
+                CTFontCreateUIFontForLanguage(kCTFontSystemFontType, 0.0, ptr::null());
+            if core_text_font_ref.is_null() {
+                return Err(SelectionError::NotFound);
+            }
+            CTFont::wrap_under_create_rule(core_text_font_ref)
+        };
+        create_handle_from_descriptor(&core_text_font.copy_descriptor(), &mut HashMap::new())
+    }
+
     /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
     /// handle.
     #[inline]
@@ -165,55 +195,12 @@ fn create_handles_from_core_text_collection(
     let mut fonts = vec![];
     if let Some(descriptors) = collection.get_descriptors() {
         let mut font_data_info_cache: HashMap<PathBuf, FontDataInfo> = HashMap::new();
-
-        'outer: for index in 0..descriptors.len() {
+        for index in 0..descriptors.len() {
             let descriptor = descriptors.get(index).unwrap();
-            let font_path = descriptor.font_path().unwrap();
-
-            let data_info = if let Some(data_info) = font_data_info_cache.get(&font_path) {
-                data_info.clone()
-            } else {
-                let mut file = if let Ok(file) = File::open(&font_path) {
-                    file
-                } else {
-                    continue;
-                };
-                let data = if let Ok(data) = utils::slurp_file(&mut file) {
-                    Arc::new(data)
-                } else {
-                    continue;
-                };
-
-                let file_type = match Font::analyze_bytes(Arc::clone(&data)) {
-                    Ok(file_type) => file_type,
-                    Err(_) => continue,
-                };
-
-                let data_info = FontDataInfo { data, file_type };
-
-                font_data_info_cache.insert(font_path.clone(), data_info.clone());
-
-                data_info
-            };
-
-            match data_info.file_type {
-                FileType::Collection(font_count) => {
-                    let postscript_name = descriptor.font_name();
-                    for font_index in 0..font_count {
-                        if let Ok(font) = Font::from_bytes(Arc::clone(&data_info.data), font_index)
-                        {
-                            if let Some(font_postscript_name) = font.postscript_name() {
-                                if postscript_name == font_postscript_name {
-                                    fonts.push(Handle::from_memory(data_info.data, font_index));
-                                    continue 'outer;
-                                }
-                            }
-                        }
-                    }
-                }
-                FileType::Single => {
-                    fonts.push(Handle::from_memory(data_info.data, 0));
-                }
+            if let Ok(handle) =
+                create_handle_from_descriptor(&descriptor, &mut font_data_info_cache)
+            {
+                fonts.push(handle);
             }
         }
     }
@@ -224,39 +211,73 @@ fn create_handles_from_core_text_collection(
     }
 }
 
-fn create_handle_from_descriptor(descriptor: &CTFontDescriptor) -> Result<Handle, SelectionError> {
-    let font_path = descriptor.font_path().unwrap();
-
-    let mut file = if let Ok(file) = File::open(&font_path) {
-        file
-    } else {
-        return Err(SelectionError::CannotAccessSource);
+/// Creates a handle for the font that a descriptor refers to.
+///
+/// Fonts backed by a file on disk produce `Handle::Path` handles, resolving the index within the
+/// file by PostScript name if the file is a collection. Fonts with no backing file (for example,
+/// those registered from memory by the application) produce `Handle::Memory` handles.
+fn create_handle_from_descriptor(
+    descriptor: &CTFontDescriptor,
+    font_data_info_cache: &mut HashMap<PathBuf, FontDataInfo>,
+) -> Result<Handle, SelectionError> {
+    let font_path = match descriptor.font_path() {
+        Some(font_path) => font_path,
+        None => return create_memory_handle_from_descriptor(descriptor),
     };
 
-    let font_data = if let Ok(font_data) = utils::slurp_file(&mut file) {
-        Arc::new(font_data)
-    } else {
-        return Err(SelectionError::CannotAccessSource);
+    let data_info = match font_data_info_cache.get(&font_path) {
+        Some(data_info) => data_info.clone(),
+        None => {
+            let mut file =
+                File::open(&font_path).map_err(|_| SelectionError::CannotAccessSource)?;
+            let data = Arc::new(
+                utils::slurp_file(&mut file).map_err(|_| SelectionError::CannotAccessSource)?,
+            );
+            let file_type = Font::analyze_bytes(Arc::clone(&data))
+                .map_err(|_| SelectionError::CannotAccessSource)?;
+            let data_info = FontDataInfo { data, file_type };
+            font_data_info_cache.insert(font_path.clone(), data_info.clone());
+            data_info
+        }
     };
 
-    match Font::analyze_bytes(Arc::clone(&font_data)) {
-        Ok(FileType::Collection(font_count)) => {
+    match data_info.file_type {
+        FileType::Collection(font_count) => {
             let postscript_name = descriptor.font_name();
             for font_index in 0..font_count {
-                if let Ok(font) = Font::from_bytes(Arc::clone(&font_data), font_index) {
-                    if let Some(font_postscript_name) = font.postscript_name() {
-                        if postscript_name == font_postscript_name {
-                            return Ok(Handle::from_memory(font_data, font_index));
-                        }
+                if let Ok(font) = Font::from_bytes(Arc::clone(&data_info.data), font_index) {
+                    if font.postscript_name().as_deref() == Some(&*postscript_name) {
+                        return Ok(Handle::from_path(font_path, font_index));
                     }
                 }
             }
 
             Err(SelectionError::NotFound)
         }
-        Ok(FileType::Single) => Ok(Handle::from_memory(font_data, 0)),
-        Err(_) => Err(SelectionError::CannotAccessSource),
+        FileType::Single => Ok(Handle::from_path(font_path, 0)),
+    }
+}
+
+/// Reassembles the data of a font that has no backing file from the tables Core Text exposes.
+fn create_memory_handle_from_descriptor(
+    descriptor: &CTFontDescriptor,
+) -> Result<Handle, SelectionError> {
+    let core_text_font = core_text::font::new_from_descriptor(descriptor, 16.0);
+    let table_tags = core_text_font
+        .get_available_font_tables()
+        .ok_or(SelectionError::CannotAccessSource)?;
+
+    let mut tables = vec![];
+    for table_tag in table_tags.iter() {
+        if let Some(table_data) = core_text_font.get_font_table(*table_tag) {
+            tables.push((*table_tag, table_data.bytes().to_vec()));
+        }
     }
+    if tables.is_empty() {
+        return Err(SelectionError::CannotAccessSource);
+    }
+
+    Ok(Handle::from_memory(Arc::new(utils::build_sfnt(&tables)), 0))
 }
 
 #[cfg(test)]
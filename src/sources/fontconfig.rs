@@ -19,8 +19,10 @@ use crate::error::SelectionError;
 use crate::family_handle::FamilyHandle;
 use crate::family_name::FamilyName;
 use crate::handle::Handle;
-use crate::properties::Properties;
+use crate::properties::{Properties, Style, Weight};
+use crate::rendering_config::{HintingStyle, RenderingConfig, SubpixelOrder, XftSettings};
 use crate::source::Source;
+use crate::utils;
 use std::any::Any;
 
 /// A source that contains the fonts installed on the system, as reported by the Fontconfig
@@ -212,6 +214,199 @@ impl FontconfigSource {
     ) -> Result<Handle, SelectionError> {
         <Self as Source>::select_best_match(self, family_names, properties)
     }
+
+    /// Selects the font that fontconfig itself chooses for the given family and properties, as
+    /// GTK and Qt applications do.
+    ///
+    /// Unlike `select_best_match()`, which applies the CSS font matching algorithm to the fonts
+    /// that fontconfig lists, this applies the user's fontconfig configuration, such as aliases,
+    /// preferred families, and `<match target="pattern">` substitutions, and returns whichever
+    /// font fontconfig considers closest, even of another family. It only fails if no fonts are
+    /// installed.
+    pub fn match_font(
+        &self,
+        family_name: &str,
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        let mut pattern = fc::Pattern::from_name(family_name);
+        pattern.push_integer(fc::Object::Weight, weight_to_fc(properties.weight));
+        pattern.push_integer(fc::Object::Slant, slant_to_fc(properties.style));
+        pattern.push_integer(
+            fc::Object::Width,
+            (properties.stretch.0 * 100.0).round() as i32,
+        );
+        pattern.config_substitute(fc::MatchKind::Pattern);
+        pattern.default_substitute();
+
+        let matched = pattern
+            .font_match(&self.config)
+            .map_err(|_| SelectionError::NotFound)?;
+        let matched = matched.as_pattern_ref();
+        let font_path = matched
+            .get_string(fc::Object::File)
+            .ok_or(SelectionError::NotFound)?;
+        let font_index = matched.get_integer(fc::Object::Index).unwrap_or(0) as u32;
+        Ok(Handle::from_path(
+            std::path::PathBuf::from(font_path),
+            font_index,
+        ))
+    }
+
+    /// Returns the rendering settings that fontconfig prescribes for the given family.
+    ///
+    /// The user's `Xft.*` X resources are used as defaults, and fontconfig's configuration,
+    /// including `<match target="font">` rules, is applied on top of them, so per-font overrides
+    /// of hinting and antialiasing are honored. Settings not covered keep their default values.
+    pub fn rendering_config(&self, family_name: &str) -> RenderingConfig {
+        let mut config = RenderingConfig::default();
+        self.read_rendering_config(family_name, &XftSettings::system(), &mut config);
+        config
+    }
+
+    // Matches `family_name`, which may be empty, and stores the rendering settings of the matched
+    // font in `config`.
+    pub(crate) fn read_rendering_config(
+        &self,
+        family_name: &str,
+        xft_settings: &XftSettings,
+        config: &mut RenderingConfig,
+    ) {
+        let mut pattern = fc::Pattern::from_name(family_name);
+        pattern.config_substitute(fc::MatchKind::Pattern);
+
+        // Like Cairo, only fill in the X resources that the configuration has not set.
+        let unset = |pattern: &fc::Pattern, object| {
+            let pattern = pattern.as_pattern_ref();
+            pattern.get_bool(object).is_none() && pattern.get_integer(object).is_none()
+        };
+        if let Some(antialias) = xft_settings.antialias {
+            if unset(&pattern, fc::Object::Antialias) {
+                pattern.push_bool(fc::Object::Antialias, antialias);
+            }
+        }
+        if let Some(hinting) = xft_settings.hinting {
+            if unset(&pattern, fc::Object::Hinting) {
+                pattern.push_bool(fc::Object::Hinting, hinting);
+            }
+        }
+        if let Some(hinting_style) = xft_settings.hinting_style {
+            if unset(&pattern, fc::Object::HintStyle) {
+                pattern.push_integer(fc::Object::HintStyle, hint_style_to_fc(hinting_style));
+            }
+        }
+        if let Some(subpixel_order) = xft_settings.subpixel_order {
+            if unset(&pattern, fc::Object::Rgba) {
+                pattern.push_integer(fc::Object::Rgba, rgba_to_fc(subpixel_order));
+            }
+        }
+        if let Some(dpi) = xft_settings.dpi {
+            pattern.push_double(fc::Object::Dpi, dpi as f64);
+            config.dpi = dpi;
+        }
+        pattern.default_substitute();
+
+        let matched = match pattern.font_match(&self.config) {
+            Ok(matched) => matched,
+            Err(_) => {
+                xft_settings.apply(config);
+                return;
+            }
+        };
+        let matched = matched.as_pattern_ref();
+
+        if let Some(subpixel_order) = matched.get_integer(fc::Object::Rgba).and_then(rgba_from_fc) {
+            config.subpixel_order = subpixel_order;
+        }
+        config.set_antialias(matched.get_bool(fc::Object::Antialias).unwrap_or(true));
+        config.hinting_style = if matched.get_bool(fc::Object::Hinting) == Some(false) {
+            HintingStyle::None
+        } else {
+            matched
+                .get_integer(fc::Object::HintStyle)
+                .and_then(hint_style_from_fc)
+                .unwrap_or(HintingStyle::Full)
+        };
+    }
+}
+
+// `FC_HINT_NONE` through `FC_HINT_FULL`.
+fn hint_style_from_fc(hint_style: i32) -> Option<HintingStyle> {
+    match hint_style {
+        0 => Some(HintingStyle::None),
+        1 => Some(HintingStyle::Slight),
+        2 => Some(HintingStyle::Medium),
+        3 => Some(HintingStyle::Full),
+        _ => None,
+    }
+}
+
+// Converts a CSS weight to a fontconfig weight, as `FcWeightFromOpenTypeDouble()` does.
+fn weight_to_fc(weight: Weight) -> i32 {
+    // OpenType weights and the fontconfig weights they correspond to, from `FC_WEIGHT_THIN` to
+    // `FC_WEIGHT_EXTRABLACK`.
+    const WEIGHTS: [(f32, f32); 12] = [
+        (100.0, 0.0),
+        (200.0, 40.0),
+        (300.0, 50.0),
+        (350.0, 55.0),
+        (380.0, 75.0),
+        (400.0, 80.0),
+        (500.0, 100.0),
+        (600.0, 180.0),
+        (700.0, 200.0),
+        (800.0, 205.0),
+        (900.0, 210.0),
+        (1000.0, 215.0),
+    ];
+    let weight = weight.0.clamp(100.0, 1000.0);
+    let index = WEIGHTS[1..]
+        .iter()
+        .position(|&(upper, _)| weight <= upper)
+        .unwrap_or(WEIGHTS.len() - 2);
+    let ((lower, lower_fc), (upper, upper_fc)) = (WEIGHTS[index], WEIGHTS[index + 1]);
+    utils::lerp(lower_fc, upper_fc, (weight - lower) / (upper - lower)).round() as i32
+}
+
+// `FC_SLANT_ROMAN`, `FC_SLANT_ITALIC`, and `FC_SLANT_OBLIQUE`.
+fn slant_to_fc(style: Style) -> i32 {
+    match style {
+        Style::Normal => 0,
+        Style::Italic => 100,
+        Style::Oblique => 110,
+    }
+}
+
+fn hint_style_to_fc(hinting_style: HintingStyle) -> i32 {
+    match hinting_style {
+        HintingStyle::None => 0,
+        HintingStyle::Slight => 1,
+        HintingStyle::Medium => 2,
+        HintingStyle::Full => 3,
+    }
+}
+
+// `FC_RGBA_UNKNOWN` through `FC_RGBA_NONE`.
+fn rgba_from_fc(rgba: i32) -> Option<SubpixelOrder> {
+    match rgba {
+        0 => Some(SubpixelOrder::Unknown),
+        1 => Some(SubpixelOrder::Rgb),
+        2 => Some(SubpixelOrder::Bgr),
+        3 => Some(SubpixelOrder::VRgb),
+        4 => Some(SubpixelOrder::VBgr),
+        5 => Some(SubpixelOrder::None),
+        _ => None,
+    }
+}
+
+fn rgba_to_fc(subpixel_order: SubpixelOrder) -> i32 {
+    match subpixel_order {
+        SubpixelOrder::Unknown => 0,
+        SubpixelOrder::Rgb => 1,
+        SubpixelOrder::Bgr => 2,
+        SubpixelOrder::VRgb => 3,
+        SubpixelOrder::VBgr => 4,
+        SubpixelOrder::None => 5,
+    }
 }
 
 impl Source for FontconfigSource {
@@ -294,6 +489,14 @@ mod fc {
         File,
         Index,
         PostScriptName,
+        Antialias,
+        Hinting,
+        HintStyle,
+        Rgba,
+        Dpi,
+        Weight,
+        Slant,
+        Width,
     }
 
     impl Object {
@@ -303,6 +506,14 @@ mod fc {
                 Object::File => b"file\0",
                 Object::Index => b"index\0",
                 Object::PostScriptName => b"postscriptname\0",
+                Object::Antialias => b"antialias\0",
+                Object::Hinting => b"hinting\0",
+                Object::HintStyle => b"hintstyle\0",
+                Object::Rgba => b"rgba\0",
+                Object::Dpi => b"dpi\0",
+                Object::Weight => b"weight\0",
+                Object::Slant => b"slant\0",
+                Object::Width => b"width\0",
             }
         }
 
@@ -315,6 +526,10 @@ mod fc {
         d: *mut ffi::FcConfig,
     }
 
+    // SAFETY: Fontconfig objects aren't tied to the thread that created them, and `Config` is
+    // never shared between threads, only moved.
+    unsafe impl Send for Config {}
+
     impl Config {
         // FcInitLoadConfigAndFonts
         pub fn new() -> Self {
@@ -398,6 +613,53 @@ mod fc {
             }
         }
 
+        // FcPatternAddBool
+        pub fn push_bool(&mut self, object: Object, value: bool) {
+            unsafe {
+                ffi_dispatch!(
+                    feature = "source-fontconfig-dlopen",
+                    LIB,
+                    FcPatternAddBool,
+                    self.d,
+                    object.as_ptr(),
+                    value as ffi::FcBool
+                );
+            }
+        }
+
+        // FcPatternAddInteger
+        pub fn push_integer(&mut self, object: Object, value: i32) {
+            unsafe {
+                ffi_dispatch!(
+                    feature = "source-fontconfig-dlopen",
+                    LIB,
+                    FcPatternAddInteger,
+                    self.d,
+                    object.as_ptr(),
+                    value
+                );
+            }
+        }
+
+        // FcPatternAddDouble
+        pub fn push_double(&mut self, object: Object, value: f64) {
+            unsafe {
+                ffi_dispatch!(
+                    feature = "source-fontconfig-dlopen",
+                    LIB,
+                    FcPatternAddDouble,
+                    self.d,
+                    object.as_ptr(),
+                    value
+                );
+            }
+        }
+
+        // A read-only view of this pattern, for querying its properties.
+        pub fn as_pattern_ref(&self) -> PatternRef {
+            PatternRef { d: self.d }
+        }
+
         // FcConfigSubstitute
         pub fn config_substitute(&mut self, match_kind: MatchKind) {
             unsafe {
@@ -449,6 +711,28 @@ mod fc {
             }
         }
 
+        // FcFontMatch
+        //
+        // The returned pattern has had `<match target="font">` rules applied to it.
+        pub fn font_match(&self, config: &Config) -> Result<Pattern, Error> {
+            let mut res = ffi::FcResultMatch;
+            let d = unsafe {
+                ffi_dispatch!(
+                    feature = "source-fontconfig-dlopen",
+                    LIB,
+                    FcFontMatch,
+                    config.d,
+                    self.d,
+                    &mut res
+                )
+            };
+
+            if d.is_null() {
+                return Err(Error::NoMatch);
+            }
+            Ok(Pattern::from_ptr(d))
+        }
+
         // FcFontList
         pub fn list(&self, config: &Config, set: ObjectSet) -> Result<FontSet, Error> {
             let d = unsafe {
@@ -537,6 +821,48 @@ mod fc {
                 Some(integer)
             }
         }
+
+        // FcPatternGetBool
+        pub fn get_bool(&self, object: Object) -> Option<bool> {
+            unsafe {
+                let mut value = 0;
+                let res = ffi_dispatch!(
+                    feature = "source-fontconfig-dlopen",
+                    LIB,
+                    FcPatternGetBool,
+                    self.d,
+                    object.as_ptr(),
+                    0,
+                    &mut value
+                );
+                if res != ffi::FcResultMatch {
+                    return None;
+                }
+
+                Some(value != 0)
+            }
+        }
+
+        // FcPatternGetDouble
+        pub fn get_double(&self, object: Object) -> Option<f64> {
+            unsafe {
+                let mut value = 0.0;
+                let res = ffi_dispatch!(
+                    feature = "source-fontconfig-dlopen",
+                    LIB,
+                    FcPatternGetDouble,
+                    self.d,
+                    object.as_ptr(),
+                    0,
+                    &mut value
+                );
+                if res != ffi::FcResultMatch {
+                    return None;
+                }
+
+                Some(value)
+            }
+        }
     }
 
     pub struct FontSet {
@@ -633,3 +959,31 @@ mod fc {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{weight_to_fc, FontconfigSource};
+    use crate::loader::Loader;
+    use crate::properties::{Properties, Weight};
+
+    #[test]
+    fn test_weight_to_fc() {
+        assert_eq!(weight_to_fc(Weight::THIN), 0);
+        assert_eq!(weight_to_fc(Weight::NORMAL), 80);
+        assert_eq!(weight_to_fc(Weight(450.0)), 90);
+        assert_eq!(weight_to_fc(Weight::BOLD), 200);
+        assert_eq!(weight_to_fc(Weight(1200.0)), 215);
+    }
+
+    #[test]
+    fn test_match_font() {
+        // Fontconfig picks some font whatever the family, unless there are none at all.
+        let source = FontconfigSource::new();
+        let handle = match source.match_font("monospace", Properties::new().weight(Weight::BOLD)) {
+            Ok(handle) => handle,
+            Err(_) => return,
+        };
+        let font = handle.load().unwrap();
+        assert!(font.properties().weight >= Weight::SEMIBOLD);
+    }
+}
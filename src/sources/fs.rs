@@ -22,6 +22,8 @@ use walkdir::WalkDir;
 #[cfg(not(any(target_os = "android", target_family = "windows")))]
 use dirs_next;
 #[cfg(target_family = "windows")]
+use std::env;
+#[cfg(target_family = "windows")]
 use std::ffi::OsString;
 #[cfg(target_family = "windows")]
 use std::os::windows::ffi::OsStringExt;
@@ -35,7 +37,9 @@ use crate::family_handle::FamilyHandle;
 use crate::family_name::FamilyName;
 use crate::file_type::FileType;
 use crate::font::Font;
+use crate::font_changes::{self, FontChangeReceiver};
 use crate::handle::Handle;
+use crate::matching::MatchScorer;
 use crate::properties::Properties;
 use crate::source::Source;
 use crate::sources::mem::MemSource;
@@ -48,6 +52,7 @@ use crate::sources::mem::MemSource;
 #[allow(missing_debug_implementations)]
 pub struct FsSource {
     mem_source: MemSource,
+    directories: Vec<PathBuf>,
 }
 
 impl Default for FsSource {
@@ -63,23 +68,66 @@ impl FsSource {
     /// locate fonts in the typical platform directories, but it is too simple to pick up fonts
     /// that are stored in unusual locations but nevertheless properly installed.
     pub fn new() -> FsSource {
-        let mut fonts = vec![];
-        for font_directory in default_font_directories() {
-            fonts.extend(Self::discover_fonts(&font_directory));
-        }
+        FsSource::from_dirs(default_font_directories())
+    }
+
+    /// Recursively scans the directory tree rooted at `path` and indexes the fonts found within by
+    /// family and properties.
+    ///
+    /// Each file is analyzed to determine whether it is a single font or a collection, and every
+    /// font in a collection is indexed. Files that are not fonts, or that fail to load, are
+    /// skipped. Symbolic links are followed.
+    pub fn from_dir<P>(path: P) -> FsSource
+    where
+        P: AsRef<Path>,
+    {
+        FsSource::from_dirs(vec![path.as_ref().to_owned()])
+    }
 
+    fn from_dirs(directories: Vec<PathBuf>) -> FsSource {
+        let mem_source = MemSource::from_loadable_fonts(Self::discover_all_fonts(&directories));
         FsSource {
-            mem_source: MemSource::from_fonts(fonts.into_iter()).unwrap(),
+            mem_source,
+            directories,
         }
     }
 
-    fn discover_fonts(path: &Path) -> Vec<Handle> {
+    fn discover_all_fonts(directories: &[PathBuf]) -> impl Iterator<Item = Handle> + '_ {
+        directories
+            .iter()
+            .flat_map(|directory| Self::discover_fonts(directory))
+    }
+
+    /// Scans the directories again, so that fonts added, removed, or changed since this source was
+    /// created or last rescanned are picked up.
+    ///
+    /// The new set of fonts replaces the old one all at once, as `MemSource::replace_all()` does,
+    /// so this can be called while other threads are querying the source.
+    pub fn rescan(&self) {
+        self.mem_source
+            .replace_with_loadable_fonts(Self::discover_all_fonts(&self.directories))
+    }
+
+    /// Returns a channel that receives an event whenever files change in this source's
+    /// directories. Call `rescan()` to pick up the changes.
+    ///
+    /// The directories are watched with the operating system's file notifications on a background
+    /// thread, which exits once the receiver is dropped. Without the default `watch` feature, they
+    /// are checked every `font_changes::DEFAULT_POLL_INTERVAL` instead.
+    pub fn subscribe_changes(&self) -> FontChangeReceiver {
+        font_changes::subscribe(self.directories.clone())
+    }
+
+    pub(crate) fn discover_fonts(path: &Path) -> Vec<Handle> {
         let mut fonts = vec![];
-        for directory_entry in WalkDir::new(path).into_iter() {
+        for directory_entry in WalkDir::new(path).follow_links(true).into_iter() {
             let directory_entry = match directory_entry {
                 Ok(directory_entry) => directory_entry,
                 Err(_) => continue,
             };
+            if !directory_entry.file_type().is_file() {
+                continue;
+            }
             let path = directory_entry.path();
             let mut file = match File::open(path) {
                 Err(_) => continue,
@@ -98,15 +146,15 @@ impl FsSource {
         fonts
     }
 
-    /// Indexes all fonts found in `path`
+    /// Indexes all fonts found in `path`.
+    ///
+    /// This is equivalent to `from_dir()`.
+    #[inline]
     pub fn in_path<P>(path: P) -> FsSource
     where
         P: AsRef<Path>,
     {
-        let fonts = Self::discover_fonts(path.as_ref());
-        FsSource {
-            mem_source: MemSource::from_fonts(fonts.into_iter()).unwrap(),
-        }
+        FsSource::from_dir(path)
     }
 
     /// Returns paths of all fonts installed on the system.
@@ -143,7 +191,7 @@ impl FsSource {
         family_names: &[FamilyName],
         properties: &Properties,
     ) -> Result<Handle, SelectionError> {
-        <Self as Source>::select_best_match(self, family_names, properties)
+        self.mem_source.select_best_match(family_names, properties)
     }
 }
 
@@ -166,6 +214,26 @@ impl Source for FsSource {
         self.select_by_postscript_name(postscript_name)
     }
 
+    #[inline]
+    fn select_best_match(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.select_best_match(family_names, properties)
+    }
+
+    #[inline]
+    fn select_best_match_with_scorer(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+        scorer: &dyn MatchScorer,
+    ) -> Result<Handle, SelectionError> {
+        self.mem_source
+            .select_best_match_with_scorer(family_names, properties, scorer)
+    }
+
     #[inline]
     fn as_any(&self) -> &dyn Any {
         self
@@ -178,12 +246,12 @@ impl Source for FsSource {
 }
 
 #[cfg(target_os = "android")]
-fn default_font_directories() -> Vec<PathBuf> {
+pub(crate) fn default_font_directories() -> Vec<PathBuf> {
     vec![PathBuf::from("/system/fonts")]
 }
 
 #[cfg(target_family = "windows")]
-fn default_font_directories() -> Vec<PathBuf> {
+pub(crate) fn default_font_directories() -> Vec<PathBuf> {
     unsafe {
         let mut buffer = vec![0; MAX_PATH];
         let len = sysinfoapi::GetWindowsDirectoryW(buffer.as_mut_ptr(), buffer.len() as UINT);
@@ -192,12 +260,20 @@ fn default_font_directories() -> Vec<PathBuf> {
 
         let mut path = PathBuf::from(OsString::from_wide(&buffer));
         path.push("Fonts");
-        vec![path]
+        let mut directories = vec![path];
+
+        // Fonts installed for the current user only, on Windows 10 1809 and later.
+        if let Some(local_app_data) = env::var_os("LOCALAPPDATA") {
+            let mut path = PathBuf::from(local_app_data);
+            path.extend(&["Microsoft", "Windows", "Fonts"]);
+            directories.push(path);
+        }
+        directories
     }
 }
 
 #[cfg(target_os = "macos")]
-fn default_font_directories() -> Vec<PathBuf> {
+pub(crate) fn default_font_directories() -> Vec<PathBuf> {
     let mut directories = vec![
         PathBuf::from("/System/Library/Fonts"),
         PathBuf::from("/Library/Fonts"),
@@ -212,7 +288,7 @@ fn default_font_directories() -> Vec<PathBuf> {
 }
 
 #[cfg(not(any(target_os = "android", target_family = "windows", target_os = "macos")))]
-fn default_font_directories() -> Vec<PathBuf> {
+pub(crate) fn default_font_directories() -> Vec<PathBuf> {
     let mut directories = vec![
         PathBuf::from("/usr/share/fonts"),
         PathBuf::from("/usr/local/share/fonts"),
@@ -229,3 +305,52 @@ fn default_font_directories() -> Vec<PathBuf> {
     }
     directories
 }
+
+#[cfg(test)]
+mod test {
+    use super::FsSource;
+    use crate::family_name::FamilyName;
+    use crate::loader::Loader;
+    use crate::properties::{Properties, Style};
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn test_from_dir_indexes_fonts_recursively() {
+        let source = FsSource::from_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/tests"));
+        let families = source.all_families().unwrap();
+        assert!(families.contains(&"EB Garamond".to_owned()));
+        assert!(families.contains(&"Inconsolata".to_owned()));
+
+        let handle = source
+            .select_best_match(
+                &[FamilyName::Title("EB Garamond".to_owned())],
+                Properties::new().style(Style::Italic),
+            )
+            .unwrap();
+        let font = handle.load().unwrap();
+        assert_eq!(font.postscript_name().unwrap(), "EBGaramond12-Italic");
+    }
+
+    #[test]
+    fn test_rescan_picks_up_new_fonts() {
+        let directory = env::temp_dir().join(format!("font-kit-rescan-test-{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        let source = FsSource::from_dir(&directory);
+        assert!(source.all_fonts().unwrap().is_empty());
+        fs::copy(
+            "resources/tests/inconsolata/Inconsolata-Regular.ttf",
+            directory.join("Inconsolata-Regular.ttf"),
+        )
+        .unwrap();
+        source.rescan();
+        assert_eq!(source.all_families().unwrap(), ["Inconsolata"]);
+
+        fs::remove_dir_all(&directory).unwrap();
+        source.rescan();
+        assert!(source.all_fonts().unwrap().is_empty());
+    }
+}
@@ -13,28 +13,39 @@
 use crate::error::{FontLoadingError, SelectionError};
 use crate::family_handle::FamilyHandle;
 use crate::family_name::FamilyName;
+use crate::file_type::FileType;
 use crate::font::Font;
 use crate::handle::Handle;
 use crate::loader::Loader;
+use crate::matching::{self, MatchScorer};
 use crate::properties::Properties;
-use crate::source::Source;
+use crate::source::{self, Source};
+#[cfg(any(not(target_arch = "wasm32"), feature = "web-local-fonts"))]
+use log::warn;
 use std::any::Any;
+use std::sync::{Arc, RwLock};
 
 /// A source that keeps fonts in memory.
+///
+/// The set of fonts can be replaced atomically with `replace_all()` while other threads are
+/// querying the source.
 #[allow(missing_debug_implementations)]
 pub struct MemSource {
-    families: Vec<FamilyEntry>,
+    // Queries take a snapshot of the entries, so that a concurrent `replace_all()` never leaves
+    // them with a mix of the old and new fonts.
+    families: RwLock<Arc<Vec<FamilyEntry>>>,
 }
 
 impl MemSource {
     /// Creates a new empty memory source.
     pub fn empty() -> MemSource {
-        MemSource { families: vec![] }
+        MemSource::with_entries(vec![])
     }
 
     /// Creates a new memory source that contains the given set of font handles.
     ///
-    /// The fonts referenced by the handles are eagerly loaded into memory.
+    /// The fonts are indexed with `Handle::info()`, which reads their names and properties
+    /// without loading them.
     pub fn from_fonts<I>(fonts: I) -> Result<MemSource, FontLoadingError>
     where
         I: Iterator<Item = Handle>,
@@ -43,8 +54,86 @@ impl MemSource {
         for handle in fonts {
             add_font(handle, &mut families)?;
         }
+        Ok(MemSource::with_entries(families))
+    }
+
+    /// Creates a new memory source from raw font data, such as fonts embedded in the application
+    /// with `include_bytes!`.
+    ///
+    /// If any of the data represents a collection, every font in the collection is added.
+    pub fn from_bytes<I>(fonts: I) -> Result<MemSource, FontLoadingError>
+    where
+        I: IntoIterator<Item = Arc<Vec<u8>>>,
+    {
+        let mut handles = vec![];
+        for font_data in fonts {
+            handles.extend(handles_from_bytes(font_data)?);
+        }
+        MemSource::from_fonts(handles.into_iter())
+    }
+
+    /// Creates a new memory source from the given set of font handles, skipping (and logging) any
+    /// fonts that fail to load instead of failing as a whole.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn from_loadable_fonts<I>(fonts: I) -> MemSource
+    where
+        I: Iterator<Item = Handle>,
+    {
+        MemSource::with_entries(loadable_entries(fonts))
+    }
+
+    /// Creates a new memory source from entries that have already been indexed, without loading
+    /// any fonts.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn from_entries(families: Vec<FamilyEntry>) -> MemSource {
+        MemSource::with_entries(families)
+    }
+
+    fn with_entries(mut families: Vec<FamilyEntry>) -> MemSource {
+        families.sort_by(|a, b| a.family_name.cmp(&b.family_name));
+        MemSource {
+            families: RwLock::new(Arc::new(families)),
+        }
+    }
+
+    /// Returns a snapshot of the indexed entries, sorted by family name.
+    #[inline]
+    pub(crate) fn entries(&self) -> Arc<Vec<FamilyEntry>> {
+        Arc::clone(&self.families.read().unwrap())
+    }
+
+    // Returns the entries for modification, copying them first if a query holds a snapshot.
+    fn entries_mut(&mut self) -> &mut Vec<FamilyEntry> {
+        Arc::make_mut(self.families.get_mut().unwrap())
+    }
+
+    /// Replaces all the fonts in this source with the fonts referenced by the given handles.
+    ///
+    /// The new fonts are loaded before anything is replaced, and then swapped in all at once:
+    /// queries running concurrently on other threads see either the old set of fonts or the new
+    /// one, never a mixture. If any font fails to load, the source is left unchanged.
+    pub fn replace_all<I>(&self, handles: I) -> Result<(), FontLoadingError>
+    where
+        I: IntoIterator<Item = Handle>,
+    {
+        let mut families = vec![];
+        for handle in handles {
+            add_font(handle, &mut families)?;
+        }
+        families.sort_by(|a, b| a.family_name.cmp(&b.family_name));
+        *self.families.write().unwrap() = Arc::new(families);
+        Ok(())
+    }
+
+    /// Like `replace_all()`, but skips fonts that fail to load, as `from_loadable_fonts()` does.
+    #[cfg(any(not(target_arch = "wasm32"), feature = "web-local-fonts"))]
+    pub(crate) fn replace_with_loadable_fonts<I>(&self, fonts: I)
+    where
+        I: Iterator<Item = Handle>,
+    {
+        let mut families = loadable_entries(fonts);
         families.sort_by(|a, b| a.family_name.cmp(&b.family_name));
-        Ok(MemSource { families })
+        *self.families.write().unwrap() = Arc::new(families);
     }
 
     /// Add an existing font handle to a `MemSource`.
@@ -55,9 +144,10 @@ impl MemSource {
     /// `Handle` iterator, since this method sorts after every addition, rather than once at the
     /// end.
     pub fn add_font(&mut self, handle: Handle) -> Result<Font, FontLoadingError> {
-        let font = add_font(handle, &mut self.families)?;
-        self.families
-            .sort_by(|a, b| a.family_name.cmp(&b.family_name));
+        let font = Font::from_handle(&handle)?;
+        let families = self.entries_mut();
+        add_font(handle, families)?;
+        families.sort_by(|a, b| a.family_name.cmp(&b.family_name));
         Ok(font)
     }
 
@@ -70,18 +160,44 @@ impl MemSource {
         &mut self,
         handles: impl Iterator<Item = Handle>,
     ) -> Result<(), FontLoadingError> {
+        let families = self.entries_mut();
         for handle in handles {
-            add_font(handle, &mut self.families)?;
+            add_font(handle, families)?;
         }
-        self.families
-            .sort_by(|a, b| a.family_name.cmp(&b.family_name));
+        families.sort_by(|a, b| a.family_name.cmp(&b.family_name));
         Ok(())
     }
 
+    /// Removes the font with the given PostScript name from this `MemSource`.
+    ///
+    /// Returns the handle of the removed font, or `None` if no font had that name.
+    pub fn remove_font_by_postscript_name(&mut self, postscript_name: &str) -> Option<Handle> {
+        let families = self.entries_mut();
+        let index = families
+            .iter()
+            .position(|family_entry| family_entry.postscript_name == postscript_name)?;
+        Some(families.remove(index).font)
+    }
+
+    /// Removes all the fonts in the given family from this `MemSource`.
+    ///
+    /// Returns the handles of the removed fonts, which is empty if the family was not present.
+    pub fn remove_family(&mut self, family_name: &str) -> Vec<Handle> {
+        let mut removed = vec![];
+        self.entries_mut().retain(|family_entry| {
+            if family_entry.family_name != family_name {
+                return true;
+            }
+            removed.push(family_entry.font.clone());
+            false
+        });
+        removed
+    }
+
     /// Returns paths of all fonts installed on the system.
     pub fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
         Ok(self
-            .families
+            .entries()
             .iter()
             .map(|family| family.font.clone())
             .collect())
@@ -90,7 +206,7 @@ impl MemSource {
     /// Returns the names of all families installed on the system.
     pub fn all_families(&self) -> Result<Vec<String>, SelectionError> {
         let mut families = vec![];
-        for family in &self.families {
+        for family in self.entries().iter() {
             if families.last() == Some(&family.family_name) {
                 continue;
             }
@@ -105,24 +221,22 @@ impl MemSource {
     ///
     /// FIXME(pcwalton): Case-insensitive comparison.
     pub fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
-        let mut first_family_index = self
-            .families
+        let entries = self.entries();
+        let mut first_family_index = entries
             .binary_search_by(|family| (*family.family_name).cmp(family_name))
             .map_err(|_| SelectionError::NotFound)?;
 
-        while first_family_index > 0
-            && self.families[first_family_index - 1].family_name == family_name
-        {
+        while first_family_index > 0 && entries[first_family_index - 1].family_name == family_name {
             first_family_index -= 1
         }
         let mut last_family_index = first_family_index;
-        while last_family_index + 1 < self.families.len()
-            && self.families[last_family_index + 1].family_name == family_name
+        while last_family_index + 1 < entries.len()
+            && entries[last_family_index + 1].family_name == family_name
         {
             last_family_index += 1
         }
 
-        let families = &self.families[first_family_index..(last_family_index + 1)];
+        let families = &entries[first_family_index..(last_family_index + 1)];
         Ok(FamilyHandle::from_font_handles(
             families.iter().map(|family| family.font.clone()),
         ))
@@ -136,7 +250,7 @@ impl MemSource {
         &self,
         postscript_name: &str,
     ) -> Result<Handle, SelectionError> {
-        self.families
+        self.entries()
             .iter()
             .filter(|family_entry| family_entry.postscript_name == postscript_name)
             .map(|family_entry| family_entry.font.clone())
@@ -146,14 +260,94 @@ impl MemSource {
 
     /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
     /// handle.
-    #[inline]
+    ///
+    /// This uses the properties recorded when each font was added, so no fonts are reloaded.
     pub fn select_best_match(
         &self,
         family_names: &[FamilyName],
         properties: &Properties,
     ) -> Result<Handle, SelectionError> {
-        <Self as Source>::select_best_match(self, family_names, properties)
+        let families = self.entries();
+        for family_name in family_names {
+            let entries = match family_entries(&families, family_name) {
+                Some(entries) => entries,
+                None => continue,
+            };
+            let candidates: Vec<Properties> =
+                entries.iter().map(|entry| entry.properties).collect();
+            if let Ok(index) = matching::find_best_match(&candidates, properties) {
+                return Ok(entries[index].font.clone());
+            }
+        }
+        Err(SelectionError::NotFound)
     }
+
+    /// Performs font matching like `select_best_match()`, but only considers the fonts in the
+    /// matched family that `scorer` ranks highest.
+    pub fn select_best_match_with_scorer(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+        scorer: &dyn MatchScorer,
+    ) -> Result<Handle, SelectionError> {
+        let families = self.entries();
+        for family_name in family_names {
+            let entries = match family_entries(&families, family_name) {
+                Some(entries) => entries,
+                None => continue,
+            };
+            let candidates: Vec<Properties> =
+                entries.iter().map(|entry| entry.properties).collect();
+            let scores: Vec<f32> = entries
+                .iter()
+                .map(|entry| scorer.score(&entry.font, &entry.properties))
+                .collect();
+            if let Ok(index) =
+                matching::find_best_match_with_scores(&candidates, &scores, properties)
+            {
+                return Ok(entries[index].font.clone());
+            }
+        }
+        Err(SelectionError::NotFound)
+    }
+}
+
+/// Returns the entries for the first family that `family_name` resolves to.
+fn family_entries<'a>(
+    families: &'a [FamilyEntry],
+    family_name: &FamilyName,
+) -> Option<&'a [FamilyEntry]> {
+    source::family_names_for(family_name)
+        .into_iter()
+        .find_map(|family_name| family_entries_by_name(families, family_name))
+}
+
+/// Returns the entries for the given family, which are contiguous since they are sorted.
+fn family_entries_by_name<'a>(
+    families: &'a [FamilyEntry],
+    family_name: &str,
+) -> Option<&'a [FamilyEntry]> {
+    let start = families.partition_point(|family| &*family.family_name < family_name);
+    let end = start
+        + families[start..]
+            .iter()
+            .take_while(|family| family.family_name == family_name)
+            .count();
+    if start == end {
+        None
+    } else {
+        Some(&families[start..end])
+    }
+}
+
+// Returns handles to every font in the given data, which may be a collection.
+pub(crate) fn handles_from_bytes(font_data: Arc<Vec<u8>>) -> Result<Vec<Handle>, FontLoadingError> {
+    Ok(match Font::analyze_bytes(Arc::clone(&font_data))? {
+        FileType::Single => vec![Handle::from_memory(font_data, 0)],
+        FileType::Collection(font_count) => (0..font_count)
+            .map(|font_index| Handle::from_memory(font_data.clone(), font_index))
+            .collect(),
+    })
 }
 
 impl Source for MemSource {
@@ -175,6 +369,25 @@ impl Source for MemSource {
         self.select_by_postscript_name(postscript_name)
     }
 
+    #[inline]
+    fn select_best_match(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.select_best_match(family_names, properties)
+    }
+
+    #[inline]
+    fn select_best_match_with_scorer(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+        scorer: &dyn MatchScorer,
+    ) -> Result<Handle, SelectionError> {
+        self.select_best_match_with_scorer(family_names, properties, scorer)
+    }
+
     #[inline]
     fn as_any(&self) -> &dyn Any {
         self
@@ -186,21 +399,121 @@ impl Source for MemSource {
     }
 }
 
-/// Adds a font, but doesn't sort. Returns the font that was created to check for validity.
-fn add_font(handle: Handle, families: &mut Vec<FamilyEntry>) -> Result<Font, FontLoadingError> {
-    let font = Font::from_handle(&handle)?;
-    if let Some(postscript_name) = font.postscript_name() {
+// Indexes the fonts, skipping those that fail to load. The entries aren't sorted.
+#[cfg(any(not(target_arch = "wasm32"), feature = "web-local-fonts"))]
+fn loadable_entries<I>(fonts: I) -> Vec<FamilyEntry>
+where
+    I: Iterator<Item = Handle>,
+{
+    let mut families = vec![];
+    for handle in fonts {
+        if let Err(error) = add_font(handle.clone(), &mut families) {
+            warn!("Skipping font {:?} that failed to load: {}", handle, error);
+        }
+    }
+    families
+}
+
+/// Adds a font, but doesn't sort. Only the font's summary is read, not the font itself.
+fn add_font(handle: Handle, families: &mut Vec<FamilyEntry>) -> Result<(), FontLoadingError> {
+    let info = handle.info()?;
+    if let Some(postscript_name) = info.postscript_name {
         families.push(FamilyEntry {
-            family_name: font.family_name(),
+            family_name: info.family_name,
             postscript_name,
+            properties: info.properties,
             font: handle,
         })
     }
-    Ok(font)
+    Ok(())
 }
 
-struct FamilyEntry {
-    family_name: String,
-    postscript_name: String,
-    font: Handle,
+#[derive(Clone)]
+pub(crate) struct FamilyEntry {
+    pub(crate) family_name: String,
+    pub(crate) postscript_name: String,
+    pub(crate) properties: Properties,
+    pub(crate) font: Handle,
+}
+
+#[cfg(test)]
+mod test {
+    use super::MemSource;
+    use crate::family_name::FamilyName;
+    use crate::font::Font;
+    use crate::handle::Handle;
+    use crate::properties::Properties;
+    use crate::source::{MissingFontPolicy, Source};
+    use crate::test_fixtures::{EB_GARAMOND, INCONSOLATA};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_add_and_remove_fonts() {
+        let mut source = MemSource::from_bytes(vec![
+            Arc::new(INCONSOLATA.to_vec()),
+            Arc::new(EB_GARAMOND.to_vec()),
+        ])
+        .unwrap();
+        assert_eq!(
+            source.all_families().unwrap(),
+            ["EB Garamond", "Inconsolata"]
+        );
+        assert!(source
+            .select_best_match(
+                &[FamilyName::Title("Inconsolata".to_owned())],
+                &Properties::new()
+            )
+            .is_ok());
+
+        assert!(source
+            .remove_font_by_postscript_name("Inconsolata-Regular")
+            .is_some());
+        assert!(source
+            .remove_font_by_postscript_name("Inconsolata-Regular")
+            .is_none());
+        assert!(source
+            .select_best_match(
+                &[FamilyName::Title("Inconsolata".to_owned())],
+                &Properties::new()
+            )
+            .is_err());
+
+        assert_eq!(source.remove_family("EB Garamond").len(), 1);
+        assert!(source.all_fonts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_replace_all() {
+        let source = MemSource::from_bytes(vec![Arc::new(INCONSOLATA.to_vec())]).unwrap();
+        let snapshot = source.entries();
+        source
+            .replace_all(vec![Handle::from_memory(Arc::new(EB_GARAMOND.to_vec()), 0)])
+            .unwrap();
+        assert_eq!(source.all_families().unwrap(), ["EB Garamond"]);
+        assert_eq!(snapshot[0].family_name, "Inconsolata");
+
+        let broken = Handle::from_memory(Arc::new(vec![0; 16]), 0);
+        assert!(source.replace_all(vec![broken]).is_err());
+        assert_eq!(source.all_families().unwrap(), ["EB Garamond"]);
+    }
+
+    #[test]
+    fn test_missing_font_policy() {
+        let source = MemSource::from_fonts(vec![].into_iter()).unwrap();
+        let family_names = [FamilyName::Title("Nonexistent".to_owned())];
+        for &policy in &[MissingFontPolicy::Error, MissingFontPolicy::AnyInstalled] {
+            assert!(source
+                .select_best_match_with_policy(&family_names, &Properties::new(), policy)
+                .is_err());
+        }
+
+        let handle = source
+            .select_best_match_with_policy(
+                &family_names,
+                &Properties::new(),
+                MissingFontPolicy::Bundled,
+            )
+            .unwrap();
+        assert!(Font::from_handle(&handle).is_ok());
+    }
 }
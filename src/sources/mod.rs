@@ -12,7 +12,16 @@
 //!
 //! The system-specific sources (Core Text, DirectWrite, and Fontconfig) contain the fonts that are
 //! installed on the system. The remaining databases (`fs`, `mem`, and `multi`) allow `font-kit` to
-//! query fonts not installed on the system.
+//! query fonts not installed on the system. The `cache` database indexes fonts on disk like `fs`,
+//! but keeps the index in a file so that it doesn't have to be rebuilt on every run. The `alias`
+//! source wraps any other source and resolves family names through aliases and substitutes. The
+//! `web` source holds the fonts that a web page has been given access to, and is the system
+//! source on WebAssembly.
+
+pub mod alias;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cache;
 
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 pub mod core_text;
@@ -37,3 +46,5 @@ pub mod fs;
 pub mod mem;
 
 pub mod multi;
+
+pub mod web;
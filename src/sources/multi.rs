@@ -21,6 +21,7 @@ use crate::properties::Properties;
 use crate::source::Source;
 use std::{
     any::Any,
+    collections::HashSet,
     fmt,
     ops::{Index, IndexMut},
     slice,
@@ -30,45 +31,106 @@ use std::{
 ///
 /// This is useful when an application wants a library of fonts consisting of the installed system
 /// fonts plus some other application-supplied fonts.
+///
+/// Each source has a priority. Sources with higher priorities are queried first, and sources with
+/// equal priorities are queried in the order in which they were added. When a family is present in
+/// several sources, its fonts are merged, and fonts with the same PostScript name are taken from
+/// the highest-priority source only. So, for example, giving an application's `MemSource` a higher
+/// priority than the `SystemSource` makes lookups prefer the bundled fonts and fall back to the
+/// installed ones.
 #[allow(missing_debug_implementations)]
 pub struct MultiSource {
+    // Sorted by descending priority; `priorities` runs parallel to `subsources`.
     subsources: Vec<Box<dyn Source>>,
+    priorities: Vec<i32>,
 }
 
 impl MultiSource {
     /// Creates a new source that contains all the fonts in the supplied sources.
+    ///
+    /// All sources get priority 0, so they are queried in the order given.
     pub fn from_sources(subsources: Vec<Box<dyn Source>>) -> MultiSource {
-        MultiSource { subsources }
+        let priorities = vec![0; subsources.len()];
+        MultiSource {
+            subsources,
+            priorities,
+        }
+    }
+
+    /// Creates a new source that contains all the fonts in the supplied sources, each paired with
+    /// its priority.
+    pub fn from_prioritized_sources(subsources: Vec<(Box<dyn Source>, i32)>) -> MultiSource {
+        let mut source = MultiSource::from_sources(vec![]);
+        for (subsource, priority) in subsources {
+            source.add_source(subsource, priority);
+        }
+        source
+    }
+
+    /// Adds a source with the given priority.
+    ///
+    /// It is queried after all sources with a higher or equal priority, and before all sources
+    /// with a lower one.
+    pub fn add_source(&mut self, subsource: Box<dyn Source>, priority: i32) {
+        let index = self
+            .priorities
+            .iter()
+            .position(|&other_priority| other_priority < priority)
+            .unwrap_or(self.priorities.len());
+        self.subsources.insert(index, subsource);
+        self.priorities.insert(index, priority);
+    }
+
+    /// Returns the priority of the source at the given index.
+    #[inline]
+    pub fn priority(&self, index: usize) -> i32 {
+        self.priorities[index]
     }
 
     /// Returns paths of all fonts installed on the system.
+    ///
+    /// The handles are ordered by source priority. They are not deduplicated, since that would
+    /// require loading every font.
     pub fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
         let mut handles = vec![];
         for subsource in &self.subsources {
-            handles.extend(subsource.all_fonts()?.into_iter())
+            handles.extend(subsource.all_fonts()?)
         }
         Ok(handles)
     }
 
-    /// Returns the names of all families installed on the system.
+    /// Returns the names of all families installed on the system, without duplicates.
     pub fn all_families(&self) -> Result<Vec<String>, SelectionError> {
         let mut families = vec![];
+        let mut seen = HashSet::new();
         for subsource in &self.subsources {
-            families.extend(subsource.all_families()?.into_iter())
+            for family in subsource.all_families()? {
+                if seen.insert(family.clone()) {
+                    families.push(family);
+                }
+            }
         }
         Ok(families)
     }
 
     /// Looks up a font family by name and returns the handles of all the fonts in that family.
+    ///
+    /// If several sources contain the family, their fonts are merged in priority order, dropping
+    /// fonts whose PostScript names were already provided by a higher-priority source.
     pub fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        let mut families = vec![];
         for subsource in &self.subsources {
             match subsource.select_family_by_name(family_name) {
-                Ok(family) => return Ok(family),
+                Ok(family) => families.push(family),
                 Err(SelectionError::NotFound) => {}
                 Err(err) => return Err(err),
             }
         }
-        Err(SelectionError::NotFound)
+        match families.len() {
+            0 => Err(SelectionError::NotFound),
+            1 => Ok(families.pop().unwrap()),
+            _ => Ok(merge_families(families)),
+        }
     }
 
     /// Selects a font by PostScript name, which should be a unique identifier.
@@ -124,6 +186,38 @@ impl MultiSource {
     }
 }
 
+/// Merges family handles, keeping only the first font with each PostScript name.
+///
+/// Handles to the same font file are recognized without reading anything. The PostScript names of
+/// the others come from `Handle::info()`, which only reads the fonts' `name` tables and a few
+/// other small tables.
+fn merge_families(families: Vec<FamilyHandle>) -> FamilyHandle {
+    let mut merged = FamilyHandle::new();
+    let mut paths = HashSet::new();
+    let mut postscript_names = HashSet::new();
+    for family in families {
+        for handle in family.fonts {
+            if let Handle::Path {
+                ref path,
+                font_index,
+            } = handle
+            {
+                if !paths.insert((path.clone(), font_index)) {
+                    continue;
+                }
+            }
+            let postscript_name = handle.info().ok().and_then(|info| info.postscript_name);
+            if let Some(postscript_name) = postscript_name {
+                if !postscript_names.insert(postscript_name) {
+                    continue;
+                }
+            }
+            merged.push(handle);
+        }
+    }
+    merged
+}
+
 impl Source for MultiSource {
     #[inline]
     fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
@@ -201,3 +295,43 @@ impl fmt::Debug for MultiIterMut<'_> {
         f.debug_struct("MultiIterMut").finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::MultiSource;
+    use crate::family_name::FamilyName;
+    use crate::handle::Handle;
+    use crate::loader::Loader;
+    use crate::properties::{Properties, Style};
+    use crate::sources::mem::MemSource;
+    use crate::test_fixtures::{EB_GARAMOND, EB_GARAMOND_ITALIC};
+    use std::sync::Arc;
+
+    fn mem_source(fonts: &[&'static [u8]]) -> Box<MemSource> {
+        let handles = fonts
+            .iter()
+            .map(|font| Handle::from_memory(Arc::new(font.to_vec()), 0));
+        Box::new(MemSource::from_fonts(handles).unwrap())
+    }
+
+    #[test]
+    fn test_priorities_and_deduplication() {
+        let bundled = mem_source(&[EB_GARAMOND]);
+        let system = mem_source(&[EB_GARAMOND, EB_GARAMOND_ITALIC]);
+        let source = MultiSource::from_prioritized_sources(vec![(system, 0), (bundled, 10)]);
+        assert_eq!(source.priority(0), 10);
+        assert_eq!(source.all_families().unwrap(), ["EB Garamond"]);
+
+        let family = source.select_family_by_name("EB Garamond").unwrap();
+        assert_eq!(family.fonts().len(), 2);
+
+        let handle = source
+            .select_best_match(
+                &[FamilyName::Title("EB Garamond".to_owned())],
+                Properties::new().style(Style::Italic),
+            )
+            .unwrap();
+        let font = handle.load().unwrap();
+        assert_eq!(font.postscript_name().unwrap(), "EBGaramond12-Italic");
+    }
+}
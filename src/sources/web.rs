@@ -0,0 +1,306 @@
+// font-kit/src/sources/web.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A source containing the fonts that a web page has been given access to.
+//!
+//! Browsers don't let pages enumerate installed fonts directly. Instead, the Local Font Access API
+//! (`window.queryLocalFonts()`) asks the user for permission and then returns the fonts, whose
+//! data can be read with `FontData.blob()`. With the `web-local-fonts` feature,
+//! `WebSource::query_local_fonts()` does this through `web-sys`; otherwise, the page calls the
+//! API from JavaScript and passes the resulting bytes to `WebSource::register_local_fonts()`.
+//! Every `WebSource`, including the `SystemSource` on `wasm32`, then sees those fonts.
+//!
+//! Fonts bundled with the page can be registered the same way, or kept in a `MemSource`.
+
+use lazy_static::lazy_static;
+use std::any::Any;
+use std::sync::{Arc, RwLock};
+
+use crate::error::{FontLoadingError, SelectionError};
+use crate::family_handle::FamilyHandle;
+use crate::family_name::FamilyName;
+use crate::handle::Handle;
+use crate::matching::MatchScorer;
+use crate::properties::Properties;
+use crate::source::Source;
+use crate::sources::mem::{self, MemSource};
+#[cfg(all(target_arch = "wasm32", feature = "web-local-fonts"))]
+use js_sys::{Array, Function, Promise, Reflect, Uint8Array};
+#[cfg(all(target_arch = "wasm32", feature = "web-local-fonts"))]
+use wasm_bindgen::{JsCast, JsValue};
+#[cfg(all(target_arch = "wasm32", feature = "web-local-fonts"))]
+use wasm_bindgen_futures::JsFuture;
+#[cfg(all(target_arch = "wasm32", feature = "web-local-fonts"))]
+use web_sys::Blob;
+
+lazy_static! {
+    static ref LOCAL_FONTS: RwLock<MemSource> = RwLock::new(MemSource::empty());
+}
+
+/// A source containing the fonts registered with `register_local_fonts()`.
+///
+/// All `WebSource`s share the registered fonts, so a source created before fonts are registered
+/// sees them too.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WebSource {
+    _private: (),
+}
+
+impl WebSource {
+    /// Returns a source containing the fonts registered so far.
+    #[inline]
+    pub fn new() -> WebSource {
+        WebSource { _private: () }
+    }
+
+    /// Adds fonts from raw font data, typically the contents of the blobs that the Local Font
+    /// Access API returns.
+    ///
+    /// If any of the data represents a collection, every font in the collection is added. If any
+    /// font fails to load, no fonts are added.
+    pub fn register_local_fonts<I>(fonts: I) -> Result<(), FontLoadingError>
+    where
+        I: IntoIterator<Item = Arc<Vec<u8>>>,
+    {
+        let mut handles = vec![];
+        for font_data in fonts {
+            handles.extend(mem::handles_from_bytes(font_data)?);
+        }
+        let local_fonts = LOCAL_FONTS.write().unwrap();
+        let mut all_fonts = local_fonts.all_fonts().unwrap_or_default();
+        all_fonts.extend(handles);
+        local_fonts.replace_all(all_fonts)
+    }
+
+    /// Asks the browser for the fonts installed on the system with the Local Font Access API and
+    /// registers them, returning how many fonts were added.
+    ///
+    /// Browsers ask the user for permission first, and only allow that in response to a user
+    /// gesture such as a click. If the API is unavailable or permission is denied,
+    /// `SelectionError::CannotAccessSource` is returned. Fonts that fail to load, such as those
+    /// in bitmap formats, are skipped.
+    #[cfg(all(target_arch = "wasm32", feature = "web-local-fonts"))]
+    pub async fn query_local_fonts() -> Result<usize, SelectionError> {
+        let font_data = local_font_data()
+            .await
+            .map_err(|_| SelectionError::CannotAccessSource)?;
+        let mut handles = vec![];
+        for font_data in font_data {
+            if let Ok(font_handles) = mem::handles_from_bytes(Arc::new(font_data)) {
+                handles.extend(font_handles);
+            }
+        }
+        let local_fonts = LOCAL_FONTS.write().unwrap();
+        let mut all_fonts = local_fonts.all_fonts().unwrap_or_default();
+        let old_count = all_fonts.len();
+        all_fonts.extend(handles);
+        local_fonts.replace_with_loadable_fonts(all_fonts.into_iter());
+        Ok(local_fonts.all_fonts().map_or(0, |fonts| fonts.len()) - old_count)
+    }
+
+    /// Removes all registered fonts, for example when the user revokes the permission.
+    pub fn clear_local_fonts() {
+        *LOCAL_FONTS.write().unwrap() = MemSource::empty();
+    }
+
+    /// Returns the handles of all registered fonts.
+    #[inline]
+    pub fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
+        LOCAL_FONTS.read().unwrap().all_fonts()
+    }
+
+    /// Returns the names of all families of registered fonts.
+    #[inline]
+    pub fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        LOCAL_FONTS.read().unwrap().all_families()
+    }
+
+    /// Looks up a font family by name and returns the handles of all the fonts in that family.
+    #[inline]
+    pub fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        LOCAL_FONTS
+            .read()
+            .unwrap()
+            .select_family_by_name(family_name)
+    }
+
+    /// Selects a font by PostScript name, which should be a unique identifier.
+    #[inline]
+    pub fn select_by_postscript_name(
+        &self,
+        postscript_name: &str,
+    ) -> Result<Handle, SelectionError> {
+        LOCAL_FONTS
+            .read()
+            .unwrap()
+            .select_by_postscript_name(postscript_name)
+    }
+
+    /// Performs font matching according to the CSS Fonts Level 3 specification and returns the
+    /// handle.
+    #[inline]
+    pub fn select_best_match(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        LOCAL_FONTS
+            .read()
+            .unwrap()
+            .select_best_match(family_names, properties)
+    }
+}
+
+// Reads the data of every font that `window.queryLocalFonts()` returns. `web-sys` only binds the
+// API with unstable flags, so it is called by name.
+#[cfg(all(target_arch = "wasm32", feature = "web-local-fonts"))]
+async fn local_font_data() -> Result<Vec<Vec<u8>>, JsValue> {
+    let window = web_sys::window().ok_or(JsValue::UNDEFINED)?;
+    let fonts =
+        JsFuture::from(call_method(&window, "queryLocalFonts")?.dyn_into::<Promise>()?).await?;
+    let mut font_data = vec![];
+    for font in Array::from(&fonts).iter() {
+        let blob: Blob = JsFuture::from(call_method(&font, "blob")?.dyn_into::<Promise>()?)
+            .await?
+            .dyn_into()?;
+        let buffer = JsFuture::from(blob.array_buffer()).await?;
+        font_data.push(Uint8Array::new(&buffer).to_vec());
+    }
+    Ok(font_data)
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "web-local-fonts"))]
+fn call_method(object: &JsValue, name: &str) -> Result<JsValue, JsValue> {
+    let method: Function = Reflect::get(object, &JsValue::from_str(name))?.dyn_into()?;
+    method.call0(object)
+}
+
+impl Source for WebSource {
+    #[inline]
+    fn all_fonts(&self) -> Result<Vec<Handle>, SelectionError> {
+        self.all_fonts()
+    }
+
+    #[inline]
+    fn all_families(&self) -> Result<Vec<String>, SelectionError> {
+        self.all_families()
+    }
+
+    #[inline]
+    fn select_family_by_name(&self, family_name: &str) -> Result<FamilyHandle, SelectionError> {
+        self.select_family_by_name(family_name)
+    }
+
+    #[inline]
+    fn select_by_postscript_name(&self, postscript_name: &str) -> Result<Handle, SelectionError> {
+        self.select_by_postscript_name(postscript_name)
+    }
+
+    #[inline]
+    fn select_best_match(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+    ) -> Result<Handle, SelectionError> {
+        self.select_best_match(family_names, properties)
+    }
+
+    #[inline]
+    fn select_best_match_with_scorer(
+        &self,
+        family_names: &[FamilyName],
+        properties: &Properties,
+        scorer: &dyn MatchScorer,
+    ) -> Result<Handle, SelectionError> {
+        LOCAL_FONTS
+            .read()
+            .unwrap()
+            .select_best_match_with_scorer(family_names, properties, scorer)
+    }
+
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    #[inline]
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::WebSource;
+    use crate::canvas::{Canvas, Format, RasterizationOptions};
+    use crate::error::SelectionError;
+    use crate::family_name::FamilyName;
+    use crate::hinting::HintingOptions;
+    use crate::loader::Loader;
+    use crate::properties::Properties;
+    use crate::test_fixtures::INCONSOLATA;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_local_fonts_are_shared() {
+        let source = WebSource::new();
+        WebSource::register_local_fonts(vec![Arc::new(INCONSOLATA.to_vec())]).unwrap();
+        assert!(source.select_family_by_name("Inconsolata").is_ok());
+        assert!(WebSource::new()
+            .select_by_postscript_name("Inconsolata-Regular")
+            .is_ok());
+
+        assert!(WebSource::register_local_fonts(vec![Arc::new(vec![0; 4])]).is_err());
+        assert_eq!(source.all_fonts().unwrap().len(), 1);
+
+        // Registered fonts rasterize into pixels that a page can put on an HTML canvas.
+        let font = source
+            .select_best_match(
+                &[FamilyName::Title("Inconsolata".to_owned())],
+                &Properties::new(),
+            )
+            .unwrap()
+            .load()
+            .unwrap();
+        let glyph_id = font.glyph_for_char('A').unwrap();
+        let bounds = font
+            .raster_bounds(
+                glyph_id,
+                16.0,
+                Transform2F::default(),
+                HintingOptions::None,
+                RasterizationOptions::GrayscaleAa,
+            )
+            .unwrap();
+        let mut canvas = Canvas::new(bounds.size(), Format::A8);
+        font.rasterize_glyph(
+            &mut canvas,
+            glyph_id,
+            16.0,
+            Transform2F::from_translation(-bounds.origin().to_f32()),
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )
+        .unwrap();
+        let image_data = canvas.to_image_data();
+        assert_eq!(image_data.len(), canvas.pixels.len() * 4);
+        assert!(image_data.chunks_exact(4).any(|pixel| pixel[3] > 0));
+        assert!(image_data
+            .chunks_exact(4)
+            .all(|pixel| pixel[3] == 0 || pixel[..3] == [255; 3]));
+
+        WebSource::clear_local_fonts();
+        assert!(matches!(
+            source.select_family_by_name("Inconsolata"),
+            Err(SelectionError::NotFound)
+        ));
+    }
+}
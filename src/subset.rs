@@ -0,0 +1,767 @@
+// font-kit/src/subset.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Font subsetting: building a font that contains only some of another font's glyphs.
+//!
+//! `Font::subset()` and `Font::subset_text()` write a standalone TrueType or OpenType font with
+//! the requested glyphs, the glyphs that composite glyphs are built from, and `.notdef`. Glyphs
+//! are renumbered: `.notdef` keeps ID 0, and the rest follow in the order of their original IDs.
+//! `Subset::glyph_ids` maps between the two numberings.
+//!
+//! The subset keeps the outlines (`glyf` and `loca`, or `CFF `), the horizontal metrics, the
+//! TrueType hinting programs, and the `name`, `OS/2`, and `gasp` tables. It gets a new `cmap`
+//! covering the characters that map to the kept glyphs, and a `post` table without glyph names.
+//! Everything that refers to glyph IDs in ways that aren't rewritten, such as `GSUB`, `GPOS`,
+//! `kern`, and vertical metrics, is dropped, so the subset suits PDF embedding and fonts for
+//! pre-shaped text. `CFF2` outlines and CID-keyed `CFF` fonts are not supported.
+
+use std::collections::{BTreeMap, BTreeSet};
+use ttf_parser::{Face, GlyphId, Tag};
+
+use crate::error::SubsetError;
+use crate::utils;
+
+/// A subset of a font, from `Font::subset()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subset {
+    /// The data of the subset font.
+    pub data: Vec<u8>,
+    /// The original ID of each glyph in the subset, indexed by its new ID.
+    ///
+    /// The original IDs are in increasing order, starting with 0 for `.notdef`.
+    pub glyph_ids: Vec<u32>,
+}
+
+impl Subset {
+    /// Returns the ID in the subset of the glyph with the given ID in the original font, or
+    /// `None` if the glyph isn't in the subset.
+    pub fn new_glyph_id(&self, original_glyph_id: u32) -> Option<u32> {
+        self.glyph_ids
+            .binary_search(&original_glyph_id)
+            .ok()
+            .map(|new_glyph_id| new_glyph_id as u32)
+    }
+}
+
+// Composite glyph flags.
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+// Composite glyphs nested deeper than this are treated as malformed, to avoid cycles.
+const MAX_COMPONENT_DEPTH: u32 = 8;
+
+/// Builds a subset of `face` containing the given glyphs.
+pub(crate) fn subset(face: &Face, glyph_ids: &[u32]) -> Result<Subset, SubsetError> {
+    let raw = RawTables { face };
+    let glyph_count = face.number_of_glyphs() as u32;
+    if let Some(&glyph_id) = glyph_ids.iter().find(|&&glyph_id| glyph_id >= glyph_count) {
+        return Err(SubsetError::NoSuchGlyph(glyph_id));
+    }
+
+    let glyf = match (raw.get(b"glyf"), raw.get(b"loca")) {
+        (Some(glyf), Some(loca)) => {
+            let long_offsets = read_u16(raw.require(b"head")?, 50)? != 0;
+            Some(Glyf {
+                glyf,
+                loca,
+                long_offsets,
+            })
+        }
+        _ => None,
+    };
+    let cff = raw.get(b"CFF ");
+    if glyf.is_none() && cff.is_none() {
+        return Err(SubsetError::UnsupportedOutlines);
+    }
+
+    // Gather the glyphs, with the components of composite glyphs.
+    let mut kept: BTreeSet<u32> = glyph_ids.iter().copied().collect();
+    kept.insert(0);
+    if let Some(ref glyf) = glyf {
+        let mut pending: Vec<(u32, u32)> = kept.iter().map(|&glyph_id| (glyph_id, 0)).collect();
+        while let Some((glyph_id, depth)) = pending.pop() {
+            for (_, component) in composite_components(glyf.glyph_data(glyph_id)?)? {
+                if component as u32 >= glyph_count || depth >= MAX_COMPONENT_DEPTH {
+                    return Err(SubsetError::Parse);
+                }
+                if kept.insert(component as u32) {
+                    pending.push((component as u32, depth + 1));
+                }
+            }
+        }
+    }
+    let glyph_ids: Vec<u32> = kept.into_iter().collect();
+    let new_glyph_id = |original: u32| glyph_ids.binary_search(&original).ok();
+
+    let mut tables: Vec<(u32, Vec<u8>)> = vec![];
+    match glyf {
+        Some(ref glyf) => {
+            let (glyf_data, loca_data) = glyf.subset(&glyph_ids, &new_glyph_id)?;
+            tables.push((tag(b"glyf"), glyf_data));
+            tables.push((tag(b"loca"), loca_data));
+        }
+        None => tables.push((tag(b"CFF "), subset_cff(cff.unwrap(), &glyph_ids)?)),
+    }
+
+    let mut head = raw.require(b"head")?.to_vec();
+    if head.len() < 54 {
+        return Err(SubsetError::Parse);
+    }
+    head[8..12].copy_from_slice(&[0; 4]);
+    if glyf.is_some() {
+        head[50..52].copy_from_slice(&1u16.to_be_bytes());
+    }
+    tables.push((tag(b"head"), head));
+
+    let mut hhea = raw.require(b"hhea")?.to_vec();
+    if hhea.len() < 36 {
+        return Err(SubsetError::Parse);
+    }
+    hhea[34..36].copy_from_slice(&(glyph_ids.len() as u16).to_be_bytes());
+    tables.push((tag(b"hhea"), hhea));
+
+    let mut hmtx = Vec::with_capacity(glyph_ids.len() * 4);
+    for &glyph_id in &glyph_ids {
+        let glyph_id = GlyphId(glyph_id as u16);
+        push_u16(&mut hmtx, face.glyph_hor_advance(glyph_id).unwrap_or(0));
+        push_u16(
+            &mut hmtx,
+            face.glyph_hor_side_bearing(glyph_id).unwrap_or(0) as u16,
+        );
+    }
+    tables.push((tag(b"hmtx"), hmtx));
+
+    let mut maxp = raw.require(b"maxp")?.to_vec();
+    if maxp.len() < 6 {
+        return Err(SubsetError::Parse);
+    }
+    maxp[4..6].copy_from_slice(&(glyph_ids.len() as u16).to_be_bytes());
+    tables.push((tag(b"maxp"), maxp));
+
+    // Version 3 of `post` has no glyph names, which would have to be subset too.
+    let mut post = vec![0; 32];
+    if let Some(original) = raw.get(b"post") {
+        let len = original.len().min(32);
+        post[..len].copy_from_slice(&original[..len]);
+    }
+    post[0..4].copy_from_slice(&0x0003_0000u32.to_be_bytes());
+    tables.push((tag(b"post"), post));
+
+    tables.push((tag(b"cmap"), build_cmap(face, &new_glyph_id)));
+
+    for name in [b"name", b"OS/2", b"cvt ", b"fpgm", b"prep", b"gasp"] {
+        if let Some(table) = raw.get(name) {
+            tables.push((tag(name), table.to_vec()));
+        }
+    }
+
+    let mut data = utils::build_sfnt(&tables);
+    utils::set_checksum_adjustment(&mut data);
+    Ok(Subset { data, glyph_ids })
+}
+
+struct RawTables<'a, 'b> {
+    face: &'b Face<'a>,
+}
+
+impl<'a, 'b> RawTables<'a, 'b> {
+    fn get(&self, tag: &[u8; 4]) -> Option<&'a [u8]> {
+        self.face.raw_face().table(Tag::from_bytes(tag))
+    }
+
+    fn require(&self, tag: &[u8; 4]) -> Result<&'a [u8], SubsetError> {
+        self.get(tag).ok_or(SubsetError::Parse)
+    }
+}
+
+struct Glyf<'a> {
+    glyf: &'a [u8],
+    loca: &'a [u8],
+    long_offsets: bool,
+}
+
+impl<'a> Glyf<'a> {
+    fn glyph_data(&self, glyph_id: u32) -> Result<&'a [u8], SubsetError> {
+        let index = glyph_id as usize;
+        let (start, end) = if self.long_offsets {
+            (
+                read_u32(self.loca, index * 4)? as usize,
+                read_u32(self.loca, index * 4 + 4)? as usize,
+            )
+        } else {
+            (
+                read_u16(self.loca, index * 2)? as usize * 2,
+                read_u16(self.loca, index * 2 + 2)? as usize * 2,
+            )
+        };
+        if start > end {
+            return Err(SubsetError::Parse);
+        }
+        self.glyf.get(start..end).ok_or(SubsetError::Parse)
+    }
+
+    // Returns the new `glyf` table and its `loca` table, with long offsets.
+    fn subset<F>(
+        &self,
+        glyph_ids: &[u32],
+        new_glyph_id: &F,
+    ) -> Result<(Vec<u8>, Vec<u8>), SubsetError>
+    where
+        F: Fn(u32) -> Option<usize>,
+    {
+        let mut glyf = vec![];
+        let mut loca = vec![];
+        for &glyph_id in glyph_ids {
+            push_u32(&mut loca, glyf.len() as u32);
+            let data = self.glyph_data(glyph_id)?;
+            let start = glyf.len();
+            glyf.extend_from_slice(data);
+            for (offset, component) in composite_components(data)? {
+                let component = new_glyph_id(component as u32).ok_or(SubsetError::Parse)?;
+                glyf[(start + offset)..(start + offset + 2)]
+                    .copy_from_slice(&(component as u16).to_be_bytes());
+            }
+            glyf.resize((glyf.len() + 3) & !3, 0);
+        }
+        push_u32(&mut loca, glyf.len() as u32);
+        Ok((glyf, loca))
+    }
+}
+
+// Returns the components of a composite glyph as the offset of each glyph index in the glyph
+// data and the index itself. Simple and empty glyphs have none.
+pub(crate) fn composite_components(data: &[u8]) -> Result<Vec<(usize, u16)>, SubsetError> {
+    if data.is_empty() || read_u16(data, 0)? as i16 >= 0 {
+        return Ok(vec![]);
+    }
+    let mut components = vec![];
+    let mut offset = 10;
+    loop {
+        let flags = read_u16(data, offset)?;
+        components.push((offset + 2, read_u16(data, offset + 2)?));
+        offset += 4;
+        offset += if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+            4
+        } else {
+            2
+        };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            offset += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            offset += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            offset += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            return Ok(components);
+        }
+    }
+}
+
+// Builds a `cmap` table with a format 4 subtable for the Basic Multilingual Plane, when it fits,
+// and a format 12 subtable for everything.
+fn build_cmap<F>(face: &Face, new_glyph_id: &F) -> Vec<u8>
+where
+    F: Fn(u32) -> Option<usize>,
+{
+    let mut mapping: BTreeMap<u32, u16> = BTreeMap::new();
+    if let Some(cmap) = face.tables().cmap {
+        for subtable in cmap.subtables {
+            if !subtable.is_unicode() {
+                continue;
+            }
+            subtable.codepoints(|codepoint| {
+                let glyph = char::from_u32(codepoint)
+                    .and_then(|character| face.glyph_index(character))
+                    .and_then(|glyph_id| new_glyph_id(glyph_id.0 as u32));
+                if let Some(glyph) = glyph.filter(|&glyph| glyph != 0) {
+                    mapping.insert(codepoint, glyph as u16);
+                }
+            });
+        }
+    }
+
+    // Runs of consecutive characters mapped to consecutive glyphs, as (start, end, glyph).
+    let mut groups: Vec<(u32, u32, u16)> = vec![];
+    for (&codepoint, &glyph) in &mapping {
+        match groups.last_mut() {
+            Some(&mut (start, ref mut end, start_glyph))
+                if codepoint == *end + 1
+                    && glyph as u32 == start_glyph as u32 + (codepoint - start) =>
+            {
+                *end = codepoint
+            }
+            _ => groups.push((codepoint, codepoint, glyph)),
+        }
+    }
+
+    let format_12 = {
+        let mut data = vec![];
+        push_u16(&mut data, 12);
+        push_u16(&mut data, 0);
+        push_u32(&mut data, 16 + groups.len() as u32 * 12);
+        push_u32(&mut data, 0);
+        push_u32(&mut data, groups.len() as u32);
+        for &(start, end, glyph) in &groups {
+            push_u32(&mut data, start);
+            push_u32(&mut data, end);
+            push_u32(&mut data, glyph as u32);
+        }
+        data
+    };
+
+    // Format 4 segments can't cross 0xffff, and the last one must end there.
+    let mut segments: Vec<(u16, u16, u16)> = vec![];
+    for &(start, end, glyph) in &groups {
+        if start > 0xfffe {
+            break;
+        }
+        let end = end.min(0xfffe);
+        segments.push((start as u16, end as u16, glyph.wrapping_sub(start as u16)));
+    }
+    segments.push((0xffff, 0xffff, 1));
+    let format_4_len = 16 + segments.len() * 8;
+    let format_4 = if format_4_len <= 0xffff {
+        let seg_count = segments.len() as u16;
+        let entry_selector = 15 - seg_count.leading_zeros() as u16;
+        let search_range = 2 << entry_selector;
+        let mut data = vec![];
+        push_u16(&mut data, 4);
+        push_u16(&mut data, format_4_len as u16);
+        push_u16(&mut data, 0);
+        push_u16(&mut data, seg_count * 2);
+        push_u16(&mut data, search_range);
+        push_u16(&mut data, entry_selector);
+        push_u16(&mut data, seg_count * 2 - search_range);
+        for &(_, end, _) in &segments {
+            push_u16(&mut data, end);
+        }
+        push_u16(&mut data, 0);
+        for &(start, _, _) in &segments {
+            push_u16(&mut data, start);
+        }
+        for &(_, _, delta) in &segments {
+            push_u16(&mut data, delta);
+        }
+        for _ in &segments {
+            push_u16(&mut data, 0);
+        }
+        Some(data)
+    } else {
+        None
+    };
+
+    let subtables: Vec<(u16, Vec<u8>)> = format_4
+        .into_iter()
+        .map(|data| (1, data))
+        .chain(Some((10, format_12)))
+        .collect();
+    let mut cmap = vec![];
+    push_u16(&mut cmap, 0);
+    push_u16(&mut cmap, subtables.len() as u16);
+    let mut offset = 4 + subtables.len() as u32 * 8;
+    for (encoding_id, data) in &subtables {
+        push_u16(&mut cmap, 3);
+        push_u16(&mut cmap, *encoding_id);
+        push_u32(&mut cmap, offset);
+        offset += data.len() as u32;
+    }
+    for (_, data) in subtables {
+        cmap.extend_from_slice(&data);
+    }
+    cmap
+}
+
+// Top DICT and Private DICT operators.
+const CHARSET_OP: u16 = 15;
+const ENCODING_OP: u16 = 16;
+const CHAR_STRINGS_OP: u16 = 17;
+const PRIVATE_OP: u16 = 18;
+const SUBRS_OP: u16 = 19;
+const ROS_OP: u16 = 0x0c1e;
+
+// A DICT entry: an operator, its operands as numbers, and the encoded operands.
+struct DictEntry<'a> {
+    operator: u16,
+    operands: Vec<f64>,
+    encoded: &'a [u8],
+}
+
+// Builds a `CFF ` table containing only the given glyphs, in order.
+//
+// Subroutines are kept whole, since finding out which ones the kept glyphs use would mean
+// interpreting their charstrings.
+fn subset_cff(cff: &[u8], glyph_ids: &[u32]) -> Result<Vec<u8>, SubsetError> {
+    let header_size = *cff.get(2).ok_or(SubsetError::Parse)? as usize;
+    let (names, top_dicts_start) = read_index(cff, header_size)?;
+    let (top_dicts, strings_start) = read_index(cff, top_dicts_start)?;
+    let (_, global_subrs_start) = read_index(cff, strings_start)?;
+    let (_, global_subrs_end) = read_index(cff, global_subrs_start)?;
+    if names.len() != 1 || top_dicts.len() != 1 {
+        return Err(SubsetError::UnsupportedOutlines);
+    }
+
+    let top_dict = parse_dict(top_dicts[0])?;
+    let operand = |operator: u16, index: usize| {
+        top_dict
+            .iter()
+            .find(|entry| entry.operator == operator)
+            .and_then(|entry| entry.operands.get(index))
+            .map(|&value| value as usize)
+    };
+    if top_dict.iter().any(|entry| entry.operator == ROS_OP) {
+        return Err(SubsetError::UnsupportedOutlines);
+    }
+    let char_strings_offset = operand(CHAR_STRINGS_OP, 0).ok_or(SubsetError::Parse)?;
+    let (char_strings, _) = read_index(cff, char_strings_offset)?;
+    let glyph_names = read_charset(cff, operand(CHARSET_OP, 0).unwrap_or(0), char_strings.len())?;
+
+    let (private_dict, local_subrs) = match (operand(PRIVATE_OP, 0), operand(PRIVATE_OP, 1)) {
+        (Some(size), Some(offset)) => {
+            let private = cff.get(offset..(offset + size)).ok_or(SubsetError::Parse)?;
+            let entries = parse_dict(private)?;
+            let local_subrs = match entries.iter().find(|entry| entry.operator == SUBRS_OP) {
+                Some(entry) => {
+                    let start =
+                        offset + *entry.operands.first().ok_or(SubsetError::Parse)? as usize;
+                    let (_, end) = read_index(cff, start)?;
+                    Some(&cff[start..end])
+                }
+                None => None,
+            };
+            (Some(entries), local_subrs)
+        }
+        _ => (None, None),
+    };
+
+    // Everything from the header through the global subroutines is copied as it is, except for
+    // the Top DICT, whose offsets change. Offsets are written as five-byte integers so that the
+    // size of the Top DICT doesn't depend on them.
+    let mut top_dict_data = vec![];
+    for entry in &top_dict {
+        if !matches!(
+            entry.operator,
+            CHARSET_OP | ENCODING_OP | CHAR_STRINGS_OP | PRIVATE_OP
+        ) {
+            top_dict_data.extend_from_slice(entry.encoded);
+        }
+    }
+    let fixed_entries_len = 6 + 6 + if private_dict.is_some() { 11 } else { 0 };
+    let top_dict_index_len = index_len(&[top_dict_data.len() + fixed_entries_len]);
+
+    let mut private_data = vec![];
+    if let Some(ref entries) = private_dict {
+        for entry in entries.iter().filter(|entry| entry.operator != SUBRS_OP) {
+            private_data.extend_from_slice(entry.encoded);
+        }
+        if local_subrs.is_some() {
+            let subrs_offset = private_data.len() as i32 + 6;
+            push_dict_int(&mut private_data, subrs_offset);
+            push_dict_operator(&mut private_data, SUBRS_OP);
+        }
+    }
+
+    let kept_char_strings: Vec<&[u8]> = glyph_ids
+        .iter()
+        .map(|&glyph_id| {
+            char_strings
+                .get(glyph_id as usize)
+                .copied()
+                .ok_or(SubsetError::Parse)
+        })
+        .collect::<Result<_, _>>()?;
+
+    let strings_and_subrs = &cff[strings_start..global_subrs_end];
+    let charset_offset = top_dicts_start + top_dict_index_len + strings_and_subrs.len();
+    let charset_len = 1 + (glyph_ids.len() - 1) * 2;
+    let char_strings_offset = charset_offset + charset_len;
+    let char_strings_len = index_len(
+        &kept_char_strings
+            .iter()
+            .map(|char_string| char_string.len())
+            .collect::<Vec<_>>(),
+    );
+    let private_offset = char_strings_offset + char_strings_len;
+
+    push_dict_int(&mut top_dict_data, charset_offset as i32);
+    push_dict_operator(&mut top_dict_data, CHARSET_OP);
+    push_dict_int(&mut top_dict_data, char_strings_offset as i32);
+    push_dict_operator(&mut top_dict_data, CHAR_STRINGS_OP);
+    if private_dict.is_some() {
+        push_dict_int(&mut top_dict_data, private_data.len() as i32);
+        push_dict_int(&mut top_dict_data, private_offset as i32);
+        push_dict_operator(&mut top_dict_data, PRIVATE_OP);
+    }
+
+    let mut data = cff[..top_dicts_start].to_vec();
+    write_index(&mut data, &[&top_dict_data]);
+    debug_assert_eq!(data.len(), top_dicts_start + top_dict_index_len);
+    data.extend_from_slice(strings_and_subrs);
+    data.push(0);
+    for &glyph_id in &glyph_ids[1..] {
+        let name = *glyph_names
+            .get(glyph_id as usize)
+            .ok_or(SubsetError::Parse)?;
+        push_u16(&mut data, name);
+    }
+    write_index(&mut data, &kept_char_strings);
+    data.extend_from_slice(&private_data);
+    if let Some(local_subrs) = local_subrs {
+        data.extend_from_slice(local_subrs);
+    }
+    Ok(data)
+}
+
+// Reads an INDEX, returning its items and the offset just past it.
+fn read_index(data: &[u8], start: usize) -> Result<(Vec<&[u8]>, usize), SubsetError> {
+    let count = read_u16(data, start)? as usize;
+    if count == 0 {
+        return Ok((vec![], start + 2));
+    }
+    let offset_size = *data.get(start + 2).ok_or(SubsetError::Parse)? as usize;
+    if !(1..=4).contains(&offset_size) {
+        return Err(SubsetError::Parse);
+    }
+    let offsets_start = start + 3;
+    let data_start = offsets_start + (count + 1) * offset_size - 1;
+    let offset = |index: usize| -> Result<usize, SubsetError> {
+        let position = offsets_start + index * offset_size;
+        let bytes = data
+            .get(position..(position + offset_size))
+            .ok_or(SubsetError::Parse)?;
+        Ok(bytes
+            .iter()
+            .fold(0, |value, &byte| (value << 8) | byte as usize))
+    };
+    let mut items = Vec::with_capacity(count);
+    for index in 0..count {
+        let (item_start, item_end) = (offset(index)?, offset(index + 1)?);
+        items.push(
+            data.get((data_start + item_start)..(data_start + item_end))
+                .ok_or(SubsetError::Parse)?,
+        );
+    }
+    Ok((items, data_start + offset(count)?))
+}
+
+// Returns the size of an INDEX with items of the given sizes, as `write_index()` writes it.
+fn index_len(item_lens: &[usize]) -> usize {
+    if item_lens.is_empty() {
+        return 2;
+    }
+    3 + (item_lens.len() + 1) * 4 + item_lens.iter().sum::<usize>()
+}
+
+// Writes an INDEX with four-byte offsets.
+fn write_index(data: &mut Vec<u8>, items: &[&[u8]]) {
+    push_u16(data, items.len() as u16);
+    if items.is_empty() {
+        return;
+    }
+    data.push(4);
+    let mut offset = 1;
+    push_u32(data, offset);
+    for item in items {
+        offset += item.len() as u32;
+        push_u32(data, offset);
+    }
+    for item in items {
+        data.extend_from_slice(item);
+    }
+}
+
+// Returns the string ID of each glyph's name, indexed by glyph ID.
+fn read_charset(cff: &[u8], offset: usize, glyph_count: usize) -> Result<Vec<u16>, SubsetError> {
+    // The predefined ISOAdobe charset names glyphs by their IDs. The expert charsets would need
+    // tables of their own.
+    match offset {
+        0 => return Ok((0..glyph_count as u16).collect()),
+        1 | 2 => return Err(SubsetError::UnsupportedOutlines),
+        _ => {}
+    }
+    let mut names = vec![0];
+    let format = *cff.get(offset).ok_or(SubsetError::Parse)?;
+    let mut position = offset + 1;
+    while names.len() < glyph_count {
+        match format {
+            0 => {
+                names.push(read_u16(cff, position)?);
+                position += 2;
+            }
+            1 | 2 => {
+                let first = read_u16(cff, position)?;
+                let left = if format == 1 {
+                    *cff.get(position + 2).ok_or(SubsetError::Parse)? as u16
+                } else {
+                    read_u16(cff, position + 2)?
+                };
+                position += if format == 1 { 3 } else { 4 };
+                names.extend((0..=left).map(|index| first.wrapping_add(index)));
+            }
+            _ => return Err(SubsetError::Parse),
+        }
+    }
+    names.truncate(glyph_count);
+    Ok(names)
+}
+
+fn parse_dict(data: &[u8]) -> Result<Vec<DictEntry<'_>>, SubsetError> {
+    let mut entries = vec![];
+    let mut operands = vec![];
+    let mut entry_start = 0;
+    let mut position = 0;
+    while position < data.len() {
+        let byte = data[position];
+        match byte {
+            0..=21 => {
+                let operator = if byte == 12 {
+                    position += 1;
+                    0x0c00 | *data.get(position).ok_or(SubsetError::Parse)? as u16
+                } else {
+                    byte as u16
+                };
+                position += 1;
+                entries.push(DictEntry {
+                    operator,
+                    operands: std::mem::take(&mut operands),
+                    encoded: &data[entry_start..position],
+                });
+                entry_start = position;
+            }
+            28 => {
+                operands.push(read_u16(data, position + 1)? as i16 as f64);
+                position += 3;
+            }
+            29 => {
+                operands.push(read_u32(data, position + 1)? as i32 as f64);
+                position += 5;
+            }
+            30 => {
+                // Real numbers are only copied, never needed as values.
+                position += 1;
+                while position < data.len() {
+                    let byte = data[position];
+                    position += 1;
+                    if byte & 0x0f == 0x0f || byte >> 4 == 0x0f {
+                        break;
+                    }
+                }
+                operands.push(0.0);
+            }
+            32..=246 => {
+                operands.push(byte as f64 - 139.0);
+                position += 1;
+            }
+            247..=254 => {
+                let next = *data.get(position + 1).ok_or(SubsetError::Parse)? as f64;
+                operands.push(if byte <= 250 {
+                    (byte as f64 - 247.0) * 256.0 + next + 108.0
+                } else {
+                    -(byte as f64 - 251.0) * 256.0 - next - 108.0
+                });
+                position += 2;
+            }
+            _ => return Err(SubsetError::Parse),
+        }
+    }
+    Ok(entries)
+}
+
+fn push_dict_int(data: &mut Vec<u8>, value: i32) {
+    data.push(29);
+    data.extend_from_slice(&value.to_be_bytes());
+}
+
+fn push_dict_operator(data: &mut Vec<u8>, operator: u16) {
+    if operator > 0xff {
+        data.push(12);
+    }
+    data.push(operator as u8);
+}
+
+#[inline]
+fn tag(name: &[u8; 4]) -> u32 {
+    u32::from_be_bytes(*name)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, SubsetError> {
+    data.get(offset..(offset + 2))
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or(SubsetError::Parse)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, SubsetError> {
+    data.get(offset..(offset + 4))
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or(SubsetError::Parse)
+}
+
+#[inline]
+fn push_u16(data: &mut Vec<u8>, value: u16) {
+    data.extend_from_slice(&value.to_be_bytes());
+}
+
+#[inline]
+fn push_u32(data: &mut Vec<u8>, value: u32) {
+    data.extend_from_slice(&value.to_be_bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::SubsetError;
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use crate::test_fixtures::{EB_GARAMOND, INCONSOLATA};
+    use crate::utils;
+    use std::sync::Arc;
+
+    fn check_subset(original: &Font, text: &str) -> Font {
+        let subset = original.subset_text(text).unwrap();
+        assert_eq!(utils::table_checksum(&subset.data), utils::CHECKSUM_MAGIC);
+        let font = Font::from_bytes(Arc::new(subset.data.clone()), 0).unwrap();
+        assert_eq!(font.glyph_count() as usize, subset.glyph_ids.len());
+        assert_eq!(font.postscript_name(), original.postscript_name());
+        for character in text.chars() {
+            let original_glyph = original.glyph_for_char(character).unwrap();
+            let glyph = font.glyph_for_char(character).unwrap();
+            assert_eq!(subset.new_glyph_id(original_glyph), Some(glyph));
+            assert_eq!(
+                font.glyph_path(glyph).unwrap(),
+                original.glyph_path(original_glyph).unwrap()
+            );
+            assert_eq!(
+                font.advance(glyph).unwrap(),
+                original.advance(original_glyph).unwrap()
+            );
+        }
+        font
+    }
+
+    #[test]
+    fn test_subset_truetype() {
+        let original = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        let font = check_subset(&original, "Hé");
+        assert_eq!(font.glyph_for_char('x'), None);
+    }
+
+    #[test]
+    fn test_subset_cff() {
+        let original = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let font = check_subset(&original, "Th");
+        assert_eq!(font.glyph_count(), 3);
+        assert!(matches!(
+            original.subset(&[original.glyph_count()]),
+            Err(SubsetError::NoSuchGlyph(_))
+        ));
+    }
+}
@@ -0,0 +1,190 @@
+// font-kit/src/substitution.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Simple glyph substitution using the `GSUB` table.
+//!
+//! This is not a shaper: only single substitutions (lookup type 1) and ligature substitutions
+//! (lookup type 4) are applied. Contextual lookups, which some fonts use to implement `calt` or
+//! even `liga`, are skipped, lookup flags such as "ignore marks" are not honored, and the default
+//! script and language of the font are always used. That is enough for programming
+//! ligatures in terminals and for mapping letters to small caps, but text in complex scripts
+//! needs a real shaper.
+
+use ttf_parser::gsub::{LigatureSubstitution, SingleSubstitution, SubstitutionSubtable};
+use ttf_parser::opentype_layout::{LanguageSystem, LayoutTable};
+use ttf_parser::{Face, GlyphId, Tag};
+
+/// A glyph and the index of the cluster of the text that it came from.
+///
+/// A ligature takes the cluster of its first component.
+pub(crate) type ClusterGlyph = (GlyphId, u32);
+
+/// Applies the single and ligature substitutions of the given features to a run of glyphs.
+pub(crate) fn substitute(face: &Face, glyph_ids: &[u32], features: &[u32]) -> Vec<u32> {
+    let mut glyphs: Vec<ClusterGlyph> = glyph_ids
+        .iter()
+        .map(|&glyph_id| (GlyphId(glyph_id as u16), 0))
+        .collect();
+    substitute_clusters(face, &mut glyphs, features);
+    glyphs
+        .into_iter()
+        .map(|(glyph, _)| glyph.0 as u32)
+        .collect()
+}
+
+/// Like `substitute()`, but keeps track of which cluster each glyph belongs to.
+pub(crate) fn substitute_clusters(face: &Face, glyphs: &mut Vec<ClusterGlyph>, features: &[u32]) {
+    let table = match face.tables().gsub {
+        Some(table) => table,
+        None => return,
+    };
+
+    for lookup_index in lookup_indices(&table, features) {
+        let lookup = match table.lookups.get(lookup_index) {
+            Some(lookup) => lookup,
+            None => continue,
+        };
+        let subtables: Vec<SubstitutionSubtable> = (0..lookup.subtables.len())
+            .filter_map(|index| lookup.subtables.get(index))
+            .collect();
+        apply_lookup(&subtables, glyphs);
+    }
+}
+
+/// Returns the lookups of the requested features in a `GSUB` or `GPOS` table, in the order they
+/// must be applied, which is the order of the lookup list.
+pub(crate) fn lookup_indices(table: &LayoutTable, features: &[u32]) -> Vec<u16> {
+    let language_system = match default_language_system(table) {
+        Some(language_system) => language_system,
+        None => return vec![],
+    };
+    let mut lookup_indices: Vec<u16> = language_system
+        .feature_indices
+        .into_iter()
+        .filter_map(|index| table.features.get(index))
+        .filter(|feature| features.contains(&feature.tag.0))
+        .flat_map(|feature| feature.lookup_indices)
+        .collect();
+    lookup_indices.sort_unstable();
+    lookup_indices.dedup();
+    lookup_indices
+}
+
+// Uses the default script, or Latin if there's no default script, or else the first script.
+fn default_language_system<'a>(table: &LayoutTable<'a>) -> Option<LanguageSystem<'a>> {
+    let script = table
+        .scripts
+        .find(Tag::from_bytes(b"DFLT"))
+        .or_else(|| table.scripts.find(Tag::from_bytes(b"latn")))
+        .or_else(|| table.scripts.get(0))?;
+    script.default_language.or_else(|| script.languages.get(0))
+}
+
+fn apply_lookup(subtables: &[SubstitutionSubtable], glyphs: &mut Vec<ClusterGlyph>) {
+    let mut index = 0;
+    while index < glyphs.len() {
+        for subtable in subtables {
+            let applied = match *subtable {
+                SubstitutionSubtable::Single(ref single) => apply_single(single, glyphs, index),
+                SubstitutionSubtable::Ligature(ref ligature) => {
+                    apply_ligature(ligature, glyphs, index)
+                }
+                _ => false,
+            };
+            if applied {
+                break;
+            }
+        }
+        index += 1;
+    }
+}
+
+fn apply_single(single: &SingleSubstitution, glyphs: &mut [ClusterGlyph], index: usize) -> bool {
+    let glyph = glyphs[index].0;
+    let substitute = match *single {
+        SingleSubstitution::Format1 { coverage, delta } => {
+            if !coverage.contains(glyph) {
+                return false;
+            }
+            GlyphId((glyph.0 as i32 + delta as i32) as u16)
+        }
+        SingleSubstitution::Format2 {
+            coverage,
+            substitutes,
+        } => match coverage.get(glyph).and_then(|index| substitutes.get(index)) {
+            Some(substitute) => substitute,
+            None => return false,
+        },
+    };
+    glyphs[index].0 = substitute;
+    true
+}
+
+fn apply_ligature(
+    ligature_substitution: &LigatureSubstitution,
+    glyphs: &mut Vec<ClusterGlyph>,
+    index: usize,
+) -> bool {
+    let ligature_set = match ligature_substitution
+        .coverage
+        .get(glyphs[index].0)
+        .and_then(|coverage_index| ligature_substitution.ligature_sets.get(coverage_index))
+    {
+        Some(ligature_set) => ligature_set,
+        None => return false,
+    };
+
+    // Ligatures are listed in order of preference, which is usually longest first.
+    for ligature in ligature_set {
+        let component_count = ligature.components.len() as usize;
+        let components = match glyphs.get(index + 1..index + 1 + component_count) {
+            Some(components) => components,
+            None => continue,
+        };
+        if components
+            .iter()
+            .zip(ligature.components)
+            .all(|(&(glyph, _), component)| glyph == component)
+        {
+            glyphs[index].0 = ligature.glyph;
+            glyphs.drain(index + 1..index + 1 + component_count);
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use crate::test_fixtures::EB_GARAMOND;
+
+    #[test]
+    fn test_substitute() {
+        let font = Font::from_bytes_borrowed(EB_GARAMOND, 0).unwrap();
+        let glyphs: Vec<u32> = "Thx"
+            .chars()
+            .map(|character| font.glyph_for_char(character).unwrap())
+            .collect();
+
+        let ligated = font.substitute(&glyphs, &[u32::from_be_bytes(*b"dlig")]);
+        assert_eq!(ligated.len(), 2);
+        assert_ne!(ligated[0], glyphs[0]);
+        assert_eq!(ligated[1], glyphs[2]);
+
+        let small_caps = font.substitute(&glyphs, &[u32::from_be_bytes(*b"smcp")]);
+        assert_eq!(small_caps.len(), 3);
+        assert_eq!(small_caps[0], glyphs[0]);
+        assert_ne!(small_caps[1], glyphs[1]);
+
+        assert_eq!(font.substitute(&glyphs, &[]), glyphs);
+    }
+}
@@ -0,0 +1,158 @@
+// font-kit/src/svg.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Glyph outlines as SVG.
+//!
+//! `SvgPathSink` turns any outline into SVG path data. `Font::glyph_to_svg()` and
+//! `Font::text_to_svg()` build complete SVG documents from a glyph or a line of text, for icon
+//! pipelines and documentation. Paths keep their coordinates in font units, and each `<path>`
+//! element's `transform` scales it to the requested size and flips it to SVG's y-down
+//! coordinates. The document spans the advance horizontally and the ascent to the descent
+//! vertically, with the baseline at the ascent.
+
+use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::vector::Vector2F;
+use std::fmt::Write;
+
+use crate::outline::OutlineSink;
+
+/// An `OutlineSink` that writes the outline as the path data of an SVG `<path>` element, such as
+/// `M0 0L10 0L10 10Z`.
+///
+/// Points are written as they are received. Glyph outlines have y pointing up and SVG has y
+/// pointing down, so wrap this in a `TransformSink` that flips and positions the glyph.
+#[derive(Clone, Debug, Default)]
+pub struct SvgPathSink {
+    data: String,
+}
+
+impl SvgPathSink {
+    /// Creates a sink with empty path data.
+    #[inline]
+    pub fn new() -> SvgPathSink {
+        SvgPathSink::default()
+    }
+
+    /// Returns the path data written so far.
+    #[inline]
+    pub fn path_data(&self) -> &str {
+        &self.data
+    }
+
+    /// Consumes this sink and returns the path data.
+    #[inline]
+    pub fn into_path_data(self) -> String {
+        self.data
+    }
+
+    fn push(&mut self, command: char, points: &[Vector2F]) {
+        self.data.push(command);
+        for (index, point) in points.iter().enumerate() {
+            if index > 0 {
+                self.data.push(' ');
+            }
+            write!(self.data, "{} {}", point.x(), point.y()).unwrap();
+        }
+    }
+}
+
+/// Returns a `<path>` element that draws path data in font units, scaled by `scale` and flipped,
+/// with the glyph origin at `origin` in SVG coordinates.
+pub(crate) fn path_element(path_data: &str, origin: Vector2F, scale: f32) -> String {
+    format!(
+        r#"<path transform="translate({} {}) scale({} {})" d="{}"/>"#,
+        origin.x(),
+        origin.y(),
+        scale,
+        -scale,
+        path_data
+    )
+}
+
+/// Returns an SVG document of the given size containing `content`.
+pub(crate) fn document(size: Vector2F, content: &str) -> String {
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">{content}</svg>"#,
+        width = size.x(),
+        height = size.y(),
+        content = content
+    )
+}
+
+impl OutlineSink for SvgPathSink {
+    #[inline]
+    fn move_to(&mut self, to: Vector2F) {
+        self.push('M', &[to]);
+    }
+
+    #[inline]
+    fn line_to(&mut self, to: Vector2F) {
+        self.push('L', &[to]);
+    }
+
+    #[inline]
+    fn quadratic_curve_to(&mut self, ctrl: Vector2F, to: Vector2F) {
+        self.push('Q', &[ctrl, to]);
+    }
+
+    #[inline]
+    fn cubic_curve_to(&mut self, ctrl: LineSegment2F, to: Vector2F) {
+        self.push('C', &[ctrl.from(), ctrl.to(), to]);
+    }
+
+    #[inline]
+    fn close(&mut self) {
+        self.data.push('Z');
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SvgPathSink;
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use crate::outline::OutlineSink;
+    use crate::test_fixtures::INCONSOLATA;
+    use pathfinder_geometry::line_segment::LineSegment2F;
+    use pathfinder_geometry::vector::Vector2F;
+
+    #[test]
+    fn test_svg_path_sink() {
+        let mut sink = SvgPathSink::new();
+        sink.move_to(Vector2F::new(0.0, 0.0));
+        sink.line_to(Vector2F::new(10.0, 0.0));
+        sink.quadratic_curve_to(Vector2F::new(10.0, 10.0), Vector2F::new(0.5, 10.0));
+        sink.cubic_curve_to(
+            LineSegment2F::new(Vector2F::new(0.0, 8.0), Vector2F::new(0.0, 4.0)),
+            Vector2F::new(-1.25, 2.0),
+        );
+        sink.close();
+        assert_eq!(
+            sink.into_path_data(),
+            "M0 0L10 0Q10 10 0.5 10C0 8 0 4 -1.25 2Z"
+        );
+    }
+
+    #[test]
+    fn test_svg_export() {
+        let font = Font::from_bytes_borrowed(INCONSOLATA, 0).unwrap();
+        let glyph_id = font.glyph_for_char('A').unwrap();
+        let svg = font.glyph_to_svg(glyph_id, 10.0).unwrap();
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="5""#));
+        assert_eq!(svg.matches("<path ").count(), 1);
+        assert!(svg.contains(r#"scale(0.01 -0.01)"#));
+        assert!(svg.ends_with("\"/></svg>"));
+
+        let text = font.text_to_svg("A A", 10.0);
+        assert_eq!(text.matches("<path ").count(), 2);
+        assert!(text.contains(r#"<path transform="translate(10 "#));
+        assert!(font.glyph_to_svg(font.glyph_count(), 10.0).is_err());
+    }
+}
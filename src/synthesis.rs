@@ -0,0 +1,152 @@
+// font-kit/src/synthesis.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Synthetic bold and oblique styles, for families that lack a bold or italic font.
+//!
+//! When matching picks a regular font for a bold or italic request, `SynthesisOptions` describes
+//! how to distort the font to approximate the missing style, as FreeType and DirectWrite do:
+//! emboldening widens every stroke by offsetting the outline, and obliquing shears it to the
+//! right. Pass the options to `Loader::outline_synthesized()` or
+//! `Loader::rasterize_glyph_synthesized()`, and adjust advances with `adjust_advance()`.
+
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2F;
+
+use crate::outline::Outline;
+use crate::properties::{Properties, Style, Weight};
+
+/// How to distort a font to synthesize a bold or oblique style. The default distorts nothing.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SynthesisOptions {
+    /// How much to widen every stroke, as a fraction of the em. 0.0 means no emboldening.
+    pub embolden: f32,
+    /// How far to slant glyphs to the right, as the horizontal shift per unit of height. 0.0
+    /// means no slant.
+    pub oblique: f32,
+}
+
+impl SynthesisOptions {
+    /// The emboldening that FreeType applies for synthetic bold: 1/24 of the em.
+    pub const BOLD_EMBOLDEN: f32 = 1.0 / 24.0;
+
+    /// The slant that FreeType applies for synthetic oblique, about 12 degrees.
+    pub const OBLIQUE_SLANT: f32 = 0.2126;
+
+    /// Returns the synthesis needed to approximate the `requested` properties with a font that
+    /// has the `available` ones, such as the properties of the font that matching selected.
+    ///
+    /// Bold is synthesized when a weight of 600 or more is requested from a font lighter than
+    /// that, and oblique when an italic or oblique style is requested from an upright font.
+    pub fn for_properties(requested: &Properties, available: &Properties) -> SynthesisOptions {
+        let embolden =
+            if requested.weight >= Weight::SEMIBOLD && available.weight < Weight::SEMIBOLD {
+                SynthesisOptions::BOLD_EMBOLDEN
+            } else {
+                0.0
+            };
+        let oblique = if requested.style != Style::Normal && available.style == Style::Normal {
+            SynthesisOptions::OBLIQUE_SLANT
+        } else {
+            0.0
+        };
+        SynthesisOptions { embolden, oblique }
+    }
+
+    /// Returns true if these options distort glyphs at all.
+    #[inline]
+    pub fn is_synthesized(&self) -> bool {
+        self.embolden != 0.0 || self.oblique != 0.0
+    }
+
+    /// Returns the shear that slants glyphs, in font units with y pointing up.
+    #[inline]
+    pub fn oblique_transform(&self) -> Transform2F {
+        Transform2F::row_major(1.0, self.oblique, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    /// Emboldens and slants an outline in font units.
+    pub fn apply(&self, outline: &mut Outline, units_per_em: u32) {
+        outline.embolden(self.embolden * units_per_em as f32);
+        if self.oblique != 0.0 {
+            outline.transform(&self.oblique_transform());
+        }
+    }
+
+    /// Widens a glyph advance, in font units, by the emboldening, so that emboldened glyphs don't
+    /// collide. Zero advances, such as those of combining marks, are left alone.
+    #[inline]
+    pub fn adjust_advance(&self, advance: Vector2F, units_per_em: u32) -> Vector2F {
+        if advance.x() == 0.0 {
+            return advance;
+        }
+        Vector2F::new(
+            advance.x() + self.embolden * units_per_em as f32,
+            advance.y(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SynthesisOptions;
+    use crate::outline::{Outline, OutlineBuilder, OutlineSink};
+    use crate::properties::{Properties, Style, Weight};
+    use pathfinder_geometry::vector::Vector2F;
+
+    // A 100×200 rectangle, drawn clockwise as TrueType outer contours are.
+    fn rectangle() -> Outline {
+        let mut builder = OutlineBuilder::new();
+        builder.move_to(Vector2F::new(0.0, 0.0));
+        builder.line_to(Vector2F::new(0.0, 200.0));
+        builder.line_to(Vector2F::new(100.0, 200.0));
+        builder.line_to(Vector2F::new(100.0, 0.0));
+        builder.close();
+        builder.into_outline()
+    }
+
+    #[test]
+    fn test_synthesis() {
+        let regular = Properties::new();
+        let bold_italic = *Properties::new().weight(Weight::BOLD).style(Style::Italic);
+        let options = SynthesisOptions::for_properties(&bold_italic, &regular);
+        assert_eq!(options.embolden, SynthesisOptions::BOLD_EMBOLDEN);
+        assert_eq!(options.oblique, SynthesisOptions::OBLIQUE_SLANT);
+        assert!(!SynthesisOptions::for_properties(&regular, &regular).is_synthesized());
+
+        let mut outline = rectangle();
+        let bold = SynthesisOptions {
+            embolden: 0.01,
+            oblique: 0.0,
+        };
+        bold.apply(&mut outline, 1000);
+        assert_eq!(
+            outline.contours[0].positions,
+            [
+                Vector2F::new(-5.0, -5.0),
+                Vector2F::new(-5.0, 205.0),
+                Vector2F::new(105.0, 205.0),
+                Vector2F::new(105.0, -5.0),
+            ]
+        );
+        assert_eq!(
+            bold.adjust_advance(Vector2F::new(120.0, 0.0), 1000),
+            Vector2F::new(130.0, 0.0)
+        );
+
+        let mut outline = rectangle();
+        let oblique = SynthesisOptions {
+            embolden: 0.0,
+            oblique: 0.25,
+        };
+        oblique.apply(&mut outline, 1000);
+        assert_eq!(outline.contours[0].positions[0], Vector2F::new(0.0, 0.0));
+        assert_eq!(outline.contours[0].positions[1], Vector2F::new(50.0, 200.0));
+    }
+}
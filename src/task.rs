@@ -0,0 +1,237 @@
+// font-kit/src/task.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Futures that run blocking font work on a background thread.
+//!
+//! Reading and parsing a large font collection, or enumerating every font on the system, can
+//! take long enough to drop frames if done on a GUI thread. The asynchronous loading functions
+//! run that work on a small pool of background threads and return a `Task`, which is an ordinary
+//! `Future` that completes when the work is done. It doesn't depend on any particular async
+//! runtime, so it can be awaited from any executor.
+//!
+//! The pool starts up to four threads, as they are needed; work submitted while they are all busy
+//! waits in a queue.
+
+use lazy_static::lazy_static;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+// The most threads the pool will run at once. Font loading is mostly I/O and parsing, so a few
+// threads are enough to keep the disk busy without starving the rest of the application.
+const MAX_WORKERS: usize = 4;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+lazy_static! {
+    static ref POOL: Pool = Pool {
+        state: Mutex::new(PoolState {
+            jobs: VecDeque::new(),
+            workers: 0,
+            idle: 0,
+        }),
+        job_available: Condvar::new(),
+    };
+}
+
+struct Pool {
+    state: Mutex<PoolState>,
+    job_available: Condvar,
+}
+
+struct PoolState {
+    jobs: VecDeque<Job>,
+    // The number of threads started, and how many of them are waiting for a job.
+    workers: usize,
+    idle: usize,
+}
+
+impl Pool {
+    // Queues `job`, starting another worker if every worker is busy and there's room for one.
+    //
+    // Fails only if no worker is running and one couldn't be started, since then nothing would
+    // ever run the job.
+    fn submit(&'static self, job: Job) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.jobs.push_back(job);
+        if state.jobs.len() > state.idle && state.workers < MAX_WORKERS {
+            let spawned = thread::Builder::new()
+                .name("font-kit loader".to_owned())
+                .spawn(move || self.run_worker());
+            match spawned {
+                Ok(_) => state.workers += 1,
+                Err(error) if state.workers == 0 => {
+                    state.jobs.pop_back();
+                    return Err(error);
+                }
+                Err(_) => {}
+            }
+        }
+        drop(state);
+        self.job_available.notify_one();
+        Ok(())
+    }
+
+    fn run_worker(&self) {
+        loop {
+            let job = {
+                let mut state = self.state.lock().unwrap();
+                loop {
+                    if let Some(job) = state.jobs.pop_front() {
+                        break job;
+                    }
+                    state.idle += 1;
+                    state = self.job_available.wait(state).unwrap();
+                    state.idle -= 1;
+                }
+            };
+            job();
+        }
+    }
+}
+
+/// A future that resolves to the result of work running on a background thread.
+///
+/// Dropping the task does not stop the work, but its result is discarded.
+#[derive(Debug)]
+pub struct Task<T> {
+    state: Arc<Mutex<TaskState<T>>>,
+}
+
+#[derive(Debug)]
+struct TaskState<T> {
+    result: Option<thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+impl<T> Task<T>
+where
+    T: Send + 'static,
+{
+    /// Runs `work` on the background thread pool.
+    ///
+    /// If `work` panics, the panic is propagated to whoever polls the task. Returns an error if
+    /// the pool has no threads and the operating system refuses to start one.
+    pub fn spawn<F>(work: F) -> io::Result<Task<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let state = Arc::new(Mutex::new(TaskState {
+            result: None,
+            waker: None,
+        }));
+        let thread_state = Arc::clone(&state);
+        POOL.submit(Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(work));
+            let waker = {
+                let mut state = thread_state.lock().unwrap();
+                state.result = Some(result);
+                state.waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }))?;
+        Ok(Task { state })
+    }
+
+    /// Returns a task that is already complete with `value`.
+    ///
+    /// This lets functions that return a task report errors that occur before any work starts.
+    pub fn ready(value: T) -> Task<T> {
+        Task {
+            state: Arc::new(Mutex::new(TaskState {
+                result: Some(Ok(value)),
+                waker: None,
+            })),
+        }
+    }
+}
+
+impl<T> Future for Task<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<T> {
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(Ok(value)) => Poll::Ready(value),
+            Some(Err(payload)) => {
+                drop(state);
+                panic::resume_unwind(payload)
+            }
+            None => {
+                state.waker = Some(context.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Task, MAX_WORKERS};
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+    use std::thread::{self, Thread};
+
+    static EB_GARAMOND_COLLECTION_PATH: &str = "resources/tests/eb-garamond/EBGaramond12.otc";
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark()
+        }
+    }
+
+    fn block_on<T>(mut task: Task<T>) -> T {
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut context = Context::from_waker(&waker);
+        loop {
+            match Pin::new(&mut task).poll(&mut context) {
+                Poll::Ready(value) => return value,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_spawn_more_tasks_than_workers() {
+        let tasks: Vec<_> = (0..MAX_WORKERS * 4)
+            .map(|index| Task::spawn(move || index * 2).unwrap())
+            .collect();
+        for (index, task) in tasks.into_iter().enumerate() {
+            assert_eq!(block_on(task), index * 2);
+        }
+    }
+
+    #[test]
+    fn test_ready() {
+        assert_eq!(block_on(Task::ready(42)), 42);
+    }
+
+    #[test]
+    fn test_from_path_async() {
+        let font = block_on(Font::from_path_async(EB_GARAMOND_COLLECTION_PATH, 1)).unwrap();
+        assert_eq!(
+            font.postscript_name().as_deref(),
+            Some("EBGaramond12-Italic")
+        );
+    }
+}
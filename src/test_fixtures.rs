@@ -0,0 +1,28 @@
+// font-kit/src/test_fixtures.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Font data shared by the unit tests.
+//!
+//! Not every fixture is used in every feature configuration.
+
+#![allow(dead_code)]
+
+pub(crate) static ARIAL: &[u8] = include_bytes!("../resources/Arial_regular.ttf");
+pub(crate) static DEJAVU_SANS_MONO: &[u8] = include_bytes!("../resources/DejaVuSansMono.ttf");
+pub(crate) static EB_GARAMOND: &[u8] =
+    include_bytes!("../resources/tests/eb-garamond/EBGaramond12-Regular.otf");
+pub(crate) static EB_GARAMOND_ITALIC: &[u8] =
+    include_bytes!("../resources/tests/eb-garamond/EBGaramond12-Italic.otf");
+pub(crate) static EB_GARAMOND_OTC: &[u8] =
+    include_bytes!("../resources/tests/eb-garamond/EBGaramond12.otc");
+pub(crate) static EB_GARAMOND_TTF: &[u8] =
+    include_bytes!("../resources/tests/eb-garamond/EBGaramond12-Regular.ttf");
+pub(crate) static INCONSOLATA: &[u8] =
+    include_bytes!("../resources/tests/inconsolata/Inconsolata-Regular.ttf");
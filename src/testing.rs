@@ -0,0 +1,348 @@
+// font-kit/src/testing.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Comparing rasterized glyphs against golden images, to catch rasterization regressions.
+//!
+//! `rasterize_glyph_sheet()` draws a set of glyphs side by side on a common baseline, and
+//! `compare_to_golden()` compares an image with a PNG file saved when the output was known to be
+//! good. When they differ by more than `GoldenOptions` allows, an image of the differences is
+//! written next to the golden image, with the differing pixels in red, along with the image
+//! itself, so that the change can be inspected.
+//!
+//! Once a change in output has been checked to be intended, set the `FONT_KIT_UPDATE_GOLDEN`
+//! environment variable to write the images as the new golden images instead.
+//!
+//! This module requires the `testing` feature.
+
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2I;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::canvas::{BlendMode, Canvas, Format, RasterizationOptions};
+use crate::error::{GlyphLoadingError, GoldenError};
+use crate::hinting::HintingOptions;
+use crate::loader::Loader;
+
+/// The environment variable that makes `compare_to_golden()` write golden images instead of
+/// comparing with them.
+pub const UPDATE_GOLDEN_VAR: &str = "FONT_KIT_UPDATE_GOLDEN";
+
+/// The number of empty pixels that `rasterize_glyph_sheet()` leaves around and between glyphs.
+pub const SHEET_PADDING: i32 = 2;
+
+/// How closely an image must match its golden image.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GoldenOptions {
+    /// The largest difference allowed in any channel of a pixel before the pixel counts as
+    /// differing. A few levels allow for rounding differences between rasterizer versions.
+    pub tolerance: u8,
+    /// The number of differing pixels allowed.
+    pub max_differing_pixels: usize,
+    /// Whether to write the image as the golden image instead of comparing with it. Setting
+    /// `FONT_KIT_UPDATE_GOLDEN` does the same.
+    pub update: bool,
+}
+
+/// Rasterizes glyphs side by side on a common baseline into a single image, in the order given.
+///
+/// Each glyph is rasterized on its own at the origin and then copied into the sheet, with
+/// `SHEET_PADDING` pixels around it, so the sheet doesn't depend on the glyphs' advances or on
+/// where the rasterizer places subpixel origins. The sheet is `Rgb24` for subpixel antialiasing
+/// and `A8` otherwise.
+pub fn rasterize_glyph_sheet<F>(
+    font: &F,
+    glyph_ids: &[u32],
+    point_size: f32,
+    hinting_options: HintingOptions,
+    rasterization_options: RasterizationOptions,
+) -> Result<Canvas, GlyphLoadingError>
+where
+    F: Loader,
+{
+    let format = if rasterization_options.plain() == RasterizationOptions::SubpixelAa {
+        Format::Rgb24
+    } else {
+        Format::A8
+    };
+
+    let mut glyphs = Vec::with_capacity(glyph_ids.len());
+    let (mut ascent, mut descent) = (0, 0);
+    for &glyph_id in glyph_ids {
+        let bounds = font.raster_bounds(
+            glyph_id,
+            point_size,
+            Transform2F::default(),
+            hinting_options,
+            rasterization_options,
+        )?;
+        let mut canvas = Canvas::new(bounds.size().max(Vector2I::default()), format);
+        if bounds.width() > 0 && bounds.height() > 0 {
+            font.rasterize_glyph(
+                &mut canvas,
+                glyph_id,
+                point_size,
+                Transform2F::from_translation(-bounds.origin().to_f32()),
+                hinting_options,
+                rasterization_options,
+            )?;
+            ascent = ascent.max(-bounds.min_y());
+            descent = descent.max(bounds.max_y());
+        }
+        glyphs.push((bounds.min_y(), canvas));
+    }
+
+    let width = glyphs
+        .iter()
+        .map(|(_, canvas)| canvas.size.x() + SHEET_PADDING)
+        .sum::<i32>()
+        + SHEET_PADDING;
+    let height = ascent + descent + 2 * SHEET_PADDING;
+    let mut sheet = Canvas::new(Vector2I::new(width, height), format);
+    let mut x = SHEET_PADDING;
+    for (top, canvas) in &glyphs {
+        let position = Vector2I::new(x, SHEET_PADDING + ascent + top);
+        sheet.blend(canvas, position, BlendMode::Over);
+        x += canvas.size.x() + SHEET_PADDING;
+    }
+    Ok(sheet)
+}
+
+/// Compares `canvas` with the golden PNG image at `golden_path`.
+///
+/// The canvas is compared as `Canvas::to_png()` would encode it. If more pixels differ than
+/// `options` allows, the differences are written to `golden_path` with the extension
+/// `.diff.png`, and the canvas itself with the extension `.actual.png`, and a
+/// `GoldenError::Mismatch` is returned.
+///
+/// If `options.update` is set or the `FONT_KIT_UPDATE_GOLDEN` environment variable is set, the
+/// canvas is written to `golden_path` instead, creating its directory if needed.
+pub fn compare_to_golden<P>(
+    canvas: &Canvas,
+    golden_path: P,
+    options: &GoldenOptions,
+) -> Result<(), GoldenError>
+where
+    P: AsRef<Path>,
+{
+    let golden_path = golden_path.as_ref();
+    if options.update || env::var_os(UPDATE_GOLDEN_VAR).is_some() {
+        if let Some(directory) = golden_path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(golden_path, canvas.to_png())?;
+        return Ok(());
+    }
+
+    let data = match fs::read(golden_path) {
+        Ok(data) => data,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Err(GoldenError::Missing(golden_path.to_owned()))
+        }
+        Err(error) => return Err(error.into()),
+    };
+    let golden = DecodedPng::decode(&data)?;
+    let size = (canvas.size.x().max(0) as u32, canvas.size.y().max(0) as u32);
+    if golden.size != size {
+        return Err(GoldenError::SizeMismatch {
+            expected: golden.size,
+            actual: size,
+        });
+    }
+    let (color_type, pixels) = canvas.png_pixels();
+    if golden.color_type != color_type {
+        return Err(GoldenError::FormatMismatch);
+    }
+
+    let channels = color_type.samples();
+    let (mut differing_pixels, mut max_difference) = (0, 0);
+    let mut diff = Canvas::new(canvas.size, Format::Rgba32);
+    for ((pixel, golden_pixel), diff_pixel) in pixels
+        .chunks_exact(channels)
+        .zip(golden.pixels.chunks_exact(channels))
+        .zip(diff.pixels.chunks_exact_mut(4))
+    {
+        let difference = pixel
+            .iter()
+            .zip(golden_pixel)
+            .map(|(&sample, &golden_sample)| sample.abs_diff(golden_sample))
+            .max()
+            .unwrap_or(0);
+        max_difference = max_difference.max(difference);
+        if difference > options.tolerance {
+            differing_pixels += 1;
+            diff_pixel.copy_from_slice(&[255, 0, 0, 255]);
+        } else {
+            // Show matching pixels dimmed, so the differences stand out against the glyphs.
+            let sum = pixel.iter().map(|&sample| sample as usize).sum::<usize>();
+            let gray = (sum / channels / 3) as u8;
+            diff_pixel.copy_from_slice(&[gray, gray, gray, 255]);
+        }
+    }
+
+    if differing_pixels > options.max_differing_pixels {
+        let diff_path = golden_path.with_extension("diff.png");
+        fs::write(&diff_path, diff.to_png())?;
+        fs::write(golden_path.with_extension("actual.png"), canvas.to_png())?;
+        return Err(GoldenError::Mismatch {
+            differing_pixels,
+            max_difference,
+            diff_path,
+        });
+    }
+    Ok(())
+}
+
+/// Panics with a description of the differences unless `canvas` matches the golden image at
+/// `golden_path`, for use in tests. See `compare_to_golden()`.
+#[track_caller]
+pub fn assert_matches_golden<P>(canvas: &Canvas, golden_path: P, options: &GoldenOptions)
+where
+    P: AsRef<Path>,
+{
+    let golden_path = golden_path.as_ref();
+    if let Err(error) = compare_to_golden(canvas, golden_path, options) {
+        panic!("{}: {}", golden_path.display(), error);
+    }
+}
+
+// A PNG image with 8-bit samples.
+struct DecodedPng {
+    size: (u32, u32),
+    color_type: png::ColorType,
+    pixels: Vec<u8>,
+}
+
+impl DecodedPng {
+    fn decode(data: &[u8]) -> Result<DecodedPng, GoldenError> {
+        let decode_error = |error: png::DecodingError| GoldenError::Decode(error.to_string());
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(decode_error)?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).map_err(decode_error)?;
+        pixels.truncate(info.buffer_size());
+        Ok(DecodedPng {
+            size: (info.width, info.height),
+            color_type: info.color_type,
+            pixels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compare_to_golden, GoldenOptions};
+    use crate::canvas::{Canvas, Format};
+    use crate::error::GoldenError;
+    use pathfinder_geometry::vector::Vector2I;
+    use std::{env, fs, process};
+
+    #[test]
+    fn test_compare_to_golden() {
+        let directory = env::temp_dir().join(format!("font-kit-golden-test-{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let golden_path = directory.join("glyphs.png");
+
+        let mut canvas = Canvas::new(Vector2I::new(3, 2), Format::A8);
+        canvas.pixels.copy_from_slice(&[0, 128, 255, 255, 128, 0]);
+        let exact = GoldenOptions::default();
+        assert!(matches!(
+            compare_to_golden(&canvas, &golden_path, &exact),
+            Err(GoldenError::Missing(_))
+        ));
+        let update = GoldenOptions {
+            update: true,
+            ..exact
+        };
+        compare_to_golden(&canvas, &golden_path, &update).unwrap();
+        compare_to_golden(&canvas, &golden_path, &exact).unwrap();
+
+        let mut changed = canvas.to_format(Format::A8);
+        changed.pixels[1] += 3;
+        match compare_to_golden(&changed, &golden_path, &exact) {
+            Err(GoldenError::Mismatch {
+                differing_pixels: 1,
+                max_difference: 3,
+                diff_path,
+            }) => assert_eq!(diff_path, directory.join("glyphs.diff.png")),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert!(directory.join("glyphs.actual.png").exists());
+        let tolerant = GoldenOptions {
+            tolerance: 3,
+            ..exact
+        };
+        compare_to_golden(&changed, &golden_path, &tolerant).unwrap();
+        let lenient = GoldenOptions {
+            max_differing_pixels: 1,
+            ..exact
+        };
+        compare_to_golden(&changed, &golden_path, &lenient).unwrap();
+
+        assert!(matches!(
+            compare_to_golden(&canvas.to_format(Format::Rgba32), &golden_path, &exact),
+            Err(GoldenError::FormatMismatch)
+        ));
+        let wider = Canvas::new(Vector2I::new(4, 2), Format::A8);
+        assert!(matches!(
+            compare_to_golden(&wider, &golden_path, &exact),
+            Err(GoldenError::SizeMismatch {
+                expected: (3, 2),
+                actual: (4, 2),
+            })
+        ));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(feature = "loader-freetype")]
+    #[test]
+    fn test_rasterize_glyph_sheet() {
+        use super::{rasterize_glyph_sheet, SHEET_PADDING};
+        use crate::canvas::RasterizationOptions;
+        use crate::hinting::HintingOptions;
+        use crate::loader::Loader;
+        use crate::loaders::freetype::Font;
+        use crate::test_fixtures::INCONSOLATA;
+        use std::sync::Arc;
+
+        let font = Font::from_bytes(Arc::new(INCONSOLATA.to_vec()), 0).unwrap();
+        let glyphs = ['H', ' ', 'g'].map(|character| font.glyph_for_char(character).unwrap());
+        let sheet = rasterize_glyph_sheet(
+            &font,
+            &glyphs,
+            16.0,
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )
+        .unwrap();
+        assert_eq!(sheet.format, Format::A8);
+
+        // The padding is empty, and the descender of the "g" reaches below the "H".
+        let row = |y: i32| &sheet.pixels[(y as usize * sheet.stride)..][..sheet.size.x() as usize];
+        assert!(row(0).iter().all(|&pixel| pixel == 0));
+        assert!(row(sheet.size.y() - 1).iter().all(|&pixel| pixel == 0));
+        let column_is_empty = |x: i32| (0..sheet.size.y()).all(|y| row(y)[x as usize] == 0);
+        assert!(column_is_empty(0) && column_is_empty(SHEET_PADDING - 1));
+        assert!(!column_is_empty(SHEET_PADDING));
+        let h_bottom = (0..sheet.size.y())
+            .filter(|&y| {
+                row(y)[..(sheet.size.x() as usize / 2)]
+                    .iter()
+                    .any(|&pixel| pixel > 0)
+            })
+            .max()
+            .unwrap();
+        assert!(h_bottom < sheet.size.y() - SHEET_PADDING - 2);
+    }
+}
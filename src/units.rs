@@ -0,0 +1,381 @@
+// font-kit/src/units.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Typed lengths for the different unit spaces that fonts deal with.
+//!
+//! Font files store their geometry in *font units* (also called design units), of which there are
+//! `units_per_em` per em. Rendering happens in device *pixels*, and sizes are commonly specified
+//! in typographic *points* (1/72 of an inch). Mixing these up silently is an easy way to render
+//! text at the wrong size, so these newtypes keep them apart, and a `ScaleContext` converts between
+//! them explicitly.
+//!
+//! `Loader::advance_px()`, `Loader::typographic_bounds_px()`, and `Loader::metrics_px()` return
+//! glyph and font metrics in pixels through a `ScaleContext` from `Loader::scale_context()`.
+
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2F;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::metrics::Metrics;
+
+/// The number of typographic points in an inch.
+pub const POINTS_PER_INCH: f32 = 72.0;
+
+/// A length in font units (design units), as stored in the font file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct FontUnits(pub f32);
+
+/// A length in device pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Px(pub f32);
+
+/// A length in typographic points (1/72 of an inch).
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Pt(pub f32);
+
+macro_rules! impl_length_ops {
+    ($ty:ident) => {
+        impl Add for $ty {
+            type Output = $ty;
+            #[inline]
+            fn add(self, other: $ty) -> $ty {
+                $ty(self.0 + other.0)
+            }
+        }
+
+        impl Sub for $ty {
+            type Output = $ty;
+            #[inline]
+            fn sub(self, other: $ty) -> $ty {
+                $ty(self.0 - other.0)
+            }
+        }
+
+        impl Mul<f32> for $ty {
+            type Output = $ty;
+            #[inline]
+            fn mul(self, factor: f32) -> $ty {
+                $ty(self.0 * factor)
+            }
+        }
+
+        impl Div<f32> for $ty {
+            type Output = $ty;
+            #[inline]
+            fn div(self, divisor: f32) -> $ty {
+                $ty(self.0 / divisor)
+            }
+        }
+
+        impl Neg for $ty {
+            type Output = $ty;
+            #[inline]
+            fn neg(self) -> $ty {
+                $ty(-self.0)
+            }
+        }
+    };
+}
+
+impl_length_ops!(FontUnits);
+impl_length_ops!(Px);
+impl_length_ops!(Pt);
+
+impl Pt {
+    /// Converts this length to pixels at the given resolution in dots per inch.
+    #[inline]
+    pub fn to_px(self, dpi: f32) -> Px {
+        Px(self.0 * dpi / POINTS_PER_INCH)
+    }
+}
+
+impl Px {
+    /// Converts this length to points at the given resolution in dots per inch.
+    #[inline]
+    pub fn to_pt(self, dpi: f32) -> Pt {
+        Pt(self.0 * POINTS_PER_INCH / dpi)
+    }
+}
+
+/// A rectangle in pixels, with the y axis pointing up as in font units.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PxRect {
+    /// The left edge.
+    pub min_x: Px,
+    /// The bottom edge.
+    pub min_y: Px,
+    /// The right edge.
+    pub max_x: Px,
+    /// The top edge.
+    pub max_y: Px,
+}
+
+impl PxRect {
+    /// Returns the width of this rectangle.
+    #[inline]
+    pub fn width(&self) -> Px {
+        self.max_x - self.min_x
+    }
+
+    /// Returns the height of this rectangle.
+    #[inline]
+    pub fn height(&self) -> Px {
+        self.max_y - self.min_y
+    }
+
+    /// Returns this rectangle as an untyped `RectF`.
+    #[inline]
+    pub fn to_rect_f(&self) -> RectF {
+        RectF::from_points(
+            Vector2F::new(self.min_x.0, self.min_y.0),
+            Vector2F::new(self.max_x.0, self.max_y.0),
+        )
+    }
+}
+
+/// The font-wide metrics of `Metrics`, scaled to pixels by a `ScaleContext`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScaledMetrics {
+    /// The size of an em.
+    pub px_per_em: Px,
+    /// The maximum amount the font rises above the baseline.
+    pub ascent: Px,
+    /// The maximum amount the font descends below the baseline; usually negative.
+    pub descent: Px,
+    /// The distance between baselines, on top of the ascent and descent.
+    pub line_gap: Px,
+    /// The distance of the top of the underline from the baseline.
+    pub underline_position: Px,
+    /// The underline thickness.
+    pub underline_thickness: Px,
+    /// The distance of the top of the strikeout from the baseline.
+    pub strikeout_position: Px,
+    /// The strikeout thickness.
+    pub strikeout_thickness: Px,
+    /// The amount that uppercase letters rise above the baseline.
+    pub cap_height: Px,
+    /// The amount that non-ascending lowercase letters rise above the baseline.
+    pub x_height: Px,
+}
+
+/// How a fractional size in pixels per em is rounded.
+///
+/// Sizes are fractional whenever a point size is converted at a non-integer scale factor, such as
+/// 11pt at 1.25× (18.33px). Rendering at the exact size keeps text the intended size, while
+/// rounding to whole pixels gives crisper hinting, picks bitmap strikes predictably, and lets
+/// glyph caches share images between sizes that differ only slightly; see
+/// `GlyphRasterCache::set_ppem_rounding()`. `gasp` ranges are always looked up at the nearest
+/// whole size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PpemRounding {
+    /// The size is used as is.
+    #[default]
+    Exact,
+    /// The size is rounded to the nearest whole number of pixels.
+    Nearest,
+    /// The size is rounded down to a whole number of pixels.
+    Down,
+    /// The size is rounded up to a whole number of pixels.
+    Up,
+}
+
+impl PpemRounding {
+    /// Rounds a size in pixels per em.
+    ///
+    /// Positive sizes never round down to zero, since nothing could be rendered at that size.
+    pub fn round(self, px_per_em: Px) -> Px {
+        let rounded = match self {
+            PpemRounding::Exact => return px_per_em,
+            PpemRounding::Nearest => px_per_em.0.round(),
+            PpemRounding::Down => px_per_em.0.floor(),
+            PpemRounding::Up => px_per_em.0.ceil(),
+        };
+        if px_per_em.0 > 0.0 {
+            Px(rounded.max(1.0))
+        } else {
+            Px(rounded)
+        }
+    }
+
+    /// Rounds the size that a glyph drawn at `point_size` with `transform` is rendered at, as
+    /// `rasterize_glyph()` takes them, and returns the point size that renders at the rounded
+    /// size.
+    ///
+    /// The point size is returned unchanged if `transform` collapses the glyph, or if it already
+    /// renders at a whole number of pixels give or take rounding error, so that rounding twice
+    /// gives the same result as rounding once.
+    pub fn round_point_size(self, point_size: f32, transform: Transform2F) -> f32 {
+        let scale = transform.matrix.det().abs().sqrt();
+        if self == PpemRounding::Exact || scale == 0.0 {
+            return point_size;
+        }
+        let px_per_em = point_size * scale;
+        if (px_per_em - px_per_em.round()).abs() < 1e-3 && px_per_em >= 1.0 {
+            return point_size;
+        }
+        self.round(Px(px_per_em)).0 / scale
+    }
+}
+
+/// Returns the size in pixels per em that a glyph drawn at `point_size` with `transform` is
+/// rendered at.
+#[inline]
+pub(crate) fn pixels_per_em(point_size: f32, transform: &Transform2F) -> f32 {
+    point_size * transform.matrix.det().abs().sqrt()
+}
+
+/// Converts between font units and pixels for a font rendered at a specific size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScaleContext {
+    units_per_em: u32,
+    px_per_em: Px,
+}
+
+impl ScaleContext {
+    /// Creates a scale context for a font with the given number of units per em, rendered at
+    /// `px_per_em` pixels per em.
+    #[inline]
+    pub fn new(units_per_em: u32, px_per_em: Px) -> ScaleContext {
+        ScaleContext {
+            units_per_em,
+            px_per_em,
+        }
+    }
+
+    /// Creates a scale context for a font with the given number of units per em, rendered at the
+    /// given point size on a display with the given resolution in dots per inch.
+    #[inline]
+    pub fn from_point_size(units_per_em: u32, point_size: Pt, dpi: f32) -> ScaleContext {
+        ScaleContext::new(units_per_em, point_size.to_px(dpi))
+    }
+
+    /// Returns this scale context with its size rounded as specified.
+    #[inline]
+    pub fn rounded(self, rounding: PpemRounding) -> ScaleContext {
+        ScaleContext::new(self.units_per_em, rounding.round(self.px_per_em))
+    }
+
+    /// Returns the number of font units per em.
+    #[inline]
+    pub fn units_per_em(&self) -> u32 {
+        self.units_per_em
+    }
+
+    /// Returns the size of an em, in pixels.
+    #[inline]
+    pub fn px_per_em(&self) -> Px {
+        self.px_per_em
+    }
+
+    /// Returns the number of pixels per font unit.
+    #[inline]
+    pub fn factor(&self) -> f32 {
+        if self.units_per_em == 0 {
+            return 0.0;
+        }
+        self.px_per_em.0 / self.units_per_em as f32
+    }
+
+    /// Converts a length in font units to pixels.
+    #[inline]
+    pub fn to_px(&self, length: FontUnits) -> Px {
+        Px(length.0 * self.factor())
+    }
+
+    /// Converts a length in pixels to font units.
+    #[inline]
+    pub fn to_font_units(&self, length: Px) -> FontUnits {
+        if self.px_per_em.0 == 0.0 {
+            return FontUnits(0.0);
+        }
+        FontUnits(length.0 * self.units_per_em as f32 / self.px_per_em.0)
+    }
+
+    /// Converts a vector in font units (such as a glyph advance) to pixels.
+    #[inline]
+    pub fn vector_to_px(&self, vector: Vector2F) -> Vector2F {
+        vector * self.factor()
+    }
+
+    /// Converts a rectangle in font units (such as typographic bounds) to pixels.
+    #[inline]
+    pub fn rect_to_px(&self, rect: RectF) -> RectF {
+        rect * self.factor()
+    }
+
+    /// Converts a rectangle in font units to a typed rectangle in pixels.
+    #[inline]
+    pub fn bounds_to_px(&self, rect: RectF) -> PxRect {
+        PxRect {
+            min_x: self.to_px(FontUnits(rect.min_x())),
+            min_y: self.to_px(FontUnits(rect.min_y())),
+            max_x: self.to_px(FontUnits(rect.max_x())),
+            max_y: self.to_px(FontUnits(rect.max_y())),
+        }
+    }
+
+    /// Converts font-wide metrics in font units to pixels.
+    pub fn metrics_to_px(&self, metrics: &Metrics) -> ScaledMetrics {
+        let to_px = |length| self.to_px(FontUnits(length));
+        ScaledMetrics {
+            px_per_em: self.px_per_em,
+            ascent: to_px(metrics.ascent),
+            descent: to_px(metrics.descent),
+            line_gap: to_px(metrics.line_gap),
+            underline_position: to_px(metrics.underline_position),
+            underline_thickness: to_px(metrics.underline_thickness),
+            strikeout_position: to_px(metrics.strikeout_position),
+            strikeout_thickness: to_px(metrics.strikeout_thickness),
+            cap_height: to_px(metrics.cap_height),
+            x_height: to_px(metrics.x_height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FontUnits, PpemRounding, Pt, Px, ScaleContext};
+    use pathfinder_geometry::transform2d::Transform2F;
+
+    #[test]
+    fn test_point_pixel_conversion() {
+        assert_eq!(Pt(12.0).to_px(96.0), Px(16.0));
+        assert_eq!(Px(16.0).to_pt(96.0), Pt(12.0));
+    }
+
+    #[test]
+    fn test_scale_context() {
+        let scale = ScaleContext::from_point_size(2048, Pt(24.0), 72.0);
+        assert_eq!(scale.px_per_em(), Px(24.0));
+        assert_eq!(scale.to_px(FontUnits(1024.0)), Px(12.0));
+        assert_eq!(scale.to_font_units(Px(6.0)), FontUnits(512.0));
+    }
+
+    #[test]
+    fn test_ppem_rounding() {
+        // 11pt at 96 DPI and a 1.25× scale factor.
+        let scale = ScaleContext::from_point_size(1000, Pt(11.0), 96.0 * 1.25);
+        assert_eq!(scale.rounded(PpemRounding::Exact), scale);
+        assert_eq!(scale.rounded(PpemRounding::Nearest).px_per_em(), Px(18.0));
+        assert_eq!(scale.rounded(PpemRounding::Up).px_per_em(), Px(19.0));
+        assert_eq!(PpemRounding::Down.round(Px(0.4)), Px(1.0));
+        assert_eq!(PpemRounding::Down.round(Px(0.0)), Px(0.0));
+
+        let transform = Transform2F::from_scale(1.25);
+        let point_size = PpemRounding::Down.round_point_size(14.5, transform);
+        assert!((point_size * 1.25 - 18.0).abs() < 1e-4);
+        assert_eq!(
+            PpemRounding::Down.round_point_size(point_size, transform),
+            point_size
+        );
+        assert_eq!(PpemRounding::Exact.round_point_size(14.5, transform), 14.5);
+    }
+}
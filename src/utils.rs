@@ -12,9 +12,13 @@
 
 #![allow(dead_code)]
 
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
 use std::fs::File;
 use std::io::{Error as IOError, Read};
 
+// The checksum that a whole font must have once `checkSumAdjustment` is set.
+pub(crate) const CHECKSUM_MAGIC: u32 = 0xb1b0_afba;
+
 pub(crate) static SFNT_VERSIONS: [[u8; 4]; 4] = [
     [0x00, 0x01, 0x00, 0x00],
     [b'O', b'T', b'T', b'O'],
@@ -39,7 +43,7 @@ pub(crate) fn lerp(a: f32, b: f32, t: f32) -> f32 {
 
 #[inline]
 pub(crate) fn div_round_up(a: usize, b: usize) -> usize {
-    (a + b - 1) / b
+    a.div_ceil(b)
 }
 
 pub(crate) fn slurp_file(file: &mut File) -> Result<Vec<u8>, IOError> {
@@ -50,3 +54,90 @@ pub(crate) fn slurp_file(file: &mut File) -> Result<Vec<u8>, IOError> {
     file.read_to_end(&mut data)?;
     Ok(data)
 }
+
+/// The initial state of a 64-bit FNV-1a hash, which is stable across Rust versions unlike
+/// `DefaultHasher`.
+pub(crate) const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Adds bytes to a 64-bit FNV-1a hash.
+#[inline]
+pub(crate) fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Assembles an OpenType font file from a set of `(tag, data)` tables.
+///
+/// The table directory is written in ascending tag order, as the OpenType specification requires,
+/// and each table is padded to a 4-byte boundary. The `head` table's `checkSumAdjustment` is left
+/// untouched.
+pub(crate) fn build_sfnt(tables: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut tables: Vec<&(u32, Vec<u8>)> = tables.iter().collect();
+    tables.sort_by_key(|&&(tag, _)| tag);
+
+    let sfnt_version = if tables
+        .iter()
+        .any(|&&(tag, _)| tag == u32::from_be_bytes(*b"CFF "))
+    {
+        SFNT_VERSIONS[1]
+    } else {
+        SFNT_VERSIONS[0]
+    };
+
+    let num_tables = tables.len() as u16;
+    let mut entry_selector = 0;
+    while (2u16 << entry_selector) <= num_tables {
+        entry_selector += 1;
+    }
+    let search_range = (1u16 << entry_selector) * 16;
+
+    let mut data = vec![];
+    data.extend_from_slice(&sfnt_version);
+    data.write_u16::<BigEndian>(num_tables).unwrap();
+    data.write_u16::<BigEndian>(search_range).unwrap();
+    data.write_u16::<BigEndian>(entry_selector).unwrap();
+    data.write_u16::<BigEndian>(num_tables * 16 - search_range)
+        .unwrap();
+
+    let mut offset = 12 + 16 * tables.len();
+    for &&(tag, ref table_data) in &tables {
+        data.write_u32::<BigEndian>(tag).unwrap();
+        data.write_u32::<BigEndian>(table_checksum(table_data))
+            .unwrap();
+        data.write_u32::<BigEndian>(offset as u32).unwrap();
+        data.write_u32::<BigEndian>(table_data.len() as u32)
+            .unwrap();
+        offset += (table_data.len() + 3) & !3;
+    }
+    for (_, table_data) in &tables {
+        data.extend_from_slice(table_data);
+        data.resize((data.len() + 3) & !3, 0);
+    }
+    data
+}
+
+/// Sets the `head` table's `checkSumAdjustment` in a font assembled by `build_sfnt()`, whose
+/// `head` table must have the field zeroed.
+pub(crate) fn set_checksum_adjustment(font_data: &mut [u8]) {
+    let num_tables = BigEndian::read_u16(&font_data[4..6]) as usize;
+    for record in (0..num_tables).map(|index| 12 + index * 16) {
+        if BigEndian::read_u32(&font_data[record..]) == u32::from_be_bytes(*b"head") {
+            let offset = BigEndian::read_u32(&font_data[(record + 8)..]) as usize + 8;
+            let adjustment = CHECKSUM_MAGIC.wrapping_sub(table_checksum(font_data));
+            BigEndian::write_u32(&mut font_data[offset..(offset + 4)], adjustment);
+        }
+    }
+}
+
+/// Computes the OpenType checksum of a table: the wrapping sum of its big-endian 32-bit words,
+/// with the final word zero-padded.
+pub(crate) fn table_checksum(table_data: &[u8]) -> u32 {
+    table_data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
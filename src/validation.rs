@@ -0,0 +1,489 @@
+// font-kit/src/validation.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Checking fonts for structural problems, for services that accept fonts from untrusted sources.
+//!
+//! `validate()` and `Font::validate()` check the table directory and checksums, the tables that
+//! every font needs, the consistency of `loca` with `glyf`, the glyph IDs in `cmap`, and the
+//! horizontal metrics. `sanitize()` rewrites a font without the tables that failed, with correct
+//! checksums. A sanitized font may still lack tables that a renderer needs; validate it again or
+//! load it with `LoadOptions::strict()` to find out.
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::error::FontLoadingError;
+use crate::utils;
+
+const COLLECTION_SIGNATURE: &[u8; 4] = b"ttcf";
+
+// Tables without which a font can't map characters, measure glyphs, or be named.
+const REQUIRED_TABLES: [&[u8; 4]; 6] = [b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name"];
+
+// Tables any one of which holds glyph images.
+const GLYPH_TABLES: [&[u8; 4]; 6] = [b"glyf", b"CFF ", b"CFF2", b"CBDT", b"sbix", b"SVG "];
+
+/// A problem found by `validate()`.
+///
+/// Tables are identified by their OpenType tags, packed big-endian into a `u32`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValidationIssue {
+    /// A table extends past the end of the font data.
+    TableOutOfBounds {
+        /// The table.
+        table: u32,
+    },
+    /// A table's checksum doesn't match the one in the table directory.
+    ChecksumMismatch {
+        /// The table.
+        table: u32,
+        /// The checksum in the table directory.
+        expected: u32,
+        /// The checksum of the table data.
+        actual: u32,
+    },
+    /// A table that every font needs is missing. Fonts with no glyph images at all report a
+    /// missing `glyf` table.
+    MissingTable {
+        /// The table.
+        table: u32,
+    },
+    /// A table is too short or has fields out of range.
+    MalformedTable {
+        /// The table.
+        table: u32,
+    },
+    /// A glyph's data in `glyf` ends before it starts, according to `loca`.
+    GlyphOutOfOrder {
+        /// The glyph.
+        glyph_id: u32,
+    },
+    /// A glyph's data extends past the end of `glyf`.
+    GlyphOutOfBounds {
+        /// The glyph.
+        glyph_id: u32,
+    },
+    /// The character map maps a character to a glyph that doesn't exist. Only the first such
+    /// character in each subtable is reported.
+    CmapGlyphOutOfRange {
+        /// The character.
+        codepoint: u32,
+        /// The glyph that it maps to.
+        glyph_id: u32,
+    },
+    /// The font's minimum and maximum of some metric are swapped: the ascent is below the
+    /// descent, or the bounding box in `head` is inverted.
+    InvertedMetrics {
+        /// The table holding the metrics.
+        table: u32,
+    },
+}
+
+impl ValidationIssue {
+    /// Returns the table that has the problem.
+    pub fn table(&self) -> u32 {
+        match *self {
+            ValidationIssue::TableOutOfBounds { table }
+            | ValidationIssue::ChecksumMismatch { table, .. }
+            | ValidationIssue::MissingTable { table }
+            | ValidationIssue::MalformedTable { table }
+            | ValidationIssue::InvertedMetrics { table } => table,
+            ValidationIssue::GlyphOutOfOrder { .. } | ValidationIssue::GlyphOutOfBounds { .. } => {
+                tag(b"loca")
+            }
+            ValidationIssue::CmapGlyphOutOfRange { .. } => tag(b"cmap"),
+        }
+    }
+
+    /// Returns true if the problem is in the structure of a table, so that reading the table can
+    /// fail or give wrong glyphs. Checksum mismatches, missing tables, and inverted metrics are
+    /// not structural.
+    pub fn is_structural(&self) -> bool {
+        !matches!(
+            *self,
+            ValidationIssue::ChecksumMismatch { .. }
+                | ValidationIssue::MissingTable { .. }
+                | ValidationIssue::InvertedMetrics { .. }
+        )
+    }
+}
+
+pub(crate) struct TableRecord {
+    pub(crate) tag: u32,
+    checksum: u32,
+    pub(crate) offset: usize,
+    pub(crate) length: usize,
+}
+
+// The table directory of a font.
+pub(crate) struct Directory {
+    pub(crate) tables: Vec<TableRecord>,
+}
+
+impl Directory {
+    pub(crate) fn parse(data: &[u8], font_index: u32) -> Result<Directory, FontLoadingError> {
+        let truncated = || FontLoadingError::Truncated { offset: data.len() };
+        let offset = if data.starts_with(COLLECTION_SIGNATURE) {
+            let font_count = read_u32(data, 8).ok_or_else(truncated)?;
+            if font_index >= font_count {
+                return Err(FontLoadingError::NoSuchFontInCollection);
+            }
+            read_u32(data, 12 + font_index as usize * 4).ok_or_else(truncated)? as usize
+        } else if font_index != 0 {
+            return Err(FontLoadingError::NoSuchFontInCollection);
+        } else {
+            0
+        };
+        let font = data.get(offset..).ok_or_else(truncated)?;
+        if !utils::SFNT_VERSIONS
+            .iter()
+            .any(|version| font.starts_with(version))
+        {
+            return Err(FontLoadingError::unknown_format(font));
+        }
+        let table_count = read_u16(data, offset + 4).ok_or_else(truncated)?;
+        let mut tables = Vec::with_capacity(table_count as usize);
+        for index in 0..table_count as usize {
+            let record = offset + 12 + index * 16;
+            let field = |field: usize| read_u32(data, record + field).ok_or_else(truncated);
+            tables.push(TableRecord {
+                tag: field(0)?,
+                checksum: field(4)?,
+                offset: field(8)? as usize,
+                length: field(12)? as usize,
+            });
+        }
+        Ok(Directory { tables })
+    }
+
+    // Returns the number of glyphs from `maxp`, if the table is there and readable.
+    pub(crate) fn glyph_count(&self, data: &[u8]) -> Option<u16> {
+        let record = self
+            .tables
+            .iter()
+            .find(|record| record.tag == tag(b"maxp"))?;
+        let maxp = data.get(record.offset..(record.offset.checked_add(record.length)?))?;
+        read_u16(maxp, 4)
+    }
+}
+
+/// Checks the font at `font_index` in raw font data for problems.
+///
+/// Returns an error only if the data isn't an OpenType font or collection at all, or doesn't
+/// have a font at `font_index`.
+pub fn validate(data: &[u8], font_index: u32) -> Result<Vec<ValidationIssue>, FontLoadingError> {
+    let directory = Directory::parse(data, font_index)?;
+    let mut issues = vec![];
+
+    for record in &directory.tables {
+        let table = match data.get(record.offset..(record.offset.saturating_add(record.length))) {
+            Some(table) => table,
+            None => {
+                issues.push(ValidationIssue::TableOutOfBounds { table: record.tag });
+                continue;
+            }
+        };
+        let actual = if record.tag == tag(b"head") && table.len() >= 12 {
+            // `checkSumAdjustment` is left out of the checksum of `head`.
+            utils::table_checksum(&table[..8]).wrapping_add(utils::table_checksum(&table[12..]))
+        } else {
+            utils::table_checksum(table)
+        };
+        if actual != record.checksum {
+            issues.push(ValidationIssue::ChecksumMismatch {
+                table: record.tag,
+                expected: record.checksum,
+                actual,
+            });
+        }
+    }
+
+    let table = |name: &[u8; 4]| -> Option<&[u8]> {
+        let record = directory
+            .tables
+            .iter()
+            .find(|record| record.tag == tag(name))?;
+        data.get(record.offset..(record.offset.checked_add(record.length)?))
+    };
+    let has_table = |name: &[u8; 4]| {
+        directory
+            .tables
+            .iter()
+            .any(|record| record.tag == tag(name))
+    };
+    for name in REQUIRED_TABLES {
+        if !has_table(name) {
+            issues.push(ValidationIssue::MissingTable { table: tag(name) });
+        }
+    }
+    if !GLYPH_TABLES.iter().any(|name| has_table(name)) {
+        issues.push(ValidationIssue::MissingTable {
+            table: tag(b"glyf"),
+        });
+    }
+
+    let glyph_count = directory.glyph_count(data);
+    if table(b"maxp").is_some() && glyph_count.is_none() {
+        issues.push(ValidationIssue::MalformedTable {
+            table: tag(b"maxp"),
+        });
+    }
+    let glyph_count = glyph_count.unwrap_or(0) as u32;
+
+    if let Some(head) = table(b"head") {
+        check_head(head, &mut issues);
+        if let Some(glyf) = table(b"glyf") {
+            match (table(b"loca"), read_u16(head, 50)) {
+                (Some(loca), Some(format)) => {
+                    check_loca(loca, glyf, format != 0, glyph_count, &mut issues)
+                }
+                _ if !has_table(b"loca") => issues.push(ValidationIssue::MissingTable {
+                    table: tag(b"loca"),
+                }),
+                _ => {}
+            }
+        }
+    }
+    if let Some(cmap) = table(b"cmap") {
+        check_cmap(cmap, glyph_count, &mut issues);
+    }
+    if let Some(hhea) = table(b"hhea") {
+        check_horizontal_metrics(hhea, table(b"hmtx"), glyph_count, &mut issues);
+    }
+    Ok(issues)
+}
+
+/// Rewrites the font at `font_index` in raw font data without the tables that `validate()` finds
+/// broken, returning a single font.
+///
+/// Tables that are out of bounds or malformed are dropped, along with both `glyf` and `loca` if
+/// either is inconsistent and `cmap` if it refers to missing glyphs. Checksum mismatches are fixed
+/// by recomputing the checksums. Inverted metrics are left alone, since the tables holding them are
+/// required.
+pub fn sanitize(data: &[u8], font_index: u32) -> Result<Vec<u8>, FontLoadingError> {
+    let directory = Directory::parse(data, font_index)?;
+    let mut dropped = vec![];
+    for issue in validate(data, font_index)? {
+        match issue {
+            issue if !issue.is_structural() => {}
+            ValidationIssue::GlyphOutOfOrder { .. } | ValidationIssue::GlyphOutOfBounds { .. } => {
+                dropped.extend([tag(b"glyf"), tag(b"loca")])
+            }
+            ValidationIssue::MalformedTable { table } if table == tag(b"loca") => {
+                dropped.extend([tag(b"glyf"), tag(b"loca")])
+            }
+            issue => dropped.push(issue.table()),
+        }
+    }
+
+    let mut tables = vec![];
+    for record in &directory.tables {
+        if dropped.contains(&record.tag) {
+            continue;
+        }
+        let mut table = data[record.offset..(record.offset + record.length)].to_vec();
+        if record.tag == tag(b"head") && table.len() >= 12 {
+            table[8..12].copy_from_slice(&[0; 4]);
+        }
+        tables.push((record.tag, table));
+    }
+    let mut font_data = utils::build_sfnt(&tables);
+    utils::set_checksum_adjustment(&mut font_data);
+    Ok(font_data)
+}
+
+fn check_head(head: &[u8], issues: &mut Vec<ValidationIssue>) {
+    let bounds: Option<Vec<i16>> = (36..44)
+        .step_by(2)
+        .map(|offset| read_u16(head, offset).map(|value| value as i16))
+        .collect();
+    match bounds {
+        Some(bounds) if bounds[0] > bounds[2] || bounds[1] > bounds[3] => {
+            issues.push(ValidationIssue::InvertedMetrics {
+                table: tag(b"head"),
+            })
+        }
+        Some(_) if head.len() >= 54 => {}
+        _ => issues.push(ValidationIssue::MalformedTable {
+            table: tag(b"head"),
+        }),
+    }
+}
+
+fn check_loca(
+    loca: &[u8],
+    glyf: &[u8],
+    long_offsets: bool,
+    glyph_count: u32,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let offset = |index: u32| {
+        if long_offsets {
+            read_u32(loca, index as usize * 4).map(|offset| offset as usize)
+        } else {
+            read_u16(loca, index as usize * 2).map(|offset| offset as usize * 2)
+        }
+    };
+    if offset(glyph_count).is_none() {
+        issues.push(ValidationIssue::MalformedTable {
+            table: tag(b"loca"),
+        });
+        return;
+    }
+    for glyph_id in 0..glyph_count {
+        let (start, end) = (offset(glyph_id).unwrap(), offset(glyph_id + 1).unwrap());
+        if start > end {
+            issues.push(ValidationIssue::GlyphOutOfOrder { glyph_id });
+        } else if end > glyf.len() {
+            issues.push(ValidationIssue::GlyphOutOfBounds { glyph_id });
+        }
+    }
+}
+
+fn check_cmap(cmap: &[u8], glyph_count: u32, issues: &mut Vec<ValidationIssue>) {
+    let cmap = match ttf_parser::cmap::Table::parse(cmap) {
+        Some(cmap) => cmap,
+        None => {
+            issues.push(ValidationIssue::MalformedTable {
+                table: tag(b"cmap"),
+            });
+            return;
+        }
+    };
+    for subtable in cmap.subtables {
+        let mut out_of_range = None;
+        subtable.codepoints(|codepoint| {
+            if out_of_range.is_some() {
+                return;
+            }
+            if let Some(glyph_id) = subtable.glyph_index(codepoint) {
+                if glyph_id.0 as u32 >= glyph_count {
+                    out_of_range = Some(ValidationIssue::CmapGlyphOutOfRange {
+                        codepoint,
+                        glyph_id: glyph_id.0 as u32,
+                    });
+                }
+            }
+        });
+        issues.extend(out_of_range);
+    }
+}
+
+fn check_horizontal_metrics(
+    hhea: &[u8],
+    hmtx: Option<&[u8]>,
+    glyph_count: u32,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let (ascender, descender, metric_count) =
+        match (read_u16(hhea, 4), read_u16(hhea, 6), read_u16(hhea, 34)) {
+            (Some(ascender), Some(descender), Some(metric_count)) => {
+                (ascender as i16, descender as i16, metric_count as u32)
+            }
+            _ => {
+                issues.push(ValidationIssue::MalformedTable {
+                    table: tag(b"hhea"),
+                });
+                return;
+            }
+        };
+    if ascender < descender {
+        issues.push(ValidationIssue::InvertedMetrics {
+            table: tag(b"hhea"),
+        });
+    }
+    if metric_count == 0 || metric_count > glyph_count {
+        issues.push(ValidationIssue::MalformedTable {
+            table: tag(b"hhea"),
+        });
+        return;
+    }
+    // Each glyph has an advance and a side bearing, except that glyphs past `numberOfHMetrics`
+    // share the last advance.
+    let needed = metric_count as usize * 4 + (glyph_count - metric_count) as usize * 2;
+    if hmtx.is_some_and(|hmtx| hmtx.len() < needed) {
+        issues.push(ValidationIssue::MalformedTable {
+            table: tag(b"hmtx"),
+        });
+    }
+}
+
+#[inline]
+fn tag(name: &[u8; 4]) -> u32 {
+    u32::from_be_bytes(*name)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..(offset + 2)).map(BigEndian::read_u16)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..(offset + 4)).map(BigEndian::read_u32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sanitize, tag, validate, ValidationIssue};
+    use crate::font::Font;
+    use crate::loader::Loader;
+    use crate::test_fixtures::INCONSOLATA;
+    use byteorder::{BigEndian, ByteOrder};
+    use std::sync::Arc;
+
+    // Returns the offset of a table in a single font.
+    fn table_offset(data: &[u8], name: &[u8; 4]) -> usize {
+        let table_count = BigEndian::read_u16(&data[4..]) as usize;
+        (0..table_count)
+            .map(|index| 12 + index * 16)
+            .find(|&record| data[record..(record + 4)] == name[..])
+            .map(|record| BigEndian::read_u32(&data[(record + 8)..]) as usize)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(INCONSOLATA, 0).unwrap(), []);
+        assert!(Font::from_bytes_borrowed(INCONSOLATA, 0)
+            .unwrap()
+            .validate()
+            .is_empty());
+        assert!(validate(b"not a font", 0).is_err());
+
+        // Swap two `loca` entries, which also breaks the checksum of `loca`.
+        let mut data = INCONSOLATA.to_vec();
+        let head = table_offset(&data, b"head");
+        assert_eq!(BigEndian::read_u16(&data[(head + 50)..]), 0);
+        let loca = table_offset(&data, b"loca") + 2 * 5;
+        let (first, second) = (
+            data[loca..(loca + 2)].to_vec(),
+            data[(loca + 2)..(loca + 4)].to_vec(),
+        );
+        data[loca..(loca + 2)].copy_from_slice(&second);
+        data[(loca + 2)..(loca + 4)].copy_from_slice(&first);
+        let issues = validate(&data, 0).unwrap();
+        assert!(issues.contains(&ValidationIssue::GlyphOutOfOrder { glyph_id: 5 }));
+        assert!(issues.iter().any(|issue| matches!(
+            issue,
+            ValidationIssue::ChecksumMismatch { .. }
+        ) && issue.table() == tag(b"loca")));
+
+        let corrupt = Arc::new(data.clone());
+        assert!(Font::builder().load_bytes(corrupt.clone()).is_ok());
+        assert!(Font::builder().strict(true).load_bytes(corrupt).is_err());
+
+        let sanitized = sanitize(&data, 0).unwrap();
+        assert_eq!(
+            validate(&sanitized, 0).unwrap(),
+            [ValidationIssue::MissingTable {
+                table: tag(b"glyf")
+            }]
+        );
+        assert!(Font::from_bytes(Arc::new(sanitized), 0).is_ok());
+    }
+}